@@ -18,7 +18,7 @@ fn options() -> Options {
     
     opts.optflag("", "data-type-signature", "inserts data type signatures");
     
-    opts.optopt("", "derive-default", "Set how the default trait should be derived", "<primitive-types>");
+    opts.optopt("", "derive-default", "Set how the default trait should be derived", "<primitive-types|all>");
     
     opts.optflag("", "version", "print the version of this software");
     opts.optflag("h", "help", "print this help menu");
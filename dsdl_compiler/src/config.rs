@@ -4,7 +4,7 @@ use std::str::FromStr;
 
 /// Makes certain things in the compilation process configurable. `CompileConfig::default()` is generally safe to use.
 pub struct CompileConfig {
-    /// Compile data type signatures for types `#[DataTypeSignature = "0x12345678"]`
+    /// Compile data type signatures for types `#[uavcan(signature = 0x12345678)]`
     pub data_type_signature: bool,
     
     /// Sets strategy for deriving the `Default` trait
@@ -26,6 +26,13 @@ pub enum DeriveDefault {
 
     /// Derive default for structs only when the struct consists of primitive types or arrays of primitive types.
     PrimitiveTypes,
+
+    /// Derive default for all structs, matching the DSDL zero initialization.
+    ///
+    /// Unions default to their first variant through `UavcanStruct`, and dynamic arrays default to empty.
+    /// Structs with static arrays of more than 32 elements are left out, since the arrays don't implement `Default`
+    /// (packed arrays of `bool` do).
+    All,
 }
 
 pub enum ParseDeriveDefaultError {
@@ -34,7 +41,7 @@ pub enum ParseDeriveDefaultError {
 
 impl Default for DeriveDefault {
     fn default() -> DeriveDefault {
-        DeriveDefault::PrimitiveTypes
+        DeriveDefault::All
     }
 }
 
@@ -44,6 +51,7 @@ impl FromStr for DeriveDefault {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s{
             "primitive-types" => Ok(DeriveDefault::PrimitiveTypes),
+            "all" => Ok(DeriveDefault::All),
             _ => Err(ParseDeriveDefaultError::NotVariant),
         }
     }
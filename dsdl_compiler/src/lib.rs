@@ -44,6 +44,34 @@
 //! # }
 //! 
 //! ```
+//!
+//! ### Generate code from a build script
+//! `compile_to_file` reads a DSDL directory and writes the compiled items to a file,
+//! which makes it possible to generate the data types as part of the build (`build.rs`).
+//!
+//! ```no_run
+//! extern crate dsdl_compiler;
+//!
+//! use std::env;
+//! use std::path::Path;
+//!
+//! use dsdl_compiler::CompileConfig;
+//!
+//! fn main() {
+//!     let out_dir = env::var("OUT_DIR").unwrap();
+//!     let out_path = Path::new(&out_dir).join("dsdl.rs");
+//!
+//!     dsdl_compiler::compile_to_file("dsdl/", &out_path, &CompileConfig::default()).unwrap();
+//!
+//!     // only recompile DSDL if it has changed
+//!     println!("cargo:rerun-if-changed=dsdl/");
+//! }
+//! ```
+//!
+//! The generated code is then included in the crate with `include!(concat!(env!("OUT_DIR"), "/dsdl.rs"));`
+//!
+//! Vendor specific namespaces that live outside the standard DSDL directory are compiled together with it
+//! by listing the root namespaces, `compile_roots_to_file(&["dsdl/uavcan", "vendor/com_example"], &out_path, &config)`.
 
 #![recursion_limit="128"]
 
@@ -54,6 +82,13 @@ extern crate dsdl_parser;
 extern crate syn;
 extern crate inflections;
 
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
 use inflections::Inflect;
 
 pub mod bin;
@@ -70,6 +105,91 @@ pub trait Compile<T> {
     fn compile(self, config: &CompileConfig) -> T;
 }
 
+/// Compiles the DSDL definitions found in `input` and writes the generated code to `output`
+///
+/// This is intended to be used from build scripts, see the crate level documentation for an example.
+pub fn compile_to_file<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output: Q, config: &CompileConfig) -> io::Result<()> {
+    let dsdl = DSDL::read(input)?;
+    write_to_file(dsdl, output, config)
+}
+
+/// Compiles the DSDL definitions of several root namespaces and writes the generated code to `output`
+///
+/// Every root is a directory holding a root namespace, like `dsdl/uavcan` or a vendor specific `vendor/com_example`,
+/// so vendor namespaces can be compiled together with the standard definitions they refer to.
+///
+/// The default data type IDs given by the file names (`20000.Status.uavcan`) become the `TYPE_ID` of the
+/// `Message`, `Request` and `Response` impls, which is what broadcasting, subscribing and services use.
+/// Compilation fails if two messages, or two services, are assigned the same default data type ID.
+///
+/// The roots are copied into a directory next to `output` (with the extension `dsdl`) before compiling,
+/// as the definitions must be read as a single tree for references between namespaces to resolve.
+pub fn compile_roots_to_file<P: AsRef<Path>, Q: AsRef<Path>>(roots: &[P], output: Q, config: &CompileConfig) -> io::Result<()> {
+    let staging_path = output.as_ref().with_extension("dsdl");
+    if staging_path.exists() {
+        fs::remove_dir_all(&staging_path)?;
+    }
+    fs::create_dir_all(&staging_path)?;
+
+    for root in roots {
+        let namespace = match root.as_ref().file_name() {
+            Some(namespace) => namespace,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a namespace directory", root.as_ref().display()))),
+        };
+        let namespace_path = staging_path.join(namespace);
+        if namespace_path.exists() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("the root namespace {:?} is given more than once", namespace)));
+        }
+        copy_definitions(root.as_ref(), &namespace_path)?;
+    }
+
+    let dsdl = DSDL::read(&staging_path)?;
+    check_type_ids(&dsdl)?;
+    write_to_file(dsdl, output, config)
+}
+
+fn write_to_file<Q: AsRef<Path>>(dsdl: DSDL, output: Q, config: &CompileConfig) -> io::Result<()> {
+    let items = dsdl.compile(config);
+
+    let tokens = quote!{#(#items)*};
+
+    let mut file = File::create(output)?;
+    file.write_all(tokens.as_str().as_bytes())
+}
+
+/// Copies the definitions (`.uavcan` files) in `from` and its sub namespaces to `to`
+fn copy_definitions(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let path = entry?.path();
+        let target = to.join(path.file_name().unwrap());
+        if path.is_dir() {
+            copy_definitions(&path, &target)?;
+        } else if path.extension().map_or(false, |extension| extension == "uavcan") {
+            fs::copy(&path, &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Makes sure no default data type ID is assigned twice, messages and services have separate ID spaces
+fn check_type_ids(dsdl: &DSDL) -> io::Result<()> {
+    let mut assigned = HashMap::new();
+    for file in dsdl.files() {
+        if let Some(ref id) = file.name.id {
+            let kind = match file.definition {
+                dsdl_parser::TypeDefinition::Message(_) => "message",
+                dsdl_parser::TypeDefinition::Service(_) => "service",
+            };
+            let name = format!("{}", file.name);
+            if let Some(other) = assigned.insert((kind, format!("{}", id)), name.clone()) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} and {} are both assigned the {} type ID {}", other, name, kind, id)));
+            }
+        }
+    }
+    Ok(())
+}
+
 impl Compile<Vec<syn::Item>> for DSDL {
     fn compile(self, config: &CompileConfig) -> Vec<syn::Item> {
         let mut items = Vec::new();
@@ -120,14 +240,17 @@ impl Compile<Vec<syn::Item>> for DSDL {
 }
 
 
+/// The `#[uavcan(key = value)]` attribute read by the `UavcanStruct` derive
+fn uavcan_attribute(key: &str, value: syn::Lit) -> syn::Attribute {
+    syn::Attribute{style: syn::AttrStyle::Outer, is_sugared_doc: false, value: syn::MetaItem::List(syn::Ident::from("uavcan"), vec![
+        syn::NestedMetaItem::MetaItem(syn::MetaItem::NameValue(syn::Ident::from(key), value)),
+    ])}
+}
+
 fn add_data_type_signature(current_item: &mut syn::Item, signature: u64) {
     match current_item.node {
         syn::ItemKind::Enum(_,_) | syn::ItemKind::Struct(_,_) => {
-            current_item.attrs.push(syn::Attribute{
-                style: syn::AttrStyle::Outer,
-                value: syn::MetaItem::NameValue(syn::Ident::from("DataTypeSignature"), syn::Lit::Str(format!("0x{:x}", signature), syn::StrStyle::Cooked)),
-                is_sugared_doc: true,
-            });
+            current_item.attrs.push(uavcan_attribute("signature", syn::Lit::Int(signature, syn::IntTy::Unsuffixed)));
         },
         syn::ItemKind::Mod(Some(ref mut items)) => {
             for item in items {
@@ -139,6 +262,83 @@ fn add_data_type_signature(current_item: &mut syn::Item, signature: u64) {
 }
 
 
+/// Compiles the constant definitions of `message` into associated constants of the type `type_name`
+///
+/// Constants of types without a native Rust counterpart (`uint3`, `float16`, ...) get the smallest native type that can hold them.
+fn compile_constants(message: &dsdl_parser::MessageDefinition, type_name: &str, config: &CompileConfig) -> Option<syn::Item> {
+    let mut constants = Vec::new();
+    for line in &message.0 {
+        if let dsdl_parser::Line::Definition{definition: dsdl_parser::AttributeDefinition::Const(ref def), comment: ref opt_comment} = *line {
+            let ty = native_type(&def.field_type.clone().compile(config));
+
+            let mut value = format!("{}", def.constant);
+            if value.starts_with('\'') {
+                // character constants are the ASCII code of the character
+                value = format!("{}", value.chars().nth(1).unwrap() as u32);
+            } else if ty.starts_with('f') && !value.contains('.') && !value.contains('e') && !value.contains('E') {
+                value.push_str(".0");
+            }
+
+            let mut attrs = Vec::new();
+            if let Some(ref comment) = *opt_comment {
+                attrs.push(comment.clone().compile(config));
+            }
+
+            constants.push(syn::ImplItem{
+                ident: def.name.clone().compile(config),
+                vis: syn::Visibility::Public,
+                defaultness: syn::Defaultness::Final,
+                attrs: attrs,
+                node: syn::ImplItemKind::Const(
+                    syn::parse::ty(&ty).expect(""),
+                    syn::parse::expr(&value).expect("Constant is not a valid literal"),
+                ),
+            });
+        }
+    }
+
+    if constants.is_empty() {
+        return None;
+    }
+
+    Some(syn::Item {
+        ident: syn::Ident::from(type_name),
+        vis: syn::Visibility::Inherited,
+        attrs: Vec::new(),
+        node: syn::ItemKind::Impl(
+            syn::Unsafety::Normal,
+            syn::ImplPolarity::Positive,
+            syn::Generics{lifetimes: Vec::new(), ty_params: Vec::new(), where_clause: syn::WhereClause::none()},
+            None,
+            Box::new(syn::Ty::Path(None, syn::Path{global: false, segments: vec![syn::PathSegment{ident: syn::Ident::from(type_name), parameters: syn::PathParameters::none()}]})),
+            constants,
+        ),
+    })
+}
+
+/// The native type used for constants of the primitive type `ty`
+fn native_type(ty: &syn::Ty) -> String {
+    let name = match *ty {
+        syn::Ty::Path(_, ref path) => String::from(path.segments.last().unwrap().ident.as_ref()),
+        _ => unreachable!("Primitive types are always compiled to paths"),
+    };
+
+    if name == "f16" {
+        String::from("f32")
+    } else if name.starts_with('u') || name.starts_with('i') {
+        let bits: usize = name[1..].parse().unwrap();
+        let native_bits = match bits {
+            0..=8 => 8,
+            9..=16 => 16,
+            17..=32 => 32,
+            _ => 64,
+        };
+        format!("{}{}", &name[..1], native_bits)
+    } else {
+        name
+    }
+}
+
 fn add_item(new_item: syn::Item, items: &mut Vec<syn::Item>) {
     if let (module_name, syn::ItemKind::Mod(Some(new_sub_items))) = (&new_item.ident.clone(), new_item.node.clone()) {
         match items.iter_mut().find(|x| {
@@ -169,17 +369,14 @@ impl Compile<Vec<syn::Item>> for dsdl_parser::File {
         let dsdl_signature = self.clone().normalize().dsdl_signature();
         match self.definition {
             dsdl_parser::TypeDefinition::Message(message) => {
+                let constants = compile_constants(&message, &self.name.name, config);
                 let (item_kinds, struct_attributes) = message.compile(config);
                 for item_kind in item_kinds {
                     
                     let attrs = match item_kind {
                         syn::ItemKind::Enum(_,_) | syn::ItemKind::Struct(_,_) => {
                             let mut attrs = struct_attributes.clone();
-                            attrs.push(syn::Attribute{
-                                style: syn::AttrStyle::Outer,
-                                value: syn::MetaItem::NameValue(syn::Ident::from("DSDLSignature"), syn::Lit::Str(format!("0x{:x}", dsdl_signature), syn::StrStyle::Cooked)),
-                                is_sugared_doc: true,
-                            });
+                            attrs.push(uavcan_attribute("dsdl_signature", syn::Lit::Int(dsdl_signature, syn::IntTy::Unsuffixed)));
                             attrs
                         },
                         _ => Vec::new(),
@@ -193,6 +390,8 @@ impl Compile<Vec<syn::Item>> for dsdl_parser::File {
                     });
                 }
 
+                items.extend(constants);
+
                 if let Some(ref id) = self.name.id {
                     items.push(syn::Item {
                         ident: syn::Ident::from(self.name.name.clone()),
@@ -224,6 +423,8 @@ impl Compile<Vec<syn::Item>> for dsdl_parser::File {
                 }                           
             },
             dsdl_parser::TypeDefinition::Service(service) => {
+                let constants_req = compile_constants(&service.request, &(self.name.name.clone() + "Request"), config);
+                let constants_res = compile_constants(&service.response, &(self.name.name.clone() + "Response"), config);
                 let (item_kinds_req, struct_attributes_req) = service.request.compile(config);
                 let (item_kinds_res, struct_attributes_res) = service.response.compile(config);
                 
@@ -232,11 +433,7 @@ impl Compile<Vec<syn::Item>> for dsdl_parser::File {
                     let attrs = match item_kind {
                         syn::ItemKind::Enum(_,_) | syn::ItemKind::Struct(_,_) => {
                             let mut attrs = struct_attributes_req.clone();
-                            attrs.push(syn::Attribute{
-                                style: syn::AttrStyle::Outer,
-                                value: syn::MetaItem::NameValue(syn::Ident::from("DSDLSignature"), syn::Lit::Str(format!("0x{:x}", dsdl_signature), syn::StrStyle::Cooked)),
-                                is_sugared_doc: true,
-                            });
+                            attrs.push(uavcan_attribute("dsdl_signature", syn::Lit::Int(dsdl_signature, syn::IntTy::Unsuffixed)));
                             attrs
                         },
                         _ => Vec::new(),
@@ -256,11 +453,7 @@ impl Compile<Vec<syn::Item>> for dsdl_parser::File {
                     let attrs = match item_kind {
                         syn::ItemKind::Enum(_,_) | syn::ItemKind::Struct(_,_) => {
                             let mut attrs = struct_attributes_res.clone();
-                            attrs.push(syn::Attribute{
-                                style: syn::AttrStyle::Outer,
-                                value: syn::MetaItem::NameValue(syn::Ident::from("DSDLSignature"), syn::Lit::Str(format!("0x{:x}", dsdl_signature), syn::StrStyle::Cooked)),
-                                is_sugared_doc: true,
-                            });
+                            attrs.push(uavcan_attribute("dsdl_signature", syn::Lit::Int(dsdl_signature, syn::IntTy::Unsuffixed)));
                             attrs
                         },
                         _ => Vec::new(),
@@ -275,6 +468,9 @@ impl Compile<Vec<syn::Item>> for dsdl_parser::File {
                     
                 }
 
+                items.extend(constants_req);
+                items.extend(constants_res);
+
                 if let Some(ref id) = self.name.id {
                     items.push(syn::Item {
                         ident: syn::Ident::from(self.name.name.clone() + "Request"),
@@ -395,6 +591,7 @@ impl Compile<(Vec<syn::ItemKind>, Vec<syn::Attribute>)> for dsdl_parser::Message
         let mut attributes = current_comments.clone();
         let mut void_number = 0;
         let mut only_primitive_types = true;
+        let mut long_static_array = false;
 
         if union {
             let mut variants = Vec::new();
@@ -442,6 +639,16 @@ impl Compile<(Vec<syn::ItemKind>, Vec<syn::Attribute>)> for dsdl_parser::Message
                         if let Some(comment) = opt_comment {
                             current_comments.push(comment.compile(config));
                         }
+                        if let Some(dsdl_parser::ArrayInfo::Static(size)) = def.array {
+                            // Arrays of `bool` are packed in a `BitArray`, which implements `Default` at any length
+                            let packed = match def.field_type {
+                                dsdl_parser::Ty::Primitive(dsdl_parser::PrimitiveType::Bool) => true,
+                                _ => false,
+                            };
+                            if size > 32 && !packed {
+                                long_static_array = true;
+                            }
+                        }
                         let mut field: syn::Field = def.clone().compile(config);
                         if def.field_type.is_void() {
                             field.ident = Some(syn::Ident::from(format!("_v{}", void_number)));
@@ -471,50 +678,91 @@ impl Compile<(Vec<syn::ItemKind>, Vec<syn::Attribute>)> for dsdl_parser::Message
                     derives.push(syn::NestedMetaItem::MetaItem(syn::MetaItem::Word(syn::Ident::from("Default"))));
                 }
             },
+            DeriveDefault::All => {
+                if !union && !long_static_array {
+                    derives.push(syn::NestedMetaItem::MetaItem(syn::MetaItem::Word(syn::Ident::from("Default"))));
+                }
+            },
         }
         
         attributes.push(syn::Attribute{style: syn::AttrStyle::Outer, is_sugared_doc: false, value: syn::MetaItem::List(syn::Ident::from("derive"), derives)});
         
-        attributes.push(syn::Attribute{style: syn::AttrStyle::Outer, is_sugared_doc: false, value: syn::MetaItem::NameValue(
-            syn::Ident::from("UavcanCrateName"),
-            syn::Lit::Str(String::from("uavcan_rs"), syn::StrStyle::Cooked),
-        )});
+        attributes.push(uavcan_attribute("crate_name", syn::Lit::Str(String::from("uavcan_rs"), syn::StrStyle::Cooked)));
 
         (items, attributes)
     }
 }
 
 
+/// The type of a field, or of the elements of an array field, with the cast mode of the field
+///
+/// Fields declared `truncated` are wrapped in `Truncated`, `saturated` is the default cast mode of the plain types.
+fn compile_element_type(cast_mode: Option<dsdl_parser::CastMode>, field_type: dsdl_parser::Ty, config: &CompileConfig) -> syn::Ty {
+    let ty = field_type.compile(config);
+    match cast_mode {
+        Some(dsdl_parser::CastMode::Truncated) => syn::Ty::Path(
+            None, syn::Path{
+                global: true,
+                segments: vec![syn::PathSegment{
+                    ident: syn::Ident::from("Truncated"),
+                    parameters: syn::PathParameters::AngleBracketed(syn::AngleBracketedParameterData{
+                        lifetimes: Vec::new(),
+                        types: vec![ty],
+                        bindings: Vec::new(),
+                    })
+                }],
+            }),
+        _ => ty,
+    }
+}
+
+/// Names of the fields that conventionally hold text
+const TEXTUAL_FIELDS: [&str; 4] = ["name", "source", "text", "string_value"];
+
+/// The type of a field, including the array it is declared as
+///
+/// Arrays of `bool` are packed in `BitArray` and `DynamicBitArray`,
+/// and dynamic arrays of `uint8` that conventionally hold text are `Utf8String`.
+fn compile_field_type(name: &Option<dsdl_parser::Ident>, cast_mode: Option<dsdl_parser::CastMode>, field_type: dsdl_parser::Ty, array: Option<dsdl_parser::ArrayInfo>, config: &CompileConfig) -> syn::Ty {
+    let (packed, textual) = match (name, &field_type) {
+        (_, &dsdl_parser::Ty::Primitive(dsdl_parser::PrimitiveType::Bool)) => (true, false),
+        (&Some(ref name), &dsdl_parser::Ty::Primitive(dsdl_parser::PrimitiveType::Uint8)) => (false, TEXTUAL_FIELDS.contains(&name.as_ref())),
+        _ => (false, false),
+    };
+    let dynamic_array = if packed {
+        "DynamicBitArray"
+    } else if textual {
+        "Utf8String"
+    } else {
+        "Dynamic"
+    };
+
+    let element_type = compile_element_type(cast_mode, field_type, config);
+    let (array_type, size) = match array {
+        None => return element_type,
+        Some(dsdl_parser::ArrayInfo::DynamicLess(size)) => (dynamic_array, size-1),
+        Some(dsdl_parser::ArrayInfo::DynamicLeq(size)) => (dynamic_array, size),
+        Some(dsdl_parser::ArrayInfo::Static(size)) if packed => ("BitArray", size),
+        Some(dsdl_parser::ArrayInfo::Static(size)) => return syn::Ty::Array(Box::new(element_type), syn::ConstExpr::Lit(syn::Lit::Int(size, syn::IntTy::Unsuffixed))),
+    };
+
+    syn::Ty::Path(
+        None, syn::Path{
+            global: true,
+            segments: vec![syn::PathSegment{
+                ident: syn::Ident::from(array_type),
+                parameters: syn::PathParameters::AngleBracketed(syn::AngleBracketedParameterData{
+                    lifetimes: Vec::new(),
+                    types: vec![syn::Ty::Array(Box::new(element_type), syn::ConstExpr::Lit(syn::Lit::Int(size, syn::IntTy::Unsuffixed)))],
+                    bindings: Vec::new(),
+                })
+            }],
+        })
+}
+
 impl Compile<syn::Field> for dsdl_parser::FieldDefinition {
     fn compile(self, config: &CompileConfig) -> syn::Field {
-        let ty = match self.array {
-            None => self.field_type.compile(config),
-            Some(dsdl_parser::ArrayInfo::DynamicLess(size)) => syn::Ty::Path(
-                None, syn::Path{
-                    global: true,
-                    segments: vec![syn::PathSegment{
-                        ident: syn::Ident::from("Dynamic"),
-                        parameters: syn::PathParameters::AngleBracketed(syn::AngleBracketedParameterData{
-                            lifetimes: Vec::new(),
-                            types: vec![syn::Ty::Array(Box::new(self.field_type.compile(config)), syn::ConstExpr::Lit(syn::Lit::Int(size-1, syn::IntTy::Unsuffixed)))],
-                            bindings: Vec::new(),
-                        })
-                    }],
-                }),
-            Some(dsdl_parser::ArrayInfo::DynamicLeq(size)) => syn::Ty::Path(
-                None, syn::Path{
-                    global: true,
-                    segments: vec![syn::PathSegment{
-                        ident: syn::Ident::from("Dynamic"),
-                        parameters: syn::PathParameters::AngleBracketed(syn::AngleBracketedParameterData{
-                            lifetimes: Vec::new(),
-                            types: vec![syn::Ty::Array(Box::new(self.field_type.compile(config)), syn::ConstExpr::Lit(syn::Lit::Int(size, syn::IntTy::Unsuffixed)))],
-                            bindings: Vec::new(),
-                        })
-                    }],
-                }),
-            Some(dsdl_parser::ArrayInfo::Static(size)) => syn::Ty::Array(Box::new(self.field_type.compile(config)), syn::ConstExpr::Lit(syn::Lit::Int(size, syn::IntTy::Unsuffixed))),
-        };
+        let ty = compile_field_type(&self.name, self.cast_mode, self.field_type, self.array, config);
         
         syn::Field{
             ident: self.name.map(|x| x.compile(config)),
@@ -527,34 +775,7 @@ impl Compile<syn::Field> for dsdl_parser::FieldDefinition {
 
 impl Compile<syn::Variant> for dsdl_parser::FieldDefinition {
     fn compile(self, config: &CompileConfig) -> syn::Variant {
-        let ty = match self.array {
-            None => self.field_type.compile(config),
-            Some(dsdl_parser::ArrayInfo::DynamicLess(size)) => syn::Ty::Path(
-                None, syn::Path{
-                    global: true,
-                    segments: vec![syn::PathSegment{
-                        ident: syn::Ident::from("Dynamic"),
-                        parameters: syn::PathParameters::AngleBracketed(syn::AngleBracketedParameterData{
-                            lifetimes: Vec::new(),
-                            types: vec![syn::Ty::Array(Box::new(self.field_type.compile(config)), syn::ConstExpr::Lit(syn::Lit::Int(size-1, syn::IntTy::Unsuffixed)))],
-                            bindings: Vec::new(),
-                        })
-                    }],
-                }),
-            Some(dsdl_parser::ArrayInfo::DynamicLeq(size)) => syn::Ty::Path(
-                None, syn::Path{
-                    global: true,
-                    segments: vec![syn::PathSegment{
-                        ident: syn::Ident::from("Dynamic"),
-                        parameters: syn::PathParameters::AngleBracketed(syn::AngleBracketedParameterData{
-                            lifetimes: Vec::new(),
-                            types: vec![syn::Ty::Array(Box::new(self.field_type.compile(config)), syn::ConstExpr::Lit(syn::Lit::Int(size, syn::IntTy::Unsuffixed)))],
-                            bindings: Vec::new(),
-                        })
-                    }],
-                }),
-            Some(dsdl_parser::ArrayInfo::Static(size)) => syn::Ty::Array(Box::new(self.field_type.compile(config)), syn::ConstExpr::Lit(syn::Lit::Int(size, syn::IntTy::Unsuffixed))),
-        };
+        let ty = compile_field_type(&self.name, self.cast_mode, self.field_type, self.array, config);
 
         syn::Variant {
             ident: syn::Ident::from(String::from(self.name.unwrap()).to_pascal_case()),
@@ -824,6 +1045,8 @@ impl Compile<syn::Ty> for dsdl_parser::PrimitiveType {
 
 #[cfg(test)]
 mod tests {
+    use std::env;
+    use std::io::Read;
     use std::str::FromStr;
 
     use *;
@@ -835,6 +1058,25 @@ mod tests {
         dsdl.compile(&CompileConfig::default());
     }    
 
+    #[test]
+    fn compile_vendor_roots() {
+        let output = env::temp_dir().join("dsdl_compiler_vendor_roots.rs");
+        compile_roots_to_file(&["tests/dsdl/uavcan", "tests/vendor_dsdl/com_example"], &output, &CompileConfig::default()).unwrap();
+
+        let mut code = String::new();
+        File::open(&output).unwrap().read_to_string(&mut code).unwrap();
+        assert!(code.contains("pub mod com_example"));
+        assert!(code.contains("pub struct NodeStatus"));
+        assert!(code.contains("20000"));
+    }
+
+    #[test]
+    fn conflicting_type_ids() {
+        let output = env::temp_dir().join("dsdl_compiler_conflicting_type_ids.rs");
+        let error = compile_roots_to_file(&["tests/dsdl/uavcan", "tests/vendor_dsdl/conflicting"], &output, &CompileConfig::default()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
     #[test]
     fn compile_data_type_signature() {
         let dsdl = DSDL::read("tests/dsdl/uavcan/protocol/341.NodeStatus.uavcan").unwrap();
@@ -847,6 +1089,8 @@ mod tests {
         
         let file = dsdl.compile(&compile_config);
 
+        let dsdl_signature = syn::Lit::Int(0xbe7710808d2ff575, syn::IntTy::Unsuffixed);
+        let signature = syn::Lit::Int(0xbe7710808d2ff575, syn::IntTy::Unsuffixed);
         assert_eq!(quote!(#(#file)*), quote!{
             #[allow(unused_imports)]
             #[macro_use]
@@ -860,9 +1104,9 @@ mod tests {
             #[doc = " Any UAVCAN node is required to publish this message periodically."]
             #[doc = ""]
             #[derive(Debug, Clone, UavcanStruct, Default)]
-            #[UavcanCrateName = "uavcan_rs"]
-            #[DSDLSignature = "0xbe7710808d2ff575"] 
-            #[DataTypeSignature = "0xbe7710808d2ff575"] 
+            #[uavcan(crate_name = "uavcan_rs")]
+            #[uavcan(dsdl_signature = #dsdl_signature)]
+            #[uavcan(signature = #signature)]
             pub struct NodeStatus {
                 #[doc = ""]
                 #[doc = " Uptime counter should never overflow."]
@@ -892,6 +1136,30 @@ mod tests {
                 #[doc = ""]
                 pub vendor_specific_status_code: u16
             }
+
+            impl NodeStatus {
+                pub const MAX_BROADCASTING_PERIOD_MS: u16 = 1000;
+                pub const MIN_BROADCASTING_PERIOD_MS: u16 = 2;
+                pub const OFFLINE_TIMEOUT_MS: u16 = 3000;
+                #[doc = " The node is functioning properly."]
+                pub const HEALTH_OK: u8 = 0;
+                #[doc = " A critical parameter went out of range or the node encountered a minor failure."]
+                pub const HEALTH_WARNING: u8 = 1;
+                #[doc = " The node encountered a major failure."]
+                pub const HEALTH_ERROR: u8 = 2;
+                #[doc = " The node suffered a fatal malfunction."]
+                pub const HEALTH_CRITICAL: u8 = 3;
+                #[doc = " Normal operating mode."]
+                pub const MODE_OPERATIONAL: u8 = 0;
+                #[doc = " Initialization is in progress; this mode is entered immediately after startup."]
+                pub const MODE_INITIALIZATION: u8 = 1;
+                #[doc = " E.g. calibration, the bootloader is running, etc."]
+                pub const MODE_MAINTENANCE: u8 = 2;
+                #[doc = " New software/firmware is being loaded."]
+                pub const MODE_SOFTWARE_UPDATE: u8 = 3;
+                #[doc = " The node is no longer available."]
+                pub const MODE_OFFLINE: u8 = 7;
+            }
             
             impl ::uavcan_rs::Message for NodeStatus {
                 const TYPE_ID: Option<u16> = Some(341);
@@ -905,6 +1173,7 @@ mod tests {
         let dsdl = DSDL::read("tests/dsdl/").unwrap();
         let file = dsdl.get_file(&String::from("uavcan.protocol.GetNodeInfo")).unwrap().clone().compile(&CompileConfig::default());
         
+        let dsdl_signature = syn::Lit::Int(0xa80dc8995053e685, syn::IntTy::Unsuffixed);
         assert_eq!(quote!(#(#file)*), quote!{
             pub mod uavcan {
                 pub mod protocol {
@@ -913,13 +1182,13 @@ mod tests {
                     #[doc = " Note that all fields of the response section are byte-aligned."]
                     #[doc = ""]
                     #[derive(Debug, Clone, UavcanStruct, Default)]
-                    #[UavcanCrateName = "uavcan_rs"]
-                    #[DSDLSignature = "0xa80dc8995053e685"]
+                    #[uavcan(crate_name = "uavcan_rs")]
+                    #[uavcan(dsdl_signature = #dsdl_signature)]
                     pub struct GetNodeInfoRequest {}
 
-                    #[derive(Debug, Clone, UavcanStruct)]
-                    #[UavcanCrateName = "uavcan_rs"]
-                    #[DSDLSignature = "0xa80dc8995053e685"]
+                    #[derive(Debug, Clone, UavcanStruct, Default)]
+                    #[uavcan(crate_name = "uavcan_rs")]
+                    #[uavcan(dsdl_signature = #dsdl_signature)]
                     pub struct GetNodeInfoResponse {
                         #[doc = ""]
                         #[doc = " Current node status"]
@@ -939,7 +1208,7 @@ mod tests {
                         #[doc = " Allowed characters are: a-z (lowercase ASCII letters) 0-9 (decimal digits) . (dot) - (dash) _ (underscore)."]
                         #[doc = " Node name is a reversed internet domain name (like Java packages), e.g. \"com.manufacturer.project.product\"."]
                         #[doc = ""]
-                        pub name: ::Dynamic<[u8; 80]>
+                        pub name: ::Utf8String<[u8; 80]>
                     }
 
                     impl ::uavcan_rs::Request for GetNodeInfoRequest {
@@ -962,6 +1231,7 @@ mod tests {
         let dsdl = DSDL::read("tests/dsdl/").unwrap();
         let file = dsdl.get_file(&String::from("uavcan.protocol.param.Value")).unwrap().clone().compile(&CompileConfig::default());
         
+        let dsdl_signature = syn::Lit::Int(0xc3d96f448f2b00a1, syn::IntTy::Unsuffixed);
         assert_eq!(quote!(#(#file)*), quote!{
             pub mod uavcan {
                 pub mod protocol {
@@ -973,8 +1243,8 @@ mod tests {
                         #[doc = " The structure is prefixed with tag - a selector value that indicates which particular field is encoded."]
                         #[doc = ""]
                         #[derive(Debug, Clone, UavcanStruct)]
-                        #[UavcanCrateName = "uavcan_rs"]
-                        #[DSDLSignature = "0xc3d96f448f2b00a1"]
+                        #[uavcan(crate_name = "uavcan_rs")]
+                        #[uavcan(dsdl_signature = #dsdl_signature)]
                         pub enum Value {
                             #[doc = " Empty field, used to represent an undefined value."]
                             Empty(Empty),
@@ -984,7 +1254,7 @@ mod tests {
                             #[doc = " 8-bit value is used for alignment reasons"]
                             BooleanValue(u8),
                             #[doc = " Length prefix is exactly one byte long, which ensures proper alignment of payload"]
-                            StringValue(::Dynamic<[u8; 128]>),
+                            StringValue(::Utf8String<[u8; 128]>),
                         }
                     }
                 }
@@ -998,6 +1268,7 @@ mod tests {
         let dsdl = DSDL::read("tests/dsdl/").unwrap();
         let file = dsdl.get_file(&String::from("uavcan.protocol.NodeStatus")).unwrap().clone().compile(&CompileConfig::default());
         
+        let dsdl_signature = syn::Lit::Int(0xf0868d0c1a7c6f1, syn::IntTy::Unsuffixed);
         assert_eq!(quote!(#(#file)*), quote!{
             pub mod uavcan {
                 pub mod protocol {
@@ -1007,8 +1278,8 @@ mod tests {
                     #[doc = " Any UAVCAN node is required to publish this message periodically."]
                     #[doc = ""]
                     #[derive(Debug, Clone, UavcanStruct, Default)]
-                    #[UavcanCrateName = "uavcan_rs"]
-                    #[DSDLSignature = "0xf0868d0c1a7c6f1"] 
+                    #[uavcan(crate_name = "uavcan_rs")]
+                    #[uavcan(dsdl_signature = #dsdl_signature)]
                     pub struct NodeStatus {
                         #[doc = ""]
                         #[doc = " Uptime counter should never overflow."]
@@ -1038,6 +1309,30 @@ mod tests {
                         #[doc = ""]
                         pub vendor_specific_status_code: u16
                     }
+
+                    impl NodeStatus {
+                        pub const MAX_BROADCASTING_PERIOD_MS: u16 = 1000;
+                        pub const MIN_BROADCASTING_PERIOD_MS: u16 = 2;
+                        pub const OFFLINE_TIMEOUT_MS: u16 = 3000;
+                        #[doc = " The node is functioning properly."]
+                        pub const HEALTH_OK: u8 = 0;
+                        #[doc = " A critical parameter went out of range or the node encountered a minor failure."]
+                        pub const HEALTH_WARNING: u8 = 1;
+                        #[doc = " The node encountered a major failure."]
+                        pub const HEALTH_ERROR: u8 = 2;
+                        #[doc = " The node suffered a fatal malfunction."]
+                        pub const HEALTH_CRITICAL: u8 = 3;
+                        #[doc = " Normal operating mode."]
+                        pub const MODE_OPERATIONAL: u8 = 0;
+                        #[doc = " Initialization is in progress; this mode is entered immediately after startup."]
+                        pub const MODE_INITIALIZATION: u8 = 1;
+                        #[doc = " E.g. calibration, the bootloader is running, etc."]
+                        pub const MODE_MAINTENANCE: u8 = 2;
+                        #[doc = " New software/firmware is being loaded."]
+                        pub const MODE_SOFTWARE_UPDATE: u8 = 3;
+                        #[doc = " The node is no longer available."]
+                        pub const MODE_OFFLINE: u8 = 7;
+                    }
                     
                     impl ::uavcan_rs::Message for NodeStatus {
                         const TYPE_ID: Option<u16> = Some(341);
@@ -1091,7 +1386,7 @@ mod tests {
             #[doc = "about struct0"]
             #[doc = "about struct1"]
             #[derive(Debug, Clone, UavcanStruct, Default)]
-            #[UavcanCrateName = "uavcan_rs"]
+            #[uavcan(crate_name = "uavcan_rs")]
         ), quote!{#(#struct_attributes)*});
         
         assert_eq!(quote!({
@@ -1150,7 +1445,7 @@ mod tests {
             #[doc = "about enum0"]
             #[doc = "about enum1"]
             #[derive(Debug, Clone, UavcanStruct)]
-            #[UavcanCrateName = "uavcan_rs"]
+            #[uavcan(crate_name = "uavcan_rs")]
         }, quote!{#(#struct_attributes)*});
         
         assert_eq!(quote!{
@@ -1211,7 +1506,7 @@ mod tests {
             name: Some(dsdl_parser::Ident::from("very_long_name")),
         }.compile(&CompileConfig::default());
         
-        assert_eq!(quote!(VeryLongName(::Dynamic<[bool; 370]>)), quote!{#dynless_array_field});
+        assert_eq!(quote!(VeryLongName(::DynamicBitArray<[bool; 370]>)), quote!{#dynless_array_field});
 
     }
     
@@ -1260,7 +1555,7 @@ mod tests {
             name: Some(dsdl_parser::Ident::from("name")),
         }.compile(&CompileConfig::default());
         
-        assert_eq!(quote!(pub name: ::Dynamic<[bool; 370]>), quote!{#dynless_array_field});
+        assert_eq!(quote!(pub name: ::DynamicBitArray<[bool; 370]>), quote!{#dynless_array_field});
 
     }
         
@@ -20,7 +20,8 @@ enum UavcanType {
 }
 
 
-#[proc_macro_derive(UavcanStruct, attributes(DSDLSignature, DataTypeSignature, UavcanCrateName))]
+// The attributes besides `uavcan` are only accepted to point their users at the `uavcan` keys replacing them
+#[proc_macro_derive(UavcanStruct, attributes(uavcan, DSDLSignature, DataTypeSignature, UavcanCrateName, TypeID, ServiceRequest, ServiceResponse))]
 pub fn uavcan_sized(input: TokenStream) -> TokenStream {
     let s = input.to_string();
     let ast = syn::parse_macro_input(&s).unwrap();
@@ -35,74 +36,271 @@ fn impl_uavcan_struct(ast: &syn::DeriveInput) -> quote::Tokens {
     let mut dsdl_signature = quote!{0x00};
     let mut data_type_signature = quote!{0x00};
     let mut crate_name = quote!{uavcan};
+    let mut type_id = None;
+    let mut response_type = None;
+    let mut request_type = None;
+    let mut message = false;
+    let mut max_bytes = None;
+    let mut debug = false;
+    let mut arbitrary = false;
     
     for attr in &ast.attrs {
-        if let syn::MetaItem::NameValue(ref ident, ref lit) = attr.value {
-            if ident == "DSDLSignature" {
-                if let syn::Lit::Str(ref lit_str, _) = *lit {
-                    let value = Ident::from(lit_str.clone()); // hack needed since only string literals is supported for attributes
-                    dsdl_signature = quote!{#value};
-                } else {
-                    panic!("DSDLSignature must be on the form \"0x123456789abc\"");
-                }
-            } else if ident == "DataTypeSignature" {
-                if let syn::Lit::Str(ref lit_str, _) = *lit {
-                    let value = Ident::from(lit_str.clone()); // hack needed since only string literals is supported for attributes
-                    data_type_signature = quote!{#value};
-                } else {
-                    panic!("Data type signature must be on the form \"0x123456789abc\"");
-                }
-            } else if ident == "UavcanCrateName" {
-                if let syn::Lit::Str(ref lit_str, _) = *lit {
-                    let value = Ident::from(lit_str.clone()); // hack needed since only string literals is supported for attributes
-                    crate_name = quote!{#value};
-                } else {
-                    panic!("Crate name must be on the form \"uavcan_alternative\"");
+        // `#[uavcan(message, id = 341, signature = 0x0f0868d0c1a7c6f1)]`, a type may have several `uavcan` attributes.
+        // The halves of a service name each other, `#[uavcan(id = 1, response = "GetNodeInfoResponse")]` on the request.
+        if let syn::MetaItem::List(ref ident, ref items) = attr.value {
+            if ident == "uavcan" {
+                for item in items {
+                    match *item {
+                        syn::NestedMetaItem::MetaItem(syn::MetaItem::Word(ref word)) if word == "message" => message = true,
+                        syn::NestedMetaItem::MetaItem(syn::MetaItem::Word(ref word)) if word == "debug" => debug = true,
+                        syn::NestedMetaItem::MetaItem(syn::MetaItem::Word(ref word)) if word == "arbitrary" => arbitrary = true,
+                        syn::NestedMetaItem::MetaItem(syn::MetaItem::NameValue(ref key, syn::Lit::Int(value, _))) if key == "id" => {
+                            let value = Ident::from(value.to_string());
+                            type_id = Some(quote!{Some(#value)});
+                        },
+                        syn::NestedMetaItem::MetaItem(syn::MetaItem::NameValue(ref key, syn::Lit::Int(value, _))) if key == "signature" => {
+                            let value = Ident::from(format!("{:#x}", value));
+                            data_type_signature = quote!{#value};
+                        },
+                        syn::NestedMetaItem::MetaItem(syn::MetaItem::NameValue(ref key, syn::Lit::Int(value, _))) if key == "dsdl_signature" => {
+                            let value = Ident::from(format!("{:#x}", value));
+                            dsdl_signature = quote!{#value};
+                        },
+                        syn::NestedMetaItem::MetaItem(syn::MetaItem::NameValue(ref key, syn::Lit::Int(value, _))) if key == "max_bytes" => max_bytes = Some(value as usize),
+                        syn::NestedMetaItem::MetaItem(syn::MetaItem::NameValue(ref key, syn::Lit::Str(ref value, _))) if key == "request" => {
+                            let value = Ident::from(value.clone());
+                            request_type = Some(quote!{#value});
+                        },
+                        syn::NestedMetaItem::MetaItem(syn::MetaItem::NameValue(ref key, syn::Lit::Str(ref value, _))) if key == "response" => {
+                            let value = Ident::from(value.clone());
+                            response_type = Some(quote!{#value});
+                        },
+                        syn::NestedMetaItem::MetaItem(syn::MetaItem::NameValue(ref key, syn::Lit::Str(ref value, _))) if key == "crate_name" => {
+                            let value = Ident::from(value.clone());
+                            crate_name = quote!{#value};
+                        },
+                        _ => panic!("The uavcan attribute takes `message`, `debug`, `arbitrary`, `id = 341`, `signature = 0x123456789abc`, `dsdl_signature = 0x123456789abc`, `max_bytes = 7`, \
+                                     `response = \"GetNodeInfoResponse\"`, `request = \"GetNodeInfoRequest\"` and `crate_name = \"uavcan_alternative\"`"),
+                    }
                 }
             }
+        }
 
+        // The attributes of the first versions of the derive are replaced by keys of `#[uavcan(...)]`
+        if let syn::MetaItem::NameValue(ref ident, _) = attr.value {
+            let replacement = match ident.as_ref() {
+                "DSDLSignature" => Some("`#[uavcan(dsdl_signature = 0x123456789abc)]`"),
+                "DataTypeSignature" => Some("`#[uavcan(signature = 0x123456789abc)]`"),
+                "UavcanCrateName" => Some("`#[uavcan(crate_name = \"uavcan_alternative\")]`"),
+                "TypeID" => Some("`#[uavcan(id = 341)]`"),
+                "ServiceRequest" => Some("`#[uavcan(response = \"GetNodeInfoResponse\")]` on the request"),
+                "ServiceResponse" => Some("`#[uavcan(request = \"GetNodeInfoRequest\")]` on the response"),
+                _ => None,
+            };
+            if let Some(replacement) = replacement {
+                panic!("`#[{} = ...]` is no longer supported, use {} instead", ident, replacement);
+            }
         }
     }
 
+    // The type parameters of generic structures are serialized as fields
+    let generics = with_bound(&ast.generics, &format!("::{}::Serializable", crate_name));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
     let mut bit_length_min = Tokens::new();
+    let mut bit_length_max = Tokens::new();
+    let mut tail_bit_length_min = Tokens::new();
+    let mut tail_bit_length_max = Tokens::new();
     let mut flattened_fields = Tokens::new();
     let mut serialize_body = Tokens::new();
     let mut deserialize_body = Tokens::new();
+    let mut default_impl = Tokens::new();
+    let mut borrow_impl = Tokens::new();
+    let mut accessor_impl = Tokens::new();
     
     match ast.body {
         Body::Enum(ref variants) => {
-            // MIN and MAX bit length for enums is not implemented yet
-            bit_length_min.append(quote!{0});
+            // Enums are DSDL unions, a tag of the minimal bit length selecting the variant followed by the field of the variant.
+            // The tag is flattened field 0, the fields of the variants follow in order.
+            if variants.len() < 2 {
+                panic!("Unions must have at least two variants");
+            }
+            let mut tag_bits = 0usize;
+            while (1usize << tag_bits) < variants.len() {
+                tag_bits += 1;
+            }
 
-            flattened_fields.append(quote!{0});
-            
-            for variant in variants {
-                if variant.data.fields().len() != 1 {
+            let mut field_index = Tokens::new();
+            let mut variants_bit_length_min = Tokens::new();
+            let mut variants_bit_length_max = Tokens::new();
+            let mut variants_tail_bit_length_min = Tokens::new();
+            let mut variants_tail_bit_length_max = Tokens::new();
+            let mut serialize_tag = Tokens::new();
+            let mut serialize_fields = Tokens::new();
+            let mut deserialize_variants = Tokens::new();
+            let mut deserialize_fields = Tokens::new();
+
+            flattened_fields.append(quote!{1});
+            field_index.append(quote!{1});
+
+            for (i, variant) in variants.iter().enumerate() {
+                let field_type = if variant.data.fields().len() != 1 {
                     panic!("Enum variants must have exactly one field");
-                } else if let Some(field) = variant.data.fields().first() {
-                    let field_type = &field.ty;
-                    
-                    match classify_type(field_type) {
-                        UavcanType::PrimitiveType | UavcanType::DynamicArray | UavcanType::StaticArray => flattened_fields.append(quote!{ + 1}),
-                        UavcanType::Struct => flattened_fields.append(quote!{ + #field_type::FLATTENED_FIELDS_NUMBER}),
+                } else {
+                    &variant.data.fields()[0].ty
+                };
+                let variant_ident = &variant.ident;
+                let tag = i as u64;
+
+                let field_length = match classify_type(field_type) {
+                    UavcanType::PrimitiveType => quote!(1),
+                    UavcanType::DynamicArray => {
+                        let array_type = array_from_dynamic(field_type);
+                        quote!{<::#crate_name::types::Dynamic<#array_type> as ::#crate_name::Serializable>::FLATTENED_FIELDS_NUMBER}
+                    },
+                    UavcanType::StaticArray | UavcanType::Struct => quote!{<#field_type as ::#crate_name::Serializable>::FLATTENED_FIELDS_NUMBER},
+                };
+                let serializable_type = match classify_type(field_type) {
+                    UavcanType::DynamicArray => {
+                        let array_type = array_from_dynamic(field_type);
+                        quote!{<::#crate_name::types::Dynamic<#array_type> as ::#crate_name::Serializable>}
+                    },
+                    _ => quote!{<#field_type as ::#crate_name::Serializable>},
+                };
+
+                flattened_fields.append(quote!{ + #field_length});
+
+                // The variant field is the last field of the union
+                variants_bit_length_min = extremum(i, variants_bit_length_min, quote!{#serializable_type::BIT_LENGTH_MIN}, quote!{<});
+                variants_bit_length_max = extremum(i, variants_bit_length_max, quote!{#serializable_type::BIT_LENGTH_MAX}, quote!{>});
+                variants_tail_bit_length_min = extremum(i, variants_tail_bit_length_min, quote!{#serializable_type::TAIL_BIT_LENGTH_MIN}, quote!{<});
+                variants_tail_bit_length_max = extremum(i, variants_tail_bit_length_max, quote!{#serializable_type::TAIL_BIT_LENGTH_MAX}, quote!{>});
+
+                serialize_tag.append(quote!{ #name::#variant_ident(_) => (#tag, #field_index), });
+
+                serialize_fields.append(quote!{ #name::#variant_ident(ref field) => {
+                    let mut current_field = *flattened_field - (#field_index);
+                    if current_field < #field_length {
+                        let result = ::#crate_name::Serializable::serialize(field, &mut current_field, bit, last_field, buffer);
+                        *flattened_field = (#field_index) + current_field;
+                        if result != ::#crate_name::SerializationResult::Finished {
+                            return ::#crate_name::SerializationResult::BufferFull;
+                        }
                     }
-                }
+                }});
+
+                deserialize_variants.append(quote!{ #tag => {
+                    *self = #name::#variant_ident(unsafe { ::#crate_name::zeroed() });
+                    *flattened_field = #field_index;
+                }});
+
+                deserialize_fields.append(quote!{ #name::#variant_ident(ref mut field) => {
+                    let mut current_field = *flattened_field - (#field_index);
+                    if current_field < #field_length {
+                        let result = ::#crate_name::Serializable::deserialize(field, &mut current_field, bit, last_field, buffer);
+                        *flattened_field = (#field_index) + current_field;
+                        if result != ::#crate_name::DeserializationResult::Finished {
+                            return ::#crate_name::DeserializationResult::BufferInsufficient;
+                        }
+                    }
+                }});
+
+                field_index.append(quote!{ + #field_length});
             }
 
-            serialize_body = quote!(unimplemented!("Serialization is not implemented for enum yet"));
-            deserialize_body = quote!(unimplemented!("Serialization is not implemented for enum yet"));
+            bit_length_min.append(quote!{#tag_bits + #variants_bit_length_min});
+            bit_length_max.append(quote!{#tag_bits + #variants_bit_length_max});
+            tail_bit_length_min.append(quote!{#tag_bits + #variants_tail_bit_length_min});
+            tail_bit_length_max.append(quote!{#tag_bits + #variants_tail_bit_length_max});
+
+            serialize_body = quote!{
+                if *flattened_field == 0 {
+                    let (tag, first_field): (u64, usize) = match *self {
+                        #serialize_tag
+                    };
+                    let tag_bits_remaining = #tag_bits - *bit;
+                    let buffer_bits_remaining = buffer.bits_remaining();
+                    if buffer_bits_remaining == 0 {
+                        return ::#crate_name::SerializationResult::BufferFull;
+                    } else if buffer_bits_remaining < tag_bits_remaining {
+                        buffer.push_bits(buffer_bits_remaining, tag >> *bit);
+                        *bit += buffer_bits_remaining;
+                        return ::#crate_name::SerializationResult::BufferFull;
+                    }
+                    buffer.push_bits(tag_bits_remaining, tag >> *bit);
+                    *bit = 0;
+                    *flattened_field = first_field;
+                }
+
+                match *self {
+                    #serialize_fields
+                }
+                *flattened_field = Self::FLATTENED_FIELDS_NUMBER;
+                *bit = 0;
+            };
+
+            // A union defaults to what an all zero payload decodes to, the first variant with its field zero initialized.
+            // `Default` can't be derived for enums with fields, and the variant fields may not implement it either.
+            default_impl = quote!{
+                impl #impl_generics Default for #name #ty_generics #where_clause {
+                    fn default() -> Self {
+                        ::#crate_name::Deserializer::<Self>::new().into_structure().expect("tag zero is always a valid variant")
+                    }
+                }
+            };
+
+            // While the tag is read, `bit` holds the number of tag bits read in the lowest byte and the tag bits read so far above it.
+            // The tag bits that were not received read as zero when the buffer is zero extended.
+            // A tag without a variant leaves the union unfinished.
+            deserialize_body = quote!{
+                if *flattened_field == 0 {
+                    let tag_bits_read = *bit & 0xff;
+                    let mut tag = (*bit >> 8) as u64;
+                    let buffer_len = buffer.bit_length();
+                    if buffer_len < #tag_bits - tag_bits_read && !buffer.is_zero_extended() {
+                        if buffer_len > 0 {
+                            tag |= buffer.pop_bits(buffer_len) << tag_bits_read;
+                        }
+                        *bit = (tag_bits_read + buffer_len) | ((tag as usize) << 8);
+                        return ::#crate_name::DeserializationResult::BufferInsufficient;
+                    }
+                    if tag_bits_read < #tag_bits && !buffer.is_zero_extended() {
+                        tag |= buffer.pop_bits(#tag_bits - tag_bits_read) << tag_bits_read;
+                    }
+                    *bit = #tag_bits | ((tag as usize) << 8);
+                    match tag {
+                        #deserialize_variants
+                        _ => return ::#crate_name::DeserializationResult::BufferInsufficient,
+                    }
+                    *bit = 0;
+                }
 
+                match *self {
+                    #deserialize_fields
+                }
+                *flattened_field = Self::FLATTENED_FIELDS_NUMBER;
+                *bit = 0;
+            };
         },
         Body::Struct(syn::VariantData::Struct(ref fields)) => {
             let mut field_index = Tokens::new();
             
             bit_length_min.append(quote!{0});
+            bit_length_max.append(quote!{0});
+            tail_bit_length_min.append(quote!{0});
+            tail_bit_length_max.append(quote!{0});
             flattened_fields.append(quote!{0});
             field_index.append(quote!{0});
             
             for (i, field) in fields.iter().enumerate() {
                 let field_ident = &field.ident;
-                let field_type = &field.ty;
+
+                // Native fields with a `#[uavcan(bits = N)]` attribute are handled as the UAVCAN type they are serialized as
+                let native_field = native_field(field, &crate_name);
+                let field_type = match native_field {
+                    Some((ref wire_type, _)) => wire_type,
+                    None => &field.ty,
+                };
 
                 let last_field = if i == fields.len()-1 {
                     quote!{true}
@@ -120,6 +318,23 @@ fn impl_uavcan_struct(ast: &syn::DeriveInput) -> quote::Tokens {
                     },
                     UavcanType::Struct => bit_length_min.append(quote!{ + <#field_type as ::#crate_name::Serializable>::BIT_LENGTH_MIN}),
                 }
+
+                let serializable_type = match classify_type(field_type) {
+                    UavcanType::DynamicArray => {
+                        let array_type = array_from_dynamic(field_type);
+                        quote!{<::#crate_name::types::Dynamic<#array_type> as ::#crate_name::Serializable>}
+                    },
+                    _ => quote!{<#field_type as ::#crate_name::Serializable>},
+                };
+
+                bit_length_max.append(quote!{ + #serializable_type::BIT_LENGTH_MAX});
+                if i == fields.len()-1 {
+                    tail_bit_length_min.append(quote!{ + #serializable_type::TAIL_BIT_LENGTH_MIN});
+                    tail_bit_length_max.append(quote!{ + #serializable_type::TAIL_BIT_LENGTH_MAX});
+                } else {
+                    tail_bit_length_min.append(quote!{ + #serializable_type::BIT_LENGTH_MIN});
+                    tail_bit_length_max.append(quote!{ + #serializable_type::BIT_LENGTH_MAX});
+                }
                 
                 match classify_type(field_type) {
                     UavcanType::PrimitiveType => flattened_fields.append(quote!{ + 1}),
@@ -145,9 +360,29 @@ fn impl_uavcan_struct(ast: &syn::DeriveInput) -> quote::Tokens {
                     UavcanType::Struct => quote!{<#field_type as ::#crate_name::Serializable>::FLATTENED_FIELDS_NUMBER},
                 };
                 
+                let (serialize_field, deserialize_field) = match native_field {
+                    Some((ref wire_type, ref widening)) => (
+                        quote!{{
+                            let value: #wire_type = ::#crate_name::types::CastFrom::cast_from(self.#field_ident);
+                            ::#crate_name::Serializable::serialize(&value, &mut current_field, bit, #last_field && last_field, buffer)
+                        }},
+                        // The bits deserialized so far always fit the native type, so a field split between buffers is kept in it
+                        quote!{{
+                            let mut value: #wire_type = ::#crate_name::types::CastFrom::cast_from(self.#field_ident);
+                            let result = ::#crate_name::Serializable::deserialize(&mut value, &mut current_field, bit, #last_field && last_field, buffer);
+                            self.#field_ident = #widening;
+                            result
+                        }},
+                    ),
+                    None => (
+                        quote!{::#crate_name::Serializable::serialize(&self.#field_ident, &mut current_field, bit, #last_field && last_field, buffer)},
+                        quote!{::#crate_name::Serializable::deserialize(&mut self.#field_ident, &mut current_field, bit, #last_field && last_field, buffer)},
+                    ),
+                };
+
                 serialize_body.append(quote!{if *flattened_field >= (#field_index) && *flattened_field < (#field_index) + #field_length {
                     let mut current_field = *flattened_field - (#field_index);
-                    if ::#crate_name::Serializable::serialize(&self.#field_ident, &mut current_field, bit, #last_field && last_field, buffer) == ::#crate_name::SerializationResult::Finished {
+                    if (#serialize_field) == ::#crate_name::SerializationResult::Finished {
                         *flattened_field = (#field_index) + current_field;
                         *bit = 0;
                     } else {
@@ -156,9 +391,49 @@ fn impl_uavcan_struct(ast: &syn::DeriveInput) -> quote::Tokens {
                     }
                 }});
 
+                // `uint8[]` fields can be borrowed from a reassembled payload
+                if let Some(array_type) = array_from_dynamic(field_type).filter(is_byte_array) {
+                    let method = Ident::from(format!("borrow_{}", field_ident.as_ref().unwrap()));
+                    let doc = format!("Borrows `{}` from a reassembled payload, returns `None` if the bytes are not byte aligned or the payload ends within them", field_ident.as_ref().unwrap());
+                    borrow_impl.append(quote!{
+                        #[doc = #doc]
+                        pub fn #method(payload: &[u8]) -> Option<&[u8]> {
+                            ::#crate_name::borrow_byte_array::<Self>(
+                                payload,
+                                #field_index,
+                                <::#crate_name::types::Dynamic<#array_type>>::LENGTH_BITS,
+                                <::#crate_name::types::Dynamic<#array_type>>::MAX_LENGTH,
+                                #last_field,
+                            )
+                        }
+                    });
+                }
+
+                // Integer fields without a native equivalent, like `u13`, get accessors converting to and from the smallest native type holding them
+                if let (None, Some((native, wide))) = (native_field.as_ref(), native_integer_type(field_type)) {
+                    let vis = &field.vis;
+                    let field_name = field_ident.as_ref().unwrap();
+                    let setter = Ident::from(format!("set_{}", field_name));
+                    let getter_doc = format!("Returns `{}` as a `{}`", field_name, native);
+                    let setter_doc = format!("Sets `{}` from a `{}`, saturated to the range of the field", field_name, native);
+                    accessor_impl.append(quote!{
+                        #[doc = #getter_doc]
+                        #[allow(dead_code)]
+                        #vis fn #field_ident(&self) -> #native {
+                            #wide::from(self.#field_ident) as #native
+                        }
+
+                        #[doc = #setter_doc]
+                        #[allow(dead_code)]
+                        #vis fn #setter(&mut self, value: #native) {
+                            self.#field_ident = ::#crate_name::types::CastFrom::cast_from(value);
+                        }
+                    });
+                }
+
                 deserialize_body.append(quote!{if *flattened_field >= (#field_index) && *flattened_field < (#field_index) + #field_length {
                     let mut current_field = *flattened_field - (#field_index);
-                    if ::#crate_name::Serializable::deserialize(&mut self.#field_ident, &mut current_field, bit, #last_field && last_field, buffer) == ::#crate_name::DeserializationResult::Finished {
+                    if (#deserialize_field) == ::#crate_name::DeserializationResult::Finished {
                         *flattened_field = (#field_index) + current_field;
                         *bit = 0;
                     } else {
@@ -172,6 +447,9 @@ fn impl_uavcan_struct(ast: &syn::DeriveInput) -> quote::Tokens {
         },
         Body::Struct(syn::VariantData::Unit) => {
             bit_length_min = quote!(0);
+            bit_length_max = quote!(0);
+            tail_bit_length_min = quote!(0);
+            tail_bit_length_max = quote!(0);
             flattened_fields = quote!(0);
             serialize_body = quote!{
                 assert_eq!(*flattened_fields, 0);
@@ -188,20 +466,122 @@ fn impl_uavcan_struct(ast: &syn::DeriveInput) -> quote::Tokens {
         _ => panic!("UavcanStruct is only derivable for enums and named structs"),
     };
 
-    
+
+    // The halves of a service share the service type ID, which is only given to the request
+    let type_id_impl = match (response_type, request_type) {
+        (Some(_), Some(_)) => panic!("A type can't be both the request and the response of a service"),
+        (Some(_), None) | (None, Some(_)) if message => panic!("A type can't be both a message and a half of a service"),
+        (Some(response_type), None) => {
+            let type_id = type_id.unwrap_or(quote!{None});
+            quote!{
+                impl #impl_generics ::#crate_name::Request for #name #ty_generics #where_clause {
+                    type RESPONSE = #response_type;
+                    const TYPE_ID: Option<u8> = #type_id;
+                }
+            }
+        },
+        (None, Some(request_type)) => {
+            if type_id.is_some() {
+                panic!("The type ID of a service is given to the request, the response shares it");
+            }
+            quote!{
+                impl #impl_generics ::#crate_name::Response for #name #ty_generics #where_clause {
+                    type REQUEST = #request_type;
+                    const TYPE_ID: Option<u8> = <#request_type as ::#crate_name::Request>::TYPE_ID;
+                }
+            }
+        },
+        (None, None) => match type_id {
+            Some(type_id) => quote!{
+                impl #impl_generics ::#crate_name::Message for #name #ty_generics #where_clause {
+                    const TYPE_ID: Option<u16> = #type_id;
+                }
+            },
+            None if message => quote!{
+                impl #impl_generics ::#crate_name::Message for #name #ty_generics #where_clause {
+                    const TYPE_ID: Option<u16> = None;
+                }
+            },
+            None => Tokens::new(),
+        },
+    };
+
+    if !borrow_impl.as_str().is_empty() {
+        borrow_impl = quote!{
+            impl #impl_generics #name #ty_generics #where_clause {
+                #borrow_impl
+            }
+        };
+    }
+
+    // Types that must fit a transport are checked when they are compiled rather than when they are sent
+    let max_bytes_check = match max_bytes {
+        Some(_) if !ast.generics.ty_params.is_empty() => panic!("`max_bytes` can't be checked for generic types"),
+        Some(max_bytes) => {
+            let message = format!("`{}` can take more than {} bytes", name, max_bytes);
+            quote!{
+                const _: () = assert!(<#name as ::#crate_name::Struct>::MAX_BYTES <= #max_bytes, #message);
+            }
+        },
+        None => Tokens::new(),
+    };
+
+    if !accessor_impl.as_str().is_empty() {
+        accessor_impl = quote!{
+            impl #impl_generics #name #ty_generics #where_clause {
+                #accessor_impl
+            }
+        };
+    }
+
+    let debug_impl = if debug {
+        impl_debug(ast, &crate_name)
+    } else {
+        Tokens::new()
+    };
+    let serde_impl = impl_serde(ast, &crate_name);
+    let defmt_impl = impl_defmt(ast, &crate_name);
+    let arbitrary_impl = if arbitrary {
+        impl_arbitrary(ast, &crate_name)
+    } else {
+        Tokens::new()
+    };
+
     quote!{
-        impl ::#crate_name::Struct for #name {
+        #type_id_impl
+
+        #default_impl
+
+        #borrow_impl
+
+        #accessor_impl
+
+        #max_bytes_check
+
+        #debug_impl
+
+        #serde_impl
+
+        #defmt_impl
+
+        #arbitrary_impl
+
+        impl #impl_generics ::#crate_name::Struct for #name #ty_generics #where_clause {
             const DSDL_SIGNATURE: u64 = #dsdl_signature;
             const DATA_TYPE_SIGNATURE: u64 = #data_type_signature;
         }
 
-        impl ::#crate_name::Serializable for #name {
+        impl #impl_generics ::#crate_name::Serializable for #name #ty_generics #where_clause {
             const BIT_LENGTH_MIN: usize = #bit_length_min;
+            const BIT_LENGTH_MAX: usize = #bit_length_max;
+            const TAIL_BIT_LENGTH_MIN: usize = #tail_bit_length_min;
+            const TAIL_BIT_LENGTH_MAX: usize = #tail_bit_length_max;
             const FLATTENED_FIELDS_NUMBER: usize = #flattened_fields;
             #[allow(unused_comparisons)]
             #[allow(unused_variables)]
             fn serialize(&self, flattened_field: &mut usize, bit: &mut usize, last_field: bool, buffer: &mut ::#crate_name::SerializationBuffer) -> ::#crate_name::SerializationResult {
-                assert!(*flattened_field < Self::FLATTENED_FIELDS_NUMBER);
+                // structures without fields, like most service requests, are finished right away
+                assert!(*flattened_field <= Self::FLATTENED_FIELDS_NUMBER);
                 while *flattened_field != Self::FLATTENED_FIELDS_NUMBER{
                     assert!(*flattened_field < Self::FLATTENED_FIELDS_NUMBER);
                     #serialize_body
@@ -212,7 +592,8 @@ fn impl_uavcan_struct(ast: &syn::DeriveInput) -> quote::Tokens {
             #[allow(unused_comparisons)]
             #[allow(unused_variables)]
             fn deserialize(&mut self, flattened_field: &mut usize, bit: &mut usize, last_field: bool, buffer: &mut ::#crate_name::DeserializationBuffer) -> ::#crate_name::DeserializationResult {
-                assert!(*flattened_field < Self::FLATTENED_FIELDS_NUMBER);
+                // structures without fields, like most service requests, are finished right away
+                assert!(*flattened_field <= Self::FLATTENED_FIELDS_NUMBER);
                 while *flattened_field != Self::FLATTENED_FIELDS_NUMBER{
                     assert!(*flattened_field < Self::FLATTENED_FIELDS_NUMBER);
                     #deserialize_body
@@ -226,6 +607,415 @@ fn impl_uavcan_struct(ast: &syn::DeriveInput) -> quote::Tokens {
     }
 }
 
+/// The smallest (`<`) or largest (`>`) of the lengths of the variants so far and the length of variant `i`
+fn extremum(i: usize, variants: Tokens, variant: Tokens, comparison: Tokens) -> Tokens {
+    if i == 0 {
+        variant
+    } else {
+        quote!{{
+            let extremum = #variants;
+            let variant = #variant;
+            if variant #comparison extremum { variant } else { extremum }
+        }}
+    }
+}
+
+/// Implements `Debug` with the fields formatted by `debug_field`, by their decoded values
+fn impl_debug(ast: &syn::DeriveInput, crate_name: &Tokens) -> Tokens {
+    let name = &ast.ident;
+    let name_str = name.to_string();
+    let generics = with_bound(&ast.generics, &format!("::{}::debug_field::DebugField", crate_name));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match ast.body {
+        Body::Enum(ref variants) => {
+            let mut arms = Tokens::new();
+            for variant in variants {
+                let variant_ident = &variant.ident;
+                let variant_str = variant_ident.to_string();
+                arms.append(quote!{ #name::#variant_ident(ref field) => {
+                    f.debug_tuple(#variant_str).field(&::#crate_name::debug_field::Field(field, None)).finish()
+                }});
+            }
+            quote!{ match *self { #arms } }
+        },
+        Body::Struct(syn::VariantData::Struct(ref fields)) => {
+            let mut body = quote!{ f.debug_struct(#name_str) };
+            for field in fields {
+                let field_ident = &field.ident;
+                let field_type = &field.ty;
+                let field_str = field_ident.as_ref().unwrap().to_string();
+
+                // The first constant equal to the field names its value
+                let mut constant_name = quote!{ None };
+                for constant in constant_names(field).iter().rev() {
+                    let constant_str = constant.to_string();
+                    constant_name = quote!{
+                        if self.#field_ident == <#field_type as ::#crate_name::types::CastFrom<_>>::cast_from(Self::#constant) {
+                            Some(#constant_str)
+                        } else {
+                            #constant_name
+                        }
+                    };
+                }
+
+                body.append(quote!{ .field(#field_str, &::#crate_name::debug_field::Field(&self.#field_ident, #constant_name)) });
+            }
+            quote!{ #body.finish() }
+        },
+        Body::Struct(syn::VariantData::Unit) => quote!{ f.write_str(#name_str) },
+        _ => panic!("UavcanStruct is only derivable for enums and named structs"),
+    };
+
+    quote!{
+        impl #impl_generics ::#crate_name::debug_field::DebugField for #name #ty_generics #where_clause {
+            fn fmt_field(&self, f: &mut ::#crate_name::debug_field::fmt::Formatter) -> ::#crate_name::debug_field::fmt::Result {
+                #body
+            }
+        }
+
+        impl #impl_generics ::#crate_name::debug_field::fmt::Debug for #name #ty_generics #where_clause {
+            fn fmt(&self, f: &mut ::#crate_name::debug_field::fmt::Formatter) -> ::#crate_name::debug_field::fmt::Result {
+                ::#crate_name::debug_field::DebugField::fmt_field(self, f)
+            }
+        }
+    }
+}
+
+/// The constants naming the values of a field with a `#[uavcan(names(HEALTH_OK, HEALTH_WARNING))]` attribute
+fn constant_names(field: &syn::Field) -> Vec<Ident> {
+    let mut names = Vec::new();
+    for attr in &field.attrs {
+        if let syn::MetaItem::List(ref ident, ref items) = attr.value {
+            if ident == "uavcan" {
+                for item in items {
+                    if let syn::NestedMetaItem::MetaItem(syn::MetaItem::List(ref key, ref constants)) = *item {
+                        if key == "names" {
+                            for constant in constants {
+                                match *constant {
+                                    syn::NestedMetaItem::MetaItem(syn::MetaItem::Word(ref constant)) => names.push(constant.clone()),
+                                    _ => panic!("`names` takes the names of constants, like `names(HEALTH_OK, HEALTH_WARNING)`"),
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    names
+}
+
+/// Implements serde's `Serialize` and `Deserialize` through a remote definition,
+/// where the fields are converted by `serde_field` since their types are foreign to serde.
+#[cfg(feature = "serde")]
+fn impl_serde(ast: &syn::DeriveInput, crate_name: &Tokens) -> Tokens {
+    let name = &ast.ident;
+    let definition_name = Ident::from(format!("__{}SerdeDefinition", name));
+    let serde_crate = format!("::{}::serde", crate_name);
+    let remote = name.to_string();
+    let with = format!("::{}::serde_field", crate_name);
+
+    // The fields are converted with `serde_field`, so serde can't infer the bounds of the type parameters
+    let generics = with_bound(&ast.generics, &format!("::{}::serde_field::SerdeField", crate_name));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let definition_generics = &ast.generics;
+    let definition_where_clause = &ast.generics.where_clause;
+    let bound = ast.generics.ty_params.iter()
+        .map(|param| format!("{}: ::{}::serde_field::SerdeField", param.ident, crate_name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut de_generics = generics.clone();
+    de_generics.lifetimes.insert(0, syn::LifetimeDef::new("'de"));
+    let (de_impl_generics, _, _) = de_generics.split_for_impl();
+
+    let definition = match ast.body {
+        Body::Enum(ref variants) => {
+            let mut body = Tokens::new();
+            for variant in variants {
+                let variant_ident = &variant.ident;
+                let field_type = &variant.data.fields()[0].ty;
+                body.append(quote!{ #variant_ident(#[serde(with = #with)] #field_type), });
+            }
+            quote!{ enum #definition_name #definition_generics #definition_where_clause { #body } }
+        },
+        Body::Struct(syn::VariantData::Struct(ref fields)) => {
+            let mut body = Tokens::new();
+            for field in fields {
+                let field_ident = &field.ident;
+                let field_type = &field.ty;
+                body.append(quote!{ #[serde(with = #with)] #field_ident: #field_type, });
+            }
+            quote!{ struct #definition_name #definition_generics #definition_where_clause { #body } }
+        },
+        Body::Struct(syn::VariantData::Unit) => quote!{ struct #definition_name; },
+        _ => panic!("UavcanStruct is only derivable for enums and named structs"),
+    };
+
+    quote!{
+        const _: () = {
+            #[allow(dead_code, non_camel_case_types)]
+            #[derive(::#crate_name::serde::Serialize, ::#crate_name::serde::Deserialize)]
+            #[serde(crate = #serde_crate, remote = #remote, bound = #bound)]
+            #definition
+
+            impl #impl_generics ::#crate_name::serde::Serialize for #name #ty_generics #where_clause {
+                fn serialize<S: ::#crate_name::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    #definition_name::serialize(self, serializer)
+                }
+            }
+
+            impl #de_impl_generics ::#crate_name::serde::Deserialize<'de> for #name #ty_generics #where_clause {
+                fn deserialize<D: ::#crate_name::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    #definition_name::deserialize(deserializer)
+                }
+            }
+
+            impl #impl_generics ::#crate_name::serde_field::SerdeField for #name #ty_generics #where_clause {
+                fn serialize_field<S: ::#crate_name::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    ::#crate_name::serde::Serialize::serialize(self, serializer)
+                }
+
+                fn deserialize_field<'de, D: ::#crate_name::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    ::#crate_name::serde::Deserialize::deserialize(deserializer)
+                }
+            }
+        };
+    }
+}
+
+#[cfg(not(feature = "serde"))]
+fn impl_serde(_ast: &syn::DeriveInput, _crate_name: &Tokens) -> Tokens {
+    Tokens::new()
+}
+
+/// Implements `defmt::Format` like the `Debug` representation, with the fields formatted by `defmt_field`
+#[cfg(feature = "defmt")]
+fn impl_defmt(ast: &syn::DeriveInput, crate_name: &Tokens) -> Tokens {
+    let name = &ast.ident;
+    let generics = with_bound(&ast.generics, &format!("::{}::defmt_field::DefmtField", crate_name));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match ast.body {
+        Body::Enum(ref variants) => {
+            let mut arms = Tokens::new();
+            for variant in variants {
+                let variant_ident = &variant.ident;
+                let prefix = format!("{}(", variant_ident);
+                arms.append(quote!{ #name::#variant_ident(ref field) => {
+                    ::#crate_name::defmt::write!(f, #prefix);
+                    ::#crate_name::defmt_field::DefmtField::format_field(field, f);
+                    ::#crate_name::defmt::write!(f, ")");
+                }});
+            }
+            quote!{ match *self { #arms } }
+        },
+        Body::Struct(syn::VariantData::Struct(ref fields)) => {
+            let mut body = Tokens::new();
+            for (i, field) in fields.iter().enumerate() {
+                let field_ident = &field.ident;
+                let prefix = if i == 0 {
+                    format!("{} {{{{ {}: ", name, field_ident.as_ref().unwrap())
+                } else {
+                    format!(", {}: ", field_ident.as_ref().unwrap())
+                };
+                body.append(quote!{
+                    ::#crate_name::defmt::write!(f, #prefix);
+                    ::#crate_name::defmt_field::DefmtField::format_field(&self.#field_ident, f);
+                });
+            }
+            if fields.is_empty() {
+                let empty = format!("{} {{{{ }}}}", name);
+                body.append(quote!{ ::#crate_name::defmt::write!(f, #empty); });
+            } else {
+                body.append(quote!{ ::#crate_name::defmt::write!(f, " }}"); });
+            }
+            body
+        },
+        Body::Struct(syn::VariantData::Unit) => {
+            let unit = name.to_string();
+            quote!{ ::#crate_name::defmt::write!(f, #unit); }
+        },
+        _ => panic!("UavcanStruct is only derivable for enums and named structs"),
+    };
+
+    quote!{
+        impl #impl_generics ::#crate_name::defmt::Format for #name #ty_generics #where_clause {
+            fn format(&self, f: ::#crate_name::defmt::Formatter) {
+                #body
+            }
+        }
+
+        impl #impl_generics ::#crate_name::defmt_field::DefmtField for #name #ty_generics #where_clause {
+            fn format_field(&self, f: ::#crate_name::defmt::Formatter) {
+                ::#crate_name::defmt::Format::format(self, f)
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "defmt"))]
+fn impl_defmt(_ast: &syn::DeriveInput, _crate_name: &Tokens) -> Tokens {
+    Tokens::new()
+}
+
+/// Implements proptest's `Arbitrary` and `ArbitraryField` from the `ArbitraryField` implementations of the fields
+#[cfg(feature = "testing")]
+fn impl_arbitrary(ast: &syn::DeriveInput, crate_name: &Tokens) -> Tokens {
+    let name = &ast.ident;
+    let proptest = quote!{ ::#crate_name::proptest };
+    let any_field = quote!{ ::#crate_name::testing::any_field };
+    let generics = with_bound(&ast.generics, &format!("::{}::testing::ArbitraryField", crate_name));
+    let generics = with_bound(&generics, "'static");
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let strategy = match ast.body {
+        Body::Enum(ref variants) => {
+            let mut strategies = Tokens::new();
+            for variant in variants {
+                let variant_ident = &variant.ident;
+                let field_type = &variant.data.fields()[0].ty;
+                strategies.append(quote!{
+                    #proptest::strategy::Strategy::boxed(
+                        #proptest::strategy::Strategy::prop_map(#any_field::<#field_type>(), #name::#variant_ident)
+                    ),
+                });
+            }
+            quote!{ #proptest::strategy::Union::new([#strategies]) }
+        },
+        Body::Struct(syn::VariantData::Struct(ref fields)) => {
+            // The fields are generated as nested pairs, since tuples of strategies have a limited length
+            let mut strategy = quote!{ #proptest::strategy::Just(()) };
+            let mut pattern = quote!{ () };
+            let mut field_values = Tokens::new();
+            for field in fields.iter().rev() {
+                let field_ident = &field.ident;
+                let field_type = &field.ty;
+                strategy = quote!{ (#any_field::<#field_type>(), #strategy) };
+                pattern = quote!{ (#field_ident, #pattern) };
+                field_values.append(quote!{ #field_ident: #field_ident, });
+            }
+            quote!{ #proptest::strategy::Strategy::prop_map(#strategy, |#pattern| #name{#field_values}) }
+        },
+        Body::Struct(syn::VariantData::Unit) => quote!{ #proptest::strategy::LazyJust::new(|| #name) },
+        _ => panic!("UavcanStruct is only derivable for enums and named structs"),
+    };
+
+    quote!{
+        impl #impl_generics #proptest::arbitrary::Arbitrary for #name #ty_generics #where_clause {
+            type Parameters = ();
+            type Strategy = #proptest::strategy::BoxedStrategy<Self>;
+
+            fn arbitrary_with(_: ()) -> Self::Strategy {
+                #proptest::strategy::Strategy::boxed(#strategy)
+            }
+        }
+
+        impl #impl_generics ::#crate_name::testing::ArbitraryField for #name #ty_generics #where_clause {
+            fn strategy() -> #proptest::strategy::BoxedStrategy<Self> {
+                #proptest::arbitrary::any::<Self>()
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "testing"))]
+fn impl_arbitrary(_ast: &syn::DeriveInput, _crate_name: &Tokens) -> Tokens {
+    Tokens::new()
+}
+
+/// The UAVCAN type a native field with a `#[uavcan(bits = 13)]` attribute is serialized as,
+/// and the widening conversion of the deserialized `value` back to the native type
+///
+/// The native value is converted with the saturated cast mode when it's serialized.
+/// Returns `None` for the fields without the attribute and the fields that are serialized as they are (`#[uavcan(bits = 32)] u32`).
+fn native_field(field: &syn::Field, crate_name: &Tokens) -> Option<(syn::Ty, Tokens)> {
+    let mut bits = None;
+    for attr in &field.attrs {
+        if let syn::MetaItem::List(ref ident, ref items) = attr.value {
+            if ident == "uavcan" {
+                for item in items {
+                    match *item {
+                        syn::NestedMetaItem::MetaItem(syn::MetaItem::NameValue(ref key, syn::Lit::Int(value, _))) if key == "bits" => bits = Some(value),
+                        syn::NestedMetaItem::MetaItem(syn::MetaItem::List(ref key, _)) if key == "names" => (),
+                        _ => panic!("The uavcan attribute of a field takes `bits = 13` and `names(HEALTH_OK, HEALTH_WARNING)`"),
+                    }
+                }
+            }
+        }
+    }
+    let bits = bits?;
+
+    let native = match field.ty {
+        syn::Ty::Path(None, ref path) if path.segments.len() == 1 => path.segments[0].ident.clone(),
+        _ => panic!("`#[uavcan(bits = N)]` is only supported on `u8`, `u16`, `u32`, `u64`, `i8`, `i16`, `i32`, `i64`, `f32`, `f64` and `bool` fields"),
+    };
+    let field_name = field.ident.as_ref().unwrap();
+
+    let (wire_type, widening) = match native.as_ref() {
+        "u8" | "u16" | "u32" | "u64" | "i8" | "i16" | "i32" | "i64" => {
+            let width: u64 = native.as_ref()[1..].parse().unwrap();
+            if bits < 2 || bits > width {
+                panic!("The field `{}` of type `{}` can't be serialized in {} bits, integers take 2 to {} bits", field_name, native, bits, width);
+            }
+            let signedness = &native.as_ref()[..1];
+            let wire_type = if bits == 8 || bits == 16 || bits == 32 || bits == 64 {
+                format!("{}{}", signedness, bits)
+            } else {
+                format!("::{}::types::{}{}", crate_name, signedness, bits)
+            };
+            let wide_type = Ident::from(format!("{}64", signedness));
+            (wire_type, quote!{#wide_type::from(value) as #native})
+        },
+        "f32" | "f64" => match bits {
+            16 => (format!("::{}::types::f16", crate_name), quote!{f32::from(value) as #native}),
+            32 | 64 if bits <= if native == "f32" { 32 } else { 64 } => (format!("f{}", bits), quote!{value as #native}),
+            _ => panic!("The field `{}` of type `{}` can't be serialized in {} bits, floats take 16, 32 or 64 bits", field_name, native, bits),
+        },
+        "bool" if bits == 1 => return None,
+        "bool" => panic!("The field `{}` of type `bool` can't be serialized in {} bits, booleans take 1 bit", field_name, bits),
+        _ => panic!("`#[uavcan(bits = N)]` is only supported on `u8`, `u16`, `u32`, `u64`, `i8`, `i16`, `i32`, `i64`, `f32`, `f64` and `bool` fields"),
+    };
+
+    if wire_type == native.as_ref() {
+        None
+    } else {
+        Some((syn::parse_type(&wire_type).unwrap(), widening))
+    }
+}
+
+/// The generics of a type with `bound` added to its type parameters
+fn with_bound(generics: &syn::Generics, bound: &str) -> syn::Generics {
+    let bound = syn::parse_ty_param_bound(bound).unwrap();
+    let mut generics = generics.clone();
+    for param in &mut generics.ty_params {
+        param.bounds.push(bound.clone());
+    }
+    generics
+}
+
+/// The smallest native integer type holding the values of a `u13` or `i13` field, and the 64 bit type it converts through
+///
+/// Returns `None` for the other fields, including the native integer types.
+fn native_integer_type(ty: &syn::Ty) -> Option<(Ident, Ident)> {
+    if let syn::Ty::Path(_, ref path) = *ty {
+        let re = Regex::new(r"^([ui])([0-9]+)$").unwrap();
+        if let Some(captures) = re.captures(path.segments.as_slice().last().unwrap().ident.as_ref()) {
+            let signedness = &captures[1];
+            let bits: usize = captures[2].parse().unwrap();
+            let width = match bits {
+                2..=7 => 8,
+                9..=15 => 16,
+                17..=31 => 32,
+                33..=63 => 64,
+                _ => return None,
+            };
+            return Some((Ident::from(format!("{}{}", signedness, width)), Ident::from(format!("{}64", signedness))));
+        }
+    }
+    None
+}
+
 fn classify_type(ty: &syn::Ty) -> UavcanType {
     if is_primitive_type(ty) {
         UavcanType::PrimitiveType
@@ -304,6 +1094,16 @@ fn is_dynamic_array(type_name: &syn::Ty) -> bool {
     false
 }
 
+/// Arrays of `u8`, the arrays that can be borrowed as `&[u8]`
+fn is_byte_array(array_type: &syn::Ty) -> bool {
+    if let syn::Ty::Array(ref element_type, _) = *array_type {
+        if let syn::Ty::Path(_, ref path) = **element_type {
+            return path.segments.len() == 1 && path.segments[0].ident == "u8";
+        }
+    }
+    false
+}
+
 fn array_from_dynamic(type_name: &syn::Ty) -> Option<syn::Ty> {
     if let syn::Ty::Path(_, ref path) = *type_name {
         if path.segments.as_slice().last().unwrap().ident == syn::Ident::from("Dynamic") {
@@ -0,0 +1,158 @@
+//! Asynchronous variant of `TransferInterface`
+//!
+//! Interfaces backed by async HALs (or executors such as tokio and embassy) can implement `AsyncTransferInterface`
+//! to let tasks await buffer space or incoming frames instead of busy-polling `TransferInterface::receive`.
+
+use lib::core::future::Future;
+use lib::core::pin::Pin;
+use lib::core::task::Context;
+use lib::core::task::Poll;
+
+use transfer::TransferInterface;
+use transfer::IOError;
+
+/// A `TransferInterface` that can wake a task when it's ready to transmit or receive.
+///
+/// The poll methods follow the usual `Future` contract, when returning `Poll::Pending`
+/// the interface must arrange for the waker in `cx` to be woken when progress can be made
+/// (typically from the transmit or receive interrupt).
+pub trait AsyncTransferInterface: TransferInterface {
+    /// Attempt to put `frame` in the transmit buffer.
+    ///
+    /// Returns `Poll::Pending` while the transmit buffer is full, instead of `Err(IOError::BufferExhausted)`.
+    fn poll_transmit(&self, frame: &Self::Frame, cx: &mut Context) -> Poll<Result<(), IOError>>;
+
+    /// Attempt to receive a frame.
+    ///
+    /// Returns `Poll::Pending` while the receive buffer is empty.
+    fn poll_receive(&self, cx: &mut Context) -> Poll<Self::Frame>;
+
+    /// Returns a future that completes when `frame` has been put in the transmit buffer.
+    fn transmit_async<'a>(&'a self, frame: &'a Self::Frame) -> Transmit<'a, Self> where Self: Sized {
+        Transmit {
+            interface: self,
+            frame,
+        }
+    }
+
+    /// Returns a future that completes with the next received frame.
+    fn receive_async(&self) -> Receive<Self> where Self: Sized {
+        Receive {
+            interface: self,
+        }
+    }
+}
+
+/// Future returned by `AsyncTransferInterface::transmit_async`
+#[derive(Debug)]
+pub struct Transmit<'a, I: AsyncTransferInterface + 'a> {
+    interface: &'a I,
+    frame: &'a I::Frame,
+}
+
+impl<'a, I: AsyncTransferInterface> Future for Transmit<'a, I> {
+    type Output = Result<(), IOError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        self.interface.poll_transmit(self.frame, cx)
+    }
+}
+
+/// Future returned by `AsyncTransferInterface::receive_async`
+#[derive(Debug)]
+pub struct Receive<'a, I: AsyncTransferInterface + 'a> {
+    interface: &'a I,
+}
+
+impl<'a, I: AsyncTransferInterface> Future for Receive<'a, I> {
+    type Output = I::Frame;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        self.interface.poll_receive(cx)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use lib::core::cell::Cell;
+    use lib::core::future::Future;
+    use lib::core::pin::Pin;
+    use lib::core::ptr;
+    use lib::core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    use tests::CanFrame;
+
+    use transfer::TransferFrame;
+    use transfer::TransferFrameID;
+    use transfer::TransferInterface;
+    use transfer::IOError;
+
+    use async_transfer::*;
+
+    fn noop_raw_waker() -> RawWaker {
+        fn clone(_: *const ()) -> RawWaker { noop_raw_waker() }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        RawWaker::new(ptr::null(), &VTABLE)
+    }
+
+    struct MailboxInterface {
+        mailbox: Cell<Option<TransferFrameID>>,
+    }
+
+    impl TransferInterface for MailboxInterface {
+        type Frame = CanFrame;
+
+        fn transmit(&self, frame: &CanFrame) -> Result<(), IOError> {
+            if self.mailbox.get().is_some() {
+                Err(IOError::BufferExhausted)
+            } else {
+                self.mailbox.set(Some(frame.id()));
+                Ok(())
+            }
+        }
+
+        fn receive(&self) -> Option<CanFrame> {
+            self.mailbox.take().map(CanFrame::new)
+        }
+    }
+
+    impl AsyncTransferInterface for MailboxInterface {
+        fn poll_transmit(&self, frame: &CanFrame, _cx: &mut Context) -> Poll<Result<(), IOError>> {
+            match self.transmit(frame) {
+                Err(IOError::BufferExhausted) => Poll::Pending,
+                result => Poll::Ready(result),
+            }
+        }
+
+        fn poll_receive(&self, _cx: &mut Context) -> Poll<CanFrame> {
+            match self.receive() {
+                Some(frame) => Poll::Ready(frame),
+                None => Poll::Pending,
+            }
+        }
+    }
+
+    #[test]
+    fn transmit_and_receive() {
+        let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+        let mut cx = Context::from_waker(&waker);
+
+        let interface = MailboxInterface{ mailbox: Cell::new(None) };
+        let frame = CanFrame::new(TransferFrameID::new(42));
+
+        let mut receive = interface.receive_async();
+        assert_eq!(Pin::new(&mut receive).poll(&mut cx), Poll::Pending);
+
+        let mut first = interface.transmit_async(&frame);
+        assert!(Pin::new(&mut first).poll(&mut cx).is_ready());
+
+        let mut second = interface.transmit_async(&frame);
+        assert!(Pin::new(&mut second).poll(&mut cx).is_pending());
+
+        assert_eq!(Pin::new(&mut receive).poll(&mut cx), Poll::Ready(CanFrame::new(TransferFrameID::new(42))));
+        assert!(Pin::new(&mut second).poll(&mut cx).is_ready());
+    }
+}
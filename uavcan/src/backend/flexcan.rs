@@ -0,0 +1,526 @@
+//! Backend for the NXP FlexCAN controller (i.MX RT, Kinetis and S32K)
+//!
+//! Requires the `flexcan` feature. The controller is accessed directly through its registers,
+//! no HAL or vendor SDK is needed. Clocks and pins must be configured by the application before the interface is created.
+//!
+//! The message buffers are split in two regions. The first `RX` buffers are receive mailboxes,
+//! one is configured with an individual mask for every message type the node subscribes to
+//! and one for the service transfers to the node, once its ID is set with `set_node_id`.
+//! The following `TX` buffers are transmit mailboxes, the controller arbitrates between them the same way as the bus would.
+//!
+//! # Examples
+//! ```no_run
+//! # use uavcan::{NodeConfig, NodeID, SimpleNode};
+//! # use uavcan::storage::Storage;
+//! # use uavcan::transfer::TransferInterface;
+//! use uavcan::backend::flexcan::{BitTiming, FlexCanInterface};
+//!
+//! # fn main() {}
+//! # fn start<S: Storage<<FlexCanInterface<8, 4> as TransferInterface>::Frame>>() {
+//! // CAN1 on i.MX RT1060, clocked from the 24 MHz oscillator
+//! let timing = BitTiming::from_clock(24_000_000, 1_000_000).unwrap();
+//! let interface: FlexCanInterface<8, 4> = unsafe { FlexCanInterface::new(0x401d_0000, timing) };
+//! interface.set_node_id(Some(NodeID::new(42))).unwrap();
+//! let node: SimpleNode<_, _, S> = SimpleNode::new(&interface, NodeConfig::default());
+//! # }
+//! ```
+
+use lib::core::cell::Cell;
+use lib::core::ptr;
+
+use embedded_types::can::ExtendedDataFrame;
+use embedded_types::can::ExtendedID;
+
+use transfer::TransferInterface;
+use transfer::TransferFrame;
+use transfer::TransportStats;
+use transfer::BusState;
+use transfer::Timestamped;
+use transfer::IOError;
+
+use node::NodeID;
+
+const MCR: usize = 0x00;
+const CTRL1: usize = 0x04;
+const TIMER: usize = 0x08;
+const ESR1: usize = 0x20;
+const IFLAG2: usize = 0x2c;
+const IFLAG1: usize = 0x30;
+const MESSAGE_BUFFERS: usize = 0x80;
+const RXIMR: usize = 0x880;
+
+const MCR_MDIS: u32 = 1 << 31;
+const MCR_FRZ: u32 = 1 << 30;
+const MCR_RFEN: u32 = 1 << 29;
+const MCR_HALT: u32 = 1 << 28;
+const MCR_NOTRDY: u32 = 1 << 27;
+const MCR_SOFTRST: u32 = 1 << 25;
+const MCR_FRZACK: u32 = 1 << 24;
+const MCR_LPMACK: u32 = 1 << 20;
+const MCR_SRXDIS: u32 = 1 << 17;
+const MCR_IRMQ: u32 = 1 << 16;
+const MCR_MAXMB_MASK: u32 = 0x7f;
+
+const CTRL1_LOM: u32 = 1 << 3;
+const CTRL1_LBUF: u32 = 1 << 4;
+
+const ESR1_FLTCONF_SHIFT: u32 = 4;
+
+const CS_CODE_SHIFT: u32 = 24;
+const CS_CODE_MASK: u32 = 0xf << CS_CODE_SHIFT;
+const CS_SRR: u32 = 1 << 22;
+const CS_IDE: u32 = 1 << 21;
+const CS_RTR: u32 = 1 << 20;
+const CS_DLC_SHIFT: u32 = 16;
+const CS_TIMESTAMP_MASK: u32 = 0xffff;
+
+const CODE_RX_INACTIVE: u32 = 0b0000;
+const CODE_RX_EMPTY: u32 = 0b0100;
+const CODE_RX_OVERRUN: u32 = 0b0110;
+const CODE_TX_INACTIVE: u32 = 0b1000;
+const CODE_TX_DATA: u32 = 0b1100;
+
+const EXTENDED_ID_MASK: u32 = 0x1fff_ffff;
+
+/// The 29-bit ID bits that must match for a frame to be a message of a given type (type ID and service-not-message)
+const MESSAGE_FILTER_MASK: u32 = (0xffff << 8) | (1 << 7);
+
+/// The 29-bit ID bits that must match for a frame to be a service frame to a given node (destination and service-not-message)
+const SERVICE_FILTER_MASK: u32 = (0x7f << 8) | (1 << 7);
+
+/// The maximal number of message buffers in any FlexCAN instance
+pub const MAX_MESSAGE_BUFFERS: usize = 64;
+
+/// The bit timing of a FlexCAN controller in time quanta
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitTiming {
+    /// The prescaler division factor (1 to 256)
+    pub presdiv: u32,
+
+    /// The propagation segment (1 to 8)
+    pub propseg: u32,
+
+    /// The phase segment 1 (1 to 8)
+    pub pseg1: u32,
+
+    /// The phase segment 2 (2 to 8)
+    pub pseg2: u32,
+
+    /// The resync jump width (1 to 4)
+    pub rjw: u32,
+}
+
+impl BitTiming {
+    /// Finds a bit timing for `bit_rate` with a sample point close to the recommended 87.5%
+    ///
+    /// `clock` is the frequency of the protocol engine clock. Returns `None` if `bit_rate` can't be reached exactly.
+    pub fn from_clock(clock: u32, bit_rate: u32) -> Option<Self> {
+        for tq in (8..26).rev() {
+            let quanta_rate = match bit_rate.checked_mul(tq) {
+                Some(quanta_rate) if quanta_rate != 0 && clock % quanta_rate == 0 => quanta_rate,
+                _ => continue,
+            };
+
+            let presdiv = clock / quanta_rate;
+            let pseg2 = ::lib::core::cmp::max(2, (tq + 4) / 8);
+            let tseg1 = tq - 1 - pseg2;
+            let pseg1 = tseg1 / 2;
+            let propseg = tseg1 - pseg1;
+
+            if presdiv >= 1 && presdiv <= 256 && pseg1 >= 1 && pseg1 <= 8 && propseg <= 8 && pseg2 <= 8 {
+                return Some(BitTiming {
+                    presdiv,
+                    propseg,
+                    pseg1,
+                    pseg2,
+                    rjw: ::lib::core::cmp::min(4, pseg2),
+                });
+            }
+        }
+        None
+    }
+
+    fn ctrl1(&self) -> u32 {
+        ((self.presdiv - 1) << 24)
+            | ((self.rjw - 1) << 22)
+            | ((self.pseg1 - 1) << 19)
+            | ((self.pseg2 - 1) << 16)
+            | (self.propseg - 1)
+    }
+}
+
+/// What a receive mailbox is configured for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Filter {
+    /// Messages with `type_id`, subscribed to `count` times
+    Message{type_id: u16, count: usize},
+
+    /// Service requests and responses to the node
+    Service(NodeID),
+}
+
+impl Filter {
+    /// The individual mask and the ID to match with it
+    fn mask_and_id(&self) -> (u32, u32) {
+        match *self {
+            Filter::Message{type_id, ..} => (MESSAGE_FILTER_MASK, u32::from(type_id) << 8),
+            Filter::Service(node_id) => (SERVICE_FILTER_MASK, (u32::from(node_id) << 8) | (1 << 7)),
+        }
+    }
+}
+
+/// A `TransferInterface` for a FlexCAN controller with `RX` receive and `TX` transmit mailboxes
+///
+/// Received frames are timestamped with the 16-bit free running timer of the controller, which counts CAN bit times.
+/// Only extended data frames are received, as the receive mailboxes are only configured for subscribed messages
+/// and for the services to the node.
+#[derive(Debug)]
+pub struct FlexCanInterface<const RX: usize, const TX: usize> {
+    base: usize,
+    filters: [Cell<Option<Filter>>; RX],
+    receive_overruns: Cell<u64>,
+}
+
+impl<const RX: usize, const TX: usize> FlexCanInterface<RX, TX> {
+    /// Resets and starts the FlexCAN instance with registers at `base`
+    ///
+    /// All receive mailboxes are inactive until a message is subscribed to or the node ID is set.
+    ///
+    /// ## Panics
+    /// Panics if `RX + TX` is larger than `MAX_MESSAGE_BUFFERS` or if `TX` is zero.
+    ///
+    /// ## Safety
+    /// `base` must be the base address of a FlexCAN instance with at least `RX + TX` message buffers,
+    /// the instance must be clocked and must not be accessed through anything but the returned interface.
+    pub unsafe fn new(base: usize, timing: BitTiming) -> Self {
+        assert!(RX + TX <= MAX_MESSAGE_BUFFERS && TX > 0);
+
+        let interface = FlexCanInterface {
+            base,
+            filters: ::lib::core::array::from_fn(|_| Cell::new(None)),
+            receive_overruns: Cell::new(0),
+        };
+
+        interface.modify(MCR, |mcr| mcr & !MCR_MDIS);
+        while interface.read(MCR) & MCR_LPMACK != 0 {}
+
+        interface.write(MCR, interface.read(MCR) | MCR_SOFTRST);
+        while interface.read(MCR) & MCR_SOFTRST != 0 {}
+
+        interface.enter_freeze();
+
+        interface.modify(MCR, |mcr| {
+            (mcr & !(MCR_RFEN | MCR_MAXMB_MASK)) | MCR_SRXDIS | MCR_IRMQ | (RX + TX - 1) as u32
+        });
+        // The lowest ID is transmitted first (LBUF cleared), as it would on the bus
+        interface.write(CTRL1, timing.ctrl1() & !(CTRL1_LBUF | CTRL1_LOM));
+
+        for mailbox in 0..RX + TX {
+            let code = if mailbox < RX { CODE_RX_INACTIVE } else { CODE_TX_INACTIVE };
+            interface.write_mailbox(mailbox, 0, code << CS_CODE_SHIFT);
+            interface.write_mailbox(mailbox, 1, 0);
+            interface.write_mailbox(mailbox, 2, 0);
+            interface.write_mailbox(mailbox, 3, 0);
+            interface.write(RXIMR + 4*mailbox, 0);
+        }
+        interface.clear_flag(0..RX + TX);
+
+        interface.exit_freeze();
+        interface
+    }
+
+    /// Returns the number of receive mailboxes currently in use
+    pub fn active_subscriptions(&self) -> usize {
+        self.filters.iter().filter(|f| f.get().is_some()).count()
+    }
+
+    /// Receive the service requests and responses to `node_id`, or none if the node is anonymous
+    ///
+    /// Takes a receive mailbox of its own, fails with `BufferExhausted` if all are in use.
+    pub fn set_node_id(&self, node_id: Option<NodeID>) -> Result<(), IOError> {
+        let current = self.filters.iter().position(|f| matches!(f.get(), Some(Filter::Service(_))));
+
+        let mailbox = match (current, node_id) {
+            (Some(mailbox), _) => mailbox,
+            (None, Some(_)) => self.free_mailbox()?,
+            (None, None) => return Ok(()),
+        };
+
+        let filter = node_id.map(Filter::Service);
+        self.filters[mailbox].set(filter);
+        self.configure_receive_mailbox(mailbox, filter);
+        Ok(())
+    }
+
+    fn free_mailbox(&self) -> Result<usize, IOError> {
+        self.filters.iter().position(|f| f.get().is_none()).ok_or(IOError::BufferExhausted)
+    }
+
+    fn read(&self, offset: usize) -> u32 {
+        unsafe { ptr::read_volatile((self.base + offset) as *const u32) }
+    }
+
+    fn write(&self, offset: usize, value: u32) {
+        unsafe { ptr::write_volatile((self.base + offset) as *mut u32, value) }
+    }
+
+    fn modify<F: FnOnce(u32) -> u32>(&self, offset: usize, f: F) {
+        let value = self.read(offset);
+        self.write(offset, f(value));
+    }
+
+    fn read_mailbox(&self, mailbox: usize, word: usize) -> u32 {
+        self.read(MESSAGE_BUFFERS + 16*mailbox + 4*word)
+    }
+
+    fn write_mailbox(&self, mailbox: usize, word: usize, value: u32) {
+        self.write(MESSAGE_BUFFERS + 16*mailbox + 4*word, value)
+    }
+
+    fn flag(&self, mailbox: usize) -> bool {
+        if mailbox < 32 {
+            self.read(IFLAG1) & (1 << mailbox) != 0
+        } else {
+            self.read(IFLAG2) & (1 << (mailbox - 32)) != 0
+        }
+    }
+
+    fn clear_flag<R: Iterator<Item=usize>>(&self, mailboxes: R) {
+        for mailbox in mailboxes {
+            // The flags are cleared by writing 1
+            if mailbox < 32 {
+                self.write(IFLAG1, 1 << mailbox);
+            } else {
+                self.write(IFLAG2, 1 << (mailbox - 32));
+            }
+        }
+    }
+
+    fn code(&self, mailbox: usize) -> u32 {
+        (self.read_mailbox(mailbox, 0) & CS_CODE_MASK) >> CS_CODE_SHIFT
+    }
+
+    /// The controller stops participating on the bus while frozen, RXIMR and CTRL1 can only be written in freeze mode
+    fn enter_freeze(&self) {
+        self.modify(MCR, |mcr| mcr | MCR_FRZ | MCR_HALT);
+        while self.read(MCR) & MCR_FRZACK == 0 {}
+    }
+
+    fn exit_freeze(&self) {
+        self.modify(MCR, |mcr| mcr & !(MCR_FRZ | MCR_HALT));
+        while self.read(MCR) & MCR_FRZACK != 0 {}
+        while self.read(MCR) & MCR_NOTRDY != 0 {}
+    }
+
+    /// Finds the transmit mailbox to use for a frame with the given ID
+    ///
+    /// The controller transmits the pending frame with the lowest ID first and, for equal IDs, the one in the lowest mailbox.
+    /// A new frame must therefore be placed in a mailbox above every pending frame with the same ID,
+    /// otherwise it could overtake them.
+    fn transmit_mailbox(&self, id: u32) -> Option<usize> {
+        let mut first_candidate = RX;
+        for mailbox in RX..RX + TX {
+            if self.code(mailbox) == CODE_TX_DATA && self.read_mailbox(mailbox, 1) & EXTENDED_ID_MASK == id {
+                first_candidate = mailbox + 1;
+            }
+        }
+
+        (first_candidate..RX + TX).find(|&mailbox| self.code(mailbox) != CODE_TX_DATA)
+    }
+
+    /// Finds the full receive mailbox holding the oldest frame
+    fn oldest_reception(&self) -> Option<usize> {
+        let now = self.read(TIMER) as u16;
+        let mut oldest: Option<(u16, usize)> = None;
+
+        for mailbox in 0..RX {
+            if self.filters[mailbox].get().is_none() || !self.flag(mailbox) {
+                continue;
+            }
+            let timestamp = (self.read_mailbox(mailbox, 0) & CS_TIMESTAMP_MASK) as u16;
+            let age = now.wrapping_sub(timestamp);
+            if oldest.map_or(true, |(oldest_age, _)| age > oldest_age) {
+                oldest = Some((age, mailbox));
+            }
+        }
+        // Reading the timer unlocks any mailbox locked by reading its control and status word
+        self.read(TIMER);
+
+        oldest.map(|(_, mailbox)| mailbox)
+    }
+
+    fn configure_receive_mailbox(&self, mailbox: usize, filter: Option<Filter>) {
+        self.enter_freeze();
+        self.write_mailbox(mailbox, 0, CODE_RX_INACTIVE << CS_CODE_SHIFT);
+        if let Some(filter) = filter {
+            let (mask, id) = filter.mask_and_id();
+            self.write(RXIMR + 4*mailbox, mask);
+            self.write_mailbox(mailbox, 1, id);
+            self.write_mailbox(mailbox, 0, (CODE_RX_EMPTY << CS_CODE_SHIFT) | CS_IDE);
+        }
+        self.clear_flag(mailbox..mailbox + 1);
+        self.exit_freeze();
+    }
+}
+
+impl<const RX: usize, const TX: usize> TransferInterface for FlexCanInterface<RX, TX> {
+    type Frame = Timestamped<ExtendedDataFrame, u16>;
+
+    fn transmit(&self, frame: &Self::Frame) -> Result<(), IOError> {
+        let id = u32::from(frame.id()) & EXTENDED_ID_MASK;
+        let mailbox = self.transmit_mailbox(id).ok_or(IOError::BufferExhausted)?;
+
+        let mut data = [0u8; 8];
+        data[..frame.data().len()].copy_from_slice(frame.data());
+
+        self.clear_flag(mailbox..mailbox + 1);
+        self.write_mailbox(mailbox, 0, CODE_TX_INACTIVE << CS_CODE_SHIFT);
+        self.write_mailbox(mailbox, 1, id);
+        self.write_mailbox(mailbox, 2, u32::from_be_bytes([data[0], data[1], data[2], data[3]]));
+        self.write_mailbox(mailbox, 3, u32::from_be_bytes([data[4], data[5], data[6], data[7]]));
+        self.write_mailbox(mailbox, 0, (CODE_TX_DATA << CS_CODE_SHIFT) | CS_SRR | CS_IDE | ((frame.data().len() as u32) << CS_DLC_SHIFT));
+
+        Ok(())
+    }
+
+    fn receive(&self) -> Option<Self::Frame> {
+        loop {
+            let mailbox = self.oldest_reception()?;
+
+            // Reading the control and status word locks the mailbox until the timer is read
+            let cs = self.read_mailbox(mailbox, 0);
+            let id = self.read_mailbox(mailbox, 1) & EXTENDED_ID_MASK;
+            let mut data = [0u8; 8];
+            data[..4].copy_from_slice(&self.read_mailbox(mailbox, 2).to_be_bytes());
+            data[4..].copy_from_slice(&self.read_mailbox(mailbox, 3).to_be_bytes());
+            self.read(TIMER);
+            self.clear_flag(mailbox..mailbox + 1);
+
+            if (cs & CS_CODE_MASK) >> CS_CODE_SHIFT == CODE_RX_OVERRUN {
+                self.receive_overruns.set(self.receive_overruns.get() + 1);
+            }
+
+            // Remote frames aren't used by UAVCAN, skip them and take the next reception
+            if cs & CS_RTR != 0 {
+                continue;
+            }
+
+            let data_length = ::lib::core::cmp::min(8, ((cs >> CS_DLC_SHIFT) & 0xf) as usize);
+            let mut frame = ExtendedDataFrame::new(ExtendedID::new(id));
+            frame.set_data_length(data_length);
+            frame.data_as_mut().copy_from_slice(&data[..data_length]);
+
+            return Some(Timestamped::new(frame, (cs & CS_TIMESTAMP_MASK) as u16));
+        }
+    }
+
+    fn statistics(&self) -> TransportStats {
+        TransportStats {
+            receive_overruns: self.receive_overruns.get(),
+            ..TransportStats::default()
+        }
+    }
+
+    fn bus_state(&self) -> BusState {
+        match (self.read(ESR1) >> ESR1_FLTCONF_SHIFT) & 0b11 {
+            0b00 => BusState::ErrorActive,
+            0b01 => BusState::ErrorPassive,
+            _ => BusState::BusOff,
+        }
+    }
+
+    fn set_listen_only(&self, listen_only: bool) -> bool {
+        self.enter_freeze();
+        self.modify(CTRL1, |ctrl1| if listen_only { ctrl1 | CTRL1_LOM } else { ctrl1 & !CTRL1_LOM });
+        self.exit_freeze();
+        true
+    }
+
+    fn on_subscribe(&self, type_id: u16) -> Result<(), IOError> {
+        for filter in self.filters.iter() {
+            if let Some(Filter::Message{type_id: t, count}) = filter.get() {
+                if t == type_id {
+                    filter.set(Some(Filter::Message{type_id, count: count + 1}));
+                    return Ok(());
+                }
+            }
+        }
+
+        let mailbox = self.free_mailbox()?;
+        let filter = Some(Filter::Message{type_id, count: 1});
+        self.filters[mailbox].set(filter);
+        self.configure_receive_mailbox(mailbox, filter);
+        Ok(())
+    }
+
+    fn on_unsubscribe(&self, type_id: u16) -> Result<(), IOError> {
+        for (mailbox, filter) in self.filters.iter().enumerate() {
+            match filter.get() {
+                Some(Filter::Message{type_id: t, count}) if t == type_id && count > 1 => {
+                    filter.set(Some(Filter::Message{type_id, count: count - 1}));
+                },
+                Some(Filter::Message{type_id: t, ..}) if t == type_id => {
+                    filter.set(None);
+                    self.configure_receive_mailbox(mailbox, None);
+                },
+                _ => continue,
+            }
+            return Ok(());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use backend::flexcan::*;
+
+    #[test]
+    fn bit_timing_from_clock() {
+        let timing = BitTiming::from_clock(24_000_000, 1_000_000).unwrap();
+        assert_eq!(timing, BitTiming{presdiv: 2, propseg: 5, pseg1: 4, pseg2: 2, rjw: 2});
+        assert_eq!(timing.ctrl1(), 0x0159_0004);
+
+        let timing = BitTiming::from_clock(80_000_000, 500_000).unwrap();
+        assert_eq!(timing.presdiv * (1 + timing.propseg + timing.pseg1 + timing.pseg2) * 500_000, 80_000_000);
+
+        assert_eq!(BitTiming::from_clock(24_000_000, 123_457), None);
+    }
+
+    #[test]
+    fn bit_timing_out_of_range() {
+        assert_eq!(BitTiming::from_clock(24_000_000, 0), None);
+        // bit_rate * tq overflows
+        assert_eq!(BitTiming::from_clock(u32::MAX, u32::MAX), None);
+        assert_eq!(BitTiming::from_clock(24_000_000, u32::MAX / 8), None);
+        assert_eq!(BitTiming::from_clock(0, 1_000_000), None);
+    }
+
+    #[test]
+    fn filters() {
+        fn matches(filter: Filter, id: u32) -> bool {
+            let (mask, filter_id) = filter.mask_and_id();
+            id & mask == filter_id & mask
+        }
+
+        // NodeStatus (341) from node 10, priority 16
+        let node_status = (16 << 24) | (341 << 8) | 10;
+        let message = Filter::Message{type_id: 341, count: 1};
+        assert!(matches(message, node_status));
+        assert!(!matches(message, node_status + (1 << 8)));
+
+        // Requests and responses of any service type from node 10 to node 42
+        let request = (16 << 24) | (1 << 16) | (1 << 15) | (42 << 8) | (1 << 7) | 10;
+        let response = request & !(1 << 15);
+        let service = Filter::Service(NodeID::new(42));
+        assert!(matches(service, request));
+        assert!(matches(service, response));
+        assert!(!matches(service, request + (1 << 8)));
+        assert!(!matches(service, node_status));
+        assert!(!matches(message, request));
+
+        // A message with a type ID looking like a service to node 42
+        assert!(!matches(service, (42 << 8) | 10));
+    }
+}
@@ -0,0 +1,385 @@
+//! Backend for gs_usb adapters (candleLight firmware and compatible devices) through `rusb`
+//!
+//! Requires the `gs-usb` feature. The adapter is accessed directly over USB, this works on every platform supported by libusb
+//! and doesn't require the `gs_usb` kernel driver (the kernel driver is detached if it's loaded).
+//!
+//! # Examples
+//! ```no_run
+//! # use uavcan::{NodeConfig, SimpleNode};
+//! # use uavcan::storage::HeapStorage;
+//! use uavcan::backend::gs_usb::GsUsbInterface;
+//!
+//! let interface = GsUsbInterface::open_first(1_000_000).unwrap();
+//! let node: SimpleNode<_, _, HeapStorage<_>> = SimpleNode::new(&interface, NodeConfig::default());
+//! ```
+
+use lib::core::cell::Cell;
+
+use std::time::Duration;
+
+use rusb;
+use rusb::{DeviceHandle, GlobalContext, Direction, RequestType, Recipient};
+
+use embedded_types::can::ExtendedDataFrame;
+use embedded_types::can::ExtendedID;
+
+use transfer::TransferInterface;
+use transfer::TransferFrame;
+use transfer::TransportStats;
+use transfer::Timestamped;
+use transfer::IOError;
+
+/// USB vendor and product IDs of known gs_usb devices
+pub const KNOWN_DEVICES: [(u16, u16); 4] = [
+    (0x1d50, 0x606f), // candleLight / CANable
+    (0x1209, 0x2323), // candleLight (pid.codes)
+    (0x1cd2, 0x606f), // CES CANext FD
+    (0x16d0, 0x10b8), // ABE CANdebugger FD
+];
+
+const GS_USB_BREQ_HOST_FORMAT: u8 = 0;
+const GS_USB_BREQ_BITTIMING: u8 = 1;
+const GS_USB_BREQ_MODE: u8 = 2;
+const GS_USB_BREQ_BT_CONST: u8 = 4;
+
+const GS_CAN_MODE_RESET: u32 = 0;
+const GS_CAN_MODE_START: u32 = 1;
+
+const GS_CAN_FEATURE_HW_TIMESTAMP: u32 = 1 << 4;
+const GS_CAN_MODE_HW_TIMESTAMP: u32 = 1 << 4;
+
+const ENDPOINT_IN: u8 = 0x81;
+const ENDPOINT_OUT: u8 = 0x02;
+
+const ECHO_ID_RX: u32 = 0xffff_ffff;
+
+const CAN_EFF_FLAG: u32 = 0x8000_0000;
+const CAN_RTR_FLAG: u32 = 0x4000_0000;
+const CAN_ERR_FLAG: u32 = 0x2000_0000;
+const CAN_EFF_MASK: u32 = 0x1fff_ffff;
+
+const HOST_FRAME_SIZE: usize = 20;
+const HOST_FRAME_TIMESTAMP_SIZE: usize = 24;
+
+const CONTROL_TIMEOUT: Duration = Duration::from_millis(100);
+const TRANSMIT_TIMEOUT: Duration = Duration::from_millis(10);
+// A zero timeout means infinite for libusb, this is the closest to non-blocking
+const RECEIVE_TIMEOUT: Duration = Duration::from_millis(1);
+
+/// Errors from opening a gs_usb device
+#[derive(Debug)]
+pub enum GsUsbError {
+    /// No known gs_usb device was found
+    NotFound,
+
+    /// The device can't be configured for the requested bit rate with a sample point close to 87.5%
+    UnsupportedBitRate(u32),
+
+    /// An error from libusb
+    Usb(rusb::Error),
+}
+
+impl From<rusb::Error> for GsUsbError {
+    fn from(e: rusb::Error) -> Self {
+        GsUsbError::Usb(e)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct BitTiming {
+    prop_seg: u32,
+    phase_seg1: u32,
+    phase_seg2: u32,
+    sjw: u32,
+    brp: u32,
+}
+
+struct BitTimingConst {
+    feature: u32,
+    fclk_can: u32,
+    tseg1_min: u32,
+    tseg1_max: u32,
+    tseg2_min: u32,
+    tseg2_max: u32,
+    brp_min: u32,
+    brp_max: u32,
+    brp_inc: u32,
+}
+
+impl BitTimingConst {
+    fn from_bytes(bytes: &[u8; 40]) -> Self {
+        let word = |i: usize| u32::from_le_bytes([bytes[4*i], bytes[4*i+1], bytes[4*i+2], bytes[4*i+3]]);
+        BitTimingConst {
+            feature: word(0),
+            fclk_can: word(1),
+            tseg1_min: word(2),
+            tseg1_max: word(3),
+            tseg2_min: word(4),
+            tseg2_max: word(5),
+            // word(6) is sjw_max, a sjw of 1 is always used
+            brp_min: word(7),
+            brp_max: word(8),
+            brp_inc: word(9),
+        }
+    }
+
+    /// Finds the bit timing with the sample point closest to the recommended 87.5%
+    ///
+    /// Returns `None` if no prescaler gives a timing within the limits of the device, e.g. for a bit rate of 0 or above the CAN clock.
+    fn bit_timing(&self, bit_rate: u32) -> Option<BitTiming> {
+        let mut best: Option<(u32, BitTiming)> = None;
+        let mut brp = ::lib::core::cmp::max(self.brp_min, 1);
+
+        while brp <= self.brp_max {
+            if let Some((error, timing)) = self.bit_timing_with_prescaler(bit_rate, brp) {
+                if best.as_ref().map_or(true, |&(best_error, _)| error < best_error) {
+                    best = Some((error, timing));
+                }
+            }
+
+            brp = match brp.checked_add(::lib::core::cmp::max(self.brp_inc, 1)) {
+                Some(brp) => brp,
+                None => break,
+            };
+        }
+
+        best.map(|(_, timing)| timing)
+    }
+
+    /// The bit timing with the prescaler `brp` and its sample point error in per mille
+    ///
+    /// Returns `None` if the bit time isn't a whole number of time quanta within the limits of the device, or the calculation overflows.
+    fn bit_timing_with_prescaler(&self, bit_rate: u32, brp: u32) -> Option<(u32, BitTiming)> {
+        let divisor = brp.checked_mul(bit_rate)?;
+        if self.fclk_can.checked_rem(divisor)? != 0 {
+            return None;
+        }
+        let tq = self.fclk_can.checked_div(divisor)?;
+        let tseg1 = tq.checked_mul(7)?.checked_add(4)? / 8;
+        let tseg1 = tseg1.checked_sub(1)?;
+        let tseg2 = tq.saturating_sub(1 + tseg1);
+
+        if tseg1 < self.tseg1_min || tseg1 > self.tseg1_max || tseg2 < self.tseg2_min || tseg2 > self.tseg2_max || tseg1 < 2 {
+            return None;
+        }
+
+        // Sample point error in per mille
+        let sample_point = (1 + tseg1).checked_mul(1000)? / tq;
+        let error = if sample_point > 875 { sample_point - 875 } else { 875 - sample_point };
+
+        Some((error, BitTiming {
+            prop_seg: 1,
+            phase_seg1: tseg1 - 1,
+            phase_seg2: tseg2,
+            sjw: 1,
+            brp,
+        }))
+    }
+}
+
+/// A `TransferInterface` for channel 0 of a gs_usb device
+///
+/// Received frames are timestamped with the hardware timestamp in microseconds if the device supports it.
+/// Standard (11-bit), remote and error frames are discarded on reception.
+pub struct GsUsbInterface {
+    handle: DeviceHandle<GlobalContext>,
+    hardware_timestamps: bool,
+    echo_id: Cell<u32>,
+    statistics: Cell<TransportStats>,
+}
+
+impl GsUsbInterface {
+    /// Opens the first connected device from `KNOWN_DEVICES` and starts it at the given bit rate
+    pub fn open_first(bit_rate: u32) -> Result<Self, GsUsbError> {
+        for &(vendor_id, product_id) in KNOWN_DEVICES.iter() {
+            if let Some(handle) = rusb::open_device_with_vid_pid(vendor_id, product_id) {
+                return Self::from_handle(handle, bit_rate);
+            }
+        }
+        Err(GsUsbError::NotFound)
+    }
+
+    /// Configures and starts an already opened gs_usb device at the given bit rate
+    pub fn from_handle(mut handle: DeviceHandle<GlobalContext>, bit_rate: u32) -> Result<Self, GsUsbError> {
+        // Not supported on all platforms, the claim will fail if a kernel driver is still attached
+        let _ = handle.set_auto_detach_kernel_driver(true);
+        handle.claim_interface(0)?;
+
+        let out_type = rusb::request_type(Direction::Out, RequestType::Vendor, Recipient::Interface);
+        let in_type = rusb::request_type(Direction::In, RequestType::Vendor, Recipient::Interface);
+
+        handle.write_control(out_type, GS_USB_BREQ_HOST_FORMAT, 1, 0, &0x0000_beefu32.to_le_bytes(), CONTROL_TIMEOUT)?;
+
+        let mut bt_const = [0u8; 40];
+        handle.read_control(in_type, GS_USB_BREQ_BT_CONST, 0, 0, &mut bt_const, CONTROL_TIMEOUT)?;
+        let bt_const = BitTimingConst::from_bytes(&bt_const);
+
+        let timing = bt_const.bit_timing(bit_rate).ok_or(GsUsbError::UnsupportedBitRate(bit_rate))?;
+        let mut timing_bytes = [0u8; 20];
+        for (i, value) in [timing.prop_seg, timing.phase_seg1, timing.phase_seg2, timing.sjw, timing.brp].iter().enumerate() {
+            timing_bytes[4*i..4*i+4].copy_from_slice(&value.to_le_bytes());
+        }
+
+        let hardware_timestamps = bt_const.feature & GS_CAN_FEATURE_HW_TIMESTAMP != 0;
+        let flags = if hardware_timestamps { GS_CAN_MODE_HW_TIMESTAMP } else { 0 };
+
+        let mut mode = [0u8; 8];
+        mode[0..4].copy_from_slice(&GS_CAN_MODE_RESET.to_le_bytes());
+        handle.write_control(out_type, GS_USB_BREQ_MODE, 0, 0, &mode, CONTROL_TIMEOUT)?;
+
+        handle.write_control(out_type, GS_USB_BREQ_BITTIMING, 0, 0, &timing_bytes, CONTROL_TIMEOUT)?;
+
+        mode[0..4].copy_from_slice(&GS_CAN_MODE_START.to_le_bytes());
+        mode[4..8].copy_from_slice(&flags.to_le_bytes());
+        handle.write_control(out_type, GS_USB_BREQ_MODE, 0, 0, &mode, CONTROL_TIMEOUT)?;
+
+        Ok(GsUsbInterface {
+            handle,
+            hardware_timestamps,
+            echo_id: Cell::new(0),
+            statistics: Cell::new(TransportStats::default()),
+        })
+    }
+
+    fn update_statistics<F: FnOnce(&mut TransportStats)>(&self, f: F) {
+        let mut stats = self.statistics.get();
+        f(&mut stats);
+        self.statistics.set(stats);
+    }
+}
+
+impl Drop for GsUsbInterface {
+    fn drop(&mut self) {
+        let out_type = rusb::request_type(Direction::Out, RequestType::Vendor, Recipient::Interface);
+        let mut mode = [0u8; 8];
+        mode[0..4].copy_from_slice(&GS_CAN_MODE_RESET.to_le_bytes());
+        let _ = self.handle.write_control(out_type, GS_USB_BREQ_MODE, 0, 0, &mode, CONTROL_TIMEOUT);
+        let _ = self.handle.release_interface(0);
+    }
+}
+
+impl TransferInterface for GsUsbInterface {
+    type Frame = Timestamped<ExtendedDataFrame, u32>;
+
+    fn transmit(&self, frame: &Self::Frame) -> Result<(), IOError> {
+        let data = frame.data();
+
+        // Echo IDs are only used to match echoes, which are ignored, they just have to differ from `ECHO_ID_RX`
+        let echo_id = self.echo_id.get();
+        self.echo_id.set((echo_id + 1) % 64);
+
+        let mut buffer = [0u8; HOST_FRAME_SIZE];
+        buffer[0..4].copy_from_slice(&echo_id.to_le_bytes());
+        buffer[4..8].copy_from_slice(&(u32::from(frame.id()) | CAN_EFF_FLAG).to_le_bytes());
+        buffer[8] = data.len() as u8;
+        buffer[12..12 + data.len()].copy_from_slice(data);
+
+        match self.handle.write_bulk(ENDPOINT_OUT, &buffer, TRANSMIT_TIMEOUT) {
+            Ok(HOST_FRAME_SIZE) => Ok(()),
+            Ok(_) | Err(rusb::Error::Timeout) => Err(IOError::BufferExhausted),
+            Err(_) => {
+                self.update_statistics(|stats| stats.transmit_drops += 1);
+                Ok(())
+            },
+        }
+    }
+
+    fn receive(&self) -> Option<Self::Frame> {
+        loop {
+            let mut buffer = [0u8; HOST_FRAME_TIMESTAMP_SIZE];
+            let length = match self.handle.read_bulk(ENDPOINT_IN, &mut buffer, RECEIVE_TIMEOUT) {
+                Ok(length) => length,
+                Err(_) => return None,
+            };
+
+            if length < HOST_FRAME_SIZE {
+                continue;
+            }
+
+            let echo_id = u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]);
+            let can_id = u32::from_le_bytes([buffer[4], buffer[5], buffer[6], buffer[7]]);
+
+            if echo_id != ECHO_ID_RX {
+                continue;
+            }
+
+            if can_id & CAN_ERR_FLAG != 0 {
+                self.update_statistics(|stats| stats.protocol_errors += 1);
+                continue;
+            }
+
+            if can_id & CAN_RTR_FLAG != 0 || can_id & CAN_EFF_FLAG == 0 {
+                continue;
+            }
+
+            let mut frame = ExtendedDataFrame::new(ExtendedID::new(can_id & CAN_EFF_MASK));
+            let data_length = ::lib::core::cmp::min(buffer[8] as usize, 8);
+            frame.set_data_length(data_length);
+            frame.data_as_mut().copy_from_slice(&buffer[12..12 + data_length]);
+
+            let timestamp = if self.hardware_timestamps && length >= HOST_FRAME_TIMESTAMP_SIZE {
+                Some(u32::from_le_bytes([buffer[20], buffer[21], buffer[22], buffer[23]]))
+            } else {
+                None
+            };
+
+            return Some(Timestamped { frame, timestamp });
+        }
+    }
+
+    fn statistics(&self) -> TransportStats {
+        self.statistics.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use backend::gs_usb::{
+        BitTiming,
+        BitTimingConst,
+    };
+
+    /// The constants reported by a candleLight (STM32F072, 48 MHz CAN clock)
+    fn candle_light() -> BitTimingConst {
+        BitTimingConst {
+            feature: 0,
+            fclk_can: 48_000_000,
+            tseg1_min: 1,
+            tseg1_max: 16,
+            tseg2_min: 1,
+            tseg2_max: 8,
+            brp_min: 1,
+            brp_max: 1024,
+            brp_inc: 1,
+        }
+    }
+
+    #[test]
+    fn common_bit_rates() {
+        let constants = candle_light();
+        assert_eq!(constants.bit_timing(1_000_000), Some(BitTiming{prop_seg: 1, phase_seg1: 12, phase_seg2: 2, sjw: 1, brp: 3}));
+        assert_eq!(constants.bit_timing(500_000), Some(BitTiming{prop_seg: 1, phase_seg1: 12, phase_seg2: 2, sjw: 1, brp: 6}));
+        assert_eq!(constants.bit_timing(250_000), Some(BitTiming{prop_seg: 1, phase_seg1: 12, phase_seg2: 2, sjw: 1, brp: 12}));
+        assert_eq!(constants.bit_timing(125_000), Some(BitTiming{prop_seg: 1, phase_seg1: 12, phase_seg2: 2, sjw: 1, brp: 24}));
+    }
+
+    #[test]
+    fn out_of_range_bit_rates() {
+        let constants = candle_light();
+        assert_eq!(constants.bit_timing(0), None);
+        assert_eq!(constants.bit_timing(48_000_000), None);
+        assert_eq!(constants.bit_timing(u32::MAX), None);
+        // No prescaler divides the CAN clock into a whole number of time quanta per bit
+        assert_eq!(constants.bit_timing(999_999), None);
+
+        // Limits that would overflow the calculations
+        let mut constants = candle_light();
+        constants.fclk_can = u32::MAX;
+        constants.tseg1_max = u32::MAX;
+        constants.tseg2_max = u32::MAX;
+        constants.brp_max = u32::MAX;
+        constants.brp_inc = u32::MAX;
+        assert_eq!(constants.bit_timing(1), None);
+    }
+}
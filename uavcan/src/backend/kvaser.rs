@@ -0,0 +1,236 @@
+//! Backend for Kvaser adapters through CANlib
+//!
+//! Requires the `kvaser` feature and the Kvaser CANlib library (`canlib32.dll` on Windows, `libcanlib` on Linux)
+//! to be available at link time.
+//!
+//! # Examples
+//! ```no_run
+//! # use uavcan::{NodeConfig, SimpleNode};
+//! # use uavcan::storage::HeapStorage;
+//! use uavcan::backend::kvaser::KvaserInterface;
+//!
+//! let interface = KvaserInterface::new(0, 1_000_000).unwrap();
+//! let node: SimpleNode<_, _, HeapStorage<_>> = SimpleNode::new(&interface, NodeConfig::default());
+//! ```
+
+use lib::core::cell::Cell;
+
+use std::os::raw::{c_int, c_long, c_uint, c_ulong, c_void};
+use std::sync::Once;
+
+use embedded_types::can::ExtendedDataFrame;
+use embedded_types::can::ExtendedID;
+
+use transfer::TransferInterface;
+use transfer::TransferFrame;
+use transfer::TransportStats;
+use transfer::BusState;
+use transfer::Timestamped;
+use transfer::IOError;
+
+#[cfg_attr(windows, link(name = "canlib32"))]
+#[cfg_attr(not(windows), link(name = "canlib"))]
+extern "system" {
+    fn canInitializeLibrary();
+    fn canOpenChannel(channel: c_int, flags: c_int) -> c_int;
+    fn canClose(handle: c_int) -> c_int;
+    fn canSetBusParams(handle: c_int, freq: c_long, tseg1: c_uint, tseg2: c_uint, sjw: c_uint, no_samp: c_uint, syncmode: c_uint) -> c_int;
+    fn canBusOn(handle: c_int) -> c_int;
+    fn canBusOff(handle: c_int) -> c_int;
+    fn canIoCtl(handle: c_int, func: c_uint, buf: *mut c_void, buflen: c_uint) -> c_int;
+    fn canWrite(handle: c_int, id: c_long, msg: *const c_void, dlc: c_uint, flag: c_uint) -> c_int;
+    fn canRead(handle: c_int, id: *mut c_long, msg: *mut c_void, dlc: *mut c_uint, flag: *mut c_uint, time: *mut c_ulong) -> c_int;
+    fn canReadStatus(handle: c_int, flags: *mut c_ulong) -> c_int;
+}
+
+const CAN_OK: c_int = 0;
+const CAN_ERR_NOMSG: c_int = -2;
+const CAN_ERR_TXBUFOFL: c_int = -13;
+
+const CAN_OPEN_ACCEPT_VIRTUAL: c_int = 0x0020;
+
+const CAN_IOCTL_SET_TIMER_SCALE: c_uint = 6;
+
+const CAN_MSG_RTR: c_uint = 0x0001;
+const CAN_MSG_EXT: c_uint = 0x0004;
+const CAN_MSG_ERROR_FRAME: c_uint = 0x0020;
+const CAN_MSGERR_OVERRUN: c_uint = 0x0600;
+
+const CAN_STAT_ERROR_PASSIVE: c_ulong = 0x0001;
+const CAN_STAT_BUS_OFF: c_ulong = 0x0002;
+
+static INITIALIZE: Once = Once::new();
+
+/// An error returned from CANlib
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KvaserError {
+    /// The bit rate is not one of the predefined CANlib bit rates
+    UnsupportedBitRate(u32),
+
+    /// The raw `canStatus` returned from the API
+    Status(i32),
+}
+
+fn predefined_bit_rate(bit_rate: u32) -> Option<c_long> {
+    match bit_rate {
+        1_000_000 => Some(-1),
+        500_000 => Some(-2),
+        250_000 => Some(-3),
+        125_000 => Some(-4),
+        100_000 => Some(-5),
+        62_000 => Some(-6),
+        50_000 => Some(-7),
+        83_000 => Some(-8),
+        10_000 => Some(-9),
+        _ => None,
+    }
+}
+
+fn check(status: c_int) -> Result<(), KvaserError> {
+    if status < CAN_OK {
+        Err(KvaserError::Status(status as i32))
+    } else {
+        Ok(())
+    }
+}
+
+/// A `TransferInterface` for a Kvaser CANlib channel
+///
+/// Received frames are timestamped with the hardware timestamp in microseconds. The CANlib timer is 32 bits on Windows,
+/// timestamps will wrap around after about 71 minutes there.
+/// Standard (11-bit), remote and error frames are discarded on reception.
+#[derive(Debug)]
+pub struct KvaserInterface {
+    handle: c_int,
+    statistics: Cell<TransportStats>,
+}
+
+impl KvaserInterface {
+    /// Opens `channel`, configures it with the given bit rate and goes bus on
+    ///
+    /// Virtual channels are accepted, which allows testing without hardware.
+    pub fn new(channel: i32, bit_rate: u32) -> Result<Self, KvaserError> {
+        let freq = predefined_bit_rate(bit_rate).ok_or(KvaserError::UnsupportedBitRate(bit_rate))?;
+
+        INITIALIZE.call_once(|| unsafe { canInitializeLibrary() });
+
+        let handle = unsafe { canOpenChannel(channel as c_int, CAN_OPEN_ACCEPT_VIRTUAL) };
+        check(handle)?;
+
+        // Construct the interface before further configuration so that the channel is closed on errors
+        let interface = KvaserInterface {
+            handle,
+            statistics: Cell::new(TransportStats::default()),
+        };
+
+        check(unsafe { canSetBusParams(handle, freq, 0, 0, 0, 0, 0) })?;
+
+        let mut scale_us: u32 = 1;
+        check(unsafe {
+            canIoCtl(handle, CAN_IOCTL_SET_TIMER_SCALE, &mut scale_us as *mut u32 as *mut c_void, 4)
+        })?;
+
+        check(unsafe { canBusOn(handle) })?;
+
+        Ok(interface)
+    }
+
+    fn update_statistics<F: FnOnce(&mut TransportStats)>(&self, f: F) {
+        let mut stats = self.statistics.get();
+        f(&mut stats);
+        self.statistics.set(stats);
+    }
+}
+
+impl Drop for KvaserInterface {
+    fn drop(&mut self) {
+        unsafe {
+            canBusOff(self.handle);
+            canClose(self.handle);
+        }
+    }
+}
+
+impl TransferInterface for KvaserInterface {
+    type Frame = Timestamped<ExtendedDataFrame, u64>;
+
+    fn transmit(&self, frame: &Self::Frame) -> Result<(), IOError> {
+        let data = frame.data();
+        let status = unsafe {
+            canWrite(
+                self.handle,
+                u32::from(frame.id()) as c_long,
+                data.as_ptr() as *const c_void,
+                data.len() as c_uint,
+                CAN_MSG_EXT,
+            )
+        };
+
+        match status {
+            CAN_OK => Ok(()),
+            CAN_ERR_TXBUFOFL => Err(IOError::BufferExhausted),
+            _ => {
+                self.update_statistics(|stats| stats.transmit_drops += 1);
+                Ok(())
+            },
+        }
+    }
+
+    fn receive(&self) -> Option<Self::Frame> {
+        loop {
+            let mut id: c_long = 0;
+            let mut data = [0u8; 8];
+            let mut dlc: c_uint = 0;
+            let mut flags: c_uint = 0;
+            let mut time: c_ulong = 0;
+
+            let status = unsafe {
+                canRead(self.handle, &mut id, data.as_mut_ptr() as *mut c_void, &mut dlc, &mut flags, &mut time)
+            };
+
+            if status == CAN_ERR_NOMSG {
+                return None;
+            } else if status != CAN_OK {
+                self.update_statistics(|stats| stats.protocol_errors += 1);
+                return None;
+            }
+
+            if flags & CAN_MSGERR_OVERRUN != 0 {
+                self.update_statistics(|stats| stats.receive_overruns += 1);
+            }
+
+            if flags & CAN_MSG_ERROR_FRAME != 0 {
+                self.update_statistics(|stats| stats.protocol_errors += 1);
+                continue;
+            }
+
+            if flags & CAN_MSG_RTR != 0 || flags & CAN_MSG_EXT == 0 {
+                continue;
+            }
+
+            let mut frame = ExtendedDataFrame::new(ExtendedID::new(id as u32 & 0x1fff_ffff));
+            let length = ::lib::core::cmp::min(dlc as usize, 8);
+            frame.set_data_length(length);
+            frame.data_as_mut().copy_from_slice(&data[..length]);
+
+            return Some(Timestamped::new(frame, time as u64));
+        }
+    }
+
+    fn statistics(&self) -> TransportStats {
+        self.statistics.get()
+    }
+
+    fn bus_state(&self) -> BusState {
+        let mut flags: c_ulong = 0;
+        unsafe { canReadStatus(self.handle, &mut flags); }
+
+        if flags & CAN_STAT_BUS_OFF != 0 {
+            BusState::BusOff
+        } else if flags & CAN_STAT_ERROR_PASSIVE != 0 {
+            BusState::ErrorPassive
+        } else {
+            BusState::ErrorActive
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! `TransferInterface` implementations for desktop CAN adapters and microcontroller CAN peripherals
+//!
+//! Every backend is behind its own feature flag, as they link to vendor libraries, pull in additional dependencies
+//! or only make sense on a specific target.
+
+#[cfg(feature="pcan")]
+pub mod pcan;
+
+#[cfg(feature="kvaser")]
+pub mod kvaser;
+
+#[cfg(all(feature="socketcan", target_os="linux"))]
+pub mod socketcan;
+
+#[cfg(all(feature="vcan-harness", target_os="linux"))]
+pub mod vcan_harness;
+
+#[cfg(feature="gs-usb")]
+pub mod gs_usb;
+
+#[cfg(all(feature="sim-bus", unix))]
+pub mod sim_bus;
+
+#[cfg(feature="flexcan")]
+pub mod flexcan;
+
+#[cfg(feature="nb-can")]
+pub mod nb_can;
+
+#[cfg(feature="smoltcp")]
+pub mod smoltcp_udp;
+
+#[cfg(feature="w5500")]
+pub mod w5500;
+
+#[cfg(feature="serial-port")]
+pub mod serial_port;
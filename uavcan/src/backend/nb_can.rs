@@ -0,0 +1,438 @@
+//! Backend for CAN controllers with an `embedded_can::nb::Can` driver
+//!
+//! Requires the `nb-can` feature. The interface wraps any driver implementing `embedded_can::nb::Can`,
+//! e.g. the `Twai` driver of `esp-hal` for the ESP32 TWAI controller. The driver must be configured and started
+//! (bit rate, pins and acceptance filter) by the application before it's wrapped.
+//!
+//! # Examples
+//! ```no_run
+//! # extern crate embedded_can;
+//! # extern crate uavcan;
+//! use uavcan::backend::nb_can::NbCanInterface;
+//!
+//! # fn main() {}
+//! # fn start<C: embedded_can::nb::Can>(twai: C) {
+//! // With esp-hal, the driver is configured and started like this
+//! // let mut config = TwaiConfiguration::new(peripherals.TWAI0, rx_pin, tx_pin, BaudRate::B1000K, TwaiMode::Normal);
+//! // config.set_filter(SingleExtendedFilter::new(b"xxxxxxxxxxxxxxxxxxxxxxxxxxxxx", b"x"));
+//! // let twai = config.start();
+//! let interface = NbCanInterface::new(twai);
+//! # }
+//! ```
+
+use lib::core::cell::Cell;
+use lib::core::cell::RefCell;
+
+use embedded_can;
+use embedded_can::Frame as CanFrame;
+use embedded_can::Error as CanError;
+use embedded_can::ErrorKind;
+use embedded_can::nb::Can;
+use nb;
+
+use embedded_types::can::BaseID;
+use embedded_types::can::DataFrame;
+use embedded_types::can::ExtendedID;
+use embedded_types::can::ID;
+
+use transfer::TransferInterface;
+use transfer::TransportStats;
+use transfer::IOError;
+
+/// A `TransferInterface` for a controller with an `embedded_can::nb::Can` driver
+///
+/// Controllers like the TWAI have a single transmit buffer. If the driver replaces a pending frame with a higher priority one,
+/// the replaced frame is kept by the interface and handed back to the driver before any new frame is accepted.
+/// Standard (11-bit) frames are received as base format `DataFrame`s and handled by the node's `StandardFramePolicy`,
+/// remote frames are counted in the statistics and discarded.
+///
+/// `transmit` returns `IOError::BufferExhausted` while the transmit buffer is full, `IOError::InvalidInput` for frames
+/// the driver can't represent and `IOError::Other` for driver errors, whose kind is kept for `take_transmit_error`.
+/// A driver error ends `receive`. Driver errors are counted in the statistics.
+pub struct NbCanInterface<C: Can> {
+    driver: RefCell<C>,
+    replaced: RefCell<Option<C::Frame>>,
+    statistics: Cell<TransportStats>,
+    transmit_error: Cell<Option<ErrorKind>>,
+}
+
+impl<C: Can> NbCanInterface<C> {
+    /// Wraps a configured and started driver
+    pub fn new(driver: C) -> Self {
+        NbCanInterface {
+            driver: RefCell::new(driver),
+            replaced: RefCell::new(None),
+            statistics: Cell::new(TransportStats::default()),
+            transmit_error: Cell::new(None),
+        }
+    }
+
+    /// Returns the wrapped driver, dropping a replaced frame that was not yet retransmitted
+    pub fn into_inner(self) -> C {
+        self.driver.into_inner()
+    }
+
+    /// Returns (and clears) the kind of the driver error from the last `transmit` that failed for another reason than a full transmit buffer
+    pub fn take_transmit_error(&self) -> Option<ErrorKind> {
+        self.transmit_error.take()
+    }
+
+    fn update_statistics<F: FnOnce(&mut TransportStats)>(&self, f: F) {
+        let mut stats = self.statistics.get();
+        f(&mut stats);
+        self.statistics.set(stats);
+    }
+
+    fn count_error(&self, error: &C::Error) {
+        match error.kind() {
+            ErrorKind::Overrun => self.update_statistics(|stats| stats.receive_overruns += 1),
+            ErrorKind::Crc => self.update_statistics(|stats| stats.crc_errors += 1),
+            _ => self.update_statistics(|stats| stats.protocol_errors += 1),
+        }
+    }
+
+    /// Hands a frame to the driver, keeping the frame it replaces (if any)
+    fn transmit_driver_frame(&self, frame: &C::Frame) -> Result<(), IOError> {
+        match self.driver.borrow_mut().transmit(frame) {
+            Ok(replaced) => {
+                *self.replaced.borrow_mut() = replaced;
+                Ok(())
+            },
+            Err(nb::Error::WouldBlock) => Err(IOError::BufferExhausted),
+            Err(nb::Error::Other(ref e)) => {
+                self.count_error(e);
+                self.transmit_error.set(Some(e.kind()));
+                Err(IOError::Other)
+            },
+        }
+    }
+}
+
+impl<C: Can> TransferInterface for NbCanInterface<C> {
+    type Frame = DataFrame;
+
+    fn transmit(&self, frame: &Self::Frame) -> Result<(), IOError> {
+        let replaced = self.replaced.borrow_mut().take();
+        if let Some(replaced) = replaced {
+            if let Err(e) = self.transmit_driver_frame(&replaced) {
+                *self.replaced.borrow_mut() = Some(replaced);
+                return Err(e);
+            }
+            if self.replaced.borrow().is_some() {
+                return Err(IOError::BufferExhausted);
+            }
+        }
+
+        let id: embedded_can::Id = match frame.id() {
+            ID::ExtendedID(id) => embedded_can::ExtendedId::new(u32::from(id)).ok_or(IOError::InvalidInput)?.into(),
+            ID::BaseID(id) => embedded_can::StandardId::new(u16::from(id)).ok_or(IOError::InvalidInput)?.into(),
+        };
+        let driver_frame = C::Frame::new(id, frame.data()).ok_or(IOError::InvalidInput)?;
+        self.transmit_driver_frame(&driver_frame)
+    }
+
+    fn receive(&self) -> Option<Self::Frame> {
+        loop {
+            let driver_frame = match self.driver.borrow_mut().receive() {
+                Ok(frame) => frame,
+                Err(nb::Error::WouldBlock) => return None,
+                Err(nb::Error::Other(ref e)) => {
+                    self.count_error(e);
+                    return None;
+                },
+            };
+
+            if driver_frame.is_remote_frame() {
+                self.update_statistics(|stats| stats.remote_frames += 1);
+                continue;
+            }
+
+            let id = match driver_frame.id() {
+                embedded_can::Id::Extended(id) => ID::ExtendedID(ExtendedID::new(id.as_raw())),
+                embedded_can::Id::Standard(id) => ID::BaseID(BaseID::new(id.as_raw())),
+            };
+            let mut frame = DataFrame::new(id);
+            frame.set_data_length(driver_frame.data().len());
+            frame.data_as_mut().copy_from_slice(driver_frame.data());
+            return Some(frame);
+        }
+    }
+
+    fn statistics(&self) -> TransportStats {
+        self.statistics.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use embedded_can::{
+        ErrorKind,
+        ExtendedId,
+        Frame,
+        Id,
+        StandardId,
+    };
+    use embedded_can::nb::Can;
+    use nb;
+    use heapless::{
+        Deque,
+        Vec,
+    };
+
+    use embedded_types::can::BaseID;
+    use embedded_types::can::DataFrame;
+    use embedded_types::can::ExtendedID;
+    use embedded_types::can::ID;
+
+    use transfer::TransferInterface;
+    use transfer::TransferFrame;
+    use transfer::IOError;
+
+    use backend::nb_can::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct MockFrame {
+        id: Id,
+        remote: bool,
+        data: Vec<u8, 8>,
+    }
+
+    impl Frame for MockFrame {
+        fn new(id: impl Into<Id>, data: &[u8]) -> Option<Self> {
+            Some(MockFrame{id: id.into(), remote: false, data: Vec::from_slice(data).ok()?})
+        }
+
+        fn new_remote(id: impl Into<Id>, _dlc: usize) -> Option<Self> {
+            Some(MockFrame{id: id.into(), remote: true, data: Vec::new()})
+        }
+
+        fn is_extended(&self) -> bool {
+            match self.id {
+                Id::Extended(_) => true,
+                Id::Standard(_) => false,
+            }
+        }
+
+        fn is_remote_frame(&self) -> bool {
+            self.remote
+        }
+
+        fn id(&self) -> Id {
+            self.id
+        }
+
+        fn dlc(&self) -> usize {
+            self.data.len()
+        }
+
+        fn data(&self) -> &[u8] {
+            &self.data
+        }
+    }
+
+    /// A controller with a single transmit buffer, replacing the pending frame with a higher priority one
+    #[derive(Default)]
+    struct MockCan {
+        pending: Option<MockFrame>,
+        sent: Vec<MockFrame, 8>,
+        received: Deque<Result<MockFrame, ErrorKind>, 8>,
+        transmit_error: Option<ErrorKind>,
+    }
+
+    impl MockCan {
+        /// The pending frame is sent on the bus
+        fn complete(&mut self) {
+            if let Some(frame) = self.pending.take() {
+                self.sent.push(frame).unwrap();
+            }
+        }
+    }
+
+    impl Can for MockCan {
+        type Frame = MockFrame;
+        type Error = ErrorKind;
+
+        fn transmit(&mut self, frame: &MockFrame) -> nb::Result<Option<MockFrame>, ErrorKind> {
+            if let Some(error) = self.transmit_error {
+                return Err(nb::Error::Other(error));
+            }
+            match self.pending.take() {
+                None => {
+                    self.pending = Some(frame.clone());
+                    Ok(None)
+                },
+                Some(pending) => if raw_id(frame) < raw_id(&pending) {
+                    self.pending = Some(frame.clone());
+                    Ok(Some(pending))
+                } else {
+                    self.pending = Some(pending);
+                    Err(nb::Error::WouldBlock)
+                },
+            }
+        }
+
+        fn receive(&mut self) -> nb::Result<MockFrame, ErrorKind> {
+            match self.received.pop_front() {
+                Some(Ok(frame)) => Ok(frame),
+                Some(Err(error)) => Err(nb::Error::Other(error)),
+                None => Err(nb::Error::WouldBlock),
+            }
+        }
+    }
+
+    fn raw_id(frame: &MockFrame) -> u32 {
+        match frame.id {
+            Id::Extended(id) => id.as_raw(),
+            Id::Standard(id) => u32::from(id.as_raw()),
+        }
+    }
+
+    fn frame(id: u32, data: &[u8]) -> DataFrame {
+        let mut frame = DataFrame::new(ID::ExtendedID(ExtendedID::new(id)));
+        frame.set_data_length(data.len());
+        frame.data_as_mut().copy_from_slice(data);
+        frame
+    }
+
+    fn driver_frame(id: u32, data: &[u8]) -> MockFrame {
+        MockFrame::new(ExtendedId::new(id).unwrap(), data).unwrap()
+    }
+
+    #[test]
+    fn transmit_and_receive() {
+        let interface = NbCanInterface::new(MockCan::default());
+        assert_eq!(interface.transmit(&frame(0x1001_552a, &[1, 2, 0xc0])), Ok(()));
+        assert_eq!(interface.driver.borrow().pending, Some(driver_frame(0x1001_552a, &[1, 2, 0xc0])));
+
+        {
+            let mut driver = interface.driver.borrow_mut();
+            driver.received.push_back(Ok(MockFrame::new(StandardId::new(0x123).unwrap(), &[1]).unwrap())).unwrap();
+            driver.received.push_back(Ok(MockFrame::new_remote(ExtendedId::new(0x55).unwrap(), 0).unwrap())).unwrap();
+            driver.received.push_back(Ok(driver_frame(0x1001_5520, &[3, 0xc1]))).unwrap();
+        }
+        // The standard frame is left to the node's `StandardFramePolicy`
+        let standard = interface.receive().unwrap();
+        assert!(!standard.is_extended());
+        assert_eq!(standard.id(), ID::BaseID(BaseID::new(0x123)));
+        assert_eq!(standard.data(), &[1]);
+        assert_eq!(interface.receive(), Some(frame(0x1001_5520, &[3, 0xc1])));
+        assert_eq!(interface.receive(), None);
+        assert_eq!(interface.statistics().remote_frames, 1);
+    }
+
+    #[test]
+    fn transmit_frames_the_driver_cant_represent() {
+        /// A driver for frames of at most two data bytes
+        struct ShortCan;
+
+        #[derive(Clone, Debug)]
+        struct ShortFrame(MockFrame);
+
+        impl Frame for ShortFrame {
+            fn new(id: impl Into<Id>, data: &[u8]) -> Option<Self> {
+                if data.len() > 2 {
+                    return None;
+                }
+                Some(ShortFrame(MockFrame::new(id, data)?))
+            }
+
+            fn new_remote(id: impl Into<Id>, dlc: usize) -> Option<Self> {
+                Some(ShortFrame(MockFrame::new_remote(id, dlc)?))
+            }
+
+            fn is_extended(&self) -> bool {
+                self.0.is_extended()
+            }
+
+            fn is_remote_frame(&self) -> bool {
+                self.0.is_remote_frame()
+            }
+
+            fn id(&self) -> Id {
+                self.0.id()
+            }
+
+            fn dlc(&self) -> usize {
+                self.0.dlc()
+            }
+
+            fn data(&self) -> &[u8] {
+                self.0.data()
+            }
+        }
+
+        impl Can for ShortCan {
+            type Frame = ShortFrame;
+            type Error = ErrorKind;
+
+            fn transmit(&mut self, _frame: &ShortFrame) -> nb::Result<Option<ShortFrame>, ErrorKind> {
+                Ok(None)
+            }
+
+            fn receive(&mut self) -> nb::Result<ShortFrame, ErrorKind> {
+                Err(nb::Error::WouldBlock)
+            }
+        }
+
+        let interface = NbCanInterface::new(ShortCan);
+        assert_eq!(interface.transmit(&frame(0x1001_552a, &[1, 2, 0xc0])), Err(IOError::InvalidInput));
+        assert_eq!(interface.transmit(&frame(0x1001_552a, &[2, 0xc0])), Ok(()));
+
+        // Base format frames are transmitted with a standard ID
+        let interface = NbCanInterface::new(MockCan::default());
+        let mut base = DataFrame::new(ID::BaseID(BaseID::new(0x7ff)));
+        base.set_data_length(1);
+        assert_eq!(interface.transmit(&base), Ok(()));
+        assert_eq!(interface.driver.borrow().pending.as_ref().map(|frame| frame.id), Some(Id::Standard(StandardId::MAX)));
+    }
+
+    #[test]
+    fn replaced_frames_are_retransmitted() {
+        let interface = NbCanInterface::new(MockCan::default());
+        assert_eq!(interface.transmit(&frame(0x1000_0001, &[0xc0])), Ok(()));
+        // The higher priority frame replaces the pending one, which is kept by the interface
+        assert_eq!(interface.transmit(&frame(0x0800_0001, &[0xc0])), Ok(()));
+        assert_eq!(interface.transmit(&frame(0x0800_0002, &[0xc0])), Err(IOError::BufferExhausted));
+
+        interface.driver.borrow_mut().complete();
+        // The replaced frame goes first, leaving no room for the new frame
+        assert_eq!(interface.transmit(&frame(0x1800_0002, &[0xc0])), Err(IOError::BufferExhausted));
+        interface.driver.borrow_mut().complete();
+        assert_eq!(interface.transmit(&frame(0x1800_0002, &[0xc0])), Ok(()));
+        interface.driver.borrow_mut().complete();
+
+        let sent: Vec<u32, 8> = interface.into_inner().sent.iter().map(raw_id).collect();
+        assert_eq!(&sent[..], &[0x0800_0001, 0x1000_0001, 0x1800_0002]);
+    }
+
+    #[test]
+    fn driver_errors() {
+        let interface = NbCanInterface::new(MockCan::default());
+        interface.driver.borrow_mut().transmit_error = Some(ErrorKind::Bit);
+        assert_eq!(interface.transmit(&frame(0x1001_552a, &[0xc0])), Err(IOError::Other));
+        assert_eq!(interface.take_transmit_error(), Some(ErrorKind::Bit));
+        assert_eq!(interface.take_transmit_error(), None);
+
+        interface.driver.borrow_mut().transmit_error = None;
+        assert_eq!(interface.transmit(&frame(0x1001_552a, &[0xc0])), Ok(()));
+        assert_eq!(interface.take_transmit_error(), None);
+
+        // A receive error ends the reception, the frame after it is received next time
+        {
+            let mut driver = interface.driver.borrow_mut();
+            driver.received.push_back(Err(ErrorKind::Overrun)).unwrap();
+            driver.received.push_back(Err(ErrorKind::Crc)).unwrap();
+            driver.received.push_back(Ok(driver_frame(0x1001_5520, &[0xc1]))).unwrap();
+        }
+        assert_eq!(interface.receive(), None);
+        assert_eq!(interface.receive(), None);
+        assert_eq!(interface.receive(), Some(frame(0x1001_5520, &[0xc1])));
+
+        let statistics = interface.statistics();
+        assert_eq!(statistics.receive_overruns, 1);
+        assert_eq!(statistics.crc_errors, 1);
+        assert_eq!(statistics.protocol_errors, 1);
+        assert_eq!(statistics.transmit_drops, 0);
+    }
+}
@@ -0,0 +1,236 @@
+//! Backend for PEAK-System adapters through the PCAN-Basic API
+//!
+//! Requires the `pcan` feature and the PCAN-Basic library (`PCANBasic.dll` on Windows, `libpcanbasic` on Linux)
+//! to be available at link time.
+//!
+//! # Examples
+//! ```no_run
+//! # use uavcan::{NodeConfig, SimpleNode};
+//! # use uavcan::storage::HeapStorage;
+//! use uavcan::backend::pcan::{self, PcanInterface};
+//!
+//! let interface = PcanInterface::new(pcan::PCAN_USBBUS1, 1_000_000).unwrap();
+//! let node: SimpleNode<_, _, HeapStorage<_>> = SimpleNode::new(&interface, NodeConfig::default());
+//! ```
+
+use lib::core::cell::Cell;
+
+use embedded_types::can::ExtendedDataFrame;
+use embedded_types::can::ExtendedID;
+
+use transfer::TransferInterface;
+use transfer::TransferFrame;
+use transfer::TransportStats;
+use transfer::BusState;
+use transfer::Timestamped;
+use transfer::IOError;
+
+#[allow(non_snake_case)]
+#[repr(C)]
+struct TPCANMsg {
+    ID: u32,
+    MSGTYPE: u8,
+    LEN: u8,
+    DATA: [u8; 8],
+}
+
+#[repr(C)]
+struct TPCANTimestamp {
+    millis: u32,
+    millis_overflow: u16,
+    micros: u16,
+}
+
+#[cfg_attr(windows, link(name = "PCANBasic"))]
+#[cfg_attr(not(windows), link(name = "pcanbasic"))]
+extern "system" {
+    fn CAN_Initialize(channel: u16, btr0btr1: u16, hw_type: u8, io_port: u32, interrupt: u16) -> u32;
+    fn CAN_Uninitialize(channel: u16) -> u32;
+    fn CAN_GetStatus(channel: u16) -> u32;
+    fn CAN_Read(channel: u16, message: *mut TPCANMsg, timestamp: *mut TPCANTimestamp) -> u32;
+    fn CAN_Write(channel: u16, message: *mut TPCANMsg) -> u32;
+}
+
+/// The first PCAN-USB channel
+pub const PCAN_USBBUS1: u16 = 0x51;
+
+/// The second PCAN-USB channel
+pub const PCAN_USBBUS2: u16 = 0x52;
+
+/// The first PCAN-PCI channel
+pub const PCAN_PCIBUS1: u16 = 0x41;
+
+const PCAN_ERROR_OK: u32 = 0x0_0000;
+const PCAN_ERROR_XMTFULL: u32 = 0x0_0001;
+const PCAN_ERROR_OVERRUN: u32 = 0x0_0002;
+const PCAN_ERROR_BUSOFF: u32 = 0x0_0010;
+const PCAN_ERROR_QRCVEMPTY: u32 = 0x0_0020;
+const PCAN_ERROR_QOVERRUN: u32 = 0x0_0040;
+const PCAN_ERROR_BUSPASSIVE: u32 = 0x4_0000;
+
+const PCAN_MESSAGE_RTR: u8 = 0x01;
+const PCAN_MESSAGE_EXTENDED: u8 = 0x02;
+const PCAN_MESSAGE_ERRFRAME: u8 = 0x40;
+const PCAN_MESSAGE_STATUS: u8 = 0x80;
+
+/// An error status returned from the PCAN-Basic API
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PcanError {
+    /// The bit rate is not one of the predefined PCAN-Basic bit rates
+    UnsupportedBitRate(u32),
+
+    /// The raw `TPCANStatus` returned from the API
+    Status(u32),
+}
+
+fn btr0btr1(bit_rate: u32) -> Option<u16> {
+    match bit_rate {
+        1_000_000 => Some(0x0014),
+        800_000 => Some(0x0016),
+        500_000 => Some(0x001c),
+        250_000 => Some(0x011c),
+        125_000 => Some(0x031c),
+        100_000 => Some(0x432f),
+        50_000 => Some(0x472f),
+        20_000 => Some(0x532f),
+        10_000 => Some(0x672f),
+        _ => None,
+    }
+}
+
+/// A `TransferInterface` for a PCAN-Basic channel
+///
+/// Received frames are timestamped with the adapter timestamp in microseconds.
+/// Standard (11-bit), remote and error frames are discarded on reception.
+///
+/// The adapter transmits frames in the order they're written, so priority inversion may occur if several frames are queued.
+///
+/// `transmit` returns `IOError::BufferExhausted` for every failed write, as `IOError` has no other variant,
+/// the status of a failure other than a full transmit queue is kept for `take_transmit_error`.
+/// A failed read ends `receive` and is counted as a protocol error.
+#[derive(Debug)]
+pub struct PcanInterface {
+    channel: u16,
+    statistics: Cell<TransportStats>,
+    transmit_error: Cell<Option<u32>>,
+}
+
+impl PcanInterface {
+    /// Initializes `channel` with the given bit rate
+    pub fn new(channel: u16, bit_rate: u32) -> Result<Self, PcanError> {
+        let btr0btr1 = btr0btr1(bit_rate).ok_or(PcanError::UnsupportedBitRate(bit_rate))?;
+
+        let status = unsafe { CAN_Initialize(channel, btr0btr1, 0, 0, 0) };
+        if status != PCAN_ERROR_OK {
+            return Err(PcanError::Status(status));
+        }
+
+        Ok(PcanInterface {
+            channel,
+            statistics: Cell::new(TransportStats::default()),
+            transmit_error: Cell::new(None),
+        })
+    }
+
+    /// Returns the PCAN-Basic channel handle
+    pub fn channel(&self) -> u16 {
+        self.channel
+    }
+
+    /// Returns (and clears) the status of the last `transmit` that failed for another reason than a full transmit queue
+    pub fn take_transmit_error(&self) -> Option<PcanError> {
+        self.transmit_error.take().map(PcanError::Status)
+    }
+
+    fn update_statistics<F: FnOnce(&mut TransportStats)>(&self, f: F) {
+        let mut stats = self.statistics.get();
+        f(&mut stats);
+        self.statistics.set(stats);
+    }
+}
+
+impl Drop for PcanInterface {
+    fn drop(&mut self) {
+        unsafe { CAN_Uninitialize(self.channel); }
+    }
+}
+
+impl TransferInterface for PcanInterface {
+    type Frame = Timestamped<ExtendedDataFrame, u64>;
+
+    fn transmit(&self, frame: &Self::Frame) -> Result<(), IOError> {
+        let data = frame.data();
+        let mut message = TPCANMsg {
+            ID: u32::from(frame.id()),
+            MSGTYPE: PCAN_MESSAGE_EXTENDED,
+            LEN: data.len() as u8,
+            DATA: [0; 8],
+        };
+        message.DATA[..data.len()].copy_from_slice(data);
+
+        match unsafe { CAN_Write(self.channel, &mut message) } {
+            PCAN_ERROR_OK => Ok(()),
+            PCAN_ERROR_XMTFULL => Err(IOError::BufferExhausted),
+            status => {
+                self.transmit_error.set(Some(status));
+                Err(IOError::BufferExhausted)
+            },
+        }
+    }
+
+    fn receive(&self) -> Option<Self::Frame> {
+        loop {
+            let mut message = TPCANMsg { ID: 0, MSGTYPE: 0, LEN: 0, DATA: [0; 8] };
+            let mut timestamp = TPCANTimestamp { millis: 0, millis_overflow: 0, micros: 0 };
+
+            let status = unsafe { CAN_Read(self.channel, &mut message, &mut timestamp) };
+
+            if status & (PCAN_ERROR_OVERRUN | PCAN_ERROR_QOVERRUN) != 0 {
+                self.update_statistics(|stats| stats.receive_overruns += 1);
+            }
+
+            if status & PCAN_ERROR_QRCVEMPTY != 0 {
+                return None;
+            } else if status & !(PCAN_ERROR_OVERRUN | PCAN_ERROR_QOVERRUN) != PCAN_ERROR_OK {
+                // The status persists (e.g. bus off), reading again would not make progress
+                self.update_statistics(|stats| stats.protocol_errors += 1);
+                return None;
+            }
+
+            if message.MSGTYPE & PCAN_MESSAGE_ERRFRAME != 0 {
+                self.update_statistics(|stats| stats.protocol_errors += 1);
+                continue;
+            }
+
+            if message.MSGTYPE & (PCAN_MESSAGE_STATUS | PCAN_MESSAGE_RTR) != 0 || message.MSGTYPE & PCAN_MESSAGE_EXTENDED == 0 {
+                continue;
+            }
+
+            let micros = u64::from(timestamp.micros)
+                + 1000 * u64::from(timestamp.millis)
+                + 1000 * (u64::from(timestamp.millis_overflow) << 32);
+
+            let mut frame = ExtendedDataFrame::new(ExtendedID::new(message.ID & 0x1fff_ffff));
+            let length = ::lib::core::cmp::min(message.LEN as usize, 8);
+            frame.set_data_length(length);
+            frame.data_as_mut().copy_from_slice(&message.DATA[..length]);
+
+            return Some(Timestamped::new(frame, micros));
+        }
+    }
+
+    fn statistics(&self) -> TransportStats {
+        self.statistics.get()
+    }
+
+    fn bus_state(&self) -> BusState {
+        let status = unsafe { CAN_GetStatus(self.channel) };
+        if status & PCAN_ERROR_BUSOFF != 0 {
+            BusState::BusOff
+        } else if status & PCAN_ERROR_BUSPASSIVE != 0 {
+            BusState::ErrorPassive
+        } else {
+            BusState::ErrorActive
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! Backend for the serial transport on a byte stream, like a USB CDC-ACM port
+//!
+//! Requires the `serial-port` feature. The port is accessed through the `embedded_io` traits, which are implemented
+//! by the `SerialPort` of `usbd-serial` (letting boards with only USB join the network), by UART drivers and by `std` streams.
+//!
+//! The framing is done by the `serial` module. Like the other UDP style transports this is not a `TransferInterface`,
+//! the frames are reassembled with a `session::DatagramSessionManager`. A serial link connects two nodes,
+//! so every frame is received and it's up to the application to drop the service transfers to other nodes.
+//!
+//! # Examples
+//! ```no_run
+//! # extern crate embedded_io;
+//! # extern crate uavcan;
+//! # use embedded_io::{Read, ReadReady, Write};
+//! use uavcan::serial;
+//! use uavcan::backend::serial_port::SerialPortTransport;
+//!
+//! # struct UsbDevice;
+//! # impl UsbDevice {
+//! #     fn poll<P>(&mut self, _ports: &mut [&mut P]) -> bool { false }
+//! # }
+//! # fn main() {}
+//! # fn run<P: Read + ReadReady + Write>(mut usb_device: UsbDevice, usb_serial: P) {
+//! let mut transport = SerialPortTransport::<_, { serial::max_encoded_frame_len(256) }>::new(usb_serial);
+//!
+//! loop {
+//!     usb_device.poll(&mut [transport.port_mut()]);
+//!     let mut buffer = [0u8; 256];
+//!     while let Some(frame) = transport.receive(&mut buffer) {
+//!         // handle frame
+//!     }
+//! }
+//! # }
+//! ```
+
+use embedded_io::Read;
+use embedded_io::ReadReady;
+use embedded_io::Write;
+
+use serial::{
+    encode_frame,
+    DecodeError,
+    FrameDecoder,
+};
+use udp::UdpFrameHeader;
+
+/// Errors from the serial port backend
+#[derive(Debug, PartialEq, Eq)]
+pub enum SerialPortError<E> {
+    /// An error from the port
+    Io(E),
+
+    /// The encoded frame doesn't fit in the transport buffer
+    FrameTooLarge,
+
+    /// A received frame was discarded
+    Decode(DecodeError),
+}
+
+/// A serial transport on the port `P`, with buffers for encoded frames of up to `N` bytes
+///
+/// `N` is at least `serial::max_encoded_frame_len` of the largest payload, longer received frames are discarded.
+pub struct SerialPortTransport<P, const N: usize> {
+    port: P,
+    decoder: FrameDecoder<N>,
+    encoded: [u8; N],
+}
+
+impl<P: Read + ReadReady + Write, const N: usize> SerialPortTransport<P, N> {
+    /// Constructs a transport on `port`, the first frame is received after the first delimiter
+    pub fn new(port: P) -> Self {
+        SerialPortTransport {
+            port: port,
+            decoder: FrameDecoder::new(),
+            encoded: [0; N],
+        }
+    }
+
+    /// The port, e.g. to poll the USB device with it
+    pub fn port_mut(&mut self) -> &mut P {
+        &mut self.port
+    }
+
+    /// Returns the port
+    pub fn into_port(self) -> P {
+        self.port
+    }
+
+    /// Send a frame with `header` and `payload`
+    ///
+    /// Blocks until the port has accepted the whole frame, a port that can't take more bytes should block or return an error.
+    pub fn send(&mut self, header: &UdpFrameHeader, payload: &[u8]) -> Result<(), SerialPortError<P::Error>> {
+        let length = encode_frame(header, payload, &mut self.encoded).ok_or(SerialPortError::FrameTooLarge)?;
+        self.port.write_all(&self.encoded[..length]).map_err(SerialPortError::Io)?;
+        self.port.flush().map_err(SerialPortError::Io)
+    }
+
+    /// Receive the next frame into `buffer`, returning its header and payload
+    ///
+    /// Reads the bytes that are ready without blocking and returns `None` when they don't complete a frame.
+    /// Payloads that don't fit in `buffer` are truncated (the transfer CRC will then fail).
+    pub fn receive<'b>(&mut self, buffer: &'b mut [u8]) -> Option<Result<(UdpFrameHeader, &'b [u8]), SerialPortError<P::Error>>> {
+        loop {
+            match self.port.read_ready() {
+                Ok(true) => (),
+                Ok(false) => return None,
+                Err(e) => return Some(Err(SerialPortError::Io(e))),
+            }
+
+            let mut byte = [0u8; 1];
+            match self.port.read(&mut byte) {
+                Ok(0) => return None,
+                Ok(_) => (),
+                Err(e) => return Some(Err(SerialPortError::Io(e))),
+            }
+
+            match self.decoder.push(byte[0]) {
+                Some(Ok((header, payload))) => {
+                    let length = ::lib::core::cmp::min(payload.len(), buffer.len());
+                    buffer[..length].copy_from_slice(&payload[..length]);
+                    return Some(Ok((header, &buffer[..length])));
+                },
+                Some(Err(e)) => return Some(Err(SerialPortError::Decode(e))),
+                None => (),
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature="std"))]
+mod tests {
+
+    use std::collections::VecDeque;
+
+    use embedded_io::{
+        ErrorKind,
+        ErrorType,
+        Read,
+        ReadReady,
+        Write,
+    };
+
+    use backend::serial_port::*;
+    use serial::{
+        max_encoded_frame_len,
+        FRAME_DELIMITER,
+    };
+    use udp::{
+        DataSpecifier,
+        HeaderError,
+        UNSET_NODE_ID,
+    };
+
+    /// A port reading from `received` and writing to `sent`, like a USB CDC-ACM port without buffering
+    #[derive(Default)]
+    struct MockPort {
+        received: VecDeque<u8>,
+        sent: Vec<u8>,
+        fail: bool,
+    }
+
+    impl ErrorType for MockPort {
+        type Error = ErrorKind;
+    }
+
+    impl Read for MockPort {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+            let mut length = 0;
+            while length < buf.len() {
+                match self.received.pop_front() {
+                    Some(byte) => buf[length] = byte,
+                    None => break,
+                }
+                length += 1;
+            }
+            Ok(length)
+        }
+    }
+
+    impl ReadReady for MockPort {
+        fn read_ready(&mut self) -> Result<bool, ErrorKind> {
+            Ok(!self.received.is_empty())
+        }
+    }
+
+    impl Write for MockPort {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+            if self.fail {
+                return Err(ErrorKind::BrokenPipe);
+            }
+            self.sent.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), ErrorKind> {
+            Ok(())
+        }
+    }
+
+    const N: usize = max_encoded_frame_len(16);
+
+    fn header(data_specifier: DataSpecifier, destination_node_id: u16) -> UdpFrameHeader {
+        UdpFrameHeader {
+            priority: 4,
+            source_node_id: 42,
+            destination_node_id: destination_node_id,
+            data_specifier: data_specifier,
+            transfer_id: 3,
+            frame_index: 0,
+            end_of_transfer: true,
+            user_data: 0,
+        }
+    }
+
+    #[test]
+    fn send_and_receive() {
+        let mut sender = SerialPortTransport::<_, N>::new(MockPort::default());
+        let message = header(DataSpecifier::Message(7509), UNSET_NODE_ID);
+        let request = header(DataSpecifier::Request(430), 7);
+        sender.send(&message, &[1, 0, 2]).unwrap();
+        sender.send(&request, &[]).unwrap();
+
+        let sent = sender.into_port().sent;
+        assert_eq!(sent[0], FRAME_DELIMITER);
+        assert_eq!(sent[sent.len() - 1], FRAME_DELIMITER);
+
+        let mut receiver = SerialPortTransport::<_, N>::new(MockPort::default());
+        let mut buffer = [0u8; 16];
+        assert!(receiver.receive(&mut buffer).is_none());
+
+        receiver.port_mut().received.extend(sent);
+        assert_eq!(receiver.receive(&mut buffer), Some(Ok((message, &[1, 0, 2][..]))));
+        assert_eq!(receiver.receive(&mut buffer), Some(Ok((request, &[][..]))));
+        assert!(receiver.receive(&mut buffer).is_none());
+    }
+
+    #[test]
+    fn frames_split_across_reads() {
+        let mut sender = SerialPortTransport::<_, N>::new(MockPort::default());
+        let message = header(DataSpecifier::Message(7509), UNSET_NODE_ID);
+        sender.send(&message, &[1, 2, 3, 4]).unwrap();
+        let sent = sender.into_port().sent;
+
+        let mut receiver = SerialPortTransport::<_, N>::new(MockPort::default());
+        let mut buffer = [0u8; 2];
+        receiver.port_mut().received.extend(&sent[..10]);
+        assert!(receiver.receive(&mut buffer).is_none());
+        receiver.port_mut().received.extend(&sent[10..]);
+
+        // The payload is truncated to the buffer
+        assert_eq!(receiver.receive(&mut buffer), Some(Ok((message, &[1, 2][..]))));
+    }
+
+    #[test]
+    fn errors() {
+        let mut transport = SerialPortTransport::<_, N>::new(MockPort::default());
+        let message = header(DataSpecifier::Message(7509), UNSET_NODE_ID);
+        assert_eq!(transport.send(&message, &[0x55; 17]), Err(SerialPortError::FrameTooLarge));
+        transport.port_mut().fail = true;
+        assert_eq!(transport.send(&message, &[]), Err(SerialPortError::Io(ErrorKind::BrokenPipe)));
+
+        let mut buffer = [0u8; 16];
+        transport.port_mut().received.extend(&[FRAME_DELIMITER, 0x02, 0x01, FRAME_DELIMITER]);
+        assert_eq!(transport.receive(&mut buffer), Some(Err(SerialPortError::Decode(DecodeError::Header(HeaderError::TooShort)))));
+        assert!(transport.receive(&mut buffer).is_none());
+    }
+}
@@ -0,0 +1,314 @@
+//! A simulated CAN bus connecting nodes in different processes on the same machine
+//!
+//! Requires the `sim-bus` feature and a Unix platform. The bus consists of a `SimBusHub` bound to a Unix domain socket
+//! and any number of `SimBusInterface`s (typically one per process) connected to it. This allows software in the loop
+//! setups where every process runs its own `Node` as if they shared a physical bus.
+//!
+//! The hub mimics bus arbitration: all frames that are pending when the hub processes the bus are delivered in
+//! `TransferFrameID` order (frames with equal ID in the order they were sent), and frames are never echoed back to the sender.
+//!
+//! # Examples
+//! ```no_run
+//! # use std::io;
+//! # use std::time::Duration;
+//! # use uavcan::{NodeConfig, SimpleNode};
+//! # use uavcan::storage::HeapStorage;
+//! use uavcan::backend::sim_bus::{SimBusHub, SimBusInterface};
+//!
+//! # fn main() -> io::Result<()> {
+//! // In the simulation supervisor
+//! SimBusHub::bind("/tmp/uavcan-sim")?.spawn(Duration::from_millis(1));
+//!
+//! // In every simulated node
+//! let interface = SimBusInterface::connect("/tmp/uavcan-sim")?;
+//! let node: SimpleNode<_, _, HeapStorage<_>> = SimpleNode::new(&interface, NodeConfig::default());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BinaryHeap;
+use std::cmp::{Ordering, Reverse};
+use std::fs;
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::thread;
+use std::time::Duration;
+
+use embedded_types::can::ExtendedDataFrame;
+use embedded_types::can::ExtendedID;
+
+use transfer::TransferInterface;
+use transfer::TransferFrame;
+use transfer::TransferFrameID;
+use transfer::IOError;
+
+const MESSAGE_REGISTER: u8 = 0;
+const MESSAGE_FRAME: u8 = 1;
+const MESSAGE_UNREGISTER: u8 = 2;
+
+const MAX_MESSAGE_SIZE: usize = 14;
+
+static CLIENT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+struct PendingFrame {
+    id: TransferFrameID,
+    sequence: u64,
+    sender: PathBuf,
+    message: Vec<u8>,
+}
+
+impl PendingFrame {
+    fn key(&self) -> Reverse<(TransferFrameID, u64)> {
+        Reverse((self.id, self.sequence))
+    }
+}
+
+impl PartialEq for PendingFrame {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for PendingFrame {}
+
+impl PartialOrd for PendingFrame {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PendingFrame {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// The hub of a simulated bus, forwarding frames between the connected interfaces
+#[derive(Debug)]
+pub struct SimBusHub {
+    socket: UnixDatagram,
+    path: PathBuf,
+    clients: Vec<PathBuf>,
+    sequence: u64,
+}
+
+impl SimBusHub {
+    /// Creates a bus with the hub bound to `path`, replacing any stale socket file
+    pub fn bind<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let _ = fs::remove_file(&path);
+
+        let socket = UnixDatagram::bind(&path)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(SimBusHub {
+            socket,
+            path,
+            clients: Vec::new(),
+            sequence: 0,
+        })
+    }
+
+    /// Arbitrates and delivers all frames that are pending on the bus, returns the number of frames delivered
+    pub fn process(&mut self) -> io::Result<usize> {
+        let mut pending = BinaryHeap::new();
+        let mut buffer = [0u8; MAX_MESSAGE_SIZE];
+
+        loop {
+            let (length, address) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            };
+
+            let sender = match address.as_pathname() {
+                Some(path) => path.to_path_buf(),
+                None => continue,
+            };
+
+            match buffer[..length].first() {
+                Some(&MESSAGE_REGISTER) => {
+                    if !self.clients.contains(&sender) {
+                        self.clients.push(sender);
+                    }
+                },
+                Some(&MESSAGE_UNREGISTER) => self.clients.retain(|client| *client != sender),
+                Some(&MESSAGE_FRAME) => {
+                    if let Some(frame) = decode_frame(&buffer[..length]) {
+                        pending.push(PendingFrame {
+                            id: TransferFrame::id(&frame),
+                            sequence: self.sequence,
+                            sender,
+                            message: buffer[..length].to_vec(),
+                        });
+                        self.sequence += 1;
+                    }
+                },
+                _ => (),
+            }
+        }
+
+        let delivered = pending.len();
+        while let Some(frame) = pending.pop() {
+            for client in self.clients.iter().filter(|client| **client != frame.sender) {
+                // A client that has disappeared without unregistering will fail, this must not take down the bus
+                let _ = self.socket.send_to(&frame.message, client);
+            }
+        }
+
+        Ok(delivered)
+    }
+
+    /// Runs the hub in a new thread, processing the bus every `period`
+    pub fn spawn(mut self, period: Duration) -> thread::JoinHandle<io::Result<()>> {
+        thread::spawn(move || {
+            loop {
+                self.process()?;
+                thread::sleep(period);
+            }
+        })
+    }
+}
+
+impl Drop for SimBusHub {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn encode_frame(frame: &ExtendedDataFrame) -> ([u8; MAX_MESSAGE_SIZE], usize) {
+    let data = TransferFrame::data(frame);
+    let mut message = [0u8; MAX_MESSAGE_SIZE];
+    message[0] = MESSAGE_FRAME;
+    message[1..5].copy_from_slice(&u32::from(TransferFrame::id(frame)).to_le_bytes());
+    message[5] = data.len() as u8;
+    message[6..6 + data.len()].copy_from_slice(data);
+    (message, 6 + data.len())
+}
+
+fn decode_frame(message: &[u8]) -> Option<ExtendedDataFrame> {
+    if message.len() < 6 || message[0] != MESSAGE_FRAME {
+        return None;
+    }
+
+    let id = u32::from_le_bytes([message[1], message[2], message[3], message[4]]);
+    let length = message[5] as usize;
+    if id > 0x1fff_ffff || length > 8 || message.len() != 6 + length {
+        return None;
+    }
+
+    let mut frame = ExtendedDataFrame::new(ExtendedID::new(id));
+    frame.set_data_length(length);
+    frame.data_as_mut().copy_from_slice(&message[6..]);
+    Some(frame)
+}
+
+/// A `TransferInterface` connected to a `SimBusHub`
+#[derive(Debug)]
+pub struct SimBusInterface {
+    socket: UnixDatagram,
+    hub: PathBuf,
+    path: PathBuf,
+}
+
+impl SimBusInterface {
+    /// Connects to the hub bound to `hub`
+    ///
+    /// The interface binds its own socket next to the hub socket.
+    pub fn connect<P: AsRef<Path>>(hub: P) -> io::Result<Self> {
+        let hub = hub.as_ref().to_path_buf();
+
+        let mut name = hub.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+        name.push(format!(".{}.{}", process::id(), CLIENT_COUNT.fetch_add(1, AtomicOrdering::SeqCst)));
+        let path = hub.with_file_name(name);
+        let _ = fs::remove_file(&path);
+
+        let socket = UnixDatagram::bind(&path)?;
+        socket.set_nonblocking(true)?;
+
+        // Construct the interface before registering so that the socket file is removed on errors
+        let interface = SimBusInterface {
+            socket,
+            hub,
+            path,
+        };
+        interface.socket.send_to(&[MESSAGE_REGISTER], &interface.hub)?;
+
+        Ok(interface)
+    }
+}
+
+impl Drop for SimBusInterface {
+    fn drop(&mut self) {
+        let _ = self.socket.send_to(&[MESSAGE_UNREGISTER], &self.hub);
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl TransferInterface for SimBusInterface {
+    type Frame = ExtendedDataFrame;
+
+    fn transmit(&self, frame: &Self::Frame) -> Result<(), IOError> {
+        let (message, length) = encode_frame(frame);
+        match self.socket.send_to(&message[..length], &self.hub) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(IOError::BufferExhausted),
+        }
+    }
+
+    fn receive(&self) -> Option<Self::Frame> {
+        let mut buffer = [0u8; MAX_MESSAGE_SIZE];
+        loop {
+            match self.socket.recv(&mut buffer) {
+                Ok(length) => {
+                    if let Some(frame) = decode_frame(&buffer[..length]) {
+                        return Some(frame);
+                    }
+                },
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::env;
+    use std::process;
+
+    use transfer::TransferFrame;
+    use transfer::TransferFrameID;
+    use transfer::TransferInterface;
+
+    use embedded_types::can::ExtendedDataFrame;
+
+    use backend::sim_bus::*;
+
+    #[test]
+    fn arbitration_order() {
+        let path = env::temp_dir().join(format!("uavcan-sim-bus-test.{}", process::id()));
+        let mut hub = SimBusHub::bind(&path).unwrap();
+
+        let sender = SimBusInterface::connect(&path).unwrap();
+        let receiver = SimBusInterface::connect(&path).unwrap();
+        hub.process().unwrap();
+
+        for &id in [30, 10, 20, 10].iter() {
+            sender.transmit(&<ExtendedDataFrame as TransferFrame>::new(TransferFrameID::new(id))).unwrap();
+        }
+        assert_eq!(hub.process().unwrap(), 4);
+
+        let mut received = Vec::new();
+        while let Some(frame) = receiver.receive() {
+            received.push(u32::from(TransferFrame::id(&frame)));
+        }
+        assert_eq!(received, vec![10, 10, 20, 30]);
+
+        assert!(sender.receive().is_none());
+    }
+}
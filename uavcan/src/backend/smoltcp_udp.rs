@@ -0,0 +1,356 @@
+//! Backend for the UDP/IP transport on bare-metal targets through `smoltcp`
+//!
+//! Requires the `smoltcp` feature. The transport owns one UDP socket in a `SocketSet`, bound to `udp::UDP_PORT`,
+//! while the `Interface` and device stay with the application which must keep polling them as usual.
+//! Multicast groups are joined and left through the `Interface` as subjects are subscribed and unsubscribed,
+//! and service transfers are received once the node ID is set with `set_node_id`.
+//!
+//! The transport hands over datagrams rather than implementing `TransferInterface`, whose frames are CAN style frames with a tail byte.
+//! The received datagrams are reassembled with `session::DatagramSessionManager`.
+//!
+//! # Examples
+//! ```no_run
+//! # extern crate smoltcp;
+//! # extern crate uavcan;
+//! # use smoltcp::iface::{Interface, SocketSet, SocketStorage};
+//! # use smoltcp::phy::Device;
+//! # use smoltcp::socket::udp;
+//! # use smoltcp::time::Instant;
+//! use uavcan::backend::smoltcp_udp::SmoltcpUdpTransport;
+//!
+//! # fn now() -> Instant { Instant::from_millis(0) }
+//! # fn main() {}
+//! # fn run<D: Device>(mut iface: Interface, mut device: D) {
+//! # let mut rx_metadata = [udp::PacketMetadata::EMPTY; 4];
+//! # let mut rx_payload = [0u8; 1536];
+//! # let mut tx_metadata = [udp::PacketMetadata::EMPTY; 4];
+//! # let mut tx_payload = [0u8; 1536];
+//! # let mut socket_storage = [SocketStorage::EMPTY; 1];
+//! # let mut sockets = SocketSet::new(&mut socket_storage[..]);
+//! # let mut buffer = [0u8; 1536];
+//! let socket = udp::Socket::new(
+//!     udp::PacketBuffer::new(&mut rx_metadata[..], &mut rx_payload[..]),
+//!     udp::PacketBuffer::new(&mut tx_metadata[..], &mut tx_payload[..]),
+//! );
+//! let mut transport: SmoltcpUdpTransport<16> = SmoltcpUdpTransport::new(&mut sockets, socket).unwrap();
+//! transport.subscribe(&mut iface, 7509).unwrap();
+//! transport.set_node_id(&mut iface, Some(42)).unwrap();
+//!
+//! loop {
+//!     iface.poll(now(), &mut device, &mut sockets);
+//!     while let Some(Ok((header, payload))) = transport.receive(&mut sockets, &mut buffer) {
+//!         // Reassemble the transfer
+//!     }
+//! }
+//! # }
+//! ```
+
+use lib::core::cell::RefCell;
+
+use smoltcp::iface::Interface;
+use smoltcp::iface::MulticastError;
+use smoltcp::iface::SocketHandle;
+use smoltcp::iface::SocketSet;
+use smoltcp::socket::udp;
+use smoltcp::wire::IpAddress;
+use smoltcp::wire::IpEndpoint;
+use smoltcp::wire::Ipv4Address;
+
+use udp::{
+    UDP_PORT,
+    HEADER_SIZE,
+    DataSpecifier,
+    UdpFrameHeader,
+    HeaderError,
+    MulticastSocket,
+    MulticastMembership,
+    MembershipError,
+    service_multicast_group,
+};
+
+/// Errors from sending a datagram with `SmoltcpUdpTransport`
+#[derive(Debug, PartialEq, Eq)]
+pub enum SendError {
+    /// The subject ID, or the destination node ID of a service transfer, can't be mapped to a multicast group
+    InvalidDestination,
+
+    /// An error from the socket (e.g. the transmit buffer is full)
+    Socket(udp::SendError),
+}
+
+/// Lets `MulticastMembership` join and leave groups through a smoltcp `Interface`
+struct InterfaceMembership<'a>(RefCell<&'a mut Interface>);
+
+impl<'a> MulticastSocket for InterfaceMembership<'a> {
+    type Error = MulticastError;
+
+    fn join_multicast_group(&self, group: [u8; 4]) -> Result<(), Self::Error> {
+        self.0.borrow_mut().join_multicast_group(Ipv4Address::new(group[0], group[1], group[2], group[3]))
+    }
+
+    fn leave_multicast_group(&self, group: [u8; 4]) -> Result<(), Self::Error> {
+        self.0.borrow_mut().leave_multicast_group(Ipv4Address::new(group[0], group[1], group[2], group[3]))
+    }
+}
+
+/// A UDP/IP transport on top of a smoltcp socket, subscribing to at most `N` subjects
+///
+/// Datagrams are handed over whole, a datagram is one frame (`UdpFrameHeader` followed by the payload).
+/// Received datagrams on subjects that are not subscribed to, and service transfers to other nodes, are discarded.
+#[derive(Debug)]
+pub struct SmoltcpUdpTransport<const N: usize> {
+    handle: SocketHandle,
+    membership: MulticastMembership<N>,
+    node_id: Option<u16>,
+}
+
+impl<const N: usize> SmoltcpUdpTransport<N> {
+    /// Binds `socket` to `UDP_PORT` and adds it to `sockets`
+    pub fn new<'a>(sockets: &mut SocketSet<'a>, mut socket: udp::Socket<'a>) -> Result<Self, udp::BindError> {
+        socket.bind(UDP_PORT)?;
+        Ok(SmoltcpUdpTransport {
+            handle: sockets.add(socket),
+            membership: MulticastMembership::new(),
+            node_id: None,
+        })
+    }
+
+    /// Returns the handle of the socket used by the transport
+    pub fn handle(&self) -> SocketHandle {
+        self.handle
+    }
+
+    /// Subscribe to `subject_id`, joining its multicast group if it's the first subscription
+    pub fn subscribe(&mut self, interface: &mut Interface, subject_id: u16) -> Result<(), MembershipError<MulticastError>> {
+        self.membership.join(&InterfaceMembership(RefCell::new(interface)), subject_id)
+    }
+
+    /// Remove a subscription to `subject_id`, leaving its multicast group if it was the last subscription
+    pub fn unsubscribe(&mut self, interface: &mut Interface, subject_id: u16) -> Result<(), MembershipError<MulticastError>> {
+        self.membership.leave(&InterfaceMembership(RefCell::new(interface)), subject_id)
+    }
+
+    /// Returns true if `subject_id` is subscribed to
+    pub fn is_subscribed(&self, subject_id: u16) -> bool {
+        self.membership.is_joined(subject_id)
+    }
+
+    /// Receive the service transfers to `node_id`, joining its service multicast group and leaving the one of the previous node ID
+    ///
+    /// `None` stops receiving service transfers, like an anonymous node.
+    pub fn set_node_id(&mut self, interface: &mut Interface, node_id: Option<u16>) -> Result<(), MulticastError> {
+        let group = match node_id {
+            Some(node_id) => Some(service_multicast_group(node_id).ok_or(MulticastError::Unaddressable)?),
+            None => None,
+        };
+
+        let membership = InterfaceMembership(RefCell::new(interface));
+        if let Some(previous) = self.node_id.and_then(service_multicast_group) {
+            membership.leave_multicast_group(previous)?;
+            self.node_id = None;
+        }
+        if let Some(group) = group {
+            membership.join_multicast_group(group)?;
+        }
+        self.node_id = node_id;
+        Ok(())
+    }
+
+    /// Returns the node ID service transfers are received for
+    pub fn node_id(&self) -> Option<u16> {
+        self.node_id
+    }
+
+    /// Queue a datagram with `header` and `payload` for transmission to the multicast group of the subject, or of the destination node of a service transfer
+    ///
+    /// The datagram is sent the next time the application polls the `Interface`.
+    pub fn send(&self, sockets: &mut SocketSet, header: &UdpFrameHeader, payload: &[u8]) -> Result<(), SendError> {
+        let group = header.multicast_group().ok_or(SendError::InvalidDestination)?;
+        let endpoint = IpEndpoint::new(IpAddress::Ipv4(Ipv4Address::new(group[0], group[1], group[2], group[3])), UDP_PORT);
+
+        let socket = sockets.get_mut::<udp::Socket>(self.handle);
+        let datagram = socket.send(HEADER_SIZE + payload.len(), endpoint).map_err(SendError::Socket)?;
+        datagram[..HEADER_SIZE].copy_from_slice(&header.serialize());
+        datagram[HEADER_SIZE..].copy_from_slice(payload);
+        Ok(())
+    }
+
+    /// Receive the oldest datagram into `buffer`, returning its header and payload
+    ///
+    /// Returns `None` when there are no more datagrams. Datagrams that don't fit in `buffer` are dropped by the socket and skipped,
+    /// datagrams with a malformed header are returned as errors.
+    pub fn receive<'b>(&self, sockets: &mut SocketSet, buffer: &'b mut [u8]) -> Option<Result<(UdpFrameHeader, &'b [u8]), HeaderError>> {
+        let socket = sockets.get_mut::<udp::Socket>(self.handle);
+
+        loop {
+            let length = match socket.recv_slice(buffer) {
+                Ok((length, _)) => length,
+                Err(udp::RecvError::Truncated) => continue,
+                Err(udp::RecvError::Exhausted) => return None,
+            };
+
+            let header = match UdpFrameHeader::deserialize(&buffer[..length]) {
+                Ok(header) => header,
+                Err(e) => return Some(Err(e)),
+            };
+
+            let accepted = match header.data_specifier {
+                DataSpecifier::Message(subject_id) => self.membership.is_joined(subject_id),
+                DataSpecifier::Request(_) | DataSpecifier::Response(_) => self.node_id == Some(header.destination_node_id),
+            };
+            if accepted {
+                return Some(Ok((header, &buffer[HEADER_SIZE..length])));
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature="std"))]
+mod tests {
+
+    use smoltcp::iface::Config;
+    use smoltcp::iface::Interface;
+    use smoltcp::iface::PollResult;
+    use smoltcp::iface::SocketSet;
+    use smoltcp::iface::SocketStorage;
+    use smoltcp::phy::Loopback;
+    use smoltcp::phy::Medium;
+    use smoltcp::socket::udp;
+    use smoltcp::time::Instant;
+    use smoltcp::wire::EthernetAddress;
+    use smoltcp::wire::IpAddress;
+    use smoltcp::wire::IpCidr;
+
+    use backend::smoltcp_udp::*;
+
+    use udp::{
+        DataSpecifier,
+        UdpFrameHeader,
+        UNSET_NODE_ID,
+    };
+
+    fn interface(device: &mut Loopback) -> Interface {
+        let config = Config::new(EthernetAddress([0x02, 0x00, 0x00, 0x00, 0x00, 0x2a]).into());
+        let mut interface = Interface::new(config, device, Instant::ZERO);
+        interface.update_ip_addrs(|addresses| {
+            addresses.push(IpCidr::new(IpAddress::v4(192, 168, 1, 42), 24)).unwrap();
+        });
+        interface
+    }
+
+    fn socket() -> udp::Socket<'static> {
+        udp::Socket::new(
+            udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 8], vec![0; 4096]),
+            udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 8], vec![0; 4096]),
+        )
+    }
+
+    fn header(data_specifier: DataSpecifier, destination_node_id: u16, transfer_id: u64) -> UdpFrameHeader {
+        UdpFrameHeader {
+            priority: 4,
+            source_node_id: 42,
+            destination_node_id,
+            data_specifier,
+            transfer_id,
+            frame_index: 0,
+            end_of_transfer: true,
+            user_data: 0,
+        }
+    }
+
+    /// Sends the queued datagrams through the loopback device and receives them again
+    fn poll(interface: &mut Interface, device: &mut Loopback, sockets: &mut SocketSet) {
+        while interface.poll(Instant::ZERO, device, sockets) == PollResult::SocketStateChanged {}
+    }
+
+    #[test]
+    fn messages_on_subscribed_subjects() {
+        let mut device = Loopback::new(Medium::Ethernet);
+        let mut interface = interface(&mut device);
+        let mut storage = [SocketStorage::EMPTY; 1];
+        let mut sockets = SocketSet::new(&mut storage[..]);
+        let mut transport: SmoltcpUdpTransport<4> = SmoltcpUdpTransport::new(&mut sockets, socket()).unwrap();
+
+        transport.subscribe(&mut interface, 7509).unwrap();
+        assert!(transport.is_subscribed(7509));
+
+        transport.send(&mut sockets, &header(DataSpecifier::Message(7509), UNSET_NODE_ID, 1), &[1, 2, 3]).unwrap();
+        poll(&mut interface, &mut device, &mut sockets);
+
+        let mut buffer = [0u8; 64];
+        let (received, payload) = transport.receive(&mut sockets, &mut buffer).unwrap().unwrap();
+        assert_eq!(received, header(DataSpecifier::Message(7509), UNSET_NODE_ID, 1));
+        assert_eq!(payload, &[1, 2, 3]);
+        assert!(transport.receive(&mut sockets, &mut buffer).is_none());
+
+        // The group is left with the last subscription, the datagrams are no longer received
+        transport.unsubscribe(&mut interface, 7509).unwrap();
+        transport.send(&mut sockets, &header(DataSpecifier::Message(7509), UNSET_NODE_ID, 2), &[1, 2, 3]).unwrap();
+        poll(&mut interface, &mut device, &mut sockets);
+        assert!(transport.receive(&mut sockets, &mut buffer).is_none());
+    }
+
+    #[test]
+    fn oversized_datagrams_are_skipped() {
+        let mut device = Loopback::new(Medium::Ethernet);
+        let mut interface = interface(&mut device);
+        let mut storage = [SocketStorage::EMPTY; 1];
+        let mut sockets = SocketSet::new(&mut storage[..]);
+        let mut transport: SmoltcpUdpTransport<4> = SmoltcpUdpTransport::new(&mut sockets, socket()).unwrap();
+        transport.subscribe(&mut interface, 7509).unwrap();
+
+        transport.send(&mut sockets, &header(DataSpecifier::Message(7509), UNSET_NODE_ID, 1), &[0; 100]).unwrap();
+        transport.send(&mut sockets, &header(DataSpecifier::Message(7509), UNSET_NODE_ID, 2), &[1, 2, 3]).unwrap();
+        poll(&mut interface, &mut device, &mut sockets);
+
+        let mut buffer = [0u8; 64];
+        let (received, payload) = transport.receive(&mut sockets, &mut buffer).unwrap().unwrap();
+        assert_eq!(received.transfer_id, 2);
+        assert_eq!(payload, &[1, 2, 3]);
+        assert!(transport.receive(&mut sockets, &mut buffer).is_none());
+    }
+
+    #[test]
+    fn services_to_the_local_node() {
+        let mut device = Loopback::new(Medium::Ethernet);
+        let mut interface = interface(&mut device);
+        let mut storage = [SocketStorage::EMPTY; 1];
+        let mut sockets = SocketSet::new(&mut storage[..]);
+        let mut transport: SmoltcpUdpTransport<4> = SmoltcpUdpTransport::new(&mut sockets, socket()).unwrap();
+
+        assert_eq!(
+            transport.send(&mut sockets, &header(DataSpecifier::Request(430), UNSET_NODE_ID, 1), &[]),
+            Err(SendError::InvalidDestination),
+        );
+        assert_eq!(transport.set_node_id(&mut interface, Some(UNSET_NODE_ID)), Err(MulticastError::Unaddressable));
+
+        // A request to the local node and its response, while the node ID isn't set they aren't received
+        transport.send(&mut sockets, &header(DataSpecifier::Request(430), 7, 1), &[1]).unwrap();
+        poll(&mut interface, &mut device, &mut sockets);
+        let mut buffer = [0u8; 64];
+        assert!(transport.receive(&mut sockets, &mut buffer).is_none());
+
+        transport.set_node_id(&mut interface, Some(7)).unwrap();
+        assert_eq!(transport.node_id(), Some(7));
+        transport.send(&mut sockets, &header(DataSpecifier::Request(430), 7, 2), &[1]).unwrap();
+        transport.send(&mut sockets, &header(DataSpecifier::Response(430), 7, 2), &[2]).unwrap();
+        poll(&mut interface, &mut device, &mut sockets);
+
+        let (received, payload) = transport.receive(&mut sockets, &mut buffer).unwrap().unwrap();
+        assert_eq!(received.data_specifier, DataSpecifier::Request(430));
+        assert_eq!(payload, &[1]);
+        let (received, payload) = transport.receive(&mut sockets, &mut buffer).unwrap().unwrap();
+        assert_eq!(received.data_specifier, DataSpecifier::Response(430));
+        assert_eq!(payload, &[2]);
+
+        // Moving to another node ID leaves the group of the previous one
+        transport.set_node_id(&mut interface, Some(8)).unwrap();
+        transport.send(&mut sockets, &header(DataSpecifier::Request(430), 7, 3), &[1]).unwrap();
+        transport.send(&mut sockets, &header(DataSpecifier::Request(430), 8, 3), &[3]).unwrap();
+        poll(&mut interface, &mut device, &mut sockets);
+        let (received, payload) = transport.receive(&mut sockets, &mut buffer).unwrap().unwrap();
+        assert_eq!(received.destination_node_id, 8);
+        assert_eq!(payload, &[3]);
+        assert!(transport.receive(&mut sockets, &mut buffer).is_none());
+    }
+}
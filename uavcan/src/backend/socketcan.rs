@@ -0,0 +1,492 @@
+//! Backend for Linux SocketCAN interfaces
+//!
+//! Requires the `socketcan` feature and only builds on Linux. Works with both hardware interfaces and virtual (`vcan`) ones.
+//!
+//! # Examples
+//! ```no_run
+//! # use uavcan::{NodeConfig, SimpleNode};
+//! # use uavcan::storage::HeapStorage;
+//! use uavcan::backend::socketcan::SocketCanInterface;
+//!
+//! let interface = SocketCanInterface::open("can0").unwrap();
+//! let node: SimpleNode<_, _, HeapStorage<_>> = SimpleNode::new(&interface, NodeConfig::default());
+//! ```
+//!
+//! Received frames can be timestamped by the driver (hardware timestamps) or the kernel, see `SocketCanInterface::enable_timestamping`.
+//!
+//! Fixed rate single frame publications (e.g. node status or setpoints) can be offloaded to the kernel through a `BcmSocket`,
+//! which removes the jitter of publishing from user space.
+//!
+//! ```no_run
+//! # #[macro_use]
+//! # extern crate uavcan;
+//! # use std::time::Duration;
+//! # use uavcan::{NodeID, Struct};
+//! # use uavcan::types::*;
+//! use uavcan::backend::socketcan::BcmSocket;
+//!
+//! # #[derive(UavcanStruct)]
+//! # #[uavcan(message, id = 341, signature = 0x0f0868d0c1a7c6f1)]
+//! # struct NodeStatus {
+//! #     uptime_sec: u32,
+//! #     health: u2,
+//! #     mode: u3,
+//! #     sub_mode: u3,
+//! #     vendor_specific_status_code: u16,
+//! # }
+//! # fn main() {
+//! # let status = NodeStatus { uptime_sec: 0, health: u2::new(0), mode: u3::new(0), sub_mode: u3::new(0), vendor_specific_status_code: 0 };
+//! # let node_id = NodeID::new(42);
+//! let bcm = BcmSocket::open("can0").unwrap();
+//! let id = bcm.publish_periodic(status, 16, node_id, Duration::from_millis(100)).unwrap();
+//! # }
+//! ```
+
+use std::cell::Cell;
+use std::ffi::CString;
+use std::io;
+use std::mem;
+use std::os::raw::{c_int, c_void};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::Duration;
+
+use libc;
+
+use embedded_types::can::ExtendedDataFrame;
+use embedded_types::can::ExtendedID;
+
+use {
+    Frame,
+    Struct,
+    Message,
+};
+
+use node::NodeID;
+
+use transfer::TransferInterface;
+use transfer::TransferFrame;
+use transfer::TransferFrameID;
+use transfer::TransferID;
+use transfer::TailByte;
+use transfer::Timestamped;
+use transfer::IOError;
+
+use frame_disassembler::FrameDisassembler;
+
+const AF_CAN: c_int = 29;
+const CAN_RAW: c_int = 1;
+const CAN_BCM: c_int = 2;
+
+const TX_SETUP: u32 = 1;
+const TX_DELETE: u32 = 2;
+
+const SETTIMER: u32 = 0x0001;
+const STARTTIMER: u32 = 0x0002;
+
+/// The number of distinct transfer IDs, the kernel cycles through one frame for each of them
+const TRANSFER_ID_COUNT: usize = 32;
+
+const CAN_EFF_FLAG: u32 = 0x8000_0000;
+const CAN_RTR_FLAG: u32 = 0x4000_0000;
+const CAN_ERR_FLAG: u32 = 0x2000_0000;
+const CAN_EFF_MASK: u32 = 0x1fff_ffff;
+
+#[repr(C)]
+struct SockaddrCan {
+    can_family: libc::sa_family_t,
+    can_ifindex: c_int,
+    can_addr: [u64; 2],
+}
+
+#[repr(C, align(8))]
+struct CanFrame {
+    can_id: u32,
+    can_dlc: u8,
+    pad: u8,
+    res0: u8,
+    len8_dlc: u8,
+    data: [u8; 8],
+}
+
+#[repr(C)]
+struct BcmTimeval {
+    tv_sec: libc::c_long,
+    tv_usec: libc::c_long,
+}
+
+#[repr(C)]
+struct BcmMsgHead {
+    opcode: u32,
+    flags: u32,
+    count: u32,
+    ival1: BcmTimeval,
+    ival2: BcmTimeval,
+    can_id: u32,
+    nframes: u32,
+}
+
+#[repr(C)]
+struct BcmMessage {
+    head: BcmMsgHead,
+    frames: [CanFrame; TRANSFER_ID_COUNT],
+}
+
+fn open_socket(interface: &str, kind: c_int, protocol: c_int) -> io::Result<(RawFd, SockaddrCan)> {
+    let name = CString::new(interface).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Interface name contains a nul byte"))?;
+
+    let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    if index == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let fd = unsafe { libc::socket(AF_CAN, kind | libc::SOCK_CLOEXEC, protocol) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let address = SockaddrCan {
+        can_family: AF_CAN as libc::sa_family_t,
+        can_ifindex: index as c_int,
+        can_addr: [0; 2],
+    };
+
+    Ok((fd, address))
+}
+
+/// Returns true for the errors of writing to a socket with a full send buffer
+fn is_buffer_full(error: &io::Error) -> bool {
+    error.raw_os_error() == Some(libc::ENOBUFS) || error.kind() == io::ErrorKind::WouldBlock
+}
+
+/// The source of the reception timestamps of a `SocketCanInterface`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampSource {
+    /// Frames are not timestamped
+    None,
+
+    /// The raw timestamp from the CAN controller or driver, in the time base of the device clock
+    Hardware,
+
+    /// The time (`CLOCK_REALTIME`) the kernel received the frame, used when the driver doesn't provide hardware timestamps
+    Software,
+}
+
+/// A `TransferInterface` for a SocketCAN raw socket
+///
+/// The socket is non-blocking, `transmit` returns `IOError::BufferExhausted` when the socket send buffer is full (`ENOBUFS` or `EAGAIN`).
+/// `IOError` has no other variant, so other failures (like `ENETDOWN` when the interface is down) are reported the same way
+/// and kept apart in `take_transmit_error`. `send` returns the OS error instead.
+/// Standard (11-bit), remote and error frames are discarded on reception.
+#[derive(Debug)]
+pub struct SocketCanInterface {
+    fd: RawFd,
+    timestamping: Cell<bool>,
+    timestamp_source: Cell<TimestampSource>,
+    transmit_error: Cell<Option<i32>>,
+}
+
+impl SocketCanInterface {
+    /// Opens a raw CAN socket bound to the network interface `interface` (e.g. `"can0"` or `"vcan0"`)
+    pub fn open(interface: &str) -> io::Result<Self> {
+        let (fd, address) = open_socket(interface, libc::SOCK_RAW | libc::SOCK_NONBLOCK, CAN_RAW)?;
+
+        // Construct the interface before binding so that the socket is closed on errors
+        let socket = SocketCanInterface {
+            fd,
+            timestamping: Cell::new(false),
+            timestamp_source: Cell::new(TimestampSource::None),
+            transmit_error: Cell::new(None),
+        };
+
+        let result = unsafe {
+            libc::bind(
+                fd,
+                &address as *const SockaddrCan as *const libc::sockaddr,
+                mem::size_of::<SockaddrCan>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(socket)
+    }
+
+    /// Requests reception timestamps through `SO_TIMESTAMPING`
+    ///
+    /// Both hardware and software timestamps are requested, received frames carry the hardware timestamp when the driver provides one
+    /// and the software timestamp otherwise. Hardware timestamps must also be enabled on the network device (`SIOCSHWTSTAMP`),
+    /// which requires `CAP_NET_ADMIN` and is left to the system configuration.
+    pub fn enable_timestamping(&self) -> io::Result<()> {
+        let flags: c_int = (libc::SOF_TIMESTAMPING_RX_HARDWARE
+            | libc::SOF_TIMESTAMPING_RAW_HARDWARE
+            | libc::SOF_TIMESTAMPING_RX_SOFTWARE
+            | libc::SOF_TIMESTAMPING_SOFTWARE) as c_int;
+
+        let result = unsafe {
+            libc::setsockopt(
+                self.fd,
+                libc::SOL_SOCKET,
+                libc::SO_TIMESTAMPING,
+                &flags as *const c_int as *const c_void,
+                mem::size_of::<c_int>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        self.timestamping.set(true);
+        Ok(())
+    }
+
+    /// Returns the source of the timestamp of the last received frame
+    ///
+    /// Time synchronization followers should only mix timestamps from the same source.
+    pub fn timestamp_source(&self) -> TimestampSource {
+        self.timestamp_source.get()
+    }
+
+    /// Writes `frame` to the socket, returning the OS error on failure
+    pub fn send(&self, frame: &<Self as TransferInterface>::Frame) -> io::Result<()> {
+        let data = TransferFrame::data(frame);
+        let mut raw = CanFrame {
+            can_id: u32::from(TransferFrame::id(frame)) | CAN_EFF_FLAG,
+            can_dlc: data.len() as u8,
+            pad: 0,
+            res0: 0,
+            len8_dlc: 0,
+            data: [0; 8],
+        };
+        raw.data[..data.len()].copy_from_slice(data);
+
+        let written = unsafe { libc::write(self.fd, &raw as *const CanFrame as *const c_void, mem::size_of::<CanFrame>()) };
+        if written == mem::size_of::<CanFrame>() as isize {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    /// Returns (and clears) the error of the last `transmit` that failed for another reason than a full send buffer
+    pub fn take_transmit_error(&self) -> Option<io::Error> {
+        self.transmit_error.take().map(io::Error::from_raw_os_error)
+    }
+
+    /// Receives one raw frame, returning it together with its timestamp and the source of the timestamp
+    fn receive_raw(&self) -> Option<(CanFrame, Option<Duration>, TimestampSource)> {
+        let mut raw: CanFrame = unsafe { mem::zeroed() };
+        // Room for a `scm_timestamping` control message (three timespecs)
+        let mut control = [0u64; 16];
+
+        let mut iov = libc::iovec {
+            iov_base: &mut raw as *mut CanFrame as *mut c_void,
+            iov_len: mem::size_of::<CanFrame>(),
+        };
+        let mut header: libc::msghdr = unsafe { mem::zeroed() };
+        header.msg_iov = &mut iov;
+        header.msg_iovlen = 1;
+        if self.timestamping.get() {
+            header.msg_control = control.as_mut_ptr() as *mut c_void;
+            header.msg_controllen = mem::size_of_val(&control) as _;
+        }
+
+        let read = unsafe { libc::recvmsg(self.fd, &mut header, 0) };
+        if read != mem::size_of::<CanFrame>() as isize {
+            return None;
+        }
+
+        let mut timestamp = None;
+        let mut source = TimestampSource::None;
+        unsafe {
+            let mut message = libc::CMSG_FIRSTHDR(&header);
+            while !message.is_null() {
+                if (*message).cmsg_level == libc::SOL_SOCKET && (*message).cmsg_type == libc::SCM_TIMESTAMPING {
+                    let timestamps = ::std::ptr::read_unaligned(libc::CMSG_DATA(message) as *const [libc::timespec; 3]);
+                    let to_duration = |ts: &libc::timespec| Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32);
+
+                    if timestamps[2].tv_sec != 0 || timestamps[2].tv_nsec != 0 {
+                        timestamp = Some(to_duration(&timestamps[2]));
+                        source = TimestampSource::Hardware;
+                    } else if timestamps[0].tv_sec != 0 || timestamps[0].tv_nsec != 0 {
+                        timestamp = Some(to_duration(&timestamps[0]));
+                        source = TimestampSource::Software;
+                    }
+                }
+                message = libc::CMSG_NXTHDR(&header, message);
+            }
+        }
+
+        Some((raw, timestamp, source))
+    }
+}
+
+impl AsRawFd for SocketCanInterface {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for SocketCanInterface {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd); }
+    }
+}
+
+impl TransferInterface for SocketCanInterface {
+    type Frame = Timestamped<ExtendedDataFrame, Duration>;
+
+    fn transmit(&self, frame: &Self::Frame) -> Result<(), IOError> {
+        match self.send(frame) {
+            Ok(()) => Ok(()),
+            Err(ref e) if is_buffer_full(e) => Err(IOError::BufferExhausted),
+            Err(e) => {
+                self.transmit_error.set(e.raw_os_error());
+                Err(IOError::BufferExhausted)
+            },
+        }
+    }
+
+    fn receive(&self) -> Option<Self::Frame> {
+        loop {
+            let (raw, timestamp, source) = self.receive_raw()?;
+
+            if raw.can_id & (CAN_RTR_FLAG | CAN_ERR_FLAG) != 0 || raw.can_id & CAN_EFF_FLAG == 0 {
+                continue;
+            }
+
+            let mut frame = ExtendedDataFrame::new(ExtendedID::new(raw.can_id & CAN_EFF_MASK));
+            let length = ::lib::core::cmp::min(raw.can_dlc as usize, 8);
+            frame.set_data_length(length);
+            frame.data_as_mut().copy_from_slice(&raw.data[..length]);
+
+            self.timestamp_source.set(source);
+            return Some(Timestamped { frame, timestamp });
+        }
+    }
+}
+
+/// A SocketCAN Broadcast Manager socket for publishing single frame messages periodically from the kernel
+///
+/// Transfer IDs must increment between publications, so the kernel is set up with one frame for every transfer ID
+/// and cycles through them. Only messages fitting in a single frame can be published.
+#[derive(Debug)]
+pub struct BcmSocket {
+    fd: RawFd,
+}
+
+impl BcmSocket {
+    /// Opens a Broadcast Manager socket connected to the network interface `interface` (e.g. `"can0"` or `"vcan0"`)
+    pub fn open(interface: &str) -> io::Result<Self> {
+        let (fd, address) = open_socket(interface, libc::SOCK_DGRAM, CAN_BCM)?;
+
+        // Construct the socket before connecting so that it's closed on errors
+        let socket = BcmSocket { fd };
+
+        let result = unsafe {
+            libc::connect(
+                fd,
+                &address as *const SockaddrCan as *const libc::sockaddr,
+                mem::size_of::<SockaddrCan>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(socket)
+    }
+
+    /// Starts publishing `message` every `period`, returning the frame ID that identifies the publication
+    ///
+    /// Starting a publication with the same frame ID as a running one replaces it.
+    pub fn publish_periodic<T: Struct + Message>(&self, message: T, priority: u8, source_node: NodeID, period: Duration) -> io::Result<TransferFrameID> {
+        let mut bcm_message = Self::setup_message(message, priority, source_node)?;
+        bcm_message.head.flags = SETTIMER | STARTTIMER;
+        bcm_message.head.ival2 = BcmTimeval {
+            tv_sec: period.as_secs() as libc::c_long,
+            tv_usec: period.subsec_micros() as libc::c_long,
+        };
+
+        self.write_message(&bcm_message, mem::size_of::<BcmMessage>())?;
+        Ok(TransferFrameID::new(bcm_message.head.can_id & CAN_EFF_MASK))
+    }
+
+    /// Replaces the content of a running publication with `message`, keeping its period
+    ///
+    /// `priority` and `source_node` must be the same as when the publication was started.
+    pub fn update<T: Struct + Message>(&self, message: T, priority: u8, source_node: NodeID) -> io::Result<()> {
+        let bcm_message = Self::setup_message(message, priority, source_node)?;
+        self.write_message(&bcm_message, mem::size_of::<BcmMessage>())
+    }
+
+    /// Stops the publication identified by `id`
+    pub fn stop(&self, id: TransferFrameID) -> io::Result<()> {
+        let mut bcm_message: BcmMessage = unsafe { mem::zeroed() };
+        bcm_message.head.opcode = TX_DELETE;
+        bcm_message.head.can_id = u32::from(id) | CAN_EFF_FLAG;
+        self.write_message(&bcm_message, mem::size_of::<BcmMsgHead>())
+    }
+
+    fn setup_message<T: Struct + Message>(message: T, priority: u8, source_node: NodeID) -> io::Result<BcmMessage> {
+        let mut generator = FrameDisassembler::from_uavcan_frame(Frame::from_message(message, priority, source_node), TransferID::new(0));
+        let frame: ExtendedDataFrame = generator.next_transfer_frame().expect("A transfer consists of at least one frame");
+        if generator.next_transfer_frame::<ExtendedDataFrame>().is_some() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Only single frame messages can be published periodically"));
+        }
+
+        let data = TransferFrame::data(&frame);
+        let mut bcm_message: BcmMessage = unsafe { mem::zeroed() };
+        bcm_message.head.opcode = TX_SETUP;
+        bcm_message.head.can_id = u32::from(TransferFrame::id(&frame)) | CAN_EFF_FLAG;
+        bcm_message.head.nframes = TRANSFER_ID_COUNT as u32;
+
+        for (transfer_id, raw) in bcm_message.frames.iter_mut().enumerate() {
+            raw.can_id = bcm_message.head.can_id;
+            raw.can_dlc = data.len() as u8;
+            raw.data[..data.len()].copy_from_slice(data);
+            raw.data[data.len() - 1] = u8::from(TailByte::new(true, true, false, TransferID::new(transfer_id as u8)));
+        }
+
+        Ok(bcm_message)
+    }
+
+    fn write_message(&self, message: &BcmMessage, size: usize) -> io::Result<()> {
+        let written = unsafe { libc::write(self.fd, message as *const BcmMessage as *const c_void, size) };
+        if written == size as isize {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+impl AsRawFd for BcmSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for BcmSocket {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd); }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::io;
+
+    use libc;
+
+    use backend::socketcan::is_buffer_full;
+
+    #[test]
+    fn full_send_buffer_errors() {
+        assert!(is_buffer_full(&io::Error::from_raw_os_error(libc::ENOBUFS)));
+        assert!(is_buffer_full(&io::Error::from_raw_os_error(libc::EAGAIN)));
+        assert!(!is_buffer_full(&io::Error::from_raw_os_error(libc::ENETDOWN)));
+        assert!(!is_buffer_full(&io::Error::from_raw_os_error(libc::EINVAL)));
+    }
+}
@@ -0,0 +1,315 @@
+//! A harness for end to end tests on Linux virtual CAN (`vcan`) interfaces
+//!
+//! Nodes created by the harness communicate through the kernel CAN stack, exercising the full path from serialization to
+//! reception. The `vcan` interface must be created before the tests are run, e.g.
+//!
+//! ```text
+//! sudo ip link add dev vcan0 type vcan
+//! sudo ip link set up vcan0
+//! ```
+//!
+//! Service transfers are not supported by `Node` yet, `service_round_trip` sends and reassembles the frames of the request
+//! and the response on sockets of its own.
+//!
+//! # Examples
+//! ```no_run
+//! # #[macro_use]
+//! # extern crate uavcan;
+//! # use uavcan::Struct;
+//! # use uavcan::types::*;
+//! use uavcan::backend::vcan_harness::VcanHarness;
+//!
+//! # #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+//! # #[uavcan(message, id = 341, signature = 0x0f0868d0c1a7c6f1)]
+//! # struct NodeStatus {
+//! #     uptime_sec: u32,
+//! #     health: u2,
+//! #     mode: u3,
+//! #     sub_mode: u3,
+//! #     vendor_specific_status_code: u16,
+//! # }
+//! # #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+//! # #[uavcan(id = 5, response = "RestartNodeResponse", signature = 0x569e05394a3017f0)]
+//! # struct RestartNodeRequest {
+//! #     magic_number: u40,
+//! # }
+//! # #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+//! # #[uavcan(request = "RestartNodeRequest", signature = 0x569e05394a3017f0)]
+//! # struct RestartNodeResponse {
+//! #     ok: bool,
+//! # }
+//! # fn main() {
+//! # let node_status = NodeStatus { uptime_sec: 0, health: u2::new(0), mode: u3::new(0), sub_mode: u3::new(0), vendor_specific_status_code: 0 };
+//! let harness = VcanHarness::new("vcan0").unwrap();
+//! let publisher = harness.node(1).unwrap();
+//! let subscriber = harness.node(2).unwrap();
+//!
+//! let received = harness.round_trip(&publisher, &subscriber, node_status.clone()).unwrap();
+//! assert_eq!(received, node_status);
+//!
+//! let request = RestartNodeRequest { magic_number: u40::new(0xacce551b1e) };
+//! let response = harness.service_round_trip(1, 2, request, |request| {
+//!     RestartNodeResponse { ok: request.magic_number == u40::new(0xacce551b1e) }
+//! }).unwrap();
+//! assert!(response.ok);
+//! # }
+//! ```
+
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use {
+    Frame,
+    Struct,
+    Message,
+    Request,
+};
+
+use node::{
+    Node,
+    NodeConfig,
+    NodeID,
+    SimpleNode,
+    ReceiveError,
+    ReceiveErrorCode,
+};
+
+use storage::HeapStorage;
+use transfer::TransferInterface;
+use transfer::TransferFrame;
+use transfer::TransferID;
+use transfer::IOError;
+
+use frame_assembler::{
+    AssemblerError,
+    AssemblerResult,
+    BuildError,
+    FrameAssembler,
+};
+use frame_disassembler::FrameDisassembler;
+
+use backend::socketcan::SocketCanInterface;
+
+/// A node created by `VcanHarness`
+pub type TestNode = SimpleNode<SocketCanInterface, Box<SocketCanInterface>, HeapStorage<<SocketCanInterface as TransferInterface>::Frame>>;
+
+type CanFrame = <SocketCanInterface as TransferInterface>::Frame;
+
+/// The priority of the service transfers sent by `service_round_trip`
+const SERVICE_PRIORITY: u8 = 16;
+
+/// Errors from a round trip through the harness
+#[derive(Debug)]
+pub enum HarnessError {
+    /// Subscribing or broadcasting failed, `InvalidInput` if the data type has no type ID
+    IO(IOError),
+
+    /// Opening a socket or writing a frame to it failed, e.g. `ENETDOWN` when the interface is down
+    Socket(io::Error),
+
+    /// The transfer was received but could not be reassembled
+    Receive(ReceiveError),
+
+    /// The transfer was not received before the timeout expired
+    Timeout,
+}
+
+/// Creates nodes on a `vcan` interface and drives transfers between them
+#[derive(Debug)]
+pub struct VcanHarness {
+    interface: String,
+    timeout: Duration,
+}
+
+impl VcanHarness {
+    /// Creates a harness for the network interface `interface`
+    ///
+    /// Fails if the interface doesn't exist or can't be opened.
+    pub fn new(interface: &str) -> io::Result<Self> {
+        SocketCanInterface::open(interface)?;
+        Ok(VcanHarness {
+            interface: interface.to_string(),
+            timeout: Duration::from_secs(1),
+        })
+    }
+
+    /// Sets how long `round_trip` waits for a transfer to be received (1 s by default)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Creates a node with the given node ID and its own socket on the interface
+    pub fn node(&self, id: u8) -> io::Result<TestNode> {
+        let interface = SocketCanInterface::open(&self.interface)?;
+
+        let mut config = NodeConfig::default();
+        config.id = Some(NodeID::new(id));
+
+        Ok(SimpleNode::new(Box::new(interface), config))
+    }
+
+    /// Broadcasts `message` from `publisher` and returns it as received by `subscriber`
+    pub fn round_trip<T: Struct + Message + Clone>(&self, publisher: &TestNode, subscriber: &TestNode, message: T) -> Result<T, HarnessError> {
+        let subscription = subscriber.subscribe::<T>().map_err(HarnessError::IO)?;
+        publisher.broadcast(message).map_err(HarnessError::IO)?;
+
+        let deadline = Instant::now() + self.timeout;
+        while Instant::now() < deadline {
+            publisher.flush_transmissions();
+            subscriber.flush_receptions();
+
+            if let Some(result) = subscription.receive() {
+                return result.map_err(HarnessError::Receive);
+            }
+
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        Err(HarnessError::Timeout)
+    }
+
+    /// Sends `request` from a client with node ID `client` to a server with node ID `server`, which answers it with `respond`,
+    /// and returns the response as received by the client
+    pub fn service_round_trip<T, F>(&self, client: u8, server: u8, request: T, respond: F) -> Result<T::RESPONSE, HarnessError>
+        where T: Struct + Request,
+              F: FnOnce(T) -> T::RESPONSE,
+    {
+        let client_socket = SocketCanInterface::open(&self.interface).map_err(HarnessError::Socket)?;
+        let server_socket = SocketCanInterface::open(&self.interface).map_err(HarnessError::Socket)?;
+        let deadline = Instant::now() + self.timeout;
+
+        let request = Frame::from_request(request, SERVICE_PRIORITY, NodeID::new(client), NodeID::new(server))
+            .ok_or(HarnessError::IO(IOError::InvalidInput))?;
+        let request = Self::transfer(&client_socket, &server_socket, request, deadline)?;
+
+        let response = Frame::from_response(respond(request), SERVICE_PRIORITY, NodeID::new(server), NodeID::new(client))
+            .ok_or(HarnessError::IO(IOError::InvalidInput))?;
+        Self::transfer(&server_socket, &client_socket, response, deadline)
+    }
+
+    /// Sends the frames of `frame` from `source` and reassembles them from the frames received by `destination`
+    fn transfer<T: Struct>(source: &SocketCanInterface, destination: &SocketCanInterface, frame: Frame<T>, deadline: Instant) -> Result<T, HarnessError> {
+        let id = frame.id;
+        let mut disassembler = FrameDisassembler::from_uavcan_frame(frame, TransferID::new(0));
+        while let Some(transfer_frame) = disassembler.next_transfer_frame::<CanFrame>() {
+            source.send(&transfer_frame).map_err(HarnessError::Socket)?;
+        }
+
+        let receive_error = |error_code| HarnessError::Receive(ReceiveError {
+            transfer_frame_id: id,
+            transfer_id: TransferID::new(0),
+            error_code: error_code,
+        });
+
+        let mut assembler = FrameAssembler::new();
+        while Instant::now() < deadline {
+            let received = match destination.receive() {
+                Some(received) => received,
+                None => {
+                    thread::sleep(Duration::from_millis(1));
+                    continue;
+                },
+            };
+            if TransferFrame::id(&received) != id {
+                continue;
+            }
+
+            match assembler.add_transfer_frame(received) {
+                Ok(AssemblerResult::Ok) => (),
+                Ok(AssemblerResult::Finished) => {
+                    return match assembler.build() {
+                        Ok(frame) => Ok(frame.into_parts().1),
+                        Err(BuildError::CRCError) => Err(receive_error(ReceiveErrorCode::CRCError)),
+                        Err(_) => panic!("Unexpected error from FrameAssembler"),
+                    };
+                },
+                Err(AssemblerError::ToggleError) => return Err(receive_error(ReceiveErrorCode::ToggleError)),
+                Err(_) => panic!("Unexpected error from FrameAssembler"),
+            }
+        }
+
+        Err(HarnessError::Timeout)
+    }
+}
+
+/// The tests need the `vcan0` interface (see the module documentation) and are ignored by default
+#[cfg(test)]
+mod tests {
+
+    use *;
+    use types::*;
+    use backend::vcan_harness::*;
+
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    #[uavcan(id = 341, signature = 0x0f0868d0c1a7c6f1)]
+    struct NodeStatus {
+        uptime_sec: u32,
+        health: u2,
+        mode: u3,
+        sub_mode: u3,
+        vendor_specific_status_code: u16,
+    }
+
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    #[uavcan(id = 5, response = "RestartNodeResponse", signature = 0x569e05394a3017f0)]
+    struct RestartNodeRequest {
+        magic_number: u40,
+    }
+
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    #[uavcan(request = "RestartNodeRequest", signature = 0x569e05394a3017f0)]
+    struct RestartNodeResponse {
+        ok: bool,
+    }
+
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    #[uavcan(id = 4, response = "GetTransportStatsResponse")]
+    struct GetTransportStatsRequest {}
+
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    #[uavcan(request = "GetTransportStatsRequest")]
+    struct GetTransportStatsResponse {
+        transfers_tx: u48,
+        transfers_rx: u48,
+        transfer_errors: u48,
+    }
+
+    #[test]
+    #[ignore]
+    fn message_round_trip() {
+        let harness = VcanHarness::new("vcan0").unwrap();
+        let publisher = harness.node(1).unwrap();
+        let subscriber = harness.node(2).unwrap();
+
+        let status = NodeStatus {
+            uptime_sec: 42,
+            health: u2::new(1),
+            mode: u3::new(2),
+            sub_mode: u3::new(0),
+            vendor_specific_status_code: 0x1234,
+        };
+        assert_eq!(harness.round_trip(&publisher, &subscriber, status.clone()).unwrap(), status);
+    }
+
+    #[test]
+    #[ignore]
+    fn service_round_trip() {
+        let harness = VcanHarness::new("vcan0").unwrap();
+
+        let response = harness.service_round_trip(1, 2, RestartNodeRequest{magic_number: u40::new(0xacce551b1e)}, |request| {
+            RestartNodeResponse{ok: request.magic_number == u40::new(0xacce551b1e)}
+        });
+        assert_eq!(response.unwrap(), RestartNodeResponse{ok: true});
+
+        // The response spans several frames
+        let stats = GetTransportStatsResponse {
+            transfers_tx: u48::new(1),
+            transfers_rx: u48::new(2),
+            transfer_errors: u48::new(3),
+        };
+        let response = harness.service_round_trip(3, 4, GetTransportStatsRequest{}, |_| stats.clone());
+        assert_eq!(response.unwrap(), stats);
+    }
+}
@@ -0,0 +1,686 @@
+//! Backend for the UDP/IP transport on the WIZnet W5500 Ethernet controller
+//!
+//! Requires the `w5500` feature. The chip is accessed through an `embedded_hal::spi::SpiDevice`,
+//! which lets MCUs without an Ethernet MAC (or an IP stack) run the UDP transport.
+//!
+//! The W5500 has eight hardware sockets and a socket in multicast mode receives a single group.
+//! Socket 0 is used for transmission, the remaining seven sockets are opened on demand for the subscribed subjects
+//! and for the service transfers to the node (see `set_node_id`).
+//!
+//! The chip is polled while it completes a command or sends a datagram, giving up after `MAX_POLLS` register reads.
+//!
+//! # Examples
+//! ```no_run
+//! # extern crate embedded_hal;
+//! # extern crate uavcan;
+//! # use embedded_hal::spi::SpiDevice;
+//! use uavcan::backend::w5500::{NetworkConfig, W5500Transport};
+//!
+//! # fn main() {}
+//! # fn start<S: SpiDevice>(spi_device: S) {
+//! let config = NetworkConfig {
+//!     mac: [0x02, 0x00, 0x00, 0x00, 0x00, 0x2a],
+//!     ip: [192, 168, 1, 42],
+//!     subnet: [255, 255, 255, 0],
+//!     gateway: [192, 168, 1, 1],
+//! };
+//! let transport = W5500Transport::new(spi_device, config).unwrap();
+//! transport.subscribe(7509).unwrap();
+//! transport.set_node_id(Some(42)).unwrap();
+//! # }
+//! ```
+
+use lib::core::cell::Cell;
+use lib::core::cell::RefCell;
+
+use embedded_hal::spi::SpiDevice;
+use embedded_hal::spi::Operation;
+
+use udp::{
+    UDP_PORT,
+    HEADER_SIZE,
+    UdpFrameHeader,
+    HeaderError,
+    MulticastSocket,
+    MulticastMembership,
+    MembershipError,
+    service_multicast_group,
+};
+
+const SOCKET_COUNT: usize = 8;
+const TRANSMIT_SOCKET: u8 = 0;
+
+/// The number of subjects that can be subscribed at the same time (one socket each), one less while the node ID is set
+pub const MAX_SUBSCRIPTIONS: usize = SOCKET_COUNT - 1;
+
+/// The number of times a register is read while waiting for the chip, before it's considered unresponsive
+pub const MAX_POLLS: usize = 10_000;
+
+const COMMON_BLOCK: u8 = 0;
+
+const MR: u16 = 0x0000;
+const GAR: u16 = 0x0001;
+const SUBR: u16 = 0x0005;
+const SHAR: u16 = 0x0009;
+const SIPR: u16 = 0x000f;
+const VERSIONR: u16 = 0x0039;
+
+const MR_RST: u8 = 0x80;
+const CHIP_VERSION: u8 = 0x04;
+
+const SN_MR: u16 = 0x0000;
+const SN_CR: u16 = 0x0001;
+const SN_IR: u16 = 0x0002;
+const SN_SR: u16 = 0x0003;
+const SN_PORT: u16 = 0x0004;
+const SN_DHAR: u16 = 0x0006;
+const SN_DIPR: u16 = 0x000c;
+const SN_DPORT: u16 = 0x0010;
+const SN_TX_FSR: u16 = 0x0020;
+const SN_TX_WR: u16 = 0x0024;
+const SN_RX_RSR: u16 = 0x0026;
+const SN_RX_RD: u16 = 0x0028;
+
+const SN_MR_UDP: u8 = 0x02;
+const SN_MR_MULTI: u8 = 0x80;
+
+const SN_CR_OPEN: u8 = 0x01;
+const SN_CR_CLOSE: u8 = 0x10;
+const SN_CR_SEND_MAC: u8 = 0x21;
+const SN_CR_RECV: u8 = 0x40;
+
+const SN_IR_SEND_OK: u8 = 0x10;
+const SN_IR_TIMEOUT: u8 = 0x08;
+
+const SN_SR_CLOSED: u8 = 0x00;
+const SN_SR_UDP: u8 = 0x22;
+
+/// The chip prepends the source address, source port and length to every datagram in the receive buffer
+const RECEIVE_INFO_SIZE: u16 = 8;
+
+/// The network configuration of the W5500
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkConfig {
+    pub mac: [u8; 6],
+    pub ip: [u8; 4],
+    pub subnet: [u8; 4],
+    pub gateway: [u8; 4],
+}
+
+/// Errors from the W5500 backend
+#[derive(Debug, PartialEq, Eq)]
+pub enum W5500Error<E> {
+    /// An error from the SPI device
+    Spi(E),
+
+    /// The chip reported a version other than the one of the W5500
+    UnsupportedChip(u8),
+
+    /// A socket didn't reach the expected state after a command
+    SocketState(u8),
+
+    /// There is not enough room in the transmit buffer for the datagram
+    BufferExhausted,
+
+    /// The datagram was not sent before the chip timed out
+    Timeout,
+
+    /// The chip didn't complete a reset, a command or the transmission of a datagram within `MAX_POLLS` register reads
+    Unresponsive,
+
+    /// The subject ID, or the destination node ID of a service transfer, can't be mapped to a multicast group
+    InvalidDestination,
+
+    /// A received datagram has a malformed header
+    Header(HeaderError),
+}
+
+fn socket_block(socket: u8) -> u8 {
+    socket * 4 + 1
+}
+
+fn transmit_buffer_block(socket: u8) -> u8 {
+    socket * 4 + 2
+}
+
+fn receive_buffer_block(socket: u8) -> u8 {
+    socket * 4 + 3
+}
+
+fn multicast_mac(group: [u8; 4]) -> [u8; 6] {
+    [0x01, 0x00, 0x5e, group[1] & 0x7f, group[2], group[3]]
+}
+
+/// A UDP/IP transport on a W5500 connected through `S`
+///
+/// Received datagrams are returned in the order of the sockets they arrived on, not strictly in the order they arrived in.
+pub struct W5500Transport<S: SpiDevice> {
+    spi: RefCell<S>,
+    membership: RefCell<MulticastMembership<MAX_SUBSCRIPTIONS>>,
+    node_id: Cell<Option<u16>>,
+}
+
+impl<S: SpiDevice> W5500Transport<S> {
+    /// Resets and configures the chip, and opens the transmit socket
+    pub fn new(spi: S, config: NetworkConfig) -> Result<Self, W5500Error<S::Error>> {
+        let transport = W5500Transport {
+            spi: RefCell::new(spi),
+            membership: RefCell::new(MulticastMembership::new()),
+            node_id: Cell::new(None),
+        };
+
+        transport.write(COMMON_BLOCK, MR, &[MR_RST])?;
+        transport.poll(|| Ok(transport.read_u8(COMMON_BLOCK, MR)? & MR_RST == 0))?;
+
+        let version = transport.read_u8(COMMON_BLOCK, VERSIONR)?;
+        if version != CHIP_VERSION {
+            return Err(W5500Error::UnsupportedChip(version));
+        }
+
+        transport.write(COMMON_BLOCK, GAR, &config.gateway)?;
+        transport.write(COMMON_BLOCK, SUBR, &config.subnet)?;
+        transport.write(COMMON_BLOCK, SHAR, &config.mac)?;
+        transport.write(COMMON_BLOCK, SIPR, &config.ip)?;
+
+        transport.open_socket(TRANSMIT_SOCKET, SN_MR_UDP, None)?;
+        Ok(transport)
+    }
+
+    /// Subscribe to `subject_id`, opening a socket for its multicast group if it's the first subscription
+    pub fn subscribe(&self, subject_id: u16) -> Result<(), MembershipError<W5500Error<S::Error>>> {
+        self.membership.borrow_mut().join(self, subject_id)
+    }
+
+    /// Remove a subscription to `subject_id`, closing the socket of its multicast group if it was the last subscription
+    pub fn unsubscribe(&self, subject_id: u16) -> Result<(), MembershipError<W5500Error<S::Error>>> {
+        self.membership.borrow_mut().leave(self, subject_id)
+    }
+
+    /// Receive the service transfers to `node_id`, opening a socket for its service multicast group and closing the one of the previous node ID
+    ///
+    /// `None` stops receiving service transfers, like an anonymous node.
+    pub fn set_node_id(&self, node_id: Option<u16>) -> Result<(), W5500Error<S::Error>> {
+        let group = match node_id {
+            Some(node_id) => Some(service_multicast_group(node_id).ok_or(W5500Error::InvalidDestination)?),
+            None => None,
+        };
+
+        if let Some(previous) = self.node_id.get().and_then(service_multicast_group) {
+            self.leave_multicast_group(previous)?;
+            self.node_id.set(None);
+        }
+        if let Some(group) = group {
+            self.join_multicast_group(group)?;
+        }
+        self.node_id.set(node_id);
+        Ok(())
+    }
+
+    /// Send a datagram with `header` and `payload` to the multicast group of the subject, or of the destination node of a service transfer
+    ///
+    /// Blocks until the chip has sent the datagram.
+    pub fn send(&self, header: &UdpFrameHeader, payload: &[u8]) -> Result<(), W5500Error<S::Error>> {
+        let group = header.multicast_group().ok_or(W5500Error::InvalidDestination)?;
+
+        let length = HEADER_SIZE + payload.len();
+        let block = socket_block(TRANSMIT_SOCKET);
+        if usize::from(self.read_u16_stable(block, SN_TX_FSR)?) < length {
+            return Err(W5500Error::BufferExhausted);
+        }
+
+        let pointer = self.read_u16(block, SN_TX_WR)?;
+        self.write(transmit_buffer_block(TRANSMIT_SOCKET), pointer, &header.serialize())?;
+        self.write(transmit_buffer_block(TRANSMIT_SOCKET), pointer.wrapping_add(HEADER_SIZE as u16), payload)?;
+        self.write(block, SN_TX_WR, &pointer.wrapping_add(length as u16).to_be_bytes())?;
+
+        // Multicast destinations can't be resolved by ARP, the MAC is derived from the group instead
+        self.write(block, SN_DIPR, &group)?;
+        self.write(block, SN_DPORT, &UDP_PORT.to_be_bytes())?;
+        self.write(block, SN_DHAR, &multicast_mac(group))?;
+        self.command(TRANSMIT_SOCKET, SN_CR_SEND_MAC)?;
+
+        let mut interrupts = 0;
+        self.poll(|| {
+            interrupts = self.read_u8(block, SN_IR)?;
+            Ok(interrupts & (SN_IR_SEND_OK | SN_IR_TIMEOUT) != 0)
+        })?;
+        self.write(block, SN_IR, &[SN_IR_SEND_OK | SN_IR_TIMEOUT])?;
+        if interrupts & SN_IR_SEND_OK != 0 {
+            Ok(())
+        } else {
+            Err(W5500Error::Timeout)
+        }
+    }
+
+    /// Receive the next datagram into `buffer`, returning its header and payload
+    ///
+    /// Returns `None` when there are no more datagrams. Datagrams that don't fit in `buffer` are truncated (the transfer CRC will then fail).
+    pub fn receive<'b>(&self, buffer: &'b mut [u8]) -> Option<Result<(UdpFrameHeader, &'b [u8]), W5500Error<S::Error>>> {
+        for socket in 1..SOCKET_COUNT as u8 {
+            let block = socket_block(socket);
+            match self.read_u16_stable(block, SN_RX_RSR) {
+                Ok(0) => continue,
+                Ok(_) => return Some(self.receive_datagram(socket, buffer)),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        None
+    }
+
+    fn receive_datagram<'b>(&self, socket: u8, buffer: &'b mut [u8]) -> Result<(UdpFrameHeader, &'b [u8]), W5500Error<S::Error>> {
+        let block = socket_block(socket);
+        let pointer = self.read_u16(block, SN_RX_RD)?;
+
+        let mut info = [0u8; RECEIVE_INFO_SIZE as usize];
+        self.read(receive_buffer_block(socket), pointer, &mut info)?;
+        let datagram_length = u16::from_be_bytes([info[6], info[7]]);
+
+        let length = ::lib::core::cmp::min(usize::from(datagram_length), buffer.len());
+        self.read(receive_buffer_block(socket), pointer.wrapping_add(RECEIVE_INFO_SIZE), &mut buffer[..length])?;
+
+        self.write(block, SN_RX_RD, &pointer.wrapping_add(RECEIVE_INFO_SIZE + datagram_length).to_be_bytes())?;
+        self.command(socket, SN_CR_RECV)?;
+
+        let header = UdpFrameHeader::deserialize(&buffer[..length]).map_err(W5500Error::Header)?;
+        Ok((header, &buffer[HEADER_SIZE..length]))
+    }
+
+    fn open_socket(&self, socket: u8, mode: u8, group: Option<[u8; 4]>) -> Result<(), W5500Error<S::Error>> {
+        let block = socket_block(socket);
+        self.write(block, SN_MR, &[mode])?;
+        self.write(block, SN_PORT, &UDP_PORT.to_be_bytes())?;
+        if let Some(group) = group {
+            self.write(block, SN_DIPR, &group)?;
+            self.write(block, SN_DPORT, &UDP_PORT.to_be_bytes())?;
+            self.write(block, SN_DHAR, &multicast_mac(group))?;
+        }
+        self.command(socket, SN_CR_OPEN)?;
+
+        let state = self.read_u8(block, SN_SR)?;
+        if state == SN_SR_UDP {
+            Ok(())
+        } else {
+            Err(W5500Error::SocketState(state))
+        }
+    }
+
+    fn command(&self, socket: u8, command: u8) -> Result<(), W5500Error<S::Error>> {
+        let block = socket_block(socket);
+        self.write(block, SN_CR, &[command])?;
+        // The command register is cleared when the command is accepted
+        self.poll(|| Ok(self.read_u8(block, SN_CR)? == 0))
+    }
+
+    /// Calls `done` until it returns true, at most `MAX_POLLS` times
+    fn poll<F: FnMut() -> Result<bool, W5500Error<S::Error>>>(&self, mut done: F) -> Result<(), W5500Error<S::Error>> {
+        for _ in 0..MAX_POLLS {
+            if done()? {
+                return Ok(());
+            }
+        }
+        Err(W5500Error::Unresponsive)
+    }
+
+    fn read(&self, block: u8, address: u16, data: &mut [u8]) -> Result<(), W5500Error<S::Error>> {
+        let address = address.to_be_bytes();
+        let control = block << 3;
+        self.spi.borrow_mut()
+            .transaction(&mut [Operation::Write(&[address[0], address[1], control]), Operation::Read(data)])
+            .map_err(W5500Error::Spi)
+    }
+
+    fn write(&self, block: u8, address: u16, data: &[u8]) -> Result<(), W5500Error<S::Error>> {
+        let address = address.to_be_bytes();
+        let control = (block << 3) | 0x04;
+        self.spi.borrow_mut()
+            .transaction(&mut [Operation::Write(&[address[0], address[1], control]), Operation::Write(data)])
+            .map_err(W5500Error::Spi)
+    }
+
+    fn read_u8(&self, block: u8, address: u16) -> Result<u8, W5500Error<S::Error>> {
+        let mut value = [0u8; 1];
+        self.read(block, address, &mut value)?;
+        Ok(value[0])
+    }
+
+    fn read_u16(&self, block: u8, address: u16) -> Result<u16, W5500Error<S::Error>> {
+        let mut value = [0u8; 2];
+        self.read(block, address, &mut value)?;
+        Ok(u16::from_be_bytes(value))
+    }
+
+    /// Reads a 16-bit register the chip may update between the two bytes, until two reads agree
+    fn read_u16_stable(&self, block: u8, address: u16) -> Result<u16, W5500Error<S::Error>> {
+        let mut previous = self.read_u16(block, address)?;
+        let mut stable = previous;
+        self.poll(|| {
+            let value = self.read_u16(block, address)?;
+            stable = value;
+            let done = value == previous;
+            previous = value;
+            Ok(done)
+        })?;
+        Ok(stable)
+    }
+}
+
+impl<S: SpiDevice> MulticastSocket for W5500Transport<S> {
+    type Error = W5500Error<S::Error>;
+
+    fn join_multicast_group(&self, group: [u8; 4]) -> Result<(), Self::Error> {
+        for socket in 1..SOCKET_COUNT as u8 {
+            if self.read_u8(socket_block(socket), SN_SR)? == SN_SR_CLOSED {
+                return self.open_socket(socket, SN_MR_UDP | SN_MR_MULTI, Some(group));
+            }
+        }
+        Err(W5500Error::SocketState(SN_SR_UDP))
+    }
+
+    fn leave_multicast_group(&self, group: [u8; 4]) -> Result<(), Self::Error> {
+        for socket in 1..SOCKET_COUNT as u8 {
+            let block = socket_block(socket);
+            let mut joined = [0u8; 4];
+            self.read(block, SN_DIPR, &mut joined)?;
+            if joined == group && self.read_u8(block, SN_SR)? == SN_SR_UDP {
+                return self.command(socket, SN_CR_CLOSE);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature="std"))]
+mod tests {
+
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::convert::Infallible;
+    use std::rc::Rc;
+
+    use embedded_hal::spi::{
+        ErrorType,
+        Operation,
+        SpiDevice,
+    };
+
+    use backend::w5500::*;
+    use udp::{
+        DataSpecifier,
+        UdpFrameHeader,
+        UNSET_NODE_ID,
+    };
+
+    /// Emulates the registers and buffers of a W5500, with the commands completing immediately
+    #[derive(Default)]
+    struct Chip {
+        memory: HashMap<(u8, u16), u8>,
+        /// The number of bytes written to the receive buffer of each socket
+        received: [u16; SOCKET_COUNT],
+        /// The datagrams sent, with their destination group
+        sent: Vec<([u8; 4], Vec<u8>)>,
+        sent_pointer: u16,
+        /// The reset and the commands never complete
+        stuck: bool,
+        /// Transmissions end with a timeout instead of `SEND_OK`
+        send_timeout: bool,
+    }
+
+    impl Chip {
+        fn get(&self, block: u8, address: u16) -> u8 {
+            self.memory.get(&(block, address)).cloned().unwrap_or(0)
+        }
+
+        fn get_u16(&self, block: u8, address: u16) -> u16 {
+            u16::from_be_bytes([self.get(block, address), self.get(block, address + 1)])
+        }
+
+        fn set(&mut self, block: u8, address: u16, value: u8) {
+            self.memory.insert((block, address), value);
+        }
+
+        fn read(&self, block: u8, address: u16) -> u8 {
+            if block == COMMON_BLOCK && address == MR {
+                return if self.stuck { MR_RST } else { 0 };
+            }
+            if block % 4 == 1 && (address == SN_RX_RSR || address == SN_RX_RSR + 1) {
+                let socket = usize::from(block / 4);
+                let pending = self.received[socket].wrapping_sub(self.get_u16(block, SN_RX_RD));
+                return pending.to_be_bytes()[usize::from(address - SN_RX_RSR)];
+            }
+            self.get(block, address)
+        }
+
+        fn write(&mut self, block: u8, address: u16, value: u8) {
+            if block % 4 == 1 && address == SN_IR {
+                let interrupts = self.get(block, SN_IR) & !value;
+                self.set(block, SN_IR, interrupts);
+            } else if block % 4 == 1 && address == SN_CR {
+                if self.stuck {
+                    self.set(block, SN_CR, value);
+                } else {
+                    self.execute(block, value);
+                }
+            } else {
+                self.set(block, address, value);
+            }
+        }
+
+        fn execute(&mut self, block: u8, command: u8) {
+            match command {
+                SN_CR_OPEN => self.set(block, SN_SR, SN_SR_UDP),
+                SN_CR_CLOSE => self.set(block, SN_SR, SN_SR_CLOSED),
+                SN_CR_SEND_MAC => {
+                    let end = self.get_u16(block, SN_TX_WR);
+                    let mut datagram = Vec::new();
+                    while self.sent_pointer != end {
+                        datagram.push(self.get(block + 1, self.sent_pointer));
+                        self.sent_pointer = self.sent_pointer.wrapping_add(1);
+                    }
+                    let group = [self.get(block, SN_DIPR), self.get(block, SN_DIPR + 1), self.get(block, SN_DIPR + 2), self.get(block, SN_DIPR + 3)];
+                    self.sent.push((group, datagram));
+                    if !self.send_timeout {
+                        self.set(block, SN_IR, SN_IR_SEND_OK);
+                    }
+                },
+                _ => (),
+            }
+        }
+
+        /// Appends a datagram to the receive buffer of `socket`, behind the receive info
+        fn inject(&mut self, socket: u8, datagram: &[u8]) {
+            let mut info = [192, 168, 1, 7, 0, 0, 0, 0];
+            info[4..6].copy_from_slice(&UDP_PORT.to_be_bytes());
+            info[6..8].copy_from_slice(&(datagram.len() as u16).to_be_bytes());
+            for &byte in info.iter().chain(datagram.iter()) {
+                let pointer = self.received[usize::from(socket)];
+                self.set(receive_buffer_block(socket), pointer, byte);
+                self.received[usize::from(socket)] = pointer.wrapping_add(1);
+            }
+        }
+    }
+
+    #[derive(Clone)]
+    struct MockSpi(Rc<RefCell<Chip>>);
+
+    impl ErrorType for MockSpi {
+        type Error = Infallible;
+    }
+
+    impl SpiDevice for MockSpi {
+        fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Infallible> {
+            let (address, control) = match operations[0] {
+                Operation::Write(header) => (u16::from_be_bytes([header[0], header[1]]), header[2]),
+                _ => panic!("Transactions start with the address and control bytes"),
+            };
+            let block = control >> 3;
+            let mut chip = self.0.borrow_mut();
+            match operations[1] {
+                Operation::Read(ref mut data) => {
+                    assert_eq!(control & 0x04, 0);
+                    for (offset, byte) in data.iter_mut().enumerate() {
+                        *byte = chip.read(block, address.wrapping_add(offset as u16));
+                    }
+                },
+                Operation::Write(data) => {
+                    assert_eq!(control & 0x04, 0x04);
+                    for (offset, &byte) in data.iter().enumerate() {
+                        chip.write(block, address.wrapping_add(offset as u16), byte);
+                    }
+                },
+                _ => panic!("Unexpected SPI operation"),
+            }
+            Ok(())
+        }
+    }
+
+    const CONFIG: NetworkConfig = NetworkConfig {
+        mac: [0x02, 0x00, 0x00, 0x00, 0x00, 0x2a],
+        ip: [192, 168, 1, 42],
+        subnet: [255, 255, 255, 0],
+        gateway: [192, 168, 1, 1],
+    };
+
+    fn chip() -> Rc<RefCell<Chip>> {
+        let chip = Rc::new(RefCell::new(Chip::default()));
+        chip.borrow_mut().set(COMMON_BLOCK, VERSIONR, CHIP_VERSION);
+        chip.borrow_mut().set(socket_block(TRANSMIT_SOCKET), SN_TX_FSR, 0x08);
+        chip
+    }
+
+    fn header(data_specifier: DataSpecifier, destination_node_id: u16) -> UdpFrameHeader {
+        UdpFrameHeader {
+            priority: 4,
+            source_node_id: 42,
+            destination_node_id: destination_node_id,
+            data_specifier: data_specifier,
+            transfer_id: 3,
+            frame_index: 0,
+            end_of_transfer: true,
+            user_data: 0,
+        }
+    }
+
+    #[test]
+    fn multicast_mac_from_group() {
+        assert_eq!(multicast_mac([239, 0, 0x1d, 0x55]), [0x01, 0x00, 0x5e, 0x00, 0x1d, 0x55]);
+        assert_eq!(multicast_mac([239, 0x80, 1, 2]), [0x01, 0x00, 0x5e, 0x00, 0x01, 0x02]);
+    }
+
+    fn datagram(header: &UdpFrameHeader, payload: &[u8]) -> Vec<u8> {
+        let mut datagram = header.serialize().to_vec();
+        datagram.extend_from_slice(payload);
+        datagram
+    }
+
+    #[test]
+    fn configures_the_chip() {
+        let chip = chip();
+        W5500Transport::new(MockSpi(chip.clone()), CONFIG).unwrap();
+
+        let chip = chip.borrow();
+        let ip: Vec<u8> = (0..4).map(|i| chip.get(COMMON_BLOCK, SIPR + i)).collect();
+        assert_eq!(ip, CONFIG.ip);
+        assert_eq!(chip.get(socket_block(TRANSMIT_SOCKET), SN_SR), SN_SR_UDP);
+        assert_eq!(chip.get(socket_block(TRANSMIT_SOCKET), SN_MR), SN_MR_UDP);
+    }
+
+    #[test]
+    fn unsupported_chip() {
+        let chip = chip();
+        chip.borrow_mut().set(COMMON_BLOCK, VERSIONR, 0x03);
+        assert_eq!(W5500Transport::new(MockSpi(chip), CONFIG).err(), Some(W5500Error::UnsupportedChip(0x03)));
+    }
+
+    #[test]
+    fn unresponsive_chip() {
+        let chip = chip();
+        chip.borrow_mut().stuck = true;
+        assert_eq!(W5500Transport::new(MockSpi(chip.clone()), CONFIG).err(), Some(W5500Error::Unresponsive));
+
+        // The commands don't complete either
+        chip.borrow_mut().stuck = false;
+        let transport = W5500Transport::new(MockSpi(chip.clone()), CONFIG).unwrap();
+        chip.borrow_mut().stuck = true;
+        assert_eq!(transport.send(&header(DataSpecifier::Message(7509), UNSET_NODE_ID), &[1, 2, 3]), Err(W5500Error::Unresponsive));
+    }
+
+    #[test]
+    fn send_messages_and_services() {
+        let chip = chip();
+        let transport = W5500Transport::new(MockSpi(chip.clone()), CONFIG).unwrap();
+
+        let message = header(DataSpecifier::Message(7509), UNSET_NODE_ID);
+        transport.send(&message, &[1, 2, 3]).unwrap();
+        let request = header(DataSpecifier::Request(430), 7);
+        transport.send(&request, &[4, 5]).unwrap();
+
+        let chip = chip.borrow();
+        assert_eq!(chip.sent, vec![
+            ([239, 0, 29, 85], datagram(&message, &[1, 2, 3])),
+            ([239, 1, 0, 7], datagram(&request, &[4, 5])),
+        ]);
+        assert_eq!(chip.get(socket_block(TRANSMIT_SOCKET), SN_IR), 0);
+    }
+
+    #[test]
+    fn send_errors() {
+        let chip = chip();
+        let transport = W5500Transport::new(MockSpi(chip.clone()), CONFIG).unwrap();
+
+        assert_eq!(transport.send(&header(DataSpecifier::Request(430), UNSET_NODE_ID), &[]), Err(W5500Error::InvalidDestination));
+        assert_eq!(transport.send(&header(DataSpecifier::Message(0x2000), UNSET_NODE_ID), &[]), Err(W5500Error::InvalidDestination));
+        assert_eq!(transport.send(&header(DataSpecifier::Message(7509), UNSET_NODE_ID), &[0; 2048]), Err(W5500Error::BufferExhausted));
+        assert!(chip.borrow().sent.is_empty());
+
+        chip.borrow_mut().send_timeout = true;
+        assert_eq!(transport.send(&header(DataSpecifier::Message(7509), UNSET_NODE_ID), &[]), Err(W5500Error::Unresponsive));
+        chip.borrow_mut().set(socket_block(TRANSMIT_SOCKET), SN_IR, SN_IR_TIMEOUT);
+        assert_eq!(transport.send(&header(DataSpecifier::Message(7509), UNSET_NODE_ID), &[]), Err(W5500Error::Timeout));
+    }
+
+    #[test]
+    fn receive_on_subscribed_subjects() {
+        let chip = chip();
+        let transport = W5500Transport::new(MockSpi(chip.clone()), CONFIG).unwrap();
+        transport.subscribe(7509).unwrap();
+        assert_eq!(chip.borrow().get(socket_block(1), SN_SR), SN_SR_UDP);
+        assert_eq!(chip.borrow().get(socket_block(1), SN_DIPR + 3), 85);
+
+        let mut buffer = [0u8; 64];
+        assert!(transport.receive(&mut buffer).is_none());
+
+        let message = header(DataSpecifier::Message(7509), UNSET_NODE_ID);
+        chip.borrow_mut().inject(1, &datagram(&message, &[1, 2, 3]));
+        chip.borrow_mut().inject(1, &datagram(&message, &[4]));
+        assert_eq!(transport.receive(&mut buffer), Some(Ok((message, &[1, 2, 3][..]))));
+        assert_eq!(transport.receive(&mut buffer), Some(Ok((message, &[4][..]))));
+        assert!(transport.receive(&mut buffer).is_none());
+
+        transport.unsubscribe(7509).unwrap();
+        assert_eq!(chip.borrow().get(socket_block(1), SN_SR), SN_SR_CLOSED);
+    }
+
+    #[test]
+    fn receive_services_to_the_node() {
+        let chip = chip();
+        let transport = W5500Transport::new(MockSpi(chip.clone()), CONFIG).unwrap();
+        transport.set_node_id(Some(42)).unwrap();
+        assert_eq!(chip.borrow().get(socket_block(1), SN_SR), SN_SR_UDP);
+        assert_eq!(chip.borrow().get(socket_block(1), SN_DIPR + 1), 1);
+        assert_eq!(chip.borrow().get(socket_block(1), SN_DIPR + 3), 42);
+
+        let request = header(DataSpecifier::Request(430), 42);
+        chip.borrow_mut().inject(1, &datagram(&request, &[7]));
+        let mut buffer = [0u8; 64];
+        assert_eq!(transport.receive(&mut buffer), Some(Ok((request, &[7][..]))));
+
+        // Changing the node ID moves the socket to the group of the new node ID
+        transport.set_node_id(Some(43)).unwrap();
+        assert_eq!(chip.borrow().get(socket_block(1), SN_DIPR + 3), 43);
+        assert_eq!(chip.borrow().get(socket_block(2), SN_SR), SN_SR_CLOSED);
+
+        transport.set_node_id(None).unwrap();
+        assert_eq!(chip.borrow().get(socket_block(1), SN_SR), SN_SR_CLOSED);
+        assert_eq!(transport.set_node_id(Some(UNSET_NODE_ID)), Err(W5500Error::InvalidDestination));
+    }
+}
@@ -0,0 +1,158 @@
+//! Automatic bit rate detection
+//!
+//! Field configurable nodes should be able to determine the bit rate of the bus they're connected to
+//! without disturbing it. This is done by listening in silent (listen-only) mode at each candidate bit rate
+//! until a valid frame is received.
+
+use transfer::TransferInterface;
+use transfer::IOError;
+
+/// The bit rates recommended by the Uavcan specification, in order of preference.
+pub const STANDARD_BIT_RATES: [u32; 4] = [1_000_000, 500_000, 250_000, 125_000];
+
+/// Interfaces that can change bit rate at runtime.
+///
+/// Silent mode is entered and left through `TransferInterface::set_listen_only`, which the interface must support.
+/// While listening only, the controller must only pass frames that were received without errors to `receive`.
+pub trait BitRateDetection: TransferInterface {
+    /// Reconfigure the controller to operate at `bit_rate` bits per second.
+    fn set_bit_rate(&self, bit_rate: u32) -> Result<(), IOError>;
+}
+
+/// Detects the bit rate of the bus by listening at each of the `candidates` in turn.
+///
+/// The interface is put in listen-only mode before probing starts, `Err(IOError::Other)` is returned if it doesn't support
+/// listen-only mode as probing would then disturb the bus. For each candidate,
+/// `listen(bit_rate)` is called repeatedly and the interface is polled for frames for as long as it returns true.
+/// This lets the caller decide how long to listen for (and how to wait) at each bit rate.
+///
+/// When a valid frame is received the interface is taken out of listen-only mode, left configured at the detected bit rate and
+/// `Ok(Some(bit_rate))` is returned. If no frame was received at any of the candidates the interface is left in listen-only mode
+/// and `Ok(None)` is returned, the procedure can then be repeated.
+pub fn detect_bit_rate<I, L>(interface: &I, candidates: &[u32], mut listen: L) -> Result<Option<u32>, IOError>
+    where I: BitRateDetection + ?Sized,
+          L: FnMut(u32) -> bool,
+{
+    if !interface.set_listen_only(true) {
+        return Err(IOError::Other);
+    }
+
+    for &bit_rate in candidates {
+        // Frames received at the previous bit rate must not be taken as evidence of this one.
+        while interface.receive().is_some() {}
+        interface.set_bit_rate(bit_rate)?;
+
+        while listen(bit_rate) {
+            if interface.receive().is_some() {
+                interface.set_listen_only(false);
+                return Ok(Some(bit_rate));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use lib::core::cell::Cell;
+
+    use tests::CanFrame;
+
+    use transfer::TransferFrame;
+    use transfer::TransferFrameID;
+    use transfer::TransferInterface;
+    use transfer::IOError;
+
+    use bit_rate::*;
+
+    struct ProbeInterface {
+        bus_bit_rate: u32,
+        bit_rate: Cell<u32>,
+        silent: Cell<bool>,
+        supports_listen_only: bool,
+    }
+
+    impl TransferInterface for ProbeInterface {
+        type Frame = CanFrame;
+
+        fn transmit(&self, _frame: &CanFrame) -> Result<(), IOError> {
+            assert!(!self.silent.get());
+            Ok(())
+        }
+
+        fn receive(&self) -> Option<CanFrame> {
+            if self.bit_rate.get() == self.bus_bit_rate {
+                Some(CanFrame::new(TransferFrameID::new(0)))
+            } else {
+                None
+            }
+        }
+
+        fn set_listen_only(&self, listen_only: bool) -> bool {
+            if self.supports_listen_only {
+                self.silent.set(listen_only);
+            }
+            self.supports_listen_only
+        }
+    }
+
+    impl BitRateDetection for ProbeInterface {
+        fn set_bit_rate(&self, bit_rate: u32) -> Result<(), IOError> {
+            self.bit_rate.set(bit_rate);
+            Ok(())
+        }
+    }
+
+    fn probe_interface(bus_bit_rate: u32) -> ProbeInterface {
+        ProbeInterface {
+            bus_bit_rate,
+            bit_rate: Cell::new(0),
+            silent: Cell::new(false),
+            supports_listen_only: true,
+        }
+    }
+
+    #[test]
+    fn detects_bus_bit_rate() {
+        let interface = probe_interface(250_000);
+        let mut polls = 0;
+
+        let detected = detect_bit_rate(&interface, &STANDARD_BIT_RATES, |_| {
+            polls += 1;
+            polls % 10 != 0
+        });
+
+        assert_eq!(detected.ok(), Some(Some(250_000)));
+        assert_eq!(interface.bit_rate.get(), 250_000);
+        assert!(!interface.silent.get());
+    }
+
+    #[test]
+    fn stays_silent_when_nothing_is_detected() {
+        let interface = probe_interface(50_000);
+        let mut probed = [0; 4];
+        let mut i = 0;
+
+        let detected = detect_bit_rate(&interface, &STANDARD_BIT_RATES, |bit_rate| {
+            probed[i] = bit_rate;
+            i += 1;
+            false
+        });
+
+        assert_eq!(detected.ok(), Some(None));
+        assert_eq!(probed, STANDARD_BIT_RATES);
+        assert!(interface.silent.get());
+    }
+
+    #[test]
+    fn requires_listen_only() {
+        let mut interface = probe_interface(250_000);
+        interface.supports_listen_only = false;
+
+        assert_eq!(detect_bit_rate(&interface, &STANDARD_BIT_RATES, |_| true), Err(IOError::Other));
+        assert_eq!(interface.bit_rate.get(), 0);
+    }
+}
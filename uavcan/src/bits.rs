@@ -0,0 +1,269 @@
+//! Bit cursors for custom serialization
+//!
+//! `BitWriter` and `BitReader` write and read bits in the same order as the rest of the crate (UAVCAN v0):
+//! the bits of a byte are used from the most significant one, and a value spanning several bytes is split with its lowest bits first.
+//! They are meant for encodings the derive macro can't express. Since `write` and `read` go through the `Serializable`
+//! implementation of a type, custom fields can be mixed with the derived and generated types.
+//!
+//! ## Example
+//! ```
+//! use uavcan::bits::*;
+//! use uavcan::types::*;
+//!
+//! let mut data = [0u8; 4];
+//! let byte_length = {
+//!     let mut writer = BitWriter::new(&mut data);
+//!     writer.write_bits(3, 0b101).unwrap();
+//!     writer.write(&u13::new(0x1234), false).unwrap();
+//!     writer.write_bool(true).unwrap();
+//!     writer.byte_length()
+//! };
+//! assert_eq!(byte_length, 3);
+//!
+//! let mut reader = BitReader::new(&data[..byte_length]);
+//! assert_eq!(reader.read_bits(3), 0b101);
+//! assert_eq!(reader.read::<u13>(false), u13::new(0x1234));
+//! assert!(reader.read_bool());
+//!
+//! // Bits past the end of the data read as zero
+//! assert_eq!(reader.read_bits(16), 0);
+//! ```
+
+use lib::core::cmp;
+use lib::core::mem;
+
+use Serializable;
+
+use serializer::{
+    SerializationBuffer,
+    SerializationResult,
+};
+
+use deserializer::{
+    DeserializationBuffer,
+    DeserializationResult,
+};
+
+/// The error when the bits don't fit the buffer of a `BitWriter`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BufferFull;
+
+/// Writes bits to a byte buffer
+pub struct BitWriter<'a> {
+    buffer: SerializationBuffer<'a>,
+}
+
+impl<'a> BitWriter<'a> {
+    /// Starts writing at the beginning of `data`, which is zeroed
+    pub fn new(data: &'a mut [u8]) -> Self {
+        for byte in data.iter_mut() {
+            *byte = 0;
+        }
+        BitWriter{buffer: SerializationBuffer::with_empty_buffer(data)}
+    }
+
+    /// Writes the lowest `bit_length` bits of `bits`, nothing is written if they don't fit
+    pub fn write_bits(&mut self, bit_length: usize, bits: u64) -> Result<(), BufferFull> {
+        assert!(bit_length <= 64);
+        if self.buffer.bits_remaining() < bit_length {
+            return Err(BufferFull);
+        }
+        self.buffer.push_bits(bit_length, bits);
+        Ok(())
+    }
+
+    pub fn write_bool(&mut self, value: bool) -> Result<(), BufferFull> {
+        self.write_bits(1, value as u64)
+    }
+
+    /// Writes `value` like the field of a structure, `last_field` selects the tail array optimization
+    ///
+    /// The bits of `value` that fit are written even if the whole value doesn't.
+    pub fn write<T: Serializable>(&mut self, value: &T, last_field: bool) -> Result<(), BufferFull> {
+        let mut field = 0;
+        let mut bit = 0;
+        match value.serialize(&mut field, &mut bit, last_field, &mut self.buffer) {
+            SerializationResult::Finished => Ok(()),
+            SerializationResult::BufferFull => Err(BufferFull),
+        }
+    }
+
+    /// Writes zero bits up to the next byte boundary
+    pub fn pad_to_byte(&mut self) -> Result<(), BufferFull> {
+        let padding = (8 - self.bit_length() % 8) % 8;
+        self.write_bits(padding, 0)
+    }
+
+    /// The number of bits written
+    pub fn bit_length(&self) -> usize {
+        self.buffer.bit_length()
+    }
+
+    /// The number of bytes holding the bits written, the unused bits of the last byte are zero
+    pub fn byte_length(&self) -> usize {
+        (self.bit_length() + 7) / 8
+    }
+}
+
+/// Reads bits from a byte slice, the bits past the end of the slice read as zero
+///
+/// Reading past the end is how the implicit zero extension of truncated payloads works.
+pub struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    /// Starts reading at the beginning of `data`
+    pub fn new(data: &'a [u8]) -> Self {
+        BitReader{data: data, position: 0}
+    }
+
+    /// Reads `bit_length` bits into the lowest bits of the result
+    pub fn read_bits(&mut self, bit_length: usize) -> u64 {
+        assert!(bit_length <= 64);
+        let mut value = 0u64;
+        let mut shift = 0;
+        while shift < bit_length && self.bits_remaining() > 0 {
+            let offset = self.position % 8;
+            let chunk = cmp::min(8 - offset, bit_length - shift);
+            let bits = (u16::from(self.data[self.position / 8]) >> (8 - offset - chunk)) & ((1 << chunk) - 1);
+            value |= u64::from(bits) << shift;
+            shift += chunk;
+            self.position += chunk;
+        }
+        self.position += bit_length - shift;
+        value
+    }
+
+    pub fn read_bool(&mut self) -> bool {
+        self.read_bits(1) == 1
+    }
+
+    /// Reads a value like the field of a structure, `last_field` selects the tail array optimization
+    ///
+    /// Like the `Deserializer`, the value is deserialized into a zeroed `T`.
+    /// A tail array takes the rest of the data. Like `read_bits`, the position advances past the end
+    /// of the data by the bits that were read as zero.
+    pub fn read<T: Serializable>(&mut self, last_field: bool) -> T {
+        let start = self.position;
+        let mut value: T = unsafe { mem::zeroed() };
+        let mut field = 0;
+        let mut bit = 0;
+
+        // The data is fed through a scratch buffer since the buffers of the crate take mutable data
+        while self.position < self.data.len() * 8 {
+            let start = self.position / 8;
+            let length = cmp::min(8, self.data.len() - start);
+            let mut scratch = [0u8; 8];
+            scratch[..length].copy_from_slice(&self.data[start..start + length]);
+
+            let mut buffer = DeserializationBuffer::with_full_buffer(&mut scratch[..length]);
+            buffer.skip_bits(self.position % 8);
+            let available = buffer.bit_length();
+            let result = value.deserialize(&mut field, &mut bit, last_field, &mut buffer);
+            self.position += available - buffer.bit_length();
+            if result == DeserializationResult::Finished {
+                return value;
+            }
+        }
+
+        let mut zero_extension = DeserializationBuffer::with_zero_extension();
+        value.deserialize(&mut field, &mut bit, last_field, &mut zero_extension);
+        self.position = cmp::max(self.position, start + encoded_bit_length(&value, last_field));
+        value
+    }
+
+    /// Skips `bit_length` bits
+    pub fn skip_bits(&mut self, bit_length: usize) {
+        self.position += bit_length;
+    }
+
+    /// The number of bits read or skipped
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The number of bits left in the data
+    pub fn bits_remaining(&self) -> usize {
+        (self.data.len() * 8).saturating_sub(self.position)
+    }
+}
+
+/// The number of bits `value` takes when serialized
+fn encoded_bit_length<T: Serializable>(value: &T, last_field: bool) -> usize {
+    let mut field = 0;
+    let mut bit = 0;
+    let mut bit_length = 0;
+    loop {
+        let mut scratch = [0u8; 8];
+        let mut buffer = SerializationBuffer::with_empty_buffer(&mut scratch);
+        let result = value.serialize(&mut field, &mut bit, last_field, &mut buffer);
+        bit_length += buffer.bit_length();
+        if result == SerializationResult::Finished {
+            return bit_length;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use *;
+    use bits::*;
+    use types::*;
+    use serializer::Serializer;
+
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    struct Reading {
+        sensor: u5,
+        value: f16,
+        text: Dynamic<[u8; 8]>,
+    }
+
+    #[test]
+    fn matches_the_serializer() {
+        let reading = Reading{sensor: u5::new(17), value: f16::from_f32(-2.5), text: Dynamic::<[u8; 8]>::with_data(b"abc")};
+
+        let mut expected = [0u8; 16];
+        let length = {
+            let mut serializer = Serializer::from_structure(reading.clone());
+            let mut buffer = SerializationBuffer::with_empty_buffer(&mut expected);
+            serializer.serialize(&mut buffer);
+            (buffer.bit_length() + 7) / 8
+        };
+
+        let mut data = [0xffu8; 16];
+        {
+            let mut writer = BitWriter::new(&mut data);
+            writer.write_bits(5, 17).unwrap();
+            writer.write(&reading.value, false).unwrap();
+            writer.write(&reading.text, true).unwrap();
+            assert_eq!(writer.byte_length(), length);
+        }
+        assert_eq!(data, expected);
+
+        let mut reader = BitReader::new(&data[..length]);
+        assert_eq!(reader.read::<Reading>(true), reading);
+        assert_eq!(reader.bits_remaining(), 0);
+    }
+
+    #[test]
+    fn buffer_full() {
+        let mut data = [0u8; 2];
+        let mut writer = BitWriter::new(&mut data);
+        writer.write_bits(7, 0x7f).unwrap();
+        assert_eq!(writer.write(&0x1234u16, false), Err(BufferFull));
+        assert_eq!(writer.pad_to_byte(), Ok(()));
+        assert_eq!(writer.write_bits(9, 0), Err(BufferFull));
+        assert_eq!(writer.bit_length(), 16);
+    }
+
+    #[test]
+    fn zero_extension() {
+        let mut reader = BitReader::new(&[0xab]);
+        reader.skip_bits(4);
+        assert_eq!(reader.read::<u16>(false), 0x000b);
+        assert_eq!(reader.position(), 4 + 16);
+    }
+}
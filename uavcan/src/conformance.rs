@@ -0,0 +1,275 @@
+//! Wire conformance test vectors
+//!
+//! Each vector cites where its bytes come from:
+//!
+//! - [v0 DSDL]: the UAVCAN v0 specification, chapter "Data structure description language", for the bit layout:
+//!   fields are packed without padding, the bits of a byte are used from the most significant one,
+//!   multi-byte values are little-endian, a dynamic array is prefixed by its length in the fewest bits holding its maximum length
+//!   unless it is a tail array of elements of at least 8 bits, and a union is prefixed by the smallest tag holding the index of its last field.
+//! - [v0 CAN]: the UAVCAN v0 specification, chapter "CAN bus transport layer", for the CAN ID, the tail byte
+//!   and the transfer CRC (CRC-16-CCITT-FALSE, check value 0x29B1) leading a multi frame transfer.
+//! - [v0 signatures]: the same specification, section "Data type signature" (CRC-64-WE, check value 0x62EC59E3F1A4F00A).
+//! - [DSDL]: the definitions `uavcan/protocol/341.NodeStatus.uavcan` and `uavcan/protocol/debug/16383.LogMessage.uavcan`
+//!   of the public v0 DSDL repository, and their signatures as published in the generated DroneCAN headers.
+//! - [IEEE 754]: the binary16, binary32 and binary64 interchange formats.
+//!
+//! The vectors only place values that straddle a byte boundary on byte aligned offsets.
+//! A value that starts inside a byte and continues in the next one is split lowest bits first by this crate
+//! (see the tests of the serializer). No reference vector for that case could be checked against it, so none is included.
+//!
+//! Every vector is checked against the serializer and deserializer, the transfer vectors against the frame generator and reassembler.
+
+use lib::core::f32::consts::PI;
+use lib::core::fmt::Debug;
+
+use *;
+use types::*;
+use serializer::Serializer;
+use tests::CanFrame;
+use crc::TransferCRC;
+use transfer::TransferID;
+use frame_assembler::*;
+use frame_disassembler::*;
+use signature::{
+    dsdl_signature,
+    Signature,
+};
+
+/// uavcan.protocol.NodeStatus
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[uavcan(signature = 0x0f0868d0c1a7c6f1)]
+struct NodeStatus {
+    uptime_sec: u32,
+    health: u2,
+    mode: u3,
+    sub_mode: u3,
+    vendor_specific_status_code: u16,
+}
+
+impl Message for NodeStatus {
+    const TYPE_ID: Option<u16> = Some(341);
+}
+
+/// uavcan.protocol.debug.LogLevel
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+struct LogLevel {
+    value: u3,
+}
+
+/// uavcan.protocol.debug.LogMessage
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[uavcan(signature = 0xd654a48e0c049d75)]
+struct LogMessage {
+    level: LogLevel,
+    source: Dynamic<[u8; 31]>,
+    text: Dynamic<[u8; 90]>,
+}
+
+impl Message for LogMessage {
+    const TYPE_ID: Option<u16> = Some(16383);
+}
+
+/// uavcan.protocol.RestartNode request
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[uavcan(id = 5, response = "RestartNodeResponse", signature = 0x569e05394a3017f0)]
+struct RestartNodeRequest {
+    magic_number: u40,
+}
+
+/// uavcan.protocol.RestartNode response
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[uavcan(request = "RestartNodeRequest", signature = 0x569e05394a3017f0)]
+struct RestartNodeResponse {
+    ok: bool,
+}
+
+/// `uint6` followed by `uint8[<=3]`, the 2 bit length completes the first byte
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+struct Prefixed {
+    header: u6,
+    bytes: Dynamic<[u8; 3]>,
+    trailer: u8,
+}
+
+/// A union of three `uint6` fields, the 2 bit tag completes the byte
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+enum Choice {
+    First(u6),
+    Second(u6),
+    Third(u6),
+}
+
+/// Checks that `value` serializes into `expected`, and `expected` deserializes into `value`
+fn check_payload<T: Struct + Clone + PartialEq + Debug>(value: T, expected: &[u8]) {
+    let mut data = [0u8; 64];
+    let length = {
+        let mut serializer = Serializer::from_structure(value.clone());
+        let mut buffer = SerializationBuffer::with_empty_buffer(&mut data);
+        assert_eq!(serializer.serialize(&mut buffer), SerializationResult::Finished);
+        (buffer.bit_length() + 7) / 8
+    };
+    assert_eq!(&data[..length], expected);
+
+    let mut deserializer = Deserializer::<T>::new();
+    deserializer.deserialize_chunk(expected);
+    assert_eq!(deserializer.into_structure(), Ok(value));
+}
+
+/// Checks that `frame` is split into `expected`, and `expected` is reassembled into `frame`
+fn check_transfer<T: Struct + Clone + PartialEq + Debug>(frame: Frame<T>, transfer_id: TransferID, expected: &[CanFrame]) {
+    let (id, body) = frame.into_parts();
+    let mut generator = FrameDisassembler::from_uavcan_frame(Frame::from_parts(id, body.clone()), transfer_id);
+    for expected_frame in expected {
+        assert_eq!(generator.next_transfer_frame::<CanFrame>().as_ref(), Some(expected_frame));
+    }
+    assert_eq!(generator.next_transfer_frame::<CanFrame>(), None);
+
+    let mut assembler = FrameAssembler::<T>::new();
+    for (index, expected_frame) in expected.iter().enumerate() {
+        let result = if index == expected.len() - 1 { AssemblerResult::Finished } else { AssemblerResult::Ok };
+        assert_eq!(assembler.add_transfer_frame(expected_frame.clone()), Ok(result));
+    }
+    assert_eq!(assembler.build(), Ok(Frame::from_parts(id, body)));
+}
+
+#[test]
+fn node_status_payload() {
+    // [DSDL] uint32 uptime_sec, uint2 health, uint3 mode, uint3 sub_mode, uint16 vendor_specific_status_code
+    // [v0 DSDL] health 2, mode 3 and sub_mode 4 share the fifth byte as 10 011 100
+    check_payload(NodeStatus{
+        uptime_sec: 1,
+        health: u2::new(2),
+        mode: u3::new(3),
+        sub_mode: u3::new(4),
+        vendor_specific_status_code: 5,
+    }, &[0x01, 0x00, 0x00, 0x00, 0x9c, 0x05, 0x00]);
+}
+
+#[test]
+fn floats() {
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    struct Floats {
+        half: f16,
+        single: f32,
+        double: f64,
+    }
+
+    // [IEEE 754] -2.5 is 0xc100 in binary16, pi is 0x40490fdb in binary32 and -2.5 is 0xc004000000000000 in binary64
+    // [v0 DSDL] in little-endian bytes
+    check_payload(Floats{half: f16::from_f32(-2.5), single: PI, double: -2.5}, &[
+        0x00, 0xc1,
+        0xdb, 0x0f, 0x49, 0x40,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0xc0,
+    ]);
+}
+
+#[test]
+fn half_precision_encodings() {
+    // [IEEE 754] 1.0, the largest finite value, the smallest subnormal and infinity
+    assert_eq!(f16::from_f32(1.0).as_bits(), 0x3c00);
+    assert_eq!(f16::from_f32(65504.0).as_bits(), 0x7bff);
+    assert_eq!(f16::from_f32(5.960_464_5e-8).as_bits(), 0x0001);
+    assert_eq!(f16::from_f32(::lib::core::f32::INFINITY).as_bits(), 0x7c00);
+}
+
+#[test]
+fn dynamic_array_length_prefix() {
+    // [v0 DSDL] `uint8[<=3]` is not the last field, its length takes 2 bits: 101010 10, then the elements and the trailer
+    check_payload(Prefixed{
+        header: u6::new(0x2a),
+        bytes: Dynamic::<[u8; 3]>::with_data(&[0x01, 0x02]),
+        trailer: 0x03,
+    }, &[0xaa, 0x01, 0x02, 0x03]);
+}
+
+#[test]
+fn tail_array_optimization() {
+    // [DSDL] LogLevel level (uint3), uint8[<=31] source, uint8[<=90] text
+    // [v0 DSDL] the 5 bit length of source completes the first byte, text is a tail array without a length
+    check_payload(LogMessage{
+        level: LogLevel{value: u3::new(0)},
+        source: Dynamic::<[u8; 31]>::with_data(b"test source"),
+        text: Dynamic::<[u8; 90]>::with_data(b"test text"),
+    }, b"\x0btest sourcetest text");
+}
+
+#[test]
+fn union_tag() {
+    // [v0 DSDL] three fields take a 2 bit tag: 01 010101 selects the second field
+    check_payload(Choice::Second(u6::new(0x15)), &[0x55]);
+    check_payload(Choice::Third(u6::new(0x3f)), &[0xbf]);
+}
+
+#[test]
+fn checksums() {
+    // [v0 CAN] and [v0 signatures] check values, the CRC of the ASCII string "123456789"
+    let mut crc = TransferCRC::from(0xffff);
+    crc.add(b"123456789");
+    assert_eq!(u16::from(crc), 0x29b1);
+
+    let mut signature = Signature::new();
+    signature.add(b"123456789");
+    assert_eq!(signature.value(), 0x62ec_59e3_f1a4_f00a);
+}
+
+#[test]
+fn data_type_signatures() {
+    // [DSDL] the published signature of uavcan.protocol.NodeStatus, from its normalized definition [v0 signatures]
+    let node_status = "
+uint32 uptime_sec
+uint2 health
+uint3 mode
+uint3 sub_mode
+uint16 vendor_specific_status_code
+";
+    assert_eq!(dsdl_signature("uavcan.protocol.NodeStatus", node_status), 0x0f08_68d0_c1a7_c6f1);
+    assert_eq!(NodeStatus::DATA_TYPE_SIGNATURE, 0x0f08_68d0_c1a7_c6f1);
+}
+
+#[test]
+fn single_frame_transfer() {
+    let node_status = NodeStatus{
+        uptime_sec: 1,
+        health: u2::new(2),
+        mode: u3::new(3),
+        sub_mode: u3::new(4),
+        vendor_specific_status_code: 5,
+    };
+
+    // [v0 CAN] priority 16 in bits 28..24, data type 341 in bits 23..8, source node 42 in bits 6..0,
+    // the tail byte 0xc7 has the start and end of transfer bits set and transfer ID 7
+    check_transfer(Frame::from_message(node_status, 16, NodeID::new(42)), TransferID::new(7), &[
+        CanFrame::from_parts(0x1001_552a, &[0x01, 0x00, 0x00, 0x00, 0x9c, 0x05, 0x00, 0xc7]),
+    ]);
+}
+
+#[test]
+fn multi_frame_transfer() {
+    let log_message = LogMessage{
+        level: LogLevel{value: u3::new(0)},
+        source: Dynamic::<[u8; 31]>::with_data(b"test source"),
+        text: Dynamic::<[u8; 90]>::with_data(b"test text"),
+    };
+
+    // [v0 CAN] the transfer CRC 0x6383, seeded with the signature 0xd654a48e0c049d75 [DSDL] and computed over the payload
+    // of `tail_array_optimization`, leads the first frame. The tail bytes have the start of transfer bit in the first frame,
+    // the end of transfer bit in the last and a toggle bit starting at zero.
+    check_transfer(Frame::from_message(log_message, 0, NodeID::new(32)), TransferID::new(0), &[
+        CanFrame::from_parts(0x003f_ff20, &[0x83, 0x63, 0x0b, b't', b'e', b's', b't', 0x80]),
+        CanFrame::from_parts(0x003f_ff20, &[b' ', b's', b'o', b'u', b'r', b'c', b'e', 0x20]),
+        CanFrame::from_parts(0x003f_ff20, &[b't', b'e', b's', b't', b' ', b't', b'e', 0x00]),
+        CanFrame::from_parts(0x003f_ff20, &[b'x', b't', 0x60]),
+    ]);
+}
+
+#[test]
+fn service_transfers() {
+    // [v0 CAN] priority 16 in bits 28..24, service type 5 in bits 23..16, the request flag in bit 15,
+    // destination node in bits 14..8, the service flag in bit 7 and source node in bits 6..0
+    check_transfer(Frame::from_request(RestartNodeRequest{magic_number: u40::new(0xac_ce55_1b1e)}, 16, NodeID::new(10), NodeID::new(42)).unwrap(), TransferID::new(3), &[
+        CanFrame::from_parts(0x1005_aa8a, &[0x1e, 0x1b, 0x55, 0xce, 0xac, 0xc3]),
+    ]);
+    check_transfer(Frame::from_response(RestartNodeResponse{ok: true}, 16, NodeID::new(42), NodeID::new(10)).unwrap(), TransferID::new(3), &[
+        CanFrame::from_parts(0x1005_0aaa, &[0x80, 0xc3]),
+    ]);
+}
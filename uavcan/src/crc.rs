@@ -1,3 +1,143 @@
+//! Transfer CRC algorithms
+//!
+//! Multi-frame transfers are protected by a transfer CRC, the algorithm depends on the transport.
+//! CAN uses CRC-16-CCITT seeded with the data type signature (`TransferCRC`, see `signature` for computing the signature)
+//! at the start of multi frame transfers, while the UDP and serial transports use CRC-32C (`TransferCRC32C`) after the payload.
+//! `TransferChecksum` describes both the algorithm and the placement.
+//!
+//! The CRCs are calculated bit by bit unless a lookup table is enabled, which is faster on every frame at the cost of flash:
+//!
+//! | Feature            | Table size (CRC-16 + CRC-32C) | Bits per lookup |
+//! |--------------------|-------------------------------|-----------------|
+//! | `crc-table`        | 512 + 1024 bytes              | 8               |
+//! | `crc-nibble-table` | 32 + 64 bytes                 | 4               |
+//!
+//! `crc-table` takes precedence when both are enabled.
+
+/// Where the transfer CRC of a multi frame transfer is placed
+///
+/// Single frame transfers don't carry a transfer CRC with either placement.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ChecksumPlacement {
+    /// At the start of the first frame, before the payload (UAVCAN v0 on CAN)
+    FirstFrame,
+
+    /// After the payload and the padding of the last frame, which may spill it over into an extra frame (Cyphal/UDP and Cyphal/CAN)
+    PayloadEnd,
+}
+
+/// A transfer CRC algorithm and where it's placed in a transfer
+///
+/// The frame generation and reassembly is generic over this trait, which lets every transport use its own algorithm.
+pub trait TransferChecksum: Clone {
+    /// The number of bytes the CRC occupies in a transfer (at most 8)
+    const LENGTH: usize;
+
+    /// Where the CRC is placed in multi frame transfers
+    const PLACEMENT: ChecksumPlacement;
+
+    /// Starts the calculation of the CRC for a transfer of a data type with the given signature.
+    ///
+    /// Algorithms that are not seeded with the data type signature ignore it.
+    fn from_signature(data_type_signature: u64) -> Self;
+
+    /// Adds `data` to the CRC calculation
+    fn add(&mut self, data: &[u8]);
+
+    /// Writes the CRC, in the byte order used in transfers, into `buffer`
+    ///
+    /// ## Panics
+    /// Panics if `buffer.len() != Self::LENGTH`
+    fn write_bytes(&self, buffer: &mut [u8]);
+
+    /// Returns true if the CRC matches the `Self::LENGTH` bytes in `received`
+    fn matches(&self, received: &[u8]) -> bool {
+        let mut calculated = [0u8; 8];
+        self.write_bytes(&mut calculated[..Self::LENGTH]);
+        calculated[..Self::LENGTH] == received[..Self::LENGTH]
+    }
+}
+
+/// Holds back the last bytes of a transfer while it's reassembled, they are the CRC with `ChecksumPlacement::PayloadEnd`
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct TrailingChecksum {
+    held: [u8; 8],
+    held_length: usize,
+}
+
+impl TrailingChecksum {
+    /// Adds the next `data` of the transfer, passing on the bytes that can no longer be part of a CRC of `length` bytes to `f`
+    pub fn push<F: FnMut(&[u8])>(&mut self, length: usize, data: &[u8], mut f: F) {
+        if data.len() >= length {
+            f(&self.held[..self.held_length]);
+            f(&data[..data.len() - length]);
+            self.held[..length].copy_from_slice(&data[data.len() - length..]);
+            self.held_length = length;
+        } else {
+            let passed = (self.held_length + data.len()).saturating_sub(length);
+            f(&self.held[..passed]);
+            self.held.copy_within(passed..self.held_length, 0);
+            self.held_length -= passed;
+            self.held[self.held_length..self.held_length + data.len()].copy_from_slice(data);
+            self.held_length += data.len();
+        }
+    }
+
+    /// Returns the bytes held back, the received CRC once the transfer is complete
+    pub fn received(&self) -> &[u8] {
+        &self.held[..self.held_length]
+    }
+}
+
+#[cfg(feature="crc-table")]
+const CRC16_TABLE: [u16; 256] = crc16_table::<256, 8>();
+
+#[cfg(all(feature="crc-nibble-table", not(feature="crc-table")))]
+const CRC16_TABLE: [u16; 16] = crc16_table::<16, 4>();
+
+#[cfg(feature="crc-table")]
+const CRC32C_TABLE: [u32; 256] = crc32c_table::<256, 8>();
+
+#[cfg(all(feature="crc-nibble-table", not(feature="crc-table")))]
+const CRC32C_TABLE: [u32; 16] = crc32c_table::<16, 4>();
+
+/// The CRC-16-CCITT of every `BITS` bit value, shifted in from the top
+#[cfg(any(feature="crc-table", feature="crc-nibble-table"))]
+const fn crc16_table<const N: usize, const BITS: usize>() -> [u16; N] {
+    let mut table = [0u16; N];
+    let mut i = 0;
+    while i < N {
+        let mut value = (i as u16) << (16 - BITS);
+        let mut bit = 0;
+        while bit < BITS {
+            value = if (value & 0x8000) != 0 { (value << 1) ^ 0x1021 } else { value << 1 };
+            bit += 1;
+        }
+        table[i] = value;
+        i += 1;
+    }
+    table
+}
+
+/// The reflected CRC-32C of every `BITS` bit value
+#[cfg(any(feature="crc-table", feature="crc-nibble-table"))]
+const fn crc32c_table<const N: usize, const BITS: usize>() -> [u32; N] {
+    let mut table = [0u32; N];
+    let mut i = 0;
+    while i < N {
+        let mut value = i as u32;
+        let mut bit = 0;
+        while bit < BITS {
+            value = if (value & 0x1) != 0 { (value >> 1) ^ 0x82f6_3b78 } else { value >> 1 };
+            bit += 1;
+        }
+        table[i] = value;
+        i += 1;
+    }
+    table
+}
+
+/// The CRC-16-CCITT used as transfer CRC by the CAN transport
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct TransferCRC(u16);
 
@@ -10,6 +150,7 @@ impl TransferCRC {
         crc
     }
     
+    #[cfg(not(any(feature="crc-table", feature="crc-nibble-table")))]
     fn add_byte(&mut self, data: &u8) {
         match self {
             &mut TransferCRC(ref mut value) => {
@@ -26,6 +167,19 @@ impl TransferCRC {
             },
         }
     }
+
+    #[cfg(feature="crc-table")]
+    fn add_byte(&mut self, data: &u8) {
+        let TransferCRC(ref mut value) = *self;
+        *value = (*value << 8) ^ CRC16_TABLE[usize::from((*value >> 8) as u8 ^ *data)];
+    }
+
+    #[cfg(all(feature="crc-nibble-table", not(feature="crc-table")))]
+    fn add_byte(&mut self, data: &u8) {
+        let TransferCRC(ref mut value) = *self;
+        *value = (*value << 4) ^ CRC16_TABLE[usize::from(((*value >> 12) as u8 ^ (*data >> 4)) & 0x0f)];
+        *value = (*value << 4) ^ CRC16_TABLE[usize::from(((*value >> 12) as u8 ^ *data) & 0x0f)];
+    }
     
     pub fn add(&mut self, data: &[u8]) {
         for b in data {
@@ -47,10 +201,105 @@ impl From<u16> for TransferCRC {
     }
 }
 
+impl TransferChecksum for TransferCRC {
+    const LENGTH: usize = 2;
+    const PLACEMENT: ChecksumPlacement = ChecksumPlacement::FirstFrame;
+
+    fn from_signature(data_type_signature: u64) -> Self {
+        TransferCRC::from_signature(data_type_signature)
+    }
+
+    fn add(&mut self, data: &[u8]) {
+        TransferCRC::add(self, data)
+    }
+
+    fn write_bytes(&self, buffer: &mut [u8]) {
+        let value = u16::from(*self);
+        buffer.copy_from_slice(&value.to_le_bytes());
+    }
+}
+
+/// The CRC-32C (Castagnoli) used as transfer CRC by the Cyphal/UDP transport
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TransferCRC32C(u32);
+
+impl TransferCRC32C {
+    pub fn new() -> TransferCRC32C {
+        TransferCRC32C(0xffff_ffff)
+    }
+
+    #[cfg(not(any(feature="crc-table", feature="crc-nibble-table")))]
+    fn add_byte(&mut self, data: &u8) {
+        let TransferCRC32C(ref mut value) = *self;
+
+        *value ^= u32::from(*data);
+
+        for _bit in 0..8 {
+            if (*value & 0x1) != 0 {
+                *value = (*value >> 1) ^ 0x82f6_3b78;
+            } else {
+                *value >>= 1;
+            }
+        }
+    }
+
+    #[cfg(feature="crc-table")]
+    fn add_byte(&mut self, data: &u8) {
+        let TransferCRC32C(ref mut value) = *self;
+        *value = (*value >> 8) ^ CRC32C_TABLE[((*value ^ u32::from(*data)) & 0xff) as usize];
+    }
+
+    #[cfg(all(feature="crc-nibble-table", not(feature="crc-table")))]
+    fn add_byte(&mut self, data: &u8) {
+        let TransferCRC32C(ref mut value) = *self;
+        *value = (*value >> 4) ^ CRC32C_TABLE[((*value ^ u32::from(*data)) & 0x0f) as usize];
+        *value = (*value >> 4) ^ CRC32C_TABLE[((*value ^ u32::from(*data >> 4)) & 0x0f) as usize];
+    }
+
+    pub fn add(&mut self, data: &[u8]) {
+        for b in data {
+            self.add_byte(b);
+        }
+    }
+
+    /// Returns the final CRC value of the data added so far
+    pub fn value(&self) -> u32 {
+        let TransferCRC32C(value) = *self;
+        value ^ 0xffff_ffff
+    }
+}
+
+impl TransferChecksum for TransferCRC32C {
+    const LENGTH: usize = 4;
+    const PLACEMENT: ChecksumPlacement = ChecksumPlacement::PayloadEnd;
+
+    fn from_signature(_data_type_signature: u64) -> Self {
+        TransferCRC32C::new()
+    }
+
+    fn add(&mut self, data: &[u8]) {
+        TransferCRC32C::add(self, data)
+    }
+
+    fn write_bytes(&self, buffer: &mut [u8]) {
+        buffer.copy_from_slice(&self.value().to_le_bytes());
+    }
+}
+
+impl Default for TransferCRC32C {
+    fn default() -> Self {
+        TransferCRC32C::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use crc::TransferCRC;
+    use crc::TransferCRC32C;
+    use crc::TransferChecksum;
+    use crc::ChecksumPlacement;
+    use crc::TrailingChecksum;
     
     #[test]
     fn test_add_byte() {
@@ -66,9 +315,90 @@ mod tests {
         assert_eq!(u16::from(crc), 0x3b0a);
     }
 
+    #[test]
+    fn test_check_value() {
+        // The check value of CRC-16-CCITT-FALSE, which the specification uses for the transfer CRC
+        let mut crc = TransferCRC(0xffff);
+        crc.add(b"123456789");
+        assert_eq!(u16::from(crc), 0x29b1);
+    }
+
     #[test]
     fn test_from_signature() {
         let crc = TransferCRC::from_signature(0xd654a48e0c049d75);
         assert_eq!(u16::from(crc), 0x4570);
     }
+
+    #[test]
+    fn test_crc32c_check_value() {
+        let mut crc = TransferCRC32C::new();
+        crc.add(b"123456789");
+        assert_eq!(crc.value(), 0xe306_9283);
+    }
+
+    #[test]
+    fn test_write_bytes() {
+        let mut crc = TransferCRC::from(0xffff);
+        TransferCRC::add(&mut crc, &[1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        let mut buffer = [0u8; 2];
+        crc.write_bytes(&mut buffer);
+        assert_eq!(buffer, [0x0a, 0x3b]);
+        assert!(crc.matches(&[0x0a, 0x3b]));
+
+        let mut crc = TransferCRC32C::new();
+        TransferCRC32C::add(&mut crc, b"123456789");
+        let mut buffer = [0u8; 4];
+        crc.write_bytes(&mut buffer);
+        assert_eq!(buffer, [0x83, 0x92, 0x06, 0xe3]);
+    }
+
+    #[test]
+    fn test_crc32c_empty() {
+        assert_eq!(TransferCRC32C::new().value(), 0);
+    }
+
+    #[test]
+    fn test_crc32c_udp_vectors() {
+        // The CRC-32C test vectors of RFC 3720 (iSCSI), the same algorithm protects Cyphal/UDP transfers
+        let vectors: [([u8; 32], u32); 2] = [([0; 32], 0x8a91_36aa), ([0xff; 32], 0x62a8_ab43)];
+        for &(ref data, expected) in vectors.iter() {
+            let mut crc = TransferCRC32C::new();
+            crc.add(data);
+            assert_eq!(crc.value(), expected);
+        }
+
+        let mut increasing = [0u8; 32];
+        for (i, byte) in increasing.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let mut crc = TransferCRC32C::new();
+        crc.add(&increasing);
+        assert_eq!(crc.value(), 0x46dd_794e);
+
+        // Appended little endian after the payload, the CRC over the payload and the CRC is the residue
+        let mut crc = TransferCRC32C::new();
+        crc.add(b"123456789");
+        let mut appended = [0u8; 4];
+        crc.write_bytes(&mut appended);
+        crc.add(&appended);
+        assert_eq!(crc.value(), 0x4867_4bc7);
+
+        assert_eq!(<TransferCRC32C as TransferChecksum>::PLACEMENT, ChecksumPlacement::PayloadEnd);
+        assert_eq!(<TransferCRC as TransferChecksum>::PLACEMENT, ChecksumPlacement::FirstFrame);
+    }
+
+    #[test]
+    fn test_trailing_checksum() {
+        let mut trailing = TrailingChecksum::default();
+        let mut passed = [0u8; 16];
+        let mut passed_length = 0;
+        for data in [&[1u8, 2, 3, 4, 5][..], &[6], &[7, 8, 9], &[10, 11]].iter() {
+            trailing.push(4, data, |bytes| {
+                passed[passed_length..passed_length + bytes.len()].copy_from_slice(bytes);
+                passed_length += bytes.len();
+            });
+        }
+        assert_eq!(&passed[..passed_length], &[1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(trailing.received(), &[8, 9, 10, 11]);
+    }
 }
@@ -0,0 +1,159 @@
+//! The readable `Debug` representation of the field types of derived structures
+//!
+//! Structures deriving `UavcanStruct` with `#[uavcan(debug)]` get a `Debug` implementation
+//! formatting their fields by their decoded values: integers of arbitrary bit lengths as numbers,
+//! `f16` as `f32`, text as strings and static and dynamic arrays as lists.
+//! Fields with `#[uavcan(names(HEALTH_OK, HEALTH_WARNING))]` are followed by the name of the constant they are equal to.
+//!
+//! `DebugField` is only exposed so `Debug` can be derived.
+//! It is not intended for use outside the derive macro and
+//! must not be considered as a stable part of the API.
+
+pub use lib::core::fmt;
+
+use types::*;
+
+/// A field of a structure with `#[uavcan(debug)]`
+pub trait DebugField {
+    fn fmt_field(&self, f: &mut fmt::Formatter) -> fmt::Result;
+}
+
+/// Formats a field through `DebugField`, with the name of the constant it is equal to
+pub struct Field<'a, T: 'a + ?Sized>(pub &'a T, pub Option<&'static str>);
+
+impl<'a, T: DebugField + ?Sized> fmt::Debug for Field<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt_field(f)?;
+        match self.1 {
+            Some(name) => write!(f, " ({})", name),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: DebugField> DebugField for [T] {
+    fn fmt_field(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter().map(|element| Field(element, None))).finish()
+    }
+}
+
+macro_rules! impl_native {
+    ($($type:ident),*) => {$(
+        impl DebugField for $type {
+            fn fmt_field(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Debug::fmt(self, f)
+            }
+        }
+    )*};
+}
+
+impl_native!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64, bool);
+
+macro_rules! impl_ux {
+    {[$($type:ident),*], $underlying_type:ident} => {$(
+        impl DebugField for $type {
+            fn fmt_field(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Debug::fmt(&$underlying_type::from(*self), f)
+            }
+        }
+    )*};
+}
+
+impl_ux!([u2, u3, u4, u5, u6, u7], u8);
+impl_ux!([u9, u10, u11, u12, u13, u14, u15], u16);
+impl_ux!([u17, u18, u19, u20, u21, u22, u23, u24, u25, u26, u27, u28, u29, u30, u31], u32);
+impl_ux!([u33, u34, u35, u36, u37, u38, u39, u40, u41, u42, u43, u44, u45, u46, u47, u48,
+          u49, u50, u51, u52, u53, u54, u55, u56, u57, u58, u59, u60, u61, u62, u63], u64);
+
+impl_ux!([i2, i3, i4, i5, i6, i7], i8);
+impl_ux!([i9, i10, i11, i12, i13, i14, i15], i16);
+impl_ux!([i17, i18, i19, i20, i21, i22, i23, i24, i25, i26, i27, i28, i29, i30, i31], i32);
+impl_ux!([i33, i34, i35, i36, i37, i38, i39, i40, i41, i42, i43, i44, i45, i46, i47, i48,
+          i49, i50, i51, i52, i53, i54, i55, i56, i57, i58, i59, i60, i61, i62, i63], i64);
+
+impl<const BITS: usize> DebugField for Uint<BITS> {
+    fn fmt_field(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&u64::from(*self), f)
+    }
+}
+
+impl<const BITS: usize> DebugField for Int<BITS> {
+    fn fmt_field(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&i64::from(*self), f)
+    }
+}
+
+macro_rules! impl_vx {
+    ($($type:ident),*) => {$(
+        impl DebugField for $type {
+            fn fmt_field(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("void")
+            }
+        }
+    )*};
+}
+
+impl_vx!(void1, void2, void3, void4, void5, void6, void7, void8, void9, void10, void11, void12, void13, void14, void15, void16,
+         void17, void18, void19, void20, void21, void22, void23, void24, void25, void26, void27, void28, void29, void30, void31, void32,
+         void33, void34, void35, void36, void37, void38, void39, void40, void41, void42, void43, void44, void45, void46, void47, void48,
+         void49, void50, void51, void52, void53, void54, void55, void56, void57, void58, void59, void60, void61, void62, void63, void64);
+
+impl DebugField for f16 {
+    fn fmt_field(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_f32(), f)
+    }
+}
+
+impl<T: DebugField> DebugField for Truncated<T> {
+    fn fmt_field(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt_field(f)
+    }
+}
+
+impl<T: DebugField> DebugField for Delimited<T> {
+    fn fmt_field(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.value.fmt_field(f)
+    }
+}
+
+impl<T: DebugField> DebugField for Option<T> {
+    fn fmt_field(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Some(ref value) => f.debug_tuple("Some").field(&Field(value, None)).finish(),
+            None => f.write_str("None"),
+        }
+    }
+}
+
+impl<T: BoolArray> DebugField for BitArray<T> {
+    fn fmt_field(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: BoolArray> DebugField for DynamicBitArray<T> {
+    fn fmt_field(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: DebugField, S> DebugField for Scaled<T, S> {
+    fn fmt_field(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.raw.fmt_field(f)
+    }
+}
+
+impl<T: DynamicArray> DebugField for Utf8String<T> where Dynamic<T>: AsRef<[u8]> {
+    fn fmt_field(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.as_str() {
+            Ok(text) => fmt::Debug::fmt(text, f),
+            Err(_) => self.as_bytes().fmt_field(f),
+        }
+    }
+}
+
+impl<T: DebugField, const N: usize> DebugField for [T; N] {
+    fn fmt_field(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self[..].fmt_field(f)
+    }
+}
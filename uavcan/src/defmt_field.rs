@@ -0,0 +1,296 @@
+//! `defmt::Format` for the protocol types, and the conversion of the field types of derived structures
+//!
+//! The field types are formatted like their `Debug` representation: integers of arbitrary bit lengths as numbers,
+//! `f16` as `f32`, and static and dynamic arrays as lists.
+//!
+//! `DefmtField` is only exposed so `defmt::Format` can be derived.
+//! It is not intended for use outside the derive macro and
+//! must not be considered as a stable part of the API.
+
+use defmt;
+use defmt::{
+    Format,
+    Formatter,
+};
+
+use types::*;
+
+use transfer::{
+    FullTransferID,
+    TailByte,
+    TransferFrameID,
+    TransferID,
+};
+
+use node::NodeID;
+
+use session::{
+    Port,
+    ReceivedTransfer,
+    SessionError,
+    SessionKey,
+};
+
+use udp::HeaderError;
+
+use serial::DecodeError;
+
+/// A field of a structure with `defmt::Format` derived
+pub trait DefmtField {
+    fn format_field(&self, f: Formatter);
+}
+
+/// Formats `elements` as a list
+pub fn format_slice<T: DefmtField>(elements: &[T], f: Formatter) {
+    defmt::write!(f, "[");
+    for (index, element) in elements.iter().enumerate() {
+        if index != 0 {
+            defmt::write!(f, ", ");
+        }
+        element.format_field(f);
+    }
+    defmt::write!(f, "]");
+}
+
+/// Formats packed `bool` arrays as a list
+fn format_bits<I: Iterator<Item = bool>>(bits: I, f: Formatter) {
+    defmt::write!(f, "[");
+    for (index, value) in bits.enumerate() {
+        if index != 0 {
+            defmt::write!(f, ", ");
+        }
+        defmt::write!(f, "{=bool}", value);
+    }
+    defmt::write!(f, "]");
+}
+
+macro_rules! impl_native {
+    ($($type:ident),*) => {$(
+        impl DefmtField for $type {
+            fn format_field(&self, f: Formatter) {
+                defmt::write!(f, "{}", self);
+            }
+        }
+    )*};
+}
+
+impl_native!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64, bool);
+
+macro_rules! impl_ux {
+    {[$($type:ident),*], $underlying_type:ident} => {$(
+        impl DefmtField for $type {
+            fn format_field(&self, f: Formatter) {
+                defmt::write!(f, "{}", $underlying_type::from(*self));
+            }
+        }
+    )*};
+}
+
+impl_ux!([u2, u3, u4, u5, u6, u7], u8);
+impl_ux!([u9, u10, u11, u12, u13, u14, u15], u16);
+impl_ux!([u17, u18, u19, u20, u21, u22, u23, u24, u25, u26, u27, u28, u29, u30, u31], u32);
+impl_ux!([u33, u34, u35, u36, u37, u38, u39, u40, u41, u42, u43, u44, u45, u46, u47, u48,
+          u49, u50, u51, u52, u53, u54, u55, u56, u57, u58, u59, u60, u61, u62, u63], u64);
+
+impl_ux!([i2, i3, i4, i5, i6, i7], i8);
+impl_ux!([i9, i10, i11, i12, i13, i14, i15], i16);
+impl_ux!([i17, i18, i19, i20, i21, i22, i23, i24, i25, i26, i27, i28, i29, i30, i31], i32);
+impl_ux!([i33, i34, i35, i36, i37, i38, i39, i40, i41, i42, i43, i44, i45, i46, i47, i48,
+          i49, i50, i51, i52, i53, i54, i55, i56, i57, i58, i59, i60, i61, i62, i63], i64);
+
+impl<const BITS: usize> DefmtField for Uint<BITS> {
+    fn format_field(&self, f: Formatter) {
+        defmt::write!(f, "{}", u64::from(*self));
+    }
+}
+
+impl<const BITS: usize> DefmtField for Int<BITS> {
+    fn format_field(&self, f: Formatter) {
+        defmt::write!(f, "{}", i64::from(*self));
+    }
+}
+
+macro_rules! impl_vx {
+    ($($type:ident),*) => {$(
+        impl DefmtField for $type {
+            fn format_field(&self, f: Formatter) {
+                defmt::write!(f, "void");
+            }
+        }
+    )*};
+}
+
+impl_vx!(void1, void2, void3, void4, void5, void6, void7, void8, void9, void10, void11, void12, void13, void14, void15, void16,
+         void17, void18, void19, void20, void21, void22, void23, void24, void25, void26, void27, void28, void29, void30, void31, void32,
+         void33, void34, void35, void36, void37, void38, void39, void40, void41, void42, void43, void44, void45, void46, void47, void48,
+         void49, void50, void51, void52, void53, void54, void55, void56, void57, void58, void59, void60, void61, void62, void63, void64);
+
+impl DefmtField for f16 {
+    fn format_field(&self, f: Formatter) {
+        defmt::write!(f, "{}", self.to_f32());
+    }
+}
+
+impl<T: DefmtField> DefmtField for Truncated<T> {
+    fn format_field(&self, f: Formatter) {
+        self.0.format_field(f);
+    }
+}
+
+impl<T: DefmtField> DefmtField for Delimited<T> {
+    fn format_field(&self, f: Formatter) {
+        self.value.format_field(f);
+    }
+}
+
+impl<T: DefmtField> DefmtField for Option<T> {
+    fn format_field(&self, f: Formatter) {
+        match *self {
+            Some(ref value) => {
+                defmt::write!(f, "Some(");
+                value.format_field(f);
+                defmt::write!(f, ")");
+            },
+            None => defmt::write!(f, "None"),
+        }
+    }
+}
+
+impl<T: BoolArray> DefmtField for BitArray<T> {
+    fn format_field(&self, f: Formatter) {
+        format_bits(self.iter(), f);
+    }
+}
+
+impl<T: BoolArray> DefmtField for DynamicBitArray<T> {
+    fn format_field(&self, f: Formatter) {
+        format_bits(self.iter(), f);
+    }
+}
+
+impl<T: DefmtField, S> DefmtField for Scaled<T, S> {
+    fn format_field(&self, f: Formatter) {
+        self.raw.format_field(f);
+    }
+}
+
+impl<T: DynamicArray> DefmtField for Utf8String<T> where Dynamic<T>: AsRef<[u8]> {
+    fn format_field(&self, f: Formatter) {
+        match self.as_str() {
+            Ok(text) => defmt::write!(f, "{=str}", text),
+            Err(_) => format_slice(self.as_bytes(), f),
+        }
+    }
+}
+
+impl<T: DefmtField, const N: usize> DefmtField for [T; N] {
+    fn format_field(&self, f: Formatter) {
+        format_slice(&self[..], f);
+    }
+}
+
+impl Format for TransferFrameID {
+    fn format(&self, f: Formatter) {
+        defmt::write!(f, "TransferFrameID({=u32:#x})", u32::from(*self));
+    }
+}
+
+impl Format for TransferID {
+    fn format(&self, f: Formatter) {
+        defmt::write!(f, "TransferID({=u8})", u8::from(*self));
+    }
+}
+
+impl Format for FullTransferID {
+    fn format(&self, f: Formatter) {
+        defmt::write!(f, "FullTransferID {{ frame_id: {}, transfer_id: {} }}", self.frame_id, self.transfer_id);
+    }
+}
+
+impl Format for TailByte {
+    fn format(&self, f: Formatter) {
+        defmt::write!(
+            f,
+            "TailByte {{ start_of_transfer: {=bool}, end_of_transfer: {=bool}, toggle: {=bool}, transfer_id: {} }}",
+            self.start_of_transfer(),
+            self.end_of_transfer(),
+            self.toggle(),
+            self.transfer_id(),
+        );
+    }
+}
+
+impl Format for NodeID {
+    fn format(&self, f: Formatter) {
+        defmt::write!(f, "NodeID({=u8})", u8::from(*self));
+    }
+}
+
+impl Format for Port {
+    fn format(&self, f: Formatter) {
+        match *self {
+            Port::Message(id) => defmt::write!(f, "Message({=u16})", id),
+            Port::Request(id) => defmt::write!(f, "Request({=u8})", id),
+            Port::Response(id) => defmt::write!(f, "Response({=u8})", id),
+        }
+    }
+}
+
+impl Format for SessionKey {
+    fn format(&self, f: Formatter) {
+        defmt::write!(f, "SessionKey {{ port: {}, source: {=u8}, destination: {} }}", self.port(), self.source_node(), self.destination_node());
+    }
+}
+
+impl<'a> Format for ReceivedTransfer<'a> {
+    fn format(&self, f: Formatter) {
+        defmt::write!(
+            f,
+            "ReceivedTransfer {{ key: {}, anonymous: {=bool}, priority: {=u8}, transfer_id: {}, timestamp: {=u64}, payload: {=[u8]:x} }}",
+            self.key,
+            self.anonymous,
+            self.priority,
+            self.transfer_id,
+            self.timestamp,
+            self.payload,
+        );
+    }
+}
+
+impl Format for SessionError {
+    fn format(&self, f: Formatter) {
+        match *self {
+            SessionError::MalformedFrame => defmt::write!(f, "MalformedFrame"),
+            SessionError::UnexpectedFrame => defmt::write!(f, "UnexpectedFrame"),
+            SessionError::ToggleError => defmt::write!(f, "ToggleError"),
+            SessionError::Duplicate => defmt::write!(f, "Duplicate"),
+            SessionError::PayloadTooLarge => defmt::write!(f, "PayloadTooLarge"),
+            SessionError::OutOfMemory => defmt::write!(f, "OutOfMemory"),
+            SessionError::NotSubscribed => defmt::write!(f, "NotSubscribed"),
+            SessionError::TooManySubscriptions => defmt::write!(f, "TooManySubscriptions"),
+            SessionError::AnonymousMultiFrame => defmt::write!(f, "AnonymousMultiFrame"),
+            SessionError::CRCError => defmt::write!(f, "CRCError"),
+            SessionError::NotAddressed => defmt::write!(f, "NotAddressed"),
+        }
+    }
+}
+
+impl Format for HeaderError {
+    fn format(&self, f: Formatter) {
+        match *self {
+            HeaderError::TooShort => defmt::write!(f, "TooShort"),
+            HeaderError::UnsupportedVersion(version) => defmt::write!(f, "UnsupportedVersion({=u8})", version),
+            HeaderError::CRCError => defmt::write!(f, "CRCError"),
+        }
+    }
+}
+
+impl Format for DecodeError {
+    fn format(&self, f: Formatter) {
+        match *self {
+            DecodeError::Overflow => defmt::write!(f, "Overflow"),
+            DecodeError::InvalidEncoding => defmt::write!(f, "InvalidEncoding"),
+            DecodeError::Header(error) => defmt::write!(f, "Header({})", error),
+        }
+    }
+}
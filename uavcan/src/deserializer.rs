@@ -1,3 +1,4 @@
+use lib::core::cmp;
 use lib::core::mem;
 
 pub use serializer::SerializationBuffer as DeserializationBuffer;
@@ -14,10 +15,18 @@ pub enum DeserializationResult {
 
 
 
+/// Deserializes a structure from data arriving in one or more buffers
+///
+/// The position in the structure, down to the bit, is kept between calls, so a transfer can be decoded
+/// a frame (or a `session::StreamChunk`) at a time without reassembling the payload first.
+///
+/// Follows the implicit truncation and zero extension rules: the bits following a finished structure are ignored,
+/// and the bits missing from a structure that is not finished when the data ends read as zero.
 pub struct Deserializer<T: Struct> {
     structure: T,
     field_index: usize,
     bit_index: usize,
+    finished: bool,
 }
 
 impl<T: Struct> Deserializer<T> {
@@ -26,19 +35,116 @@ impl<T: Struct> Deserializer<T> {
         unsafe {
             structure = mem::zeroed();
         };            
-        Deserializer{structure: structure, field_index: 0, bit_index: 0}
+        Deserializer{structure: structure, field_index: 0, bit_index: 0, finished: false}
     }
 
     pub fn deserialize(&mut self, input: &mut [u8]) -> DeserializationResult {
+        if self.finished {
+            return DeserializationResult::Finished;
+        }
         let mut buffer = DeserializationBuffer::with_full_buffer(input);
-        self.structure.deserialize(&mut self.field_index, &mut self.bit_index, true, &mut buffer)
+        let result = self.structure.deserialize(&mut self.field_index, &mut self.bit_index, true, &mut buffer);
+        self.finished = result == DeserializationResult::Finished;
+        result
+    }
+
+    /// Deserializes the next part of the payload from `data`, which can be split at any byte
+    pub fn deserialize_chunk(&mut self, data: &[u8]) -> DeserializationResult {
+        let mut result = if self.finished {
+            DeserializationResult::Finished
+        } else {
+            DeserializationResult::BufferInsufficient
+        };
+        for chunk in data.chunks(8) {
+            let mut buffer = [0u8; 8];
+            buffer[..chunk.len()].copy_from_slice(chunk);
+            result = self.deserialize(&mut buffer[..chunk.len()]);
+            if result == DeserializationResult::Finished {
+                break;
+            }
+        }
+        result
+    }
+
+    /// Returns true when the structure is complete, the following data will be ignored
+    pub fn is_finished(&self) -> bool {
+        self.finished
     }
 
-    pub fn into_structure(self) -> Result<T, ()> {
+    /// Returns the structure, with the bits that were not received read as zero
+    ///
+    /// Fails if the missing bits can't be zero (a union tag without a variant).
+    pub fn into_structure(mut self) -> Result<T, ()> {
+        if !self.finished {
+            let mut buffer = DeserializationBuffer::with_zero_extension();
+            if self.structure.deserialize(&mut self.field_index, &mut self.bit_index, true, &mut buffer) != DeserializationResult::Finished {
+                return Err(());
+            }
+        }
         Ok(self.structure)    
     }
 }
 
+/// Borrows the bytes of a `uint8[]` field of `T` from a reassembled `payload`, instead of copying them into the structure
+///
+/// `field` is the first flattened field of the dynamic array, which is a tail array if `tail_array` is true.
+/// Returns `None` if the elements don't start at a byte boundary or the payload ends before the last of them.
+///
+/// This function is only exposed so the borrowing accessors can be derived.
+/// It is not intended for use outside the derive macro and
+/// must not be considered as a stable part of the API.
+#[doc(hidden)]
+pub fn borrow_byte_array<T: Struct>(payload: &[u8], field: usize, length_bits: usize, max_length: usize, tail_array: bool) -> Option<&[u8]> {
+    // The elements start at the first byte boundary where the array is entered: its length is read or, for a tail array,
+    // the fields before it are. They are not byte aligned if the array was entered a bit earlier.
+    let start = (0..payload.len() + 1).find(|&bytes| deserialize_prefix::<T>(payload, bytes * 8) > field)?;
+    if start > 0 && deserialize_prefix::<T>(payload, start * 8 - 1) > field {
+        return None;
+    }
+
+    let length = if tail_array {
+        cmp::min(payload.len() - start, max_length)
+    } else {
+        // The length ends at the byte boundary, in the byte before it or the two bytes before it
+        let first_byte = (start * 8 - length_bits) / 8;
+        let mut scratch = [0u8; 2];
+        scratch[..start - first_byte].copy_from_slice(&payload[first_byte..start]);
+        let mut buffer = DeserializationBuffer::with_full_buffer(&mut scratch[..start - first_byte]);
+        buffer.skip_bits((start * 8 - length_bits) % 8);
+        cmp::min(buffer.pop_bits(length_bits) as usize, max_length)
+    };
+
+    payload.get(start..start + length)
+}
+
+/// Deserializes the first `bit_length` bits of `payload` into a zeroed `T` and returns the flattened field it reached
+///
+/// The bytes are deserialized one at a time, like frames are received. A value is only split at byte boundaries,
+/// since the bits within a byte are not in the order they are deserialized in.
+fn deserialize_prefix<T: Struct>(payload: &[u8], bit_length: usize) -> usize {
+    let mut structure: T = unsafe { mem::zeroed() };
+    let mut flattened_field = 0;
+    let mut bit = 0;
+
+    // Fields without bits, like a tail array at the start of the structure, are entered without data
+    let mut empty = [];
+    structure.deserialize(&mut flattened_field, &mut bit, true, &mut DeserializationBuffer::with_full_buffer(&mut empty));
+
+    for (index, &byte) in payload.iter().enumerate().take((bit_length + 7) / 8) {
+        let mut scratch = [byte];
+        let mut buffer = DeserializationBuffer::with_full_buffer(&mut scratch);
+        let mut view = buffer.limited(cmp::min(8, bit_length - index * 8));
+        structure.deserialize(&mut flattened_field, &mut bit, true, &mut view);
+    }
+    flattened_field
+}
+
+impl<T: Struct> Default for Deserializer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -47,6 +153,7 @@ mod tests {
     
     use *;
     use deserializer::*;
+    use serializer::Serializer;
     use types::*;
     
     #[test]
@@ -130,6 +237,212 @@ mod tests {
         );
     }
 
+    #[test]
+    fn deserialize_empty_dynamic_array() {
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct TestMessage {
+            array: Dynamic<[u8; 3]>,
+            value: u8,
+        }
+
+        let message = TestMessage{
+            array: Dynamic::<[u8; 3]>::new(),
+            value: 0xa5,
+        };
+
+        let mut data = [0u8; 2];
+        {
+            let mut buffer = SerializationBuffer::with_empty_buffer(&mut data);
+            let mut serializer = Serializer::from_structure(message.clone());
+            assert_eq!(serializer.serialize(&mut buffer), SerializationResult::Finished);
+        }
+
+        let mut deserializer: Deserializer<TestMessage> = Deserializer::new();
+        assert_eq!(deserializer.deserialize(&mut data), DeserializationResult::Finished);
+        assert_eq!(deserializer.into_structure().unwrap(), message);
+    }
+
+    #[test]
+    fn union_default_is_zero_initialized() {
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        enum Inner {
+            Text(Dynamic<[u8; 7]>),
+            Value(u16),
+        }
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        enum Outer {
+            Nested(Inner),
+            Value(u8),
+            Flag(bool),
+        }
+
+        assert_eq!(Inner::default(), Inner::Text(Dynamic::<[u8; 7]>::new()));
+        assert_eq!(Outer::default(), Outer::Nested(Inner::Text(Dynamic::<[u8; 7]>::new())));
+    }
+
+    #[test]
+    fn implicit_truncation_and_zero_extension() {
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Message {
+            v1: u8,
+            v2: u16,
+            array: Dynamic<[u8; 4]>,
+            v3: u8,
+        }
+
+        // The bits of v2 that were not received, the missing array elements and v3 are zero
+        let mut deserializer: Deserializer<Message> = Deserializer::new();
+        assert_eq!(deserializer.deserialize(&mut [17, 0x34]), DeserializationResult::BufferInsufficient);
+        assert_eq!(deserializer.into_structure().unwrap(), Message{
+            v1: 17,
+            v2: 0x34,
+            array: Dynamic::<[u8; 4]>::new(),
+            v3: 0,
+        });
+
+        // The array length was received, but not all of its elements
+        let mut deserializer: Deserializer<Message> = Deserializer::new();
+        deserializer.deserialize(&mut [17, 0x34, 0x12, 0b011_00000 | 5, 0]);
+        assert_eq!(deserializer.into_structure().unwrap(), Message{
+            v1: 17,
+            v2: 0x1234,
+            array: Dynamic::<[u8; 4]>::with_data(&[5, 0, 0]),
+            v3: 0,
+        });
+
+        // The data following the structure is ignored
+        let mut deserializer: Deserializer<Message> = Deserializer::new();
+        assert_eq!(deserializer.deserialize(&mut [17, 0x34, 0x12, 23, 0, 0xff, 0xff]), DeserializationResult::Finished);
+        assert_eq!(deserializer.deserialize(&mut [0xff]), DeserializationResult::Finished);
+        assert_eq!(deserializer.into_structure().unwrap(), Message{
+            v1: 17,
+            v2: 0x1234,
+            array: Dynamic::<[u8; 4]>::new(),
+            v3: 23,
+        });
+    }
+
+    #[test]
+    fn const_generic_integers() {
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Named {
+            a: u13,
+            b: i7,
+            c: u64,
+        }
+
+        // The derive doesn't parse const generic arguments, the fields are named through aliases
+        type U13 = Uint<13>;
+        type I7 = Int<7>;
+        type U64 = Uint<64>;
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Generic {
+            a: U13,
+            b: I7,
+            c: U64,
+        }
+
+        assert_eq!(Generic::BIT_LENGTH_MAX, Named::BIT_LENGTH_MAX);
+
+        let named = Named{a: u13::new(0x1234), b: i7::new(-3), c: u64::max_value()};
+        let generic = Generic{a: Uint::new(0x1234), b: Int::new(-3), c: Uint::new(u64::max_value())};
+
+        let mut named_data = [0u8; 11];
+        let mut generic_data = [0u8; 11];
+        {
+            let mut buffer = SerializationBuffer::with_empty_buffer(&mut named_data);
+            assert_eq!(Serializer::from_structure(named).serialize(&mut buffer), SerializationResult::Finished);
+            let mut buffer = SerializationBuffer::with_empty_buffer(&mut generic_data);
+            assert_eq!(Serializer::from_structure(generic.clone()).serialize(&mut buffer), SerializationResult::Finished);
+        }
+        assert_eq!(named_data, generic_data);
+
+        let mut deserializer: Deserializer<Generic> = Deserializer::new();
+        deserializer.deserialize(&mut generic_data);
+        assert_eq!(deserializer.into_structure().unwrap(), generic);
+    }
+
+    #[test]
+    fn deserialize_in_chunks() {
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Message {
+            pad: u5,
+            text: Dynamic<[u8; 20]>,
+            value: u16,
+        }
+
+        let message = Message{
+            pad: u5::new(17),
+            text: Dynamic::<[u8; 20]>::with_data("streamed payload".as_bytes()),
+            value: 0xbeef,
+        };
+
+        let mut data = [0u8; 24];
+        let length;
+        {
+            let mut serializer = Serializer::from_structure(message.clone());
+            length = serializer.byte_length();
+            let mut buffer = SerializationBuffer::with_empty_buffer(&mut data);
+            assert_eq!(serializer.serialize(&mut buffer), SerializationResult::Finished);
+        }
+
+        for chunk_length in 1..length {
+            let mut deserializer: Deserializer<Message> = Deserializer::new();
+            for chunk in data[..length].chunks(chunk_length) {
+                assert!(!deserializer.is_finished());
+                deserializer.deserialize_chunk(chunk);
+            }
+            assert!(deserializer.is_finished());
+            assert_eq!(deserializer.into_structure().unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn deserialize_union() {
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        enum Value {
+            Integer(u8),
+            Boolean(u6),
+            Text(Dynamic<[u8; 7]>),
+        }
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Message {
+            pad: u7,
+            value: Value,
+        }
+
+        fn round_trip(message: Message) {
+            let mut data = [0u8; 8];
+            let length;
+            {
+                let mut serializer = Serializer::from_structure(message.clone());
+                length = serializer.byte_length();
+                let mut buffer = SerializationBuffer::with_empty_buffer(&mut data);
+                assert_eq!(serializer.serialize(&mut buffer), SerializationResult::Finished);
+            }
+
+            // The tag straddles the first two bytes, which are deserialized separately
+            let mut deserializer: Deserializer<Message> = Deserializer::new();
+            for byte in data[..length].iter() {
+                deserializer.deserialize(&mut [*byte]);
+            }
+            assert_eq!(deserializer.into_structure().unwrap(), message);
+        }
+
+        round_trip(Message{pad: u7::new(0), value: Value::Integer(0xa5)});
+        round_trip(Message{pad: u7::new(0), value: Value::Boolean(u6::new(0b101010))});
+        round_trip(Message{pad: u7::new(0), value: Value::Text(Dynamic::<[u8; 7]>::with_data("union".as_bytes()))});
+    }
+
     #[test]
     fn tail_array_optimization_struct() {
         #[derive(Debug, PartialEq, UavcanStruct, Clone)]
@@ -218,6 +531,47 @@ mod tests {
 
     }
 
+    #[test]
+    fn composite_arrays_across_buffers() {
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        pub struct Element {
+            pub a: u3,
+            pub b: u7,
+        }
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        pub struct Message {
+            pub count: u8,
+            pub dynamic: Dynamic<[Element; 4]>,
+            pub fixed: [Element; 2],
+            pub tail: u8,
+        }
+
+        let element = |a, b| Element{a: u3::new(a), b: u7::new(b)};
+        let message = Message{
+            count: 3,
+            dynamic: Dynamic::<[Element; 4]>::with_data(&[element(1, 100), element(2, 50), element(7, 127)]),
+            fixed: [element(5, 1), element(0, 64)],
+            tail: 0xa5,
+        };
+
+        let mut data = [0u8; 16];
+        let length;
+        {
+            let mut serializer = Serializer::from_structure(message.clone());
+            length = serializer.byte_length();
+            let mut buffer = SerializationBuffer::with_empty_buffer(&mut data);
+            assert_eq!(serializer.serialize(&mut buffer), SerializationResult::Finished);
+        }
+
+        // Deserializing one byte at a time, like a payload arriving in frames
+        let mut deserializer: Deserializer<Message> = Deserializer::new();
+        for byte in data[..length].iter() {
+            deserializer.deserialize(&mut [*byte]);
+        }
+        assert_eq!(deserializer.into_structure().unwrap(), message);
+    }
+
     #[test]
     fn static_array_of_structs() {
         #[derive(Debug, PartialEq, Clone, UavcanStruct)]
@@ -251,6 +605,268 @@ mod tests {
         
     }
 
+    #[test]
+    fn borrow_byte_arrays() {
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct LogMessage {
+            level: u3,
+            source: Dynamic<[u8; 31]>,
+            text: Dynamic<[u8; 90]>,
+        }
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Misaligned {
+            flag: bool,
+            data: Dynamic<[u8; 4]>,
+            value: u8,
+        }
+
+        let mut serializer = Serializer::from_structure(LogMessage{
+            level: u3::new(2),
+            source: Dynamic::<[u8; 31]>::with_data(b"src"),
+            text: Dynamic::<[u8; 90]>::with_data(b"hello"),
+        });
+        let mut array = [0u8; 16];
+        let length = {
+            let mut buffer = SerializationBuffer::with_empty_buffer(&mut array);
+            assert_eq!(serializer.serialize(&mut buffer), SerializationResult::Finished);
+            (buffer.bit_length() + 7) / 8
+        };
+        let payload = &array[..length];
+
+        // The length of `source` ends at a byte boundary and `text` is a tail array
+        assert_eq!(LogMessage::borrow_source(payload), Some(&b"src"[..]));
+        assert_eq!(LogMessage::borrow_text(payload), Some(&b"hello"[..]));
+        assert_eq!(LogMessage::borrow_text(&payload[..6]), Some(&b"he"[..]));
+        assert_eq!(LogMessage::borrow_source(&payload[..3]), None);
+
+        let mut serializer = Serializer::from_structure(Misaligned{flag: true, data: Dynamic::<[u8; 4]>::with_data(&[1, 2]), value: 3});
+        let mut array = [0u8; 4];
+        let mut buffer = SerializationBuffer::with_empty_buffer(&mut array);
+        serializer.serialize(&mut buffer);
+        assert_eq!(Misaligned::borrow_data(buffer.data), None);
+    }
+
+    #[test]
+    fn optional_fields() {
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Empty {}
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        enum OptionalU4 {
+            Empty(Empty),
+            Value(u4),
+        }
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct WithOption {
+            a: Option<u4>,
+            b: Option<u4>,
+            c: u8,
+        }
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct WithUnion {
+            a: OptionalU4,
+            b: OptionalU4,
+            c: u8,
+        }
+
+        assert_eq!(WithOption::MIN_BITS, WithUnion::MIN_BITS);
+        assert_eq!(WithOption::MAX_BITS, WithUnion::MAX_BITS);
+
+        let with_option = WithOption{a: Some(u4::new(9)), b: None, c: 0x5a};
+        let mut option_array = [0u8; 3];
+        let mut serializer = Serializer::from_structure(with_option.clone());
+        serializer.serialize(&mut SerializationBuffer::with_empty_buffer(&mut option_array));
+
+        let mut union_array = [0u8; 3];
+        let mut serializer = Serializer::from_structure(WithUnion{a: OptionalU4::Value(u4::new(9)), b: OptionalU4::Empty(Empty{}), c: 0x5a});
+        serializer.serialize(&mut SerializationBuffer::with_empty_buffer(&mut union_array));
+
+        assert_eq!(option_array, union_array);
+
+        let mut deserializer: Deserializer<WithOption> = Deserializer::new();
+        for byte in option_array.iter() {
+            deserializer.deserialize(&mut [*byte]);
+        }
+        assert_eq!(deserializer.into_structure().unwrap(), with_option);
+
+        let mut deserializer: Deserializer<WithOption> = Deserializer::new();
+        deserializer.deserialize(&mut [0x80]);
+        assert_eq!(deserializer.into_structure().unwrap(), WithOption{a: Some(u4::new(0)), b: None, c: 0});
+    }
+
+    #[test]
+    fn native_fields() {
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Native {
+            #[uavcan(bits = 13)]
+            a: u16,
+            #[uavcan(bits = 5)]
+            b: i32,
+            #[uavcan(bits = 16)]
+            c: f32,
+            #[uavcan(bits = 1)]
+            d: bool,
+            #[uavcan(bits = 8)]
+            e: u32,
+        }
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Wrapped {
+            a: u13,
+            b: i5,
+            c: f16,
+            d: bool,
+            e: u8,
+        }
+
+        assert_eq!(Native::MIN_BITS, Wrapped::MIN_BITS);
+        assert_eq!(Native::MAX_BITS, Wrapped::MAX_BITS);
+
+        // The native values are saturated to the bit length
+        let native = Native{a: 9000, b: -7, c: -2.5, d: true, e: 300};
+        let mut native_array = [0u8; 6];
+        let mut serializer = Serializer::from_structure(native);
+        serializer.serialize(&mut SerializationBuffer::with_empty_buffer(&mut native_array));
+
+        let wrapped = Wrapped{a: u13::new(8191), b: i5::new(-7), c: f16::from_f32(-2.5), d: true, e: 255};
+        let mut wrapped_array = [0u8; 6];
+        let mut serializer = Serializer::from_structure(wrapped);
+        serializer.serialize(&mut SerializationBuffer::with_empty_buffer(&mut wrapped_array));
+
+        assert_eq!(native_array, wrapped_array);
+
+        // The fields split between buffers are kept in the native types
+        let mut deserializer: Deserializer<Native> = Deserializer::new();
+        for byte in native_array.iter() {
+            deserializer.deserialize(&mut [*byte]);
+        }
+        assert_eq!(deserializer.into_structure().unwrap(), Native{a: 8191, b: -7, c: -2.5, d: true, e: 255});
+    }
+
+    #[test]
+    fn generic_structs() {
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct WithCovariance<T> {
+            value: T,
+            covariance: [f16; 2],
+        }
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Position {
+            x: i12,
+            y: i12,
+        }
+
+        assert_eq!(WithCovariance::<u13>::MAX_BITS, 13 + 2*16);
+        assert_eq!(WithCovariance::<Position>::MAX_BITS, 2*12 + 2*16);
+        assert_eq!(WithCovariance::<Dynamic<[u8; 4]>>::MAX_BITS, 3 + 4*8 + 2*16);
+
+        let message = WithCovariance{
+            value: Position{x: i12::new(-100), y: i12::new(2000)},
+            covariance: [f16::from_f32(0.5), f16::from_f32(1.5)],
+        };
+        let mut array = [0u8; 7];
+        let mut serializer = Serializer::from_structure(message.clone());
+        serializer.serialize(&mut SerializationBuffer::with_empty_buffer(&mut array));
+
+        let mut deserializer: Deserializer<WithCovariance<Position>> = Deserializer::new();
+        deserializer.deserialize(&mut array);
+        assert_eq!(deserializer.into_structure().unwrap(), message);
+    }
+
+    #[test]
+    fn packed_bool_arrays() {
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Packed {
+            flags: BitArray<[bool; 10]>,
+            more_flags: DynamicBitArray<[bool; 9]>,
+            value: u8,
+        }
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Unpacked {
+            flags: [bool; 10],
+            more_flags: Dynamic<[bool; 9]>,
+            value: u8,
+        }
+
+        let flags = [true, false, true, true, false, false, false, true, false, true];
+        let packed = Packed{flags: BitArray::from(flags), more_flags: DynamicBitArray::with_data(&[false, true, true]), value: 0xa5};
+
+        let mut packed_array = [0u8; 4];
+        let mut serializer = Serializer::from_structure(packed.clone());
+        serializer.serialize(&mut SerializationBuffer::with_empty_buffer(&mut packed_array));
+
+        let mut unpacked_array = [0u8; 4];
+        let mut serializer = Serializer::from_structure(Unpacked{flags: flags, more_flags: Dynamic::<[bool; 9]>::with_data(&[false, true, true]), value: 0xa5});
+        serializer.serialize(&mut SerializationBuffer::with_empty_buffer(&mut unpacked_array));
+
+        assert_eq!(packed_array, unpacked_array);
+
+        let mut deserializer: Deserializer<Packed> = Deserializer::new();
+        for byte in packed_array.iter() {
+            deserializer.deserialize(&mut [*byte]);
+        }
+        assert_eq!(deserializer.into_structure().unwrap(), packed);
+    }
+
+    #[test]
+    fn delimited_composite_versions() {
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct InnerV1 {
+            x: u8,
+        }
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct InnerV2 {
+            x: u8,
+            y: u4,
+        }
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct OuterV1 {
+            a: u8,
+            inner: Delimited<InnerV1>,
+            b: u8,
+        }
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct OuterV2 {
+            a: u8,
+            inner: Delimited<InnerV2>,
+            b: u8,
+        }
+
+        // The content of 12 bits is padded to 2 bytes
+        let mut serializer = Serializer::from_structure(OuterV2{a: 1, inner: Delimited::new(InnerV2{x: 2, y: u4::new(5)}), b: 3});
+        let mut array = [0u8; 8];
+        let mut buffer = SerializationBuffer::with_empty_buffer(&mut array);
+        assert_eq!(serializer.serialize(&mut buffer), SerializationResult::Finished);
+        assert_eq!(buffer.data, [1, 2, 0, 0, 0, 2, 0b0101_0000, 3]);
+
+        // An older receiver skips `y`, also when the data arrives a byte at a time
+        let mut deserializer: Deserializer<OuterV1> = Deserializer::new();
+        for byte in [1, 2, 0, 0, 0, 2, 0b0101_0000, 3].iter() {
+            deserializer.deserialize(&mut [*byte]);
+        }
+        assert!(deserializer.is_finished());
+        assert_eq!(deserializer.into_structure().unwrap(), OuterV1{a: 1, inner: Delimited::new(InnerV1{x: 2}), b: 3});
+
+        // A newer receiver reads the missing `y` as zero
+        let mut deserializer: Deserializer<OuterV2> = Deserializer::new();
+        deserializer.deserialize(&mut [1, 1, 0, 0, 0, 2, 3]);
+        assert_eq!(deserializer.into_structure().unwrap(), OuterV2{a: 1, inner: Delimited::new(InnerV2{x: 2, y: u4::new(0)}), b: 3});
+    }
+
 
 
 }
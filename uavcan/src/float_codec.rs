@@ -0,0 +1,141 @@
+//! The bit patterns of float fields
+//!
+//! A `float16`, `float32` or `float64` field holds the IEEE 754 binary16, binary32 or binary64 bit pattern of its value,
+//! which is serialized like an unsigned integer of the same bit length, with its lowest byte first.
+//! The conversions are exact both ways: the sign of zero, subnormals and the payload of NaN are kept.
+//!
+//! The serializer, the deserializer and `reflection` all convert floats through this module.
+
+use types::f16;
+
+pub(crate) fn f16_to_bits(value: f16) -> u64 {
+    u64::from(value.as_bits())
+}
+
+/// Ignores the bits above the lowest 16
+pub(crate) fn f16_from_bits(bits: u64) -> f16 {
+    f16::from_bits(bits as u16)
+}
+
+pub(crate) fn f32_to_bits(value: f32) -> u64 {
+    u64::from(value.to_bits())
+}
+
+/// Ignores the bits above the lowest 32
+pub(crate) fn f32_from_bits(bits: u64) -> f32 {
+    f32::from_bits(bits as u32)
+}
+
+pub(crate) fn f64_to_bits(value: f64) -> u64 {
+    value.to_bits()
+}
+
+pub(crate) fn f64_from_bits(bits: u64) -> f64 {
+    f64::from_bits(bits)
+}
+
+/// Decodes the bits of a float field of `bit_length` bits, other lengths than 16 and 32 are decoded as `float64`
+pub(crate) fn decode(bit_length: u8, bits: u64) -> f64 {
+    match bit_length {
+        16 => f64::from(f16_from_bits(bits).to_f32()),
+        32 => f64::from(f32_from_bits(bits)),
+        _ => f64_from_bits(bits),
+    }
+}
+
+/// Encodes `value` as a float field of `bit_length` bits, rounding to nearest and saturating finite values like `f16::from_f32`
+pub(crate) fn encode(bit_length: u8, value: f64) -> u64 {
+    match bit_length {
+        16 => f16_to_bits(f16::from_f32(value as f32)),
+        32 => f32_to_bits(value as f32),
+        _ => f64_to_bits(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use *;
+    use types::*;
+    use float_codec::*;
+
+    fn serialize<T: Serializable>(value: T) -> [u8; 8] {
+        let mut data = [0u8; 8];
+        {
+            let mut buffer = SerializationBuffer::with_empty_buffer(&mut data);
+            assert_eq!(value.serialize(&mut 0, &mut 0, false, &mut buffer), SerializationResult::Finished);
+        }
+        data
+    }
+
+    /// Serializes `value` after a `uint3`, and deserializes it into `zero` a byte at a time
+    fn unaligned_round_trip<T: Serializable>(value: T, mut zero: T) -> T {
+        let mut data = [0u8; 9];
+        {
+            let mut buffer = SerializationBuffer::with_empty_buffer(&mut data);
+            u3::new(5).serialize(&mut 0, &mut 0, false, &mut buffer);
+            value.serialize(&mut 0, &mut 0, false, &mut buffer);
+        }
+
+        let mut offset = u3::new(0);
+        let mut offset_bit = 0;
+        let mut offset_finished = false;
+        let mut field = 0;
+        let mut bit = 0;
+        for byte in data.iter() {
+            let mut chunk = [*byte];
+            let mut buffer = DeserializationBuffer::with_full_buffer(&mut chunk);
+            if !offset_finished {
+                offset_finished = offset.deserialize(&mut 0, &mut offset_bit, false, &mut buffer) == DeserializationResult::Finished;
+            }
+            if offset_finished && field == 0 {
+                zero.deserialize(&mut field, &mut bit, false, &mut buffer);
+            }
+        }
+        assert_eq!(offset, u3::new(5));
+        assert_eq!(field, 1);
+        zero
+    }
+
+    #[test]
+    fn little_endian_bit_order() {
+        assert_eq!(serialize(f16::from_f32(1.0)), [0x00, 0x3c, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(serialize(f16::from_f32(-2.5)), [0x00, 0xc1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(serialize(1.0f32), [0x00, 0x00, 0x80, 0x3f, 0, 0, 0, 0]);
+        assert_eq!(serialize(-2.5f32), [0x00, 0x00, 0x20, 0xc0, 0, 0, 0, 0]);
+        assert_eq!(serialize(1.0f64), [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf0, 0x3f]);
+        assert_eq!(serialize(-2.5f64), [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0xc0]);
+        assert_eq!(serialize(f32::from_bits(0x0102_0304)), [0x04, 0x03, 0x02, 0x01, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn exact_round_trips() {
+        let floats32 = [0.0, -0.0, 1.0, -2.5, f32::MAX, f32::MIN_POSITIVE, f32::from_bits(1), f32::INFINITY, f32::NEG_INFINITY,
+                        f32::from_bits(0x7fa0_0001), f32::from_bits(0xffc0_1234)];
+        for value in floats32.iter() {
+            assert_eq!(unaligned_round_trip(*value, 0.0).to_bits(), value.to_bits());
+            assert_eq!(f32_from_bits(f32_to_bits(*value)).to_bits(), value.to_bits());
+        }
+
+        let floats64 = [0.0, -0.0, 1.0, -2.5, f64::MAX, f64::from_bits(1), f64::NEG_INFINITY,
+                        f64::from_bits(0x7ff4_0000_0000_0001), f64::from_bits(0xfff8_0000_dead_beef)];
+        for value in floats64.iter() {
+            assert_eq!(unaligned_round_trip(*value, 0.0).to_bits(), value.to_bits());
+        }
+
+        for bits in [0x0000, 0x8000, 0x3c00, 0x0001, 0x7bff, 0xfc00, 0x7d01, 0xfe42].iter() {
+            assert_eq!(unaligned_round_trip(f16::from_bits(*bits), f16::from_bits(0)).as_bits(), *bits);
+        }
+    }
+
+    #[test]
+    fn reflection_codec() {
+        assert_eq!(decode(16, 0x3c00), 1.0);
+        assert_eq!(decode(32, 0xc020_0000), -2.5);
+        assert_eq!(decode(64, 0x3ff0_0000_0000_0000), 1.0);
+        assert_eq!(encode(16, 100_000.0), 0x7bff);
+        assert_eq!(encode(32, -2.5), 0xc020_0000);
+        assert_eq!(encode(64, -0.0), 0x8000_0000_0000_0000);
+        assert!(decode(32, 0x7fc0_0000).is_nan());
+    }
+}
@@ -1,4 +1,9 @@
-use crc::TransferCRC;
+use crc::{
+    ChecksumPlacement,
+    TrailingChecksum,
+    TransferCRC,
+    TransferChecksum,
+};
 
 use transfer::{
     TransferFrame,
@@ -35,32 +40,41 @@ pub enum BuildError {
     NotFinishedParsing,
 }
 
-pub(crate) struct FrameAssembler<S: Struct> {
+pub(crate) struct FrameAssembler<S: Struct, C: TransferChecksum = TransferCRC> {
     deserializer: Deserializer<S>,
     started: bool,
     finished: bool,
     id: TransferFrameID,
-    crc_received: Option<TransferCRC>,
-    crc_calculated: TransferCRC,
+    crc_received: Option<[u8; 8]>,
+    crc_calculated: C,
+    trailing: Option<TrailingChecksum>,
     toggle: bool,
     transfer_id: TransferID,    
 }
 
-impl<S: Struct> FrameAssembler<S> {
+impl<S: Struct> FrameAssembler<S, TransferCRC> {
     pub fn new() -> Self {
+        Self::with_crc()
+    }
+}
+
+impl<S: Struct, C: TransferChecksum> FrameAssembler<S, C> {
+    /// Creates a frame assembler validating multi frame transfers with the transfer CRC `C`
+    pub fn with_crc() -> Self {
         Self{
             deserializer: Deserializer::new(),
             started: false,
             finished: false,
             id: TransferFrameID::new(0x00),
             crc_received: None,
-            crc_calculated: TransferCRC::from_signature(S::DATA_TYPE_SIGNATURE),
+            crc_calculated: C::from_signature(S::DATA_TYPE_SIGNATURE),
+            trailing: None,
             toggle: false,
             transfer_id: TransferID::new(0x00),
         }
     }
     
-    pub fn add_transfer_frame<T: TransferFrame>(&mut self, mut frame: T) -> Result<AssemblerResult, AssemblerError> {
+    pub fn add_transfer_frame<T: TransferFrame>(&mut self, frame: T) -> Result<AssemblerResult, AssemblerError> {
         let end_frame = frame.is_end_frame();
         
         if self.finished {
@@ -77,7 +91,14 @@ impl<S: Struct> FrameAssembler<S> {
             }
             
             if !end_frame {
-                self.crc_received = Some(TransferCRC::from((frame.data()[0] as u16) | (frame.data()[1] as u16) << 8));
+                match C::PLACEMENT {
+                    ChecksumPlacement::FirstFrame => {
+                        let mut crc_received = [0u8; 8];
+                        crc_received[..C::LENGTH].copy_from_slice(&frame.data()[..C::LENGTH]);
+                        self.crc_received = Some(crc_received);
+                    },
+                    ChecksumPlacement::PayloadEnd => self.trailing = Some(TrailingChecksum::default()),
+                }
             }
             
             self.toggle = false;
@@ -91,14 +112,24 @@ impl<S: Struct> FrameAssembler<S> {
         }
 
         let data_len = frame.data().len();
-        let payload = if frame.is_start_frame() && !frame.is_end_frame() {
-            &mut frame.data_as_mut()[2..data_len-1]
+        let payload = if frame.is_start_frame() && !frame.is_end_frame() && C::PLACEMENT == ChecksumPlacement::FirstFrame {
+            &frame.data()[C::LENGTH..data_len-1]
         } else {
-            &mut frame.data_as_mut()[0..data_len-1]
+            &frame.data()[0..data_len-1]
         };
 
-        self.crc_calculated.add(payload);
-        self.deserializer.deserialize(payload);            
+        // A CRC after the payload is held back until the next frame shows that the bytes aren't part of it
+        {
+            let FrameAssembler{ref mut crc_calculated, ref mut deserializer, ref mut trailing, ..} = *self;
+            let mut receive = |bytes: &[u8]| {
+                crc_calculated.add(bytes);
+                deserializer.deserialize_chunk(bytes);
+            };
+            match *trailing {
+                Some(ref mut trailing) => trailing.push(C::LENGTH, payload, &mut receive),
+                None => receive(payload),
+            }
+        }
 
         if end_frame {
             self.finished = true;
@@ -109,7 +140,13 @@ impl<S: Struct> FrameAssembler<S> {
     }
 
     pub fn build(self) -> Result<Frame<S>, BuildError> {
-        if self.crc_calculated != self.crc_received.unwrap_or(self.crc_calculated) {
+        let crc_valid = match (self.crc_received, self.trailing) {
+            (_, Some(ref trailing)) => trailing.received().len() == C::LENGTH && self.crc_calculated.matches(trailing.received()),
+            (Some(ref received), None) => self.crc_calculated.matches(&received[..C::LENGTH]),
+            (None, None) => true,
+        };
+
+        if !crc_valid {
             Result::Err(BuildError::CRCError)
         } else if let Ok(body) = self.deserializer.into_structure() {
             Ok(Frame::from_parts(self.id, body))
@@ -136,6 +173,7 @@ mod tests {
     };
 
     use frame_assembler::*;
+    use frame_disassembler::FrameDisassembler;
     
     #[test]
     fn parse_from_can_frames_simple() {
@@ -172,15 +210,13 @@ mod tests {
     fn deserialize_multi_frame() {
         
         #[derive(Debug, PartialEq, Clone, UavcanStruct)]
-        #[DSDLSignature = "0x711bf141af572346"]
-        #[DataTypeSignature = "0x711bf141af572346"]
+        #[uavcan(signature = 0x711bf141af572346, dsdl_signature = 0x711bf141af572346)]
         struct LogLevel {
             value: u3,
         }
 
         #[derive(Debug, PartialEq, Clone, UavcanStruct)]
-        #[DataTypeSignature = "0xd654a48e0c049d75"]
-        #[DSDLSignature = "0xe9862b78d38762ba"]
+        #[uavcan(signature = 0xd654a48e0c049d75, dsdl_signature = 0xe9862b78d38762ba)]
         struct LogMessage {
             level: LogLevel,
             source: Dynamic<[u8; 31]>,
@@ -228,8 +264,81 @@ mod tests {
         assert_eq!(Ok(uavcan_frame), message_builder.build());
         
     }
-   
 
+    #[test]
+    fn validate_transfer_crc() {
 
-}
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        #[uavcan(message, id = 20000, signature = 0x0123456789abcdef)]
+        struct Counters {
+            first: u32,
+            second: u32,
+            third: u16,
+        }
+
+        let counters = Counters{first: 1, second: 2, third: 3};
+        let frames = || {
+            let uavcan_frame = Frame::from_message(counters.clone(), 0, NodeID::new(32));
+            let mut frame_generator = FrameDisassembler::from_uavcan_frame(uavcan_frame, TransferID::new(0));
+            (frame_generator.next_transfer_frame::<CanFrame>().unwrap(), frame_generator.next_transfer_frame::<CanFrame>().unwrap())
+        };
 
+        // The CRC is stripped from the payload of the first frame
+        let (first, second) = frames();
+        let mut message_builder = FrameAssembler::<Counters>::new();
+        assert_eq!(message_builder.add_transfer_frame(first), Ok(AssemblerResult::Ok));
+        assert_eq!(message_builder.add_transfer_frame(second), Ok(AssemblerResult::Finished));
+        assert_eq!(message_builder.build().unwrap().body, counters);
+
+        // A corrupted payload still deserializes, but doesn't match the transfer CRC
+        let (mut first, second) = frames();
+        first.data[3] ^= 0x01;
+        let mut message_builder = FrameAssembler::<Counters>::new();
+        message_builder.add_transfer_frame(first).unwrap();
+        message_builder.add_transfer_frame(second).unwrap();
+        assert_eq!(message_builder.build(), Err(BuildError::CRCError));
+    }
+
+    #[test]
+    fn crc_after_payload() {
+        use crc::TransferCRC32C;
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        #[uavcan(message, id = 20000, signature = 0x0123456789abcdef)]
+        struct Counters {
+            first: u32,
+            second: u32,
+            third: u16,
+        }
+
+        let counters = Counters{first: 1, second: 2, third: 3};
+        let frames = || {
+            let uavcan_frame = Frame::from_message(counters.clone(), 0, NodeID::new(32));
+            let mut frame_generator = FrameDisassembler::<Counters, TransferCRC32C>::with_crc(uavcan_frame, TransferID::new(0));
+            let frames = (frame_generator.next_transfer_frame::<CanFrame>().unwrap(), frame_generator.next_transfer_frame::<CanFrame>().unwrap());
+            assert_eq!(frame_generator.next_transfer_frame::<CanFrame>(), None);
+            frames
+        };
+
+        // The CRC-32C follows the last three bytes of the payload
+        let (first, second) = frames();
+        let mut crc = TransferCRC32C::new();
+        crc.add(&first.data[..7]);
+        crc.add(&second.data[..3]);
+        assert_eq!(&first.data[..7], &[1, 0, 0, 0, 2, 0, 0]);
+        assert_eq!(&second.data[..3], &[0, 3, 0]);
+        assert_eq!(second.data[3..7], crc.value().to_le_bytes());
+
+        let mut message_builder = FrameAssembler::<Counters, TransferCRC32C>::with_crc();
+        assert_eq!(message_builder.add_transfer_frame(first), Ok(AssemblerResult::Ok));
+        assert_eq!(message_builder.add_transfer_frame(second), Ok(AssemblerResult::Finished));
+        assert_eq!(message_builder.build().unwrap().body, counters);
+
+        let (first, mut second) = frames();
+        second.data[6] ^= 0x01;
+        let mut message_builder = FrameAssembler::<Counters, TransferCRC32C>::with_crc();
+        message_builder.add_transfer_frame(first).unwrap();
+        message_builder.add_transfer_frame(second).unwrap();
+        assert_eq!(message_builder.build(), Err(BuildError::CRCError));
+    }
+}
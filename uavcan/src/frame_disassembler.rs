@@ -1,5 +1,3 @@
-use bit_field::BitField;
-
 use {
     Struct,
     Frame,
@@ -12,21 +10,42 @@ use transfer::{
     TransferID,
 };
 
+use crc::{
+    ChecksumPlacement,
+    TransferCRC,
+    TransferChecksum,
+};
+
 use serializer::*;
 
+use lib::core::marker::PhantomData;
 
 
-pub(crate) struct FrameDisassembler<S: Struct> {
+
+pub(crate) struct FrameDisassembler<S: Struct, C: TransferChecksum = TransferCRC> {
     serializer: Serializer<S>,
     started: bool,
     finished: bool,
     id: TransferFrameID,
     toggle: bool,
     transfer_id: TransferID,
+    pad_byte: u8,
+    /// The CRC after the payload, the padding in front of it and the serialized length of the structure
+    trailer: Option<([u8; 8], usize, usize)>,
+    /// The number of bytes of the transfer sent so far, with a CRC after the payload
+    offset: usize,
+    crc: PhantomData<C>,
 }
 
-impl<S: Struct> FrameDisassembler<S> {
+impl<S: Struct> FrameDisassembler<S, TransferCRC> {
     pub fn from_uavcan_frame(frame: Frame<S>, transfer_id: TransferID) -> Self {
+        Self::with_crc(frame, transfer_id)
+    }
+}
+
+impl<S: Struct, C: TransferChecksum> FrameDisassembler<S, C> {
+    /// Creates a frame generator protecting multi frame transfers with the transfer CRC `C`
+    pub fn with_crc(frame: Frame<S>, transfer_id: TransferID) -> Self {
         let (id, body) = frame.into_parts();
         Self{
             serializer: Serializer::from_structure(body),
@@ -35,47 +54,101 @@ impl<S: Struct> FrameDisassembler<S> {
             id: id,
             toggle: false,
             transfer_id: transfer_id,
+            pad_byte: 0,
+            trailer: None,
+            offset: 0,
+            crc: PhantomData,
         }
     }
 
+    /// Sets the byte used for padding frames to a legal data length (see `TransferFrame::quantize_data_length`)
+    pub fn with_pad_byte(mut self, pad_byte: u8) -> Self {
+        self.pad_byte = pad_byte;
+        self
+    }
+
+    /// Generates the next frame, using the full data length of `T`
+    #[cfg(any(test, feature="socketcan"))]
     pub fn next_transfer_frame<T: TransferFrame>(&mut self) -> Option<T> {
-        let max_data_length = T::MAX_DATA_LENGTH;
+        self.next_transfer_frame_with_mtu(T::MAX_DATA_LENGTH)
+    }
+
+    /// Generates the next frame, using at most `mtu` bytes of the frame data (including the tail byte).
+    ///
+    /// `mtu` is limited to `T::MAX_DATA_LENGTH` and must be a legal data length for `T`.
+    /// All frames of a transfer must be generated with the same `mtu`.
+    ///
+    /// The last frame is padded to a legal data length, the padding is included in the transfer CRC.
+    /// A CRC after the payload (see `ChecksumPlacement`) follows the padding.
+    ///
+    /// ## Panics
+    /// Panics if `mtu < C::LENGTH + 1`, as a frame of a multi frame transfer wouldn't fit the transfer CRC and tail byte.
+    pub fn next_transfer_frame_with_mtu<T: TransferFrame>(&mut self, mtu: usize) -> Option<T> {
+        assert!(mtu > C::LENGTH, "The MTU must fit the transfer CRC and the tail byte");
+        if self.finished {
+            return None;
+        }
+
+        let max_data_length = ::lib::core::cmp::min(mtu, T::MAX_DATA_LENGTH);
         let mut transport_frame = T::new(self.id);
         transport_frame.set_data_length(max_data_length);
-        
-        let first_of_multi_frame = if !self.started {
+
+        if self.trailer.is_some() {
+            self.payload_end_frame(&mut transport_frame, max_data_length);
+            return Some(transport_frame);
+        }
+
+        // The structure is serialized straight into the frame data, a single frame transfer is serialized only once.
+        // Structures that never fit a single frame go straight to the multi frame transfer.
+        let (data_length, end_of_transfer) = if !self.started && S::MIN_BYTES > max_data_length - 1 {
+            (max_data_length-1, false)
+        } else {
             let mut buffer = SerializationBuffer::with_empty_buffer(&mut transport_frame.data_as_mut()[0..max_data_length-1]);
-            if let SerializationResult::Finished = self.serializer.peek_serialize(&mut buffer) {
-                false
+            if SerializationResult::Finished == self.serializer.serialize(&mut buffer) {
+                ((buffer.bit_length()+7)/8, true)
             } else {
-                true
-            }                
-        } else {
-            false
+                (max_data_length-1, false)
+            }
         };
 
-        if self.finished {
-            return None;
-        } else if first_of_multi_frame {
-            let crc = self.serializer.crc(S::DATA_TYPE_SIGNATURE);
-            transport_frame.data_as_mut()[0] = crc.get_bits(0..8) as u8;
-            transport_frame.data_as_mut()[1] = crc.get_bits(8..16) as u8;
+        if !self.started && !end_of_transfer {
+            // Every frame carries `max_data_length-1` bytes of the payload and the CRC.
+            // The CRC and the length are calculated in a single pass before the payload is serialized again.
+            let mut crc = C::from_signature(S::DATA_TYPE_SIGNATURE);
+            let mut length = 0;
+            self.serializer.rewind();
+            self.serializer.for_each_chunk(|chunk| {
+                crc.add(chunk);
+                length += chunk.len();
+            });
+
+            let last_frame_length = (length + C::LENGTH - 1) % (max_data_length - 1) + 2;
+            let padding_length = T::quantize_data_length(last_frame_length) - last_frame_length;
+            for _ in 0..padding_length {
+                crc.add(&[self.pad_byte]);
+            }
+
+            if C::PLACEMENT == ChecksumPlacement::PayloadEnd {
+                let mut crc_bytes = [0u8; 8];
+                crc.write_bytes(&mut crc_bytes[..C::LENGTH]);
+                self.trailer = Some((crc_bytes, padding_length, length));
+                self.payload_end_frame(&mut transport_frame, max_data_length);
+                return Some(transport_frame);
+            }
+
+            crc.write_bytes(&mut transport_frame.data_as_mut()[0..C::LENGTH]);
             {
-                let mut buffer = SerializationBuffer::with_empty_buffer(&mut transport_frame.data_as_mut()[2..max_data_length-1]);
+                let mut buffer = SerializationBuffer::with_empty_buffer(&mut transport_frame.data_as_mut()[C::LENGTH..max_data_length-1]);
                 self.serializer.serialize(&mut buffer);
             }
-            transport_frame.data_as_mut()[max_data_length-1] = TailByte::new(!self.started, false, self.toggle, self.transfer_id).into();
+            transport_frame.data_as_mut()[max_data_length-1] = TailByte::new(true, false, self.toggle, self.transfer_id).into();
         } else {
-            let (frame_length, end_of_transfer) = {
-                let mut buffer = SerializationBuffer::with_empty_buffer(&mut transport_frame.data_as_mut()[0..max_data_length-1]);
-                if SerializationResult::Finished == self.serializer.serialize(&mut buffer){
-                    self.finished = true;
-                    ((buffer.bit_length()+7)/8 + 1, true)
-                } else {
-                    (max_data_length, false)
-                }
-            };
+            self.finished = end_of_transfer;
+            let frame_length = T::quantize_data_length(data_length + 1);
             transport_frame.set_data_length(frame_length);
+            for byte in &mut transport_frame.data_as_mut()[data_length..frame_length-1] {
+                *byte = self.pad_byte;
+            }
             transport_frame.data_as_mut()[frame_length-1] = TailByte::new(!self.started, end_of_transfer, self.toggle, self.transfer_id).into();
         }
         
@@ -84,6 +157,37 @@ impl<S: Struct> FrameDisassembler<S> {
         
         Some(transport_frame)
     }
+
+    /// Fills `transport_frame` with the next bytes of a multi frame transfer with the CRC after the payload
+    fn payload_end_frame<T: TransferFrame>(&mut self, transport_frame: &mut T, max_data_length: usize) {
+        let (crc, padding_length, length) = self.trailer.expect("The CRC is calculated before the first frame");
+        let transfer_length = length + padding_length + C::LENGTH;
+        let data_length = ::lib::core::cmp::min(max_data_length - 1, transfer_length - self.offset);
+
+        let serialized_length = ::lib::core::cmp::min(data_length, length.saturating_sub(self.offset));
+        if serialized_length > 0 {
+            let mut buffer = SerializationBuffer::with_empty_buffer(&mut transport_frame.data_as_mut()[0..serialized_length]);
+            self.serializer.serialize(&mut buffer);
+        }
+        for index in serialized_length..data_length {
+            let trailer_index = self.offset + index - length;
+            transport_frame.data_as_mut()[index] = if trailer_index < padding_length {
+                self.pad_byte
+            } else {
+                crc[trailer_index - padding_length]
+            };
+        }
+        self.offset += data_length;
+
+        // The padding was chosen to make the length of the last frame legal
+        let end_of_transfer = self.offset == transfer_length;
+        transport_frame.set_data_length(data_length + 1);
+        transport_frame.data_as_mut()[data_length] = TailByte::new(!self.started, end_of_transfer, self.toggle, self.transfer_id).into();
+
+        self.finished = end_of_transfer;
+        self.started = true;
+        self.toggle = !self.toggle;
+    }
 }
 
 
@@ -144,7 +248,7 @@ mod tests {
         }
         
         #[derive(Debug, PartialEq, Clone, UavcanStruct)]
-        #[DataTypeSignature = "0xd654a48e0c049d75"]
+        #[uavcan(signature = 0xd654a48e0c049d75)]
         struct LogMessage {
             level: LogLevel,
             source: Dynamic<[u8; 31]>,
@@ -206,4 +310,164 @@ mod tests {
        
     }
 
+    #[derive(Debug, PartialEq)]
+    struct QuantizedFrame {
+        id: TransferFrameID,
+        dlc: usize,
+        data: [u8; 8],
+    }
+
+    impl TransferFrame for QuantizedFrame {
+        const MAX_DATA_LENGTH: usize = 8;
+
+        fn new(id: TransferFrameID) -> QuantizedFrame {
+            QuantizedFrame{id: id, dlc: 0, data: [0; 8]}
+        }
+
+        fn set_data_length(&mut self, length: usize) {
+            assert!(length <= 8);
+            self.dlc = length;
+        }
+
+        fn data(&self) -> &[u8] {
+            &self.data[0..self.dlc]
+        }
+
+        fn data_as_mut(&mut self) -> &mut[u8] {
+            &mut self.data[0..self.dlc]
+        }
+
+        fn id(&self) -> TransferFrameID {
+            self.id
+        }
+
+        fn quantize_data_length(length: usize) -> usize {
+            match length {
+                0..=2 => length,
+                3..=4 => 4,
+                _ => 8,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    #[uavcan(message, id = 20000)]
+    struct Counters {
+        first: u32,
+        second: u32,
+        third: u16,
+    }
+
+    #[test]
+    fn pad_single_frame() {
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Counter {
+            value: u32,
+        }
+
+        impl Message for Counter {
+            const TYPE_ID: Option<u16> = Some(20000);
+        }
+
+        let uavcan_frame = Frame::from_message(Counter{value: 1}, 0, NodeID::new(32));
+        let mut frame_generator = FrameDisassembler::from_uavcan_frame(uavcan_frame, TransferID::new(0)).with_pad_byte(0x55);
+
+        assert_eq!(
+            frame_generator.next_transfer_frame(),
+            Some(QuantizedFrame{
+                id: TransferFrameID::new(5120032),
+                dlc: 8,
+                data: [1, 0, 0, 0, 0x55, 0x55, 0x55, TailByte::new(true, true, false, TransferID::new(0)).into()],
+            })
+        );
+        assert_eq!(frame_generator.next_transfer_frame::<QuantizedFrame>(), None);
+    }
+
+    #[test]
+    fn pad_multi_frame() {
+        let uavcan_frame = Frame::from_message(Counters{first: 1, second: 2, third: 3}, 0, NodeID::new(32));
+        let mut frame_generator = FrameDisassembler::from_uavcan_frame(uavcan_frame, TransferID::new(0)).with_pad_byte(0x55);
+
+        let crc = frame_generator.serializer.crc_with_padding(0, 2, 0x55);
+
+        assert_eq!(
+            frame_generator.next_transfer_frame(),
+            Some(QuantizedFrame{
+                id: TransferFrameID::new(5120032),
+                dlc: 8,
+                data: [crc.get_bits(0..8) as u8, crc.get_bits(8..16) as u8, 1, 0, 0, 0, 2, TailByte::new(true, false, false, TransferID::new(0)).into()],
+            })
+        );
+
+        assert_eq!(
+            frame_generator.next_transfer_frame(),
+            Some(QuantizedFrame{
+                id: TransferFrameID::new(5120032),
+                dlc: 8,
+                data: [0, 0, 0, 3, 0, 0x55, 0x55, TailByte::new(false, true, true, TransferID::new(0)).into()],
+            })
+        );
+
+        assert_eq!(frame_generator.next_transfer_frame::<QuantizedFrame>(), None);
+    }
+
+    #[test]
+    fn serialize_with_reduced_mtu() {
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct, Default)]
+        struct NodeStatus {
+            uptime_sec: u32,
+            health: u2,
+            mode: u3,
+            sub_mode: u3,
+            vendor_specific_status_code: u16,
+        }
+
+        impl Message for NodeStatus {
+            const TYPE_ID: Option<u16> = Some(341);
+        }
+
+        let uavcan_frame = Frame::from_message(NodeStatus{
+            uptime_sec: 1,
+            health: u2::new(2),
+            mode: u3::new(3),
+            sub_mode: u3::new(4),
+            vendor_specific_status_code: 5,
+        }, 0, NodeID::new(32));
+
+        let mut frame_generator = FrameDisassembler::from_uavcan_frame(uavcan_frame, TransferID::new(0));
+
+        let crc = frame_generator.serializer.crc(0);
+
+        assert_eq!(
+            frame_generator.next_transfer_frame_with_mtu(4),
+            Some(CanFrame{
+                id: TransferFrameID::new(87328),
+                dlc: 4,
+                data: [crc.get_bits(0..8) as u8, crc.get_bits(8..16) as u8, 1, TailByte::new(true, false, false, TransferID::new(0)).into(), 0, 0, 0, 0],
+            })
+        );
+
+        assert_eq!(
+            frame_generator.next_transfer_frame_with_mtu(4),
+            Some(CanFrame{
+                id: TransferFrameID::new(87328),
+                dlc: 4,
+                data: [0, 0, 0, TailByte::new(false, false, true, TransferID::new(0)).into(), 0, 0, 0, 0],
+            })
+        );
+
+        assert_eq!(
+            frame_generator.next_transfer_frame_with_mtu(4),
+            Some(CanFrame{
+                id: TransferFrameID::new(87328),
+                dlc: 4,
+                data: [0b10011100, 5, 0, TailByte::new(false, true, false, TransferID::new(0)).into(), 0, 0, 0, 0],
+            })
+        );
+
+        assert_eq!(frame_generator.next_transfer_frame_with_mtu::<CanFrame>(4), None);
+    }
+
 }
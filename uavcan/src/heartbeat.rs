@@ -0,0 +1,323 @@
+//! Periodic publication of the node status
+//!
+//! Every Uavcan node has to broadcast its status at least once a second. In Uavcan v0 the heartbeat is
+//! `uavcan.protocol.NodeStatus`, in Uavcan v1 it is `uavcan.node.Heartbeat`.
+//! A `HeartbeatPublisher` keeps the health and mode of the node, counts the uptime with a `Clock`
+//! and broadcasts the status through a `Node` when it's polled.
+//!
+//! `Node` only carries the v0 encoding, so `poll` broadcasts `NodeStatus`. A node on a v1 transport takes
+//! `HeartbeatPublisher::heartbeat` when `due` returns a status, and publishes it with its `v1::Composite`
+//! encoding on `Heartbeat::PORT_ID`.
+//!
+//! # Examples
+//! ```no_run
+//! # use std::time::Instant;
+//! # use uavcan::Node;
+//! # use uavcan::transfer::IOError;
+//! # use uavcan::types::*;
+//! use uavcan::heartbeat::HeartbeatPublisher;
+//! use uavcan::regulated::protocol::NodeStatus;
+//!
+//! # fn main() {}
+//! # fn run<N: Node>(node: N) -> Result<(), IOError> {
+//! let start = Instant::now();
+//! let mut heartbeat = HeartbeatPublisher::new(move || start.elapsed().as_millis() as u64);
+//!
+//! // Initialization is done
+//! heartbeat.set_mode(u3::new(NodeStatus::MODE_OPERATIONAL));
+//!
+//! loop {
+//!     heartbeat.poll(&node)?;
+//!     node.process_pending();
+//! }
+//! # }
+//! ```
+
+use types::*;
+
+use node::Node;
+use regulated::protocol::NodeStatus;
+use regulated::node::{Heartbeat, Health, Mode};
+
+use embedded_types::io::Error as IOError;
+
+/// A monotonic clock
+pub trait Clock {
+    /// Returns the time in milliseconds since an arbitrary point in the past
+    fn now_ms(&self) -> u64;
+}
+
+impl<F: Fn() -> u64> Clock for F {
+    fn now_ms(&self) -> u64 {
+        self()
+    }
+}
+
+/// Broadcasts `NodeStatus` a little more often than once a second
+///
+/// The uptime is counted from the creation of the publisher. The node starts out healthy and in
+/// `MODE_INITIALIZATION`, the application is expected to switch to `MODE_OPERATIONAL` when it is ready.
+pub struct HeartbeatPublisher<C: Clock> {
+    clock: C,
+    start_ms: u64,
+    next_ms: u64,
+    health: u2,
+    mode: u3,
+    sub_mode: u3,
+    vendor_specific_status_code: u16,
+}
+
+impl<C: Clock> HeartbeatPublisher<C> {
+    /// The period between two broadcasts
+    ///
+    /// The period is shorter than `NodeStatus::MAX_BROADCASTING_PERIOD_MS` (and `Heartbeat::MAX_PUBLICATION_PERIOD`),
+    /// leaving a margin for the time between polls and the time the status waits in the transmit queue.
+    pub const PERIOD_MS: u64 = NodeStatus::MAX_BROADCASTING_PERIOD_MS as u64 - 100;
+
+    /// Creates a publisher that broadcasts the first status when it's first polled
+    pub fn new(clock: C) -> Self {
+        let now = clock.now_ms();
+        HeartbeatPublisher {
+            clock,
+            start_ms: now,
+            next_ms: now,
+            health: u2::new(NodeStatus::HEALTH_OK),
+            mode: u3::new(NodeStatus::MODE_INITIALIZATION),
+            sub_mode: u3::new(0),
+            vendor_specific_status_code: 0,
+        }
+    }
+
+    pub fn health(&self) -> u2 {
+        self.health
+    }
+
+    /// Sets the health reported by the node, like `NodeStatus::HEALTH_WARNING`
+    pub fn set_health(&mut self, health: u2) {
+        self.health = health;
+    }
+
+    pub fn mode(&self) -> u3 {
+        self.mode
+    }
+
+    /// Sets the mode reported by the node, like `NodeStatus::MODE_OPERATIONAL`
+    ///
+    /// The sub mode is reset, it is specific to the mode.
+    pub fn set_mode(&mut self, mode: u3) {
+        self.mode = mode;
+        self.sub_mode = u3::new(0);
+    }
+
+    pub fn sub_mode(&self) -> u3 {
+        self.sub_mode
+    }
+
+    pub fn set_sub_mode(&mut self, sub_mode: u3) {
+        self.sub_mode = sub_mode;
+    }
+
+    pub fn vendor_specific_status_code(&self) -> u16 {
+        self.vendor_specific_status_code
+    }
+
+    pub fn set_vendor_specific_status_code(&mut self, code: u16) {
+        self.vendor_specific_status_code = code;
+    }
+
+    /// Returns the seconds elapsed since the publisher was created, saturating at `u32::MAX`
+    pub fn uptime_sec(&self) -> u32 {
+        let uptime = self.clock.now_ms().saturating_sub(self.start_ms) / 1000;
+        if uptime > u64::from(::lib::core::u32::MAX) { ::lib::core::u32::MAX } else { uptime as u32 }
+    }
+
+    /// Returns the current status of the node
+    pub fn status(&self) -> NodeStatus {
+        NodeStatus {
+            uptime_sec: self.uptime_sec(),
+            health: self.health,
+            mode: self.mode,
+            sub_mode: self.sub_mode,
+            vendor_specific_status_code: self.vendor_specific_status_code,
+        }
+    }
+
+    /// Returns the current status of the node as the Uavcan v1 `uavcan.node.Heartbeat`
+    ///
+    /// The health and mode values are the same in both versions, the v0 `MODE_OFFLINE` is passed through
+    /// as a value v1 reserves. The v1 vendor specific status code is 8 bits long and takes the lowest byte.
+    pub fn heartbeat(&self) -> Heartbeat {
+        Heartbeat {
+            uptime: self.uptime_sec(),
+            health: Health{value: self.health},
+            mode: Mode{value: self.mode},
+            vendor_specific_status_code: self.vendor_specific_status_code as u8,
+        }
+    }
+
+    /// Returns the status if it is due for broadcasting, and schedules the next broadcast
+    ///
+    /// Broadcasts are scheduled a period apart without accumulating drift.
+    /// If the publisher wasn't polled for more than a period, the missed broadcasts are skipped.
+    pub fn due(&mut self) -> Option<NodeStatus> {
+        let now = self.clock.now_ms();
+        if now < self.next_ms {
+            return None;
+        }
+
+        self.next_ms += Self::PERIOD_MS;
+        if self.next_ms <= now {
+            self.next_ms = now + Self::PERIOD_MS;
+        }
+        Some(self.status())
+    }
+
+    /// Broadcasts the status on `node` if it is due, returning true if it was broadcasted
+    ///
+    /// Nothing is broadcasted while the node is listen-only or asleep, a status that is due is then broadcasted
+    /// when the node is polled after waking up. A broadcast that fails is not retried before the next period.
+    ///
+    /// The health is reported as set by the application. To report bus errors as well,
+    /// the application can take `BusState::health` of the node into account when setting it.
+    pub fn poll<N: Node>(&mut self, node: &N) -> Result<bool, IOError> {
+        if node.is_listen_only() || node.is_asleep() {
+            return Ok(false);
+        }
+
+        match self.due() {
+            Some(status) => node.broadcast(status).map(|()| true),
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use lib::core::cell::Cell;
+
+    use types::*;
+    use heartbeat::*;
+    use regulated::protocol::NodeStatus;
+    use regulated::node::{Heartbeat, Health, Mode};
+    use v1::Composite;
+
+    #[cfg(feature="std")]
+    use {SimpleNode, NodeConfig, NodeID};
+    #[cfg(feature="std")]
+    use storage::HeapStorage;
+    #[cfg(feature="std")]
+    use transfer::BusState;
+    #[cfg(feature="std")]
+    use tests::{CanFrame, MockInterface};
+
+    #[cfg(feature="std")]
+    type TestNode<'a> = SimpleNode<MockInterface, &'a MockInterface, HeapStorage<CanFrame>>;
+
+    #[cfg(feature="std")]
+    fn config() -> NodeConfig {
+        NodeConfig{id: Some(NodeID::new(1)), ..NodeConfig::default()}
+    }
+
+    #[test]
+    fn schedule() {
+        let now = Cell::new(5_000u64);
+        let mut heartbeat = HeartbeatPublisher::new(|| now.get());
+
+        let status = heartbeat.due().unwrap();
+        assert_eq!(status.uptime_sec, 0);
+        assert_eq!(status.mode, u3::new(NodeStatus::MODE_INITIALIZATION));
+        assert!(heartbeat.due().is_none());
+
+        // Polling late doesn't delay the next broadcast
+        now.set(6_300);
+        heartbeat.set_mode(u3::new(NodeStatus::MODE_OPERATIONAL));
+        heartbeat.set_health(u2::new(NodeStatus::HEALTH_WARNING));
+        let status = heartbeat.due().unwrap();
+        assert_eq!(status.uptime_sec, 1);
+        assert_eq!(status.mode, u3::new(NodeStatus::MODE_OPERATIONAL));
+        assert_eq!(status.health, u2::new(NodeStatus::HEALTH_WARNING));
+        now.set(6_799);
+        assert!(heartbeat.due().is_none());
+        now.set(6_800);
+        assert!(heartbeat.due().is_some());
+
+        // Missed broadcasts are skipped
+        now.set(12_500);
+        assert_eq!(heartbeat.due().unwrap().uptime_sec, 7);
+        assert!(heartbeat.due().is_none());
+        now.set(13_400);
+        assert!(heartbeat.due().is_some());
+    }
+
+    #[test]
+    fn period_margin() {
+        assert!(HeartbeatPublisher::<fn() -> u64>::PERIOD_MS < u64::from(NodeStatus::MAX_BROADCASTING_PERIOD_MS));
+        assert!(HeartbeatPublisher::<fn() -> u64>::PERIOD_MS < u64::from(Heartbeat::MAX_PUBLICATION_PERIOD) * 1000);
+    }
+
+    #[test]
+    fn v1_heartbeat() {
+        let now = Cell::new(0u64);
+        let mut heartbeat = HeartbeatPublisher::new(|| now.get());
+        heartbeat.set_health(u2::new(NodeStatus::HEALTH_ERROR));
+        heartbeat.set_mode(u3::new(NodeStatus::MODE_MAINTENANCE));
+        heartbeat.set_vendor_specific_status_code(0x1234);
+        now.set(42_000);
+
+        let expected = Heartbeat {
+            uptime: 42,
+            health: Health{value: u2::new(Health::CAUTION)},
+            mode: Mode{value: u3::new(Mode::MAINTENANCE)},
+            vendor_specific_status_code: 0x34,
+        };
+        assert_eq!(heartbeat.heartbeat(), expected);
+
+        let mut data = [0u8; Heartbeat::MAX_BYTES];
+        assert_eq!(heartbeat.heartbeat().serialize(&mut data), Ok(7));
+        assert_eq!(data, [42, 0, 0, 0, 2, 2, 0x34]);
+    }
+
+    #[test]
+    #[cfg(feature="std")]
+    fn poll() {
+        let now = Cell::new(0u64);
+        let mut heartbeat = HeartbeatPublisher::new(|| now.get());
+        let interface = MockInterface::new();
+        let node: TestNode = SimpleNode::new(&interface, config());
+
+        assert_eq!(heartbeat.poll(&node), Ok(true));
+        assert_eq!(heartbeat.poll(&node), Ok(false));
+        node.flush_transmissions();
+        assert_eq!(interface.tx.borrow().len(), 1);
+
+        // The health of the bus isn't merged into the health set by the application
+        interface.bus_state.set(BusState::ErrorPassive);
+        now.set(1_000);
+        assert_eq!(heartbeat.poll(&node), Ok(true));
+        node.flush_transmissions();
+        let frame = interface.tx.borrow_mut().pop_back().unwrap();
+        assert_eq!(frame.data[4] >> 6, NodeStatus::HEALTH_OK);
+
+        // A sleeping node doesn't broadcast, the status is broadcasted when the node wakes up
+        now.set(2_000);
+        assert!(node.sleep());
+        assert_eq!(heartbeat.poll(&node), Ok(false));
+        node.wake();
+        now.set(2_500);
+        assert_eq!(heartbeat.poll(&node), Ok(true));
+    }
+
+    #[test]
+    #[cfg(feature="std")]
+    fn poll_listen_only() {
+        let now = Cell::new(0u64);
+        let mut heartbeat = HeartbeatPublisher::new(|| now.get());
+        let interface = MockInterface::new();
+        let node: TestNode = SimpleNode::new(&interface, NodeConfig{listen_only: true, ..config()});
+
+        assert_eq!(heartbeat.poll(&node), Ok(false));
+        node.flush_transmissions();
+        assert!(interface.tx.borrow().is_empty());
+    }
+}
@@ -0,0 +1,289 @@
+//! JSON rendering of decoded transfers, for bus monitors and golden-file tests
+//!
+//! Values are rendered like the builtin form of pycyphal (`pycyphal.dsdl.to_builtin`): composites are objects keyed by field name,
+//! unions are objects with the name of the selected variant as the only key, arrays are lists and void fields are left out.
+//! Dynamic arrays of `uint8` holding valid UTF-8 are rendered as strings.
+//!
+//! Types known at compile time are rendered through their serde implementation, types only known at runtime
+//! through a `reflection::FieldType`. Comparing `serde_json::Value`s rather than strings ignores the order of the keys.
+//!
+//! ## Example
+//! ```
+//! # extern crate uavcan;
+//! # #[macro_use]
+//! # extern crate serde_json;
+//! use uavcan::reflection::*;
+//!
+//! // uavcan.protocol.NodeStatus
+//! static NODE_STATUS: FieldType = FieldType::Composite(&[
+//!     Field { name: "uptime_sec", ty: FieldType::Unsigned(32) },
+//!     Field { name: "health", ty: FieldType::Unsigned(2) },
+//!     Field { name: "mode", ty: FieldType::Unsigned(3) },
+//!     Field { name: "sub_mode", ty: FieldType::Unsigned(3) },
+//!     Field { name: "vendor_specific_status_code", ty: FieldType::Unsigned(16) },
+//! ]);
+//!
+//! # fn main() {
+//! let value = uavcan::json::reflect(&NODE_STATUS, &[1, 0, 0, 0, 0b10011100, 5, 0]).unwrap();
+//! assert_eq!(value, json!({
+//!     "uptime_sec": 1,
+//!     "health": 2,
+//!     "mode": 3,
+//!     "sub_mode": 4,
+//!     "vendor_specific_status_code": 5,
+//! }));
+//! # }
+//! ```
+
+use std::str;
+
+use serde::Serialize;
+
+pub use serde_json::Value;
+use serde_json::Map;
+
+use Struct;
+use Deserializer;
+
+use reflection;
+use reflection::{
+    FieldType,
+    ReflectionError,
+    Visitor,
+};
+
+/// Renders a value through its serde implementation
+pub fn to_json<T: Serialize>(value: &T) -> Value {
+    // The serde implementations of the crate only produce values representable in JSON
+    ::serde_json::to_value(value).expect("the value can't be represented in JSON")
+}
+
+/// The error when a payload can't be decoded as a type known at compile time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The payload is longer than the largest serialized structure, with the length of the payload
+    InvalidLength(usize),
+
+    /// The payload can't be deserialized, like a union tag without a variant
+    Deserialization,
+}
+
+/// Deserializes the payload of a transfer as `T` and renders it
+///
+/// A payload shorter than `T` is zero extended. Fails like `Deserializer::into_structure` if the missing bits can't be zero.
+pub fn decode<T: Struct + Serialize>(payload: &[u8]) -> Result<Value, DecodeError> {
+    if payload.len() > T::MAX_BYTES {
+        return Err(DecodeError::InvalidLength(payload.len()));
+    }
+    let mut deserializer = Deserializer::<T>::new();
+    deserializer.deserialize_chunk(payload);
+    deserializer.into_structure().map(|structure| to_json(&structure)).map_err(|()| DecodeError::Deserialization)
+}
+
+/// Deserializes the payload of a transfer as a value of type `ty` and renders it
+pub fn reflect(ty: &FieldType, payload: &[u8]) -> Result<Value, ReflectionError> {
+    let mut builder = Builder{stack: Vec::new(), result: Value::Null};
+    reflection::deserialize(ty, payload, &mut builder)?;
+    let mut value = builder.result;
+    stringify(ty, &mut value);
+    Ok(value)
+}
+
+/// An object or list under construction
+enum Node {
+    Object(String, Map<String, Value>),
+    List(String, Vec<Value>),
+    /// A union, closed by its variant
+    Union(String, Map<String, Value>),
+}
+
+/// Builds a `Value` from the fields reported by `reflection::deserialize`
+struct Builder {
+    stack: Vec<Node>,
+    result: Value,
+}
+
+impl Builder {
+    fn insert(&mut self, name: &str, value: Value) {
+        match self.stack.pop() {
+            Some(Node::Object(object_name, mut fields)) => {
+                fields.insert(name.to_string(), value);
+                self.stack.push(Node::Object(object_name, fields));
+            },
+            Some(Node::List(list_name, mut elements)) => {
+                elements.push(value);
+                self.stack.push(Node::List(list_name, elements));
+            },
+            Some(Node::Union(union_name, mut variant)) => {
+                variant.insert(name.to_string(), value);
+                self.insert(&union_name, Value::Object(variant));
+            },
+            None => self.result = value,
+        }
+    }
+}
+
+impl Visitor for Builder {
+    fn value(&mut self, name: &str, value: reflection::Value) {
+        let value = match value {
+            reflection::Value::Unsigned(value) => Value::from(value),
+            reflection::Value::Signed(value) => Value::from(value),
+            // NaN and the infinities are rendered as null
+            reflection::Value::Float(value) => Value::from(value),
+            reflection::Value::Bool(value) => Value::from(value),
+        };
+        self.insert(name, value);
+    }
+
+    fn begin_composite(&mut self, name: &str) {
+        self.stack.push(Node::Object(name.to_string(), Map::new()));
+    }
+
+    fn end_composite(&mut self, _name: &str) {
+        if let Some(Node::Object(name, fields)) = self.stack.pop() {
+            self.insert(&name, Value::Object(fields));
+        }
+    }
+
+    fn begin_array(&mut self, name: &str, length: usize) {
+        self.stack.push(Node::List(name.to_string(), Vec::with_capacity(length)));
+    }
+
+    fn end_array(&mut self, _name: &str) {
+        if let Some(Node::List(name, elements)) = self.stack.pop() {
+            self.insert(&name, Value::Array(elements));
+        }
+    }
+
+    fn union_variant(&mut self, name: &str, _variant: &str) {
+        self.stack.push(Node::Union(name.to_string(), Map::new()));
+    }
+}
+
+/// Replaces the dynamic arrays of `uint8` holding valid UTF-8 in `value` (of type `ty`) by strings
+fn stringify(ty: &FieldType, value: &mut Value) {
+    match (*ty, value) {
+        (FieldType::DynamicArray(&FieldType::Unsigned(8), _), value) => {
+            let text = match *value {
+                Value::Array(ref elements) => {
+                    let bytes: Vec<u8> = elements.iter().filter_map(|element| element.as_u64()).map(|byte| byte as u8).collect();
+                    str::from_utf8(&bytes).ok().map(|text| text.to_string())
+                },
+                _ => None,
+            };
+            if let Some(text) = text {
+                *value = Value::String(text);
+            }
+        },
+        (FieldType::StaticArray(element, _), &mut Value::Array(ref mut elements))
+            | (FieldType::DynamicArray(element, _), &mut Value::Array(ref mut elements)) => {
+            for value in elements.iter_mut() {
+                stringify(element, value);
+            }
+        },
+        (FieldType::Composite(fields), &mut Value::Object(ref mut object))
+            | (FieldType::Union(fields), &mut Value::Object(ref mut object)) => {
+            for field in fields {
+                if let Some(value) = object.get_mut(field.name) {
+                    stringify(&field.ty, value);
+                }
+            }
+        },
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use *;
+    use json::*;
+    use types::*;
+    use serializer::Serializer;
+    use reflection::Field;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    struct Reading {
+        sensor: u5,
+        _padding: void3,
+        value: f16,
+        samples: Dynamic<[i4; 4]>,
+        text: Utf8String<[u8; 8]>,
+    }
+
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    enum Choice {
+        First(u6),
+        Second(u6),
+        Third(u6),
+    }
+
+    static READING: FieldType = FieldType::Composite(&[
+        Field { name: "sensor", ty: FieldType::Unsigned(5) },
+        Field { name: "_padding", ty: FieldType::Void(3) },
+        Field { name: "value", ty: FieldType::Float(16) },
+        Field { name: "samples", ty: FieldType::DynamicArray(&FieldType::Signed(4), 4) },
+        Field { name: "text", ty: FieldType::DynamicArray(&FieldType::Unsigned(8), 8) },
+    ]);
+
+    static COMMAND: FieldType = FieldType::Union(&[
+        Field { name: "empty", ty: FieldType::Composite(&[]) },
+        Field { name: "readings", ty: FieldType::StaticArray(&READING, 2) },
+    ]);
+
+    fn serialize(reading: &Reading, data: &mut [u8]) -> usize {
+        let mut serializer = Serializer::from_structure(reading.clone());
+        let mut buffer = SerializationBuffer::with_empty_buffer(data);
+        serializer.serialize(&mut buffer);
+        (buffer.bit_length() + 7) / 8
+    }
+
+    #[test]
+    fn reflect_matches_serde() {
+        let reading = Reading{
+            sensor: u5::new(17),
+            _padding: void3::default(),
+            value: f16::from_f32(-2.5),
+            samples: Dynamic::<[i4; 4]>::with_data(&[i4::new(-8), i4::new(7)]),
+            text: "abc".parse().unwrap(),
+        };
+
+        let mut data = [0u8; 32];
+        let length = serialize(&reading, &mut data);
+
+        let expected = json!({
+            "sensor": 17,
+            "value": -2.5,
+            "samples": [-8, 7],
+            "text": "abc",
+        });
+        assert_eq!(reflect(&READING, &data[..length]).unwrap(), expected);
+
+        // serde renders the void field as null
+        let mut decoded = decode::<Reading>(&data[..length]).unwrap();
+        decoded.as_object_mut().unwrap().remove("_padding");
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn unions_and_nested_arrays() {
+        // The tag selects `readings`, the second one has the text length 1 and the byte 0xff
+        let payload = [0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xfe];
+        assert_eq!(reflect(&COMMAND, &payload).unwrap(), json!({
+            "readings": [
+                {"sensor": 0, "value": 0.0, "samples": [], "text": ""},
+                {"sensor": 0, "value": 0.0, "samples": [], "text": [0xff]},
+            ],
+        }));
+        assert_eq!(reflect(&COMMAND, &[0x00]).unwrap(), json!({"empty": {}}));
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert_eq!(decode::<Choice>(&[0x55]), Ok(json!({"Second": 0x15})));
+        assert_eq!(decode::<Choice>(&[0x55, 0x00]), Err(DecodeError::InvalidLength(2)));
+        // The tag 3 selects no variant
+        assert_eq!(decode::<Choice>(&[0xc0]), Err(DecodeError::Deserialization));
+    }
+}
@@ -24,7 +24,36 @@ extern crate uavcan_derive;
 extern crate bit_field;
 extern crate embedded_types;
 extern crate ux;
-extern crate half;
+extern crate heapless;
+#[cfg(feature="alloc")]
+extern crate alloc;
+#[cfg(feature="critical-section")]
+extern crate critical_section;
+#[cfg(feature="libc")]
+extern crate libc;
+#[cfg(feature="rusb")]
+extern crate rusb;
+#[cfg(feature="embedded-can")]
+extern crate embedded_can;
+#[cfg(feature="nb")]
+extern crate nb;
+#[cfg(feature="smoltcp")]
+extern crate smoltcp;
+#[cfg(feature="embedded-hal")]
+extern crate embedded_hal;
+#[cfg(feature="embedded-io")]
+extern crate embedded_io;
+#[cfg(feature="serde")]
+#[doc(hidden)]
+pub extern crate serde;
+#[cfg(any(feature="json", all(test, feature="serde")))]
+extern crate serde_json;
+#[cfg(feature="defmt")]
+#[doc(hidden)]
+pub extern crate defmt;
+#[cfg(feature="testing")]
+#[doc(hidden)]
+pub extern crate proptest;
 
 mod lib {
     pub mod core {
@@ -47,14 +76,43 @@ mod uavcan {
 pub use uavcan_derive::*;
 
 pub mod transfer;
+pub mod async_transfer;
 pub mod types;
-mod crc;
+pub mod crc;
+pub mod signature;
+pub mod reflection;
+pub mod bits;
+pub mod v1;
+#[cfg(feature="json")]
+pub mod json;
+#[cfg(feature="testing")]
+pub mod testing;
 mod deserializer;
+mod float_codec;
 mod frame_assembler;
 mod serializer;
 mod frame_disassembler;
+#[cfg(feature="serde")]
+#[doc(hidden)]
+pub mod serde_field;
+#[cfg(feature="defmt")]
+#[doc(hidden)]
+pub mod defmt_field;
+#[doc(hidden)]
+pub mod debug_field;
 pub mod node;
 pub mod storage;
+pub mod session;
+pub mod bit_rate;
+pub mod udp;
+pub mod serial;
+pub mod backend;
+#[cfg(feature="regulated")]
+pub mod regulated;
+#[cfg(feature="regulated")]
+pub mod heartbeat;
+#[cfg(feature="critical-section")]
+pub mod shared_interface;
 
 use bit_field::BitField;
 
@@ -62,9 +120,11 @@ use transfer::TransferFrameID;
 
 
 pub use node::NodeConfig;
+pub use node::StandardFramePolicy;
 pub use node::NodeID;
 pub use node::Node;
 pub use node::SimpleNode;
+pub use node::MultiNode;
 
 
 /// These data type is only exposed so `Struct` can be derived.
@@ -85,6 +145,23 @@ pub use deserializer::{
     DeserializationBuffer,
 };
 
+pub use deserializer::Deserializer;
+
+/// This function is only exposed so the borrowing accessors of `uint8[]` fields can be derived.
+/// It is not intended for use outside the derive macro and
+/// must not be considered as a stable part of the API.
+#[doc(hidden)]
+pub use deserializer::borrow_byte_array;
+
+
+/// This function is only exposed so `Struct` can be derived for unions.
+/// It is not intended for use outside the derive macro and
+/// must not be considered as a stable part of the API.
+#[doc(hidden)]
+pub unsafe fn zeroed<T>() -> T {
+    lib::core::mem::zeroed()
+}
+
 
 /// The trait that needs to be implemented for all types that will be sent over Uavcan
 ///
@@ -124,17 +201,58 @@ pub trait Serializable {
     ///
     /// assert_eq!(Foo::BIT_LENGTH_MIN, 2 + 62*4 + 2);
     ///
-    /// // Enums have the minimum of all variants `MIN_BIT_LENGTH` as their `MIN_BIT_LENGTH`.
-    /// // (But this is not implemented yet)
+    /// // Enums are unions, their `MIN_BIT_LENGTH` is the length of the tag and the smallest `MIN_BIT_LENGTH` of the variants.
+    /// #[derive(UavcanStruct)]
+    /// enum Bar {
+    ///     V1(u8),
+    ///     V2(u3),
+    ///     V3(Dynamic<[void11; 3]>),
+    /// }
+    ///
+    /// assert_eq!(Bar::BIT_LENGTH_MIN, 2 + 2);
     ///
     /// # }
     /// ```
     const BIT_LENGTH_MIN: usize;
+
+    /// The maximum bit length an uavcan type can have
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate uavcan;
+    /// # use uavcan::Struct;
+    /// # use uavcan::types::*;
+    /// # use uavcan::Serializable;
+    ///
+    /// # fn main() {
+    /// // The dynamic arrays have their length coding and all elements included
+    /// assert_eq!(Dynamic::<[u8; 3]>::BIT_LENGTH_MAX, 2 + 8*3);
+    ///
+    /// // Enums have the length of the tag and the largest `BIT_LENGTH_MAX` of the variants.
+    /// #[derive(UavcanStruct)]
+    /// enum Bar {
+    ///     V1(u8),
+    ///     V2(u3),
+    ///     V3(Dynamic<[void11; 3]>),
+    /// }
+    ///
+    /// assert_eq!(Bar::BIT_LENGTH_MAX, 2 + 2 + 11*3);
+    /// # }
+    /// ```
+    const BIT_LENGTH_MAX: usize;
+
+    /// The minimum bit length when serialized as the last field, where tail array optimization may leave out the length of a dynamic array
+    const TAIL_BIT_LENGTH_MIN: usize = Self::BIT_LENGTH_MIN;
+
+    /// The maximum bit length when serialized as the last field, where tail array optimization may leave out the length of a dynamic array
+    const TAIL_BIT_LENGTH_MAX: usize = Self::BIT_LENGTH_MAX;
     
     /// Number of primitive fields after flattening of data type.
     ///
     /// Flattening of a struct consists of replacing all structs with its fields.
-    /// Flattening of an enum consists of putting all fields in order
+    /// Flattening of an enum consists of putting the union tag and then all fields in order
     ///
     /// # Examples
     /// ## Flattening of struct
@@ -179,8 +297,8 @@ pub trait Serializable {
     /// }
     ///
     /// # fn main() {
-    /// assert_eq!(InnerEnum::FLATTENED_FIELDS_NUMBER, 2);
-    /// assert_eq!(OuterEnum::FLATTENED_FIELDS_NUMBER, 4);
+    /// assert_eq!(InnerEnum::FLATTENED_FIELDS_NUMBER, 3);
+    /// assert_eq!(OuterEnum::FLATTENED_FIELDS_NUMBER, 7);
     /// # }
     /// ```
     const FLATTENED_FIELDS_NUMBER: usize;
@@ -189,20 +307,172 @@ pub trait Serializable {
     fn deserialize(&mut self, flattened_field: &mut usize, bit: &mut usize, last_field: bool, buffer: &mut DeserializationBuffer) -> DeserializationResult;
 }
 
+/// A structure that can be sent as the payload of a transfer, usually derived with `UavcanStruct`
+///
+/// Fields of native integer, float and bool types can be serialized in another bit length with `#[uavcan(bits = N)]`.
+/// The values are saturated to the bit length when serialized.
+/// Generic structures can be derived as well, with the type parameters bounded by `Serializable`.
+///
+/// `#[uavcan(max_bytes = N)]` fails the compilation if the structure can take more than `N` bytes,
+/// like a message that must fit a single frame.
+///
+/// `#[uavcan(debug)]` derives `Debug` with the decoded values of the fields instead of their wrapper types.
+/// A field with `#[uavcan(names(HEALTH_OK, HEALTH_ERROR))]` is followed by the name of the associated constant it is equal to.
+/// With the `testing` feature, `#[uavcan(arbitrary)]` implements proptest's `Arbitrary` and `testing::ArbitraryField` (see the `testing` module).
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate uavcan;
+/// # use uavcan::Struct;
+/// # use uavcan::types::*;
+/// #[derive(UavcanStruct)]
+/// #[uavcan(max_bytes = 7)]
+/// struct NodeStatus {
+///     uptime_sec: u32,
+///     #[uavcan(bits = 2)]
+///     health: u8,
+///     #[uavcan(bits = 3)]
+///     mode: u8,
+///     #[uavcan(bits = 3)]
+///     sub_mode: u8,
+///     vendor_specific_status_code: u16,
+/// }
+///
+/// # fn main() {
+/// assert_eq!(NodeStatus::MAX_BITS, 56);
+/// # }
+/// ```
 pub trait Struct: Sized + Serializable {
     const DSDL_SIGNATURE: u64;
     const DATA_TYPE_SIGNATURE: u64;
+
+    /// The minimum length of the serialized structure in bits, with tail array optimization applied
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate uavcan;
+    /// # use uavcan::Struct;
+    /// # use uavcan::types::*;
+    ///
+    /// #[derive(UavcanStruct)]
+    /// struct LogMessage {
+    ///     level: u3,
+    ///     source: Dynamic<[u8; 31]>,
+    ///     text: Dynamic<[u8; 90]>,
+    /// }
+    ///
+    /// # fn main() {
+    /// // The length of `text` is left out by tail array optimization
+    /// assert_eq!(LogMessage::MIN_BITS, 3 + 5);
+    /// assert_eq!(LogMessage::MAX_BITS, 3 + 5 + 8*31 + 8*90);
+    /// assert_eq!(LogMessage::MAX_BYTES, 122);
+    /// # }
+    /// ```
+    const MIN_BITS: usize = Self::TAIL_BIT_LENGTH_MIN;
+
+    /// The maximum length of the serialized structure in bits, with tail array optimization applied
+    const MAX_BITS: usize = Self::TAIL_BIT_LENGTH_MAX;
+
+    /// The minimum length of the serialized structure in bytes
+    const MIN_BYTES: usize = (Self::MIN_BITS + 7) / 8;
+
+    /// The maximum length of the serialized structure in bytes
+    const MAX_BYTES: usize = (Self::MAX_BITS + 7) / 8;
+
+    /// The size of a buffer holding any serialized structure in bytes, the same as `MAX_BYTES`
+    const MAX_SERIALIZED_SIZE: usize = Self::MAX_BYTES;
+
+    /// The number of CAN 2.0 frames (8 bytes of data) the largest serialized structure takes, see `transfer::frame_count`
+    const MAX_CAN_FRAMES: usize = transfer::frame_count(Self::MAX_BYTES, 8);
 }
 
+/// A message, broadcasted with the data type ID `TYPE_ID` in the frame header
+///
+/// The data type ID and signature are attached to the type when it is derived.
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate uavcan;
+/// # use uavcan::Struct;
+/// # use uavcan::types::*;
+/// use uavcan::{Message, Request, Response};
+///
+/// #[derive(UavcanStruct)]
+/// #[uavcan(message, id = 341, signature = 0x0f0868d0c1a7c6f1)]
+/// struct NodeStatus {
+///     uptime_sec: u32,
+///     health: u2,
+///     mode: u3,
+///     sub_mode: u3,
+///     vendor_specific_status_code: u16,
+/// }
+///
+/// // The halves of a service name each other, the request carries the service type ID
+/// #[derive(UavcanStruct)]
+/// #[uavcan(id = 5, signature = 0x569e05394a3017f0, response = "RestartNodeResponse")]
+/// struct RestartNodeRequest {
+///     magic_number: u40,
+/// }
+///
+/// #[derive(UavcanStruct)]
+/// #[uavcan(signature = 0x569e05394a3017f0, request = "RestartNodeRequest")]
+/// struct RestartNodeResponse {
+///     ok: bool,
+/// }
+///
+/// # fn main() {
+/// assert_eq!(<NodeStatus as Message>::TYPE_ID, Some(341));
+/// assert_eq!(NodeStatus::DATA_TYPE_SIGNATURE, 0x0f0868d0c1a7c6f1);
+/// assert_eq!(<RestartNodeRequest as Request>::TYPE_ID, Some(5));
+/// assert_eq!(<RestartNodeResponse as Response>::TYPE_ID, Some(5));
+/// # }
+/// ```
 pub trait Message: Struct {
     const TYPE_ID: Option<u16>;
 }
 
+/// The request half of a service, `RESPONSE` is the other half
+///
+/// Both halves of a service can be derived, the request carries the service type ID and the response shares it.
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate uavcan;
+/// # use uavcan::Struct;
+/// # use uavcan::types::*;
+/// use uavcan::{Request, Response};
+///
+/// #[derive(UavcanStruct)]
+/// #[uavcan(id = 4, response = "GetTransportStatsResponse")]
+/// struct GetTransportStatsRequest {}
+///
+/// #[derive(UavcanStruct)]
+/// #[uavcan(request = "GetTransportStatsRequest")]
+/// struct GetTransportStatsResponse {
+///     transfers_tx: u48,
+///     transfers_rx: u48,
+///     transfer_errors: u48,
+/// }
+///
+/// # fn main() {
+/// assert_eq!(<GetTransportStatsRequest as Request>::TYPE_ID, Some(4));
+/// assert_eq!(<GetTransportStatsResponse as Response>::TYPE_ID, Some(4));
+/// # }
+/// ```
 pub trait Request: Struct {
     type RESPONSE: Response;
     const TYPE_ID: Option<u8>;
 }
 
+/// The response half of a service, `REQUEST` is the other half
 pub trait Response: Struct {
     type REQUEST: Request;
     const TYPE_ID: Option<u8>;
@@ -253,47 +523,38 @@ impl<T: Struct> Frame<T> {
         }
 
     }
+    */
 
-    pub fn from_request(request: T, priority: u8, source_node: NodeID, destination_node: NodeID) -> Self where T: Request{
-        if let Some(type_id) = T::TYPE_ID {
-            let mut id = 0;
-            id.set_bits(0..7, u32::from(source_node));
-            id.set_bit(7, false);
-            id.set_bits(8..15, u32::from(destination_node));
-            id.set_bit(15, true);
-            id.set_bits(16..24, u32::from(type_id));
-            id.set_bits(24..29, u32::from(priority));
-            
-            Frame::from_parts(
-                TransferFrameID::new(id),
-                request,
-            )
-        } else {
-            unimplemented!("Resolvation of type id is not supported yet")
-        }
-
+    /// A service request frame from `source_node` to `destination_node`, or `None` if the service has no type ID
+    #[cfg(any(test, feature="vcan-harness"))]
+    pub fn from_request(request: T, priority: u8, source_node: NodeID, destination_node: NodeID) -> Option<Self> where T: Request {
+        Self::from_service(request, <T as Request>::TYPE_ID, true, priority, source_node, destination_node)
     }
 
-    pub fn from_response(response: T, priority: u8, source_node: NodeID, destination_node: NodeID) -> Self where T: Response {
-        if let Some(type_id) = T::TYPE_ID {
-            let mut id = 0;
-            id.set_bits(0..7, u32::from(source_node));
-            id.set_bit(7, false);
-            id.set_bits(8..15, u32::from(destination_node));
-            id.set_bit(15, true);
-            id.set_bits(16..24, u32::from(type_id));
-            id.set_bits(24..29, u32::from(priority));
-
-            Frame::from_parts(
-                TransferFrameID::new(id),
-                response,
-            )
-        } else {
-            unimplemented!("Resolvation of type id is not supported yet")
-        }
+    /// A service response frame from `source_node` to `destination_node`, or `None` if the service has no type ID
+    #[cfg(any(test, feature="vcan-harness"))]
+    pub fn from_response(response: T, priority: u8, source_node: NodeID, destination_node: NodeID) -> Option<Self> where T: Response {
+        Self::from_service(response, <T as Response>::TYPE_ID, false, priority, source_node, destination_node)
+    }
 
+    #[cfg(any(test, feature="vcan-harness"))]
+    fn from_service(body: T, type_id: Option<u8>, request: bool, priority: u8, source_node: NodeID, destination_node: NodeID) -> Option<Self> {
+        // Resolvation of type ids is not supported yet
+        let type_id = type_id?;
+
+        let mut id = 0;
+        id.set_bits(0..7, u32::from(source_node));
+        id.set_bit(7, true);
+        id.set_bits(8..15, u32::from(destination_node));
+        id.set_bit(15, request);
+        id.set_bits(16..24, u32::from(type_id));
+        id.set_bits(24..29, u32::from(priority));
+
+        Some(Frame::from_parts(
+            TransferFrameID::new(id),
+            body,
+        ))
     }
-    */
     
     fn from_parts(id: TransferFrameID, body: T) -> Self {
         Frame{id: id, body: body}
@@ -309,20 +570,41 @@ impl<T: Struct> Frame<T> {
 
 
 
+#[cfg(test)]
+mod conformance;
+
 #[cfg(test)]
 mod tests {
 
     use *;
 
+    #[cfg(feature="std")]
+    use lib::core::cell::{Cell, RefCell};
+
+    #[cfg(feature="std")]
+    use heapless::Deque;
+
+    #[cfg(feature="std")]
+    use transfer::BusState;
+
     // Implementing some types common for several tests
     
-    #[derive(Debug, PartialEq)]
+    #[derive(Debug, PartialEq, Clone)]
     pub struct CanFrame {
         pub id: TransferFrameID,
         pub dlc: usize,
         pub data: [u8; 8],
     }
 
+    impl CanFrame {
+        /// Constructs a frame with the raw `id` carrying `data`
+        pub fn from_parts(id: u32, data: &[u8]) -> CanFrame {
+            let mut frame = CanFrame{id: TransferFrameID::new(id), dlc: data.len(), data: [0; 8]};
+            frame.data[..data.len()].copy_from_slice(data);
+            frame
+        }
+    }
+
     impl transfer::TransferFrame for CanFrame {
         const MAX_DATA_LENGTH: usize = 8;
         
@@ -344,10 +626,99 @@ mod tests {
         }
         
         fn id(&self) -> TransferFrameID {
-            self.id 
+            self.id
         }
     }
 
-    
-    
+    /// An interface backed by frame queues, for testing nodes without a bus
+    #[cfg(feature="std")]
+    pub struct MockInterface<F = CanFrame> {
+        /// Frames waiting to be received, in order
+        pub rx: RefCell<Deque<F, 64>>,
+
+        /// Frames handed over for transmission, in order
+        pub tx: RefCell<Deque<F, 64>>,
+
+        /// `transmit` reports `BufferExhausted` while `tx` holds this many frames
+        pub tx_capacity: Cell<usize>,
+
+        /// The number of frames handed over with `transmit_single_shot`
+        pub single_shots: Cell<usize>,
+        pub bus_state: Cell<BusState>,
+        pub listen_only: Cell<bool>,
+        pub sleeping: Cell<bool>,
+    }
+
+    #[cfg(feature="std")]
+    impl<F> MockInterface<F> {
+        pub const fn new() -> Self {
+            MockInterface {
+                rx: RefCell::new(Deque::new()),
+                tx: RefCell::new(Deque::new()),
+                tx_capacity: Cell::new(64),
+                single_shots: Cell::new(0),
+                bus_state: Cell::new(BusState::ErrorActive),
+                listen_only: Cell::new(false),
+                sleeping: Cell::new(false),
+            }
+        }
+
+        /// Queues `frame` for reception
+        pub fn push_rx(&self, frame: F) {
+            self.rx.borrow_mut().push_back(frame).ok().expect("Mock receive queue full");
+        }
+
+        /// Moves every frame transmitted on this interface to the receive queue of `other`
+        pub fn deliver_to(&self, other: &MockInterface<F>) {
+            while let Some(frame) = self.tx.borrow_mut().pop_front() {
+                other.push_rx(frame);
+            }
+        }
+    }
+
+    #[cfg(feature="std")]
+    impl<F: transfer::TransferFrame + Clone> transfer::TransferInterface for MockInterface<F> {
+        type Frame = F;
+
+        fn transmit(&self, frame: &F) -> Result<(), transfer::IOError> {
+            let mut tx = self.tx.borrow_mut();
+            if tx.len() >= self.tx_capacity.get() {
+                return Err(transfer::IOError::BufferExhausted);
+            }
+            tx.push_back(frame.clone()).ok().expect("Mock transmit queue full");
+            Ok(())
+        }
+
+        fn transmit_single_shot(&self, frame: &F) -> Result<(), transfer::IOError> {
+            self.transmit(frame)?;
+            self.single_shots.set(self.single_shots.get() + 1);
+            Ok(())
+        }
+
+        fn receive(&self) -> Option<F> {
+            self.rx.borrow_mut().pop_front()
+        }
+
+        fn bus_state(&self) -> BusState {
+            self.bus_state.get()
+        }
+
+        fn set_listen_only(&self, listen_only: bool) -> bool {
+            self.listen_only.set(listen_only);
+            true
+        }
+
+        fn sleep(&self) -> bool {
+            self.sleeping.set(true);
+            true
+        }
+
+        fn wake(&self) {
+            self.sleeping.set(false);
+        }
+
+        fn is_sleeping(&self) -> bool {
+            self.sleeping.get()
+        }
+    }
 }
@@ -1,6 +1,7 @@
 //! Everything related to Uavcan Nodes
 
 use lib::core::marker::PhantomData;
+use lib::core::cell::Cell;
 
 use {
     Frame,
@@ -17,9 +18,13 @@ use storage::{
 use transfer::{
     TransferInterface,
     TransferFrame,
+    TimestampedFrame,
     TransferFrameID,
     TransferID,
     TransferFrameIDFilter,
+    TransportStats,
+    BusState,
+    FrameKind,
 };
 
 use frame_disassembler::FrameDisassembler;
@@ -58,13 +63,70 @@ impl NodeID {
 /// The Uavcan node trait.
 ///
 /// Allows implementation of application level features genericaly for all types of Uavcan Nodes.
-pub trait Node<I: TransferInterface, S: Storage<I::Frame>> {
+pub trait Node {
 
-    /// Broadcast a `Message` on the Uavcan network. 
-    fn broadcast<T: Struct + Message>(&self, message: T) -> Result<(), IOError>;
+    /// The subscription handle returned by `subscribe`
+    type Subscriber<T: Struct + Message>: Receive<T>;
+
+    /// Broadcast a `Message` on the Uavcan network.
+    ///
+    /// The frames of the message are queued for transmission. If the queue can't take all of them,
+    /// the frames already queued are removed and `IOError::BufferExhausted` is returned.
+    /// Messages without a type ID are rejected with `IOError::InvalidInput`.
+    ///
+    /// Listen-only and sleeping nodes discard the message and return `Ok(())`,
+    /// use `is_listen_only` and `is_asleep` to tell whether a message would be transmitted.
+    /// Messages are `Clone` so that nodes with several transports can broadcast them on each of them.
+    fn broadcast<T: Struct + Message + Clone>(&self, message: T) -> Result<(), IOError>;
+
+    /// Broadcast a `Message` without automatic retransmission of its frames.
+    ///
+    /// The frames are handed directly to the interface for single shot transmission (see `TransferInterface::transmit_single_shot`)
+    /// instead of being queued. If a frame can't be handed over, the rest of the transfer is dropped and the error is returned.
+    fn broadcast_single_shot<T: Struct + Message + Clone>(&self, message: T) -> Result<(), IOError>;
 
     /// Subscribe to broadcasts of a specific `Message`.
-    fn subscribe<T: Struct + Message>(&self) -> Subscriber<T, I::Frame, S::SubscriberStorageHandle>;
+    ///
+    /// Fails with `IOError::InvalidInput` if the message has no type ID (resolving type IDs is not supported yet),
+    /// or if the interface is unable to start receiving the message.
+    fn subscribe<T: Struct + Message>(&self) -> Result<Self::Subscriber<T>, IOError>;
+
+    /// Remove a subscription created by `subscribe`.
+    ///
+    /// This lets the interface stop receiving the message if there are no other subscriptions to it.
+    fn unsubscribe<T: Struct + Message>(&self, subscriber: Self::Subscriber<T>) -> Result<(), IOError>;
+
+    /// Move all frames buffered in the interface into storage, returning the number of frames processed.
+    ///
+    /// The frames are fetched in batches with `TransferInterface::receive_into`,
+    /// which reduces the per frame overhead on fast links compared to receiving them one by one.
+    fn process_pending(&self) -> usize;
+
+    /// Put the node and its interface to sleep.
+    ///
+    /// While sleeping, broadcasts (e.g. periodic node status messages) are discarded
+    /// and frames already queued are held back until the node is woken up.
+    /// Returns false, and keeps the node awake, if the interface doesn't support sleeping.
+    fn sleep(&self) -> bool;
+
+    /// Wake the node and its interface up, resuming transmission of queued frames.
+    fn wake(&self);
+
+    /// Returns true while the node is sleeping.
+    ///
+    /// A node is woken up implicitly when its interface reports that it was woken up by bus activity.
+    fn is_asleep(&self) -> bool;
+
+    /// Returns true if the node is configured to suppress all transmissions, see `NodeConfig::listen_only`.
+    fn is_listen_only(&self) -> bool;
+
+    /// Returns the transport statistics aggregated over the node and its interfaces.
+    fn statistics(&self) -> TransportStats;
+
+    /// Returns the error state of the underlying bus.
+    ///
+    /// The state can be used to update the health reported by the node, see `BusState::health`.
+    fn bus_state(&self) -> BusState;
 }
 
     
@@ -87,16 +149,92 @@ pub struct NodeConfig {
     ///
     /// Nodes with `id = None` is, in Uavcan terms, an anonymous Node.
     pub id: Option<NodeID>,
+
+    /// What to do with received base format (11-bit ID) frames, by default they're dropped.
+    pub standard_frame_policy: StandardFramePolicy,
+
+    /// The byte used for padding frames to a legal data length on transports with quantized frame lengths.
+    pub pad_byte: u8,
+
+    /// Suppress all transmissions from the node.
+    ///
+    /// Broadcasts are discarded, and the interface is put in listen-only mode if it supports it
+    /// so that it doesn't acknowledge frames either.
+    pub listen_only: bool,
+
+    /// Insert every received data frame into storage, even if the `software-acceptance-filter` feature is enabled.
+    ///
+    /// The frames are counted in the statistics, whether or not they match a subscription.
+    pub promiscuous: bool,
+}
+
+impl NodeConfig {
+    /// Returns the configuration for a bus monitor (sniffer) that observes the bus without influencing it.
+    ///
+    /// The node is anonymous, listen-only and promiscuous. Hardware acceptance filters are still
+    /// configured by the interface from the subscriptions of the node.
+    pub fn sniffer() -> Self {
+        NodeConfig {
+            listen_only: true,
+            promiscuous: true,
+            ..NodeConfig::default()
+        }
+    }
 }
 
 impl Default for NodeConfig {
     fn default() -> Self {
         NodeConfig{
             id: None,
+            standard_frame_policy: StandardFramePolicy::Drop,
+            pad_byte: 0,
+            listen_only: false,
+            promiscuous: false,
         }
     }
 }
 
+/// What a node does with received base format (11-bit ID) frames
+///
+/// Uavcan only uses extended frames, but may share the bus with other protocols using base format frames.
+#[derive(Clone, Copy)]
+pub enum StandardFramePolicy {
+    /// Drop the frames silently, as required by the Uavcan specification.
+    Drop,
+
+    /// Pass the ID and data of the frames to a function before dropping them.
+    ///
+    /// This is useful for applications handling a second protocol on the same bus.
+    Callback(fn(u16, &[u8])),
+}
+
+impl ::lib::core::fmt::Debug for StandardFramePolicy {
+    fn fmt(&self, f: &mut ::lib::core::fmt::Formatter) -> ::lib::core::fmt::Result {
+        match *self {
+            StandardFramePolicy::Drop => write!(f, "Drop"),
+            StandardFramePolicy::Callback(callback) => write!(f, "Callback({:p})", callback as *const ()),
+        }
+    }
+}
+
+impl PartialEq for StandardFramePolicy {
+    fn eq(&self, other: &Self) -> bool {
+        match (*self, *other) {
+            (StandardFramePolicy::Drop, StandardFramePolicy::Drop) => true,
+            (StandardFramePolicy::Callback(a), StandardFramePolicy::Callback(b)) => a as *const () == b as *const (),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for StandardFramePolicy {}
+
+
+/// Receives the messages of a subscription, implemented by the subscription handles of every `Node`
+pub trait Receive<T> {
+    /// Receives a message that is subscribed on, or `None` if no message is complete.
+    fn receive(&self) -> Option<Result<T, ReceiveError>>;
+}
 
 /// A subscription handle used to receive a specific `Message`
 #[derive(Debug)]
@@ -118,10 +256,35 @@ impl <T: Struct + Message, F: TransferFrame, H: SubscriberStorageHandle<F>> Subs
     /// Messages are returned in a manner that respects the `TransferFrameID` priority.
     /// For equal priority, FIFO logic is used.
     pub fn receive(&self) -> Option<Result<T, ReceiveError>> {
+        self.receive_inspect(|_| ())
+    }
+
+    /// Receives a message that is subscribed on together with its reception timestamp.
+    ///
+    /// The timestamp of the transfer is the timestamp of its first frame.
+    /// Messages are returned in the same order as with `receive`.
+    pub fn receive_timestamped(&self) -> Option<Result<Transfer<T, F::Instant>, ReceiveError>>
+        where F: TimestampedFrame {
+        let mut timestamp = None;
+        let mut first_frame = true;
+        
+        let result = self.receive_inspect(|frame| {
+            if first_frame {
+                timestamp = frame.timestamp();
+                first_frame = false;
+            }
+        })?;
+
+        Some(result.map(|body| Transfer{timestamp, body}))
+    }
+
+    fn receive_inspect<C: FnMut(&F)>(&self, mut inspect: C) -> Option<Result<T, ReceiveError>> {
         if let Some(full_id) = self.storage_handle.find_id(|x| x.is_end_frame()) {
             let mut assembler = FrameAssembler::new();
             loop {
-                match assembler.add_transfer_frame(self.storage_handle.remove(&full_id.frame_id).unwrap()) {
+                let frame = self.storage_handle.remove(&full_id.frame_id).unwrap();
+                inspect(&frame);
+                match assembler.add_transfer_frame(frame) {
                     Err(AssemblerError::ToggleError) => {
                         self.storage_handle.retain(|x| x.full_id() != full_id);
                         return Some(Err(ReceiveError {
@@ -156,6 +319,22 @@ impl <T: Struct + Message, F: TransferFrame, H: SubscriberStorageHandle<F>> Subs
     
 }
 
+impl <T: Struct + Message, F: TransferFrame, H: SubscriberStorageHandle<F>> Receive<T> for Subscriber<T, F, H> {
+    fn receive(&self) -> Option<Result<T, ReceiveError>> {
+        Subscriber::receive(self)
+    }
+}
+
+/// A received message together with the instant it was received at
+#[derive(Debug, PartialEq)]
+pub struct Transfer<T, I> {
+    /// The reception timestamp of the first frame in the transfer, `None` if the frame was not timestamped.
+    pub timestamp: Option<I>,
+
+    /// The received message
+    pub body: T,
+}
+
 /// Full Error status from a failed receive
 #[derive(Debug, PartialEq, Eq)]
 pub struct ReceiveError {
@@ -171,6 +350,9 @@ pub enum ReceiveErrorCode {
     ToggleError,
 }
 
+/// The number of frames fetched from the interface at the time by `Node::process_pending`
+const RECEIVE_BATCH_SIZE: usize = 8;
+
 /// A minimal featured Uavcan node.
 ///
 /// This type of node lack some features that the `FullNode` provides,
@@ -185,6 +367,8 @@ pub struct SimpleNode<I, D, S>
     interface_storage: S::InterfaceStorageHandle,
     storage: S,
     config: NodeConfig,
+    statistics: Cell<TransportStats>,
+    asleep: Cell<bool>,
 }
 
 
@@ -194,12 +378,18 @@ impl<I, D, S> SimpleNode<I, D, S>
           S: Storage<I::Frame>,
 {
     pub fn new(interface: D, config: NodeConfig) -> Self {
+        if config.listen_only {
+            interface.set_listen_only(true);
+        }
+
         let storage = S::new();
         SimpleNode{
             interface: interface,
             interface_storage: storage.new_interface(),
             config: config,
             storage: storage,
+            statistics: Cell::new(TransportStats::default()),
+            asleep: Cell::new(false),
         }
     }
 
@@ -207,9 +397,14 @@ impl<I, D, S> SimpleNode<I, D, S>
     ///
     /// This method is responsible for moving as many frames as possible
     /// from incoming interface mailboxes to the storage buffer.
+    ///
+    /// With the `software-acceptance-filter` feature, frames not matching any subscription are dropped here
+    /// before they're counted and inserted into storage, unless the node is `promiscuous`.
+    /// Base format frames are handled according to the `standard_frame_policy`,
+    /// while remote and error frames are counted in the statistics and dropped.
     pub fn flush_receptions(&self) {
         while let Some(new_frame) = self.interface.receive() {
-            self.storage.insert_subscriber_queue(new_frame).expect("Storage full");
+            self.ingest_frame(new_frame);
         }
     }
 
@@ -217,11 +412,18 @@ impl<I, D, S> SimpleNode<I, D, S>
     ///
     /// This method is responsible for moving as many frames as possible
     /// from storage buffers to the outgoing interface mailboxes.
+    ///
+    /// Transmission is suppressed while the node is asleep or the interface reports `BusState::BusOff`,
+    /// frames are then kept in storage until the node is woken up or the controller has recovered.
     pub fn flush_transmissions(&self) {
+        if self.config.listen_only || self.is_asleep() || !self.interface.bus_state().can_transmit() {
+            return;
+        }
+
         //TODO: Handle priority inversion concerns correctly
         while let Some(top_frame) = self.interface_storage.pop() {
             match self.interface.transmit(&top_frame) {
-                Ok(_) => (),
+                Ok(_) => self.update_statistics(|stats| stats.frames_transmitted += 1),
                 Err(_) => {
                     self.interface_storage.push(top_frame).expect("Storage Full");
                     return;
@@ -229,42 +431,343 @@ impl<I, D, S> SimpleNode<I, D, S>
             }
         }
     }
+
+    fn update_statistics<F: FnOnce(&mut TransportStats)>(&self, f: F) {
+        let mut stats = self.statistics.get();
+        f(&mut stats);
+        self.statistics.set(stats);
+    }
+
+    fn ingest_frame(&self, new_frame: I::Frame) {
+        match new_frame.kind() {
+            FrameKind::Data => (),
+            FrameKind::Remote => {
+                self.update_statistics(|stats| stats.remote_frames += 1);
+                return;
+            },
+            FrameKind::Error => {
+                self.update_statistics(|stats| stats.error_frames += 1);
+                return;
+            },
+        }
+        if !new_frame.is_extended() {
+            if let StandardFramePolicy::Callback(callback) = self.config.standard_frame_policy {
+                callback(u32::from(new_frame.id()) as u16, new_frame.data());
+            }
+            return;
+        }
+        if cfg!(feature="software-acceptance-filter") && !self.config.promiscuous && !self.storage.is_subscribed(new_frame.id()) {
+            return;
+        }
+        self.update_statistics(|stats| stats.frames_received += 1);
+        self.storage.insert_subscriber_queue(new_frame).expect("Storage full");
+    }
+
+    fn message_generator<T: Struct + Message>(&self, message: T) -> Result<FrameDisassembler<T>, IOError> {
+        let priority = 0;
+        let transfer_id = TransferID::new(0);
+
+        // Resolvation of type id is not supported yet
+        if T::TYPE_ID.is_none() {
+            return Err(IOError::InvalidInput);
+        }
+
+        if let Some(ref node_id) = self.config.id {
+            Ok(FrameDisassembler::from_uavcan_frame(Frame::from_message(message, priority, *node_id), transfer_id)
+                .with_pad_byte(self.config.pad_byte))
+        } else {
+            unimplemented!("Anonymous transfers not implemented")
+        }
+    }
 }
 
 
-impl<I, D, S> Node<I, S> for SimpleNode<I, D, S>
+impl<I, D, S> Node for SimpleNode<I, D, S>
     where I: TransferInterface,
           D: ::lib::core::ops::Deref<Target=I>,
           S: Storage<I::Frame>,
 {
-    fn broadcast<T: Struct + Message>(&self, message: T) -> Result<(), IOError> {
-        let priority = 0;
-        let transfer_id = TransferID::new(0);
-        
-        let mut generator = if let Some(ref node_id) = self.config.id {
-            FrameDisassembler::from_uavcan_frame(Frame::from_message(message, priority, *node_id), transfer_id)
-        } else {
-            unimplemented!("Anonymous transfers not implemented")
-        };
+    type Subscriber<T: Struct + Message> = Subscriber<T, I::Frame, S::SubscriberStorageHandle>;
+
+    fn broadcast<T: Struct + Message + Clone>(&self, message: T) -> Result<(), IOError> {
+        if self.config.listen_only || self.is_asleep() {
+            return Ok(());
+        }
+
+        let mut generator = self.message_generator(message)?;
         
-        while let Some(can_frame) = generator.next_transfer_frame() {
-            self.storage.insert_interface_queue(can_frame).unwrap();
+        let mtu = self.interface.mtu();
+        let mut queued = 0;
+        while let Some(can_frame) = generator.next_transfer_frame_with_mtu::<I::Frame>(mtu) {
+            if self.storage.insert_interface_queue(can_frame).is_err() {
+                // Only the frames of this transfer are removed, earlier transfers may share its full ID
+                self.interface_storage.remove_newest(queued as usize);
+                self.update_statistics(|stats| stats.transmit_drops += queued + 1);
+                return Err(IOError::BufferExhausted);
+            }
+            queued += 1;
         }
         // TODO: Transfer into interface at this point or first attempt to add directly to interface.
 
         Ok(())
     }
 
-    fn subscribe<T: Struct + Message>(&self) -> Subscriber<T, I::Frame, S::SubscriberStorageHandle> {
-        let id = if let Some(type_id) = T::TYPE_ID {
-            u32::from(type_id) << 8
+    fn broadcast_single_shot<T: Struct + Message + Clone>(&self, message: T) -> Result<(), IOError> {
+        if self.config.listen_only || self.is_asleep() {
+            return Ok(());
+        }
+
+        let mut generator = self.message_generator(message)?;
+
+        let mtu = self.interface.mtu();
+        while let Some(can_frame) = generator.next_transfer_frame_with_mtu(mtu) {
+            match self.interface.transmit_single_shot(&can_frame) {
+                Ok(()) => self.update_statistics(|stats| stats.frames_transmitted += 1),
+                Err(e) => {
+                    self.update_statistics(|stats| stats.transmit_drops += 1);
+                    return Err(e);
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    fn subscribe<T: Struct + Message>(&self) -> Result<Subscriber<T, I::Frame, S::SubscriberStorageHandle>, IOError> {
+        // Resolvation of type id is not supported yet
+        let type_id = T::TYPE_ID.ok_or(IOError::InvalidInput)?;
+
+        self.interface.on_subscribe(type_id)?;
+
+        let filter = TransferFrameIDFilter::new(u32::from(type_id) << 8, 0x1ff << 7);
+    
+        Ok(Subscriber::new(self.storage.subscribe_to(filter)))
+    }
+
+    fn unsubscribe<T: Struct + Message>(&self, subscriber: Subscriber<T, I::Frame, S::SubscriberStorageHandle>) -> Result<(), IOError> {
+        let type_id = T::TYPE_ID.ok_or(IOError::InvalidInput)?;
+
+        drop(subscriber);
+        self.interface.on_unsubscribe(type_id)
+    }
+
+    fn sleep(&self) -> bool {
+        if self.interface.sleep() {
+            self.asleep.set(true);
+            true
         } else {
-            unimplemented!("Resolvation of type id is not supported yet")
+            false
+        }
+    }
+
+    fn wake(&self) {
+        self.interface.wake();
+        self.asleep.set(false);
+    }
+
+    fn is_asleep(&self) -> bool {
+        if self.asleep.get() && !self.interface.is_sleeping() {
+            self.asleep.set(false);
+        }
+        self.asleep.get()
+    }
+
+    fn is_listen_only(&self) -> bool {
+        self.config.listen_only
+    }
+
+    fn process_pending(&self) -> usize {
+        let mut batch: [I::Frame; RECEIVE_BATCH_SIZE] = ::lib::core::array::from_fn(|_| I::Frame::new(TransferFrameID::new(0)));
+        let mut processed = 0;
+
+        loop {
+            let received = self.interface.receive_into(&mut batch);
+            for frame in batch[..received].iter_mut() {
+                self.ingest_frame(::lib::core::mem::replace(frame, I::Frame::new(TransferFrameID::new(0))));
+            }
+            processed += received;
+
+            if received < RECEIVE_BATCH_SIZE {
+                return processed;
+            }
+        }
+    }
+
+    fn statistics(&self) -> TransportStats {
+        self.statistics.get() + self.interface.statistics()
+    }
+
+    fn bus_state(&self) -> BusState {
+        self.interface.bus_state()
+    }
+}
+
+
+
+/// A node that forwards to two underlying nodes, each with its own transport.
+///
+/// Broadcasts are published on both nodes while subscriptions merge the receptions from both of them.
+/// The two nodes don't need to share `TransferFrame` type, which allows gateways and vehicles with mixed transports
+/// (e.g. CAN and UDP) to be handled through a single node.
+#[derive(Debug)]
+pub struct MultiNode<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> MultiNode<A, B> {
+    /// Combines two nodes into a `MultiNode`
+    pub fn new(first: A, second: B) -> Self {
+        MultiNode {
+            first,
+            second,
+        }
+    }
+
+    /// Returns a reference to the first node
+    pub fn first(&self) -> &A {
+        &self.first
+    }
+
+    /// Returns a reference to the second node
+    pub fn second(&self) -> &B {
+        &self.second
+    }
+
+    /// Splits the `MultiNode` into the nodes it was constructed from
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<A: Node, B: Node> Node for MultiNode<A, B> {
+    type Subscriber<T: Struct + Message> = MultiSubscriber<A::Subscriber<T>, B::Subscriber<T>>;
+
+    /// Broadcast a `Message` on both nodes.
+    ///
+    /// The message is broadcasted on both nodes even if broadcasting on the first node fails,
+    /// the first error encountered is returned.
+    fn broadcast<T: Struct + Message + Clone>(&self, message: T) -> Result<(), IOError> {
+        let first_result = self.first.broadcast(message.clone());
+        let second_result = self.second.broadcast(message);
+        first_result.and(second_result)
+    }
+
+    /// Broadcast a `Message` on both nodes without automatic retransmission, see `broadcast` for the error handling.
+    fn broadcast_single_shot<T: Struct + Message + Clone>(&self, message: T) -> Result<(), IOError> {
+        let first_result = self.first.broadcast_single_shot(message.clone());
+        let second_result = self.second.broadcast_single_shot(message);
+        first_result.and(second_result)
+    }
+
+    /// Subscribe to broadcasts of a specific `Message` on both nodes.
+    ///
+    /// Fails if subscribing on either of the nodes fails.
+    fn subscribe<T: Struct + Message>(&self) -> Result<Self::Subscriber<T>, IOError> {
+        let first = self.first.subscribe()?;
+        let second = match self.second.subscribe() {
+            Ok(second) => second,
+            Err(e) => {
+                let _ = self.first.unsubscribe(first);
+                return Err(e);
+            },
         };
 
-        let filter = TransferFrameIDFilter::new(id, 0x1ff << 7);
-    
-        Subscriber::new(self.storage.subscribe_to(filter))
+        Ok(MultiSubscriber {
+            first,
+            second,
+            poll_second: Cell::new(false),
+        })
+    }
+
+    /// Remove a subscription created by `subscribe` from both nodes.
+    fn unsubscribe<T: Struct + Message>(&self, subscriber: Self::Subscriber<T>) -> Result<(), IOError> {
+        let first_result = self.first.unsubscribe(subscriber.first);
+        let second_result = self.second.unsubscribe(subscriber.second);
+        first_result.and(second_result)
+    }
+
+    /// Process the frames pending on both nodes, returning the total number of frames processed.
+    fn process_pending(&self) -> usize {
+        self.first.process_pending() + self.second.process_pending()
+    }
+
+    /// Put both nodes to sleep, or neither of them if one of them doesn't support sleeping.
+    fn sleep(&self) -> bool {
+        if !self.first.sleep() {
+            return false;
+        }
+        if !self.second.sleep() {
+            self.first.wake();
+            return false;
+        }
+        true
+    }
+
+    fn wake(&self) {
+        self.first.wake();
+        self.second.wake();
+    }
+
+    /// Returns true while both nodes are sleeping.
+    fn is_asleep(&self) -> bool {
+        self.first.is_asleep() && self.second.is_asleep()
+    }
+
+    /// Returns true if both nodes are listen-only.
+    fn is_listen_only(&self) -> bool {
+        self.first.is_listen_only() && self.second.is_listen_only()
+    }
+
+    /// Returns the transport statistics aggregated over both nodes.
+    fn statistics(&self) -> TransportStats {
+        self.first.statistics() + self.second.statistics()
+    }
+
+    /// Returns the better of the states of the two buses.
+    ///
+    /// The node keeps communicating as long as one of its transports can, the states of the
+    /// individual buses are available through `first` and `second`.
+    fn bus_state(&self) -> BusState {
+        ::lib::core::cmp::min(self.first.bus_state(), self.second.bus_state())
+    }
+}
+
+/// A subscription handle merging the receptions of the two nodes of a `MultiNode`
+///
+/// Receptions are not deduplicated, if both transports carry the same transfer it will be received twice.
+#[derive(Debug)]
+pub struct MultiSubscriber<A, B> {
+    first: A,
+    second: B,
+    poll_second: Cell<bool>,
+}
+
+impl<A, B> MultiSubscriber<A, B> {
+    /// Returns the subscription handle of the first node
+    pub fn first(&self) -> &A {
+        &self.first
+    }
+
+    /// Returns the subscription handle of the second node
+    pub fn second(&self) -> &B {
+        &self.second
+    }
+}
+
+impl<T, A: Receive<T>, B: Receive<T>> Receive<T> for MultiSubscriber<A, B> {
+    /// Receives a message from either of the nodes.
+    ///
+    /// The nodes are polled in turns, so that a busy transport can't starve the other.
+    fn receive(&self) -> Option<Result<T, ReceiveError>> {
+        let poll_second = self.poll_second.get();
+        self.poll_second.set(!poll_second);
+
+        if poll_second {
+            self.second.receive().or_else(|| self.first.receive())
+        } else {
+            self.first.receive().or_else(|| self.second.receive())
+        }
     }
 }
 
@@ -284,3 +787,365 @@ impl From<NodeID> for u32 {
         u32::from(id.0)
     }
 }
+
+
+#[cfg(all(test, feature="std"))]
+mod tests {
+
+    use *;
+    use node::*;
+    use types::*;
+    use storage::{HeapStorage, StorageError};
+    use tests::{CanFrame, MockInterface};
+    use transfer::Timestamped;
+
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    #[uavcan(message, id = 341, signature = 0x0f0868d0c1a7c6f1)]
+    struct NodeStatus {
+        uptime_sec: u32,
+        health: u2,
+        mode: u3,
+        sub_mode: u3,
+        vendor_specific_status_code: u16,
+    }
+
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    #[uavcan(message, id = 342, signature = 0xd8a7486238ec3af3)]
+    struct KeyValue {
+        value: f32,
+    }
+
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    #[uavcan(message, id = 16383, signature = 0xd654a48e0c049d75)]
+    struct LogMessage {
+        level: u3,
+        source: Dynamic<[u8; 31]>,
+        text: Dynamic<[u8; 90]>,
+    }
+
+    /// A message whose type ID would have to be resolved
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    #[uavcan(message, signature = 0x1234)]
+    struct Unregistered {
+        value: u8,
+    }
+
+    /// A `HeapStorage` taking no more than two frames for transmission over its lifetime
+    struct SmallStorage {
+        storage: HeapStorage<CanFrame>,
+        queued: Cell<usize>,
+    }
+
+    impl Storage<CanFrame> for SmallStorage {
+        type SubscriberStorageHandle = <HeapStorage<CanFrame> as Storage<CanFrame>>::SubscriberStorageHandle;
+        type InterfaceStorageHandle = <HeapStorage<CanFrame> as Storage<CanFrame>>::InterfaceStorageHandle;
+
+        fn new() -> Self {
+            SmallStorage{storage: HeapStorage::new(), queued: Cell::new(0)}
+        }
+
+        fn subscribe_to(&self, filter: TransferFrameIDFilter) -> Self::SubscriberStorageHandle {
+            self.storage.subscribe_to(filter)
+        }
+
+        fn new_interface(&self) -> Self::InterfaceStorageHandle {
+            self.storage.new_interface()
+        }
+
+        fn insert_subscriber_queue(&self, frame: CanFrame) -> Result<(), StorageError> {
+            self.storage.insert_subscriber_queue(frame)
+        }
+
+        fn insert_interface_queue(&self, frame: CanFrame) -> Result<(), StorageError> {
+            if self.queued.get() == 2 {
+                return Err(StorageError::OutOfSpace);
+            }
+            self.queued.set(self.queued.get() + 1);
+            self.storage.insert_interface_queue(frame)
+        }
+    }
+
+    type TestNode<'a> = SimpleNode<MockInterface, &'a MockInterface, HeapStorage<CanFrame>>;
+
+    fn node_status(uptime_sec: u32) -> NodeStatus {
+        NodeStatus{uptime_sec, health: u2::new(0), mode: u3::new(0), sub_mode: u3::new(0), vendor_specific_status_code: 0}
+    }
+
+    fn log_message(text: &[u8]) -> LogMessage {
+        LogMessage{level: u3::new(1), source: Dynamic::<[u8; 31]>::with_data(b"test"), text: Dynamic::<[u8; 90]>::with_data(text)}
+    }
+
+    fn config(id: u8) -> NodeConfig {
+        NodeConfig{id: Some(NodeID::new(id)), ..NodeConfig::default()}
+    }
+
+    #[test]
+    fn software_acceptance_filter() {
+        let sender_interface = MockInterface::new();
+        let sender: TestNode = SimpleNode::new(&sender_interface, config(1));
+        let interface = MockInterface::new();
+        let node: TestNode = SimpleNode::new(&interface, config(2));
+        let subscriber = node.subscribe::<NodeStatus>().unwrap();
+
+        sender.broadcast(node_status(7)).unwrap();
+        sender.broadcast(KeyValue{value: 1.5}).unwrap();
+        sender.flush_transmissions();
+        sender_interface.deliver_to(&interface);
+        node.flush_receptions();
+
+        // The frame of the unsubscribed message is dropped before it's counted
+        let expected_received = if cfg!(feature="software-acceptance-filter") { 1 } else { 2 };
+        assert_eq!(node.statistics().frames_received, expected_received);
+        assert_eq!(subscriber.receive(), Some(Ok(node_status(7))));
+        assert_eq!(subscriber.receive(), None);
+    }
+
+    #[test]
+    fn sniffer() {
+        let sender_interface = MockInterface::new();
+        let sender: TestNode = SimpleNode::new(&sender_interface, config(1));
+        let interface = MockInterface::new();
+        let node: TestNode = SimpleNode::new(&interface, NodeConfig::sniffer());
+        let subscriber = node.subscribe::<NodeStatus>().unwrap();
+        assert!(interface.listen_only.get());
+
+        sender.broadcast(node_status(7)).unwrap();
+        sender.broadcast(KeyValue{value: 1.5}).unwrap();
+        sender.flush_transmissions();
+        sender_interface.deliver_to(&interface);
+        node.flush_receptions();
+
+        // Unlike the node in `software_acceptance_filter`, the sniffer counts the unsubscribed message as well
+        assert_eq!(node.statistics().frames_received, 2);
+        assert_eq!(subscriber.receive(), Some(Ok(node_status(7))));
+
+        node.flush_transmissions();
+        assert!(interface.tx.borrow().is_empty());
+    }
+
+    #[test]
+    fn timestamp_of_first_frame() {
+        let sender_interface = MockInterface::new();
+        let sender: TestNode = SimpleNode::new(&sender_interface, config(1));
+        let interface = MockInterface::new();
+        let node: SimpleNode<_, _, HeapStorage<_>> = SimpleNode::new(&interface, config(2));
+        let subscriber = node.subscribe::<LogMessage>().unwrap();
+
+        let message = log_message(b"a multi frame transfer");
+        sender.broadcast(message.clone()).unwrap();
+        sender.flush_transmissions();
+        let mut instant = 1000u32;
+        while let Some(frame) = sender_interface.tx.borrow_mut().pop_front() {
+            interface.push_rx(Timestamped::new(frame, instant));
+            instant += 10;
+        }
+        assert!(instant > 1010);
+        node.flush_receptions();
+
+        assert_eq!(subscriber.receive_timestamped(), Some(Ok(Transfer{timestamp: Some(1000), body: message})));
+    }
+
+    #[test]
+    fn bus_off_holds_transmissions() {
+        let interface = MockInterface::new();
+        let node: TestNode = SimpleNode::new(&interface, config(1));
+
+        interface.bus_state.set(BusState::BusOff);
+        assert_eq!(node.bus_state(), BusState::BusOff);
+        node.broadcast(node_status(1)).unwrap();
+        node.flush_transmissions();
+        assert!(interface.tx.borrow().is_empty());
+
+        // The queued frame is transmitted when the controller has recovered
+        interface.bus_state.set(BusState::ErrorActive);
+        node.flush_transmissions();
+        assert_eq!(interface.tx.borrow().len(), 1);
+        assert_eq!(node.statistics().frames_transmitted, 1);
+    }
+
+    #[test]
+    fn single_shot_drops_the_rest_of_the_transfer() {
+        let interface = MockInterface::new();
+        let node: TestNode = SimpleNode::new(&interface, config(1));
+
+        interface.tx_capacity.set(2);
+        assert_eq!(node.broadcast_single_shot(log_message(b"a multi frame transfer")), Err(IOError::BufferExhausted));
+        assert_eq!(interface.single_shots.get(), 2);
+        assert_eq!(node.statistics().frames_transmitted, 2);
+        assert_eq!(node.statistics().transmit_drops, 1);
+
+        // Nothing was queued for retransmission
+        interface.tx_capacity.set(64);
+        node.flush_transmissions();
+        assert_eq!(interface.tx.borrow().len(), 2);
+    }
+
+    #[test]
+    fn process_pending_drains_several_batches() {
+        let sender_interface = MockInterface::new();
+        let sender: TestNode = SimpleNode::new(&sender_interface, config(1));
+        let interface = MockInterface::new();
+        let node: TestNode = SimpleNode::new(&interface, config(2));
+        let subscriber = node.subscribe::<NodeStatus>().unwrap();
+
+        let count = RECEIVE_BATCH_SIZE as u32 * 2 + 3;
+        for uptime_sec in 0..count {
+            sender.broadcast(node_status(uptime_sec)).unwrap();
+            sender.flush_transmissions();
+        }
+        sender_interface.deliver_to(&interface);
+
+        assert_eq!(node.process_pending(), count as usize);
+        assert_eq!(node.process_pending(), 0);
+        for uptime_sec in 0..count {
+            assert_eq!(subscriber.receive(), Some(Ok(node_status(uptime_sec))));
+        }
+        assert_eq!(subscriber.receive(), None);
+    }
+
+    #[test]
+    fn sleep_and_wake() {
+        let interface = MockInterface::new();
+        let node: TestNode = SimpleNode::new(&interface, config(1));
+
+        node.broadcast(node_status(1)).unwrap();
+        assert!(node.sleep());
+        assert!(interface.sleeping.get());
+        assert!(node.is_asleep());
+
+        // The queued frame is held back and new broadcasts are discarded
+        node.broadcast(node_status(2)).unwrap();
+        node.flush_transmissions();
+        assert!(interface.tx.borrow().is_empty());
+
+        // Bus activity wakes the interface up, and the node with it
+        interface.sleeping.set(false);
+        assert!(!node.is_asleep());
+        node.flush_transmissions();
+        assert_eq!(interface.tx.borrow().len(), 1);
+
+        assert!(node.sleep());
+        node.wake();
+        assert!(!interface.sleeping.get());
+        assert!(!node.is_asleep());
+    }
+
+    #[test]
+    fn broadcast_queues_whole_transfers() {
+        let interface = MockInterface::new();
+        let node: SimpleNode<_, _, SmallStorage> = SimpleNode::new(&interface, config(1));
+
+        assert_eq!(node.broadcast(log_message(b"a multi frame transfer")), Err(IOError::BufferExhausted));
+        assert_eq!(node.statistics().transmit_drops, 3);
+
+        // The first two frames were removed again
+        node.flush_transmissions();
+        assert!(interface.tx.borrow().is_empty());
+    }
+
+    #[test]
+    fn messages_without_type_id_are_rejected() {
+        let interface = MockInterface::new();
+        let node: TestNode = SimpleNode::new(&interface, config(1));
+
+        assert_eq!(node.broadcast(Unregistered{value: 1}), Err(IOError::InvalidInput));
+        assert_eq!(node.broadcast_single_shot(Unregistered{value: 1}), Err(IOError::InvalidInput));
+        assert!(matches!(node.subscribe::<Unregistered>(), Err(IOError::InvalidInput)));
+        node.flush_transmissions();
+        assert!(interface.tx.borrow().is_empty());
+    }
+
+    #[test]
+    fn failed_broadcast_keeps_earlier_transfers() {
+        let interface = MockInterface::new();
+        let node: SimpleNode<_, _, SmallStorage> = SimpleNode::new(&interface, config(1));
+
+        // Both transfers have the same type, source and transfer ID
+        node.broadcast(log_message(b"")).unwrap();
+        assert_eq!(node.broadcast(log_message(b"a multi frame transfer")), Err(IOError::BufferExhausted));
+        assert_eq!(node.statistics().transmit_drops, 2);
+
+        // Only the frame of the failed transfer was removed
+        node.flush_transmissions();
+        assert_eq!(interface.tx.borrow().len(), 1);
+        assert!(interface.tx.borrow().front().unwrap().is_single_frame());
+    }
+
+    #[test]
+    fn multi_node_fan_out() {
+        let first_interface = MockInterface::new();
+        let second_interface = MockInterface::new();
+        let node = MultiNode::new(
+            TestNode::new(&first_interface, config(1)),
+            TestNode::new(&second_interface, config(1)),
+        );
+
+        node.broadcast(node_status(1)).unwrap();
+        node.first().flush_transmissions();
+        node.second().flush_transmissions();
+        assert_eq!(first_interface.tx.borrow().len(), 1);
+        assert_eq!(second_interface.tx.borrow().len(), 1);
+        assert_eq!(node.statistics().frames_transmitted, 2);
+
+        // Receptions of both transports are merged
+        let receiver = MultiNode::new(
+            TestNode::new(&first_interface, config(2)),
+            TestNode::new(&second_interface, config(2)),
+        );
+        let subscriber = receiver.subscribe::<NodeStatus>().unwrap();
+        first_interface.deliver_to(&first_interface);
+        second_interface.deliver_to(&second_interface);
+        assert_eq!(receiver.process_pending(), 2);
+        assert_eq!(subscriber.receive(), Some(Ok(node_status(1))));
+        assert_eq!(subscriber.receive(), Some(Ok(node_status(1))));
+        assert_eq!(subscriber.receive(), None);
+    }
+
+    #[test]
+    fn multi_node_failover() {
+        let first_interface = MockInterface::new();
+        let second_interface = MockInterface::new();
+        let node = MultiNode::new(
+            TestNode::new(&first_interface, config(1)),
+            TestNode::new(&second_interface, config(1)),
+        );
+
+        // The message is broadcasted on the second transport when the first one fails
+        first_interface.tx_capacity.set(0);
+        assert_eq!(node.broadcast_single_shot(node_status(1)), Err(IOError::BufferExhausted));
+        assert!(first_interface.tx.borrow().is_empty());
+        assert_eq!(second_interface.tx.borrow().len(), 1);
+
+        // The node communicates as long as one of the buses does
+        first_interface.bus_state.set(BusState::BusOff);
+        assert_eq!(node.bus_state(), BusState::ErrorActive);
+        second_interface.bus_state.set(BusState::ErrorPassive);
+        assert_eq!(node.bus_state(), BusState::ErrorPassive);
+
+        // Receptions keep coming from the second transport while the first is silent
+        let subscriber = node.subscribe::<NodeStatus>().unwrap();
+        second_interface.deliver_to(&second_interface);
+        assert_eq!(node.process_pending(), 1);
+        assert_eq!(subscriber.receive(), Some(Ok(node_status(1))));
+        assert_eq!(subscriber.receive(), None);
+        node.unsubscribe(subscriber).unwrap();
+    }
+
+    #[test]
+    fn multi_node_sleep() {
+        let first_interface = MockInterface::new();
+        let second_interface = MockInterface::new();
+        let node = MultiNode::new(
+            TestNode::new(&first_interface, config(1)),
+            TestNode::new(&second_interface, NodeConfig{listen_only: true, ..config(1)}),
+        );
+        assert!(!node.is_listen_only());
+
+        assert!(node.sleep());
+        assert!(node.is_asleep());
+        second_interface.sleeping.set(false);
+        assert!(!node.is_asleep());
+        node.wake();
+        assert!(!first_interface.sleeping.get());
+    }
+}
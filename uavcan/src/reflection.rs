@@ -0,0 +1,431 @@
+//! Runtime descriptions of data types
+//!
+//! A `FieldType` describes the layout of a data type (primitive field kinds and bit lengths, array bounds, composites and unions)
+//! as plain data, so tools like bus monitors can decode types that are only known at runtime.
+//! `deserialize` and `serialize` encode exactly like the `Serializable` implementations of the equivalent compile-time types,
+//! including the tail array optimization and the implicit zero extension of truncated payloads.
+//!
+//! ## Example
+//! ```
+//! use uavcan::reflection::*;
+//!
+//! // uavcan.protocol.NodeStatus
+//! static NODE_STATUS: FieldType = FieldType::Composite(&[
+//!     Field { name: "uptime_sec", ty: FieldType::Unsigned(32) },
+//!     Field { name: "health", ty: FieldType::Unsigned(2) },
+//!     Field { name: "mode", ty: FieldType::Unsigned(3) },
+//!     Field { name: "sub_mode", ty: FieldType::Unsigned(3) },
+//!     Field { name: "vendor_specific_status_code", ty: FieldType::Unsigned(16) },
+//! ]);
+//!
+//! struct Mode(u64);
+//!
+//! impl Visitor for Mode {
+//!     fn value(&mut self, name: &str, value: Value) {
+//!         if let ("mode", Value::Unsigned(mode)) = (name, value) {
+//!             self.0 = mode;
+//!         }
+//!     }
+//! }
+//!
+//! let mut mode = Mode(0);
+//! deserialize(&NODE_STATUS, &[1, 0, 0, 0, 0b10011100, 5, 0], &mut mode).unwrap();
+//! assert_eq!(mode.0, 3);
+//! ```
+
+use lib::core::cmp;
+
+use bits::BitReader;
+
+use float_codec;
+
+use serializer::SerializationBuffer;
+
+use types::ProtocolVersion;
+
+/// The type of a field
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FieldType<'a> {
+    /// An unsigned integer of the given bit length (1 to 64)
+    Unsigned(u8),
+
+    /// A two's complement signed integer of the given bit length (2 to 64)
+    Signed(u8),
+
+    /// An IEEE 754 float of the given bit length (16, 32 or 64)
+    Float(u8),
+
+    Bool,
+
+    /// Padding of the given bit length
+    Void(u8),
+
+    /// An array of exactly `length` elements
+    StaticArray(&'a FieldType<'a>, usize),
+
+    /// An array of up to `max_length` elements, prefixed by its length (unless the tail array optimization applies)
+    DynamicArray(&'a FieldType<'a>, usize),
+
+    /// A structure with the given fields in order
+    Composite(&'a [Field<'a>]),
+
+    /// A union of the given variants, prefixed by the index of the selected variant
+    Union(&'a [Field<'a>]),
+}
+
+/// A named field of a composite, or a variant of a union
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Field<'a> {
+    pub name: &'a str,
+    pub ty: FieldType<'a>,
+}
+
+/// The value of a primitive field
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+/// Errors from `deserialize` and `serialize`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReflectionError {
+    /// A union tag without a variant
+    InvalidUnionTag(u64),
+
+    /// An array length larger than the maximum length
+    InvalidArrayLength(usize),
+
+    /// A value of another kind than the field type
+    TypeMismatch,
+
+    /// The serialized data doesn't fit the buffer
+    BufferFull,
+}
+
+/// Receives the fields of a deserialized value in order
+///
+/// Composites and arrays are reported around their fields and elements, which are reported with the name of the array.
+/// Void fields are skipped.
+pub trait Visitor {
+    fn value(&mut self, name: &str, value: Value);
+
+    fn begin_composite(&mut self, _name: &str) {}
+
+    fn end_composite(&mut self, _name: &str) {}
+
+    fn begin_array(&mut self, _name: &str, _length: usize) {}
+
+    fn end_array(&mut self, _name: &str) {}
+
+    /// Reports the selected variant of a union, the variant is then reported as a field
+    fn union_variant(&mut self, _name: &str, _variant: &str) {}
+}
+
+/// Provides the fields of a value to serialize in order, in the same order a `Visitor` receives them
+pub trait Source {
+    fn value(&mut self, name: &str, ty: &FieldType) -> Value;
+
+    /// Returns the number of elements in the dynamic array `name`
+    fn array_length(&mut self, name: &str, max_length: usize) -> usize;
+
+    /// Returns the index of the selected variant of the union `name`
+    fn union_variant(&mut self, name: &str, variants: &[Field]) -> usize;
+}
+
+impl<'a> FieldType<'a> {
+    /// Returns the minimal number of bits the type serializes into
+    pub fn bit_length_min(&self) -> usize {
+        match *self {
+            FieldType::Unsigned(bits) | FieldType::Signed(bits) | FieldType::Float(bits) | FieldType::Void(bits) => usize::from(bits),
+            FieldType::Bool => 1,
+            FieldType::StaticArray(element, length) => length * element.bit_length_min(),
+            FieldType::DynamicArray(_, max_length) => length_bits(max_length),
+            FieldType::Composite(fields) => fields.iter().map(|field| field.ty.bit_length_min()).sum(),
+            FieldType::Union(variants) => tag_bits(variants.len()) + variants.iter().map(|variant| variant.ty.bit_length_min()).min().unwrap_or(0),
+        }
+    }
+}
+
+/// The number of bits of the length of a dynamic array
+fn length_bits(max_length: usize) -> usize {
+    ProtocolVersion::V0.length_bits(max_length)
+}
+
+/// Whether a dynamic array that is the last field leaves out its length
+fn tail_array_optimization(element_bits: usize) -> bool {
+    ProtocolVersion::V0.tail_array_optimization(element_bits)
+}
+
+/// The number of bits of the tag of a union
+fn tag_bits(variants: usize) -> usize {
+    let mut bits = 0;
+    while (1usize << bits) < variants {
+        bits += 1;
+    }
+    bits
+}
+
+/// Deserializes `payload` as a value of type `ty`, reporting the fields to `visitor`
+///
+/// Returns the number of bits the value took.
+pub fn deserialize<V: Visitor>(ty: &FieldType, payload: &[u8], visitor: &mut V) -> Result<usize, ReflectionError> {
+    let mut reader = BitReader::new(payload);
+    deserialize_field(ty, "", true, &mut reader, visitor)?;
+    Ok(reader.position())
+}
+
+fn deserialize_field<V: Visitor>(ty: &FieldType, name: &str, last_field: bool, reader: &mut BitReader, visitor: &mut V) -> Result<(), ReflectionError> {
+    match *ty {
+        FieldType::Unsigned(bits) => visitor.value(name, Value::Unsigned(reader.read_bits(usize::from(bits)))),
+        FieldType::Signed(bits) => {
+            let unused_bits = 64 - u32::from(bits);
+            let value = (reader.read_bits(usize::from(bits)) << unused_bits) as i64 >> unused_bits;
+            visitor.value(name, Value::Signed(value));
+        },
+        FieldType::Float(bits) => visitor.value(name, Value::Float(float_codec::decode(bits, reader.read_bits(usize::from(bits))))),
+        FieldType::Bool => visitor.value(name, Value::Bool(reader.read_bits(1) == 1)),
+        FieldType::Void(bits) => {
+            reader.read_bits(usize::from(bits));
+        },
+        FieldType::StaticArray(element, length) => {
+            visitor.begin_array(name, length);
+            for _ in 0..length {
+                deserialize_field(element, name, false, reader, visitor)?;
+            }
+            visitor.end_array(name);
+        },
+        FieldType::DynamicArray(element, max_length) => {
+            let element_bits = element.bit_length_min();
+            if last_field && tail_array_optimization(element_bits) {
+                // The tail array takes the rest of the payload, a partial element is dropped
+                let length = cmp::min(reader.bits_remaining() / element_bits, max_length);
+                visitor.begin_array(name, length);
+                for _ in 0..length {
+                    deserialize_field(element, name, false, reader, visitor)?;
+                }
+                visitor.end_array(name);
+            } else {
+                let length = cmp::min(reader.read_bits(length_bits(max_length)) as usize, max_length);
+                visitor.begin_array(name, length);
+                for _ in 0..length {
+                    deserialize_field(element, name, false, reader, visitor)?;
+                }
+                visitor.end_array(name);
+            }
+        },
+        FieldType::Composite(fields) => {
+            visitor.begin_composite(name);
+            for (index, field) in fields.iter().enumerate() {
+                deserialize_field(&field.ty, field.name, last_field && index == fields.len() - 1, reader, visitor)?;
+            }
+            visitor.end_composite(name);
+        },
+        FieldType::Union(variants) => {
+            let tag = reader.read_bits(tag_bits(variants.len()));
+            let variant = variants.get(tag as usize).ok_or(ReflectionError::InvalidUnionTag(tag))?;
+            visitor.union_variant(name, variant.name);
+            deserialize_field(&variant.ty, variant.name, last_field, reader, visitor)?;
+        },
+    }
+    Ok(())
+}
+
+/// Serializes the value provided by `source` as type `ty` into `buffer`
+///
+/// Returns the number of bytes used, the bits after the value in the last byte are zero.
+pub fn serialize<S: Source>(ty: &FieldType, source: &mut S, buffer: &mut [u8]) -> Result<usize, ReflectionError> {
+    for byte in buffer.iter_mut() {
+        *byte = 0;
+    }
+    let mut buffer = SerializationBuffer::with_empty_buffer(buffer);
+    serialize_field(ty, "", true, source, &mut buffer)?;
+    Ok((buffer.bit_length() + 7) / 8)
+}
+
+fn push_bits(buffer: &mut SerializationBuffer, length: usize, bits: u64) -> Result<(), ReflectionError> {
+    if buffer.bits_remaining() < length {
+        return Err(ReflectionError::BufferFull);
+    }
+    buffer.push_bits(length, bits);
+    Ok(())
+}
+
+fn serialize_field<S: Source>(ty: &FieldType, name: &str, last_field: bool, source: &mut S, buffer: &mut SerializationBuffer) -> Result<(), ReflectionError> {
+    match *ty {
+        FieldType::Unsigned(_) | FieldType::Signed(_) | FieldType::Float(_) | FieldType::Bool => {
+            let bits = match (*ty, source.value(name, ty)) {
+                (FieldType::Unsigned(_), Value::Unsigned(value)) => value,
+                (FieldType::Signed(_), Value::Signed(value)) => value as u64,
+                (FieldType::Float(bits), Value::Float(value)) => float_codec::encode(bits, value),
+                (FieldType::Bool, Value::Bool(value)) => value as u64,
+                _ => return Err(ReflectionError::TypeMismatch),
+            };
+            push_bits(buffer, ty.bit_length_min(), bits)?;
+        },
+        FieldType::Void(bits) => push_bits(buffer, usize::from(bits), 0)?,
+        FieldType::StaticArray(element, length) => {
+            for _ in 0..length {
+                serialize_field(element, name, false, source, buffer)?;
+            }
+        },
+        FieldType::DynamicArray(element, max_length) => {
+            let length = source.array_length(name, max_length);
+            if length > max_length {
+                return Err(ReflectionError::InvalidArrayLength(length));
+            }
+            if !(last_field && tail_array_optimization(element.bit_length_min())) {
+                push_bits(buffer, length_bits(max_length), length as u64)?;
+            }
+            for _ in 0..length {
+                serialize_field(element, name, false, source, buffer)?;
+            }
+        },
+        FieldType::Composite(fields) => {
+            for (index, field) in fields.iter().enumerate() {
+                serialize_field(&field.ty, field.name, last_field && index == fields.len() - 1, source, buffer)?;
+            }
+        },
+        FieldType::Union(variants) => {
+            let tag = source.union_variant(name, variants);
+            let variant = variants.get(tag).ok_or(ReflectionError::InvalidUnionTag(tag as u64))?;
+            push_bits(buffer, tag_bits(variants.len()), tag as u64)?;
+            serialize_field(&variant.ty, variant.name, last_field, source, buffer)?;
+        },
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use *;
+    use types::*;
+    use serializer::Serializer;
+    use reflection::*;
+
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    enum Reading {
+        Raw(i5),
+        Scaled(f16),
+    }
+
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    struct Message {
+        flag: bool,
+        pad: void3,
+        readings: [Reading; 2],
+        offset: i12,
+        text: Dynamic<[u8; 8]>,
+    }
+
+    static READING: FieldType = FieldType::Union(&[
+        Field { name: "raw", ty: FieldType::Signed(5) },
+        Field { name: "scaled", ty: FieldType::Float(16) },
+    ]);
+
+    static MESSAGE: FieldType = FieldType::Composite(&[
+        Field { name: "flag", ty: FieldType::Bool },
+        Field { name: "pad", ty: FieldType::Void(3) },
+        Field { name: "readings", ty: FieldType::StaticArray(&READING, 2) },
+        Field { name: "offset", ty: FieldType::Signed(12) },
+        Field { name: "text", ty: FieldType::DynamicArray(&FieldType::Unsigned(8), 8) },
+    ]);
+
+    fn message() -> Message {
+        Message {
+            flag: true,
+            pad: void3{},
+            readings: [Reading::Raw(i5::new(-3)), Reading::Scaled(f16::from_f32(0.5))],
+            offset: i12::new(-1000),
+            text: Dynamic::<[u8; 8]>::with_data("tail".as_bytes()),
+        }
+    }
+
+    /// Records the reported fields, and provides them again for serialization
+    #[derive(Default)]
+    struct Recorder {
+        values: [Option<Value>; 16],
+        variants: [usize; 4],
+        lengths: [usize; 4],
+        counts: (usize, usize, usize),
+    }
+
+    impl Visitor for Recorder {
+        fn value(&mut self, _name: &str, value: Value) {
+            self.values[self.counts.0] = Some(value);
+            self.counts.0 += 1;
+        }
+
+        fn begin_array(&mut self, _name: &str, length: usize) {
+            self.lengths[self.counts.2] = length;
+            self.counts.2 += 1;
+        }
+
+        fn union_variant(&mut self, _name: &str, variant: &str) {
+            self.variants[self.counts.1] = if variant == "raw" { 0 } else { 1 };
+            self.counts.1 += 1;
+        }
+    }
+
+    impl Source for Recorder {
+        fn value(&mut self, _name: &str, _ty: &FieldType) -> Value {
+            self.counts.0 += 1;
+            self.values[self.counts.0 - 1].unwrap()
+        }
+
+        fn array_length(&mut self, _name: &str, _max_length: usize) -> usize {
+            // The static array of readings is reported as well, the text is the second array
+            self.counts.2 += 1;
+            self.lengths[self.counts.2]
+        }
+
+        fn union_variant(&mut self, _name: &str, _variants: &[Field]) -> usize {
+            self.counts.1 += 1;
+            self.variants[self.counts.1 - 1]
+        }
+    }
+
+    #[test]
+    fn matches_compile_time_types() {
+        let mut data = [0u8; 16];
+        let length;
+        {
+            let mut serializer = Serializer::from_structure(message());
+            length = serializer.byte_length();
+            let mut buffer = SerializationBuffer::with_empty_buffer(&mut data);
+            assert_eq!(serializer.serialize(&mut buffer), SerializationResult::Finished);
+        }
+        assert_eq!(MESSAGE.bit_length_min(), <Message as Serializable>::BIT_LENGTH_MIN);
+
+        let mut recorder = Recorder::default();
+        deserialize(&MESSAGE, &data[..length], &mut recorder).unwrap();
+        assert_eq!(recorder.values[0], Some(Value::Bool(true)));
+        assert_eq!(recorder.values[1], Some(Value::Signed(-3)));
+        assert_eq!(recorder.values[2], Some(Value::Float(0.5)));
+        assert_eq!(recorder.values[3], Some(Value::Signed(-1000)));
+        assert_eq!(recorder.values[4], Some(Value::Unsigned(b't' as u64)));
+        assert_eq!(recorder.lengths[..2], [2, 4]);
+        assert_eq!(recorder.variants[..2], [0, 1]);
+        assert_eq!(recorder.counts.0, 8);
+
+        recorder.counts = (0, 0, 0);
+        let mut reserialized = [0xffu8; 16];
+        assert_eq!(serialize(&MESSAGE, &mut recorder, &mut reserialized), Ok(length));
+        assert_eq!(reserialized[..length], data[..length]);
+    }
+
+    #[test]
+    fn invalid_union_tag() {
+        static UNION: FieldType = FieldType::Union(&[
+            Field { name: "a", ty: FieldType::Unsigned(8) },
+            Field { name: "b", ty: FieldType::Unsigned(8) },
+            Field { name: "c", ty: FieldType::Unsigned(8) },
+        ]);
+
+        let mut recorder = Recorder::default();
+        assert_eq!(deserialize(&UNION, &[0b1100_0000, 0], &mut recorder), Err(ReflectionError::InvalidUnionTag(3)));
+    }
+}
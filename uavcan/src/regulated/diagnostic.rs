@@ -0,0 +1,77 @@
+//! The `uavcan.diagnostic` namespace
+//!
+//! Generated from the DSDL definitions in `dsdl/v1` by `tests/regulated.rs`, regenerate it instead of editing it.
+
+use types::*;
+use v1::*;
+
+/// Generic human readable text message for logging and displaying purposes
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Record {
+    /// Optional, zero if unknown
+    pub timestamp: ::regulated::time::SynchronizedTimestamp,
+    pub severity: Severity,
+    /// UTF-8 encoded message text
+    pub text: ::heapless::Vec<u8, 255>,
+}
+
+impl FixedPort for Record {
+    const PORT_ID: u16 = 8184;
+}
+
+impl Composite for Record {
+    const SEALED: bool = false;
+    const MAX_BYTES: usize = 264;
+    const EXTENT: usize = 300;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_composite(&self.timestamp);
+        writer.write_composite(&self.severity);
+        writer.write_dynamic_array(&self.text, 255);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let timestamp = reader.read_composite()?;
+        let severity = reader.read_composite()?;
+        let text = reader.read_dynamic_array()?;
+        Ok(Record {
+            timestamp,
+            severity,
+            text,
+        })
+    }
+}
+
+/// The severity of a diagnostic record
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Severity {
+    pub value: u3,
+}
+
+impl Severity {
+    pub const TRACE: u8 = 0;
+    pub const DEBUG: u8 = 1;
+    pub const INFO: u8 = 2;
+    pub const NOTICE: u8 = 3;
+    pub const WARNING: u8 = 4;
+    pub const ERROR: u8 = 5;
+    pub const CRITICAL: u8 = 6;
+    pub const ALERT: u8 = 7;
+}
+
+impl Composite for Severity {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 1;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.value);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let value = reader.read();
+        Ok(Severity {
+            value,
+        })
+    }
+}
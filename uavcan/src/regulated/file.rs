@@ -0,0 +1,413 @@
+//! The `uavcan.file` namespace
+//!
+//! Generated from the DSDL definitions in `dsdl/v1` by `tests/regulated.rs`, regenerate it instead of editing it.
+
+use types::*;
+use v1::*;
+
+/// File operation result code
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Error {
+    pub value: u16,
+}
+
+impl Error {
+    pub const OK: u16 = 0;
+    pub const UNKNOWN_ERROR: u16 = 65535;
+    pub const NOT_FOUND: u16 = 2;
+    pub const IO_ERROR: u16 = 5;
+    pub const ACCESS_DENIED: u16 = 13;
+    /// The operation is not supported on directories
+    pub const IS_DIRECTORY: u16 = 21;
+    pub const INVALID_VALUE: u16 = 22;
+    pub const FILE_TOO_LARGE: u16 = 27;
+    pub const OUT_OF_SPACE: u16 = 28;
+    pub const NOT_SUPPORTED: u16 = 38;
+}
+
+impl Composite for Error {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 2;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.value);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let value = reader.read();
+        Ok(Error {
+            value,
+        })
+    }
+}
+
+/// Request info about a remote file system entry
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct GetInfoRequest {
+    pub path: Path,
+}
+
+impl FixedPort for GetInfoRequest {
+    const PORT_ID: u16 = 405;
+}
+
+impl Composite for GetInfoRequest {
+    const SEALED: bool = false;
+    const MAX_BYTES: usize = 256;
+    const EXTENT: usize = 300;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_composite(&self.path);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let path = reader.read_composite()?;
+        Ok(GetInfoRequest {
+            path,
+        })
+    }
+}
+
+/// Response to `GetInfoRequest`, the other fields are zero if `error` isn't `OK`
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct GetInfoResponse {
+    pub error: Error,
+    /// File size in bytes, should be zero for directories
+    pub size: u40,
+    /// The time of the last modification in seconds since the Unix epoch, zero if unknown
+    pub unix_timestamp_of_last_modification: u40,
+    pub is_file_not_directory: bool,
+    pub is_link: bool,
+    pub is_readable: bool,
+    pub is_writeable: bool,
+}
+
+impl FixedPort for GetInfoResponse {
+    const PORT_ID: u16 = 405;
+}
+
+impl Composite for GetInfoResponse {
+    const SEALED: bool = false;
+    const MAX_BYTES: usize = 13;
+    const EXTENT: usize = 48;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_composite(&self.error);
+        writer.write(self.size);
+        writer.write(self.unix_timestamp_of_last_modification);
+        writer.write(self.is_file_not_directory);
+        writer.write(self.is_link);
+        writer.write(self.is_readable);
+        writer.write(self.is_writeable);
+        writer.write_bits(4, 0);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let error = reader.read_composite()?;
+        let size = reader.read();
+        let unix_timestamp_of_last_modification = reader.read();
+        let is_file_not_directory = reader.read();
+        let is_link = reader.read();
+        let is_readable = reader.read();
+        let is_writeable = reader.read();
+        reader.skip_bits(4);
+        Ok(GetInfoResponse {
+            error,
+            size,
+            unix_timestamp_of_last_modification,
+            is_file_not_directory,
+            is_link,
+            is_readable,
+            is_writeable,
+        })
+    }
+}
+
+/// Request the name of a directory entry by its index, used to list remote directories
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct ListRequest {
+    pub entry_index: u32,
+    pub directory_path: Path,
+}
+
+impl FixedPort for ListRequest {
+    const PORT_ID: u16 = 406;
+}
+
+impl Composite for ListRequest {
+    const SEALED: bool = false;
+    const MAX_BYTES: usize = 264;
+    const EXTENT: usize = 300;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.entry_index);
+        writer.write_bits(32, 0);
+        writer.write_composite(&self.directory_path);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let entry_index = reader.read();
+        reader.skip_bits(32);
+        let directory_path = reader.read_composite()?;
+        Ok(ListRequest {
+            entry_index,
+            directory_path,
+        })
+    }
+}
+
+/// The base name of the entry, empty if the index is out of range
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct ListResponse {
+    pub entry_base_name: Path,
+}
+
+impl FixedPort for ListResponse {
+    const PORT_ID: u16 = 406;
+}
+
+impl Composite for ListResponse {
+    const SEALED: bool = false;
+    const MAX_BYTES: usize = 260;
+    const EXTENT: usize = 300;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_bits(32, 0);
+        writer.write_composite(&self.entry_base_name);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        reader.skip_bits(32);
+        let entry_base_name = reader.read_composite()?;
+        Ok(ListResponse {
+            entry_base_name,
+        })
+    }
+}
+
+/// Copy, move, touch or remove remote file system entries
+///
+/// An empty `destination` removes `source`, the same paths touch it.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct ModifyRequest {
+    /// Keep `source` when moving it to `destination`, i.e. copy it
+    pub preserve_source: bool,
+    /// Replace `destination` if it exists
+    pub overwrite_destination: bool,
+    pub source: Path,
+    pub destination: Path,
+}
+
+impl FixedPort for ModifyRequest {
+    const PORT_ID: u16 = 407;
+}
+
+impl Composite for ModifyRequest {
+    const SEALED: bool = false;
+    const MAX_BYTES: usize = 516;
+    const EXTENT: usize = 600;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.preserve_source);
+        writer.write(self.overwrite_destination);
+        writer.write_bits(30, 0);
+        writer.write_composite(&self.source);
+        writer.write_composite(&self.destination);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let preserve_source = reader.read();
+        let overwrite_destination = reader.read();
+        reader.skip_bits(30);
+        let source = reader.read_composite()?;
+        let destination = reader.read_composite()?;
+        Ok(ModifyRequest {
+            preserve_source,
+            overwrite_destination,
+            source,
+            destination,
+        })
+    }
+}
+
+/// Response to `ModifyRequest`
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct ModifyResponse {
+    pub error: Error,
+}
+
+impl FixedPort for ModifyResponse {
+    const PORT_ID: u16 = 407;
+}
+
+impl Composite for ModifyResponse {
+    const SEALED: bool = false;
+    const MAX_BYTES: usize = 2;
+    const EXTENT: usize = 48;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_composite(&self.error);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let error = reader.read_composite()?;
+        Ok(ModifyResponse {
+            error,
+        })
+    }
+}
+
+/// A file system path in UTF-8 using `/` as separator
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Path {
+    pub path: ::heapless::Vec<u8, 255>,
+}
+
+impl Path {
+    pub const SEPARATOR: u8 = b'/';
+    pub const MAX_LENGTH: u8 = 255;
+}
+
+impl Composite for Path {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 256;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_dynamic_array(&self.path, 255);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let path = reader.read_dynamic_array()?;
+        Ok(Path {
+            path,
+        })
+    }
+}
+
+/// Read a chunk of a remote file, starting at `offset`
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct ReadRequest {
+    pub offset: u40,
+    pub path: Path,
+}
+
+impl FixedPort for ReadRequest {
+    const PORT_ID: u16 = 408;
+}
+
+impl Composite for ReadRequest {
+    const SEALED: bool = false;
+    const MAX_BYTES: usize = 261;
+    const EXTENT: usize = 300;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.offset);
+        writer.write_composite(&self.path);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let offset = reader.read();
+        let path = reader.read_composite()?;
+        Ok(ReadRequest {
+            offset,
+            path,
+        })
+    }
+}
+
+/// Response to `ReadRequest`, less data than the maximum means that the end of the file is reached
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct ReadResponse {
+    pub error: Error,
+    pub data: ::regulated::primitive::Unstructured,
+}
+
+impl FixedPort for ReadResponse {
+    const PORT_ID: u16 = 408;
+}
+
+impl Composite for ReadResponse {
+    const SEALED: bool = false;
+    const MAX_BYTES: usize = 260;
+    const EXTENT: usize = 300;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_composite(&self.error);
+        writer.write_composite(&self.data);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let error = reader.read_composite()?;
+        let data = reader.read_composite()?;
+        Ok(ReadResponse {
+            error,
+            data,
+        })
+    }
+}
+
+/// Write a chunk of a remote file, starting at `offset`
+///
+/// Writing no data truncates the file at `offset`.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct WriteRequest {
+    pub offset: u40,
+    pub path: Path,
+    pub data: ::regulated::primitive::Unstructured,
+}
+
+impl FixedPort for WriteRequest {
+    const PORT_ID: u16 = 409;
+}
+
+impl Composite for WriteRequest {
+    const SEALED: bool = false;
+    const MAX_BYTES: usize = 519;
+    const EXTENT: usize = 600;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.offset);
+        writer.write_composite(&self.path);
+        writer.write_composite(&self.data);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let offset = reader.read();
+        let path = reader.read_composite()?;
+        let data = reader.read_composite()?;
+        Ok(WriteRequest {
+            offset,
+            path,
+            data,
+        })
+    }
+}
+
+/// Response to `WriteRequest`
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct WriteResponse {
+    pub error: Error,
+}
+
+impl FixedPort for WriteResponse {
+    const PORT_ID: u16 = 409;
+}
+
+impl Composite for WriteResponse {
+    const SEALED: bool = false;
+    const MAX_BYTES: usize = 2;
+    const EXTENT: usize = 48;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_composite(&self.error);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let error = reader.read_composite()?;
+        Ok(WriteResponse {
+            error,
+        })
+    }
+}
@@ -0,0 +1,249 @@
+//! The standard data types, generated from the DSDL definitions in `dsdl`
+//!
+//! These are the types that are needed to take part in a standard network,
+//! which makes it possible to use them without adding the DSDL definitions and `dsdl_compiler` to the build.
+//! The UAVCAN v0 types are compiled from the same definitions as the `dsdl` crate and are interchangeable on the bus.
+//!
+//! | Namespace                          | Data types                                                       |
+//! |------------------------------------|------------------------------------------------------------------|
+//! | `uavcan.protocol`                  | `NodeStatus`, `GetNodeInfo`, `RestartNode`, `GlobalTimeSync`, `GetTransportStats` |
+//! | `uavcan.protocol.param`            | `GetSet`, `ExecuteOpcode`                                        |
+//! | `uavcan.protocol.file`             | `GetInfo`, `GetDirectoryEntryInfo`, `Delete`, `Read`, `Write`, `BeginFirmwareUpdate` |
+//! | `uavcan.protocol.dynamic_node_id`  | `Allocation`                                                     |
+//! | `uavcan.protocol.debug`            | `LogMessage`                                                     |
+//!
+//! The UAVCAN v1 types implement `v1::Composite` instead of `Struct`, and `v1::FixedPort` when they have a fixed port ID.
+//!
+//! | Namespace                          | Data types                                                       |
+//! |------------------------------------|------------------------------------------------------------------|
+//! | `uavcan.node`                      | `Heartbeat.1.0`, `GetInfo.1.0`                                   |
+//! | `uavcan.register`                  | `Access.1.0`, `List.1.0`                                         |
+//! | `uavcan.file`                      | `GetInfo.0.2`, `List.0.2`, `Modify.1.1`, `Read.1.1`, `Write.1.1` |
+//! | `uavcan.pnp`                       | `NodeIDAllocationData.1.0` and `2.0` (as `NodeIDAllocationDataV2`) |
+//! | `uavcan.pnp.cluster`               | `AppendEntries.1.0`, `RequestVote.1.0`, `Discovery.1.0`          |
+//! | `uavcan.diagnostic`                | `Record.1.1`                                                     |
+//! | `uavcan.time`                      | `Synchronization.1.0`                                            |
+//!
+//! The DSDL constants are associated constants of the types, like `NodeStatus::MODE_OPERATIONAL`.
+//! Constants of types without a native counterpart use the smallest native type that can hold them,
+//! so `NodeStatus::HEALTH_OK` is an `u8` that converts with `u2::new(NodeStatus::HEALTH_OK)`.
+//!
+//! The modules are generated by `tests/regulated.rs`, which fails when they don't match the definitions.
+//! After changing a definition they are regenerated with `REGENERATE=1 cargo test --features regulated --test regulated`.
+//!
+//! # Examples
+//!
+//! ```
+//! use uavcan::Message;
+//! use uavcan::Request;
+//! use uavcan::v1::FixedPort;
+//! use uavcan::regulated::protocol::{NodeStatus, GetNodeInfoRequest};
+//! use uavcan::regulated::node::Heartbeat;
+//!
+//! assert_eq!(NodeStatus::TYPE_ID, Some(341));
+//! assert_eq!(GetNodeInfoRequest::TYPE_ID, Some(1));
+//! assert_eq!(Heartbeat::PORT_ID, 7509);
+//! ```
+
+pub mod diagnostic;
+pub mod file;
+pub mod node;
+pub mod pnp;
+pub mod primitive;
+pub mod protocol;
+pub mod register;
+pub mod time;
+
+#[cfg(test)]
+mod tests {
+
+    use *;
+    use types::*;
+    use serializer::Serializer;
+    use v1::*;
+    use regulated::protocol::*;
+    use regulated::{
+        node,
+        register,
+    };
+
+    #[test]
+    fn signatures() {
+        assert_eq!(<NodeStatus as Message>::TYPE_ID, Some(341));
+        assert_eq!(NodeStatus::DATA_TYPE_SIGNATURE, 0x0f0868d0c1a7c6f1);
+        assert_eq!(<GetNodeInfoRequest as Request>::TYPE_ID, Some(1));
+        assert_eq!(<GetNodeInfoResponse as Response>::TYPE_ID, Some(1));
+        assert_eq!(GetNodeInfoResponse::DATA_TYPE_SIGNATURE, 0xee468a8121c46a9e);
+        assert_eq!(param::GetSetRequest::DATA_TYPE_SIGNATURE, 0xa7b622f939d1a4d5);
+        assert_eq!(<dynamic_node_id::Allocation as Message>::TYPE_ID, Some(1));
+        assert_eq!(<debug::LogMessage as Message>::TYPE_ID, Some(16383));
+    }
+
+    #[test]
+    fn node_status_layout() {
+        let status = NodeStatus{
+            uptime_sec: 1,
+            health: u2::new(NodeStatus::HEALTH_ERROR),
+            mode: u3::new(NodeStatus::MODE_SOFTWARE_UPDATE),
+            sub_mode: u3::new(4),
+            vendor_specific_status_code: 5,
+        };
+
+        // Always fits a single CAN frame next to the tail byte
+        assert_eq!(NodeStatus::MIN_BYTES, 7);
+        assert_eq!(NodeStatus::MAX_BYTES, 7);
+        assert_eq!(NodeStatus::MAX_CAN_FRAMES, 1);
+
+        let mut serializer: Serializer<NodeStatus> = Serializer::from_structure(status.clone());
+        let mut array = [0u8; NodeStatus::MAX_BYTES];
+        let mut buffer = SerializationBuffer::with_empty_buffer(&mut array);
+        serializer.serialize(&mut buffer);
+        assert_eq!(buffer.data, [1, 0, 0, 0, 0b10011100, 5, 0]);
+
+        let mut deserializer: Deserializer<NodeStatus> = Deserializer::new();
+        deserializer.deserialize_chunk(&[1, 0, 0, 0, 0b10011100, 5, 0]);
+        assert_eq!(deserializer.into_structure().unwrap(), status);
+    }
+
+    #[test]
+    fn native_accessors() {
+        let mut status = NodeStatus::default();
+        status.set_health(NodeStatus::HEALTH_WARNING);
+        status.set_mode(NodeStatus::MODE_MAINTENANCE);
+        status.set_sub_mode(9);
+
+        assert_eq!(status.health, u2::new(1));
+        assert_eq!(status.health(), NodeStatus::HEALTH_WARNING);
+        assert_eq!(status.mode(), NodeStatus::MODE_MAINTENANCE);
+
+        // Values out of range saturate like the saturated cast mode
+        assert_eq!(status.sub_mode(), 7);
+    }
+
+    #[test]
+    fn readable_debug() {
+        use lib::core::fmt::Write;
+
+        let status = NodeStatus{
+            uptime_sec: 1,
+            health: u2::new(NodeStatus::HEALTH_ERROR),
+            mode: u3::new(NodeStatus::MODE_SOFTWARE_UPDATE),
+            sub_mode: u3::new(4),
+            vendor_specific_status_code: 5,
+        };
+        let mut text = ::heapless::String::<256>::new();
+        write!(text, "{:?}", status).unwrap();
+        assert_eq!(
+            text.as_str(),
+            "NodeStatus { uptime_sec: 1, health: 2 (HEALTH_ERROR), mode: 3 (MODE_SOFTWARE_UPDATE), sub_mode: 4, vendor_specific_status_code: 5 }"
+        );
+
+        let message = debug::LogMessage{
+            level: debug::LogLevel{value: u3::new(debug::LogLevel::ERROR)},
+            source: "gps".parse().unwrap(),
+            text: "no fix".parse().unwrap(),
+        };
+        let mut text = ::heapless::String::<256>::new();
+        write!(text, "{:?}", message).unwrap();
+        assert_eq!(text.as_str(), "LogMessage { level: LogLevel { value: 3 (ERROR) }, source: \"gps\", text: \"no fix\" }");
+
+        let mut text = ::heapless::String::<256>::new();
+        write!(text, "{:?}", param::Value::IntegerValue(-3)).unwrap();
+        assert_eq!(text.as_str(), "IntegerValue(-3)");
+    }
+
+    #[test]
+    fn empty_request() {
+        let mut serializer: Serializer<GetNodeInfoRequest> = Serializer::from_structure(GetNodeInfoRequest{});
+        let mut array: [u8; 0] = [];
+        let mut buffer = SerializationBuffer::with_empty_buffer(&mut array);
+        assert_eq!(serializer.serialize(&mut buffer), SerializationResult::Finished);
+
+        let mut deserializer: Deserializer<GetNodeInfoRequest> = Deserializer::new();
+        assert_eq!(deserializer.deserialize_chunk(&[]), DeserializationResult::BufferInsufficient);
+        assert_eq!(deserializer.into_structure().unwrap(), GetNodeInfoRequest{});
+    }
+
+    #[test]
+    fn zero_initialized_default() {
+        let response = param::GetSetResponse::default();
+        assert_eq!(response.value, param::Value::Empty(param::Empty{}));
+        assert_eq!(response.max_value, param::NumericValue::Empty(param::Empty{}));
+        assert_eq!(response.name.0.length(), 0);
+    }
+
+    #[test]
+    fn get_node_info_round_trip() {
+        let mut response = GetNodeInfoResponse::default();
+        response.status.uptime_sec = 12;
+        response.software_version.major = 1;
+        response.hardware_version.unique_id = [0xab; 16];
+        response.name = "org.uavcan.test".parse().unwrap();
+
+        let mut serializer: Serializer<GetNodeInfoResponse> = Serializer::from_structure(response.clone());
+        let mut array: [u8; 64] = [0; 64];
+        let length = {
+            let mut buffer = SerializationBuffer::with_empty_buffer(&mut array);
+            assert_eq!(serializer.serialize(&mut buffer), SerializationResult::Finished);
+            (buffer.bit_length() + 7) / 8
+        };
+
+        // status, software version, hardware version with an empty certificate, and the name as a tail array
+        assert_eq!(length, 7 + 15 + 19 + 15);
+
+        let mut deserializer: Deserializer<GetNodeInfoResponse> = Deserializer::new();
+        deserializer.deserialize_chunk(&array[..length]);
+        assert_eq!(deserializer.into_structure().unwrap(), response);
+    }
+
+    #[test]
+    fn heartbeat_layout() {
+        let heartbeat = node::Heartbeat{
+            uptime: 1,
+            health: node::Health{value: u2::new(node::Health::CAUTION)},
+            mode: node::Mode{value: u3::new(node::Mode::MAINTENANCE)},
+            vendor_specific_status_code: 5,
+        };
+
+        // The nested sealed composites start at a byte boundary
+        assert_eq!(node::Heartbeat::PORT_ID, 7509);
+        assert_eq!(node::Heartbeat::MAX_BYTES, 7);
+
+        let mut buffer = [0u8; node::Heartbeat::MAX_BYTES];
+        assert_eq!(heartbeat.serialize(&mut buffer), Ok(7));
+        assert_eq!(buffer, [1, 0, 0, 0, 2, 2, 5]);
+        assert_eq!(node::Heartbeat::deserialize(&buffer), Ok(heartbeat));
+    }
+
+    #[test]
+    fn extensible_sizes() {
+        assert_eq!(node::GetInfoRequest::PORT_ID, 430);
+        assert_eq!(node::GetInfoResponse::PORT_ID, 430);
+        assert!(!node::GetInfoResponse::SEALED);
+        assert_eq!(node::GetInfoResponse::MAX_BYTES, 313);
+        assert_eq!(node::GetInfoResponse::EXTENT, 448);
+
+        // The name and the union tag with the largest variant, a string of 256 bytes with a 16 bit length
+        assert!(register::AccessRequest::SEALED);
+        assert_eq!(register::AccessRequest::MAX_BYTES, 256 + 259);
+    }
+
+    #[test]
+    fn register_value_round_trip() {
+        let mut request = register::AccessRequest::default();
+        request.name.name.extend_from_slice(b"uavcan.node.id").unwrap();
+        let mut values = ::heapless::Vec::new();
+        values.push(42).unwrap();
+        request.value = register::Value::Natural16(::regulated::primitive::array::Natural16{value: values});
+
+        let mut buffer = [0u8; register::AccessRequest::MAX_BYTES];
+        let length = request.serialize(&mut buffer).unwrap();
+        assert_eq!(length, 1 + 14 + 1 + 1 + 2);
+        assert_eq!(&buffer[15..length], &[10, 1, 42, 0]);
+        assert_eq!(register::AccessRequest::deserialize(&buffer[..length]), Ok(request));
+
+        // Tag 15 names no variant
+        buffer[15] = 15;
+        assert_eq!(register::AccessRequest::deserialize(&buffer[..length]), Err(DecodeError::UnionTag));
+    }
+}
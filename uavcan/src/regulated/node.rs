@@ -0,0 +1,253 @@
+//! The `uavcan.node` namespace
+//!
+//! Generated from the DSDL definitions in `dsdl/v1` by `tests/regulated.rs`, regenerate it instead of editing it.
+
+use types::*;
+use v1::*;
+
+/// Full node info request
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct GetInfoRequest {}
+
+impl FixedPort for GetInfoRequest {
+    const PORT_ID: u16 = 430;
+}
+
+impl Composite for GetInfoRequest {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 0;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, _writer: &mut Writer) {}
+
+    fn deserialize_fields(_reader: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(GetInfoRequest {})
+    }
+}
+
+/// Full node info response, the fields don't change while the node is running
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct GetInfoResponse {
+    /// The UAVCAN protocol version implemented by the node, 1.0
+    pub protocol_version: Version,
+    pub hardware_version: Version,
+    pub software_version: Version,
+    /// Version control system revision of the software, zero if not available
+    pub software_vcs_revision_id: u64,
+    /// Unique ID of the node, it never changes
+    pub unique_id: [u8; 16],
+    /// Human readable non-empty ASCII node name in reversed domain notation, e.g. "com.example.product_name"
+    pub name: ::heapless::Vec<u8, 50>,
+    /// CRC-64-WE of the software image, if available
+    pub software_image_crc: ::heapless::Vec<u64, 1>,
+    /// Certificate of authenticity of the hardware, empty if not available
+    pub certificate_of_authenticity: ::heapless::Vec<u8, 222>,
+}
+
+impl FixedPort for GetInfoResponse {
+    const PORT_ID: u16 = 430;
+}
+
+impl Composite for GetInfoResponse {
+    const SEALED: bool = false;
+    const MAX_BYTES: usize = 313;
+    const EXTENT: usize = 448;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_composite(&self.protocol_version);
+        writer.write_composite(&self.hardware_version);
+        writer.write_composite(&self.software_version);
+        writer.write(self.software_vcs_revision_id);
+        writer.write_array(&self.unique_id);
+        writer.write_dynamic_array(&self.name, 50);
+        writer.write_dynamic_array(&self.software_image_crc, 1);
+        writer.write_dynamic_array(&self.certificate_of_authenticity, 222);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let protocol_version = reader.read_composite()?;
+        let hardware_version = reader.read_composite()?;
+        let software_version = reader.read_composite()?;
+        let software_vcs_revision_id = reader.read();
+        let unique_id = reader.read_array();
+        let name = reader.read_dynamic_array()?;
+        let software_image_crc = reader.read_dynamic_array()?;
+        let certificate_of_authenticity = reader.read_dynamic_array()?;
+        Ok(GetInfoResponse {
+            protocol_version,
+            hardware_version,
+            software_version,
+            software_vcs_revision_id,
+            unique_id,
+            name,
+            software_image_crc,
+            certificate_of_authenticity,
+        })
+    }
+}
+
+/// Abstract component health information
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Health {
+    pub value: u2,
+}
+
+impl Health {
+    /// The component is functioning properly
+    pub const NOMINAL: u8 = 0;
+    /// A critical parameter went out of range or the component encountered a minor failure
+    pub const ADVISORY: u8 = 1;
+    /// The component encountered a major failure and is performing in a degraded mode or is outside of its designed limitations
+    pub const CAUTION: u8 = 2;
+    /// The component suffered a fatal malfunction and is unable to perform its intended function
+    pub const WARNING: u8 = 3;
+}
+
+impl Composite for Health {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 1;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.value);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let value = reader.read();
+        Ok(Health {
+            value,
+        })
+    }
+}
+
+/// Abstract node status information, published by every node at least once a second
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Heartbeat {
+    /// Uptime counter in seconds, it should never overflow
+    pub uptime: u32,
+    pub health: Health,
+    pub mode: Mode,
+    /// Optional, vendor specific node status code
+    pub vendor_specific_status_code: u8,
+}
+
+impl Heartbeat {
+    /// The longest period between two heartbeats, in seconds
+    pub const MAX_PUBLICATION_PERIOD: u16 = 1;
+    /// A node that hasn't published `Heartbeat` for this long should be considered offline, in seconds
+    pub const OFFLINE_TIMEOUT: u16 = 3;
+}
+
+impl FixedPort for Heartbeat {
+    const PORT_ID: u16 = 7509;
+}
+
+impl Composite for Heartbeat {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 7;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.uptime);
+        writer.write_composite(&self.health);
+        writer.write_composite(&self.mode);
+        writer.write(self.vendor_specific_status_code);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let uptime = reader.read();
+        let health = reader.read_composite()?;
+        let mode = reader.read_composite()?;
+        let vendor_specific_status_code = reader.read();
+        Ok(Heartbeat {
+            uptime,
+            health,
+            mode,
+            vendor_specific_status_code,
+        })
+    }
+}
+
+/// A node ID, the range depends on the transport
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct ID {
+    pub value: u16,
+}
+
+impl Composite for ID {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 2;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.value);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let value = reader.read();
+        Ok(ID {
+            value,
+        })
+    }
+}
+
+/// The operating mode of a node
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Mode {
+    pub value: u3,
+}
+
+impl Mode {
+    /// Normal operating mode
+    pub const OPERATIONAL: u8 = 0;
+    /// Initialization is in progress, entered immediately after startup
+    pub const INITIALIZATION: u8 = 1;
+    /// E.g. calibration, self-test, etc
+    pub const MAINTENANCE: u8 = 2;
+    /// New software or firmware is being loaded or the bootloader is running
+    pub const SOFTWARE_UPDATE: u8 = 3;
+}
+
+impl Composite for Mode {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 1;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.value);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let value = reader.read();
+        Ok(Mode {
+            value,
+        })
+    }
+}
+
+/// A shortened semantic version, major and minor
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Version {
+    pub major: u8,
+    pub minor: u8,
+}
+
+impl Composite for Version {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 2;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.major);
+        writer.write(self.minor);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let major = reader.read();
+        let minor = reader.read();
+        Ok(Version {
+            major,
+            minor,
+        })
+    }
+}
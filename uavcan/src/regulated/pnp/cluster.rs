@@ -0,0 +1,227 @@
+//! The `uavcan.pnp.cluster` namespace
+//!
+//! Generated from the DSDL definitions in `dsdl/v1` by `tests/regulated.rs`, regenerate it instead of editing it.
+
+use types::*;
+use v1::*;
+
+/// Replicates the allocation table from the leader to the followers, also used as the heartbeat of the leader
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct AppendEntriesRequest {
+    pub term: u32,
+    pub prev_log_term: u32,
+    pub prev_log_index: u16,
+    pub leader_commit: u16,
+    /// Empty in heartbeats
+    pub entries: ::heapless::Vec<Entry, 1>,
+}
+
+impl AppendEntriesRequest {
+    /// In seconds
+    pub const DEFAULT_MIN_ELECTION_TIMEOUT: u8 = 2;
+    /// In seconds
+    pub const DEFAULT_MAX_ELECTION_TIMEOUT: u8 = 4;
+}
+
+impl FixedPort for AppendEntriesRequest {
+    const PORT_ID: u16 = 390;
+}
+
+impl Composite for AppendEntriesRequest {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 35;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.term);
+        writer.write(self.prev_log_term);
+        writer.write(self.prev_log_index);
+        writer.write(self.leader_commit);
+        writer.write_dynamic_composite_array(&self.entries, 1);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let term = reader.read();
+        let prev_log_term = reader.read();
+        let prev_log_index = reader.read();
+        let leader_commit = reader.read();
+        let entries = reader.read_dynamic_composite_array()?;
+        Ok(AppendEntriesRequest {
+            term,
+            prev_log_term,
+            prev_log_index,
+            leader_commit,
+            entries,
+        })
+    }
+}
+
+/// Response to `AppendEntriesRequest`
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct AppendEntriesResponse {
+    pub term: u32,
+    pub success: bool,
+}
+
+impl FixedPort for AppendEntriesResponse {
+    const PORT_ID: u16 = 390;
+}
+
+impl Composite for AppendEntriesResponse {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 5;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.term);
+        writer.write(self.success);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let term = reader.read();
+        let success = reader.read();
+        Ok(AppendEntriesResponse {
+            term,
+            success,
+        })
+    }
+}
+
+/// Published by the allocators while they discover each other, and when a new allocator joins
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Discovery {
+    /// The number of allocators in the cluster
+    pub configuration_size: u3,
+    /// The node IDs of the allocators the publisher knows, including itself
+    pub known_nodes: ::heapless::Vec<::regulated::node::ID, 5>,
+}
+
+impl Discovery {
+    /// The period between two publications, in seconds
+    pub const BROADCASTING_PERIOD: u8 = 1;
+    pub const MAX_CLUSTER_SIZE: u8 = 5;
+}
+
+impl FixedPort for Discovery {
+    const PORT_ID: u16 = 8164;
+}
+
+impl Composite for Discovery {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 12;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.configuration_size);
+        writer.write_bits(5, 0);
+        writer.write_dynamic_composite_array(&self.known_nodes, 5);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let configuration_size = reader.read();
+        reader.skip_bits(5);
+        let known_nodes = reader.read_dynamic_composite_array()?;
+        Ok(Discovery {
+            configuration_size,
+            known_nodes,
+        })
+    }
+}
+
+/// An entry of the replicated allocation table of the allocators
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Entry {
+    /// The term of the leader that added the entry
+    pub term: u32,
+    pub unique_id: [u8; 16],
+    pub node_id: ::regulated::node::ID,
+}
+
+impl Composite for Entry {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 22;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.term);
+        writer.write_array(&self.unique_id);
+        writer.write_composite(&self.node_id);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let term = reader.read();
+        let unique_id = reader.read_array();
+        let node_id = reader.read_composite()?;
+        Ok(Entry {
+            term,
+            unique_id,
+            node_id,
+        })
+    }
+}
+
+/// Requested by the candidates during the leader election
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct RequestVoteRequest {
+    pub term: u32,
+    pub last_log_term: u32,
+    pub last_log_index: u16,
+}
+
+impl FixedPort for RequestVoteRequest {
+    const PORT_ID: u16 = 391;
+}
+
+impl Composite for RequestVoteRequest {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 10;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.term);
+        writer.write(self.last_log_term);
+        writer.write(self.last_log_index);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let term = reader.read();
+        let last_log_term = reader.read();
+        let last_log_index = reader.read();
+        Ok(RequestVoteRequest {
+            term,
+            last_log_term,
+            last_log_index,
+        })
+    }
+}
+
+/// Response to `RequestVoteRequest`
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct RequestVoteResponse {
+    pub term: u32,
+    pub vote_granted: bool,
+}
+
+impl FixedPort for RequestVoteResponse {
+    const PORT_ID: u16 = 391;
+}
+
+impl Composite for RequestVoteResponse {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 5;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.term);
+        writer.write(self.vote_granted);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let term = reader.read();
+        let vote_granted = reader.read();
+        Ok(RequestVoteResponse {
+            term,
+            vote_granted,
+        })
+    }
+}
@@ -0,0 +1,78 @@
+//! The `uavcan.pnp` namespace
+//!
+//! Generated from the DSDL definitions in `dsdl/v1` by `tests/regulated.rs`, regenerate it instead of editing it.
+
+pub mod cluster;
+
+use types::*;
+use v1::*;
+
+/// Plug and play node ID allocation for transports with small frames, like classic CAN
+///
+/// The allocatee publishes it anonymously with the hash of its unique ID and no node ID,
+/// the allocator responds with the same hash and the allocated node ID.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct NodeIDAllocationDataV1 {
+    /// The lowest 48 bits of the 64 bit hash of the unique ID
+    pub unique_id_hash: u48,
+    /// Empty in the requests of the allocatee
+    pub allocated_node_id: ::heapless::Vec<::regulated::node::ID, 1>,
+}
+
+impl FixedPort for NodeIDAllocationDataV1 {
+    const PORT_ID: u16 = 8166;
+}
+
+impl Composite for NodeIDAllocationDataV1 {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 9;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.unique_id_hash);
+        writer.write_dynamic_composite_array(&self.allocated_node_id, 1);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let unique_id_hash = reader.read();
+        let allocated_node_id = reader.read_dynamic_composite_array()?;
+        Ok(NodeIDAllocationDataV1 {
+            unique_id_hash,
+            allocated_node_id,
+        })
+    }
+}
+
+/// Plug and play node ID allocation for transports with large frames, like UDP and CAN FD
+///
+/// The allocatee publishes it anonymously with the desired node ID, the allocator responds with the allocated one.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct NodeIDAllocationDataV2 {
+    pub node_id: ::regulated::node::ID,
+    /// The unique ID of the allocatee
+    pub unique_id: [u8; 16],
+}
+
+impl FixedPort for NodeIDAllocationDataV2 {
+    const PORT_ID: u16 = 8165;
+}
+
+impl Composite for NodeIDAllocationDataV2 {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 18;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_composite(&self.node_id);
+        writer.write_array(&self.unique_id);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let node_id = reader.read_composite()?;
+        let unique_id = reader.read_array();
+        Ok(NodeIDAllocationDataV2 {
+            node_id,
+            unique_id,
+        })
+    }
+}
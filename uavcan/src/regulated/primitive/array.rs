@@ -0,0 +1,282 @@
+//! The `uavcan.primitive.array` namespace
+//!
+//! Generated from the DSDL definitions in `dsdl/v1` by `tests/regulated.rs`, regenerate it instead of editing it.
+
+use types::*;
+use v1::*;
+
+/// An array of bits
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Bit {
+    pub value: ::heapless::Vec<bool, 2048>,
+}
+
+impl Composite for Bit {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 258;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_dynamic_array(&self.value, 2048);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let value = reader.read_dynamic_array()?;
+        Ok(Bit {
+            value,
+        })
+    }
+}
+
+/// An array of 16 bit signed integers
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Integer16 {
+    pub value: ::heapless::Vec<i16, 128>,
+}
+
+impl Composite for Integer16 {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 257;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_dynamic_array(&self.value, 128);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let value = reader.read_dynamic_array()?;
+        Ok(Integer16 {
+            value,
+        })
+    }
+}
+
+/// An array of 32 bit signed integers
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Integer32 {
+    pub value: ::heapless::Vec<i32, 64>,
+}
+
+impl Composite for Integer32 {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 257;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_dynamic_array(&self.value, 64);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let value = reader.read_dynamic_array()?;
+        Ok(Integer32 {
+            value,
+        })
+    }
+}
+
+/// An array of 64 bit signed integers
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Integer64 {
+    pub value: ::heapless::Vec<i64, 32>,
+}
+
+impl Composite for Integer64 {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 257;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_dynamic_array(&self.value, 32);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let value = reader.read_dynamic_array()?;
+        Ok(Integer64 {
+            value,
+        })
+    }
+}
+
+/// An array of 8 bit signed integers
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Integer8 {
+    pub value: ::heapless::Vec<i8, 256>,
+}
+
+impl Composite for Integer8 {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 258;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_dynamic_array(&self.value, 256);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let value = reader.read_dynamic_array()?;
+        Ok(Integer8 {
+            value,
+        })
+    }
+}
+
+/// An array of 16 bit unsigned integers
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Natural16 {
+    pub value: ::heapless::Vec<u16, 128>,
+}
+
+impl Composite for Natural16 {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 257;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_dynamic_array(&self.value, 128);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let value = reader.read_dynamic_array()?;
+        Ok(Natural16 {
+            value,
+        })
+    }
+}
+
+/// An array of 32 bit unsigned integers
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Natural32 {
+    pub value: ::heapless::Vec<u32, 64>,
+}
+
+impl Composite for Natural32 {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 257;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_dynamic_array(&self.value, 64);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let value = reader.read_dynamic_array()?;
+        Ok(Natural32 {
+            value,
+        })
+    }
+}
+
+/// An array of 64 bit unsigned integers
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Natural64 {
+    pub value: ::heapless::Vec<u64, 32>,
+}
+
+impl Composite for Natural64 {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 257;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_dynamic_array(&self.value, 32);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let value = reader.read_dynamic_array()?;
+        Ok(Natural64 {
+            value,
+        })
+    }
+}
+
+/// An array of 8 bit unsigned integers
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Natural8 {
+    pub value: ::heapless::Vec<u8, 256>,
+}
+
+impl Composite for Natural8 {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 258;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_dynamic_array(&self.value, 256);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let value = reader.read_dynamic_array()?;
+        Ok(Natural8 {
+            value,
+        })
+    }
+}
+
+/// An array of half precision floats
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Real16 {
+    pub value: ::heapless::Vec<f16, 128>,
+}
+
+impl Composite for Real16 {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 257;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_dynamic_array(&self.value, 128);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let value = reader.read_dynamic_array()?;
+        Ok(Real16 {
+            value,
+        })
+    }
+}
+
+/// An array of single precision floats
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Real32 {
+    pub value: ::heapless::Vec<f32, 64>,
+}
+
+impl Composite for Real32 {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 257;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_dynamic_array(&self.value, 64);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let value = reader.read_dynamic_array()?;
+        Ok(Real32 {
+            value,
+        })
+    }
+}
+
+/// An array of double precision floats
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Real64 {
+    pub value: ::heapless::Vec<f64, 32>,
+}
+
+impl Composite for Real64 {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 257;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_dynamic_array(&self.value, 32);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let value = reader.read_dynamic_array()?;
+        Ok(Real64 {
+            value,
+        })
+    }
+}
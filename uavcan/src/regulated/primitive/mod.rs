@@ -0,0 +1,69 @@
+//! The `uavcan.primitive` namespace
+//!
+//! Generated from the DSDL definitions in `dsdl/v1` by `tests/regulated.rs`, regenerate it instead of editing it.
+
+pub mod array;
+
+use v1::*;
+
+/// A placeholder for an empty value
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Empty {}
+
+impl Composite for Empty {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 0;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, _writer: &mut Writer) {}
+
+    fn deserialize_fields(_reader: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(Empty {})
+    }
+}
+
+/// UTF-8 encoded text
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct String {
+    pub value: ::heapless::Vec<u8, 256>,
+}
+
+impl Composite for String {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 258;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_dynamic_array(&self.value, 256);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let value = reader.read_dynamic_array()?;
+        Ok(String {
+            value,
+        })
+    }
+}
+
+/// Raw unstructured binary data
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Unstructured {
+    pub value: ::heapless::Vec<u8, 256>,
+}
+
+impl Composite for Unstructured {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 258;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_dynamic_array(&self.value, 256);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let value = reader.read_dynamic_array()?;
+        Ok(Unstructured {
+            value,
+        })
+    }
+}
@@ -0,0 +1,30 @@
+//! The `uavcan.protocol.debug` namespace
+//!
+//! Generated from the DSDL definitions in `dsdl/v0` by `tests/regulated.rs`, regenerate it instead of editing it.
+
+use types::*;
+
+/// Nested type, log message severity
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, signature = 0x711bf141af572346, dsdl_signature = 0x711bf141af572346)]
+pub struct LogLevel {
+    #[uavcan(names(DEBUG, INFO, WARNING, ERROR))]
+    pub value: u3,
+}
+
+impl LogLevel {
+    pub const DEBUG: u8 = 0;
+    pub const INFO: u8 = 1;
+    pub const WARNING: u8 = 2;
+    pub const ERROR: u8 = 3;
+}
+
+/// Generic log message, all items are optional
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, id = 16383, signature = 0xd654a48e0c049d75, dsdl_signature = 0xe9862b78d38762ba)]
+pub struct LogMessage {
+    pub level: LogLevel,
+    /// The subsystem that generated the message
+    pub source: Utf8String<[u8; 31]>,
+    pub text: Utf8String<[u8; 90]>,
+}
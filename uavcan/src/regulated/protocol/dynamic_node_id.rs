@@ -0,0 +1,30 @@
+//! The `uavcan.protocol.dynamic_node_id` namespace
+//!
+//! Generated from the DSDL definitions in `dsdl/v0` by `tests/regulated.rs`, regenerate it instead of editing it.
+
+use types::*;
+
+/// Dynamic node ID allocation, exchanged between the allocatee and the allocators
+///
+/// The unique ID is transferred in parts since it doesn't fit a single anonymous frame.
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, id = 1, signature = 0x0b2a812620a11d40, dsdl_signature = 0x0b2a812620a11d40)]
+pub struct Allocation {
+    /// The requested node ID from the allocatee, or the allocated node ID from the allocator
+    pub node_id: u7,
+    /// Set if this message carries the first part of the unique ID
+    pub first_part_of_unique_id: bool,
+    pub unique_id: Dynamic<[u8; 16]>,
+}
+
+impl Allocation {
+    pub const MAX_REQUEST_PERIOD_MS: u16 = 1000;
+    pub const MIN_REQUEST_PERIOD_MS: u16 = 600;
+    pub const MAX_FOLLOWUP_DELAY_MS: u16 = 400;
+    pub const MIN_FOLLOWUP_DELAY_MS: u16 = 0;
+    pub const FOLLOWUP_TIMEOUT_MS: u16 = 500;
+    /// The number of bytes of the unique ID an allocatee can send in a single anonymous frame
+    pub const MAX_LENGTH_OF_UNIQUE_ID_IN_REQUEST: u8 = 6;
+    /// Used by the allocatee to request any node ID
+    pub const ANY_NODE_ID: u8 = 0;
+}
@@ -0,0 +1,158 @@
+//! The `uavcan.protocol.file` namespace
+//!
+//! Generated from the DSDL definitions in `dsdl/v0` by `tests/regulated.rs`, regenerate it instead of editing it.
+
+use types::*;
+
+/// Ask a node to update its firmware from a file served by `source_node_id`
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, id = 40, response = "BeginFirmwareUpdateResponse", signature = 0xb7d725df72724126, dsdl_signature = 0x36a8b8aa5453257b)]
+pub struct BeginFirmwareUpdateRequest {
+    /// The node that serves the image, zero means the node that sent the request
+    pub source_node_id: u8,
+    pub image_file_remote_path: Path,
+}
+
+/// Response to `BeginFirmwareUpdateRequest`
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, request = "BeginFirmwareUpdateRequest", signature = 0xb7d725df72724126, dsdl_signature = 0x36a8b8aa5453257b)]
+pub struct BeginFirmwareUpdateResponse {
+    #[uavcan(names(ERROR_OK, ERROR_INVALID_MODE, ERROR_IN_PROGRESS, ERROR_UNKNOWN))]
+    pub error: u8,
+    pub optional_error_message: Dynamic<[u8; 127]>,
+}
+
+impl BeginFirmwareUpdateResponse {
+    pub const ERROR_OK: u8 = 0;
+    pub const ERROR_INVALID_MODE: u8 = 1;
+    pub const ERROR_IN_PROGRESS: u8 = 2;
+    pub const ERROR_UNKNOWN: u8 = 255;
+}
+
+/// Delete a remote file system entry
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, id = 47, response = "DeleteResponse", signature = 0x78648c99170b47aa, dsdl_signature = 0x37184f4d5e898f0e)]
+pub struct DeleteRequest {
+    pub path: Path,
+}
+
+/// Response to `DeleteRequest`
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, request = "DeleteRequest", signature = 0x78648c99170b47aa, dsdl_signature = 0x37184f4d5e898f0e)]
+pub struct DeleteResponse {
+    pub error: Error,
+}
+
+/// Nested type, the type of a file system entry as bit flags
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, signature = 0x6924572fbb2086e5, dsdl_signature = 0x6924572fbb2086e5)]
+pub struct EntryType {
+    pub flags: u8,
+}
+
+impl EntryType {
+    pub const FLAG_FILE: u8 = 1;
+    pub const FLAG_DIRECTORY: u8 = 2;
+    pub const FLAG_SYMLINK: u8 = 4;
+    pub const FLAG_READABLE: u8 = 8;
+    pub const FLAG_WRITEABLE: u8 = 16;
+}
+
+/// Nested type, file operation result code
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, signature = 0xa83071ffea4fae15, dsdl_signature = 0xa83071ffea4fae15)]
+pub struct Error {
+    #[uavcan(names(OK, UNKNOWN_ERROR, NOT_FOUND, IO_ERROR, ACCESS_DENIED, IS_DIRECTORY, INVALID_VALUE, FILE_TOO_LARGE, OUT_OF_SPACE, NOT_IMPLEMENTED))]
+    pub value: i16,
+}
+
+impl Error {
+    pub const OK: i16 = 0;
+    pub const UNKNOWN_ERROR: i16 = 32767;
+    pub const NOT_FOUND: i16 = 2;
+    pub const IO_ERROR: i16 = 5;
+    pub const ACCESS_DENIED: i16 = 13;
+    /// The operation is not supported on directories
+    pub const IS_DIRECTORY: i16 = 21;
+    pub const INVALID_VALUE: i16 = 22;
+    pub const FILE_TOO_LARGE: i16 = 27;
+    pub const OUT_OF_SPACE: i16 = 28;
+    pub const NOT_IMPLEMENTED: i16 = 38;
+}
+
+/// Request the path of a directory entry by its index, used to list remote directories
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, id = 46, response = "GetDirectoryEntryInfoResponse", signature = 0x8c46e8ab568bda79, dsdl_signature = 0xcfb1184f8c28c684)]
+pub struct GetDirectoryEntryInfoRequest {
+    pub entry_index: u32,
+    pub directory_path: Path,
+}
+
+/// Response to `GetDirectoryEntryInfoRequest`
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, request = "GetDirectoryEntryInfoRequest", signature = 0x8c46e8ab568bda79, dsdl_signature = 0xcfb1184f8c28c684)]
+pub struct GetDirectoryEntryInfoResponse {
+    pub error: Error,
+    pub entry_type: EntryType,
+    pub entry_full_path: Path,
+}
+
+/// Request info about a remote file system entry
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, id = 45, response = "GetInfoResponse", signature = 0x5004891ee8a27531, dsdl_signature = 0xcfbbc571f4fdb19e)]
+pub struct GetInfoRequest {
+    pub path: Path,
+}
+
+/// Response to `GetInfoRequest`
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, request = "GetInfoRequest", signature = 0x5004891ee8a27531, dsdl_signature = 0xcfbbc571f4fdb19e)]
+pub struct GetInfoResponse {
+    /// File size in bytes, should be zero for directories
+    pub size: u40,
+    pub error: Error,
+    pub entry_type: EntryType,
+}
+
+/// Nested type, a file system path in UTF-8 using `/` as separator
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, signature = 0x12aefc50878a43e2, dsdl_signature = 0x12aefc50878a43e2)]
+pub struct Path {
+    pub path: Dynamic<[u8; 200]>,
+}
+
+impl Path {
+    pub const SEPARATOR: u8 = b'/';
+}
+
+/// Read a chunk of a remote file, starting at `offset`
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, id = 48, response = "ReadResponse", signature = 0x8dcdca939f33f678, dsdl_signature = 0x182a9bbdfa6c8ca5)]
+pub struct ReadRequest {
+    pub offset: u40,
+    pub path: Path,
+}
+
+/// Response to `ReadRequest`, less data than the maximum means that the end of the file is reached
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, request = "ReadRequest", signature = 0x8dcdca939f33f678, dsdl_signature = 0x182a9bbdfa6c8ca5)]
+pub struct ReadResponse {
+    pub error: Error,
+    pub data: Dynamic<[u8; 256]>,
+}
+
+/// Write a chunk of a remote file, starting at `offset`
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, id = 49, response = "WriteResponse", signature = 0x515aa1dc77e58429, dsdl_signature = 0xdc262f0a3a3fbd75)]
+pub struct WriteRequest {
+    pub offset: u40,
+    pub path: Path,
+    pub data: Dynamic<[u8; 192]>,
+}
+
+/// Response to `WriteRequest`
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, request = "WriteRequest", signature = 0x515aa1dc77e58429, dsdl_signature = 0xdc262f0a3a3fbd75)]
+pub struct WriteResponse {
+    pub error: Error,
+}
@@ -0,0 +1,159 @@
+//! The `uavcan.protocol` namespace
+//!
+//! Generated from the DSDL definitions in `dsdl/v0` by `tests/regulated.rs`, regenerate it instead of editing it.
+
+pub mod debug;
+pub mod dynamic_node_id;
+pub mod file;
+pub mod param;
+
+use types::*;
+
+/// Nested type, single CAN interface statistics
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, signature = 0x13b106f0c44ca350, dsdl_signature = 0x13b106f0c44ca350)]
+pub struct CANIfaceStats {
+    pub frames_tx: u48,
+    pub frames_rx: u48,
+    pub errors: u48,
+}
+
+/// Full node info request
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, id = 1, response = "GetNodeInfoResponse", signature = 0xee468a8121c46a9e, dsdl_signature = 0xa80dc8995053e685)]
+pub struct GetNodeInfoRequest {}
+
+/// Full node info response
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, request = "GetNodeInfoRequest", signature = 0xee468a8121c46a9e, dsdl_signature = 0xa80dc8995053e685)]
+pub struct GetNodeInfoResponse {
+    /// Current node status
+    pub status: NodeStatus,
+    pub software_version: SoftwareVersion,
+    pub hardware_version: HardwareVersion,
+    /// Human readable non-empty ASCII node name, e.g. "com.example.product_name"
+    pub name: Utf8String<[u8; 80]>,
+}
+
+/// Get transport statistics
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, id = 4, response = "GetTransportStatsResponse", signature = 0xbe6f76a7ec312b04, dsdl_signature = 0x31c7f654531b61b3)]
+pub struct GetTransportStatsRequest {}
+
+/// Transport statistics of the node and its CAN interfaces
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, request = "GetTransportStatsRequest", signature = 0xbe6f76a7ec312b04, dsdl_signature = 0x31c7f654531b61b3)]
+pub struct GetTransportStatsResponse {
+    pub transfers_tx: u48,
+    pub transfers_rx: u48,
+    pub transfer_errors: u48,
+    pub can_iface_stats: Dynamic<[CANIfaceStats; 3]>,
+}
+
+/// Global time synchronization, broadcasted by the time synchronization master
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, id = 4, signature = 0x20271116a793c2db, dsdl_signature = 0x20271116a793c2db)]
+pub struct GlobalTimeSync {
+    /// The exact timestamp of the previous transmission of this message, zero if unknown
+    pub previous_transmission_timestamp_usec: Truncated<u56>,
+}
+
+impl GlobalTimeSync {
+    pub const MAX_BROADCASTING_PERIOD_MS: u16 = 1100;
+    pub const MIN_BROADCASTING_PERIOD_MS: u16 = 40;
+    /// Time synchronization slaves should switch to a new master if the current one is silent for this long
+    pub const RECOMMENDED_BROADCASTER_TIMEOUT_MS: u16 = 2200;
+}
+
+/// Nested type, generic hardware version information
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, signature = 0x0ad5c4c933f4a0c4, dsdl_signature = 0x0ad5c4c933f4a0c4)]
+pub struct HardwareVersion {
+    pub major: u8,
+    pub minor: u8,
+    /// Unique ID of the hardware, all zeros if not available
+    pub unique_id: [u8; 16],
+    /// Certificate of authenticity of the hardware, empty if not available
+    pub certificate_of_authenticity: Dynamic<[u8; 255]>,
+}
+
+/// Abstract node status information, broadcasted by every node at least once a second
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, id = 341, signature = 0x0f0868d0c1a7c6f1, dsdl_signature = 0x0f0868d0c1a7c6f1)]
+pub struct NodeStatus {
+    /// Uptime counter, it should never overflow
+    pub uptime_sec: u32,
+    /// Abstract node health
+    #[uavcan(names(HEALTH_OK, HEALTH_WARNING, HEALTH_ERROR, HEALTH_CRITICAL))]
+    pub health: u2,
+    /// Current mode of operation
+    #[uavcan(names(MODE_OPERATIONAL, MODE_INITIALIZATION, MODE_MAINTENANCE, MODE_SOFTWARE_UPDATE, MODE_OFFLINE))]
+    pub mode: u3,
+    /// Mode specific sub mode, zero if not used
+    pub sub_mode: u3,
+    /// Optional, vendor specific node status code
+    pub vendor_specific_status_code: u16,
+}
+
+impl NodeStatus {
+    pub const MAX_BROADCASTING_PERIOD_MS: u16 = 1000;
+    pub const MIN_BROADCASTING_PERIOD_MS: u16 = 2;
+    /// A node that hasn't published `NodeStatus` for this long should be considered offline
+    pub const OFFLINE_TIMEOUT_MS: u16 = 3000;
+    /// The node is functioning properly
+    pub const HEALTH_OK: u8 = 0;
+    /// A critical parameter went out of range or the node encountered a minor failure
+    pub const HEALTH_WARNING: u8 = 1;
+    /// The node encountered a major failure
+    pub const HEALTH_ERROR: u8 = 2;
+    /// The node suffered a fatal malfunction
+    pub const HEALTH_CRITICAL: u8 = 3;
+    /// Normal operating mode
+    pub const MODE_OPERATIONAL: u8 = 0;
+    /// Initialization is in progress, entered immediately after startup
+    pub const MODE_INITIALIZATION: u8 = 1;
+    /// E.g. calibration, the bootloader is running, etc
+    pub const MODE_MAINTENANCE: u8 = 2;
+    /// New software or firmware is being loaded
+    pub const MODE_SOFTWARE_UPDATE: u8 = 3;
+    /// The node is no longer available
+    pub const MODE_OFFLINE: u8 = 7;
+}
+
+/// Restart the node, the node may refuse to restart
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, id = 5, response = "RestartNodeResponse", signature = 0x569e05394a3017f0, dsdl_signature = 0x569e05394a3017f0)]
+pub struct RestartNodeRequest {
+    /// Must be `0xACCE551B1E` for the restart to be accepted
+    pub magic_number: u40,
+}
+
+impl RestartNodeRequest {
+    pub const MAGIC_NUMBER: u64 = 0xACCE551B1E;
+}
+
+/// Response to `RestartNodeRequest`, sent before the restart
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, request = "RestartNodeRequest", signature = 0x569e05394a3017f0, dsdl_signature = 0x569e05394a3017f0)]
+pub struct RestartNodeResponse {
+    pub ok: bool,
+}
+
+/// Nested type, generic software version information
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, signature = 0xdd46fd376527fea1, dsdl_signature = 0xdd46fd376527fea1)]
+pub struct SoftwareVersion {
+    pub major: u8,
+    pub minor: u8,
+    /// Tells which of the optional fields are set
+    pub optional_field_flags: u8,
+    /// Version control system commit identifier
+    pub vcs_commit: u32,
+    /// CRC-64-WE of the firmware image
+    pub image_crc: u64,
+}
+
+impl SoftwareVersion {
+    pub const OPTIONAL_FIELD_FLAG_VCS_COMMIT: u8 = 1;
+    pub const OPTIONAL_FIELD_FLAG_IMAGE_CRC: u8 = 2;
+}
@@ -0,0 +1,85 @@
+//! The `uavcan.protocol.param` namespace
+//!
+//! Generated from the DSDL definitions in `dsdl/v0` by `tests/regulated.rs`, regenerate it instead of editing it.
+
+use types::*;
+
+/// Nested type, a placeholder for an empty value
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, signature = 0x6c4d0e8ef37361df, dsdl_signature = 0x6c4d0e8ef37361df)]
+pub struct Empty {}
+
+/// Service to control the non-volatile parameter storage
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, id = 10, response = "ExecuteOpcodeResponse", signature = 0x3b131ac5eb69d2cd, dsdl_signature = 0x3b131ac5eb69d2cd)]
+pub struct ExecuteOpcodeRequest {
+    #[uavcan(names(OPCODE_SAVE, OPCODE_ERASE))]
+    pub opcode: u8,
+    /// Reserved, keep zero
+    pub argument: i48,
+}
+
+impl ExecuteOpcodeRequest {
+    /// Save all parameters to the non-volatile storage
+    pub const OPCODE_SAVE: u8 = 0;
+    /// Clear the non-volatile storage, the defaults are used after restart
+    pub const OPCODE_ERASE: u8 = 1;
+}
+
+/// Response to `ExecuteOpcodeRequest`
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, request = "ExecuteOpcodeRequest", signature = 0x3b131ac5eb69d2cd, dsdl_signature = 0x3b131ac5eb69d2cd)]
+pub struct ExecuteOpcodeResponse {
+    /// Reserved, keep zero
+    pub argument: i48,
+    /// If false, the operation failed
+    pub ok: bool,
+}
+
+/// Get or set a parameter by name or by index
+///
+/// The parameter is set if `value` is not empty.
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, id = 11, response = "GetSetResponse", signature = 0xa7b622f939d1a4d5, dsdl_signature = 0xb7d14152f13221ed)]
+pub struct GetSetRequest {
+    /// Index of the parameter, only used if `name` is empty
+    pub index: u13,
+    pub value: Value,
+    pub name: Utf8String<[u8; 92]>,
+}
+
+/// The actual parameter value after the request was processed, `name` is empty if the parameter doesn't exist
+#[derive(Clone, PartialEq, Default, UavcanStruct)]
+#[uavcan(debug, request = "GetSetRequest", signature = 0xa7b622f939d1a4d5, dsdl_signature = 0xb7d14152f13221ed)]
+pub struct GetSetResponse {
+    pub _v0: void5,
+    pub value: Value,
+    pub _v1: void5,
+    pub default_value: Value,
+    pub _v2: void6,
+    pub max_value: NumericValue,
+    pub _v3: void6,
+    pub min_value: NumericValue,
+    pub name: Utf8String<[u8; 92]>,
+}
+
+/// Nested type, a numerical parameter value used for limits
+#[derive(Clone, PartialEq, UavcanStruct)]
+#[uavcan(debug, signature = 0x0da6d6fea22e3587, dsdl_signature = 0x1222eea596ad701c)]
+pub enum NumericValue {
+    Empty(Empty),
+    IntegerValue(i64),
+    RealValue(f32),
+}
+
+/// Nested type, a single parameter value
+#[derive(Clone, PartialEq, UavcanStruct)]
+#[uavcan(debug, signature = 0x29f14bf484727267, dsdl_signature = 0xc3d96f448f2b00a1)]
+pub enum Value {
+    Empty(Empty),
+    IntegerValue(i64),
+    RealValue(f32),
+    /// 8-bit value is used for alignment reasons
+    BooleanValue(u8),
+    StringValue(Utf8String<[u8; 128]>),
+}
@@ -0,0 +1,279 @@
+//! The `uavcan.register` namespace
+//!
+//! Generated from the DSDL definitions in `dsdl/v1` by `tests/regulated.rs`, regenerate it instead of editing it.
+
+use v1::*;
+
+/// Read or write a register, the register is written if `value` isn't empty
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct AccessRequest {
+    pub name: Name,
+    /// The value to write, or empty to only read the register
+    pub value: Value,
+}
+
+impl FixedPort for AccessRequest {
+    const PORT_ID: u16 = 384;
+}
+
+impl Composite for AccessRequest {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 515;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_composite(&self.name);
+        writer.write_composite(&self.value);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let name = reader.read_composite()?;
+        let value = reader.read_composite()?;
+        Ok(AccessRequest {
+            name,
+            value,
+        })
+    }
+}
+
+/// The value of the register after the request was processed, empty if the register doesn't exist
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct AccessResponse {
+    /// The time when the value was sampled
+    pub timestamp: ::regulated::time::SynchronizedTimestamp,
+    /// Set if the register can be written
+    pub mutable: bool,
+    /// Set if the register keeps its value after a restart
+    pub persistent: bool,
+    pub value: Value,
+}
+
+impl FixedPort for AccessResponse {
+    const PORT_ID: u16 = 384;
+}
+
+impl Composite for AccessResponse {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 267;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_composite(&self.timestamp);
+        writer.write(self.mutable);
+        writer.write(self.persistent);
+        writer.write_bits(6, 0);
+        writer.write_composite(&self.value);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let timestamp = reader.read_composite()?;
+        let mutable = reader.read();
+        let persistent = reader.read();
+        reader.skip_bits(6);
+        let value = reader.read_composite()?;
+        Ok(AccessResponse {
+            timestamp,
+            mutable,
+            persistent,
+            value,
+        })
+    }
+}
+
+/// Get the name of a register by its index, used to list the registers of a node
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct ListRequest {
+    pub index: u16,
+}
+
+impl FixedPort for ListRequest {
+    const PORT_ID: u16 = 385;
+}
+
+impl Composite for ListRequest {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 2;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.index);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let index = reader.read();
+        Ok(ListRequest {
+            index,
+        })
+    }
+}
+
+/// The name of the register, empty if the index is out of range
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct ListResponse {
+    pub name: Name,
+}
+
+impl FixedPort for ListResponse {
+    const PORT_ID: u16 = 385;
+}
+
+impl Composite for ListResponse {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 256;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_composite(&self.name);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let name = reader.read_composite()?;
+        Ok(ListResponse {
+            name,
+        })
+    }
+}
+
+/// The name of a register, ASCII using `.` as the namespace separator
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Name {
+    pub name: ::heapless::Vec<u8, 255>,
+}
+
+impl Composite for Name {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 256;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write_dynamic_array(&self.name, 255);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let name = reader.read_dynamic_array()?;
+        Ok(Name {
+            name,
+        })
+    }
+}
+
+/// The value of a register, one of the standard primitive types
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// Empty, the register doesn't exist
+    Empty(::regulated::primitive::Empty),
+    /// UTF-8 encoded text
+    String(::regulated::primitive::String),
+    /// Raw unstructured binary data
+    Unstructured(::regulated::primitive::Unstructured),
+    Bit(::regulated::primitive::array::Bit),
+    Integer64(::regulated::primitive::array::Integer64),
+    Integer32(::regulated::primitive::array::Integer32),
+    Integer16(::regulated::primitive::array::Integer16),
+    Integer8(::regulated::primitive::array::Integer8),
+    Natural64(::regulated::primitive::array::Natural64),
+    Natural32(::regulated::primitive::array::Natural32),
+    Natural16(::regulated::primitive::array::Natural16),
+    Natural8(::regulated::primitive::array::Natural8),
+    Real64(::regulated::primitive::array::Real64),
+    Real32(::regulated::primitive::array::Real32),
+    Real16(::regulated::primitive::array::Real16),
+}
+
+impl Default for Value {
+    fn default() -> Self {
+        Value::Empty(Default::default())
+    }
+}
+
+impl Composite for Value {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 259;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        match *self {
+            Value::Empty(ref value) => {
+                writer.write_bits(8, 0);
+                writer.write_composite(value);
+            },
+            Value::String(ref value) => {
+                writer.write_bits(8, 1);
+                writer.write_composite(value);
+            },
+            Value::Unstructured(ref value) => {
+                writer.write_bits(8, 2);
+                writer.write_composite(value);
+            },
+            Value::Bit(ref value) => {
+                writer.write_bits(8, 3);
+                writer.write_composite(value);
+            },
+            Value::Integer64(ref value) => {
+                writer.write_bits(8, 4);
+                writer.write_composite(value);
+            },
+            Value::Integer32(ref value) => {
+                writer.write_bits(8, 5);
+                writer.write_composite(value);
+            },
+            Value::Integer16(ref value) => {
+                writer.write_bits(8, 6);
+                writer.write_composite(value);
+            },
+            Value::Integer8(ref value) => {
+                writer.write_bits(8, 7);
+                writer.write_composite(value);
+            },
+            Value::Natural64(ref value) => {
+                writer.write_bits(8, 8);
+                writer.write_composite(value);
+            },
+            Value::Natural32(ref value) => {
+                writer.write_bits(8, 9);
+                writer.write_composite(value);
+            },
+            Value::Natural16(ref value) => {
+                writer.write_bits(8, 10);
+                writer.write_composite(value);
+            },
+            Value::Natural8(ref value) => {
+                writer.write_bits(8, 11);
+                writer.write_composite(value);
+            },
+            Value::Real64(ref value) => {
+                writer.write_bits(8, 12);
+                writer.write_composite(value);
+            },
+            Value::Real32(ref value) => {
+                writer.write_bits(8, 13);
+                writer.write_composite(value);
+            },
+            Value::Real16(ref value) => {
+                writer.write_bits(8, 14);
+                writer.write_composite(value);
+            },
+        }
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        match reader.read_bits(8) {
+            0 => Ok(Value::Empty(reader.read_composite()?)),
+            1 => Ok(Value::String(reader.read_composite()?)),
+            2 => Ok(Value::Unstructured(reader.read_composite()?)),
+            3 => Ok(Value::Bit(reader.read_composite()?)),
+            4 => Ok(Value::Integer64(reader.read_composite()?)),
+            5 => Ok(Value::Integer32(reader.read_composite()?)),
+            6 => Ok(Value::Integer16(reader.read_composite()?)),
+            7 => Ok(Value::Integer8(reader.read_composite()?)),
+            8 => Ok(Value::Natural64(reader.read_composite()?)),
+            9 => Ok(Value::Natural32(reader.read_composite()?)),
+            10 => Ok(Value::Natural16(reader.read_composite()?)),
+            11 => Ok(Value::Natural8(reader.read_composite()?)),
+            12 => Ok(Value::Real64(reader.read_composite()?)),
+            13 => Ok(Value::Real32(reader.read_composite()?)),
+            14 => Ok(Value::Real16(reader.read_composite()?)),
+            _ => Err(DecodeError::UnionTag),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! The `uavcan.time` namespace
+//!
+//! Generated from the DSDL definitions in `dsdl/v1` by `tests/regulated.rs`, regenerate it instead of editing it.
+
+use types::*;
+use v1::*;
+
+/// Network time synchronization, published by the time synchronization master
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Synchronization {
+    /// The exact timestamp of the previous publication of this message in the local time of the master, zero if unknown
+    pub previous_transmission_timestamp_microsecond: u56,
+}
+
+impl Synchronization {
+    /// The longest period between two publications, in seconds
+    pub const MAX_PUBLICATION_PERIOD: u8 = 1;
+    /// Slaves should switch to a new master if the current one is silent for this many publication periods
+    pub const PUBLISHER_TIMEOUT_PERIOD_MULTIPLIER: u8 = 3;
+}
+
+impl FixedPort for Synchronization {
+    const PORT_ID: u16 = 7168;
+}
+
+impl Composite for Synchronization {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 7;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.previous_transmission_timestamp_microsecond);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let previous_transmission_timestamp_microsecond = reader.read();
+        Ok(Synchronization {
+            previous_transmission_timestamp_microsecond,
+        })
+    }
+}
+
+/// A timestamp in the synchronized network time, in microseconds
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct SynchronizedTimestamp {
+    pub microsecond: u56,
+}
+
+impl SynchronizedTimestamp {
+    /// Zero means that the time is unknown
+    pub const UNKNOWN: u64 = 0;
+}
+
+impl Composite for SynchronizedTimestamp {
+    const SEALED: bool = true;
+    const MAX_BYTES: usize = 7;
+    const EXTENT: usize = Self::MAX_BYTES;
+
+    fn serialize_fields(&self, writer: &mut Writer) {
+        writer.write(self.microsecond);
+    }
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let microsecond = reader.read();
+        Ok(SynchronizedTimestamp {
+            microsecond,
+        })
+    }
+}
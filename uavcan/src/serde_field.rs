@@ -0,0 +1,393 @@
+//! Conversion of the field types to and from the serde data model
+//!
+//! Unsigned and signed integers of arbitrary bit lengths are represented by the smallest native integer they fit,
+//! `void` fields as unit, `f16` as `f32`, and static and dynamic arrays as sequences.
+//! Values that don't fit the bit length of the field are rejected when deserializing.
+//!
+//! This module is only exposed so serde support can be derived.
+//! It is not intended for use outside the derive macro and
+//! must not be considered as a stable part of the API.
+
+use lib::core::fmt;
+use lib::core::marker::PhantomData;
+use lib::core::mem;
+use lib::core::str::FromStr;
+
+use serde::{
+    Serialize,
+    Serializer,
+    Deserialize,
+    Deserializer,
+};
+use serde::de::{
+    Error,
+    SeqAccess,
+    Visitor,
+};
+use serde::ser::SerializeTuple;
+
+use types::*;
+
+/// A field of a structure with serde support, used through `#[serde(with = "uavcan::serde_field")]`
+pub trait SerdeField: Sized {
+    fn serialize_field<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+    fn deserialize_field<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+}
+
+pub fn serialize<T: SerdeField, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    value.serialize_field(serializer)
+}
+
+pub fn deserialize<'de, T: SerdeField, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+    T::deserialize_field(deserializer)
+}
+
+/// Serializes a `SerdeField` that is an element of an array
+pub(crate) struct Element<'a, T: 'a>(pub(crate) &'a T);
+
+impl<'a, T: SerdeField> Serialize for Element<'a, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize_field(serializer)
+    }
+}
+
+/// Deserializes a `SerdeField` that is an element of an array
+pub(crate) struct OwnedElement<T>(pub(crate) T);
+
+impl<'de, T: SerdeField> Deserialize<'de> for OwnedElement<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize_field(deserializer).map(OwnedElement)
+    }
+}
+
+macro_rules! impl_native {
+    ($($type:ident),*) => {$(
+        impl SerdeField for $type {
+            fn serialize_field<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                Serialize::serialize(self, serializer)
+            }
+            fn deserialize_field<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                Deserialize::deserialize(deserializer)
+            }
+        }
+    )*};
+}
+
+impl_native!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64, bool);
+
+macro_rules! impl_ux {
+    {[$(($type:ident, $bits:expr)),*], $underlying_type:ident} => {$(
+        impl SerdeField for $type {
+            fn serialize_field<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                $underlying_type::from(*self).serialize(serializer)
+            }
+            fn deserialize_field<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = $underlying_type::deserialize(deserializer)?;
+                if u64::from(value) >> $bits != 0 {
+                    return Err(D::Error::custom(concat!("the value doesn't fit ", stringify!($type))));
+                }
+                Ok($type::new(value))
+            }
+        }
+    )*};
+}
+
+impl_ux!([(u2, 2), (u3, 3), (u4, 4), (u5, 5), (u6, 6), (u7, 7)], u8);
+
+impl_ux!([(u9, 9), (u10, 10), (u11, 11), (u12, 12), (u13, 13), (u14, 14), (u15, 15)], u16);
+
+impl_ux!([(u17, 17), (u18, 18), (u19, 19), (u20, 20), (u21, 21), (u22, 22), (u23, 23), (u24, 24),
+          (u25, 25), (u26, 26), (u27, 27), (u28, 28), (u29, 29), (u30, 30), (u31, 31)], u32);
+
+impl_ux!([(u33, 33), (u34, 34), (u35, 35), (u36, 36), (u37, 37), (u38, 38), (u39, 39), (u40, 40),
+          (u41, 41), (u42, 42), (u43, 43), (u44, 44), (u45, 45), (u46, 46), (u47, 47), (u48, 48),
+          (u49, 49), (u50, 50), (u51, 51), (u52, 52), (u53, 53), (u54, 54), (u55, 55), (u56, 56),
+          (u57, 57), (u58, 58), (u59, 59), (u60, 60), (u61, 61), (u62, 62), (u63, 63)], u64);
+
+macro_rules! impl_ix {
+    {[$(($type:ident, $bits:expr)),*], $underlying_type:ident} => {$(
+        impl SerdeField for $type {
+            fn serialize_field<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                $underlying_type::from(*self).serialize(serializer)
+            }
+            fn deserialize_field<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = $underlying_type::deserialize(deserializer)?;
+                if i64::from(value) < -(1i64 << ($bits - 1)) || i64::from(value) >= 1i64 << ($bits - 1) {
+                    return Err(D::Error::custom(concat!("the value doesn't fit ", stringify!($type))));
+                }
+                Ok($type::new(value))
+            }
+        }
+    )*};
+}
+
+impl_ix!([(i2, 2), (i3, 3), (i4, 4), (i5, 5), (i6, 6), (i7, 7)], i8);
+
+impl_ix!([(i9, 9), (i10, 10), (i11, 11), (i12, 12), (i13, 13), (i14, 14), (i15, 15)], i16);
+
+impl_ix!([(i17, 17), (i18, 18), (i19, 19), (i20, 20), (i21, 21), (i22, 22), (i23, 23), (i24, 24),
+          (i25, 25), (i26, 26), (i27, 27), (i28, 28), (i29, 29), (i30, 30), (i31, 31)], i32);
+
+impl_ix!([(i33, 33), (i34, 34), (i35, 35), (i36, 36), (i37, 37), (i38, 38), (i39, 39), (i40, 40),
+          (i41, 41), (i42, 42), (i43, 43), (i44, 44), (i45, 45), (i46, 46), (i47, 47), (i48, 48),
+          (i49, 49), (i50, 50), (i51, 51), (i52, 52), (i53, 53), (i54, 54), (i55, 55), (i56, 56),
+          (i57, 57), (i58, 58), (i59, 59), (i60, 60), (i61, 61), (i62, 62), (i63, 63)], i64);
+
+impl<const BITS: usize> SerdeField for Uint<BITS> {
+    fn serialize_field<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        u64::from(*self).serialize(serializer)
+    }
+    fn deserialize_field<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = u64::deserialize(deserializer)?;
+        if value > Self::MAX {
+            return Err(D::Error::custom("the value doesn't fit Uint"));
+        }
+        Ok(Uint::new(value))
+    }
+}
+
+impl<const BITS: usize> SerdeField for Int<BITS> {
+    fn serialize_field<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        i64::from(*self).serialize(serializer)
+    }
+    fn deserialize_field<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = i64::deserialize(deserializer)?;
+        if value < Self::MIN || value > Self::MAX {
+            return Err(D::Error::custom("the value doesn't fit Int"));
+        }
+        Ok(Int::new(value))
+    }
+}
+
+macro_rules! impl_vx {
+    ($($type:ident),*) => {$(
+        impl SerdeField for $type {
+            fn serialize_field<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_unit()
+            }
+            fn deserialize_field<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                <()>::deserialize(deserializer)?;
+                Ok($type{})
+            }
+        }
+    )*};
+}
+
+impl_vx!(void1, void2, void3, void4, void5, void6, void7, void8, void9, void10, void11, void12, void13, void14, void15, void16,
+         void17, void18, void19, void20, void21, void22, void23, void24, void25, void26, void27, void28, void29, void30, void31, void32,
+         void33, void34, void35, void36, void37, void38, void39, void40, void41, void42, void43, void44, void45, void46, void47, void48,
+         void49, void50, void51, void52, void53, void54, void55, void56, void57, void58, void59, void60, void61, void62, void63, void64);
+
+impl SerdeField for f16 {
+    fn serialize_field<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_f32().serialize(serializer)
+    }
+    fn deserialize_field<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        f32::deserialize(deserializer).map(f16::from_f32)
+    }
+}
+
+impl<T: SerdeField> SerdeField for Truncated<T> {
+    fn serialize_field<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize_field(serializer)
+    }
+    fn deserialize_field<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize_field(deserializer).map(Truncated)
+    }
+}
+
+impl<T: SerdeField> SerdeField for Delimited<T> {
+    fn serialize_field<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize_field(serializer)
+    }
+    fn deserialize_field<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize_field(deserializer).map(Delimited::new)
+    }
+}
+
+impl<T: SerdeField> SerdeField for Option<T> {
+    fn serialize_field<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Some(ref value) => serializer.serialize_some(&Element(value)),
+            None => serializer.serialize_none(),
+        }
+    }
+    fn deserialize_field<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Option::<OwnedElement<T>>::deserialize(deserializer).map(|value| value.map(|OwnedElement(value)| value))
+    }
+}
+
+/// Deserializes a sequence of at most `T::LENGTH` bools
+struct BitsVisitor<T>(PhantomData<T>);
+
+impl<'de, T: BoolArray> Visitor<'de> for BitsVisitor<T> {
+    type Value = DynamicBitArray<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a sequence of at most {} bools", T::LENGTH)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bits = DynamicBitArray::new();
+        while let Some(value) = seq.next_element()? {
+            if bits.length() == T::LENGTH {
+                return Err(A::Error::invalid_length(bits.length() + 1, &self));
+            }
+            bits.push(value);
+        }
+        Ok(bits)
+    }
+}
+
+impl<T: BoolArray> SerdeField for BitArray<T> {
+    fn serialize_field<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+    fn deserialize_field<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bits = deserializer.deserialize_seq(BitsVisitor::<T>(PhantomData))?;
+        if bits.length() != T::LENGTH {
+            return Err(D::Error::invalid_length(bits.length(), &"a sequence of exactly the array length"));
+        }
+        let mut array = BitArray::new();
+        for (index, value) in bits.iter().enumerate() {
+            array.set(index, value);
+        }
+        Ok(array)
+    }
+}
+
+impl<T: BoolArray> SerdeField for DynamicBitArray<T> {
+    fn serialize_field<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+    fn deserialize_field<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(BitsVisitor(PhantomData))
+    }
+}
+
+/// The raw value is represented, so the value is kept exactly
+impl<T: SerdeField, S> SerdeField for Scaled<T, S> {
+    fn serialize_field<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        self.raw.serialize_field(serializer)
+    }
+    fn deserialize_field<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize_field(deserializer).map(Scaled::from_raw)
+    }
+}
+
+/// Valid UTF-8 is represented as a string, and invalid UTF-8 as the sequence of bytes
+impl<T: DynamicArray> SerdeField for Utf8String<T> where Dynamic<T>: SerdeField + AsRef<[u8]>, Utf8String<T>: FromStr<Err = CapacityError> {
+    fn serialize_field<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.as_str() {
+            Ok(text) => serializer.serialize_str(text),
+            Err(_) => self.0.serialize_field(serializer),
+        }
+    }
+    fn deserialize_field<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Utf8StringVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: DynamicArray> Visitor<'de> for Utf8StringVisitor<T> where Utf8String<T>: FromStr<Err = CapacityError> {
+            type Value = Utf8String<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "a string")
+            }
+
+            fn visit_str<E: Error>(self, value: &str) -> Result<Self::Value, E> {
+                value.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(Utf8StringVisitor(PhantomData))
+    }
+}
+
+impl<T: SerdeField, const N: usize> SerdeField for [T; N] {
+    fn serialize_field<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(N)?;
+        for element in self.iter() {
+            tuple.serialize_element(&Element(element))?;
+        }
+        tuple.end()
+    }
+
+    fn deserialize_field<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ArrayVisitor<T, const N: usize>(PhantomData<T>);
+
+        impl<'de, T: SerdeField, const N: usize> Visitor<'de> for ArrayVisitor<T, N> {
+            type Value = [T; N];
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "an array of {} elements", N)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<[T; N], A::Error> {
+                // The field types are plain values, like in `Deserializer::new`
+                let mut array: [T; N] = unsafe { mem::zeroed() };
+                for (index, element) in array.iter_mut().enumerate() {
+                    match seq.next_element::<OwnedElement<T>>()? {
+                        Some(OwnedElement(value)) => *element = value,
+                        None => return Err(A::Error::invalid_length(index, &self)),
+                    }
+                }
+                Ok(array)
+            }
+        }
+
+        deserializer.deserialize_tuple(N, ArrayVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use serde_json;
+
+    use *;
+    use types::*;
+
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    enum Value {
+        Integer(i5),
+        Real(f16),
+    }
+
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    struct Message {
+        mode: u3,
+        reserved: void2,
+        values: [Value; 2],
+        text: Dynamic<[u8; 8]>,
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let message = Message{
+            mode: u3::new(5),
+            reserved: void2{},
+            values: [Value::Integer(i5::new(-3)), Value::Real(f16::from_f32(0.5))],
+            text: Dynamic::<[u8; 8]>::with_data("json".as_bytes()),
+        };
+
+        let json = serde_json::to_string(&message).unwrap();
+        assert_eq!(json, r#"{"mode":5,"reserved":null,"values":[{"Integer":-3},{"Real":0.5}],"text":[106,115,111,110]}"#);
+        assert_eq!(serde_json::from_str::<Message>(&json).unwrap(), message);
+    }
+
+    #[test]
+    fn utf8_strings() {
+        let name: Utf8String<[u8; 8]> = "node".parse().unwrap();
+        let json = serde_json::to_string(&::serde_field::Element(&name)).unwrap();
+        assert_eq!(json, r#""node""#);
+
+        let mut deserializer = serde_json::Deserializer::from_str(r#""too long!""#);
+        assert!(<Utf8String<[u8; 8]> as ::serde_field::SerdeField>::deserialize_field(&mut deserializer).is_err());
+    }
+
+    #[test]
+    fn values_must_fit_the_field() {
+        assert!(serde_json::from_str::<Message>(r#"{"mode":8,"reserved":null,"values":[{"Integer":0},{"Integer":0}],"text":[]}"#).is_err());
+        assert!(serde_json::from_str::<Message>(r#"{"mode":0,"reserved":null,"values":[{"Integer":-17},{"Integer":0}],"text":[]}"#).is_err());
+        assert!(serde_json::from_str::<Message>(r#"{"mode":0,"reserved":null,"values":[{"Integer":0},{"Integer":0}],"text":[1,2,3,4,5,6,7,8,9]}"#).is_err());
+    }
+}
@@ -0,0 +1,357 @@
+//! Support for the serial transport
+//!
+//! The serial transport carries transfers over any byte stream, e.g. a UART or a USB CDC-ACM port
+//! (such as the `SerialPort` from `usbd-serial`). Every frame consists of the same header as used by the UDP transport
+//! (`UdpFrameHeader`) followed by the payload, COBS encoded and surrounded by `FRAME_DELIMITER` bytes.
+//! As with UDP, the payload of the last frame of a transfer ends with the transfer CRC (`udp::transfer_crc`).
+//!
+//! The module only deals with framing, moving the bytes to and from the port is left to the application.
+//!
+//! # Examples
+//! ```no_run
+//! # use uavcan::udp::{DataSpecifier, UdpFrameHeader, UNSET_NODE_ID};
+//! use uavcan::serial;
+//!
+//! # struct UsbSerial;
+//! # impl UsbSerial {
+//! #     fn write(&mut self, _data: &[u8]) -> Result<usize, ()> { Ok(0) }
+//! # }
+//! # fn main() -> Result<(), ()> {
+//! # let mut usb_serial = UsbSerial;
+//! # let header = UdpFrameHeader {
+//! #     priority: 4,
+//! #     source_node_id: 42,
+//! #     destination_node_id: UNSET_NODE_ID,
+//! #     data_specifier: DataSpecifier::Message(7509),
+//! #     transfer_id: 0,
+//! #     frame_index: 0,
+//! #     end_of_transfer: true,
+//! #     user_data: 0,
+//! # };
+//! # let payload = [0u8; 16];
+//! # let (received_bytes, count) = ([0u8; 64], 0);
+//! let mut buffer = [0u8; serial::max_encoded_frame_len(64)];
+//! let len = serial::encode_frame(&header, &payload, &mut buffer).unwrap();
+//! usb_serial.write(&buffer[..len])?;
+//!
+//! let mut decoder = serial::FrameDecoder::<128>::new();
+//! for &byte in &received_bytes[..count] {
+//!     if let Some(Ok((header, payload))) = decoder.push(byte) {
+//!         // handle frame
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use heapless::Vec;
+
+use udp::UdpFrameHeader;
+use udp::HeaderError;
+use udp::HEADER_SIZE;
+
+/// The byte separating frames in the byte stream
+pub const FRAME_DELIMITER: u8 = 0x00;
+
+/// Returns the maximum length of `len` bytes after COBS encoding
+pub const fn cobs_max_encoded_len(len: usize) -> usize {
+    len + len / 254 + 1
+}
+
+/// Returns the maximum length of an encoded frame with `payload_len` bytes of payload, including delimiters
+pub const fn max_encoded_frame_len(payload_len: usize) -> usize {
+    cobs_max_encoded_len(HEADER_SIZE + payload_len) + 2
+}
+
+struct CobsEncoder<'a> {
+    output: &'a mut [u8],
+    code_index: usize,
+    write: usize,
+    code: u8,
+}
+
+impl<'a> CobsEncoder<'a> {
+    fn new(output: &'a mut [u8], start: usize) -> Option<Self> {
+        if output.len() <= start {
+            return None;
+        }
+
+        Some(CobsEncoder {
+            output,
+            code_index: start,
+            write: start + 1,
+            code: 1,
+        })
+    }
+
+    fn push(&mut self, byte: u8) -> Option<()> {
+        if byte == 0 {
+            self.finish_block()
+        } else {
+            *self.output.get_mut(self.write)? = byte;
+            self.write += 1;
+            self.code += 1;
+            if self.code == 0xff {
+                self.finish_block()
+            } else {
+                Some(())
+            }
+        }
+    }
+
+    fn finish_block(&mut self) -> Option<()> {
+        self.output[self.code_index] = self.code;
+        self.code_index = self.write;
+        if self.write >= self.output.len() {
+            return None;
+        }
+        self.write += 1;
+        self.code = 1;
+        Some(())
+    }
+
+    /// Returns the index after the last encoded byte
+    fn finish(self) -> usize {
+        self.output[self.code_index] = self.code;
+        self.write
+    }
+}
+
+/// COBS encodes `input` into `output` and returns the length of the encoded data
+///
+/// The encoded data contains no zero bytes. Returns `None` if `output` is too small,
+/// `cobs_max_encoded_len` bytes is always sufficient.
+pub fn cobs_encode(input: &[u8], output: &mut [u8]) -> Option<usize> {
+    let mut encoder = CobsEncoder::new(output, 0)?;
+    for &byte in input {
+        encoder.push(byte)?;
+    }
+    Some(encoder.finish())
+}
+
+/// Decodes COBS encoded data in place and returns the length of the decoded data
+///
+/// Returns `None` if `buffer` is not valid COBS encoded data.
+pub fn cobs_decode_in_place(buffer: &mut [u8]) -> Option<usize> {
+    let mut read = 0;
+    let mut write = 0;
+
+    while read < buffer.len() {
+        let code = buffer[read];
+        if code == 0 {
+            return None;
+        }
+        read += 1;
+
+        for _ in 1..code {
+            let byte = *buffer.get(read)?;
+            if byte == 0 {
+                return None;
+            }
+            buffer[write] = byte;
+            write += 1;
+            read += 1;
+        }
+
+        if code != 0xff && read < buffer.len() {
+            buffer[write] = 0;
+            write += 1;
+        }
+    }
+
+    Some(write)
+}
+
+/// Encodes a frame consisting of `header` and `payload` into `output`, including the delimiters
+///
+/// Returns the number of bytes written or `None` if `output` is too small,
+/// `max_encoded_frame_len` bytes is always sufficient.
+pub fn encode_frame(header: &UdpFrameHeader, payload: &[u8], output: &mut [u8]) -> Option<usize> {
+    *output.get_mut(0)? = FRAME_DELIMITER;
+
+    let mut encoder = CobsEncoder::new(output, 1)?;
+    for &byte in header.serialize().iter().chain(payload) {
+        encoder.push(byte)?;
+    }
+    let end = encoder.finish();
+
+    *output.get_mut(end)? = FRAME_DELIMITER;
+    Some(end + 1)
+}
+
+/// Errors from `FrameDecoder`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The frame didn't fit in the decoder buffer
+    Overflow,
+
+    /// The frame was not valid COBS encoded data
+    InvalidEncoding,
+
+    /// The frame header could not be parsed
+    Header(HeaderError),
+}
+
+/// A streaming decoder extracting frames from a byte stream
+///
+/// Bytes are pushed one at a time as they're received, a frame is returned when its terminating delimiter is pushed.
+/// Encoded frames longer than `N` bytes are discarded. Since frames are delimited on both sides,
+/// the decoder synchronizes to the stream at the first delimiter.
+#[derive(Debug)]
+pub struct FrameDecoder<const N: usize> {
+    buffer: Vec<u8, N>,
+    overflow: bool,
+    complete: bool,
+}
+
+impl<const N: usize> FrameDecoder<N> {
+    /// Constructs a new `FrameDecoder`
+    pub fn new() -> Self {
+        FrameDecoder {
+            buffer: Vec::new(),
+            overflow: false,
+            complete: false,
+        }
+    }
+
+    /// Pushes a received byte into the decoder
+    ///
+    /// Returns the header and payload of a frame when a complete frame has been received.
+    /// Empty frames (consecutive delimiters) are skipped.
+    pub fn push(&mut self, byte: u8) -> Option<Result<(UdpFrameHeader, &[u8]), DecodeError>> {
+        if self.complete {
+            self.buffer.clear();
+            self.complete = false;
+        }
+
+        if byte != FRAME_DELIMITER {
+            if self.buffer.push(byte).is_err() {
+                self.overflow = true;
+            }
+            return None;
+        }
+
+        if self.overflow {
+            self.overflow = false;
+            self.buffer.clear();
+            return Some(Err(DecodeError::Overflow));
+        }
+
+        if self.buffer.is_empty() {
+            return None;
+        }
+
+        self.complete = true;
+
+        let len = match cobs_decode_in_place(&mut self.buffer) {
+            Some(len) => len,
+            None => return Some(Err(DecodeError::InvalidEncoding)),
+        };
+
+        let frame = &self.buffer[..len];
+        match UdpFrameHeader::deserialize(frame) {
+            Ok(header) => Some(Ok((header, &frame[HEADER_SIZE..]))),
+            Err(e) => Some(Err(DecodeError::Header(e))),
+        }
+    }
+}
+
+impl<const N: usize> Default for FrameDecoder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use udp::UdpFrameHeader;
+    use udp::DataSpecifier;
+    use udp::UNSET_NODE_ID;
+
+    use serial::*;
+
+    fn round_trip(data: &[u8], encoded: &[u8]) {
+        let mut buffer = [0xaa; 300];
+        let len = cobs_encode(data, &mut buffer).unwrap();
+        assert_eq!(&buffer[..len], encoded);
+
+        let len = cobs_decode_in_place(&mut buffer[..len]).unwrap();
+        assert_eq!(&buffer[..len], data);
+    }
+
+    #[test]
+    fn cobs() {
+        round_trip(&[], &[0x01]);
+        round_trip(&[0x00], &[0x01, 0x01]);
+        round_trip(&[0x00, 0x00], &[0x01, 0x01, 0x01]);
+        round_trip(&[0x11, 0x22, 0x00, 0x33], &[0x03, 0x11, 0x22, 0x02, 0x33]);
+        round_trip(&[0x11, 0x22, 0x33, 0x44], &[0x05, 0x11, 0x22, 0x33, 0x44]);
+    }
+
+    #[test]
+    fn cobs_long_block() {
+        let data = [0x42; 300];
+        let mut encoded = [0; cobs_max_encoded_len(300)];
+        let len = cobs_encode(&data, &mut encoded).unwrap();
+        assert!(len <= cobs_max_encoded_len(300));
+        assert!(encoded[..len].iter().all(|&b| b != 0));
+
+        let len = cobs_decode_in_place(&mut encoded[..len]).unwrap();
+        assert_eq!(&encoded[..len], &data[..]);
+    }
+
+    #[test]
+    fn cobs_output_too_small() {
+        let mut buffer = [0; 3];
+        assert_eq!(cobs_encode(&[1, 2, 3], &mut buffer), None);
+    }
+
+    #[test]
+    fn frame_round_trip() {
+        let header = UdpFrameHeader {
+            priority: 4,
+            source_node_id: 42,
+            destination_node_id: UNSET_NODE_ID,
+            data_specifier: DataSpecifier::Message(7509),
+            transfer_id: 3,
+            frame_index: 0,
+            end_of_transfer: true,
+            user_data: 0,
+        };
+        let payload = [0x00, 0x01, 0x00, 0x02];
+
+        let mut buffer = [0; max_encoded_frame_len(4)];
+        let len = encode_frame(&header, &payload, &mut buffer).unwrap();
+        assert_eq!(buffer[0], FRAME_DELIMITER);
+        assert_eq!(buffer[len - 1], FRAME_DELIMITER);
+
+        let mut decoder = FrameDecoder::<64>::new();
+
+        // Garbage before the first delimiter is reported as an invalid frame
+        assert_eq!(decoder.push(0x55), None);
+        assert!(decoder.push(buffer[0]).unwrap().is_err());
+
+        let mut frames = 0;
+        for &byte in &buffer[1..len] {
+            if let Some(result) = decoder.push(byte) {
+                let (decoded_header, decoded_payload) = result.unwrap();
+                assert_eq!(decoded_header, header);
+                assert_eq!(decoded_payload, &payload[..]);
+                frames += 1;
+            }
+        }
+        assert_eq!(frames, 1);
+    }
+
+    #[test]
+    fn decoder_overflow() {
+        let mut decoder = FrameDecoder::<4>::new();
+        for _ in 0..10 {
+            assert_eq!(decoder.push(1), None);
+        }
+        assert_eq!(decoder.push(FRAME_DELIMITER), Some(Err(DecodeError::Overflow)));
+        assert_eq!(decoder.push(FRAME_DELIMITER), None);
+    }
+}
@@ -2,7 +2,10 @@ use {
     Struct,
 };
 
+#[cfg(test)]
 use crc::TransferCRC;
+#[cfg(test)]
+use crc::TransferChecksum;
 
 use bit_field::{
     BitField,
@@ -19,19 +22,43 @@ pub struct SerializationBuffer<'a> {
     pub data: &'a mut [u8],
     start_bit_index: usize,
     stop_bit_index: usize,
+    zero_extended: bool,
 }
 
 impl<'a> SerializationBuffer<'a> {
     pub fn with_empty_buffer(buffer: &'a mut [u8]) -> Self {
-        Self{data: buffer, start_bit_index: 0, stop_bit_index: 0}
+        Self{data: buffer, start_bit_index: 0, stop_bit_index: 0, zero_extended: false}
     }
         
     pub fn with_full_buffer(buffer: &'a mut [u8]) -> Self {
         let data_len = buffer.len()*8;
-        Self{data: buffer, start_bit_index: 0, stop_bit_index: data_len}
+        Self{data: buffer, start_bit_index: 0, stop_bit_index: data_len, zero_extended: false}
+    }
+
+    /// Constructs an empty buffer marking the end of the received data
+    ///
+    /// Deserializing from this buffer finishes the fields that are still missing bits with the missing bits read as zero,
+    /// and ends a tail array after its last complete element.
+    pub fn with_zero_extension() -> Self {
+        Self{data: &mut [], start_bit_index: 0, stop_bit_index: 0, zero_extended: true}
+    }
+        
+    /// A view of at most the next `bit_length` bits of the buffer
+    ///
+    /// Popping bits from the view doesn't consume them from this buffer, use `skip_bits` afterwards.
+    pub(crate) fn limited(&mut self, bit_length: usize) -> SerializationBuffer {
+        let stop_bit_index = ::lib::core::cmp::min(self.stop_bit_index, self.start_bit_index + bit_length);
+        SerializationBuffer{data: &mut self.data[..], start_bit_index: self.start_bit_index, stop_bit_index: stop_bit_index, zero_extended: false}
+    }
+
+    /// Consumes `bit_length` bits without reading them
+    pub(crate) fn skip_bits(&mut self, bit_length: usize) {
+        assert!(bit_length <= self.bit_length());
+        self.start_bit_index += bit_length;
     }
         
     pub fn bit_length(&self) -> usize { self.stop_bit_index - self.start_bit_index }
+    pub fn is_zero_extended(&self) -> bool { self.zero_extended }
     pub fn bits_remaining(&self) -> usize { self.data.len()*8 - self.bit_length() }
 
     pub fn pop_bits(&mut self, bit_length: usize) -> u64 {
@@ -150,15 +177,46 @@ impl<T: Struct> Serializer<T> {
         self.structure.serialize(&mut self.field_index, &mut self.bit_index, true, buffer)
     }
 
-    pub fn peek_serialize(&self, buffer: &mut SerializationBuffer) -> SerializationResult {
-        let mut field_index = self.field_index;
-        let mut bit_index = self.bit_index;
-        self.structure.serialize(&mut field_index, &mut bit_index, true, buffer)
+    /// Restarts the serialization from the beginning of the structure
+    pub(crate) fn rewind(&mut self) {
+        self.field_index = 0;
+        self.bit_index = 0;
     }
 
+    #[cfg(test)]
     pub fn crc(&mut self, data_type_signature: u64) -> u16 {
-        let mut crc = TransferCRC::from_signature(data_type_signature);
+        self.crc_with_padding(data_type_signature, 0, 0)
+    }
 
+    /// Calculates the transfer CRC of the structure followed by `padding_length` bytes of `pad_byte`
+    #[cfg(test)]
+    pub fn crc_with_padding(&mut self, data_type_signature: u64, padding_length: usize, pad_byte: u8) -> u16 {
+        self.transfer_crc::<TransferCRC>(data_type_signature, padding_length, pad_byte).into()
+    }
+
+    /// Calculates the transfer CRC with algorithm `C` of the structure followed by `padding_length` bytes of `pad_byte`
+    #[cfg(test)]
+    pub fn transfer_crc<C: TransferChecksum>(&mut self, data_type_signature: u64, padding_length: usize, pad_byte: u8) -> C {
+        let mut crc = C::from_signature(data_type_signature);
+        self.for_each_chunk(|chunk| crc.add(chunk));
+        for _ in 0..padding_length {
+            crc.add(&[pad_byte]);
+        }
+        crc
+    }
+
+    /// Returns the number of bytes the complete structure serializes into
+    #[cfg(test)]
+    pub fn byte_length(&mut self) -> usize {
+        let mut length = 0;
+        self.for_each_chunk(|chunk| length += chunk.len());
+        length
+    }
+
+    /// Serializes the complete structure from the beginning, passing the serialized bytes to `f` in chunks.
+    ///
+    /// The serialization progress is left unchanged.
+    pub(crate) fn for_each_chunk<F: FnMut(&[u8])>(&mut self, mut f: F) {
         let field_index = self.field_index;
         let bit_index = self.bit_index;
 
@@ -170,12 +228,12 @@ impl<T: Struct> Serializer<T> {
             
             let mut serialization_buffer = SerializationBuffer::with_empty_buffer(&mut buffer);
             if let SerializationResult::Finished = self.serialize(&mut serialization_buffer) {
-                crc.add(&serialization_buffer.data[0..(serialization_buffer.stop_bit_index+7)/8]);
+                f(&serialization_buffer.data[0..(serialization_buffer.stop_bit_index+7)/8]);
                 self.field_index = field_index;
                 self.bit_index = bit_index;
-                return crc.into();
+                return;
             } else {
-                crc.add(&serialization_buffer.data);
+                f(&serialization_buffer.data);
             }
             
         }
@@ -467,6 +525,76 @@ mod tests {
     }
 
 
+    #[test]
+    fn uavcan_serialize_union() {
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        enum Value {
+            Integer(u8),
+            Boolean(u6),
+            Text(Dynamic<[u8; 7]>),
+        }
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Message {
+            value: Value,
+            v: u8,
+        }
+
+        assert_eq!(Value::FLATTENED_FIELDS_NUMBER, 1 + 1 + 1 + 8);
+        assert_eq!(Value::BIT_LENGTH_MIN, 2 + 3);
+
+        let message = Message{
+            value: Value::Boolean(u6::new(0b101010)),
+            v: 17,
+        };
+
+        let mut serializer: Serializer<Message> = Serializer::from_structure(message);
+        let mut array: [u8; 2] = [0; 2];
+
+        let mut buffer = SerializationBuffer::with_empty_buffer(&mut array);
+        assert_eq!(serializer.serialize(&mut buffer), SerializationResult::Finished);
+
+        assert_eq!(buffer.data, [0b01101010, 17]);
+    }
+
+    #[test]
+    fn bit_lengths() {
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        enum Value {
+            Integer(u8),
+            Boolean(u6),
+            Text(Dynamic<[u8; 7]>),
+        }
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Head {
+            value: Value,
+            v: u8,
+        }
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Tail {
+            v: u8,
+            value: Value,
+        }
+
+        assert_eq!(Value::BIT_LENGTH_MAX, 2 + 3 + 8*7);
+        assert_eq!(Value::TAIL_BIT_LENGTH_MIN, 2);
+        assert_eq!(Value::TAIL_BIT_LENGTH_MAX, 2 + 8*7);
+
+        assert_eq!(Head::MIN_BITS, 2 + 3 + 8);
+        assert_eq!(Head::MAX_BITS, 2 + 3 + 8*7 + 8);
+        assert_eq!(Head::MAX_BYTES, 9);
+
+        // Tail array optimization leaves out the length of `Text`
+        assert_eq!(Tail::MIN_BITS, 8 + 2);
+        assert_eq!(Tail::MIN_BYTES, 2);
+        assert_eq!(Tail::MAX_BITS, 8 + 2 + 8*7);
+        assert_eq!(Tail::MAX_BYTES, 9);
+    }
+
     #[test]
     fn tail_array_optimization_struct() {
         #[derive(Debug, PartialEq, Clone, UavcanStruct)]
@@ -0,0 +1,89 @@
+//! Fixed-size block allocation for reassembly buffers
+//!
+//! A `BlockAllocator` hands out blocks of memory that are large enough for a whole payload.
+//! The blocks of a `StaticPool` live in storage provided by the application, which can be placed in any memory region
+//! (e.g. CCM RAM or a DMA capable region) with the usual linker section attributes.
+
+use lib::core::convert::TryInto;
+
+use heapless::Vec;
+
+/// A pool of fixed-size memory blocks
+pub trait BlockAllocator<'a> {
+    /// Takes a block from the pool, returns `None` when the pool is exhausted
+    fn allocate(&mut self) -> Option<&'a mut [u8]>;
+
+    /// Returns a block taken with `allocate` to the pool
+    fn free(&mut self, block: &'a mut [u8]);
+}
+
+/// A `BlockAllocator` handing out the `BLOCKS` blocks of `BLOCK_SIZE` bytes in the storage it's constructed with
+///
+/// ## Example
+/// ```
+/// use uavcan::session::StaticPool;
+///
+/// static mut STORAGE: [[u8; 256]; 4] = [[0; 256]; 4];
+///
+/// let pool = StaticPool::new(unsafe { &mut *::std::ptr::addr_of_mut!(STORAGE) });
+/// assert_eq!(pool.available(), 4);
+/// ```
+#[derive(Debug)]
+pub struct StaticPool<'a, const BLOCK_SIZE: usize, const BLOCKS: usize> {
+    free: Vec<&'a mut [u8; BLOCK_SIZE], BLOCKS>,
+}
+
+impl<'a, const BLOCK_SIZE: usize, const BLOCKS: usize> StaticPool<'a, BLOCK_SIZE, BLOCKS> {
+    /// Constructs a pool with all blocks in `storage` available
+    pub fn new(storage: &'a mut [[u8; BLOCK_SIZE]; BLOCKS]) -> Self {
+        let mut free = Vec::new();
+        for block in storage.iter_mut() {
+            if free.push(block).is_err() {
+                unreachable!("The pool has room for all blocks");
+            }
+        }
+        StaticPool { free }
+    }
+
+    /// Returns the number of blocks that can still be allocated
+    pub fn available(&self) -> usize {
+        self.free.len()
+    }
+}
+
+impl<'a, const BLOCK_SIZE: usize, const BLOCKS: usize> BlockAllocator<'a> for StaticPool<'a, BLOCK_SIZE, BLOCKS> {
+    fn allocate(&mut self) -> Option<&'a mut [u8]> {
+        self.free.pop().map(|block| &mut block[..])
+    }
+
+    /// ## Panics
+    /// Panics if `block` is not a block of this pool or more blocks are freed than allocated
+    fn free(&mut self, block: &'a mut [u8]) {
+        let block: &'a mut [u8; BLOCK_SIZE] = block.try_into().expect("The block has the block size of the pool");
+        if self.free.push(block).is_err() {
+            panic!("More blocks were freed than allocated");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use session::*;
+
+    #[test]
+    fn allocate_and_free() {
+        let mut storage = [[0u8; 16]; 2];
+        let mut pool = StaticPool::new(&mut storage);
+
+        let first = pool.allocate().unwrap();
+        let second = pool.allocate().unwrap();
+        assert_eq!(first.len(), 16);
+        assert_eq!(second.len(), 16);
+        assert!(pool.allocate().is_none());
+
+        pool.free(first);
+        assert_eq!(pool.available(), 1);
+        assert!(pool.allocate().is_some());
+    }
+}
@@ -0,0 +1,423 @@
+//! Reassembly of transfers received over datagram transports (UDP and serial)
+//!
+//! Unlike CAN, datagram transports may reorder and duplicate frames. Every frame carries its index within the transfer,
+//! so the frames are stored as they arrive and put back in order once all of them (up to the end of transfer frame) are received.
+
+use heapless::Vec;
+
+use udp::{
+    transfer_crc,
+    DataSpecifier,
+    UdpFrameHeader,
+    TRANSFER_CRC_SIZE,
+};
+
+use session::{
+    free_or_oldest_slot,
+    Activity,
+    DEFAULT_TRANSFER_ID_TIMEOUT,
+    SessionError,
+    SessionStats,
+};
+
+/// A transfer delivered by a `DatagramSessionManager`
+#[derive(Debug, PartialEq, Eq)]
+pub struct DatagramTransfer<'a> {
+    pub source_node_id: u16,
+
+    pub destination_node_id: u16,
+
+    pub data_specifier: DataSpecifier,
+
+    /// The priority of the transfer (0 is the highest priority)
+    pub priority: u8,
+
+    pub transfer_id: u64,
+
+    /// The timestamp of the first frame of the transfer that was received
+    pub timestamp: u64,
+
+    /// The payload, without the transfer CRC
+    pub payload: &'a [u8],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Fragment {
+    index: u32,
+    start: usize,
+    len: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct InProgress {
+    transfer_id: u64,
+    destination_node_id: u16,
+    priority: u8,
+    timestamp: u64,
+    last_index: Option<u32>,
+}
+
+#[derive(Debug)]
+struct DatagramSession<const MAX_PAYLOAD: usize, const MAX_FRAMES: usize> {
+    source_node_id: u16,
+    data_specifier: DataSpecifier,
+    buffer: Vec<u8, MAX_PAYLOAD>,
+    fragments: Vec<Fragment, MAX_FRAMES>,
+    in_progress: Option<InProgress>,
+    last_transfer_id: Option<u64>,
+    last_activity: u64,
+}
+
+impl<const MAX_PAYLOAD: usize, const MAX_FRAMES: usize> Activity for DatagramSession<MAX_PAYLOAD, MAX_FRAMES> {
+    fn last_activity(&self) -> u64 {
+        self.last_activity
+    }
+}
+
+impl<const MAX_PAYLOAD: usize, const MAX_FRAMES: usize> DatagramSession<MAX_PAYLOAD, MAX_FRAMES> {
+    fn new(header: &UdpFrameHeader, timestamp: u64) -> Self {
+        DatagramSession {
+            source_node_id: header.source_node_id,
+            data_specifier: header.data_specifier,
+            buffer: Vec::new(),
+            fragments: Vec::new(),
+            in_progress: None,
+            last_transfer_id: None,
+            last_activity: timestamp,
+        }
+    }
+
+    fn abort(&mut self) {
+        self.buffer.clear();
+        self.fragments.clear();
+        self.in_progress = None;
+    }
+
+    fn accept(&mut self, header: &UdpFrameHeader, payload: &[u8], timestamp: u64, transfer_id_timeout: u64) -> Result<Option<InProgress>, SessionError> {
+        if timestamp.saturating_sub(self.last_activity) > transfer_id_timeout {
+            self.last_transfer_id = None;
+            self.abort();
+        }
+
+        // Transfer IDs don't wrap around on datagram transports
+        if let Some(last) = self.last_transfer_id {
+            if header.transfer_id <= last {
+                return Err(SessionError::Duplicate);
+            }
+        }
+
+        match self.in_progress {
+            Some(ref in_progress) if header.transfer_id < in_progress.transfer_id => return Err(SessionError::UnexpectedFrame),
+            Some(ref in_progress) if header.transfer_id == in_progress.transfer_id => (),
+            _ => {
+                // A newer transfer aborts the one in progress, its remaining frames are lost
+                self.abort();
+                self.in_progress = Some(InProgress {
+                    transfer_id: header.transfer_id,
+                    destination_node_id: header.destination_node_id,
+                    priority: header.priority,
+                    timestamp,
+                    last_index: None,
+                });
+            },
+        }
+
+        if self.fragments.iter().any(|fragment| fragment.index == header.frame_index) {
+            return Err(SessionError::Duplicate);
+        }
+
+        let last_index = self.in_progress.as_ref().expect("A transfer is in progress").last_index;
+        let inconsistent = match last_index {
+            Some(last_index) => header.end_of_transfer || header.frame_index > last_index,
+            None => header.end_of_transfer && self.fragments.iter().any(|fragment| fragment.index > header.frame_index),
+        };
+        if inconsistent {
+            self.abort();
+            return Err(SessionError::MalformedFrame);
+        }
+
+        let fragment = Fragment {
+            index: header.frame_index,
+            start: self.buffer.len(),
+            len: payload.len(),
+        };
+        if self.buffer.extend_from_slice(payload).is_err() || self.fragments.push(fragment).is_err() {
+            self.abort();
+            return Err(SessionError::PayloadTooLarge);
+        }
+
+        self.last_activity = timestamp;
+        let in_progress = self.in_progress.as_mut().expect("A transfer is in progress");
+        if header.end_of_transfer {
+            in_progress.last_index = Some(header.frame_index);
+        }
+
+        match in_progress.last_index {
+            Some(last_index) if self.fragments.len() as u32 == last_index + 1 => {
+                let in_progress = *in_progress;
+                self.in_progress = None;
+                self.complete()?;
+                self.last_transfer_id = Some(in_progress.transfer_id);
+                Ok(Some(in_progress))
+            },
+            _ => Ok(None),
+        }
+    }
+
+    /// Puts the fragments in order and strips the transfer CRC after checking it
+    fn complete(&mut self) -> Result<(), SessionError> {
+        self.fragments.sort_unstable_by_key(|fragment| fragment.index);
+
+        let mut position = 0;
+        for i in 0..self.fragments.len() {
+            let Fragment { start, len, .. } = self.fragments[i];
+            if start != position {
+                // Move the fragment to `position`, the fragments stored in between are shifted behind it
+                self.buffer[position..start + len].rotate_right(len);
+                for fragment in self.fragments[i + 1..].iter_mut() {
+                    if fragment.start >= position && fragment.start < start {
+                        fragment.start += len;
+                    }
+                }
+            }
+            position += len;
+        }
+        self.fragments.clear();
+
+        if self.buffer.len() < TRANSFER_CRC_SIZE {
+            self.buffer.clear();
+            return Err(SessionError::MalformedFrame);
+        }
+        let payload_length = self.buffer.len() - TRANSFER_CRC_SIZE;
+        if transfer_crc(&self.buffer[..payload_length])[..] != self.buffer[payload_length..] {
+            self.buffer.clear();
+            return Err(SessionError::CRCError);
+        }
+        self.buffer.truncate(payload_length);
+        Ok(())
+    }
+}
+
+/// A session manager for datagram transports with room for `MAX_SESSIONS` sessions
+///
+/// Every session can buffer `MAX_PAYLOAD` bytes (including the transfer CRC) in up to `MAX_FRAMES` frames.
+/// Frames of a transfer may arrive in any order, duplicated frames are rejected without disturbing the reassembly.
+/// When all slots are busy, the session that was least recently active is evicted; a manager without slots drops every
+/// frame with `OutOfMemory`.
+///
+/// There is no subscription filtering or extent, see the module documentation of `session`.
+#[derive(Debug)]
+pub struct DatagramSessionManager<const MAX_SESSIONS: usize, const MAX_PAYLOAD: usize, const MAX_FRAMES: usize> {
+    sessions: [Option<DatagramSession<MAX_PAYLOAD, MAX_FRAMES>>; MAX_SESSIONS],
+    transfer_id_timeout: u64,
+    stats: SessionStats,
+}
+
+impl<const MAX_SESSIONS: usize, const MAX_PAYLOAD: usize, const MAX_FRAMES: usize> DatagramSessionManager<MAX_SESSIONS, MAX_PAYLOAD, MAX_FRAMES> {
+    /// Constructs a manager without any sessions, using `DEFAULT_TRANSFER_ID_TIMEOUT`
+    pub fn new() -> Self {
+        DatagramSessionManager {
+            sessions: ::lib::core::array::from_fn(|_| None),
+            transfer_id_timeout: DEFAULT_TRANSFER_ID_TIMEOUT,
+            stats: SessionStats::default(),
+        }
+    }
+
+    /// Sets the transfer-ID timeout in microseconds
+    pub fn with_transfer_id_timeout(mut self, transfer_id_timeout: u64) -> Self {
+        self.transfer_id_timeout = transfer_id_timeout;
+        self
+    }
+
+    /// Ingest a frame with the (already parsed) `header` and `payload` received at `timestamp`
+    ///
+    /// Returns the transfer when `frame` completes it, `Ok(None)` when more frames are needed.
+    pub fn ingest(&mut self, header: &UdpFrameHeader, payload: &[u8], timestamp: u64) -> Result<Option<DatagramTransfer<'_>>, SessionError> {
+        let index = match self.sessions.iter().position(|s| s.as_ref().map_or(false, |s| {
+            s.source_node_id == header.source_node_id && s.data_specifier == header.data_specifier
+        })) {
+            Some(index) => index,
+            None => {
+                let index = self.allocate()?;
+                self.sessions[index] = Some(DatagramSession::new(header, timestamp));
+                index
+            },
+        };
+
+        let transfer_id_timeout = self.transfer_id_timeout;
+        let session = self.sessions[index].as_mut().expect("The session was just found or allocated");
+        match session.accept(header, payload, timestamp, transfer_id_timeout) {
+            Ok(Some(completed)) => {
+                self.stats.transfers_completed += 1;
+                Ok(Some(DatagramTransfer {
+                    source_node_id: session.source_node_id,
+                    destination_node_id: completed.destination_node_id,
+                    data_specifier: session.data_specifier,
+                    priority: completed.priority,
+                    transfer_id: completed.transfer_id,
+                    timestamp: completed.timestamp,
+                    payload: &session.buffer,
+                }))
+            },
+            Ok(None) => Ok(None),
+            Err(error) => Err(self.stats.count_error(error)),
+        }
+    }
+
+    /// Returns the number of sessions currently allocated
+    pub fn active_sessions(&self) -> usize {
+        self.sessions.iter().filter(|s| s.is_some()).count()
+    }
+
+    /// Returns the statistics counters accumulated since the manager was constructed
+    pub fn stats(&self) -> SessionStats {
+        SessionStats {
+            active_sessions: self.active_sessions(),
+            ..self.stats
+        }
+    }
+
+    /// Returns the slot for a new session, evicting the least recently active session if all slots are busy
+    ///
+    /// Fails with `OutOfMemory` if the manager has no slots (`MAX_SESSIONS` is zero).
+    fn allocate(&mut self) -> Result<usize, SessionError> {
+        let index = match free_or_oldest_slot(&self.sessions) {
+            Some(index) => index,
+            None => return Err(self.stats.count_error(SessionError::OutOfMemory)),
+        };
+        if self.sessions[index].is_some() {
+            self.stats.evictions += 1;
+        }
+        Ok(index)
+    }
+}
+
+impl<const MAX_SESSIONS: usize, const MAX_PAYLOAD: usize, const MAX_FRAMES: usize> Default for DatagramSessionManager<MAX_SESSIONS, MAX_PAYLOAD, MAX_FRAMES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use session::*;
+
+    use udp::{
+        transfer_crc,
+        DataSpecifier,
+        UdpFrameHeader,
+        UNSET_NODE_ID,
+    };
+
+    fn header(transfer_id: u64, frame_index: u32, end_of_transfer: bool) -> UdpFrameHeader {
+        UdpFrameHeader {
+            priority: 4,
+            source_node_id: 42,
+            destination_node_id: UNSET_NODE_ID,
+            data_specifier: DataSpecifier::Message(7509),
+            transfer_id,
+            frame_index,
+            end_of_transfer,
+            user_data: 0,
+        }
+    }
+
+    /// Returns `payload` followed by its transfer CRC, and the total length
+    fn frames(payload: &[u8]) -> ([u8; 32], usize) {
+        let mut buffer = [0u8; 32];
+        buffer[..payload.len()].copy_from_slice(payload);
+        buffer[payload.len()..payload.len() + 4].copy_from_slice(&transfer_crc(payload));
+        (buffer, payload.len() + 4)
+    }
+
+    #[test]
+    fn single_frame_transfer() {
+        let mut manager: DatagramSessionManager<4, 64, 4> = DatagramSessionManager::new();
+        let (data, len) = frames(&[1, 2, 3]);
+
+        let transfer = manager.ingest(&header(7, 0, true), &data[..len], 100).unwrap().unwrap();
+        assert_eq!(transfer.payload, &[1, 2, 3]);
+        assert_eq!(transfer.transfer_id, 7);
+        assert_eq!(transfer.source_node_id, 42);
+        assert_eq!(transfer.data_specifier, DataSpecifier::Message(7509));
+    }
+
+    #[test]
+    fn reordered_and_duplicated_frames() {
+        let mut manager: DatagramSessionManager<4, 64, 4> = DatagramSessionManager::new();
+        let (data, _) = frames(&[1, 2, 3, 4, 5, 6, 7, 8]);
+
+        assert_eq!(manager.ingest(&header(7, 3, true), &data[9..12], 100), Ok(None));
+        assert_eq!(manager.ingest(&header(7, 1, false), &data[3..6], 110), Ok(None));
+        assert_eq!(manager.ingest(&header(7, 1, false), &data[3..6], 120), Err(SessionError::Duplicate));
+        assert_eq!(manager.ingest(&header(7, 0, false), &data[0..3], 130), Ok(None));
+        let transfer = manager.ingest(&header(7, 2, false), &data[6..9], 140).unwrap().unwrap();
+        assert_eq!(transfer.payload, &[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(transfer.timestamp, 100);
+
+        assert_eq!(manager.ingest(&header(7, 0, false), &data[0..3], 150), Err(SessionError::Duplicate));
+    }
+
+    #[test]
+    fn newer_transfer_aborts() {
+        let mut manager: DatagramSessionManager<4, 64, 4> = DatagramSessionManager::new();
+        let (data, len) = frames(&[1, 2, 3]);
+
+        assert_eq!(manager.ingest(&header(7, 0, false), &data[..2], 100), Ok(None));
+        assert!(manager.ingest(&header(8, 0, true), &data[..len], 110).unwrap().is_some());
+        assert_eq!(manager.ingest(&header(7, 1, true), &data[2..len], 120), Err(SessionError::Duplicate));
+    }
+
+    #[test]
+    fn crc_error() {
+        let mut manager: DatagramSessionManager<4, 64, 4> = DatagramSessionManager::new();
+        let (mut data, len) = frames(&[1, 2, 3]);
+        data[0] ^= 0x01;
+
+        assert_eq!(manager.ingest(&header(7, 0, true), &data[..len], 100), Err(SessionError::CRCError));
+        assert_eq!(manager.stats().crc_errors, 1);
+    }
+
+    #[test]
+    fn inconsistent_end_of_transfer() {
+        let mut manager: DatagramSessionManager<4, 64, 4> = DatagramSessionManager::new();
+        let (data, len) = frames(&[1, 2, 3]);
+
+        assert_eq!(manager.ingest(&header(7, 2, false), &data[..2], 100), Ok(None));
+        assert_eq!(manager.ingest(&header(7, 1, true), &data[2..len], 110), Err(SessionError::MalformedFrame));
+    }
+
+    #[test]
+    fn too_many_frames() {
+        let mut manager: DatagramSessionManager<4, 64, 2> = DatagramSessionManager::new();
+        let (data, _) = frames(&[1, 2, 3, 4, 5, 6]);
+
+        assert_eq!(manager.ingest(&header(7, 0, false), &data[0..3], 100), Ok(None));
+        assert_eq!(manager.ingest(&header(7, 1, false), &data[3..6], 110), Ok(None));
+        assert_eq!(manager.ingest(&header(7, 2, true), &data[6..10], 120), Err(SessionError::PayloadTooLarge));
+    }
+
+    #[test]
+    fn eviction() {
+        let mut manager: DatagramSessionManager<1, 64, 4> = DatagramSessionManager::new();
+        let (data, len) = frames(&[1, 2, 3]);
+
+        assert_eq!(manager.ingest(&header(7, 0, false), &data[..2], 100), Ok(None));
+        let mut other = header(7, 0, true);
+        other.source_node_id = 43;
+        assert!(manager.ingest(&other, &data[..len], 110).unwrap().is_some());
+        assert_eq!(manager.stats().evictions, 1);
+        assert_eq!(manager.active_sessions(), 1);
+    }
+
+    #[test]
+    fn without_sessions() {
+        let mut manager: DatagramSessionManager<0, 64, 4> = DatagramSessionManager::new();
+        let (data, len) = frames(&[1, 2, 3]);
+
+        assert_eq!(manager.ingest(&header(7, 0, true), &data[..len], 100), Err(SessionError::OutOfMemory));
+        assert_eq!(manager.stats().out_of_memory_drops, 1);
+        assert_eq!(manager.stats().evictions, 0);
+    }
+}
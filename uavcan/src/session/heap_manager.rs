@@ -0,0 +1,276 @@
+//! A `SessionManager` allocating sessions on the heap
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use heapless::Vec as HeaplessVec;
+
+use crc::{
+    TransferCRC,
+    TransferChecksum,
+};
+
+use transfer::TransferFrame;
+
+use session::{
+    SessionManager,
+    SessionKey,
+    Session,
+    Subscription,
+    Port,
+    SessionError,
+    SessionStats,
+    SessionInfo,
+    PayloadBuffer,
+    PayloadBufferFull,
+    ReceivedTransfer,
+    delivered_elsewhere,
+    accept_anonymous,
+    addressed_elsewhere,
+    MAX_ANONYMOUS_PAYLOAD,
+};
+
+/// A growable payload buffer refusing to grow past `limit`
+#[derive(Debug)]
+pub(crate) struct LimitedVec {
+    data: Vec<u8>,
+    limit: usize,
+}
+
+impl PayloadBuffer for LimitedVec {
+    fn clear(&mut self) {
+        self.data.clear()
+    }
+
+    fn extend(&mut self, data: &[u8]) -> Result<(), PayloadBufferFull> {
+        if self.data.len() + data.len() > self.limit {
+            return Err(PayloadBufferFull);
+        }
+        self.data.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// A `SessionManager` keeping its sessions in a `BTreeMap` keyed by port, source and interface
+///
+/// There is no limit on the number of sessions, this is meant for hosted targets (e.g. desktop tools)
+/// where tuning static capacities is more trouble than it's worth.
+/// Reassembly buffers grow on demand up to the extent of the subscription, and never past `max_payload`.
+/// A session quota (see `with_session_quota`) still bounds the memory a single remote node can make the manager allocate.
+#[derive(Debug)]
+pub struct HeapSessionManager<C = TransferCRC> {
+    sessions: BTreeMap<(SessionKey, u8), Session<LimitedVec, C>>,
+    subscriptions: Vec<Subscription>,
+    // The timestamp of the last transfer completed on every subscribed port, in the order of `subscriptions`
+    last_transfers: Vec<Option<u64>>,
+    max_payload: usize,
+    session_quota: usize,
+    local_node_id: Option<u8>,
+    stats: SessionStats,
+    anonymous: HeaplessVec<u8, MAX_ANONYMOUS_PAYLOAD>,
+}
+
+impl<C: TransferChecksum> HeapSessionManager<C> {
+    /// Constructs a manager without any limit on the payload size
+    pub fn new() -> Self {
+        Self::with_max_payload(usize::max_value())
+    }
+
+    /// Constructs a manager dropping transfers with payloads larger than `max_payload` bytes
+    pub fn with_max_payload(max_payload: usize) -> Self {
+        HeapSessionManager {
+            sessions: BTreeMap::new(),
+            subscriptions: Vec::new(),
+            last_transfers: Vec::new(),
+            max_payload,
+            session_quota: usize::max_value(),
+            local_node_id: None,
+            stats: SessionStats::default(),
+            anonymous: HeaplessVec::new(),
+        }
+    }
+
+    /// Limits the number of sessions a single remote node can occupy to `quota`, on all interfaces together
+    ///
+    /// A new session from a node that reached its quota replaces the least recently active session of that node.
+    ///
+    /// ## Panics
+    /// Panics if `quota` is zero.
+    pub fn with_session_quota(mut self, quota: usize) -> Self {
+        assert!(quota > 0, "The session quota must allow at least one session");
+        self.session_quota = quota;
+        self
+    }
+
+    /// Only receives the service transfers addressed to `node_id`, service frames to other nodes are rejected without allocating a session
+    ///
+    /// Without a local node ID the service transfers to every node are received, e.g. by a bus monitor.
+    pub fn with_local_node_id(mut self, node_id: u8) -> Self {
+        self.local_node_id = Some(node_id);
+        self
+    }
+}
+
+impl<C: TransferChecksum> Default for HeapSessionManager<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: TransferChecksum> SessionManager for HeapSessionManager<C> {
+    fn ingest_from<F: TransferFrame>(&mut self, interface: u8, frame: &F, timestamp: u64) -> Result<Option<ReceivedTransfer<'_>>, SessionError> {
+        let key = SessionKey::from_frame_id(frame.id());
+        let (subscription_index, subscription) = match self.subscriptions.iter().position(|subscription| subscription.port == key.port()) {
+            Some(index) => (index, self.subscriptions[index]),
+            None => return Err(SessionError::NotSubscribed),
+        };
+
+        if addressed_elsewhere(key, self.local_node_id) {
+            return Err(SessionError::NotAddressed);
+        }
+
+        if key.is_anonymous() {
+            return match accept_anonymous(&mut self.anonymous, key, frame, timestamp, &subscription) {
+                Ok(transfer) => {
+                    self.stats.transfers_completed += 1;
+                    self.last_transfers[subscription_index] = Some(transfer.timestamp);
+                    Ok(Some(transfer))
+                },
+                Err(error) => Err(self.stats.count_error(error)),
+            };
+        }
+
+        if !self.sessions.contains_key(&(key, interface)) {
+            if frame.data().is_empty() || !frame.is_start_frame() {
+                return Err(SessionError::UnexpectedFrame);
+            }
+            let from_source = self.sessions.values().filter(|session| session.key.source_node() == key.source_node());
+            if from_source.clone().count() >= self.session_quota {
+                if let Some(oldest) = from_source.min_by_key(|session| session.last_activity).map(|session| (session.key, session.interface)) {
+                    self.sessions.remove(&oldest);
+                    self.stats.evictions += 1;
+                }
+            }
+            let payload = LimitedVec {
+                data: Vec::new(),
+                limit: self.max_payload,
+            };
+            self.sessions.insert((key, interface), Session::new(key, interface, payload, timestamp));
+        }
+
+        let completed = match self.sessions.get_mut(&(key, interface)).expect("The session was just found or inserted").accept(frame, timestamp, &subscription) {
+            Ok(Some(completed)) => completed,
+            Ok(None) => return Ok(None),
+            Err(error) => return Err(self.stats.count_error(error)),
+        };
+
+        let redundant_sessions = self.sessions.range((key, 0)..=(key, u8::max_value())).map(|(_, session)| session);
+        if delivered_elsewhere(redundant_sessions, key, interface, completed.transfer_id, timestamp, subscription.transfer_id_timeout) {
+            return Err(SessionError::Duplicate);
+        }
+        self.stats.transfers_completed += 1;
+        self.last_transfers[subscription_index] = Some(completed.timestamp);
+
+        let session = &self.sessions[&(key, interface)];
+        Ok(Some(ReceivedTransfer {
+            key,
+            anonymous: false,
+            priority: completed.priority,
+            transfer_id: completed.transfer_id,
+            timestamp: completed.timestamp,
+            payload: session.payload.as_slice(),
+        }))
+    }
+
+    fn active_sessions(&self) -> usize {
+        self.sessions.len()
+    }
+
+    fn stats(&self) -> SessionStats {
+        SessionStats {
+            active_sessions: self.active_sessions(),
+            ..self.stats
+        }
+    }
+
+    fn subscribe(&mut self, subscription: Subscription) -> Result<(), SessionError> {
+        match self.subscriptions.iter_mut().find(|existing| existing.port == subscription.port) {
+            Some(existing) => *existing = subscription,
+            None => {
+                self.subscriptions.push(subscription);
+                self.last_transfers.push(None);
+            },
+        }
+        Ok(())
+    }
+
+    fn unsubscribe(&mut self, port: Port) -> Result<(), SessionError> {
+        let index = self.subscriptions.iter().position(|subscription| subscription.port == port).ok_or(SessionError::NotSubscribed)?;
+        self.subscriptions.swap_remove(index);
+        self.last_transfers.swap_remove(index);
+        self.sessions.retain(|&(key, _), _| key.port() != port);
+        Ok(())
+    }
+
+    fn subscriptions(&self) -> &[Subscription] {
+        &self.subscriptions
+    }
+
+    fn update(&mut self, now: u64) -> usize {
+        let before = self.sessions.len();
+        let subscriptions = &self.subscriptions;
+        self.sessions.retain(|_, session| !session.is_expired(now, subscriptions));
+        let expired = before - self.sessions.len();
+        self.stats.expirations += expired as u64;
+        expired
+    }
+
+    fn sessions(&self) -> impl Iterator<Item = SessionInfo> + '_ {
+        self.sessions.values().map(|session| session.info())
+    }
+
+    fn last_transfer_timestamp(&self, port: Port) -> Option<u64> {
+        let index = self.subscriptions.iter().position(|subscription| subscription.port == port)?;
+        self.last_transfers[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use tests::CanFrame;
+
+    use session::*;
+
+    #[test]
+    fn sessions_grow_on_demand() {
+        let mut manager: HeapSessionManager = HeapSessionManager::new();
+        manager.subscribe_message(0x0155, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+
+        for source in 1..100 {
+            assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5500 | source, &[0xaa, 0xbb, 1, 0x80]), 100), Ok(None));
+        }
+        assert_eq!(manager.active_sessions(), 99);
+
+        let transfer = manager.ingest(&CanFrame::from_parts(0x0001_5501, &[2, 0x60]), 110).unwrap().unwrap();
+        assert_eq!(transfer.payload, &[1, 2]);
+    }
+
+    #[test]
+    fn max_payload() {
+        let mut manager: HeapSessionManager = HeapSessionManager::with_max_payload(6);
+        manager.subscribe_message(0x0155, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[0xaa, 0xbb, 1, 2, 3, 4, 5, 0x80]), 100), Ok(None));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[6, 7, 0x60]), 110), Err(SessionError::PayloadTooLarge));
+
+        manager.subscribe_message(0x0155, 6, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[0xaa, 0xbb, 1, 2, 3, 4, 5, 0x81]), 120), Ok(None));
+        let transfer = manager.ingest(&CanFrame::from_parts(0x0001_5520, &[6, 7, 0x61]), 130).unwrap().unwrap();
+        assert_eq!(transfer.payload, &[1, 2, 3, 4, 5, 6]);
+    }
+}
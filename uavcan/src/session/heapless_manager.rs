@@ -0,0 +1,570 @@
+//! A `SessionManager` with purely static storage
+
+use heapless::Vec;
+
+use crc::{
+    TransferCRC,
+    TransferChecksum,
+};
+
+use transfer::TransferFrame;
+
+use session::{
+    SessionManager,
+    SessionKey,
+    Session,
+    Subscription,
+    Port,
+    SessionError,
+    SessionStats,
+    SessionInfo,
+    ReceivedTransfer,
+    delivered_elsewhere,
+    accept_anonymous,
+    addressed_elsewhere,
+    MAX_ANONYMOUS_PAYLOAD,
+    slot_for_session,
+    EvictionPolicy,
+};
+
+/// A `SessionManager` with room for `MAX_SESSIONS` sessions of at most `MAX_PAYLOAD` payload bytes each
+///
+/// Up to `MAX_SUBSCRIPTIONS` ports can be subscribed to.
+/// Sessions are only allocated by start frames. When all slots are busy, the session that was least recently active is evicted
+/// (the lowest slot wins ties) unless another `EvictionPolicy` is set,
+/// which makes the behaviour deterministic for a given sequence of frames and timestamps.
+#[derive(Debug)]
+pub struct HeaplessSessionManager<const MAX_SESSIONS: usize, const MAX_PAYLOAD: usize, const MAX_SUBSCRIPTIONS: usize = 16, C = TransferCRC> {
+    sessions: [Option<Session<Vec<u8, MAX_PAYLOAD>, C>>; MAX_SESSIONS],
+    subscriptions: Vec<Subscription, MAX_SUBSCRIPTIONS>,
+    // The timestamp of the last transfer completed on every subscribed port, in the order of `subscriptions`
+    last_transfers: Vec<Option<u64>, MAX_SUBSCRIPTIONS>,
+    session_quota: usize,
+    local_node_id: Option<u8>,
+    eviction_policy: EvictionPolicy,
+    stats: SessionStats,
+    anonymous: Vec<u8, MAX_ANONYMOUS_PAYLOAD>,
+}
+
+impl<const MAX_SESSIONS: usize, const MAX_PAYLOAD: usize, const MAX_SUBSCRIPTIONS: usize, C: TransferChecksum> HeaplessSessionManager<MAX_SESSIONS, MAX_PAYLOAD, MAX_SUBSCRIPTIONS, C> {
+    /// Constructs a manager without any sessions or subscriptions
+    pub fn new() -> Self {
+        HeaplessSessionManager {
+            sessions: ::lib::core::array::from_fn(|_| None),
+            subscriptions: Vec::new(),
+            last_transfers: Vec::new(),
+            session_quota: usize::max_value(),
+            local_node_id: None,
+            eviction_policy: EvictionPolicy::default(),
+            stats: SessionStats::default(),
+            anonymous: Vec::new(),
+        }
+    }
+
+    /// Limits the number of sessions a single remote node can occupy to `quota`, on all interfaces together
+    ///
+    /// A new session from a node that reached its quota replaces the least recently active session of that node.
+    ///
+    /// ## Panics
+    /// Panics if `quota` is zero.
+    pub fn with_session_quota(mut self, quota: usize) -> Self {
+        assert!(quota > 0, "The session quota must allow at least one session");
+        self.session_quota = quota;
+        self
+    }
+
+    /// Only receives the service transfers addressed to `node_id`, service frames to other nodes are rejected without allocating a session
+    ///
+    /// Without a local node ID the service transfers to every node are received, e.g. by a bus monitor.
+    pub fn with_local_node_id(mut self, node_id: u8) -> Self {
+        self.local_node_id = Some(node_id);
+        self
+    }
+
+    /// Sets the policy for making room for a new transfer when all session slots are busy
+    pub fn with_eviction_policy(mut self, eviction_policy: EvictionPolicy) -> Self {
+        self.eviction_policy = eviction_policy;
+        self
+    }
+
+    /// Returns the slot for a new session from `source` starting a transfer with `priority`,
+    /// evicting a session as decided by the session quota and the eviction policy
+    fn allocate(&mut self, source: u8, priority: u8) -> Result<usize, SessionError> {
+        let index = match slot_for_session(&self.sessions, source, self.session_quota, self.eviction_policy, priority) {
+            Some(index) => index,
+            None => return Err(self.stats.count_error(SessionError::OutOfMemory)),
+        };
+        if self.sessions[index].is_some() {
+            self.stats.evictions += 1;
+        }
+        Ok(index)
+    }
+}
+
+impl<const MAX_SESSIONS: usize, const MAX_PAYLOAD: usize, const MAX_SUBSCRIPTIONS: usize, C: TransferChecksum> Default for HeaplessSessionManager<MAX_SESSIONS, MAX_PAYLOAD, MAX_SUBSCRIPTIONS, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const MAX_SESSIONS: usize, const MAX_PAYLOAD: usize, const MAX_SUBSCRIPTIONS: usize, C: TransferChecksum> SessionManager for HeaplessSessionManager<MAX_SESSIONS, MAX_PAYLOAD, MAX_SUBSCRIPTIONS, C> {
+    fn ingest_from<F: TransferFrame>(&mut self, interface: u8, frame: &F, timestamp: u64) -> Result<Option<ReceivedTransfer<'_>>, SessionError> {
+        let key = SessionKey::from_frame_id(frame.id());
+        let (subscription_index, subscription) = match self.subscriptions.iter().position(|subscription| subscription.port == key.port()) {
+            Some(index) => (index, self.subscriptions[index]),
+            None => return Err(SessionError::NotSubscribed),
+        };
+
+        if addressed_elsewhere(key, self.local_node_id) {
+            return Err(SessionError::NotAddressed);
+        }
+
+        if key.is_anonymous() {
+            return match accept_anonymous(&mut self.anonymous, key, frame, timestamp, &subscription) {
+                Ok(transfer) => {
+                    self.stats.transfers_completed += 1;
+                    self.last_transfers[subscription_index] = Some(transfer.timestamp);
+                    Ok(Some(transfer))
+                },
+                Err(error) => Err(self.stats.count_error(error)),
+            };
+        }
+
+        let index = match self.sessions.iter().position(|s| s.as_ref().map_or(false, |s| s.key == key && s.interface == interface)) {
+            Some(index) => index,
+            None => {
+                if frame.data().is_empty() || !frame.is_start_frame() {
+                    return Err(SessionError::UnexpectedFrame);
+                }
+                let index = self.allocate(key.source_node(), ((u32::from(frame.id()) >> 24) & 0x1f) as u8)?;
+                self.sessions[index] = Some(Session::new(key, interface, Vec::new(), timestamp));
+                index
+            },
+        };
+
+        let completed = match self.sessions[index].as_mut().expect("The session was just found or allocated").accept(frame, timestamp, &subscription) {
+            Ok(Some(completed)) => completed,
+            Ok(None) => return Ok(None),
+            Err(error) => return Err(self.stats.count_error(error)),
+        };
+
+        if delivered_elsewhere(self.sessions.iter().flatten(), key, interface, completed.transfer_id, timestamp, subscription.transfer_id_timeout) {
+            return Err(SessionError::Duplicate);
+        }
+        self.stats.transfers_completed += 1;
+        self.last_transfers[subscription_index] = Some(completed.timestamp);
+
+        let session = self.sessions[index].as_ref().expect("The session was just found or allocated");
+        Ok(Some(ReceivedTransfer {
+            key,
+            anonymous: false,
+            priority: completed.priority,
+            transfer_id: completed.transfer_id,
+            timestamp: completed.timestamp,
+            payload: &session.payload,
+        }))
+    }
+
+    fn active_sessions(&self) -> usize {
+        self.sessions.iter().filter(|s| s.is_some()).count()
+    }
+
+    fn stats(&self) -> SessionStats {
+        SessionStats {
+            active_sessions: self.active_sessions(),
+            ..self.stats
+        }
+    }
+
+    fn subscribe(&mut self, subscription: Subscription) -> Result<(), SessionError> {
+        if let Some(existing) = self.subscriptions.iter_mut().find(|existing| existing.port == subscription.port) {
+            *existing = subscription;
+            return Ok(());
+        }
+        self.subscriptions.push(subscription).map_err(|_| SessionError::TooManySubscriptions)?;
+        self.last_transfers.push(None).expect("There is room for the timestamp of every subscription");
+        Ok(())
+    }
+
+    fn unsubscribe(&mut self, port: Port) -> Result<(), SessionError> {
+        let index = self.subscriptions.iter().position(|subscription| subscription.port == port).ok_or(SessionError::NotSubscribed)?;
+        self.subscriptions.swap_remove(index);
+        self.last_transfers.swap_remove(index);
+        for slot in self.sessions.iter_mut() {
+            if slot.as_ref().map_or(false, |session| session.key.port() == port) {
+                *slot = None;
+            }
+        }
+        Ok(())
+    }
+
+    fn subscriptions(&self) -> &[Subscription] {
+        &self.subscriptions
+    }
+
+    fn update(&mut self, now: u64) -> usize {
+        let mut expired = 0;
+        let subscriptions = &self.subscriptions;
+        for slot in self.sessions.iter_mut() {
+            if slot.as_ref().map_or(false, |session| session.is_expired(now, subscriptions)) {
+                *slot = None;
+                expired += 1;
+            }
+        }
+        self.stats.expirations += expired as u64;
+        expired
+    }
+
+    fn sessions(&self) -> impl Iterator<Item = SessionInfo> + '_ {
+        self.sessions.iter().flatten().map(|session| session.info())
+    }
+
+    fn last_transfer_timestamp(&self, port: Port) -> Option<u64> {
+        let index = self.subscriptions.iter().position(|subscription| subscription.port == port)?;
+        self.last_transfers[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use tests::CanFrame;
+
+    use session::*;
+
+    use transfer::{
+        TransferFrameID,
+        TransferID,
+    };
+
+    #[test]
+    fn single_frame_transfer() {
+        let mut manager: HeaplessSessionManager<4, 16> = HeaplessSessionManager::new();
+        manager.subscribe_message(0x0155, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+
+        let transfer = manager.ingest(&CanFrame::from_parts(0x1001_5520, &[1, 2, 3, 0xc5]), 100).unwrap().unwrap();
+        assert_eq!(transfer.payload, &[1, 2, 3]);
+        assert_eq!(transfer.priority, 0x10);
+        assert_eq!(transfer.transfer_id, TransferID::new(5));
+        assert_eq!(transfer.key, SessionKey::from_frame_id(TransferFrameID::new(0x0001_5520)));
+        assert_eq!(manager.active_sessions(), 1);
+    }
+
+    #[test]
+    fn multi_frame_transfer() {
+        let mut manager: HeaplessSessionManager<4, 16> = HeaplessSessionManager::new();
+        manager.subscribe_message(0x0155, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[0xaa, 0xbb, 1, 2, 3, 4, 5, 0x80]), 100), Ok(None));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[6, 7, 8, 9, 10, 11, 12, 0x20]), 110), Ok(None));
+        let transfer = manager.ingest(&CanFrame::from_parts(0x0001_5520, &[13, 0x40]), 120).unwrap().unwrap();
+        assert_eq!(transfer.payload, &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13]);
+        assert_eq!(transfer.timestamp, 100);
+    }
+
+    #[test]
+    fn subscribe_message_validates_crc() {
+        let payload = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let mut manager: HeaplessSessionManager<4, 16> = HeaplessSessionManager::new();
+        manager.subscribe_message(0x0155, 16, DEFAULT_TRANSFER_ID_TIMEOUT, Some(0x1234_5678)).unwrap();
+
+        let mut frames: PayloadFrames<CanFrame> = PayloadFrames::new(TransferFrameID::new(0x0001_5520), TransferID::new(3), 0x1234_5678, &payload);
+        assert_eq!(manager.ingest(&frames.next().unwrap(), 100), Ok(None));
+        assert_eq!(manager.ingest(&frames.next().unwrap(), 110).unwrap().unwrap().payload, &payload);
+
+        let mut frames: PayloadFrames<CanFrame> = PayloadFrames::new(TransferFrameID::new(0x0001_5520), TransferID::new(4), 0x1234_5678, &payload);
+        let mut corrupted = frames.next().unwrap();
+        corrupted.data[0] ^= 0x01;
+        assert_eq!(manager.ingest(&corrupted, 120), Ok(None));
+        assert_eq!(manager.ingest(&frames.next().unwrap(), 130), Err(SessionError::CRCError));
+        assert_eq!(manager.stats().crc_errors, 1);
+    }
+
+    #[test]
+    fn rejects_duplicates_and_bad_toggles() {
+        let mut manager: HeaplessSessionManager<4, 16> = HeaplessSessionManager::new();
+        manager.subscribe_message(0x0155, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+
+        assert!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[1, 0xc3]), 100).unwrap().is_some());
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[1, 0xc3]), 110), Err(SessionError::Duplicate));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5521, &[1, 0x43]), 110), Err(SessionError::UnexpectedFrame));
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[0xaa, 0xbb, 1, 0x84]), 120), Ok(None));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[2, 0x04]), 130), Err(SessionError::ToggleError));
+        assert!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[2, 0x64]), 140).unwrap().is_some());
+    }
+
+    #[test]
+    fn truncates_beyond_extent() {
+        let mut manager: HeaplessSessionManager<4, 16> = HeaplessSessionManager::new();
+        manager.subscribe_message(0x0155, 3, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+        manager.subscribe_message(0x0255, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[0xaa, 0xbb, 1, 2, 3, 4, 5, 0x80]), 100), Ok(None));
+        let transfer = manager.ingest(&CanFrame::from_parts(0x0001_5520, &[6, 0x60]), 110).unwrap().unwrap();
+        assert_eq!(transfer.payload, &[1, 2, 3]);
+
+        let transfer = manager.ingest(&CanFrame::from_parts(0x0002_5520, &[1, 2, 3, 4, 5, 0xc0]), 120).unwrap().unwrap();
+        assert_eq!(transfer.payload, &[1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn transfer_id_timeout() {
+        let mut manager: HeaplessSessionManager<4, 16> = HeaplessSessionManager::new();
+        manager.subscribe_message(0x0155, 16, 1000, None).unwrap();
+
+        assert!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[1, 0xc5]), 100).unwrap().is_some());
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[1, 0xc0]), 1100), Err(SessionError::Duplicate));
+        assert!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[1, 0xc0]), 2200).unwrap().is_some());
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[0xaa, 0xbb, 1, 0x81]), 2300), Ok(None));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[2, 0x61]), 3400), Err(SessionError::UnexpectedFrame));
+    }
+
+    #[test]
+    fn requires_subscription() {
+        let mut manager: HeaplessSessionManager<4, 16, 1> = HeaplessSessionManager::new();
+        manager.subscribe_request(0x37, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+        assert_eq!(manager.subscribe_response(0x37, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None), Err(SessionError::TooManySubscriptions));
+        assert_eq!(manager.subscriptions(), &[Subscription {
+            port: Port::Request(0x37),
+            extent: 16,
+            transfer_id_timeout: DEFAULT_TRANSFER_ID_TIMEOUT,
+            data_type_signature: None,
+        }]);
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[1, 0xc0]), 100), Err(SessionError::NotSubscribed));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0037_25a0, &[1, 0xc0]), 110), Err(SessionError::NotSubscribed));
+        assert!(manager.ingest(&CanFrame::from_parts(0x0037_a5a0, &[1, 0xc0]), 120).unwrap().is_some());
+        assert_eq!(manager.active_sessions(), 1);
+    }
+
+    #[test]
+    fn unsubscribe_frees_sessions() {
+        let mut manager: HeaplessSessionManager<4, 16> = HeaplessSessionManager::new();
+        manager.subscribe_message(0x0155, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+        manager.subscribe_message(0x0255, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[0xaa, 0xbb, 1, 0x80]), 100), Ok(None));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5521, &[0xaa, 0xbb, 1, 0x80]), 100), Ok(None));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0002_5520, &[0xaa, 0xbb, 1, 0x80]), 100), Ok(None));
+        assert_eq!(manager.active_sessions(), 3);
+
+        manager.unsubscribe(Port::Message(0x0155)).unwrap();
+        assert_eq!(manager.active_sessions(), 1);
+        assert_eq!(manager.unsubscribe(Port::Message(0x0155)), Err(SessionError::NotSubscribed));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[2, 0x60]), 110), Err(SessionError::NotSubscribed));
+        assert!(manager.ingest(&CanFrame::from_parts(0x0002_5520, &[2, 0x60]), 110).unwrap().is_some());
+
+        manager.subscribe_message(0x0155, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[2, 0x60]), 120), Err(SessionError::UnexpectedFrame));
+        assert!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[1, 0xc0]), 130).unwrap().is_some());
+    }
+
+    #[test]
+    fn last_transfer_timestamps() {
+        let mut manager: HeaplessSessionManager<4, 16> = HeaplessSessionManager::new();
+        manager.subscribe_message(0x0155, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+        manager.subscribe_message(0x0255, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+        assert_eq!(manager.last_transfer_timestamp(Port::Message(0x0155)), None);
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[0xaa, 0xbb, 1, 0x80]), 100), Ok(None));
+        assert_eq!(manager.last_transfer_timestamp(Port::Message(0x0155)), None);
+        assert!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[2, 0x60]), 110).unwrap().is_some());
+        assert!(manager.ingest(&CanFrame::from_parts(0x0002_5520, &[1, 0xc0]), 120).unwrap().is_some());
+        assert_eq!(manager.last_transfer_timestamp(Port::Message(0x0155)), Some(100));
+        assert_eq!(manager.last_transfer_timestamp(Port::Message(0x0255)), Some(120));
+
+        manager.unsubscribe(Port::Message(0x0155)).unwrap();
+        assert_eq!(manager.last_transfer_timestamp(Port::Message(0x0155)), None);
+        assert_eq!(manager.last_transfer_timestamp(Port::Message(0x0255)), Some(120));
+    }
+
+    #[test]
+    fn anonymous_transfers() {
+        let mut manager: HeaplessSessionManager<4, 16> = HeaplessSessionManager::new();
+        manager.subscribe_message(1, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+
+        let transfer = manager.ingest(&CanFrame::from_parts(0x1e48_d100, &[1, 2, 3, 0xc7]), 100).unwrap().unwrap();
+        assert!(transfer.anonymous);
+        assert_eq!(transfer.key.port(), Port::Message(1));
+        assert_eq!(transfer.priority, 0x1e);
+        assert_eq!(transfer.payload, &[1, 2, 3]);
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x1e48_d100, &[0xaa, 0xbb, 1, 0x88]), 110), Err(SessionError::AnonymousMultiFrame));
+        assert_eq!(manager.active_sessions(), 0);
+    }
+
+    #[test]
+    fn drops_services_to_other_nodes() {
+        let mut manager: HeaplessSessionManager<4, 16> = HeaplessSessionManager::new().with_local_node_id(0x25);
+        manager.subscribe_request(0x37, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+        manager.subscribe_message(0x0155, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+
+        // A request from node 0x20 to node 0x26, the start of a multi-frame transfer
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0037_a6a0, &[0xaa, 0xbb, 1, 0x80]), 100), Err(SessionError::NotAddressed));
+        assert_eq!(manager.active_sessions(), 0);
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0037_a5a0, &[0xaa, 0xbb, 1, 0x80]), 110), Ok(None));
+        assert!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[1, 0xc0]), 120).unwrap().is_some());
+        assert_eq!(manager.active_sessions(), 2);
+    }
+
+    #[test]
+    fn concurrent_service_transfers() {
+        let mut manager: HeaplessSessionManager<4, 16> = HeaplessSessionManager::new();
+        manager.subscribe_request(0x37, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+
+        // Two clients (0x20 and 0x21) send requests with the same transfer ID to server 0x25, and a third request goes to server 0x26
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0037_a5a0, &[0xaa, 0xbb, 1, 0x83]), 100), Ok(None));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0037_a5a1, &[0xaa, 0xbb, 4, 0x83]), 105), Ok(None));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0037_a6a0, &[0xaa, 0xbb, 7, 0x83]), 108), Ok(None));
+        assert_eq!(manager.active_sessions(), 3);
+
+        let transfer = manager.ingest(&CanFrame::from_parts(0x0037_a5a1, &[5, 0x63]), 110).unwrap().unwrap();
+        assert_eq!(transfer.payload, &[4, 5]);
+        assert_eq!(transfer.key.source_node(), 0x21);
+        assert_eq!(transfer.key.destination_node(), Some(0x25));
+        assert_eq!(transfer.key.port(), Port::Request(0x37));
+
+        let transfer = manager.ingest(&CanFrame::from_parts(0x0037_a5a0, &[2, 0x63]), 115).unwrap().unwrap();
+        assert_eq!(transfer.payload, &[1, 2]);
+        assert_eq!(transfer.key.source_node(), 0x20);
+
+        let transfer = manager.ingest(&CanFrame::from_parts(0x0037_a6a0, &[8, 0x63]), 120).unwrap().unwrap();
+        assert_eq!(transfer.payload, &[7, 8]);
+        assert_eq!(transfer.key.destination_node(), Some(0x26));
+    }
+
+    #[test]
+    fn redundant_interfaces() {
+        let mut manager: HeaplessSessionManager<4, 16> = HeaplessSessionManager::new();
+        manager.subscribe_message(0x0155, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+
+        assert_eq!(manager.ingest_from(0, &CanFrame::from_parts(0x0001_5520, &[0xaa, 0xbb, 1, 0x80]), 100), Ok(None));
+        assert_eq!(manager.ingest_from(1, &CanFrame::from_parts(0x0001_5520, &[0xaa, 0xbb, 1, 0x80]), 105), Ok(None));
+        assert!(manager.ingest_from(1, &CanFrame::from_parts(0x0001_5520, &[2, 0x60]), 110).unwrap().is_some());
+        assert_eq!(manager.ingest_from(0, &CanFrame::from_parts(0x0001_5520, &[2, 0x60]), 115), Err(SessionError::Duplicate));
+        assert_eq!(manager.active_sessions(), 2);
+
+        assert!(manager.ingest_from(0, &CanFrame::from_parts(0x0001_5520, &[3, 0xc1]), 120).unwrap().is_some());
+        assert!(manager.ingest_from(0, &CanFrame::from_parts(0x0001_5520, &[3, 0xc2]), 125).unwrap().is_some());
+        assert_eq!(manager.ingest_from(1, &CanFrame::from_parts(0x0001_5520, &[3, 0xc1]), 130), Err(SessionError::Duplicate));
+        assert_eq!(manager.ingest_from(1, &CanFrame::from_parts(0x0001_5520, &[3, 0xc2]), 135), Err(SessionError::Duplicate));
+    }
+
+    #[test]
+    fn evicts_least_recently_active() {
+        let mut manager: HeaplessSessionManager<2, 16> = HeaplessSessionManager::new();
+        manager.subscribe_message(0x0155, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5501, &[0xaa, 0xbb, 1, 0x80]), 100), Ok(None));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5502, &[0xaa, 0xbb, 1, 0x80]), 110), Ok(None));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5501, &[2, 0x20]), 120), Ok(None));
+        assert!(manager.ingest(&CanFrame::from_parts(0x0001_5503, &[1, 0xc0]), 130).unwrap().is_some());
+        assert_eq!(manager.active_sessions(), 2);
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5502, &[2, 0x60]), 140), Err(SessionError::UnexpectedFrame));
+        assert!(manager.ingest(&CanFrame::from_parts(0x0001_5501, &[3, 0x40]), 150).unwrap().is_some());
+
+        let stats = manager.stats();
+        assert_eq!(stats.active_sessions, 2);
+        assert_eq!(stats.evictions, 1);
+        assert_eq!(stats.transfers_completed, 2);
+    }
+
+    #[test]
+    fn session_quota() {
+        let mut manager: HeaplessSessionManager<4, 16> = HeaplessSessionManager::new().with_session_quota(2);
+        manager.subscribe_message(0x0155, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+        manager.subscribe_message(0x0255, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+        manager.subscribe_message(0x0355, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5521, &[0xaa, 0xbb, 1, 0x80]), 90), Ok(None));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[0xaa, 0xbb, 1, 0x80]), 100), Ok(None));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0002_5520, &[0xaa, 0xbb, 1, 0x80]), 110), Ok(None));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0003_5520, &[0xaa, 0xbb, 1, 0x80]), 120), Ok(None));
+        assert_eq!(manager.active_sessions(), 3);
+        assert_eq!(manager.stats().evictions, 1);
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[2, 0x60]), 130), Err(SessionError::UnexpectedFrame));
+        assert!(manager.ingest(&CanFrame::from_parts(0x0001_5521, &[2, 0x60]), 140).unwrap().is_some());
+    }
+
+    #[test]
+    fn eviction_policies() {
+        let mut manager: HeaplessSessionManager<2, 16> = HeaplessSessionManager::new().with_eviction_policy(EvictionPolicy::LowestPriority);
+        manager.subscribe_message(0x0155, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x1001_5501, &[0xaa, 0xbb, 1, 0x80]), 100), Ok(None));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0801_5502, &[0xaa, 0xbb, 1, 0x80]), 110), Ok(None));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x1801_5503, &[0xaa, 0xbb, 1, 0x80]), 120), Err(SessionError::OutOfMemory));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5504, &[0xaa, 0xbb, 1, 0x80]), 130), Ok(None));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x1001_5501, &[2, 0x60]), 140), Err(SessionError::UnexpectedFrame));
+        assert!(manager.ingest(&CanFrame::from_parts(0x0801_5502, &[2, 0x60]), 150).unwrap().is_some());
+
+        let stats = manager.stats();
+        assert_eq!(stats.evictions, 1);
+        assert_eq!(stats.out_of_memory_drops, 1);
+
+        let mut manager: HeaplessSessionManager<1, 16> = HeaplessSessionManager::new().with_eviction_policy(EvictionPolicy::RejectNew);
+        manager.subscribe_message(0x0155, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5501, &[0xaa, 0xbb, 1, 0x80]), 100), Ok(None));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5502, &[0xaa, 0xbb, 1, 0x80]), 110), Err(SessionError::OutOfMemory));
+        assert!(manager.ingest(&CanFrame::from_parts(0x0001_5501, &[2, 0x60]), 120).unwrap().is_some());
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5502, &[0xaa, 0xbb, 1, 0x80]), 130), Ok(None));
+    }
+
+    #[test]
+    fn session_snapshots() {
+        let mut manager: HeaplessSessionManager<4, 16> = HeaplessSessionManager::new();
+        manager.subscribe_message(0x0155, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[0xaa, 0xbb, 1, 2, 3, 0x80]), 100), Ok(None));
+        assert_eq!(manager.ingest_from(1, &CanFrame::from_parts(0x0001_5521, &[1, 0xc0]), 110).unwrap().unwrap().payload, &[1]);
+
+        let mut sessions = manager.sessions();
+        let first = sessions.next().unwrap();
+        assert_eq!(first.key.port(), Port::Message(0x0155));
+        assert_eq!(first.key.source_node(), 0x20);
+        assert_eq!(first.bytes_received, 3);
+        assert_eq!(first.transfer_started, Some(100));
+        assert_eq!(first.age(150), 50);
+
+        let second = sessions.next().unwrap();
+        assert_eq!(second.key.source_node(), 0x21);
+        assert_eq!(second.interface, 1);
+        assert_eq!(second.transfer_started, None);
+        assert!(sessions.next().is_none());
+    }
+
+    #[test]
+    fn update_frees_expired_sessions() {
+        let mut manager: HeaplessSessionManager<4, 16> = HeaplessSessionManager::new();
+        manager.subscribe_message(0x0155, 16, 1000, None).unwrap();
+        manager.subscribe_message(0x0255, 16, 5000, None).unwrap();
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[0xaa, 0xbb, 1, 0x80]), 100), Ok(None));
+        assert!(manager.ingest(&CanFrame::from_parts(0x0002_5520, &[1, 0xc0]), 100).unwrap().is_some());
+        assert_eq!(manager.update(1100), 0);
+        assert_eq!(manager.update(1101), 1);
+        assert_eq!(manager.sessions().next().unwrap().key.port(), Port::Message(0x0255));
+        assert_eq!(manager.update(5101), 1);
+
+        let stats = manager.stats();
+        assert_eq!(stats.active_sessions, 0);
+        assert_eq!(stats.expirations, 2);
+        assert_eq!(stats.evictions, 0);
+    }
+
+    #[test]
+    fn payload_too_large() {
+        let mut manager: HeaplessSessionManager<1, 4> = HeaplessSessionManager::new();
+        manager.subscribe_message(0x0155, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[0xaa, 0xbb, 1, 2, 3, 4, 5, 0x80]), 100), Err(SessionError::PayloadTooLarge));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5520, &[6, 0x60]), 110), Err(SessionError::UnexpectedFrame));
+        assert_eq!(manager.stats().out_of_memory_drops, 1);
+    }
+}
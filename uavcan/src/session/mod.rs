@@ -0,0 +1,789 @@
+//! Transfer reassembly sessions
+//!
+//! A session tracks the transfers from one source on one port. The `SessionManager` routes every received frame to its session,
+//! reassembles multi-frame transfers into a payload buffer and delivers the completed transfers as raw payload bytes.
+//! Unlike the subscriber storage used by `SimpleNode`, the session layer works on payload bytes and doesn't need to know the data types.
+//!
+//! Timestamps are given by the caller in microseconds from an arbitrary monotonic epoch.
+//!
+//! A session that has not seen a frame within the transfer-ID timeout is reset, a transfer in progress is then dropped
+//! and the next transfer is accepted whatever its transfer ID is. This lets a restarted remote node, whose transfer ID
+//! counter started over, be received again instead of having its transfers rejected as duplicates.
+//!
+//! Only transfers on subscribed ports are received, frames on other ports are rejected without allocating a session.
+//! Once the local node ID is set (e.g. `HeaplessSessionManager::with_local_node_id`), service frames addressed to other nodes are rejected the same way.
+//! Every subscription declares an extent, the largest payload the application is interested in, and the transfer-ID timeout of its sessions.
+//! Payload bytes beyond the extent are dropped during reassembly (the transfer is still delivered, truncated),
+//! which bounds the reassembly buffer of the port instead of sizing every buffer for the worst case.
+//! Subscriptions can be removed at any time, the sessions of the port are then freed immediately.
+//!
+//! The transfer CRC of multi-frame transfers is stripped from the payload, and validated when the subscription knows the data type signature.
+//! `PayloadFrames` splits a raw payload into frames on the transmit side, inserting the transfer CRC.
+//!
+//! Anonymous message transfers (sent by nodes without a node ID) can only be single-frame transfers.
+//! They are delivered straight from the frame without allocating a session, and multi-frame anonymous transfers are rejected.
+//!
+//! A node listening on redundant interfaces feeds the frames of every interface to the same manager with `ingest_from`.
+//! Every interface reassembles its own transfers, and a transfer completed on several interfaces is only delivered once.
+//!
+//! The reassembly buffers of the `PoolSessionManager` are blocks from a `BlockAllocator`, which lets the application decide
+//! where the reassembly memory lives and share it between many sessions.
+//!
+//! The `EvictionPolicy` decides which session makes room for a new transfer when all session slots are busy,
+//! e.g. to keep high priority transfers flowing under memory pressure.
+//!
+//! A session quota limits the number of sessions a single remote node can occupy (see e.g. `HeaplessSessionManager::with_session_quota`),
+//! so a babbling or malicious node starting many transfers only evicts its own sessions instead of starving the other nodes.
+//!
+//! Frames received in an interrupt handler can be queued with a `FrameProducer` and reassembled later in thread context
+//! by a `SplitSessionManager`, see `split`.
+//!
+//! Datagram transports (UDP and serial) may deliver the frames of a transfer out of order, they use the `DatagramSessionManager`.
+//!
+//! The session layer is a standalone API, it isn't integrated with `SimpleNode` yet: `SimpleNode` still reassembles
+//! transfers through its subscriber storage. An application using the session layer reads the frames from its interfaces
+//! and feeds them to a `SessionManager` itself.
+//! Large transfers (e.g. file reads) can instead be streamed to a callback as the frames arrive with a `DatagramStreamManager`.
+//! The datagram managers don't know about subscriptions: they allocate a session for every source and port they see,
+//! so frames on ports the application isn't interested in must be dropped before `ingest` (the UDP backends only join
+//! the multicast groups of subscribed subjects). They reject duplicated transfers by transfer ID like the `SessionManager`,
+//! but the `DatagramSessionManager` has no extent, a transfer larger than its buffer is dropped with `PayloadTooLarge`.
+
+pub mod heapless_manager;
+#[cfg(feature="alloc")]
+pub mod heap_manager;
+pub mod allocator;
+pub mod pool_manager;
+pub mod split;
+pub mod transmit;
+pub mod datagram;
+pub mod stream;
+
+pub use self::heapless_manager::HeaplessSessionManager;
+#[cfg(feature="alloc")]
+pub use self::heap_manager::HeapSessionManager;
+pub use self::allocator::{
+    BlockAllocator,
+    StaticPool,
+};
+pub use self::pool_manager::PoolSessionManager;
+pub use self::transmit::PayloadFrames;
+pub use self::split::{
+    split,
+    FrameProducer,
+    FrameQueue,
+    QueuedFrame,
+    SplitSessionManager,
+};
+pub use self::datagram::{
+    DatagramSessionManager,
+    DatagramTransfer,
+};
+pub use self::stream::{
+    DatagramStreamManager,
+    StreamChunk,
+    StreamEnd,
+};
+
+use lib::core::ops::Add;
+
+use heapless;
+
+use crc::{
+    ChecksumPlacement,
+    TrailingChecksum,
+    TransferCRC,
+    TransferChecksum,
+};
+
+use transfer::{
+    TransferFrame,
+    TransferFrameID,
+    TransferID,
+};
+
+/// The default transfer-ID timeout in microseconds
+pub const DEFAULT_TRANSFER_ID_TIMEOUT: u64 = 2_000_000;
+
+/// The port a transfer is sent on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Port {
+    /// Messages with the given data type ID
+    Message(u16),
+
+    /// Requests to the service with the given data type ID
+    Request(u8),
+
+    /// Responses from the service with the given data type ID
+    Response(u8),
+}
+
+/// Identifies a session, this is the frame ID with the priority masked out
+///
+/// For service transfers the key covers the service data type ID, the request/response flag, and the source and destination nodes,
+/// so concurrent requests from several clients (and their responses) are reassembled in separate sessions.
+/// The transfer ID is not part of the key, it orders the transfers within a session and detects duplicates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SessionKey(u32);
+
+impl SessionKey {
+    /// Returns the key of the session the frame with `id` belongs to
+    pub fn from_frame_id(id: TransferFrameID) -> Self {
+        SessionKey(u32::from(id) & 0x00ff_ffff)
+    }
+
+    /// Returns the node ID of the source
+    pub fn source_node(&self) -> u8 {
+        (self.0 & 0x7f) as u8
+    }
+
+    /// Returns true for the key of service transfers (requests and responses)
+    pub fn is_service(&self) -> bool {
+        self.0 & (1 << 7) != 0
+    }
+
+    /// Returns the node ID of the destination of a service transfer, messages have no destination
+    pub fn destination_node(&self) -> Option<u8> {
+        if self.is_service() {
+            Some(((self.0 >> 8) & 0x7f) as u8)
+        } else {
+            None
+        }
+    }
+
+    /// Returns true for the key of anonymous message transfers
+    pub fn is_anonymous(&self) -> bool {
+        self.0 & 0xff == 0
+    }
+
+    /// Returns the port of the session
+    ///
+    /// Anonymous messages only carry the 2 lowest bits of the data type ID, the other bits of the field are a discriminator.
+    pub fn port(&self) -> Port {
+        if self.is_anonymous() {
+            Port::Message(((self.0 >> 8) & 0x3) as u16)
+        } else if !self.is_service() {
+            Port::Message((self.0 >> 8) as u16)
+        } else if self.0 & (1 << 15) != 0 {
+            Port::Request((self.0 >> 16) as u8)
+        } else {
+            Port::Response((self.0 >> 16) as u8)
+        }
+    }
+}
+
+impl From<SessionKey> for u32 {
+    fn from(key: SessionKey) -> u32 {
+        key.0
+    }
+}
+
+/// A transfer delivered by a `SessionManager`
+#[derive(Debug, PartialEq, Eq)]
+pub struct ReceivedTransfer<'a> {
+    /// The session the transfer was received on
+    pub key: SessionKey,
+
+    /// True if the transfer was sent by a node without a node ID
+    pub anonymous: bool,
+
+    /// The priority of the transfer (0 is the highest priority)
+    pub priority: u8,
+
+    pub transfer_id: TransferID,
+
+    /// The timestamp of the first frame of the transfer
+    pub timestamp: u64,
+
+    /// The payload, without the transfer CRC
+    pub payload: &'a [u8],
+}
+
+/// A snapshot of a session, for diagnostics
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SessionInfo {
+    /// The port and source of the session
+    pub key: SessionKey,
+
+    /// The index of the redundant interface the session receives on
+    pub interface: u8,
+
+    /// The number of payload bytes of the current (or last) transfer stored so far
+    pub bytes_received: usize,
+
+    /// The timestamp of the first frame of the transfer in progress, `None` if no transfer is in progress
+    pub transfer_started: Option<u64>,
+
+    /// The timestamp of the last frame accepted by the session
+    pub last_activity: u64,
+}
+
+impl SessionInfo {
+    /// Returns the time in microseconds since the session last accepted a frame
+    pub fn age(&self, now: u64) -> u64 {
+        now.saturating_sub(self.last_activity)
+    }
+}
+
+/// Reasons for a frame being rejected by a `SessionManager`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionError {
+    /// The frame has no room for a tail byte (or for the transfer CRC in a start frame)
+    MalformedFrame,
+
+    /// The frame doesn't belong to a transfer in progress (e.g. the start frame was lost)
+    UnexpectedFrame,
+
+    /// The toggle bit doesn't alternate as expected
+    ToggleError,
+
+    /// The transfer ID is not newer than the one of the previous transfer in the session
+    Duplicate,
+
+    /// The payload doesn't fit in the reassembly buffer, the transfer is dropped
+    PayloadTooLarge,
+
+    /// No reassembly buffer or session could be allocated for the transfer (see `EvictionPolicy`), the transfer is dropped
+    OutOfMemory,
+
+    /// The frame is on a port without a subscription
+    NotSubscribed,
+
+    /// There is no room for another subscription
+    TooManySubscriptions,
+
+    /// An anonymous transfer doesn't fit in a single frame
+    AnonymousMultiFrame,
+
+    /// The transfer CRC doesn't match the payload, the transfer is dropped
+    CRCError,
+
+    /// The service frame is addressed to another node than the local node
+    NotAddressed,
+}
+
+/// A subscription to the transfers on a port
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Subscription {
+    pub port: Port,
+
+    /// The largest payload in bytes the application is interested in, payload bytes beyond it are dropped during reassembly
+    pub extent: usize,
+
+    /// The transfer-ID timeout of the sessions on the port in microseconds
+    pub transfer_id_timeout: u64,
+
+    /// The data type signature the transfer CRC of multi-frame transfers is seeded with
+    ///
+    /// The transfer CRC is validated if the signature is known, otherwise it's only stripped from the payload.
+    pub data_type_signature: Option<u64>,
+}
+
+/// How a session manager with all session slots busy makes room for a new transfer
+///
+/// Sessions without a transfer in progress are evicted first by all policies but `LeastRecentlyActive`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Evict the least recently active session, whatever its state (the default)
+    LeastRecentlyActive,
+
+    /// Reject the new transfer with `SessionError::OutOfMemory` if all sessions have a transfer in progress
+    RejectNew,
+
+    /// Evict the session with the lowest priority transfer in progress if the new transfer has a higher priority,
+    /// otherwise reject the new transfer with `SessionError::OutOfMemory`
+    LowestPriority,
+}
+
+impl Default for EvictionPolicy {
+    fn default() -> Self {
+        EvictionPolicy::LeastRecentlyActive
+    }
+}
+
+/// Statistics counters for a session manager
+///
+/// A growing number of drops or evictions means that transfers are silently lost, the capacities should then be increased.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SessionStats {
+    /// Number of sessions currently allocated
+    pub active_sessions: usize,
+
+    /// Number of transfers delivered
+    pub transfers_completed: u64,
+
+    /// Number of transfers dropped because the transfer CRC didn't match
+    pub crc_errors: u64,
+
+    /// Number of sessions evicted to make room for a new session (including sessions replaced because of the session quota)
+    pub evictions: u64,
+
+    /// Number of transfers dropped because they didn't fit in the reassembly buffer or no buffer could be allocated
+    pub out_of_memory_drops: u64,
+
+    /// Number of sessions freed by `SessionManager::update` because they timed out
+    pub expirations: u64,
+}
+
+impl SessionStats {
+    /// Counts the transfer dropped because of `error`
+    pub(crate) fn count_error(&mut self, error: SessionError) -> SessionError {
+        match error {
+            SessionError::CRCError => self.crc_errors += 1,
+            SessionError::PayloadTooLarge | SessionError::OutOfMemory => self.out_of_memory_drops += 1,
+            _ => (),
+        }
+        error
+    }
+}
+
+impl Add for SessionStats {
+    type Output = SessionStats;
+
+    fn add(self, other: SessionStats) -> SessionStats {
+        SessionStats {
+            active_sessions: self.active_sessions + other.active_sessions,
+            transfers_completed: self.transfers_completed + other.transfers_completed,
+            crc_errors: self.crc_errors + other.crc_errors,
+            evictions: self.evictions + other.evictions,
+            out_of_memory_drops: self.out_of_memory_drops + other.out_of_memory_drops,
+            expirations: self.expirations + other.expirations,
+        }
+    }
+}
+
+/// Routes received frames to their sessions and delivers completed transfers
+pub trait SessionManager {
+    /// Ingest a frame received at `timestamp`
+    ///
+    /// Returns the transfer when `frame` completes it, `Ok(None)` when more frames are needed.
+    fn ingest<F: TransferFrame>(&mut self, frame: &F, timestamp: u64) -> Result<Option<ReceivedTransfer<'_>>, SessionError> {
+        self.ingest_from(0, frame, timestamp)
+    }
+
+    /// Ingest a frame received on the redundant interface with index `interface` at `timestamp`
+    ///
+    /// A transfer that was already delivered from another interface is rejected as `SessionError::Duplicate`.
+    fn ingest_from<F: TransferFrame>(&mut self, interface: u8, frame: &F, timestamp: u64) -> Result<Option<ReceivedTransfer<'_>>, SessionError>;
+
+    /// Returns the number of sessions currently allocated
+    fn active_sessions(&self) -> usize;
+
+    /// Returns the statistics counters accumulated since the manager was constructed
+    fn stats(&self) -> SessionStats;
+
+    /// Adds `subscription`, replacing the previous subscription to the same port
+    ///
+    /// An extent larger than the reassembly buffer is not an error, transfers that don't fit are then dropped.
+    fn subscribe(&mut self, subscription: Subscription) -> Result<(), SessionError>;
+
+    /// Removes the subscription to `port` and frees its sessions, transfers in progress on the port are dropped
+    ///
+    /// The acceptance filters computed from `subscriptions` should be updated afterwards.
+    fn unsubscribe(&mut self, port: Port) -> Result<(), SessionError>;
+
+    /// Returns the current subscriptions, e.g. for computing the acceptance filters of the interfaces
+    fn subscriptions(&self) -> &[Subscription];
+
+    /// Periodic housekeeping at `now`, frees the sessions that haven't accepted a frame within the transfer-ID timeout of their port
+    ///
+    /// The state of such sessions is lost anyway, freeing them early makes room for new sessions without evictions.
+    /// All time-dependent behaviour uses the timestamps given by the caller, there is no hidden clock.
+    /// Returns the number of sessions freed, which are also counted in `SessionStats::expirations`.
+    fn update(&mut self, now: u64) -> usize;
+
+    /// Returns a snapshot of every session currently allocated, e.g. for debug UIs and the port introspection services
+    fn sessions(&self) -> impl Iterator<Item = SessionInfo> + '_;
+
+    /// Returns the timestamp of the last transfer completed on `port`, `None` if nothing was received or `port` isn't subscribed to
+    ///
+    /// Like `ReceivedTransfer::timestamp` this is the timestamp of the first frame of the transfer,
+    /// which lets the application detect stale data (e.g. no position fix for 500 ms) without keeping its own records.
+    fn last_transfer_timestamp(&self, port: Port) -> Option<u64>;
+
+    /// Subscribes to the messages with data type ID `subject_id`
+    ///
+    /// The transfer CRC of multi-frame transfers is validated with `data_type_signature`, `None` skips the validation.
+    fn subscribe_message(&mut self, subject_id: u16, extent: usize, transfer_id_timeout: u64, data_type_signature: Option<u64>) -> Result<(), SessionError> {
+        self.subscribe(Subscription {
+            port: Port::Message(subject_id),
+            extent,
+            transfer_id_timeout,
+            data_type_signature,
+        })
+    }
+
+    /// Subscribes to the requests to the service with data type ID `service_id`
+    ///
+    /// The transfer CRC of multi-frame transfers is validated with `data_type_signature`, `None` skips the validation.
+    fn subscribe_request(&mut self, service_id: u8, extent: usize, transfer_id_timeout: u64, data_type_signature: Option<u64>) -> Result<(), SessionError> {
+        self.subscribe(Subscription {
+            port: Port::Request(service_id),
+            extent,
+            transfer_id_timeout,
+            data_type_signature,
+        })
+    }
+
+    /// Subscribes to the responses from the service with data type ID `service_id`
+    ///
+    /// The transfer CRC of multi-frame transfers is validated with `data_type_signature`, `None` skips the validation.
+    fn subscribe_response(&mut self, service_id: u8, extent: usize, transfer_id_timeout: u64, data_type_signature: Option<u64>) -> Result<(), SessionError> {
+        self.subscribe(Subscription {
+            port: Port::Response(service_id),
+            extent,
+            transfer_id_timeout,
+            data_type_signature,
+        })
+    }
+}
+
+/// The error when the data doesn't fit the room left in a `PayloadBuffer`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PayloadBufferFull;
+
+/// A buffer for reassembling the payload of a transfer
+pub trait PayloadBuffer {
+    /// Removes the whole payload
+    fn clear(&mut self);
+
+    /// Appends `data` to the payload, fails without modifying the payload if there is not enough room
+    fn extend(&mut self, data: &[u8]) -> Result<(), PayloadBufferFull>;
+
+    fn as_slice(&self) -> &[u8];
+}
+
+impl<const N: usize> PayloadBuffer for heapless::Vec<u8, N> {
+    fn clear(&mut self) {
+        heapless::Vec::clear(self)
+    }
+
+    fn extend(&mut self, data: &[u8]) -> Result<(), PayloadBufferFull> {
+        self.extend_from_slice(data).map_err(|()| PayloadBufferFull)
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct InProgress {
+    transfer_id: TransferID,
+    toggle: bool,
+    timestamp: u64,
+    priority: u8,
+}
+
+/// A completed transfer, the payload is left in the session buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Completed {
+    pub transfer_id: TransferID,
+    pub timestamp: u64,
+    pub priority: u8,
+}
+
+/// The reassembly state of one session, shared by the `SessionManager` implementations
+#[derive(Debug)]
+pub(crate) struct Session<B, C = TransferCRC> {
+    pub key: SessionKey,
+    pub interface: u8,
+    pub payload: B,
+    pub last_activity: u64,
+    last_transfer_id: Option<TransferID>,
+    in_progress: Option<InProgress>,
+    crc_received: [u8; 8],
+    crc_calculated: Option<C>,
+    trailing: Option<TrailingChecksum>,
+}
+
+/// Returns true if `transfer_id` is ahead of `last` by less than half the transfer ID range
+fn is_newer(transfer_id: TransferID, last: TransferID) -> bool {
+    let distance = u8::from(transfer_id).wrapping_sub(u8::from(last)) & 0x1f;
+    distance >= 1 && distance <= 16
+}
+
+/// Returns true if a session of `key` on another interface than `interface` recently completed
+/// a transfer with `transfer_id` (or a later one), the transfer has then already been delivered
+pub(crate) fn delivered_elsewhere<'a, B: 'a, C: 'a, I>(sessions: I, key: SessionKey, interface: u8, transfer_id: TransferID, timestamp: u64, transfer_id_timeout: u64) -> bool
+    where I: Iterator<Item = &'a Session<B, C>>
+{
+    sessions
+        .filter(|session| session.key == key && session.interface != interface)
+        .filter(|session| timestamp.saturating_sub(session.last_activity) <= transfer_id_timeout)
+        .any(|session| session.last_transfer_id.map_or(false, |last| !is_newer(transfer_id, last)))
+}
+
+/// The largest payload of an anonymous transfer (a CAN FD frame without the tail byte)
+pub(crate) const MAX_ANONYMOUS_PAYLOAD: usize = 63;
+
+/// Copies the payload of the anonymous transfer in `frame` to `buffer` and returns the transfer
+pub(crate) fn accept_anonymous<'a, F: TransferFrame>(buffer: &'a mut heapless::Vec<u8, MAX_ANONYMOUS_PAYLOAD>, key: SessionKey, frame: &F, timestamp: u64, subscription: &Subscription) -> Result<ReceivedTransfer<'a>, SessionError> {
+    let data = frame.data();
+    if data.is_empty() {
+        return Err(SessionError::MalformedFrame);
+    }
+
+    let tail_byte = frame.tail_byte();
+    if !tail_byte.start_of_transfer() || !tail_byte.end_of_transfer() {
+        return Err(SessionError::AnonymousMultiFrame);
+    }
+    if tail_byte.toggle() {
+        return Err(SessionError::ToggleError);
+    }
+
+    let payload = &data[..data.len() - 1];
+    buffer.clear();
+    if buffer.extend_from_slice(&payload[..::lib::core::cmp::min(subscription.extent, payload.len())]).is_err() {
+        return Err(SessionError::MalformedFrame);
+    }
+
+    Ok(ReceivedTransfer {
+        key,
+        anonymous: true,
+        priority: ((u32::from(frame.id()) >> 24) & 0x1f) as u8,
+        transfer_id: tail_byte.transfer_id(),
+        timestamp,
+        payload: buffer,
+    })
+}
+
+/// A session in a slot of a session manager, evicted by `free_or_oldest_slot` when it was least recently active
+pub(crate) trait Activity {
+    /// The timestamp of the last frame accepted by the session
+    fn last_activity(&self) -> u64;
+}
+
+impl<B, C> Activity for Session<B, C> {
+    fn last_activity(&self) -> u64 {
+        self.last_activity
+    }
+}
+
+/// Returns the index of a free slot, or of the least recently active session if all slots are busy (the lowest index wins ties)
+///
+/// Returns `None` if there are no slots at all.
+pub(crate) fn free_or_oldest_slot<S: Activity>(sessions: &[Option<S>]) -> Option<usize> {
+    if let Some(free) = sessions.iter().position(|s| s.is_none()) {
+        return Some(free);
+    }
+
+    let mut oldest = None;
+    for (index, session) in sessions.iter().enumerate() {
+        let last_activity = session.as_ref().expect("All slots are busy").last_activity();
+        if oldest.map_or(true, |(_, oldest_activity)| last_activity < oldest_activity) {
+            oldest = Some((index, last_activity));
+        }
+    }
+    oldest.map(|(index, _)| index)
+}
+
+/// Returns true for the service transfers addressed to another node than `local_node_id`, `None` accepts every destination
+pub(crate) fn addressed_elsewhere(key: SessionKey, local_node_id: Option<u8>) -> bool {
+    match (key.destination_node(), local_node_id) {
+        (Some(destination), Some(local)) => destination != local,
+        _ => false,
+    }
+}
+
+/// Returns the slot for a new session from `source` starting a transfer with `priority`, `None` if `policy` rejects the transfer
+///
+/// If `source` already occupies `quota` slots, its least recently active session is replaced.
+/// Otherwise a free slot is used, and when all slots are busy the session to evict is chosen by `policy`.
+pub(crate) fn slot_for_session<B: PayloadBuffer, C: TransferChecksum>(sessions: &[Option<Session<B, C>>], source: u8, quota: usize, policy: EvictionPolicy, priority: u8) -> Option<usize> {
+    let busy = sessions.iter()
+        .enumerate()
+        .filter_map(|(index, session)| session.as_ref().map(|session| (index, session)));
+
+    let from_source = busy.clone().filter(|&(_, session)| session.key.source_node() == source);
+    if from_source.clone().count() >= quota {
+        if let Some((oldest, _)) = from_source.min_by_key(|&(_, session)| session.last_activity) {
+            return Some(oldest);
+        }
+    }
+
+    if policy == EvictionPolicy::LeastRecentlyActive {
+        return free_or_oldest_slot(sessions);
+    }
+    if let Some(free) = sessions.iter().position(|s| s.is_none()) {
+        return Some(free);
+    }
+
+    // Idle sessions only hold the state for rejecting duplicates, they are evicted before any transfer in progress
+    let idle = busy.clone()
+        .filter(|&(_, session)| !session.is_in_progress())
+        .min_by_key(|&(_, session)| session.last_activity);
+    if let Some((oldest, _)) = idle {
+        return Some(oldest);
+    }
+
+    match policy {
+        EvictionPolicy::LowestPriority => busy
+            .filter_map(|(index, session)| session.priority().map(|lowest| (index, lowest, session.last_activity)))
+            .filter(|&(_, lowest, _)| lowest > priority)
+            .min_by_key(|&(_, lowest, last_activity)| (u8::max_value() - lowest, last_activity))
+            .map(|(index, _, _)| index),
+        _ => None,
+    }
+}
+
+impl<B: PayloadBuffer, C: TransferChecksum> Session<B, C> {
+    pub fn new(key: SessionKey, interface: u8, payload: B, timestamp: u64) -> Self {
+        Session {
+            key,
+            interface,
+            payload,
+            last_activity: timestamp,
+            last_transfer_id: None,
+            in_progress: None,
+            crc_received: [0; 8],
+            crc_calculated: None,
+            trailing: None,
+        }
+    }
+
+    /// Returns true while a transfer is being reassembled
+    pub fn is_in_progress(&self) -> bool {
+        self.in_progress.is_some()
+    }
+
+    /// Returns true if the session hasn't accepted a frame within the transfer-ID timeout of its port at `now`
+    pub fn is_expired(&self, now: u64, subscriptions: &[Subscription]) -> bool {
+        subscriptions.iter()
+            .find(|subscription| subscription.port == self.key.port())
+            .map_or(true, |subscription| now.saturating_sub(self.last_activity) > subscription.transfer_id_timeout)
+    }
+
+    /// Returns a snapshot of the session
+    pub fn info(&self) -> SessionInfo {
+        SessionInfo {
+            key: self.key,
+            interface: self.interface,
+            bytes_received: self.payload.as_slice().len(),
+            transfer_started: self.in_progress.map(|in_progress| in_progress.timestamp),
+            last_activity: self.last_activity,
+        }
+    }
+
+    /// Returns the priority of the transfer being reassembled
+    pub fn priority(&self) -> Option<u8> {
+        self.in_progress.map(|in_progress| in_progress.priority)
+    }
+
+    pub fn accept<F: TransferFrame>(&mut self, frame: &F, timestamp: u64, subscription: &Subscription) -> Result<Option<Completed>, SessionError> {
+        let data = frame.data();
+        if data.is_empty() {
+            return Err(SessionError::MalformedFrame);
+        }
+
+        if timestamp.saturating_sub(self.last_activity) > subscription.transfer_id_timeout {
+            self.last_transfer_id = None;
+            self.in_progress = None;
+        }
+
+        let tail_byte = frame.tail_byte();
+        let transfer_id = tail_byte.transfer_id();
+
+        let payload = if tail_byte.start_of_transfer() {
+            if tail_byte.toggle() {
+                return Err(SessionError::ToggleError);
+            }
+            if let Some(last) = self.last_transfer_id {
+                if !is_newer(transfer_id, last) {
+                    return Err(SessionError::Duplicate);
+                }
+            }
+
+            // A new transfer aborts the one in progress, its remaining frames are lost
+            self.payload.clear();
+            self.in_progress = Some(InProgress {
+                transfer_id,
+                toggle: false,
+                timestamp,
+                priority: ((u32::from(frame.id()) >> 24) & 0x1f) as u8,
+            });
+
+            if tail_byte.end_of_transfer() {
+                self.crc_calculated = None;
+                self.trailing = None;
+                &data[..data.len() - 1]
+            } else {
+                self.crc_calculated = subscription.data_type_signature.map(C::from_signature);
+                match C::PLACEMENT {
+                    ChecksumPlacement::FirstFrame => {
+                        if data.len() < C::LENGTH + 1 {
+                            self.in_progress = None;
+                            return Err(SessionError::MalformedFrame);
+                        }
+                        self.crc_received[..C::LENGTH].copy_from_slice(&data[..C::LENGTH]);
+                        self.trailing = None;
+                        &data[C::LENGTH..data.len() - 1]
+                    },
+                    ChecksumPlacement::PayloadEnd => {
+                        self.trailing = Some(TrailingChecksum::default());
+                        &data[..data.len() - 1]
+                    },
+                }
+            }
+        } else {
+            let in_progress = match self.in_progress {
+                Some(ref mut in_progress) if in_progress.transfer_id == transfer_id => in_progress,
+                _ => return Err(SessionError::UnexpectedFrame),
+            };
+            if tail_byte.toggle() == in_progress.toggle {
+                return Err(SessionError::ToggleError);
+            }
+            in_progress.toggle = !in_progress.toggle;
+            &data[..data.len() - 1]
+        };
+
+        // Only accepted frames keep the session alive, a restarted node sending stale transfer IDs must not prevent the timeout
+        self.last_activity = timestamp;
+
+        // The CRC covers the whole payload, including the bytes beyond the extent.
+        // A CRC after the payload is held back until the next frame shows that the bytes aren't part of it.
+        let mut too_large = false;
+        {
+            let Session{payload: ref mut buffer, ref mut crc_calculated, ref mut trailing, ..} = *self;
+            let mut receive = |bytes: &[u8]| {
+                if let Some(ref mut crc) = *crc_calculated {
+                    crc.add(bytes);
+                }
+                let room = subscription.extent.saturating_sub(buffer.as_slice().len());
+                let kept = &bytes[..::lib::core::cmp::min(room, bytes.len())];
+                too_large |= buffer.extend(kept).is_err();
+            };
+            match *trailing {
+                Some(ref mut trailing) => trailing.push(C::LENGTH, payload, &mut receive),
+                None => receive(payload),
+            }
+        }
+        if too_large {
+            self.in_progress = None;
+            return Err(SessionError::PayloadTooLarge);
+        }
+
+        if tail_byte.end_of_transfer() {
+            let in_progress = self.in_progress.take().expect("A transfer is in progress after accepting a frame");
+            let trailing = self.trailing.take();
+            let crc_received = match trailing {
+                Some(ref trailing) if trailing.received().len() < C::LENGTH => return Err(SessionError::MalformedFrame),
+                Some(ref trailing) => trailing.received(),
+                None => &self.crc_received[..C::LENGTH],
+            };
+            if let Some(crc) = self.crc_calculated.take() {
+                if !crc.matches(crc_received) {
+                    return Err(SessionError::CRCError);
+                }
+            }
+            self.last_transfer_id = Some(transfer_id);
+            Ok(Some(Completed {
+                transfer_id,
+                timestamp: in_progress.timestamp,
+                priority: in_progress.priority,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+}
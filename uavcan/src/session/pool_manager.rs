@@ -0,0 +1,288 @@
+//! A `SessionManager` taking its reassembly buffers from a `BlockAllocator`
+
+use heapless::Vec;
+
+use crc::{
+    TransferCRC,
+    TransferChecksum,
+};
+
+use transfer::TransferFrame;
+
+use session::{
+    SessionManager,
+    SessionKey,
+    Session,
+    Subscription,
+    Port,
+    SessionError,
+    SessionStats,
+    SessionInfo,
+    PayloadBuffer,
+    PayloadBufferFull,
+    ReceivedTransfer,
+    BlockAllocator,
+    delivered_elsewhere,
+    accept_anonymous,
+    addressed_elsewhere,
+    MAX_ANONYMOUS_PAYLOAD,
+    slot_for_session,
+    EvictionPolicy,
+};
+
+/// A payload buffer in a block from a `BlockAllocator`
+#[derive(Debug)]
+pub(crate) struct Block<'a> {
+    data: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> PayloadBuffer for Block<'a> {
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn extend(&mut self, data: &[u8]) -> Result<(), PayloadBufferFull> {
+        if self.len + data.len() > self.data.len() {
+            return Err(PayloadBufferFull);
+        }
+        self.data[self.len..self.len + data.len()].copy_from_slice(data);
+        self.len += data.len();
+        Ok(())
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
+/// A `SessionManager` with room for `MAX_SESSIONS` sessions, every session takes a block from the allocator `A` as reassembly buffer
+///
+/// The block is returned to the allocator when the session is evicted or its port is unsubscribed from,
+/// so many sessions can share a pool sized for the number of transfers that are expected to be in progress at once.
+/// Transfers that don't fit in a block, or arrive when the allocator is exhausted, are dropped.
+#[derive(Debug)]
+pub struct PoolSessionManager<'a, A, const MAX_SESSIONS: usize, const MAX_SUBSCRIPTIONS: usize = 16, C = TransferCRC> {
+    allocator: A,
+    sessions: [Option<Session<Block<'a>, C>>; MAX_SESSIONS],
+    subscriptions: Vec<Subscription, MAX_SUBSCRIPTIONS>,
+    // The timestamp of the last transfer completed on every subscribed port, in the order of `subscriptions`
+    last_transfers: Vec<Option<u64>, MAX_SUBSCRIPTIONS>,
+    session_quota: usize,
+    local_node_id: Option<u8>,
+    eviction_policy: EvictionPolicy,
+    stats: SessionStats,
+    anonymous: Vec<u8, MAX_ANONYMOUS_PAYLOAD>,
+}
+
+impl<'a, A: BlockAllocator<'a>, const MAX_SESSIONS: usize, const MAX_SUBSCRIPTIONS: usize, C: TransferChecksum> PoolSessionManager<'a, A, MAX_SESSIONS, MAX_SUBSCRIPTIONS, C> {
+    /// Constructs a manager without any sessions or subscriptions, taking its buffers from `allocator`
+    pub fn new(allocator: A) -> Self {
+        PoolSessionManager {
+            allocator,
+            sessions: ::lib::core::array::from_fn(|_| None),
+            subscriptions: Vec::new(),
+            last_transfers: Vec::new(),
+            session_quota: usize::max_value(),
+            local_node_id: None,
+            eviction_policy: EvictionPolicy::default(),
+            stats: SessionStats::default(),
+            anonymous: Vec::new(),
+        }
+    }
+
+    /// Limits the number of sessions a single remote node can occupy to `quota`, on all interfaces together
+    ///
+    /// A new session from a node that reached its quota replaces the least recently active session of that node.
+    ///
+    /// ## Panics
+    /// Panics if `quota` is zero.
+    pub fn with_session_quota(mut self, quota: usize) -> Self {
+        assert!(quota > 0, "The session quota must allow at least one session");
+        self.session_quota = quota;
+        self
+    }
+
+    /// Only receives the service transfers addressed to `node_id`, service frames to other nodes are rejected without allocating a session
+    ///
+    /// Without a local node ID the service transfers to every node are received, e.g. by a bus monitor.
+    pub fn with_local_node_id(mut self, node_id: u8) -> Self {
+        self.local_node_id = Some(node_id);
+        self
+    }
+
+    /// Sets the policy for making room for a new transfer when all session slots are busy
+    pub fn with_eviction_policy(mut self, eviction_policy: EvictionPolicy) -> Self {
+        self.eviction_policy = eviction_policy;
+        self
+    }
+
+    /// Returns the allocator, e.g. for checking how many blocks are available
+    pub fn allocator(&self) -> &A {
+        &self.allocator
+    }
+
+    /// Frees the session in slot `index` and returns its block to the allocator
+    fn release(&mut self, index: usize) {
+        if let Some(session) = self.sessions[index].take() {
+            self.allocator.free(session.payload.data);
+        }
+    }
+}
+
+impl<'a, A: BlockAllocator<'a>, const MAX_SESSIONS: usize, const MAX_SUBSCRIPTIONS: usize, C: TransferChecksum> SessionManager for PoolSessionManager<'a, A, MAX_SESSIONS, MAX_SUBSCRIPTIONS, C> {
+    fn ingest_from<F: TransferFrame>(&mut self, interface: u8, frame: &F, timestamp: u64) -> Result<Option<ReceivedTransfer<'_>>, SessionError> {
+        let key = SessionKey::from_frame_id(frame.id());
+        let (subscription_index, subscription) = match self.subscriptions.iter().position(|subscription| subscription.port == key.port()) {
+            Some(index) => (index, self.subscriptions[index]),
+            None => return Err(SessionError::NotSubscribed),
+        };
+
+        if addressed_elsewhere(key, self.local_node_id) {
+            return Err(SessionError::NotAddressed);
+        }
+
+        if key.is_anonymous() {
+            return match accept_anonymous(&mut self.anonymous, key, frame, timestamp, &subscription) {
+                Ok(transfer) => {
+                    self.stats.transfers_completed += 1;
+                    self.last_transfers[subscription_index] = Some(transfer.timestamp);
+                    Ok(Some(transfer))
+                },
+                Err(error) => Err(self.stats.count_error(error)),
+            };
+        }
+
+        let index = match self.sessions.iter().position(|s| s.as_ref().map_or(false, |s| s.key == key && s.interface == interface)) {
+            Some(index) => index,
+            None => {
+                if frame.data().is_empty() || !frame.is_start_frame() {
+                    return Err(SessionError::UnexpectedFrame);
+                }
+                let priority = ((u32::from(frame.id()) >> 24) & 0x1f) as u8;
+                let index = match slot_for_session(&self.sessions, key.source_node(), self.session_quota, self.eviction_policy, priority) {
+                    Some(index) => index,
+                    None => return Err(self.stats.count_error(SessionError::OutOfMemory)),
+                };
+                if self.sessions[index].is_some() {
+                    self.release(index);
+                    self.stats.evictions += 1;
+                }
+                let data = match self.allocator.allocate() {
+                    Some(data) => data,
+                    None => return Err(self.stats.count_error(SessionError::OutOfMemory)),
+                };
+                self.sessions[index] = Some(Session::new(key, interface, Block { data, len: 0 }, timestamp));
+                index
+            },
+        };
+
+        let completed = match self.sessions[index].as_mut().expect("The session was just found or allocated").accept(frame, timestamp, &subscription) {
+            Ok(Some(completed)) => completed,
+            Ok(None) => return Ok(None),
+            Err(error) => return Err(self.stats.count_error(error)),
+        };
+
+        if delivered_elsewhere(self.sessions.iter().flatten(), key, interface, completed.transfer_id, timestamp, subscription.transfer_id_timeout) {
+            return Err(SessionError::Duplicate);
+        }
+        self.stats.transfers_completed += 1;
+        self.last_transfers[subscription_index] = Some(completed.timestamp);
+
+        let session = self.sessions[index].as_ref().expect("The session was just found or allocated");
+        Ok(Some(ReceivedTransfer {
+            key,
+            anonymous: false,
+            priority: completed.priority,
+            transfer_id: completed.transfer_id,
+            timestamp: completed.timestamp,
+            payload: session.payload.as_slice(),
+        }))
+    }
+
+    fn active_sessions(&self) -> usize {
+        self.sessions.iter().filter(|s| s.is_some()).count()
+    }
+
+    fn stats(&self) -> SessionStats {
+        SessionStats {
+            active_sessions: self.active_sessions(),
+            ..self.stats
+        }
+    }
+
+    fn subscribe(&mut self, subscription: Subscription) -> Result<(), SessionError> {
+        if let Some(existing) = self.subscriptions.iter_mut().find(|existing| existing.port == subscription.port) {
+            *existing = subscription;
+            return Ok(());
+        }
+        self.subscriptions.push(subscription).map_err(|_| SessionError::TooManySubscriptions)?;
+        self.last_transfers.push(None).expect("There is room for the timestamp of every subscription");
+        Ok(())
+    }
+
+    fn unsubscribe(&mut self, port: Port) -> Result<(), SessionError> {
+        let index = self.subscriptions.iter().position(|subscription| subscription.port == port).ok_or(SessionError::NotSubscribed)?;
+        self.subscriptions.swap_remove(index);
+        self.last_transfers.swap_remove(index);
+        for index in 0..MAX_SESSIONS {
+            if self.sessions[index].as_ref().map_or(false, |session| session.key.port() == port) {
+                self.release(index);
+            }
+        }
+        Ok(())
+    }
+
+    fn subscriptions(&self) -> &[Subscription] {
+        &self.subscriptions
+    }
+
+    fn update(&mut self, now: u64) -> usize {
+        let mut expired = 0;
+        for index in 0..MAX_SESSIONS {
+            if self.sessions[index].as_ref().map_or(false, |session| session.is_expired(now, &self.subscriptions)) {
+                self.release(index);
+                expired += 1;
+            }
+        }
+        self.stats.expirations += expired as u64;
+        expired
+    }
+
+    fn sessions(&self) -> impl Iterator<Item = SessionInfo> + '_ {
+        self.sessions.iter().flatten().map(|session| session.info())
+    }
+
+    fn last_transfer_timestamp(&self, port: Port) -> Option<u64> {
+        let index = self.subscriptions.iter().position(|subscription| subscription.port == port)?;
+        self.last_transfers[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use tests::CanFrame;
+
+    use session::*;
+
+    #[test]
+    fn blocks_are_returned_to_the_pool() {
+        let mut storage = [[0u8; 8]; 2];
+        let mut manager: PoolSessionManager<_, 4> = PoolSessionManager::new(StaticPool::new(&mut storage));
+        manager.subscribe_message(0x0155, 8, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5501, &[0xaa, 0xbb, 1, 2, 0x80]), 100), Ok(None));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5502, &[0xaa, 0xbb, 1, 2, 0x80]), 110), Ok(None));
+        assert_eq!(manager.ingest(&CanFrame::from_parts(0x0001_5503, &[0xaa, 0xbb, 1, 2, 0x80]), 120), Err(SessionError::OutOfMemory));
+        assert_eq!(manager.allocator().available(), 0);
+        assert_eq!(manager.stats().out_of_memory_drops, 1);
+
+        let transfer = manager.ingest(&CanFrame::from_parts(0x0001_5501, &[3, 0x60]), 130).unwrap().unwrap();
+        assert_eq!(transfer.payload, &[1, 2, 3]);
+
+        manager.unsubscribe(Port::Message(0x0155)).unwrap();
+        assert_eq!(manager.active_sessions(), 0);
+        assert_eq!(manager.allocator().available(), 2);
+    }
+}
@@ -0,0 +1,113 @@
+//! Ingestion of frames received in interrupt context
+//!
+//! The RX interrupt pushes the raw frames into a lock-free single producer, single consumer `FrameQueue` through a `FrameProducer`,
+//! and the `SplitSessionManager` reassembles them later in thread context. Neither side needs a lock or a critical section.
+
+use heapless::spsc::{
+    Consumer,
+    Producer,
+    Queue,
+};
+
+use transfer::TransferFrame;
+
+use session::{
+    SessionManager,
+    SessionError,
+    ReceivedTransfer,
+};
+
+/// A frame waiting in a `FrameQueue` to be reassembled
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueuedFrame<F> {
+    pub frame: F,
+
+    /// The time of reception in microseconds
+    pub timestamp: u64,
+
+    /// The index of the redundant interface the frame was received on
+    pub interface: u8,
+}
+
+/// The queue between the interrupt and thread contexts, with room for `N - 1` frames
+pub type FrameQueue<F, const N: usize> = Queue<QueuedFrame<F>, N>;
+
+/// The interrupt side of a split session manager
+pub struct FrameProducer<'a, F, const N: usize> {
+    producer: Producer<'a, QueuedFrame<F>, N>,
+}
+
+impl<'a, F, const N: usize> FrameProducer<'a, F, N> {
+    /// Queues `frame` received on `interface` at `timestamp`, returns the frame back if the queue is full
+    pub fn push(&mut self, interface: u8, frame: F, timestamp: u64) -> Result<(), F> {
+        self.producer.enqueue(QueuedFrame { frame, timestamp, interface }).map_err(|queued| queued.frame)
+    }
+
+    /// Returns true if there is room for another frame
+    pub fn ready(&self) -> bool {
+        self.producer.ready()
+    }
+}
+
+/// The thread side of a split session manager, reassembling the queued frames with the session manager `M`
+pub struct SplitSessionManager<'a, M, F, const N: usize> {
+    consumer: Consumer<'a, QueuedFrame<F>, N>,
+    manager: M,
+}
+
+impl<'a, M: SessionManager, F: TransferFrame, const N: usize> SplitSessionManager<'a, M, F, N> {
+    /// Returns the number of frames waiting to be reassembled
+    pub fn pending(&self) -> usize {
+        self.consumer.len()
+    }
+
+    /// Ingests the oldest queued frame, returns `None` if the queue is empty
+    pub fn process_next(&mut self) -> Option<Result<Option<ReceivedTransfer<'_>>, SessionError>> {
+        let queued = self.consumer.dequeue()?;
+        Some(self.manager.ingest_from(queued.interface, &queued.frame, queued.timestamp))
+    }
+
+    /// Returns the session manager
+    pub fn manager(&self) -> &M {
+        &self.manager
+    }
+
+    /// Returns the session manager mutably, e.g. for changing the subscriptions
+    pub fn manager_mut(&mut self) -> &mut M {
+        &mut self.manager
+    }
+}
+
+/// Splits `queue` into a `FrameProducer` for the RX interrupt and a `SplitSessionManager` reassembling with `manager`
+pub fn split<'a, M: SessionManager, F: TransferFrame, const N: usize>(queue: &'a mut FrameQueue<F, N>, manager: M) -> (FrameProducer<'a, F, N>, SplitSessionManager<'a, M, F, N>) {
+    let (producer, consumer) = queue.split();
+    (FrameProducer { producer }, SplitSessionManager { consumer, manager })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use tests::CanFrame;
+
+    use session::*;
+
+    #[test]
+    fn frames_are_reassembled_in_order() {
+        let mut manager: HeaplessSessionManager<4, 16> = HeaplessSessionManager::new();
+        manager.subscribe_message(0x0155, 16, DEFAULT_TRANSFER_ID_TIMEOUT, None).unwrap();
+
+        let mut queue: FrameQueue<CanFrame, 3> = FrameQueue::new();
+        let (mut producer, mut front_end) = split(&mut queue, manager);
+
+        producer.push(0, CanFrame::from_parts(0x0001_5520, &[0xaa, 0xbb, 1, 0x80]), 100).unwrap();
+        producer.push(0, CanFrame::from_parts(0x0001_5520, &[2, 0x60]), 110).unwrap();
+        assert!(!producer.ready());
+        assert!(producer.push(0, CanFrame::from_parts(0x0001_5520, &[3, 0xc1]), 120).is_err());
+        assert_eq!(front_end.pending(), 2);
+
+        assert_eq!(front_end.process_next(), Some(Ok(None)));
+        assert_eq!(front_end.process_next().unwrap().unwrap().unwrap().payload, &[1, 2]);
+        assert_eq!(front_end.process_next(), None);
+        assert_eq!(front_end.manager().stats().transfers_completed, 1);
+    }
+}
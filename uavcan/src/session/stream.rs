@@ -0,0 +1,372 @@
+//! Streaming reception of large transfers over datagram transports
+//!
+//! Instead of reassembling a transfer in a buffer, the `DatagramStreamManager` hands the payload to a callback
+//! chunk by chunk as the frames arrive, so the memory needed doesn't grow with the size of the transfer (e.g. for file reads).
+//! The frames of a streamed transfer must arrive in order, a missing or reordered frame aborts the transfer.
+//!
+//! The transfer CRC can only be checked when the last frame has arrived. The chunks of a transfer must therefore be
+//! treated as tentative until `ingest` returns the `StreamEnd` of the transfer; a transfer that is aborted
+//! (by an error, or by the start of a newer transfer) never returns one and its chunks must be discarded.
+
+use udp::{
+    DataSpecifier,
+    TransferCRC32C,
+    UdpFrameHeader,
+    TRANSFER_CRC_SIZE,
+};
+
+use session::{
+    free_or_oldest_slot,
+    Activity,
+    DEFAULT_TRANSFER_ID_TIMEOUT,
+    SessionError,
+    SessionStats,
+};
+
+/// A chunk of the payload of a transfer being streamed
+#[derive(Debug, PartialEq, Eq)]
+pub struct StreamChunk<'a> {
+    pub source_node_id: u16,
+
+    pub data_specifier: DataSpecifier,
+
+    pub transfer_id: u64,
+
+    /// The position of `data` in the payload
+    pub offset: usize,
+
+    pub data: &'a [u8],
+}
+
+/// A streamed transfer that was completed with a valid transfer CRC
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StreamEnd {
+    pub source_node_id: u16,
+
+    pub destination_node_id: u16,
+
+    pub data_specifier: DataSpecifier,
+
+    /// The priority of the transfer (0 is the highest priority)
+    pub priority: u8,
+
+    pub transfer_id: u64,
+
+    /// The timestamp of the first frame of the transfer
+    pub timestamp: u64,
+
+    /// The length of the payload, including the bytes beyond the extent that were not delivered
+    pub length: usize,
+}
+
+#[derive(Debug)]
+struct InProgress {
+    transfer_id: u64,
+    destination_node_id: u16,
+    priority: u8,
+    timestamp: u64,
+    next_index: u32,
+    offset: usize,
+    crc: TransferCRC32C,
+    // The last bytes received may be the transfer CRC, they are held back until the next frame arrives
+    held: [u8; TRANSFER_CRC_SIZE],
+    held_length: usize,
+}
+
+#[derive(Debug)]
+struct StreamSession {
+    source_node_id: u16,
+    data_specifier: DataSpecifier,
+    in_progress: Option<InProgress>,
+    last_transfer_id: Option<u64>,
+    last_activity: u64,
+}
+
+impl Activity for StreamSession {
+    fn last_activity(&self) -> u64 {
+        self.last_activity
+    }
+}
+
+impl StreamSession {
+    fn accept<CB: FnMut(StreamChunk)>(&mut self, header: &UdpFrameHeader, payload: &[u8], timestamp: u64, transfer_id_timeout: u64, extent: usize, mut on_chunk: CB) -> Result<Option<StreamEnd>, SessionError> {
+        if timestamp.saturating_sub(self.last_activity) > transfer_id_timeout {
+            self.last_transfer_id = None;
+            self.in_progress = None;
+        }
+
+        if let Some(last) = self.last_transfer_id {
+            if header.transfer_id <= last {
+                return Err(SessionError::Duplicate);
+            }
+        }
+
+        match self.in_progress {
+            Some(ref in_progress) if header.transfer_id < in_progress.transfer_id => return Err(SessionError::UnexpectedFrame),
+            Some(ref in_progress) if header.transfer_id == in_progress.transfer_id => {
+                if header.frame_index < in_progress.next_index {
+                    return Err(SessionError::Duplicate);
+                }
+                if header.frame_index > in_progress.next_index {
+                    self.in_progress = None;
+                    return Err(SessionError::UnexpectedFrame);
+                }
+            },
+            _ => {
+                // A newer transfer aborts the one in progress
+                self.in_progress = None;
+                if header.frame_index != 0 {
+                    return Err(SessionError::UnexpectedFrame);
+                }
+                self.in_progress = Some(InProgress {
+                    transfer_id: header.transfer_id,
+                    destination_node_id: header.destination_node_id,
+                    priority: header.priority,
+                    timestamp,
+                    next_index: 0,
+                    offset: 0,
+                    crc: TransferCRC32C::new(),
+                    held: [0; TRANSFER_CRC_SIZE],
+                    held_length: 0,
+                });
+            },
+        }
+
+        self.last_activity = timestamp;
+        let in_progress = self.in_progress.as_mut().expect("A transfer is in progress");
+
+        // Everything but the last `TRANSFER_CRC_SIZE` bytes received so far is payload
+        let available = in_progress.held_length + payload.len();
+        if header.end_of_transfer && available < TRANSFER_CRC_SIZE {
+            self.in_progress = None;
+            return Err(SessionError::MalformedFrame);
+        }
+        let emitted = available.saturating_sub(TRANSFER_CRC_SIZE);
+        let from_held = ::lib::core::cmp::min(in_progress.held_length, emitted);
+        let from_payload = emitted - from_held;
+
+        let mut held = [0u8; TRANSFER_CRC_SIZE];
+        let held_length = available - emitted;
+        for (i, byte) in held[..held_length].iter_mut().enumerate() {
+            let index = emitted + i;
+            *byte = if index < in_progress.held_length {
+                in_progress.held[index]
+            } else {
+                payload[index - in_progress.held_length]
+            };
+        }
+
+        let previous_held = in_progress.held;
+        for data in [&previous_held[..from_held], &payload[..from_payload]].iter() {
+            if data.is_empty() {
+                continue;
+            }
+            in_progress.crc.add(data);
+            let delivered = ::lib::core::cmp::min(data.len(), extent.saturating_sub(in_progress.offset));
+            if delivered > 0 {
+                on_chunk(StreamChunk {
+                    source_node_id: self.source_node_id,
+                    data_specifier: self.data_specifier,
+                    transfer_id: in_progress.transfer_id,
+                    offset: in_progress.offset,
+                    data: &data[..delivered],
+                });
+            }
+            in_progress.offset += data.len();
+        }
+        in_progress.held = held;
+        in_progress.held_length = held_length;
+        in_progress.next_index += 1;
+
+        if !header.end_of_transfer {
+            return Ok(None);
+        }
+
+        let in_progress = self.in_progress.take().expect("A transfer is in progress");
+        if in_progress.crc.value().to_le_bytes() != in_progress.held {
+            return Err(SessionError::CRCError);
+        }
+        self.last_transfer_id = Some(in_progress.transfer_id);
+        Ok(Some(StreamEnd {
+            source_node_id: self.source_node_id,
+            destination_node_id: in_progress.destination_node_id,
+            data_specifier: self.data_specifier,
+            priority: in_progress.priority,
+            transfer_id: in_progress.transfer_id,
+            timestamp: in_progress.timestamp,
+            length: in_progress.offset,
+        }))
+    }
+}
+
+/// A streaming session manager for datagram transports with room for `MAX_SESSIONS` sessions
+///
+/// Payload bytes beyond the extent are not delivered, but still count towards the transfer CRC.
+/// When all slots are busy, the session that was least recently active is evicted; a manager without slots drops every
+/// frame with `OutOfMemory`.
+///
+/// There is no subscription filtering, frames on ports the application isn't interested in must be dropped before `ingest`.
+#[derive(Debug)]
+pub struct DatagramStreamManager<const MAX_SESSIONS: usize> {
+    sessions: [Option<StreamSession>; MAX_SESSIONS],
+    extent: usize,
+    transfer_id_timeout: u64,
+    stats: SessionStats,
+}
+
+impl<const MAX_SESSIONS: usize> DatagramStreamManager<MAX_SESSIONS> {
+    /// Constructs a manager delivering at most `extent` bytes of every transfer, using `DEFAULT_TRANSFER_ID_TIMEOUT`
+    pub fn new(extent: usize) -> Self {
+        DatagramStreamManager {
+            sessions: ::lib::core::array::from_fn(|_| None),
+            extent,
+            transfer_id_timeout: DEFAULT_TRANSFER_ID_TIMEOUT,
+            stats: SessionStats::default(),
+        }
+    }
+
+    /// Sets the transfer-ID timeout in microseconds
+    pub fn with_transfer_id_timeout(mut self, transfer_id_timeout: u64) -> Self {
+        self.transfer_id_timeout = transfer_id_timeout;
+        self
+    }
+
+    /// Ingest a frame with the (already parsed) `header` and `payload` received at `timestamp`, passing its payload to `on_chunk`
+    ///
+    /// Returns the `StreamEnd` when `frame` completes the transfer with a valid transfer CRC.
+    pub fn ingest<CB: FnMut(StreamChunk)>(&mut self, header: &UdpFrameHeader, payload: &[u8], timestamp: u64, on_chunk: CB) -> Result<Option<StreamEnd>, SessionError> {
+        let index = match self.sessions.iter().position(|s| s.as_ref().map_or(false, |s| {
+            s.source_node_id == header.source_node_id && s.data_specifier == header.data_specifier
+        })) {
+            Some(index) => index,
+            None => {
+                let index = self.allocate()?;
+                self.sessions[index] = Some(StreamSession {
+                    source_node_id: header.source_node_id,
+                    data_specifier: header.data_specifier,
+                    in_progress: None,
+                    last_transfer_id: None,
+                    last_activity: timestamp,
+                });
+                index
+            },
+        };
+
+        let session = self.sessions[index].as_mut().expect("The session was just found or allocated");
+        match session.accept(header, payload, timestamp, self.transfer_id_timeout, self.extent, on_chunk) {
+            Ok(Some(end)) => {
+                self.stats.transfers_completed += 1;
+                Ok(Some(end))
+            },
+            Ok(None) => Ok(None),
+            Err(error) => Err(self.stats.count_error(error)),
+        }
+    }
+
+    /// Returns the number of sessions currently allocated
+    pub fn active_sessions(&self) -> usize {
+        self.sessions.iter().filter(|s| s.is_some()).count()
+    }
+
+    /// Returns the statistics counters accumulated since the manager was constructed
+    pub fn stats(&self) -> SessionStats {
+        SessionStats {
+            active_sessions: self.active_sessions(),
+            ..self.stats
+        }
+    }
+
+    /// Returns the slot for a new session, evicting the least recently active session if all slots are busy
+    ///
+    /// Fails with `OutOfMemory` if the manager has no slots (`MAX_SESSIONS` is zero).
+    fn allocate(&mut self) -> Result<usize, SessionError> {
+        let index = match free_or_oldest_slot(&self.sessions) {
+            Some(index) => index,
+            None => return Err(self.stats.count_error(SessionError::OutOfMemory)),
+        };
+        if self.sessions[index].is_some() {
+            self.stats.evictions += 1;
+        }
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use session::*;
+
+    use udp::{
+        transfer_crc,
+        DataSpecifier,
+        UdpFrameHeader,
+        UNSET_NODE_ID,
+    };
+
+    fn header(transfer_id: u64, frame_index: u32, end_of_transfer: bool) -> UdpFrameHeader {
+        UdpFrameHeader {
+            priority: 4,
+            source_node_id: 42,
+            destination_node_id: UNSET_NODE_ID,
+            data_specifier: DataSpecifier::Response(408),
+            transfer_id,
+            frame_index,
+            end_of_transfer,
+            user_data: 0,
+        }
+    }
+
+    #[test]
+    fn chunks_are_delivered_as_frames_arrive() {
+        let mut manager: DatagramStreamManager<2> = DatagramStreamManager::new(6);
+        let mut data = [0u8; 12];
+        data[..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        let crc = transfer_crc(&data[..8]);
+        data[8..].copy_from_slice(&crc);
+
+        let mut received = [0u8; 8];
+        let mut received_length = 0;
+        {
+            let mut on_chunk = |chunk: StreamChunk| {
+                assert_eq!(chunk.offset, received_length);
+                received[chunk.offset..chunk.offset + chunk.data.len()].copy_from_slice(chunk.data);
+                received_length += chunk.data.len();
+            };
+            assert_eq!(manager.ingest(&header(7, 0, false), &data[0..5], 100, &mut on_chunk), Ok(None));
+            assert_eq!(manager.ingest(&header(7, 0, false), &data[0..5], 105, &mut on_chunk), Err(SessionError::Duplicate));
+            assert_eq!(manager.ingest(&header(7, 1, false), &data[5..10], 110, &mut on_chunk), Ok(None));
+            let end = manager.ingest(&header(7, 2, true), &data[10..12], 120, &mut on_chunk).unwrap().unwrap();
+            assert_eq!(end.length, 8);
+            assert_eq!(end.transfer_id, 7);
+            assert_eq!(end.timestamp, 100);
+        }
+        assert_eq!(received_length, 6);
+        assert_eq!(&received[..6], &[1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn lost_frame_aborts() {
+        let mut manager: DatagramStreamManager<2> = DatagramStreamManager::new(64);
+        let mut data = [0u8; 12];
+        data[..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        let crc = transfer_crc(&data[..8]);
+        data[8..].copy_from_slice(&crc);
+
+        assert_eq!(manager.ingest(&header(7, 0, false), &data[0..5], 100, |_| ()), Ok(None));
+        assert_eq!(manager.ingest(&header(7, 2, true), &data[10..12], 110, |_| ()), Err(SessionError::UnexpectedFrame));
+
+        data[0] ^= 0x01;
+        assert_eq!(manager.ingest(&header(8, 0, true), &data, 120, |_| ()), Err(SessionError::CRCError));
+        assert_eq!(manager.stats().crc_errors, 1);
+    }
+
+    #[test]
+    fn without_sessions() {
+        let mut manager: DatagramStreamManager<0> = DatagramStreamManager::new(64);
+        let data = transfer_crc(&[]);
+
+        assert_eq!(manager.ingest(&header(7, 0, true), &data, 100, |_| ()), Err(SessionError::OutOfMemory));
+        assert_eq!(manager.stats().out_of_memory_drops, 1);
+        assert_eq!(manager.stats().evictions, 0);
+    }
+}
@@ -0,0 +1,253 @@
+//! Splitting raw payloads into transfer frames
+//!
+//! This is the transmit side counterpart of the session managers: it works on payload bytes and doesn't need to know the data type.
+
+use lib::core::marker::PhantomData;
+
+use crc::{
+    ChecksumPlacement,
+    TransferCRC,
+    TransferChecksum,
+};
+
+use transfer::{
+    TransferFrame,
+    TransferFrameID,
+    TransferID,
+    TailByte,
+};
+
+/// An iterator over the frames of a transfer carrying a raw payload
+///
+/// Multi-frame transfers carry the transfer CRC `C`, seeded with the data type signature, where `C::PLACEMENT` puts it.
+/// The last frame is padded with zeros to a legal data length (see `TransferFrame::quantize_data_length`),
+/// the padding is included in the transfer CRC. A CRC after the payload follows the padding.
+#[derive(Debug)]
+pub struct PayloadFrames<'a, T, C = TransferCRC> {
+    id: TransferFrameID,
+    transfer_id: TransferID,
+    payload: &'a [u8],
+    mtu: usize,
+    crc: Option<[u8; 8]>,
+    padding_length: usize,
+    length: usize,
+    offset: usize,
+    started: bool,
+    toggle: bool,
+    finished: bool,
+    frame: PhantomData<(T, C)>,
+}
+
+impl<'a, T: TransferFrame, C: TransferChecksum> PayloadFrames<'a, T, C> {
+    /// Splits `payload` into frames of `T::MAX_DATA_LENGTH` bytes
+    pub fn new(id: TransferFrameID, transfer_id: TransferID, data_type_signature: u64, payload: &'a [u8]) -> Self {
+        Self::with_mtu(id, transfer_id, data_type_signature, payload, T::MAX_DATA_LENGTH)
+    }
+
+    /// Splits `payload` into frames using at most `mtu` bytes of the frame data (including the tail byte)
+    ///
+    /// `mtu` is limited to `T::MAX_DATA_LENGTH` and must be a legal data length for `T`.
+    ///
+    /// ## Panics
+    /// Panics if `mtu < C::LENGTH + 1`, as a frame of a multi frame transfer wouldn't fit the transfer CRC and tail byte.
+    pub fn with_mtu(id: TransferFrameID, transfer_id: TransferID, data_type_signature: u64, payload: &'a [u8], mtu: usize) -> Self {
+        assert!(mtu > C::LENGTH, "The MTU must fit the transfer CRC and the tail byte");
+        let mtu = ::lib::core::cmp::min(mtu, T::MAX_DATA_LENGTH);
+
+        let (crc, padding_length) = if payload.len() > mtu - 1 {
+            // The last frame carries the remaining bytes of the payload and the CRC after the full frames
+            let last_frame_length = (C::LENGTH + payload.len() - 1) % (mtu - 1) + 2;
+            let padding_length = T::quantize_data_length(last_frame_length) - last_frame_length;
+
+            let mut crc = C::from_signature(data_type_signature);
+            crc.add(payload);
+            for _ in 0..padding_length {
+                crc.add(&[0]);
+            }
+            let mut bytes = [0u8; 8];
+            crc.write_bytes(&mut bytes[..C::LENGTH]);
+            (Some(bytes), padding_length)
+        } else {
+            (None, 0)
+        };
+
+        // The padding in front of a CRC after the payload is sent as part of the transfer, otherwise it's added to the last frame
+        let length = match (crc, C::PLACEMENT) {
+            (None, _) => payload.len(),
+            (Some(_), ChecksumPlacement::FirstFrame) => C::LENGTH + payload.len(),
+            (Some(_), ChecksumPlacement::PayloadEnd) => payload.len() + padding_length + C::LENGTH,
+        };
+
+        PayloadFrames {
+            id,
+            transfer_id,
+            payload,
+            mtu,
+            crc,
+            padding_length,
+            length,
+            offset: 0,
+            started: false,
+            toggle: false,
+            finished: false,
+            frame: PhantomData,
+        }
+    }
+}
+
+impl<'a, T, C: TransferChecksum> PayloadFrames<'a, T, C> {
+    /// The byte at `index` of the transfer, made up of the payload and the CRC
+    fn byte(&self, index: usize) -> u8 {
+        let crc = match self.crc {
+            Some(ref crc) => crc,
+            None => return self.payload[index],
+        };
+        match C::PLACEMENT {
+            ChecksumPlacement::FirstFrame if index < C::LENGTH => crc[index],
+            ChecksumPlacement::FirstFrame => self.payload[index - C::LENGTH],
+            ChecksumPlacement::PayloadEnd if index < self.payload.len() => self.payload[index],
+            ChecksumPlacement::PayloadEnd if index < self.payload.len() + self.padding_length => 0,
+            ChecksumPlacement::PayloadEnd => crc[index - self.payload.len() - self.padding_length],
+        }
+    }
+}
+
+impl<'a, T: TransferFrame, C: TransferChecksum> Iterator for PayloadFrames<'a, T, C> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.finished {
+            return None;
+        }
+
+        let start_of_transfer = !self.started;
+        let mut frame = T::new(self.id);
+        frame.set_data_length(self.mtu);
+
+        let position = ::lib::core::cmp::min(self.mtu - 1, self.length - self.offset);
+        for (index, byte) in frame.data_as_mut()[..position].iter_mut().enumerate() {
+            *byte = self.byte(self.offset + index);
+        }
+        self.offset += position;
+
+        let end_of_transfer = self.offset == self.length;
+        let frame_length = if end_of_transfer {
+            let frame_length = T::quantize_data_length(position + 1);
+            frame.set_data_length(frame_length);
+            for byte in &mut frame.data_as_mut()[position..frame_length - 1] {
+                *byte = 0;
+            }
+            frame_length
+        } else {
+            self.mtu
+        };
+        frame.data_as_mut()[frame_length - 1] = TailByte::new(start_of_transfer, end_of_transfer, self.toggle, self.transfer_id).into();
+
+        self.started = true;
+        self.toggle = !self.toggle;
+        self.finished = end_of_transfer;
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use tests::CanFrame;
+
+    use crc::{
+        TransferCRC,
+        TransferCRC32C,
+        TransferChecksum,
+    };
+
+    use session::*;
+
+    use transfer::{
+        TransferFrame,
+        TransferFrameID,
+        TransferID,
+    };
+
+    #[test]
+    fn single_frame() {
+        let mut frames: PayloadFrames<CanFrame> = PayloadFrames::new(TransferFrameID::new(0x0001_5520), TransferID::new(3), 0, &[1, 2, 3]);
+
+        let frame = frames.next().unwrap();
+        assert_eq!(frame.data(), &[1, 2, 3, 0xc3]);
+        assert!(frames.next().is_none());
+    }
+
+    #[test]
+    fn multi_frame_round_trip() {
+        let payload = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let mut crc = TransferCRC::from_signature(0x1234_5678);
+        crc.add(&payload);
+        let mut crc_bytes = [0u8; 2];
+        crc.write_bytes(&mut crc_bytes);
+
+        let mut frames: PayloadFrames<CanFrame> = PayloadFrames::new(TransferFrameID::new(0x0001_5520), TransferID::new(3), 0x1234_5678, &payload);
+        let first = frames.next().unwrap();
+        let second = frames.next().unwrap();
+        assert_eq!(first.data(), &[crc_bytes[0], crc_bytes[1], 1, 2, 3, 4, 5, 0x83]);
+        assert_eq!(second.data(), &[6, 7, 8, 9, 10, 0x63]);
+        assert!(frames.next().is_none());
+
+        let mut manager: HeaplessSessionManager<4, 16> = HeaplessSessionManager::new();
+        manager.subscribe(Subscription {
+            port: Port::Message(0x0155),
+            extent: 16,
+            transfer_id_timeout: DEFAULT_TRANSFER_ID_TIMEOUT,
+            data_type_signature: Some(0x1234_5678),
+        }).unwrap();
+        assert_eq!(manager.ingest(&first, 100), Ok(None));
+        assert_eq!(manager.ingest(&second, 110).unwrap().unwrap().payload, &payload);
+
+        let mut frames: PayloadFrames<CanFrame> = PayloadFrames::new(TransferFrameID::new(0x0001_5520), TransferID::new(4), 0x1234_5678, &payload);
+        let first = frames.next().unwrap();
+        let mut corrupted = frames.next().unwrap();
+        corrupted.data[0] ^= 0x01;
+        assert_eq!(manager.ingest(&first, 120), Ok(None));
+        assert_eq!(manager.ingest(&corrupted, 130), Err(SessionError::CRCError));
+        assert_eq!(manager.stats().crc_errors, 1);
+    }
+
+    #[test]
+    fn crc_after_payload() {
+        let payload = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+        let mut crc = TransferCRC32C::new();
+        crc.add(&payload);
+        let mut crc_bytes = [0u8; 4];
+        crc.write_bytes(&mut crc_bytes);
+
+        // The CRC doesn't fit the second frame and spills over into a third one
+        let mut frames: PayloadFrames<CanFrame, TransferCRC32C> = PayloadFrames::new(TransferFrameID::new(0x0001_5520), TransferID::new(3), 0, &payload);
+        let first = frames.next().unwrap();
+        let second = frames.next().unwrap();
+        let third = frames.next().unwrap();
+        assert_eq!(first.data(), &[1, 2, 3, 4, 5, 6, 7, 0x83]);
+        assert_eq!(second.data(), &[8, 9, 10, 11, 12, crc_bytes[0], crc_bytes[1], 0x23]);
+        assert_eq!(third.data(), &[crc_bytes[2], crc_bytes[3], 0x43]);
+        assert!(frames.next().is_none());
+
+        let mut manager: HeaplessSessionManager<4, 16, 16, TransferCRC32C> = HeaplessSessionManager::new();
+        manager.subscribe(Subscription {
+            port: Port::Message(0x0155),
+            extent: 16,
+            transfer_id_timeout: DEFAULT_TRANSFER_ID_TIMEOUT,
+            data_type_signature: Some(0),
+        }).unwrap();
+        assert_eq!(manager.ingest(&first, 100), Ok(None));
+        assert_eq!(manager.ingest(&second, 110), Ok(None));
+        assert_eq!(manager.ingest(&third, 120).unwrap().unwrap().payload, &payload);
+
+        let mut frames: PayloadFrames<CanFrame, TransferCRC32C> = PayloadFrames::new(TransferFrameID::new(0x0001_5520), TransferID::new(4), 0, &payload);
+        let first = frames.next().unwrap();
+        let second = frames.next().unwrap();
+        let mut corrupted = frames.next().unwrap();
+        corrupted.data[1] ^= 0x01;
+        assert_eq!(manager.ingest(&first, 130), Ok(None));
+        assert_eq!(manager.ingest(&second, 140), Ok(None));
+        assert_eq!(manager.ingest(&corrupted, 150), Err(SessionError::CRCError));
+    }
+}
@@ -0,0 +1,164 @@
+//! Sharing a `TransferInterface` between interrupt handlers and the main loop
+//!
+//! This module is only available with the `critical-section` feature.
+
+use lib::core::cell::RefCell;
+
+use critical_section::Mutex;
+
+use transfer::TransferInterface;
+use transfer::TransportStats;
+use transfer::BusState;
+use transfer::IOError;
+
+/// A `TransferInterface` wrapper that makes every access happen inside a critical section.
+///
+/// `SharedInterface` is `Sync` as long as the wrapped interface is `Send`,
+/// which allows it to be placed in a `static` and used both from an interrupt handler (typically receiving)
+/// and from the main loop (typically transmitting) without any `unsafe` code in the application.
+///
+/// # Examples
+/// ```no_run
+/// # extern crate embedded_types;
+/// # extern crate uavcan;
+/// # use embedded_types::can::ExtendedDataFrame;
+/// # use uavcan::{NodeConfig, SimpleNode};
+/// # use uavcan::storage::Storage;
+/// # use uavcan::transfer::{IOError, TransferInterface};
+/// use uavcan::shared_interface::SharedInterface;
+///
+/// # struct MyCanInterface;
+/// # impl MyCanInterface {
+/// #     const fn new() -> Self { MyCanInterface }
+/// # }
+/// # impl TransferInterface for MyCanInterface {
+/// #     type Frame = ExtendedDataFrame;
+/// #     fn transmit(&self, _frame: &ExtendedDataFrame) -> Result<(), IOError> { Ok(()) }
+/// #     fn receive(&self) -> Option<ExtendedDataFrame> { None }
+/// # }
+/// static INTERFACE: SharedInterface<MyCanInterface> = SharedInterface::new(MyCanInterface::new());
+///
+/// # fn main() {}
+/// # fn start<S: Storage<ExtendedDataFrame>>() {
+/// let node: SimpleNode<_, _, S> = SimpleNode::new(&INTERFACE, NodeConfig::default());
+/// # }
+/// ```
+pub struct SharedInterface<I> {
+    interface: Mutex<RefCell<I>>,
+}
+
+impl<I> SharedInterface<I> {
+    /// Wraps `interface` in a `SharedInterface`
+    pub const fn new(interface: I) -> Self {
+        SharedInterface {
+            interface: Mutex::new(RefCell::new(interface)),
+        }
+    }
+
+    /// Runs `f` with exclusive access to the wrapped interface inside a critical section.
+    ///
+    /// This can be used for configuring the interface or accessing functionality not exposed by `TransferInterface`.
+    ///
+    /// ## Panics
+    /// Panics if called recursively from inside `f`.
+    pub fn lock<R, F: FnOnce(&mut I) -> R>(&self, f: F) -> R {
+        critical_section::with(|cs| f(&mut self.interface.borrow_ref_mut(cs)))
+    }
+
+    /// Consumes the wrapper and returns the wrapped interface
+    pub fn into_inner(self) -> I {
+        self.interface.into_inner().into_inner()
+    }
+}
+
+impl<I: TransferInterface> TransferInterface for SharedInterface<I> {
+    type Frame = I::Frame;
+
+    fn transmit(&self, frame: &Self::Frame) -> Result<(), IOError> {
+        critical_section::with(|cs| self.interface.borrow_ref(cs).transmit(frame))
+    }
+
+    fn transmit_single_shot(&self, frame: &Self::Frame) -> Result<(), IOError> {
+        critical_section::with(|cs| self.interface.borrow_ref(cs).transmit_single_shot(frame))
+    }
+
+    fn receive(&self) -> Option<Self::Frame> {
+        critical_section::with(|cs| self.interface.borrow_ref(cs).receive())
+    }
+
+    fn receive_into(&self, frames: &mut [Self::Frame]) -> usize {
+        critical_section::with(|cs| self.interface.borrow_ref(cs).receive_into(frames))
+    }
+
+    fn statistics(&self) -> TransportStats {
+        critical_section::with(|cs| self.interface.borrow_ref(cs).statistics())
+    }
+
+    fn bus_state(&self) -> BusState {
+        critical_section::with(|cs| self.interface.borrow_ref(cs).bus_state())
+    }
+
+    fn set_listen_only(&self, listen_only: bool) -> bool {
+        critical_section::with(|cs| self.interface.borrow_ref(cs).set_listen_only(listen_only))
+    }
+
+    fn sleep(&self) -> bool {
+        critical_section::with(|cs| self.interface.borrow_ref(cs).sleep())
+    }
+
+    fn wake(&self) {
+        critical_section::with(|cs| self.interface.borrow_ref(cs).wake())
+    }
+
+    fn is_sleeping(&self) -> bool {
+        critical_section::with(|cs| self.interface.borrow_ref(cs).is_sleeping())
+    }
+
+    fn mtu(&self) -> usize {
+        critical_section::with(|cs| self.interface.borrow_ref(cs).mtu())
+    }
+
+    fn on_subscribe(&self, type_id: u16) -> Result<(), IOError> {
+        critical_section::with(|cs| self.interface.borrow_ref(cs).on_subscribe(type_id))
+    }
+
+    fn on_unsubscribe(&self, type_id: u16) -> Result<(), IOError> {
+        critical_section::with(|cs| self.interface.borrow_ref(cs).on_unsubscribe(type_id))
+    }
+}
+
+#[cfg(all(test, feature="std"))]
+mod tests {
+
+    use std::thread;
+
+    use tests::{CanFrame, MockInterface};
+
+    use transfer::TransferInterface;
+    use transfer::BusState;
+    use shared_interface::*;
+
+    static INTERFACE: SharedInterface<MockInterface> = SharedInterface::new(MockInterface::new());
+
+    #[test]
+    fn shared_between_contexts() {
+        // The receiving context, like an interrupt handler, fills the queue of the shared interface
+        thread::spawn(|| {
+            for marker in 0..4 {
+                INTERFACE.lock(|interface| interface.push_rx(CanFrame::from_parts(0x100, &[marker])));
+            }
+        }).join().unwrap();
+
+        for marker in 0..4 {
+            assert_eq!(INTERFACE.receive(), Some(CanFrame::from_parts(0x100, &[marker])));
+        }
+        assert_eq!(INTERFACE.receive(), None);
+
+        INTERFACE.transmit(&CanFrame::from_parts(0x200, &[9])).unwrap();
+        INTERFACE.lock(|interface| {
+            assert_eq!(interface.tx.borrow_mut().pop_front(), Some(CanFrame::from_parts(0x200, &[9])));
+            interface.bus_state.set(BusState::ErrorPassive);
+        });
+        assert_eq!(INTERFACE.bus_state(), BusState::ErrorPassive);
+    }
+}
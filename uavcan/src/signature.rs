@@ -0,0 +1,286 @@
+//! Data type signatures of UAVCAN v0
+//!
+//! The DSDL signature of a data type is the CRC-64-WE of its normalized definition,
+//! the data type signature extends it with the data type signatures of the nested composite types.
+//! The data type signature seeds the transfer CRC of multi frame transfers (see `crc::TransferCRC::from_signature`),
+//! which is how legacy (DroneCAN) nodes reject transfers of data types with a different definition.
+
+const MASK: u64 = 0xffff_ffff_ffff_ffff;
+
+const POLY: u64 = 0x42f0_e1eb_a9ea_3693;
+
+/// The CRC-64-WE used for data type signatures
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Signature(u64);
+
+impl Signature {
+    pub fn new() -> Signature {
+        Signature(MASK)
+    }
+
+    /// Continues the calculation of a signature from its value
+    pub fn extend(signature: u64) -> Signature {
+        Signature(signature ^ MASK)
+    }
+
+    fn add_byte(&mut self, data: &u8) {
+        let Signature(ref mut value) = *self;
+
+        *value ^= u64::from(*data) << 56;
+
+        for _bit in 0..8 {
+            if (*value & 0x8000_0000_0000_0000) != 0 {
+                *value = (*value << 1) ^ POLY;
+            } else {
+                *value <<= 1;
+            }
+        }
+    }
+
+    pub fn add(&mut self, data: &[u8]) {
+        for b in data {
+            self.add_byte(b);
+        }
+    }
+
+    /// Returns the signature of the data added so far
+    pub fn value(&self) -> u64 {
+        let Signature(value) = *self;
+        value ^ MASK
+    }
+
+    fn add_decimal(&mut self, mut value: usize) {
+        let mut digits = [0u8; 20];
+        let mut start = digits.len();
+        loop {
+            start -= 1;
+            digits[start] = b'0' + (value % 10) as u8;
+            value /= 10;
+            if value == 0 {
+                break;
+            }
+        }
+        self.add(&digits[start..]);
+    }
+}
+
+impl Default for Signature {
+    fn default() -> Self {
+        Signature::new()
+    }
+}
+
+/// Computes the DSDL signature of `definition`, the DSDL source of the data type `full_name` (like `"uavcan.protocol.NodeStatus"`)
+///
+/// The definition is normalized while the signature is calculated. Comments, constants and directives other than `@union`
+/// are left out, primitive types get an explicit cast mode, composite types get their full name and
+/// dynamic arrays are bounded with `[<=N]`.
+///
+/// # Examples
+/// ```
+/// use uavcan::signature::dsdl_signature;
+///
+/// let definition = "
+/// uint40 MAGIC_NUMBER = 0xACCE551B1E # Magic number
+/// uint40 magic_number
+/// ---
+/// bool ok
+/// ";
+///
+/// assert_eq!(dsdl_signature("uavcan.protocol.RestartNode", definition), 0x569e05394a3017f0);
+/// ```
+pub fn dsdl_signature(full_name: &str, definition: &str) -> u64 {
+    let namespace = match full_name.rfind('.') {
+        Some(index) => &full_name[..index],
+        None => "",
+    };
+
+    // The normalized lines are separated by a single newline, without a trailing one
+    let mut signature = Signature::new();
+    signature.add(full_name.as_bytes());
+
+    for line in definition.lines() {
+        let line = match line.find('#') {
+            Some(index) => &line[..index],
+            None => line,
+        }.trim();
+
+        if line.is_empty() || is_constant(line) {
+            continue;
+        } else if line.starts_with('@') {
+            if line == "@union" {
+                signature.add(b"\n@union");
+            }
+            continue;
+        } else if line == "---" {
+            signature.add(b"\n---");
+            continue;
+        }
+
+        let mut tokens = line.split_whitespace();
+        let mut field_type = tokens.next().unwrap();
+        let mut cast_mode = "saturated";
+        if field_type == "saturated" || field_type == "truncated" {
+            cast_mode = field_type;
+            field_type = match tokens.next() {
+                Some(field_type) => field_type,
+                None => continue,
+            };
+        }
+
+        let (base_type, array) = match field_type.find('[') {
+            Some(index) => (&field_type[..index], &field_type[index..]),
+            None => (field_type, ""),
+        };
+
+        signature.add(b"\n");
+        if is_primitive(base_type) {
+            signature.add(cast_mode.as_bytes());
+            signature.add(b" ");
+            signature.add(base_type.as_bytes());
+        } else if base_type.starts_with("void") || base_type.contains('.') || namespace.is_empty() {
+            signature.add(base_type.as_bytes());
+        } else {
+            signature.add(namespace.as_bytes());
+            signature.add(b".");
+            signature.add(base_type.as_bytes());
+        }
+
+        // `[<N]` is normalized to `[<=N-1]`
+        if array.starts_with("[<") && !array.starts_with("[<=") {
+            let bound = array[2..array.len()-1].trim().parse::<usize>().unwrap_or(1);
+            signature.add(b"[<=");
+            signature.add_decimal(bound.saturating_sub(1));
+            signature.add(b"]");
+        } else {
+            signature.add(array.as_bytes());
+        }
+
+        if let Some(name) = tokens.next() {
+            signature.add(b" ");
+            signature.add(name.as_bytes());
+        }
+    }
+
+    signature.value()
+}
+
+/// Extends the DSDL signature of a data type into its data type signature
+///
+/// `nested` are the data type signatures of the composite types of the fields (or array elements),
+/// in the order of the fields and including repetitions.
+///
+/// # Examples
+/// ```
+/// use uavcan::signature::data_type_signature;
+///
+/// // uavcan.protocol.GetNodeInfo nests NodeStatus, SoftwareVersion and HardwareVersion
+/// let nested = [0x0f0868d0c1a7c6f1, 0xdd46fd376527fea1, 0x0ad5c4c933f4a0c4];
+/// assert_eq!(data_type_signature(0xa80dc8995053e685, &nested), 0xee468a8121c46a9e);
+/// ```
+pub fn data_type_signature(dsdl_signature: u64, nested: &[u64]) -> u64 {
+    let mut signature = Signature::extend(dsdl_signature);
+    for nested_signature in nested {
+        let value = signature.value();
+        signature.add(&nested_signature.to_le_bytes());
+        signature.add(&value.to_le_bytes());
+    }
+    signature.value()
+}
+
+/// Constants are the lines with an assignment outside of the array brackets
+fn is_constant(line: &str) -> bool {
+    let mut depth = 0;
+    for c in line.chars() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            '=' if depth == 0 => return true,
+            _ => (),
+        }
+    }
+    false
+}
+
+fn is_primitive(base_type: &str) -> bool {
+    base_type == "bool" || ["uint", "int", "float"].iter().any(|prefix| {
+        base_type.starts_with(prefix)
+            && base_type.len() > prefix.len()
+            && base_type[prefix.len()..].bytes().all(|b| b.is_ascii_digit())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use signature::*;
+    use crc::TransferCRC;
+
+    const NODE_STATUS: &str = "
+#
+# Abstract node status information.
+#
+
+uint16 MAX_BROADCASTING_PERIOD_MS = 1000
+uint32 uptime_sec
+
+uint2 HEALTH_OK = 0 # The node is functioning properly.
+uint2 health
+
+uint3 MODE_OPERATIONAL = 0
+uint3 mode
+
+uint3 sub_mode
+
+uint16 vendor_specific_status_code
+";
+
+    const GET_NODE_INFO: &str = "
+#
+# Full node info request.
+#
+
+---
+
+NodeStatus status
+SoftwareVersion software_version
+HardwareVersion hardware_version
+
+uint8[<81] name  # Human readable non-empty ASCII node name.
+";
+
+    #[test]
+    fn check_value() {
+        let mut signature = Signature::new();
+        signature.add(b"123456789");
+        assert_eq!(signature.value(), 0x62ec_59e3_f1a4_f00a);
+    }
+
+    #[test]
+    fn node_status() {
+        let dsdl = dsdl_signature("uavcan.protocol.NodeStatus", NODE_STATUS);
+        assert_eq!(dsdl, 0x0f0868d0c1a7c6f1);
+        assert_eq!(data_type_signature(dsdl, &[]), dsdl);
+    }
+
+    #[test]
+    fn nested_types_in_the_same_namespace() {
+        let dsdl = dsdl_signature("uavcan.protocol.GetNodeInfo", GET_NODE_INFO);
+        assert_eq!(dsdl, 0xa80dc8995053e685);
+        assert_eq!(data_type_signature(dsdl, &[0x0f0868d0c1a7c6f1, 0xdd46fd376527fea1, 0x0ad5c4c933f4a0c4]), 0xee468a8121c46a9e);
+    }
+
+    #[test]
+    fn truncated_cast_mode() {
+        let definition = "truncated uint56 previous_transmission_timestamp_usec";
+        assert_eq!(dsdl_signature("uavcan.protocol.GlobalTimeSync", definition), 0x20271116a793c2db);
+    }
+
+    #[test]
+    fn seeds_the_transfer_crc() {
+        let dsdl = dsdl_signature("uavcan.protocol.debug.LogMessage", "LogLevel level\nuint8[<=31] source\nuint8[<=90] text");
+        let signature = data_type_signature(dsdl, &[0x711bf141af572346]);
+        assert_eq!(signature, 0xd654a48e0c049d75);
+        assert_eq!(u16::from(TransferCRC::from_signature(signature)), 0x4570);
+    }
+}
@@ -5,6 +5,7 @@ use std::sync::{
 };
 
 use std::collections::BinaryHeap;
+use std::cmp::Reverse;
 
 use transfer::TransferFrame;
 use transfer::TransferFrameID;
@@ -28,15 +29,51 @@ struct SubscriberListEntry<F: TransferFrame> {
 }
 
 struct InterfaceListEntry<F: TransferFrame> {
-    storage: Weak<Mutex<BinaryHeap<Priority<F>>>>,
+    storage: Weak<Mutex<InterfaceQueue<F>>>,
+}
+
+/// A priority queue returning frames with equal ID in the order they were pushed
+///
+/// The frames of a multi frame transfer share ID and must be transmitted in order.
+struct InterfaceQueue<F: TransferFrame> {
+    heap: BinaryHeap<(Priority<F>, Reverse<u64>)>,
+    pushed: u64,
+}
+
+impl<F: TransferFrame> InterfaceQueue<F> {
+    fn new() -> Self {
+        InterfaceQueue {
+            heap: BinaryHeap::new(),
+            pushed: 0,
+        }
+    }
+
+    fn push(&mut self, frame: F) {
+        self.heap.push((Priority(frame), Reverse(self.pushed)));
+        self.pushed += 1;
+    }
+
+    fn pop(&mut self) -> Option<F> {
+        Some((self.heap.pop()?.0).0)
+    }
+
+    fn peek(&self) -> Option<&F> {
+        Some(&(self.heap.peek()?.0).0)
+    }
+
+    fn remove_newest(&mut self, count: usize) {
+        self.pushed = self.pushed.saturating_sub(count as u64);
+        let pushed = self.pushed;
+        self.heap.retain(|entry| (entry.1).0 < pushed);
+    }
 }
 
 pub struct HeapSubscriberStorage<F> {
     storage: Arc<Mutex<Vec<F>>>,
 }
 
-pub struct HeapInterfaceStorage<F> {
-    storage: Arc<Mutex < BinaryHeap < Priority < F > > > >,
+pub struct HeapInterfaceStorage<F: TransferFrame> {
+    storage: Arc<Mutex<InterfaceQueue<F>>>,
 }
 
 impl<F: TransferFrame + Clone> Storage<F> for HeapStorage<F> {
@@ -68,7 +105,7 @@ impl<F: TransferFrame + Clone> Storage<F> for HeapStorage<F> {
     }
 
     fn new_interface(&self) -> Self::InterfaceStorageHandle {
-        let storage = Arc::new(Mutex::new(BinaryHeap::new()));
+        let storage = Arc::new(Mutex::new(InterfaceQueue::new()));
 
         let interface_list_entry = InterfaceListEntry {
             storage: Arc::downgrade(&storage),
@@ -84,7 +121,7 @@ impl<F: TransferFrame + Clone> Storage<F> for HeapStorage<F> {
 
 
     fn insert_subscriber_queue(&self, frame: F) -> Result<(), StorageError> {
-        for storage in self.subscriber_list.lock().unwrap().iter().filter(|x| x.filter.is_match(frame.id())).filter_map(|x| x.storage.upgrade()) {
+        for storage in matching_subscribers(&self.subscriber_list.lock().unwrap(), frame.id()) {
             storage.lock().unwrap().push(frame.clone());
         }
         Ok(())
@@ -92,10 +129,21 @@ impl<F: TransferFrame + Clone> Storage<F> for HeapStorage<F> {
 
     fn insert_interface_queue(&self, frame: F) -> Result<(), StorageError> {
         for storage in self.interface_list.lock().unwrap().iter().filter_map(|x| x.storage.upgrade()) {
-            storage.lock().unwrap().push(Priority(frame.clone()));
+            storage.lock().unwrap().push(frame.clone());
         }
         Ok(())
     }
+
+    fn is_subscribed(&self, id: TransferFrameID) -> bool {
+        matching_subscribers(&self.subscriber_list.lock().unwrap(), id).next().is_some()
+    }
+}
+
+/// The queues of the live subscriptions accepting frames with this ID
+///
+/// Frames are only inserted into these queues, so unmatched frames are dropped whether or not the node filters them first.
+fn matching_subscribers<'a, F: TransferFrame>(list: &'a [SubscriberListEntry<F>], id: TransferFrameID) -> impl Iterator<Item=Arc<Mutex<Vec<F>>>> + 'a {
+    list.iter().filter(move |x| x.filter.is_match(id)).filter_map(|x| x.storage.upgrade())
 }
 
 impl<F: TransferFrame> SubscriberStorageHandle<F> for HeapSubscriberStorage<F> {
@@ -118,15 +166,42 @@ impl<F: TransferFrame> SubscriberStorageHandle<F> for HeapSubscriberStorage<F> {
 
 impl<F: TransferFrame> InterfaceStorageHandle<F> for HeapInterfaceStorage<F> {
     fn pop(&self) -> Option<F> {
-        Some(self.storage.lock().unwrap().pop()?.0)
+        self.storage.lock().unwrap().pop()
     }
 
     fn max_priority(&self) -> Option<TransferFrameID> {
-        Some(self.storage.lock().unwrap().peek()?.0.id())
+        Some(self.storage.lock().unwrap().peek()?.id())
     }
 
     fn push(&self, frame: F) -> Result<(), StorageError> {
-        self.storage.lock().unwrap().push(Priority(frame));
+        self.storage.lock().unwrap().push(frame);
         Ok(())
     }
-}
\ No newline at end of file
+
+    fn remove_newest(&self, count: usize) {
+        self.storage.lock().unwrap().remove_newest(count)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use storage::*;
+    use tests::CanFrame;
+
+    #[test]
+    fn equal_ids_in_order() {
+        let storage = HeapStorage::<CanFrame>::new();
+        let interface = storage.new_interface();
+
+        for &(id, marker) in &[(0x200, 0), (0x100, 1), (0x200, 2), (0x100, 3), (0x200, 4)] {
+            storage.insert_interface_queue(CanFrame::from_parts(id, &[marker])).unwrap();
+        }
+
+        for &(id, marker) in &[(0x100, 1), (0x100, 3), (0x200, 0), (0x200, 2), (0x200, 4)] {
+            assert_eq!(interface.pop(), Some(CanFrame::from_parts(id, &[marker])));
+        }
+        assert_eq!(interface.pop(), None);
+    }
+}
@@ -6,9 +6,15 @@
 #[cfg(feature="std")]
 pub mod heap_storage;
 
+pub mod tx_queue;
+pub mod tiered_tx_queue;
+
 #[cfg(feature="std")]
 pub use self::heap_storage::HeapStorage;
 
+pub use self::tx_queue::PriorityTxQueue;
+pub use self::tiered_tx_queue::TieredTxQueue;
+
 use transfer::TransferFrame;
 use transfer::TransferFrameID;
 use transfer::FullTransferID;
@@ -43,6 +49,14 @@ pub trait Storage<F: TransferFrame> {
     ///
     /// If there are multiple interface storage queues the frame will be added to all of them.
     fn insert_interface_queue(&self, frame: F) -> Result<(), StorageError>;
+
+    /// Returns true if at least one subscriber would accept a frame with this ID.
+    ///
+    /// Nodes built with the `software-acceptance-filter` feature use this to drop frames before they're inserted into the storage.
+    /// Storages keeping every frame must implement it for the filter to have any effect, the default implementation accepts every frame.
+    fn is_subscribed(&self, _id: TransferFrameID) -> bool {
+        true
+    }
 }
 
 pub trait SubscriberStorageHandle<F: TransferFrame> {
@@ -74,5 +88,11 @@ pub trait InterfaceStorageHandle<F: TransferFrame> {
     ///
     /// This is the same as calling `insert_interface_queue` on the `Storage` which this handle is associated with.
     fn push(&self, frame: F) -> Result<(), StorageError>;
+
+    /// Removes the `count` frames that were pushed last, or every frame if there are fewer.
+    ///
+    /// This is used for removing the frames of a transfer that couldn't be queued in full.
+    /// The order of the remaining frames must be preserved.
+    fn remove_newest(&self, count: usize);
 }
 
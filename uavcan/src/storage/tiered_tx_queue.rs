@@ -0,0 +1,215 @@
+//! A transmit queue with a separate queue per priority level
+//!
+//! Unlike `PriorityTxQueue`, where all frames share the same capacity, every priority level gets its own reserved space.
+//! A flood of low priority frames can then never prevent high priority frames from being queued.
+
+use heapless::Vec;
+
+use transfer::TransferFrame;
+use transfer::TransferFrameID;
+
+/// A transmit queue with `L` priority levels, each holding at most `N` frames
+///
+/// The 5-bit Uavcan priority of a frame (bits 24 to 28 of the `TransferFrameID`) is mapped evenly onto the `L` levels,
+/// level 0 is the highest priority. `L` must be at least 1, a queue without levels fails to compile. Frames are returned from the highest priority non-empty level first.
+/// Within a level they're returned by `TransferFrameID` like they would win arbitration, the lowest ID first,
+/// and frames with equal ID in the order they were pushed.
+///
+/// The depth of every level can be reduced below `N` at runtime. When a level is full, frames pushed to it are dropped
+/// and counted in the drop counter of that level.
+pub struct TieredTxQueue<F: TransferFrame, const L: usize, const N: usize> {
+    // Every level is sorted by descending ID, so the next frame is popped from the back
+    levels: [Vec<F, N>; L],
+    depths: [usize; L],
+    drops: [u64; L],
+}
+
+impl<F: TransferFrame, const L: usize, const N: usize> TieredTxQueue<F, L, N> {
+    // Evaluated when `new` is instantiated, so `L == 0` is rejected at compile time
+    const HAS_LEVELS: () = assert!(L > 0, "A TieredTxQueue needs at least one priority level");
+
+    /// Constructs a new empty `TieredTxQueue` where every level has depth `N`
+    pub fn new() -> Self {
+        let () = Self::HAS_LEVELS;
+        TieredTxQueue {
+            levels: [(); L].map(|_| Vec::new()),
+            depths: [N; L],
+            drops: [0; L],
+        }
+    }
+
+    /// Constructs a new empty `TieredTxQueue` with the given depth for every level.
+    ///
+    /// Depths larger than `N` are limited to `N`.
+    pub fn with_depths(depths: [usize; L]) -> Self {
+        let mut queue = Self::new();
+        for (level, &depth) in depths.iter().enumerate() {
+            queue.set_depth(level, depth);
+        }
+        queue
+    }
+
+    /// Sets the depth of `level`, limited to `N`.
+    ///
+    /// Frames already queued above the new depth are kept.
+    ///
+    /// ## Panics
+    /// Panics if `level >= L`
+    pub fn set_depth(&mut self, level: usize, depth: usize) {
+        self.depths[level] = ::lib::core::cmp::min(depth, N);
+    }
+
+    /// Returns the depth of `level`
+    pub fn depth(&self, level: usize) -> usize {
+        self.depths[level]
+    }
+
+    /// Returns the priority level a frame with `id` is queued at
+    pub fn level_of(id: TransferFrameID) -> usize {
+        ((u32::from(id) >> 24) & 0x1f) as usize * L / 32
+    }
+
+    /// Push a frame on its priority level.
+    ///
+    /// If the level is full, the frame is counted as dropped and handed back as `Err(frame)`.
+    pub fn push(&mut self, frame: F) -> Result<(), F> {
+        let level = Self::level_of(frame.id());
+
+        if self.levels[level].len() >= self.depths[level] {
+            self.drops[level] += 1;
+            return Err(frame);
+        }
+
+        // Frames with equal ID are placed in front of the frame, since they were pushed before it
+        let index = self.levels[level].iter().position(|queued| queued.id() <= frame.id()).unwrap_or(self.levels[level].len());
+        self.levels[level].insert(index, frame)
+    }
+
+    /// Returns a reference to the frame that will be returned by the next call to `pop`, or `None` if the queue is empty.
+    pub fn peek(&self) -> Option<&F> {
+        self.levels.iter().filter_map(|level| level.last()).next()
+    }
+
+    /// Removes the lowest ID frame from the highest priority non-empty level and returns it, or `None` if the queue is empty.
+    pub fn pop(&mut self) -> Option<F> {
+        self.levels.iter_mut().filter_map(|level| level.pop()).next()
+    }
+
+    /// Returns the number of frames dropped because `level` was full
+    pub fn drop_count(&self, level: usize) -> u64 {
+        self.drops[level]
+    }
+
+    /// Returns the number of frames dropped at all levels
+    pub fn total_drop_count(&self) -> u64 {
+        self.drops.iter().sum()
+    }
+
+    /// Returns the number of frames queued at `level`
+    pub fn level_len(&self, level: usize) -> usize {
+        self.levels[level].len()
+    }
+
+    /// Returns the number of frames in the queue
+    pub fn len(&self) -> usize {
+        self.levels.iter().map(|level| level.len()).sum()
+    }
+
+    /// Returns true if there are no frames in the queue
+    pub fn is_empty(&self) -> bool {
+        self.levels.iter().all(|level| level.is_empty())
+    }
+}
+
+impl<F: TransferFrame, const L: usize, const N: usize> Default for TieredTxQueue<F, L, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use tests::CanFrame;
+
+    use transfer::TransferFrameID;
+
+    use storage::tiered_tx_queue::*;
+
+    #[test]
+    fn priority_levels() {
+        let mut queue = TieredTxQueue::<CanFrame, 4, 4>::new();
+
+        assert_eq!(TieredTxQueue::<CanFrame, 4, 4>::level_of(TransferFrameID::new(0)), 0);
+        assert_eq!(TieredTxQueue::<CanFrame, 4, 4>::level_of(TransferFrameID::new(31 << 24)), 3);
+
+        queue.push(CanFrame::from_parts(31 << 24 | 0, &[0])).unwrap();
+        queue.push(CanFrame::from_parts(16 << 24 | 1, &[1])).unwrap();
+        queue.push(CanFrame::from_parts(31 << 24 | 2, &[2])).unwrap();
+        queue.push(CanFrame::from_parts(0 << 24 | 3, &[3])).unwrap();
+
+        assert_eq!(queue.len(), 4);
+        assert_eq!(queue.peek(), Some(&CanFrame::from_parts(0 << 24 | 3, &[3])));
+        assert_eq!(queue.pop(), Some(CanFrame::from_parts(0 << 24 | 3, &[3])));
+        assert_eq!(queue.pop(), Some(CanFrame::from_parts(16 << 24 | 1, &[1])));
+        assert_eq!(queue.pop(), Some(CanFrame::from_parts(31 << 24 | 0, &[0])));
+        assert_eq!(queue.pop(), Some(CanFrame::from_parts(31 << 24 | 2, &[2])));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn level_mapping_edges() {
+        // A single level takes every priority
+        assert_eq!(TieredTxQueue::<CanFrame, 1, 4>::level_of(TransferFrameID::new(0)), 0);
+        assert_eq!(TieredTxQueue::<CanFrame, 1, 4>::level_of(TransferFrameID::new(31 << 24 | 0xffffff)), 0);
+
+        // One level per priority
+        for priority in 0..32 {
+            assert_eq!(TieredTxQueue::<CanFrame, 32, 4>::level_of(TransferFrameID::new(priority << 24 | 0xffffff)), priority as usize);
+        }
+
+        let mut queue = TieredTxQueue::<CanFrame, 32, 1>::new();
+        queue.push(CanFrame::from_parts(31 << 24 | 0, &[0])).unwrap();
+        queue.push(CanFrame::from_parts(0 << 24 | 1, &[1])).unwrap();
+        assert_eq!(queue.level_len(31), 1);
+        assert_eq!(queue.level_len(0), 1);
+        assert_eq!(queue.pop(), Some(CanFrame::from_parts(0 << 24 | 1, &[1])));
+        assert_eq!(queue.pop(), Some(CanFrame::from_parts(31 << 24 | 0, &[0])));
+    }
+
+    #[test]
+    fn flood_does_not_starve_high_priority() {
+        let mut queue = TieredTxQueue::<CanFrame, 2, 4>::with_depths([1, 2]);
+
+        queue.push(CanFrame::from_parts(31 << 24 | 0, &[0])).unwrap();
+        queue.push(CanFrame::from_parts(31 << 24 | 1, &[1])).unwrap();
+        assert_eq!(queue.push(CanFrame::from_parts(31 << 24 | 2, &[2])), Err(CanFrame::from_parts(31 << 24 | 2, &[2])));
+        assert_eq!(queue.push(CanFrame::from_parts(31 << 24 | 3, &[3])), Err(CanFrame::from_parts(31 << 24 | 3, &[3])));
+
+        queue.push(CanFrame::from_parts(0 << 24 | 4, &[4])).unwrap();
+        assert_eq!(queue.push(CanFrame::from_parts(0 << 24 | 5, &[5])), Err(CanFrame::from_parts(0 << 24 | 5, &[5])));
+
+        assert_eq!(queue.drop_count(0), 1);
+        assert_eq!(queue.drop_count(1), 2);
+        assert_eq!(queue.total_drop_count(), 3);
+        assert_eq!(queue.pop(), Some(CanFrame::from_parts(0 << 24 | 4, &[4])));
+    }
+
+    #[test]
+    fn sorted_within_level() {
+        let mut queue = TieredTxQueue::<CanFrame, 2, 4>::new();
+
+        queue.push(CanFrame::from_parts(31 << 24 | 0x300, &[0])).unwrap();
+        queue.push(CanFrame::from_parts(31 << 24 | 0x100, &[1])).unwrap();
+        queue.push(CanFrame::from_parts(31 << 24 | 0x200, &[2])).unwrap();
+        queue.push(CanFrame::from_parts(31 << 24 | 0x100, &[3])).unwrap();
+
+        assert_eq!(queue.peek(), Some(&CanFrame::from_parts(31 << 24 | 0x100, &[1])));
+        assert_eq!(queue.pop(), Some(CanFrame::from_parts(31 << 24 | 0x100, &[1])));
+        assert_eq!(queue.pop(), Some(CanFrame::from_parts(31 << 24 | 0x100, &[3])));
+        assert_eq!(queue.pop(), Some(CanFrame::from_parts(31 << 24 | 0x200, &[2])));
+        assert_eq!(queue.pop(), Some(CanFrame::from_parts(31 << 24 | 0x300, &[0])));
+        assert_eq!(queue.pop(), None);
+    }
+}
@@ -0,0 +1,212 @@
+//! A fixed capacity transmit queue that can be embedded in `TransferInterface` implementations
+//!
+//! The queue doesn't require an allocator and is suitable for bare metal interfaces.
+
+use heapless::Vec;
+
+use transfer::TransferFrame;
+
+/// A fixed capacity priority queue for `TransferFrame`s
+///
+/// Frames are returned in the order `TransferInterface` requires from its transmit buffer.
+/// The frame with the lowest `TransferFrameID` (highest priority) is returned first,
+/// while frames with equal `TransferFrameID` are returned in the same order as they were pushed.
+///
+/// Frames can optionally be given a transmission deadline of type `I`.
+/// Frames that are still queued when their deadline has passed are dropped by `pop_before`
+/// instead of being transmitted, avoiding transmission of stale data after bus congestion.
+pub struct PriorityTxQueue<F: TransferFrame, const N: usize, I = ()> {
+    entries: Vec<Entry<F, I>, N>,
+    sequence: u64,
+    expired: usize,
+}
+
+struct Entry<F, I> {
+    sequence: u64,
+    deadline: Option<I>,
+    frame: F,
+}
+
+impl<F: TransferFrame, const N: usize, I> PriorityTxQueue<F, N, I> {
+    /// Constructs a new empty `PriorityTxQueue`
+    pub fn new() -> Self {
+        PriorityTxQueue {
+            entries: Vec::new(),
+            sequence: 0,
+            expired: 0,
+        }
+    }
+
+    /// Push a frame without a deadline on the queue.
+    ///
+    /// If the queue is full the frame is handed back as `Err(frame)`.
+    pub fn push(&mut self, frame: F) -> Result<(), F> {
+        self.push_entry(frame, None)
+    }
+
+    /// Push a frame on the queue that must be transmitted before `deadline`.
+    ///
+    /// All frames of a multi-frame transfer should be given the same deadline.
+    /// If the queue is full the frame is handed back as `Err(frame)`.
+    pub fn push_with_deadline(&mut self, frame: F, deadline: I) -> Result<(), F> {
+        self.push_entry(frame, Some(deadline))
+    }
+
+    fn push_entry(&mut self, frame: F, deadline: Option<I>) -> Result<(), F> {
+        let entry = Entry {
+            sequence: self.sequence,
+            deadline,
+            frame,
+        };
+
+        match self.entries.push(entry) {
+            Ok(()) => {
+                self.sequence += 1;
+                Ok(())
+            },
+            Err(entry) => Err(entry.frame),
+        }
+    }
+
+    /// Returns a reference to the frame that will be returned by the next call to `pop`, or `None` if the queue is empty.
+    pub fn peek(&self) -> Option<&F> {
+        Some(&self.entries[self.next_index()?].frame)
+    }
+
+    /// Removes the frame with the highest priority from the queue and returns it, or `None` if the queue is empty.
+    pub fn pop(&mut self) -> Option<F> {
+        let index = self.next_index()?;
+        let entry = self.entries.swap_remove(index);
+
+        if self.entries.is_empty() {
+            self.sequence = 0;
+        }
+
+        Some(entry.frame)
+    }
+
+    /// Drops all frames with an expired deadline before removing the frame with the highest priority from the queue.
+    ///
+    /// A frame is expired if its deadline is before `now`.
+    /// Returns `None` if there are no unexpired frames left in the queue.
+    pub fn pop_before(&mut self, now: I) -> Option<F> where I: PartialOrd {
+        self.drop_expired(now);
+        self.pop()
+    }
+
+    /// Drops all frames with a deadline before `now` and returns the number of dropped frames.
+    pub fn drop_expired(&mut self, now: I) -> usize where I: PartialOrd {
+        let len_before = self.entries.len();
+        self.entries.retain(|entry| match entry.deadline {
+            Some(ref deadline) => *deadline >= now,
+            None => true,
+        });
+
+        let dropped = len_before - self.entries.len();
+        self.expired += dropped;
+        dropped
+    }
+
+    /// Returns the total number of frames that has been dropped because their deadline expired
+    pub fn expired_count(&self) -> usize {
+        self.expired
+    }
+
+    /// Returns the number of frames in the queue
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if there are no frames in the queue
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns true if no more frames can be pushed on the queue
+    pub fn is_full(&self) -> bool {
+        self.entries.is_full()
+    }
+
+    /// Returns the maximum number of frames the queue can hold
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    fn next_index(&self) -> Option<usize> {
+        self.entries.iter()
+            .enumerate()
+            .min_by_key(|&(_, entry)| (entry.frame.id(), entry.sequence))
+            .map(|(index, _)| index)
+    }
+}
+
+impl<F: TransferFrame, const N: usize, I> Default for PriorityTxQueue<F, N, I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use tests::CanFrame;
+
+    use storage::tx_queue::*;
+
+    #[test]
+    fn priority_order() {
+        let mut queue = PriorityTxQueue::<CanFrame, 4>::new();
+
+        queue.push(CanFrame::from_parts(3, &[0])).unwrap();
+        queue.push(CanFrame::from_parts(1, &[1])).unwrap();
+        queue.push(CanFrame::from_parts(2, &[2])).unwrap();
+
+        assert_eq!(queue.pop(), Some(CanFrame::from_parts(1, &[1])));
+        assert_eq!(queue.pop(), Some(CanFrame::from_parts(2, &[2])));
+        assert_eq!(queue.pop(), Some(CanFrame::from_parts(3, &[0])));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn fifo_for_equal_id() {
+        let mut queue = PriorityTxQueue::<CanFrame, 4>::new();
+
+        queue.push(CanFrame::from_parts(5, &[0])).unwrap();
+        queue.push(CanFrame::from_parts(5, &[1])).unwrap();
+        queue.push(CanFrame::from_parts(4, &[2])).unwrap();
+        queue.push(CanFrame::from_parts(5, &[3])).unwrap();
+
+        assert_eq!(queue.peek(), Some(&CanFrame::from_parts(4, &[2])));
+        assert_eq!(queue.pop(), Some(CanFrame::from_parts(4, &[2])));
+        assert_eq!(queue.pop(), Some(CanFrame::from_parts(5, &[0])));
+        assert_eq!(queue.pop(), Some(CanFrame::from_parts(5, &[1])));
+        assert_eq!(queue.pop(), Some(CanFrame::from_parts(5, &[3])));
+    }
+
+    #[test]
+    fn full_queue_returns_frame() {
+        let mut queue = PriorityTxQueue::<CanFrame, 2>::new();
+
+        queue.push(CanFrame::from_parts(1, &[0])).unwrap();
+        queue.push(CanFrame::from_parts(2, &[1])).unwrap();
+        assert!(queue.is_full());
+        assert_eq!(queue.push(CanFrame::from_parts(0, &[2])), Err(CanFrame::from_parts(0, &[2])));
+        assert_eq!(queue.len(), 2);
+    }
+
+    #[test]
+    fn expired_frames_are_dropped() {
+        let mut queue = PriorityTxQueue::<CanFrame, 4, u32>::new();
+
+        queue.push_with_deadline(CanFrame::from_parts(1, &[0]), 10).unwrap();
+        queue.push_with_deadline(CanFrame::from_parts(2, &[1]), 20).unwrap();
+        queue.push(CanFrame::from_parts(3, &[2])).unwrap();
+
+        assert_eq!(queue.pop_before(15), Some(CanFrame::from_parts(2, &[1])));
+        assert_eq!(queue.expired_count(), 1);
+        assert_eq!(queue.pop_before(1000), Some(CanFrame::from_parts(3, &[2])));
+        assert_eq!(queue.pop_before(1000), None);
+        assert_eq!(queue.expired_count(), 1);
+    }
+}
@@ -0,0 +1,412 @@
+//! Property testing of serialization and transfers
+//!
+//! The field types of the crate implement `ArbitraryField`, and so do structures deriving `UavcanStruct`
+//! with `#[uavcan(arbitrary)]` (which must implement `Debug`, like with `#[uavcan(debug)]`).
+//! The derived structures, and the field types defined in this crate, implement proptest's `Arbitrary` as well.
+//! The integers of arbitrary bit lengths come from the `ux` crate and can't implement `Arbitrary`, use `any_field::<u5>()` for them.
+//! Integers of arbitrary bit lengths take every value they can hold, dynamic arrays and strings every length up to their maximum length.
+//! `f16` only takes finite values, the native floats take every value including NaN.
+//!
+//! `check_round_trip` sends a value through a transfer split into frames and checks it comes back the same.
+//!
+//! ## Example
+//! ```
+//! #[macro_use]
+//! extern crate uavcan;
+//! #[macro_use]
+//! extern crate proptest;
+//!
+//! use proptest::prelude::*;
+//! use uavcan::types::*;
+//! use uavcan::testing::{any_field, check_round_trip};
+//!
+//! #[derive(Clone, UavcanStruct)]
+//! #[uavcan(debug, arbitrary)]
+//! struct Reading {
+//!     sensor: u5,
+//!     value: f16,
+//!     text: Utf8String<[u8; 32]>,
+//! }
+//!
+//! proptest! {
+//!     #[test]
+//!     fn reading_round_trip(reading in any::<Reading>(), mtu in uavcan::testing::mtu()) {
+//!         check_round_trip(&reading, mtu)?;
+//!     }
+//!
+//!     #[test]
+//!     fn sensor_fits(sensor in any_field::<u5>()) {
+//!         prop_assert!(u8::from(sensor) < 32);
+//!     }
+//! }
+//! # fn main() {}
+//! ```
+
+use lib::core::fmt;
+
+use proptest::prelude::*;
+use proptest::collection;
+use proptest::test_runner::TestCaseError;
+
+use {
+    Frame,
+    Struct,
+};
+
+use transfer::{
+    TransferFrame,
+    TransferFrameID,
+    TransferID,
+};
+
+use serializer::{
+    SerializationBuffer,
+    Serializer,
+};
+
+use frame_assembler::FrameAssembler;
+
+use frame_disassembler::FrameDisassembler;
+
+use types::*;
+
+/// The strategy for every value of a field type, for structures deriving `UavcanStruct` with `#[uavcan(arbitrary)]`
+///
+/// Takes the place of proptest's `Arbitrary`, which can't be implemented for the integer types of the `ux` crate.
+pub trait ArbitraryField: fmt::Debug + Sized {
+    /// Every value of the field type
+    fn strategy() -> BoxedStrategy<Self>;
+}
+
+/// Every value of the field type `T`, like `any::<T>()` but also for the integers of arbitrary bit lengths
+pub fn any_field<T: ArbitraryField>() -> BoxedStrategy<T> {
+    T::strategy()
+}
+
+/// The largest MTU `check_round_trip` supports, the data length of a CAN FD frame
+pub const MAX_MTU: usize = 64;
+
+/// The MTUs of the transports, from the smallest one fitting the transfer CRC and tail byte to CAN FD
+pub fn mtu() -> BoxedStrategy<usize> {
+    (3..=MAX_MTU).boxed()
+}
+
+/// Sends `value` through a transfer split into frames of at most `mtu` bytes, and reassembles it
+///
+/// Fails if the frames can't be reassembled, or the reassembled value serializes to other bytes than `value`.
+/// Comparing the serialized values keeps NaN floats and saturated native fields from failing the check.
+///
+/// ## Panics
+/// Panics if `mtu` is not in `3..=MAX_MTU`.
+pub fn check_round_trip<T: Struct + Clone + fmt::Debug>(value: &T, mtu: usize) -> Result<(), TestCaseError> {
+    assert!(mtu <= MAX_MTU, "The MTU can't exceed MAX_MTU");
+    let mut disassembler = FrameDisassembler::from_uavcan_frame(Frame::from_parts(TransferFrameID::new(0), value.clone()), TransferID::new(0));
+    let mut assembler = FrameAssembler::<T>::new();
+
+    let mut frames = 0;
+    while let Some(frame) = disassembler.next_transfer_frame_with_mtu::<TestFrame>(mtu) {
+        frames += 1;
+        if let Err(error) = assembler.add_transfer_frame(frame) {
+            return Err(TestCaseError::fail(format!("frame {} was rejected with {:?}", frames, error)));
+        }
+    }
+
+    let (_, reassembled) = match assembler.build() {
+        Ok(frame) => frame.into_parts(),
+        Err(error) => return Err(TestCaseError::fail(format!("the transfer was rejected with {:?}", error))),
+    };
+    prop_assert_eq!(serialize(&reassembled), serialize(value), "{:?} was reassembled as {:?}", value, reassembled);
+    Ok(())
+}
+
+/// Serializes `value` like a whole transfer payload
+fn serialize<T: Struct + Clone>(value: &T) -> Vec<u8> {
+    let mut data = vec![0u8; T::MAX_BYTES];
+    let length = {
+        let mut serializer = Serializer::from_structure(value.clone());
+        let mut buffer = SerializationBuffer::with_empty_buffer(&mut data);
+        serializer.serialize(&mut buffer);
+        (buffer.bit_length() + 7) / 8
+    };
+    data.truncate(length);
+    data
+}
+
+/// A frame of any data length up to `MAX_MTU`
+struct TestFrame {
+    id: TransferFrameID,
+    length: usize,
+    data: [u8; MAX_MTU],
+}
+
+impl TransferFrame for TestFrame {
+    const MAX_DATA_LENGTH: usize = MAX_MTU;
+
+    fn new(id: TransferFrameID) -> Self {
+        TestFrame{id: id, length: 0, data: [0; MAX_MTU]}
+    }
+
+    fn set_data_length(&mut self, length: usize) {
+        assert!(length <= MAX_MTU);
+        self.length = length;
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.data[..self.length]
+    }
+
+    fn data_as_mut(&mut self) -> &mut [u8] {
+        &mut self.data[..self.length]
+    }
+
+    fn id(&self) -> TransferFrameID {
+        self.id
+    }
+}
+
+/// Implements `ArbitraryField` for types implementing `Arbitrary`
+macro_rules! impl_arbitrary_field {
+    ($($type:ty),*) => {$(
+        impl ArbitraryField for $type {
+            fn strategy() -> BoxedStrategy<Self> {
+                any::<Self>().boxed()
+            }
+        }
+    )*};
+}
+
+impl_arbitrary_field!(bool, u8, u16, u32, u64, i8, i16, i32, i64, f32, f64, f16);
+
+macro_rules! impl_ux {
+    {[$(($type:ident, $bits:expr)),*], $underlying_type:ident} => {$(
+        impl ArbitraryField for $type {
+            fn strategy() -> BoxedStrategy<Self> {
+                (0..=$underlying_type::MAX >> ($underlying_type::BITS - $bits)).prop_map($type::new).boxed()
+            }
+        }
+    )*};
+}
+
+impl_ux!([(u2, 2), (u3, 3), (u4, 4), (u5, 5), (u6, 6), (u7, 7)], u8);
+
+impl_ux!([(u9, 9), (u10, 10), (u11, 11), (u12, 12), (u13, 13), (u14, 14), (u15, 15)], u16);
+
+impl_ux!([(u17, 17), (u18, 18), (u19, 19), (u20, 20), (u21, 21), (u22, 22), (u23, 23), (u24, 24),
+          (u25, 25), (u26, 26), (u27, 27), (u28, 28), (u29, 29), (u30, 30), (u31, 31)], u32);
+
+impl_ux!([(u33, 33), (u34, 34), (u35, 35), (u36, 36), (u37, 37), (u38, 38), (u39, 39), (u40, 40),
+          (u41, 41), (u42, 42), (u43, 43), (u44, 44), (u45, 45), (u46, 46), (u47, 47), (u48, 48),
+          (u49, 49), (u50, 50), (u51, 51), (u52, 52), (u53, 53), (u54, 54), (u55, 55), (u56, 56),
+          (u57, 57), (u58, 58), (u59, 59), (u60, 60), (u61, 61), (u62, 62), (u63, 63)], u64);
+
+macro_rules! impl_ix {
+    {[$(($type:ident, $bits:expr)),*], $underlying_type:ident} => {$(
+        impl ArbitraryField for $type {
+            fn strategy() -> BoxedStrategy<Self> {
+                let max = $underlying_type::MAX >> ($underlying_type::BITS - $bits);
+                (-max - 1..=max).prop_map($type::new).boxed()
+            }
+        }
+    )*};
+}
+
+impl_ix!([(i2, 2), (i3, 3), (i4, 4), (i5, 5), (i6, 6), (i7, 7)], i8);
+
+impl_ix!([(i9, 9), (i10, 10), (i11, 11), (i12, 12), (i13, 13), (i14, 14), (i15, 15)], i16);
+
+impl_ix!([(i17, 17), (i18, 18), (i19, 19), (i20, 20), (i21, 21), (i22, 22), (i23, 23), (i24, 24),
+          (i25, 25), (i26, 26), (i27, 27), (i28, 28), (i29, 29), (i30, 30), (i31, 31)], i32);
+
+impl_ix!([(i33, 33), (i34, 34), (i35, 35), (i36, 36), (i37, 37), (i38, 38), (i39, 39), (i40, 40),
+          (i41, 41), (i42, 42), (i43, 43), (i44, 44), (i45, 45), (i46, 46), (i47, 47), (i48, 48),
+          (i49, 49), (i50, 50), (i51, 51), (i52, 52), (i53, 53), (i54, 54), (i55, 55), (i56, 56),
+          (i57, 57), (i58, 58), (i59, 59), (i60, 60), (i61, 61), (i62, 62), (i63, 63)], i64);
+
+impl<const BITS: usize> ArbitraryField for Uint<BITS> {
+    fn strategy() -> BoxedStrategy<Self> {
+        (0..=Self::MAX).prop_map(Uint::new).boxed()
+    }
+}
+
+impl<const BITS: usize> ArbitraryField for Int<BITS> {
+    fn strategy() -> BoxedStrategy<Self> {
+        (Self::MIN..=Self::MAX).prop_map(Int::new).boxed()
+    }
+}
+
+macro_rules! impl_vx {
+    ($($type:ident),*) => {$(
+        impl Arbitrary for $type {
+            type Parameters = ();
+            type Strategy = Just<Self>;
+
+            fn arbitrary_with(_: ()) -> Self::Strategy {
+                Just($type{})
+            }
+        }
+
+        impl_arbitrary_field!($type);
+    )*};
+}
+
+impl_vx!(void1, void2, void3, void4, void5, void6, void7, void8, void9, void10, void11, void12, void13, void14, void15, void16,
+         void17, void18, void19, void20, void21, void22, void23, void24, void25, void26, void27, void28, void29, void30, void31, void32,
+         void33, void34, void35, void36, void37, void38, void39, void40, void41, void42, void43, void44, void45, void46, void47, void48,
+         void49, void50, void51, void52, void53, void54, void55, void56, void57, void58, void59, void60, void61, void62, void63, void64);
+
+impl Arbitrary for f16 {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (-65504.0f32..=65504.0).prop_map(f16::from_f32).boxed()
+    }
+}
+
+impl<T: ArbitraryField + 'static> Arbitrary for Truncated<T> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        T::strategy().prop_map(Truncated).boxed()
+    }
+}
+
+impl<T: ArbitraryField + 'static> Arbitrary for Delimited<T> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        T::strategy().prop_map(Delimited::new).boxed()
+    }
+}
+
+impl<T: ArbitraryField + 'static, S: 'static> Arbitrary for Scaled<T, S> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        T::strategy().prop_map(Scaled::from_raw).boxed()
+    }
+}
+
+impl<T: BoolArray + 'static> Arbitrary for BitArray<T> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        collection::vec(any::<bool>(), T::LENGTH).prop_map(|bits| {
+            let mut array = BitArray::new();
+            for (index, bit) in bits.into_iter().enumerate() {
+                array.set(index, bit);
+            }
+            array
+        }).boxed()
+    }
+}
+
+impl<T: BoolArray + 'static> Arbitrary for DynamicBitArray<T> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        collection::vec(any::<bool>(), 0..=T::LENGTH).prop_map(|bits| DynamicBitArray::with_data(&bits)).boxed()
+    }
+}
+
+impl<T: ArbitraryField + 'static> ArbitraryField for Truncated<T> {
+    fn strategy() -> BoxedStrategy<Self> {
+        any::<Self>()
+    }
+}
+
+impl<T: ArbitraryField + 'static> ArbitraryField for Delimited<T> {
+    fn strategy() -> BoxedStrategy<Self> {
+        any::<Self>()
+    }
+}
+
+impl<T: ArbitraryField + 'static, S: 'static> ArbitraryField for Scaled<T, S> where Self: fmt::Debug {
+    fn strategy() -> BoxedStrategy<Self> {
+        any::<Self>()
+    }
+}
+
+impl<T: BoolArray + 'static> ArbitraryField for BitArray<T> where Self: fmt::Debug {
+    fn strategy() -> BoxedStrategy<Self> {
+        any::<Self>()
+    }
+}
+
+impl<T: BoolArray + 'static> ArbitraryField for DynamicBitArray<T> where Self: fmt::Debug {
+    fn strategy() -> BoxedStrategy<Self> {
+        any::<Self>()
+    }
+}
+
+/// The strings of at most `max_length` bytes, used for the `Arbitrary` implementations of `Utf8String`
+#[doc(hidden)]
+pub fn string(max_length: usize) -> BoxedStrategy<String> {
+    collection::vec(any::<char>(), 0..=max_length).prop_map(move |chars| {
+        let mut text = String::new();
+        for c in chars {
+            if text.len() + c.len_utf8() > max_length {
+                break;
+            }
+            text.push(c);
+        }
+        text
+    }).boxed()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use *;
+    use types::*;
+    use testing::*;
+
+    #[derive(Clone, UavcanStruct)]
+    #[uavcan(debug, arbitrary)]
+    struct Reading {
+        sensor: u5,
+        _padding: void3,
+        value: f16,
+        #[uavcan(bits = 12)]
+        raw: i16,
+        flags: BitArray<[bool; 5]>,
+        samples: Dynamic<[i4; 4]>,
+        text: Utf8String<[u8; 90]>,
+    }
+
+    #[derive(Clone, UavcanStruct)]
+    #[uavcan(debug, arbitrary)]
+    enum Command {
+        Reading(Reading),
+        Delay(u24),
+        Samples(Dynamic<[Truncated<u13>; 16]>),
+    }
+
+    proptest! {
+        #[test]
+        fn integers_fit(value in any_field::<i7>(), other in any_field::<u63>()) {
+            prop_assert!(i8::from(value) >= -64 && i8::from(value) < 64);
+            prop_assert!(u64::from(other) >> 63 == 0);
+        }
+
+        #[test]
+        fn strings_fit(text in any::<Utf8String<[u8; 7]>>()) {
+            prop_assert!(text.as_bytes().len() <= 7);
+        }
+
+        #[test]
+        fn reading_round_trip(reading in any::<Reading>(), mtu in mtu()) {
+            check_round_trip(&reading, mtu)?;
+        }
+
+        #[test]
+        fn command_round_trip(command in any::<Command>(), mtu in mtu()) {
+            check_round_trip(&command, mtu)?;
+        }
+    }
+}
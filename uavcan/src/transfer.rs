@@ -4,6 +4,9 @@
 
 use lib::core::convert::From;
 use lib::core::cmp::Ordering;
+use lib::core::ops::Add;
+
+use ux::u2;
 
 use embedded_types;
 
@@ -26,9 +29,246 @@ pub trait TransferInterface {
     /// When reprioritizing the `TransferInterface` must for equal ID frames respect the order they were attempted transmitted in.
     fn transmit(&self, frame: &Self::Frame) -> Result<(), IOError>;
     
+    /// Transmit a frame without automatic retransmission.
+    ///
+    /// The frame is attempted transmitted once, if arbitration is lost or an error occurs it's dropped instead of retransmitted.
+    /// This is useful for data where a stale frame is worse than a lost one (e.g. time synchronization).
+    /// Interfaces without support for single shot transmission may rely on the default implementation,
+    /// which falls back to `transmit`.
+    fn transmit_single_shot(&self, frame: &Self::Frame) -> Result<(), IOError> {
+        self.transmit(frame)
+    }
+    
     /// Receive a frame, removing to from the receive buffer.
     /// if there are no frames in the receive buffer this function will return `None`
     fn receive(&self) -> Option<Self::Frame>;
+
+    /// Receive up to `frames.len()` frames into `frames`, returning the number of frames received.
+    ///
+    /// The frames must be placed in the order they were received, the rest of `frames` is left untouched.
+    /// Interfaces that can drain their receive buffer more efficiently than one frame at the time (e.g. by taking a lock once)
+    /// should override the default implementation, which calls `receive` repeatedly.
+    fn receive_into(&self, frames: &mut [Self::Frame]) -> usize {
+        let mut received = 0;
+        for slot in frames.iter_mut() {
+            match self.receive() {
+                Some(frame) => *slot = frame,
+                None => break,
+            }
+            received += 1;
+        }
+        received
+    }
+
+    /// Returns the statistics counters maintained by the interface itself.
+    ///
+    /// Frames passed through `transmit` and `receive` are counted by the node,
+    /// the interface should only report what it alone can observe (drops inside the interface, receive overruns and bus errors).
+    /// The default implementation reports nothing.
+    fn statistics(&self) -> TransportStats {
+        TransportStats::default()
+    }
+
+    /// Returns the current error state of the controller behind the interface.
+    ///
+    /// Interfaces without access to the controller state may rely on the default implementation,
+    /// which always reports `BusState::ErrorActive`.
+    fn bus_state(&self) -> BusState {
+        BusState::ErrorActive
+    }
+
+    /// Enter (`true`) or leave (`false`) listen-only mode.
+    ///
+    /// In listen-only mode the controller must not transmit anything on the bus, including acknowledgements and error frames.
+    /// Returns true if the mode was changed, the default implementation returns false as listen-only mode is not supported.
+    fn set_listen_only(&self, _listen_only: bool) -> bool {
+        false
+    }
+
+    /// Put the controller and transceiver to sleep.
+    ///
+    /// A sleeping interface neither transmits nor receives, but should wake up by itself on bus activity if the hardware supports it.
+    /// Returns true if the interface went to sleep, the default implementation returns false as sleeping is not supported.
+    fn sleep(&self) -> bool {
+        false
+    }
+
+    /// Wake the controller and transceiver up from sleep.
+    ///
+    /// Waking an interface that is not sleeping must have no effect.
+    fn wake(&self) {
+    }
+
+    /// Returns true while the interface is sleeping.
+    ///
+    /// This lets the node discover that the interface was woken up by bus activity.
+    fn is_sleeping(&self) -> bool {
+        false
+    }
+
+    /// Returns the maximum number of data bytes (including the tail byte) the interface can currently send in one frame.
+    ///
+    /// This allows a single `TransferFrame` type to be used on links with different payload sizes (e.g. classic CAN and CAN FD),
+    /// transfers are fragmented according to this value. It must never exceed `Self::Frame::MAX_DATA_LENGTH`,
+    /// which is what the default implementation returns.
+    fn mtu(&self) -> usize {
+        Self::Frame::MAX_DATA_LENGTH
+    }
+
+    /// Called by the node when a subscription to the message with data type ID `type_id` is created.
+    ///
+    /// Interfaces that need to take action before the message can be received (e.g. joining a multicast group)
+    /// should do it here, an error will fail the subscription. The default implementation does nothing.
+    fn on_subscribe(&self, _type_id: u16) -> Result<(), IOError> {
+        Ok(())
+    }
+
+    /// Called by the node when a subscription to the message with data type ID `type_id` is removed.
+    ///
+    /// The node calls `on_unsubscribe` once for every successful `on_subscribe`. The default implementation does nothing.
+    fn on_unsubscribe(&self, _type_id: u16) -> Result<(), IOError> {
+        Ok(())
+    }
+}
+
+/// The fault confinement state of a CAN controller
+///
+/// The state is determined by the controllers transmit and receive error counters as defined in ISO 11898-1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BusState {
+    /// Normal operation, both error counters are below 128.
+    ErrorActive,
+
+    /// One of the error counters has reached 128, the controller is still able to transmit and receive
+    /// but will not signal active error frames.
+    ErrorPassive,
+
+    /// The transmit error counter has exceeded 255, the controller has disconnected from the bus.
+    BusOff,
+}
+
+impl BusState {
+    /// Returns true if frames can be transmitted in this state
+    pub fn can_transmit(&self) -> bool {
+        *self != BusState::BusOff
+    }
+
+    /// Returns the Uavcan node health (as reported in `NodeStatus`) that this state corresponds to.
+    ///
+    /// `ErrorActive` maps to `HEALTH_OK` (0), `ErrorPassive` to `HEALTH_WARNING` (1) and `BusOff` to `HEALTH_ERROR` (2).
+    pub fn health(&self) -> u2 {
+        match *self {
+            BusState::ErrorActive => u2::new(0),
+            BusState::ErrorPassive => u2::new(1),
+            BusState::BusOff => u2::new(2),
+        }
+    }
+}
+
+/// Statistics counters for a transport
+///
+/// Counters from several sources (e.g. the node and its interfaces) can be aggregated by adding them together.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TransportStats {
+    /// Number of frames successfully handed over for transmission
+    pub frames_transmitted: u64,
+
+    /// Number of frames received
+    pub frames_received: u64,
+
+    /// Number of frames dropped before they could be transmitted
+    pub transmit_drops: u64,
+
+    /// Number of frames lost because the receive buffer was full
+    pub receive_overruns: u64,
+
+    /// Number of CRC errors detected
+    pub crc_errors: u64,
+
+    /// Number of protocol errors (form, stuffing, toggle, etc.) detected
+    pub protocol_errors: u64,
+
+    /// Number of remote frames received (and ignored)
+    pub remote_frames: u64,
+
+    /// Number of error frames received
+    pub error_frames: u64,
+}
+
+impl Add for TransportStats {
+    type Output = TransportStats;
+
+    fn add(self, other: TransportStats) -> TransportStats {
+        TransportStats {
+            frames_transmitted: self.frames_transmitted + other.frames_transmitted,
+            frames_received: self.frames_received + other.frames_received,
+            transmit_drops: self.transmit_drops + other.transmit_drops,
+            receive_overruns: self.receive_overruns + other.receive_overruns,
+            crc_errors: self.crc_errors + other.crc_errors,
+            protocol_errors: self.protocol_errors + other.protocol_errors,
+            remote_frames: self.remote_frames + other.remote_frames,
+            error_frames: self.error_frames + other.error_frames,
+        }
+    }
+}
+
+/// Returns the smallest CAN FD data length that is greater than or equal to `length`
+///
+/// CAN FD frames can only carry 0 to 8, 12, 16, 20, 24, 32, 48 or 64 bytes of data.
+///
+/// ## Panics
+/// Panics if `length > 64`
+pub fn quantize_can_fd_data_length(length: usize) -> usize {
+    match length {
+        0..=8 => length,
+        9..=12 => 12,
+        13..=16 => 16,
+        17..=20 => 20,
+        21..=24 => 24,
+        25..=32 => 32,
+        33..=48 => 48,
+        49..=64 => 64,
+        _ => panic!("CAN FD frames can't carry more than 64 bytes"),
+    }
+}
+
+/// Returns the number of frames a transfer of `payload_length` bytes takes with `mtu` bytes of frame data
+///
+/// Every frame ends with the tail byte, and a multi frame transfer starts with the 2 byte transfer CRC.
+///
+/// # Examples
+/// ```
+/// use uavcan::transfer::frame_count;
+///
+/// assert_eq!(frame_count(7, 8), 1);
+/// assert_eq!(frame_count(8, 8), 2);
+/// assert_eq!(frame_count(12, 8), 2);
+/// assert_eq!(frame_count(13, 8), 3);
+/// assert_eq!(frame_count(13, 64), 1);
+/// ```
+///
+/// ## Panics
+/// Panics if `mtu < 3`
+pub const fn frame_count(payload_length: usize, mtu: usize) -> usize {
+    assert!(mtu > 2, "The MTU must fit the transfer CRC and the tail byte");
+    if payload_length < mtu {
+        1
+    } else {
+        (payload_length + 2 + mtu - 2) / (mtu - 1)
+    }
+}
+
+/// The kind of a received CAN frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FrameKind {
+    /// A frame carrying data, the only kind used by Uavcan
+    Data,
+
+    /// A remote transmission request
+    Remote,
+
+    /// An error frame, signaled by a node that detected an error on the bus
+    Error,
 }
 
 /// `TransferFrame` is a CAN like frame that can be sent over a network
@@ -68,6 +308,33 @@ pub trait TransferFrame {
     /// ## Panics
     /// `set_data_lengt(&mut self, length: usize)` should panic if `length > T::MAX_DATA_LENGTH`
     fn set_data_length(&mut self, length: usize);
+
+    /// Returns false if this is a base format frame (11-bit ID), true if it's an extended format frame (29-bit ID).
+    ///
+    /// Uavcan only uses extended frames. Interfaces whose frame type can represent base format frames
+    /// may still return them from `receive`, leaving it to the node's `StandardFramePolicy` to decide what happens to them.
+    /// The default implementation returns true.
+    fn is_extended(&self) -> bool {
+        true
+    }
+
+    /// Returns what kind of CAN frame this is.
+    ///
+    /// Only data frames are used by Uavcan. Interfaces whose frame type can represent remote or error frames
+    /// should return them from `receive` instead of discarding them, the node will count and drop them.
+    /// The default implementation returns `FrameKind::Data`.
+    fn kind(&self) -> FrameKind {
+        FrameKind::Data
+    }
+
+    /// Returns the smallest legal data length that is greater than or equal to `length`.
+    ///
+    /// Transports where only some data lengths can be transmitted (e.g. CAN FD, see `quantize_can_fd_data_length`)
+    /// must override this. The frame generator then pads the last frame of a transfer to a legal length,
+    /// inserting the padding before the tail byte. The default implementation treats every length as legal.
+    fn quantize_data_length(length: usize) -> usize {
+        length
+    }
     
     /// Returns the tail byte of the TransferFrame assuming the current length
     ///
@@ -109,6 +376,86 @@ pub trait TransferFrame {
 }
 
 
+/// A `TransferFrame` that can carry the instant it was received at
+///
+/// Reception timestamps are needed for time synchronization and latency measurements.
+/// The timestamp of the first frame in a transfer is used as the timestamp of the whole transfer.
+pub trait TimestampedFrame: TransferFrame {
+    /// The type used for representing instants, this is typically a monotonic clock provided by the platform.
+    type Instant: Copy;
+
+    /// Returns the reception timestamp or `None` if the frame was not timestamped.
+    fn timestamp(&self) -> Option<Self::Instant>;
+}
+
+/// A wrapper that adds a reception timestamp to any `TransferFrame`
+///
+/// This can be used by `TransferInterface` implementations to timestamp frames without
+/// requiring the frame type itself to have room for a timestamp.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timestamped<F, I> {
+    pub frame: F,
+    pub timestamp: Option<I>,
+}
+
+impl<F, I> Timestamped<F, I> {
+    /// Wraps `frame` together with the instant it was received at.
+    pub fn new(frame: F, timestamp: I) -> Self {
+        Timestamped {
+            frame,
+            timestamp: Some(timestamp),
+        }
+    }
+}
+
+impl<F: TransferFrame, I: Copy> TransferFrame for Timestamped<F, I> {
+    const MAX_DATA_LENGTH: usize = F::MAX_DATA_LENGTH;
+
+    fn new(id: TransferFrameID) -> Self {
+        Timestamped {
+            frame: F::new(id),
+            timestamp: None,
+        }
+    }
+
+    fn id(&self) -> TransferFrameID {
+        self.frame.id()
+    }
+
+    fn data(&self) -> &[u8] {
+        self.frame.data()
+    }
+
+    fn data_as_mut(&mut self) -> &mut [u8] {
+        self.frame.data_as_mut()
+    }
+
+    fn set_data_length(&mut self, length: usize) {
+        self.frame.set_data_length(length)
+    }
+
+    fn is_extended(&self) -> bool {
+        self.frame.is_extended()
+    }
+
+    fn kind(&self) -> FrameKind {
+        self.frame.kind()
+    }
+
+    fn quantize_data_length(length: usize) -> usize {
+        F::quantize_data_length(length)
+    }
+}
+
+impl<F: TransferFrame, I: Copy> TimestampedFrame for Timestamped<F, I> {
+    type Instant = I;
+
+    fn timestamp(&self) -> Option<I> {
+        self.timestamp
+    }
+}
+
+
 /// Cotains both the `TransferFrameID` and `TransferID` to uniquely distinguish a transfer.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub struct FullTransferID {
@@ -436,3 +783,36 @@ impl TransferFrame for embedded_types::can::ExtendedDataFrame {
         self.id().into()
     }
 }
+
+/// A data frame of either format, for interfaces leaving base format frames to the node's `StandardFramePolicy`
+///
+/// Frames created for transmission are extended frames.
+impl TransferFrame for embedded_types::can::DataFrame {
+    const MAX_DATA_LENGTH: usize = 8;
+
+    fn new(id: TransferFrameID) -> Self {
+        embedded_types::can::DataFrame::new(id.into())
+    }
+    fn set_data_length(&mut self, length: usize) {
+        assert!(
+            length <= Self::MAX_DATA_LENGTH,
+            "DataFrame::set_data_length() needs the length to be less than 8"
+        );
+        self.set_data_length(length);
+    }
+    fn data(&self) -> &[u8] {
+        self.data()
+    }
+    fn data_as_mut(&mut self) -> &mut [u8] {
+        self.data_as_mut()
+    }
+    fn id(&self) -> TransferFrameID {
+        TransferFrameID::new(u32::from(self.id()))
+    }
+    fn is_extended(&self) -> bool {
+        match self.id() {
+            embedded_types::can::ID::ExtendedID(_) => true,
+            embedded_types::can::ID::BaseID(_) => false,
+        }
+    }
+}
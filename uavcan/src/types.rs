@@ -4,11 +4,11 @@ pub use ux::{
     i2, i3, i4, i5, i6, i7, i9, i10, i11, i12, i13, i14, i15, i17, i18, i19, i20, i21, i22, i23, i24, i25, i26, i27, i28, i29, i30, i31,
     i33, i34, i35, i36, i37, i38, i39, i40, i41, i42, i43, i44, i45, i46, i47, i48, i49, i50, i51, i52, i53, i54, i55, i56, i57, i58, i59, i60, i61, i62, i63,
 };
-pub use half::f16;
 
 use lib;
 use lib::core::fmt;
 use lib::core::cmp;
+use lib::core::ops;
 use lib::core::ops::{
     Index,
     IndexMut,
@@ -16,6 +16,8 @@ use lib::core::ops::{
 
 use bit_field::BitField;
 
+use float_codec;
+
 use serializer::{
     SerializationResult,
     SerializationBuffer,
@@ -26,7 +28,7 @@ use deserializer::{
     DeserializationBuffer,
 };
 
-trait PrimitiveType : Sized + Copy + ::Serializable {
+pub(crate) trait PrimitiveType : Sized + Copy + ::Serializable {
     /// Mask bits exceeding `BIT_LENGTH`
     fn from_bits(v: u64) -> Self;
     
@@ -35,8 +37,73 @@ trait PrimitiveType : Sized + Copy + ::Serializable {
 }
     
 
+/// The version of the UAVCAN protocol, for the rules that differ between the versions
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProtocolVersion {
+    /// UAVCAN v0 (DroneCAN), the version this crate encodes
+    V0,
+    /// UAVCAN v1 (Cyphal)
+    V1,
+}
+
+impl ProtocolVersion {
+    /// The bit length of the implicit length field of a dynamic array holding up to `max_length` elements
+    ///
+    /// UAVCAN v0 uses the minimal number of bits representing `max_length`,
+    /// UAVCAN v1 the smallest of `uint8`, `uint16`, `uint32` and `uint64` representing it.
+    ///
+    /// # Examples
+    /// ```
+    /// use uavcan::types::ProtocolVersion;
+    ///
+    /// assert_eq!(ProtocolVersion::V0.length_bits(90), 7);
+    /// assert_eq!(ProtocolVersion::V1.length_bits(90), 8);
+    /// assert_eq!(ProtocolVersion::V1.length_bits(256), 16);
+    /// ```
+    pub const fn length_bits(self, max_length: usize) -> usize {
+        let mut bits = 0;
+        while bits < 64 && (max_length as u64) >> bits != 0 {
+            bits += 1;
+        }
+
+        match self {
+            ProtocolVersion::V0 => bits,
+            ProtocolVersion::V1 if bits <= 8 => 8,
+            ProtocolVersion::V1 if bits <= 16 => 16,
+            ProtocolVersion::V1 if bits <= 32 => 32,
+            ProtocolVersion::V1 => 64,
+        }
+    }
+
+    /// Whether a dynamic array of elements at least `element_bits` long leaves out its length when it's the last field of a transfer
+    ///
+    /// The tail array optimization is specific to UAVCAN v0, UAVCAN v1 always encodes the length.
+    ///
+    /// # Examples
+    /// ```
+    /// use uavcan::types::ProtocolVersion;
+    ///
+    /// assert!(ProtocolVersion::V0.tail_array_optimization(8));
+    /// assert!(!ProtocolVersion::V0.tail_array_optimization(7));
+    /// assert!(!ProtocolVersion::V1.tail_array_optimization(8));
+    /// ```
+    pub const fn tail_array_optimization(self, element_bits: usize) -> bool {
+        match self {
+            ProtocolVersion::V0 => element_bits >= 8,
+            ProtocolVersion::V1 => false,
+        }
+    }
+}
+
 /// The Uavcan dynamic array type
 ///
+/// The elements are prefixed by the length, in the fewest bits that can hold the maximum length.
+/// When the array is the last field of a transfer and its elements are at least 8 bits long,
+/// the tail array optimization leaves out the length: the elements take the rest of the payload,
+/// and the padding of the last byte is too short to be an element. The optimization is part of
+/// the UAVCAN v0 encoding only (see `ProtocolVersion::tail_array_optimization`), the UAVCAN v1
+/// encoding of the `v1` module always writes the length.
+///
 /// # Examples
 /// ```
 /// use std::str;
@@ -48,19 +115,25 @@ trait PrimitiveType : Sized + Copy + ::Serializable {
 /// assert_eq!(str::from_utf8(dynamic_array.as_ref()).unwrap(), "dynamic array");
 ///
 /// ```
-pub struct Dynamic<T> {
-    array: lib::core::mem::ManuallyDrop<T>,
-    current_length: usize,
+pub struct Dynamic<T: DynamicArray> {
+    array: T::Vec,
     deserialized_length: usize,
 }
 
+/// The arrays that can bound a `Dynamic` array, `[T; N]` for `N` up to 256
+pub trait DynamicArray {
+    /// The vector holding the elements of the dynamic array
+    type Vec;
+}
+
 macro_rules! impl_array{
-    {[$(($size:expr, $length_bits:expr)), *]} => {$(impl_array!(($size, $length_bits));)*};
-    {($size:expr, $length_bits:expr)} => {
+    {[$($size:expr), *]} => {$(impl_array!(($size));)*};
+    {($size:expr)} => {
 
         // first implement static arrays
         impl<T: ::Serializable> ::Serializable for [T; $size] {
             const BIT_LENGTH_MIN: usize = $size * T::BIT_LENGTH_MIN;
+            const BIT_LENGTH_MAX: usize = $size * T::BIT_LENGTH_MAX;
             const FLATTENED_FIELDS_NUMBER: usize = $size * T::FLATTENED_FIELDS_NUMBER;
             
             fn serialize(&self, flattened_field: &mut usize, bit: &mut usize, _last_field: bool, buffer: &mut SerializationBuffer) -> SerializationResult {
@@ -107,14 +180,21 @@ macro_rules! impl_array{
 
         
         impl<T> Dynamic<[T; $size]> {
-            pub const LENGTH_BITS: usize = $length_bits;
+            /// The bit length of the length field in the UAVCAN v0 encoding of `Serializable`
+            pub const LENGTH_BITS: usize = Self::length_bits(ProtocolVersion::V0);
             pub const MAX_LENGTH: usize = $size;
 
+            /// The bit length of the length field in the encoding of `version`
+            ///
+            /// The UAVCAN v1 encoding is written by `v1::Writer::write_dynamic_array`.
+            pub const fn length_bits(version: ProtocolVersion) -> usize {
+                version.length_bits($size)
+            }
+
             /// Constructs a new empty `Dynamic` array
             pub fn new() -> Self {
                 Self{
-                    array: lib::core::mem::ManuallyDrop::new(unsafe{ lib::core::mem::uninitialized() }),
-                    current_length: 0,
+                    array: ::heapless::Vec::new(),
                     deserialized_length: 0,
                 }
             }
@@ -122,23 +202,22 @@ macro_rules! impl_array{
             /// Constructs a new `Dynamic` array with cloned data
             pub fn with_data(data: &[T]) -> Self where T: Clone{
                 let mut s = Self::new();
-                for i in 0..data.len() {
-                    unsafe{lib::core::ptr::write(&mut s.array[i] as *mut T, data[i].clone())};
+                for item in data {
+                    s.push(item.clone());
                 }
-                s.current_length = data.len();
                 s
             }
 
             /// Push an item to the end of the `Dynamic` array. Size will increase by one after this operation.
             pub fn push(&mut self, item: T) {
-                assert!(self.current_length < Self::MAX_LENGTH, "Can't push data to full array");
-                unsafe{lib::core::ptr::write(&mut self.array[self.current_length] as *mut T, item)};
-                self.current_length += 1;                
+                if self.array.push(item).is_err() {
+                    panic!("Can't push data to full array");
+                }
             }
 
             /// Returns the current length for the dynamic array
             pub fn length(&self) -> usize {
-                self.current_length
+                self.array.len()
             }
 
             /// Set lengths of the array.
@@ -147,44 +226,62 @@ macro_rules! impl_array{
             /// When array is shrinked, the elements that fall out of range is dropped.
             /// When array is grown, `Default::default()` is inserted for the new values.
             pub fn set_length(&mut self, length: usize) where T: Default {
-                if length < self.current_length {
+                if length < self.array.len() {
                     self.shrink(length);
-                } else if length > self.current_length {
+                } else if length > self.array.len() {
                     self.grow(length);
                 }
             }
 
             /// Shrinks array, dropping elements that fall out of range
             pub fn shrink(&mut self, length: usize) {
-                assert!(length <= self.current_length, "Dynamic::shrink() can only be used to shrink array");
-                for i in length..self.current_length {
-                    let temp: T = lib::core::mem::replace(&mut self.array[i], unsafe{ lib::core::mem::uninitialized() } );
-                    drop(temp);
-                }
-                self.current_length = length;
+                assert!(length <= self.array.len(), "Dynamic::shrink() can only be used to shrink array");
+                self.array.truncate(length);
             }
 
             /// Grow array, inserting the default element in the new spaces
             fn grow(&mut self, length: usize) where T: Default {
-                assert!(length > self.current_length);
-                for i in self.current_length..length {
-                    unsafe{lib::core::ptr::write(&mut self.array[i], T::default())};
+                assert!(length > self.array.len());
+                while self.array.len() < length {
+                    self.push(T::default());
                 }
-                self.current_length = length;
+            }
+
+            /// Push an item to the end of the `Dynamic` array, or give it back if the array is full
+            pub fn try_push(&mut self, item: T) -> Result<(), T> {
+                self.array.push(item)
+            }
+
+            /// Removes the last item of the `Dynamic` array and returns it, or `None` if the array is empty
+            pub fn pop(&mut self) -> Option<T> {
+                self.array.pop()
+            }
+
+            /// Removes all items, dropping them
+            pub fn clear(&mut self) {
+                self.array.clear();
             }
 
             pub fn iter(&self) -> lib::core::slice::Iter<T> {
-                self.array[0..self.current_length].iter()
+                self.array.iter()
             }
             
             pub fn iter_mut(&mut self) -> lib::core::slice::IterMut<T> {
-                self.array[0..self.current_length].iter_mut()
+                self.array.iter_mut()
             }
 
         }
 
+        impl<T: ::Serializable> Dynamic<[T; $size]> {
+            /// Whether the length is left out when the array is the last field of a transfer
+            const TAIL_ARRAY_OPTIMIZATION: bool = ProtocolVersion::V0.tail_array_optimization(T::BIT_LENGTH_MIN);
+        }
+
         impl<T: ::Serializable> ::Serializable for Dynamic<[T; $size]> {
-            const BIT_LENGTH_MIN: usize = $length_bits;
+            const BIT_LENGTH_MIN: usize = Self::LENGTH_BITS;
+            const BIT_LENGTH_MAX: usize = Self::LENGTH_BITS + $size * T::BIT_LENGTH_MAX;
+            const TAIL_BIT_LENGTH_MIN: usize = if Self::TAIL_ARRAY_OPTIMIZATION { 0 } else { Self::LENGTH_BITS };
+            const TAIL_BIT_LENGTH_MAX: usize = Self::TAIL_BIT_LENGTH_MIN + $size * T::BIT_LENGTH_MAX;
             const FLATTENED_FIELDS_NUMBER: usize = $size * T::FLATTENED_FIELDS_NUMBER + 1;
             
             fn serialize(&self, flattened_field: &mut usize, bit: &mut usize, last_field: bool, buffer: &mut SerializationBuffer) -> SerializationResult {
@@ -196,7 +293,7 @@ macro_rules! impl_array{
                 }                
                 
                 // check for tail optimization
-                if T::BIT_LENGTH_MIN >= 8 && last_field && *flattened_field == 0 {
+                if Self::TAIL_ARRAY_OPTIMIZATION && last_field && *flattened_field == 0 {
                     *flattened_field = 1;
                 }
                 
@@ -205,17 +302,17 @@ macro_rules! impl_array{
                     let type_bits_remaining = Self::LENGTH_BITS - *bit;
                     
                     if buffer_bits_remaining >= type_bits_remaining {
-                        buffer.push_bits(type_bits_remaining, self.current_length.get_bits((*bit as u8)..(Self::LENGTH_BITS as u8)) as u64);
+                        buffer.push_bits(type_bits_remaining, self.array.len().get_bits((*bit as u8)..(Self::LENGTH_BITS as u8)) as u64);
                         *flattened_field = 1;
                         *bit = 0;
                     } else {
-                        buffer.push_bits(buffer_bits_remaining, self.current_length.get_bits((*bit as u8)..(*bit + buffer_bits_remaining) as u8) as u64);
+                        buffer.push_bits(buffer_bits_remaining, self.array.len().get_bits((*bit as u8)..(*bit + buffer_bits_remaining) as u8) as u64);
                         *bit += buffer_bits_remaining;
                         return SerializationResult::BufferFull
                     }
                 }
 
-                while *flattened_field - 1 < self.current_length*T::FLATTENED_FIELDS_NUMBER {
+                while *flattened_field - 1 < self.array.len()*T::FLATTENED_FIELDS_NUMBER {
                     let element = (*flattened_field - 1) / T::FLATTENED_FIELDS_NUMBER;
                     let mut element_field = (*flattened_field - 1) % T::FLATTENED_FIELDS_NUMBER;
                     match self[element].serialize(&mut element_field, bit, false, buffer) {
@@ -237,7 +334,11 @@ macro_rules! impl_array{
             fn deserialize(&mut self, flattened_field: &mut usize, bit: &mut usize, last_field: bool, buffer: &mut DeserializationBuffer) -> DeserializationResult {
 
                 // check for tail optimization
-                let tail_array_optimization = last_field && (T::BIT_LENGTH_MIN >= 8);
+                let tail_array_optimization = last_field && Self::TAIL_ARRAY_OPTIMIZATION;
+
+                if *flattened_field == 0 {
+                    self.array.clear();
+                }
 
                 if tail_array_optimization && *flattened_field == 0 {
                     *flattened_field = 1;
@@ -247,25 +348,48 @@ macro_rules! impl_array{
                 if *flattened_field == 0 {
                     
                     let buffer_len = buffer.bit_length();
-                    if buffer_len + *bit < Self::LENGTH_BITS {
+                    if buffer.is_zero_extended() {
+                        // The bits of the length that were not received are zero
+                    } else if buffer_len == 0 {
+                        // The length starts in the next buffer (e.g. the next frame)
+                        return DeserializationResult::BufferInsufficient;
+                    } else if buffer_len + *bit < Self::LENGTH_BITS {
                         self.deserialized_length.set_bits(*bit as u8..(*bit+buffer_len) as u8, buffer.pop_bits(buffer_len) as usize);
                         *bit += buffer_len;
                         return DeserializationResult::BufferInsufficient
                     } else {
                         self.deserialized_length.set_bits(*bit as u8..Self::LENGTH_BITS as u8, buffer.pop_bits(Self::LENGTH_BITS-*bit) as usize);
-                        *flattened_field = 1;
-                        *bit = 0;
+                    }
+                    *flattened_field = 1;
+                    *bit = 0;
+
+                    // The length field may encode more elements than the array has room for
+                    self.deserialized_length = cmp::min(self.deserialized_length, Self::MAX_LENGTH);
+                    if self.deserialized_length == 0 {
+                        *flattened_field = Self::FLATTENED_FIELDS_NUMBER;
+                        return DeserializationResult::Finished;
                     }
                 }
                 
+                // A tail array ends with the data, an element that was only partly received is dropped
+                if tail_array_optimization && buffer.is_zero_extended() {
+                    self.array.truncate((*flattened_field - 1) / T::FLATTENED_FIELDS_NUMBER);
+                    *flattened_field = Self::FLATTENED_FIELDS_NUMBER;
+                    *bit = 0;
+                    return DeserializationResult::Finished;
+                }
+
                 while *flattened_field < Self::FLATTENED_FIELDS_NUMBER {
                     let element = (*flattened_field - 1) / T::FLATTENED_FIELDS_NUMBER;
                     let mut element_field = (*flattened_field - 1) % T::FLATTENED_FIELDS_NUMBER;
+                    if element == self.array.len() {
+                        // Like a structure, the element is deserialized in place from zeroed memory
+                        let _ = self.array.push(unsafe{ lib::core::mem::zeroed() });
+                    }
                     match self.array[element].deserialize(&mut element_field, bit, false, buffer) {
                         DeserializationResult::Finished => {
                             *flattened_field = element*T::FLATTENED_FIELDS_NUMBER + 1 + element_field;
-                            self.current_length = element+1;
-                            if !tail_array_optimization && self.current_length == self.deserialized_length {
+                            if !tail_array_optimization && self.array.len() == self.deserialized_length {
                                 *flattened_field = Self::FLATTENED_FIELDS_NUMBER;
                                 *bit = 0;
                                 return DeserializationResult::Finished;
@@ -273,43 +397,103 @@ macro_rules! impl_array{
                         },
                         DeserializationResult::BufferInsufficient => {
                             *flattened_field = element*T::FLATTENED_FIELDS_NUMBER + 1 + element_field;
-                            self.current_length = element;
                             return DeserializationResult::BufferInsufficient;
                         },
                     }
                 }
                 
                 *flattened_field = Self::FLATTENED_FIELDS_NUMBER;
-                self.current_length = (*flattened_field - 1) / T::FLATTENED_FIELDS_NUMBER;
                 *bit = 0;
                 DeserializationResult::Finished
             }
             
         }
 
-        impl<T> Index<usize> for Dynamic<[T; $size]> {
-            type Output = T;
+        impl<T, I: lib::core::slice::SliceIndex<[T]>> Index<I> for Dynamic<[T; $size]> {
+            type Output = I::Output;
             
-            fn index(&self, index: usize) -> &T {
-                &self.array[0..self.current_length][index]
+            fn index(&self, index: I) -> &I::Output {
+                &self.array[index]
             }
         }
         
-        impl<T> IndexMut<usize> for Dynamic<[T; $size]> {
-            fn index_mut(&mut self, index: usize) -> &mut T {
-                &mut self.array[0..self.current_length][index]
+        impl<T, I: lib::core::slice::SliceIndex<[T]>> IndexMut<I> for Dynamic<[T; $size]> {
+            fn index_mut(&mut self, index: I) -> &mut I::Output {
+                &mut self.array[index]
             }
         }
 
         impl<T> AsRef<[T]> for Dynamic<[T; $size]> {
             fn as_ref(&self) -> &[T] {
-                &self.array[0..self.current_length]
+                &self.array
             }
         }
 
         impl<T> AsMut<[T]> for Dynamic<[T; $size]> {
             fn as_mut(&mut self) -> &mut [T] {
-                &mut self.array[0..self.current_length]
+                &mut self.array
+            }
+        }
+
+        impl<T> lib::core::ops::Deref for Dynamic<[T; $size]> {
+            type Target = [T];
+
+            fn deref(&self) -> &[T] {
+                &self.array
+            }
+        }
+
+        impl<T> lib::core::ops::DerefMut for Dynamic<[T; $size]> {
+            fn deref_mut(&mut self) -> &mut [T] {
+                &mut self.array
+            }
+        }
+
+        impl<'a, T> IntoIterator for &'a Dynamic<[T; $size]> {
+            type Item = &'a T;
+            type IntoIter = lib::core::slice::Iter<'a, T>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.iter()
+            }
+        }
+
+        impl<'a, T> IntoIterator for &'a mut Dynamic<[T; $size]> {
+            type Item = &'a mut T;
+            type IntoIter = lib::core::slice::IterMut<'a, T>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.iter_mut()
+            }
+        }
+
+        /// Panics if the items don't fit the array, like `push`
+        impl<T> Extend<T> for Dynamic<[T; $size]> {
+            fn extend<I: IntoIterator<Item = T>>(&mut self, items: I) {
+                for item in items {
+                    self.push(item);
+                }
+            }
+        }
+
+        /// Panics if the items don't fit the array, like `push`
+        impl<T> lib::core::iter::FromIterator<T> for Dynamic<[T; $size]> {
+            fn from_iter<I: IntoIterator<Item = T>>(items: I) -> Self {
+                let mut array = Self::new();
+                array.extend(items);
+                array
+            }
+        }
+
+        impl<T> From<::heapless::Vec<T, $size>> for Dynamic<[T; $size]> {
+            fn from(vec: ::heapless::Vec<T, $size>) -> Self {
+                Self{array: vec, deserialized_length: 0}
+            }
+        }
+
+        impl<T> From<Dynamic<[T; $size]>> for ::heapless::Vec<T, $size> {
+            fn from(array: Dynamic<[T; $size]>) -> Self {
+                array.array
             }
         }
 
@@ -320,86 +504,171 @@ macro_rules! impl_array{
         }
 
         
-        // This is needed since it can't be derived for arrays larger than 32 yet
+        // The deserialized length is a leftover of deserialization, only the elements are compared
         impl<T: cmp::PartialEq> cmp::PartialEq for Dynamic<[T; $size]> {
             fn eq(&self, other: &Self) -> bool {
-                if self.current_length != other.current_length {
-                    return false;
-                }
-                
-                for i in 0..self.current_length {
-                    if self.array[i] != other.array[i] {
-                        return false;
-                    }
-                }
-
-                true
+                self.array == other.array
             }
         }
         
-        // This is needed since it can't be derived for arrays larger than 32 yet
         impl<T: fmt::Debug> fmt::Debug for Dynamic<[T; $size]> {
             fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
                 write!(f, "$i<T> {{ data: [")?;
-                for i in 0..self.current_length {
-                    write!(f, "{:?}, ", self.array[i])?;
+                for item in self.iter() {
+                    write!(f, "{:?}, ", item)?;
                 }
                 write!(f, "]}}")
             }
         }
         
+        impl<T: ::debug_field::DebugField> ::debug_field::DebugField for Dynamic<[T; $size]> {
+            fn fmt_field(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                <[T] as ::debug_field::DebugField>::fmt_field(self.as_ref(), f)
+            }
+        }
+
+        #[cfg(feature="defmt")]
+        impl<T: ::defmt_field::DefmtField> ::defmt_field::DefmtField for Dynamic<[T; $size]> {
+            fn format_field(&self, f: ::defmt::Formatter) {
+                ::defmt_field::format_slice(self.as_ref(), f);
+            }
+        }
+
+        #[cfg(feature="serde")]
+        impl<T: ::serde_field::SerdeField> ::serde_field::SerdeField for Dynamic<[T; $size]> {
+            fn serialize_field<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_seq(self.iter().map(::serde_field::Element))
+            }
+
+            fn deserialize_field<'de, D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct DynamicVisitor<T>(lib::core::marker::PhantomData<T>);
+
+                impl<'de, T: ::serde_field::SerdeField> ::serde::de::Visitor<'de> for DynamicVisitor<T> {
+                    type Value = Dynamic<[T; $size]>;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                        write!(formatter, "a sequence of at most {} elements", $size)
+                    }
+
+                    fn visit_seq<A: ::serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                        let mut array = Dynamic::<[T; $size]>::new();
+                        while let Some(::serde_field::OwnedElement(element)) = seq.next_element()? {
+                            if array.length() == Self::Value::MAX_LENGTH {
+                                return Err(::serde::de::Error::invalid_length(array.length() + 1, &self));
+                            }
+                            array.push(element);
+                        }
+                        Ok(array)
+                    }
+                }
+
+                deserializer.deserialize_seq(DynamicVisitor(lib::core::marker::PhantomData))
+            }
+        }
+
+        impl<T> DynamicArray for [T; $size] {
+            type Vec = ::heapless::Vec<T, $size>;
+        }
+
+        impl BoolArray for [bool; $size] {
+            type Bytes = [u8; ($size + 7) / 8];
+            const LENGTH: usize = $size;
+            const LENGTH_BITS: usize = ProtocolVersion::V0.length_bits($size);
+            const ZEROED: [u8; ($size + 7) / 8] = [0; ($size + 7) / 8];
+        }
+
+        impl lib::core::str::FromStr for Utf8String<[u8; $size]> {
+            type Err = CapacityError;
+
+            fn from_str(s: &str) -> Result<Self, CapacityError> {
+                if s.len() > $size {
+                    return Err(CapacityError{length: s.len(), max_length: $size});
+                }
+                Ok(Utf8String(Dynamic::<[u8; $size]>::with_data(s.as_bytes())))
+            }
+        }
+
+        #[cfg(feature="testing")]
+        impl<T: ::testing::ArbitraryField + 'static> ::proptest::arbitrary::Arbitrary for Dynamic<[T; $size]> {
+            type Parameters = ();
+            type Strategy = ::proptest::strategy::BoxedStrategy<Self>;
+
+            fn arbitrary_with(_: ()) -> Self::Strategy {
+                use proptest::strategy::Strategy;
+                ::proptest::collection::vec(T::strategy(), 0..=$size).prop_map(|elements| {
+                    let mut array = Self::new();
+                    for element in elements {
+                        array.push(element);
+                    }
+                    array
+                }).boxed()
+            }
+        }
+
+        #[cfg(feature="testing")]
+        impl ::proptest::arbitrary::Arbitrary for Utf8String<[u8; $size]> {
+            type Parameters = ();
+            type Strategy = ::proptest::strategy::BoxedStrategy<Self>;
+
+            fn arbitrary_with(_: ()) -> Self::Strategy {
+                use proptest::strategy::Strategy;
+                ::testing::string($size).prop_map(|text| text.parse().unwrap()).boxed()
+            }
+        }
+
+        #[cfg(feature="testing")]
+        impl<T: ::testing::ArbitraryField + 'static> ::testing::ArbitraryField for Dynamic<[T; $size]> where Self: fmt::Debug {
+            fn strategy() -> ::proptest::strategy::BoxedStrategy<Self> {
+                ::proptest::arbitrary::any::<Self>()
+            }
+        }
+
+        #[cfg(feature="testing")]
+        impl ::testing::ArbitraryField for Utf8String<[u8; $size]> {
+            fn strategy() -> ::proptest::strategy::BoxedStrategy<Self> {
+                ::proptest::arbitrary::any::<Self>()
+            }
+        }
+
         impl<T: Clone> Clone for Dynamic<[T; $size]> {
             fn clone(&self) -> Self {
-                let mut a = Self::new();
-                for i in 0..self.current_length {
-                    a.push(self.array[i].clone());
-                }
-                a
+                Self{array: self.array.clone(), deserialized_length: 0}
             }
         }
         
     };
 }
-
-impl<T> Drop for Dynamic<T> {
-    fn drop(&mut self) {
-        // Since const generics doesn't work we can't deconstruct elements inside arrays when it's beeing dropped
-        // This might in extreme cases cause memory leaks and other weirdness from deconstructors not running
-        // This isn't good but not UD or unsafe either.
-        // Fix as soon as const generics lands
-    }
-}
         
 
 
-impl_array!([(1, 1), (2, 2), (3, 2), (4, 3), (5, 3), (6, 3), (7, 3), (8, 4), (9, 4)]);
-impl_array!([(10, 4), (11, 4), (12, 4), (13, 4), (14, 4), (15, 4), (16, 5), (17, 5), (18, 5), (19, 5)]);
-impl_array!([(20, 5), (21, 5), (22, 5), (23, 5), (24, 5), (25, 5), (26, 5), (27, 5), (28, 5), (29, 5)]);
-impl_array!([(30, 5), (31, 5), (32, 6), (33, 6), (34, 6), (35, 6), (36, 6), (37, 6), (38, 6), (39, 6)]);
-impl_array!([(40, 6), (41, 6), (42, 6), (43, 6), (44, 6), (45, 6), (46, 6), (47, 6), (48, 6), (49, 6)]);
-impl_array!([(50, 6), (51, 6), (52, 6), (53, 6), (54, 6), (55, 6), (56, 6), (57, 6), (58, 6), (59, 6)]);
-impl_array!([(60, 6), (61, 6), (62, 6), (63, 6), (64, 7), (65, 7), (66, 7), (67, 7), (68, 7), (69, 7)]);
-impl_array!([(70, 7), (71, 7), (72, 7), (73, 7), (74, 7), (75, 7), (76, 7), (77, 7), (78, 7), (79, 7)]);
-impl_array!([(80, 7), (81, 7), (82, 7), (83, 7), (84, 7), (85, 7), (86, 7), (87, 7), (88, 7), (89, 7)]);
-impl_array!([(90, 7), (91, 7), (92, 7), (93, 7), (94, 7), (95, 7), (96, 7), (97, 7), (98, 7), (99, 7)]);
-
-impl_array!([(100, 7), (101, 7), (102, 7), (103, 7), (104, 7), (105, 7), (106, 7), (107, 7), (108, 7), (109, 7)]);
-impl_array!([(110, 7), (111, 7), (112, 7), (113, 7), (114, 7), (115, 7), (116, 7), (117, 7), (118, 7), (119, 7)]);
-impl_array!([(120, 7), (121, 7), (122, 7), (123, 7), (124, 7), (125, 7), (126, 7), (127, 7), (128, 8), (129, 8)]);
-impl_array!([(130, 8), (131, 8), (132, 8), (133, 8), (134, 8), (135, 8), (136, 8), (137, 8), (138, 8), (139, 8)]);
-impl_array!([(140, 8), (141, 8), (142, 8), (143, 8), (144, 8), (145, 8), (146, 8), (147, 8), (148, 8), (149, 8)]);
-impl_array!([(150, 8), (151, 8), (152, 8), (153, 8), (154, 8), (155, 8), (156, 8), (157, 8), (158, 8), (159, 8)]);
-impl_array!([(160, 8), (161, 8), (162, 8), (163, 8), (164, 8), (165, 8), (166, 8), (167, 8), (168, 8), (169, 8)]);
-impl_array!([(170, 8), (171, 8), (172, 8), (173, 8), (174, 8), (175, 8), (176, 8), (177, 8), (178, 8), (179, 8)]);
-impl_array!([(180, 8), (181, 8), (182, 8), (183, 8), (184, 8), (185, 8), (186, 8), (187, 8), (188, 8), (189, 8)]);
-impl_array!([(190, 8), (191, 8), (192, 8), (193, 8), (194, 8), (195, 8), (196, 8), (197, 8), (198, 8), (199, 8)]);
-
-impl_array!([(200, 8), (201, 8), (202, 8), (203, 8), (204, 8), (205, 8), (206, 8), (207, 8), (208, 8), (209, 8)]);
-impl_array!([(210, 8), (211, 8), (212, 8), (213, 8), (214, 8), (215, 8), (216, 8), (217, 8), (218, 8), (219, 8)]);
-impl_array!([(220, 8), (221, 8), (222, 8), (223, 8), (224, 8), (225, 8), (226, 8), (227, 8), (228, 8), (229, 8)]);
-impl_array!([(230, 8), (231, 8), (232, 8), (233, 8), (234, 8), (235, 8), (236, 8), (237, 8), (238, 8), (239, 8)]);
-impl_array!([(240, 8), (241, 8), (242, 8), (243, 8), (244, 8), (245, 8), (246, 8), (247, 8), (248, 8), (249, 8)]);
-impl_array!([(250, 8), (251, 8), (252, 8), (253, 8), (254, 8), (255, 8), (256, 9)]);
+impl_array!([1, 2, 3, 4, 5, 6, 7, 8, 9]);
+impl_array!([10, 11, 12, 13, 14, 15, 16, 17, 18, 19]);
+impl_array!([20, 21, 22, 23, 24, 25, 26, 27, 28, 29]);
+impl_array!([30, 31, 32, 33, 34, 35, 36, 37, 38, 39]);
+impl_array!([40, 41, 42, 43, 44, 45, 46, 47, 48, 49]);
+impl_array!([50, 51, 52, 53, 54, 55, 56, 57, 58, 59]);
+impl_array!([60, 61, 62, 63, 64, 65, 66, 67, 68, 69]);
+impl_array!([70, 71, 72, 73, 74, 75, 76, 77, 78, 79]);
+impl_array!([80, 81, 82, 83, 84, 85, 86, 87, 88, 89]);
+impl_array!([90, 91, 92, 93, 94, 95, 96, 97, 98, 99]);
+
+impl_array!([100, 101, 102, 103, 104, 105, 106, 107, 108, 109]);
+impl_array!([110, 111, 112, 113, 114, 115, 116, 117, 118, 119]);
+impl_array!([120, 121, 122, 123, 124, 125, 126, 127, 128, 129]);
+impl_array!([130, 131, 132, 133, 134, 135, 136, 137, 138, 139]);
+impl_array!([140, 141, 142, 143, 144, 145, 146, 147, 148, 149]);
+impl_array!([150, 151, 152, 153, 154, 155, 156, 157, 158, 159]);
+impl_array!([160, 161, 162, 163, 164, 165, 166, 167, 168, 169]);
+impl_array!([170, 171, 172, 173, 174, 175, 176, 177, 178, 179]);
+impl_array!([180, 181, 182, 183, 184, 185, 186, 187, 188, 189]);
+impl_array!([190, 191, 192, 193, 194, 195, 196, 197, 198, 199]);
+
+impl_array!([200, 201, 202, 203, 204, 205, 206, 207, 208, 209]);
+impl_array!([210, 211, 212, 213, 214, 215, 216, 217, 218, 219]);
+impl_array!([220, 221, 222, 223, 224, 225, 226, 227, 228, 229]);
+impl_array!([230, 231, 232, 233, 234, 235, 236, 237, 238, 239]);
+impl_array!([240, 241, 242, 243, 244, 245, 246, 247, 248, 249]);
+impl_array!([250, 251, 252, 253, 254, 255, 256]);
 
 
 #[allow(non_camel_case_types)] #[derive(Copy, Clone, Default, Debug, PartialEq, Eq)] pub struct void1{}
@@ -469,11 +738,90 @@ impl_array!([(250, 8), (251, 8), (252, 8), (253, 8), (254, 8), (255, 8), (256, 9
 #[allow(non_camel_case_types)] #[derive(Copy, Clone, Default, Debug, PartialEq, Eq)] pub struct void64{}
 
 
+/// An unsigned integer of `BITS` bits, for code that is generic over the width
+///
+/// `Uint<13>` is serialized exactly like `u13`. The width is checked at compile time,
+/// using a `Uint` with less than 2 or more than 64 bits fails to compile.
+///
+/// `#[derive(UavcanStruct)]` doesn't parse const generic arguments, fields of derived
+/// structs must name the type through an alias.
+///
+/// ```compile_fail
+/// # use uavcan::types::Uint;
+/// let _ = Uint::<65>::new(0);
+/// ```
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uint<const BITS: usize>(u64);
+
+impl<const BITS: usize> Uint<BITS> {
+    // Every use of the type goes through `WIDTH`, which fails to evaluate for invalid widths
+    const WIDTH: usize = {
+        assert!(BITS >= 2 && BITS <= 64, "Uint takes 2 to 64 bits");
+        BITS
+    };
+
+    /// The largest value of the type
+    pub const MAX: u64 = u64::MAX >> (64 - Self::WIDTH);
+
+    /// ## Panics
+    /// Panics if `value` is larger than `MAX`
+    pub fn new(value: u64) -> Self {
+        assert!(value <= Self::MAX, "The value doesn't fit the integer");
+        Uint(value)
+    }
+}
+
+impl<const BITS: usize> From<Uint<BITS>> for u64 {
+    fn from(value: Uint<BITS>) -> u64 {
+        value.0
+    }
+}
+
+/// A signed integer of `BITS` bits, for code that is generic over the width
+///
+/// `Int<13>` is serialized exactly like `i13`. The width is checked at compile time,
+/// using an `Int` with less than 2 or more than 64 bits fails to compile.
+///
+/// `#[derive(UavcanStruct)]` doesn't parse const generic arguments, fields of derived
+/// structs must name the type through an alias.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Int<const BITS: usize>(i64);
+
+impl<const BITS: usize> Int<BITS> {
+    // Every use of the type goes through `WIDTH`, which fails to evaluate for invalid widths
+    const WIDTH: usize = {
+        assert!(BITS >= 2 && BITS <= 64, "Int takes 2 to 64 bits");
+        BITS
+    };
+
+    /// The largest value of the type
+    pub const MAX: i64 = i64::MAX >> (64 - Self::WIDTH);
+
+    /// The smallest value of the type
+    pub const MIN: i64 = -Self::MAX - 1;
+
+    /// ## Panics
+    /// Panics if `value` is outside of `MIN..=MAX`
+    pub fn new(value: i64) -> Self {
+        assert!(value >= Self::MIN && value <= Self::MAX, "The value doesn't fit the integer");
+        Int(value)
+    }
+}
+
+impl<const BITS: usize> From<Int<BITS>> for i64 {
+    fn from(value: Int<BITS>) -> i64 {
+        value.0
+    }
+}
+
+
 macro_rules! impl_serializeable {
-    {$type:ident, $bits:expr} => {
-        impl ::Serializable for $type {
+    {[$($generics:tt)*] $type:ty, $bits:expr} => {
+        impl<$($generics)*> ::Serializable for $type {
             const BIT_LENGTH_MIN: usize = $bits;
 
+            const BIT_LENGTH_MAX: usize = $bits;
+
             const FLATTENED_FIELDS_NUMBER: usize = 1;
             
             fn serialize(&self, flattened_field: &mut usize, bit: &mut usize, _last_field: bool, buffer: &mut SerializationBuffer) -> SerializationResult {
@@ -502,7 +850,7 @@ macro_rules! impl_serializeable {
             fn deserialize(&mut self, flattened_field: &mut usize, bit: &mut usize, _last_field: bool, buffer: &mut DeserializationBuffer) -> DeserializationResult {
                 assert_eq!(*flattened_field, 0);
                 let buffer_len = buffer.bit_length();
-                if buffer_len == 0 && *bit == $bits {
+                if buffer_len == 0 && (*bit == $bits || buffer.is_zero_extended()) {
                     *bit = 0;
                     *flattened_field = 1;
                     DeserializationResult::Finished
@@ -523,6 +871,9 @@ macro_rules! impl_serializeable {
         }
 
     };
+    {$type:ty, $bits:expr} => {
+        impl_serializeable!([] $type, $bits);
+    };
 }
 
 macro_rules! impl_ux{
@@ -537,6 +888,27 @@ macro_rules! impl_ux{
             }
         }
         impl_serializeable!($type, $bits);
+
+        impl<T: Into<u64>> CastFrom<T> for $type {
+            fn cast_from(value: T) -> Self {
+                $type::new(cmp::min(value.into(), (1 << $bits) - 1) as $underlying_type)
+            }
+        }
+
+        impl<T: Into<u64>> CastFrom<T> for Truncated<$type> {
+            fn cast_from(value: T) -> Self {
+                Truncated($type::new((value.into() & ((1 << $bits) - 1)) as $underlying_type))
+            }
+        }
+
+        impl Integer for $type {
+            fn to_f64(self) -> f64 {
+                u64::from(self) as f64
+            }
+            fn from_f64(value: f64) -> Self {
+                $type::cast_from(round(value) as u64)
+            }
+        }
     };
 }
 
@@ -545,13 +917,40 @@ macro_rules! impl_ix{
     ($type:ident, $bits:expr, $underlying_type:ident) => {
         impl PrimitiveType for $type {
             fn from_bits(v: u64) -> Self {
-                $type::new(v as $underlying_type)
+                // The highest of the bits is the sign
+                let shift = 64 - $bits;
+                $type::new((((v << shift) as i64) >> shift) as $underlying_type)
             }
             fn to_bits(self) -> u64 {
                 i64::from(self) as u64
             }
         }
         impl_serializeable!($type, $bits);
+
+        impl<T: Into<i64>> CastFrom<T> for $type {
+            fn cast_from(value: T) -> Self {
+                let max = (1i64 << ($bits - 1)) - 1;
+                let min = -(1i64 << ($bits - 1));
+                $type::new(cmp::max(cmp::min(value.into(), max), min) as $underlying_type)
+            }
+        }
+
+        impl<T: Into<i64>> CastFrom<T> for Truncated<$type> {
+            fn cast_from(value: T) -> Self {
+                // Keeps the lowest bits and sign extends the highest of them
+                let shift = 64 - $bits;
+                Truncated($type::new(((value.into() << shift) >> shift) as $underlying_type))
+            }
+        }
+
+        impl Integer for $type {
+            fn to_f64(self) -> f64 {
+                i64::from(self) as f64
+            }
+            fn from_f64(value: f64) -> Self {
+                $type::cast_from(round(value) as i64)
+            }
+        }
     };
 }
 
@@ -608,6 +1007,71 @@ impl_vx!([(void33, 33), (void34, 34), (void35, 35), (void36, 36), (void37, 37),
                           (void49, 49), (void50, 50), (void51, 51), (void52, 52), (void53, 53), (void54, 54), (void55, 55), (void56, 56),
                           (void57, 57), (void58, 58), (void59, 59), (void60, 60), (void61, 61), (void62, 62), (void63, 63), (void64, 64)]);
 
+impl<const BITS: usize> PrimitiveType for Uint<BITS> {
+    fn from_bits(v: u64) -> Self {
+        Uint(v & Self::MAX)
+    }
+    fn to_bits(self) -> u64 {
+        self.0
+    }
+}
+impl_serializeable!([const BITS: usize] Uint<BITS>, Self::WIDTH);
+
+impl<T: Into<u64>, const BITS: usize> CastFrom<T> for Uint<BITS> {
+    fn cast_from(value: T) -> Self {
+        Uint(cmp::min(value.into(), Self::MAX))
+    }
+}
+
+impl<T: Into<u64>, const BITS: usize> CastFrom<T> for Truncated<Uint<BITS>> {
+    fn cast_from(value: T) -> Self {
+        Truncated(Uint(value.into() & Uint::<BITS>::MAX))
+    }
+}
+
+impl<const BITS: usize> Integer for Uint<BITS> {
+    fn to_f64(self) -> f64 {
+        self.0 as f64
+    }
+    fn from_f64(value: f64) -> Self {
+        Self::cast_from(round(value) as u64)
+    }
+}
+
+impl<const BITS: usize> PrimitiveType for Int<BITS> {
+    fn from_bits(v: u64) -> Self {
+        // The highest of the bits is the sign
+        let shift = 64 - Self::WIDTH;
+        Int(((v << shift) as i64) >> shift)
+    }
+    fn to_bits(self) -> u64 {
+        self.0 as u64
+    }
+}
+impl_serializeable!([const BITS: usize] Int<BITS>, Self::WIDTH);
+
+impl<T: Into<i64>, const BITS: usize> CastFrom<T> for Int<BITS> {
+    fn cast_from(value: T) -> Self {
+        Int(cmp::max(cmp::min(value.into(), Self::MAX), Self::MIN))
+    }
+}
+
+impl<T: Into<i64>, const BITS: usize> CastFrom<T> for Truncated<Int<BITS>> {
+    fn cast_from(value: T) -> Self {
+        // Keeps the lowest bits and sign extends the highest of them
+        let shift = 64 - Int::<BITS>::WIDTH;
+        Truncated(Int((value.into() << shift) >> shift))
+    }
+}
+
+impl<const BITS: usize> Integer for Int<BITS> {
+    fn to_f64(self) -> f64 {
+        self.0 as f64
+    }
+    fn from_f64(value: f64) -> Self {
+        Self::cast_from(round(value) as i64)
+    }
+}
 
 impl PrimitiveType for u8 {
     fn from_bits(v: u64) -> Self {
@@ -689,66 +1153,1198 @@ impl PrimitiveType for i64 {
 }
 impl_serializeable!(i64, 64);
 
-impl PrimitiveType for f16 {
-    fn from_bits(v: u64) -> Self {
-        f16::from_bits(v as u16)
+/// The Uavcan float16 type (IEEE 754 binary16)
+///
+/// Conversions from `f32` and `f64` round to nearest, ties to even. Infinities and NaN are preserved,
+/// while finite values outside the range of `f16` saturate to `f16::MAX` or `f16::MIN` (±65504) as DSDL requires.
+/// The strict conversions `try_from_f32` and `try_from_f64` fail on NaN and the infinities instead,
+/// for values like telemetry where a non-finite value on the wire is a bug.
+/// Comparisons follow the IEEE 754 rules (NaN is unordered, and `0.0 == -0.0`).
+/// Arithmetic is calculated in `f32` and converted back with `from_f32`, so finite results saturate as well.
+///
+/// # Examples
+/// ```
+/// use uavcan::types::*;
+///
+/// assert_eq!(f16::from_f32(1.0).as_bits(), 0x3c00);
+/// assert_eq!(f16::from_f32(100_000.0), f16::MAX);
+/// assert_eq!(f16::from_f64(-1.0e300), f16::MIN);
+/// assert_eq!(f16::try_from_f64(f64::INFINITY), Err(NonFiniteError::Infinite));
+/// assert!(f16::from_f32(0.5) < f16::from_f32(1.0));
+/// assert_eq!(f16::from_f32(1.5) * f16::from_f32(2.0) - f16::from_f32(0.5), f16::from_f32(2.5));
+/// ```
+#[allow(non_camel_case_types)]
+#[derive(Copy, Clone, Default)]
+pub struct f16(u16);
+
+impl f16 {
+    /// The largest finite value, 65504
+    pub const MAX: f16 = f16(0x7bff);
+
+    /// The smallest finite value, -65504
+    pub const MIN: f16 = f16(0xfbff);
+
+    pub const INFINITY: f16 = f16(0x7c00);
+
+    pub const NEG_INFINITY: f16 = f16(0xfc00);
+
+    pub const NAN: f16 = f16(0x7e00);
+
+    /// Constructs a `f16` from its binary representation
+    pub fn from_bits(bits: u16) -> f16 {
+        f16(bits)
     }
-    fn to_bits(self) -> u64 {
-        u64::from(f16::as_bits(self))
+
+    /// Returns the binary representation
+    pub fn as_bits(self) -> u16 {
+        self.0
     }
-}
-impl_serializeable!(f16, 16);
 
-impl PrimitiveType for f32 {
-    
-    #[cfg_attr(feature="clippy", allow(transmute_int_to_float))]
-    fn from_bits(v: u64) -> Self {
-        let mut v32 = v as u32;
-        const EXP_MASK: u32   = 0x7F80_0000;
-        const FRACT_MASK: u32 = 0x007F_FFFF;
-        if v32  & EXP_MASK == EXP_MASK && v32 & FRACT_MASK != 0 {
-            v32 = unsafe { lib::core::mem::transmute(lib::core::f32::NAN) };
+    /// Converts `value`, rounding to nearest (ties to even) and saturating finite values outside the range of `f16`
+    pub fn from_f32(value: f32) -> f16 {
+        let bits = value.to_bits();
+        let sign = ((bits >> 16) & 0x8000) as u16;
+        let exponent = ((bits >> 23) & 0xff) as i32;
+        let mantissa = bits & 0x007f_ffff;
+
+        if exponent == 0xff {
+            // Infinities are preserved and NaN stays a (quiet) NaN, keeping the upper bits of the payload
+            return if mantissa == 0 {
+                f16(sign | 0x7c00)
+            } else {
+                f16(sign | 0x7e00 | (mantissa >> 13) as u16)
+            };
         }
-        unsafe { lib::core::mem::transmute::<u32, f32>(v32) }
-    }
-    fn to_bits(self) -> u64 {
-        (unsafe { lib::core::mem::transmute::<f32, u32>(self) }) as u64
 
-            
-    }
-}
-impl_serializeable!(f32, 32);
+        let exponent = exponent - 127 + 15;
+        if exponent >= 0x1f {
+            return f16(sign | 0x7bff);
+        }
 
-impl PrimitiveType for f64 {
-    
-    #[cfg_attr(feature="clippy", allow(transmute_int_to_float))]
-    fn from_bits(mut v: u64) -> Self {
-        const EXP_MASK: u64   = 0x7FF0_0000_0000_0000;
-        const FRACT_MASK: u64 = 0x000F_FFFF_FFFF_FFFF;
-        if v & EXP_MASK == EXP_MASK && v & FRACT_MASK != 0 {
-            v = unsafe { lib::core::mem::transmute(lib::core::f64::NAN) };
+        if exponent <= 0 {
+            // Subnormal in f16, values below half the smallest subnormal round to zero
+            if exponent < -10 {
+                return f16(sign);
+            }
+            let mantissa = mantissa | 0x0080_0000;
+            let shift = (14 - exponent) as u32;
+            let truncated = mantissa >> shift;
+            let remainder = mantissa & ((1 << shift) - 1);
+            let halfway = 1 << (shift - 1);
+            let rounded = if remainder > halfway || (remainder == halfway && truncated & 1 == 1) {
+                truncated + 1
+            } else {
+                truncated
+            };
+            return f16(sign | rounded as u16);
+        }
+
+        let truncated = ((exponent as u32) << 10) | (mantissa >> 13);
+        let remainder = mantissa & 0x1fff;
+        let rounded = if remainder > 0x1000 || (remainder == 0x1000 && truncated & 1 == 1) {
+            truncated + 1
+        } else {
+            truncated
+        };
+
+        // Rounding up the largest values would give infinity
+        if rounded >= 0x7c00 {
+            f16(sign | 0x7bff)
+        } else {
+            f16(sign | rounded as u16)
         }
-        unsafe { lib::core::mem::transmute(v) }
     }
-    fn to_bits(self) -> u64 {
-        unsafe { lib::core::mem::transmute(self) }
+
+    /// Converts `value` like `from_f32`, rounding only once
+    pub fn from_f64(value: f64) -> f16 {
+        f16::from_f32(round_to_odd(value))
     }
-}
-impl_serializeable!(f64, 64);
 
-impl PrimitiveType for bool {
-    fn from_bits(v: u64) -> Self {
-        v & 1 == 1
+    /// Converts `value` like `from_f32`, but fails on NaN and the infinities instead of preserving them
+    ///
+    /// Finite values outside the range of `f16` still saturate.
+    pub fn try_from_f32(value: f32) -> Result<f16, NonFiniteError> {
+        f16::try_from_f64(f64::from(value))
     }
-    fn to_bits(self) -> u64 {
-        if self {
-            1
+
+    /// Converts `value` like `from_f64`, but fails on NaN and the infinities instead of preserving them
+    ///
+    /// Finite values outside the range of `f16` still saturate.
+    pub fn try_from_f64(value: f64) -> Result<f16, NonFiniteError> {
+        if value.is_nan() {
+            Err(NonFiniteError::NaN)
+        } else if value.is_infinite() {
+            Err(NonFiniteError::Infinite)
         } else {
-            0
+            Ok(f16::from_f64(value))
         }
     }
-}
-impl_serializeable!(bool, 1);
+
+    /// Converts to `f32`, which represents every `f16` value exactly
+    pub fn to_f32(self) -> f32 {
+        let sign = u32::from(self.0 & 0x8000) << 16;
+        let exponent = u32::from((self.0 >> 10) & 0x1f);
+        let mantissa = u32::from(self.0 & 0x03ff);
+
+        let bits = if exponent == 0x1f {
+            sign | 0x7f80_0000 | (mantissa << 13)
+        } else if exponent != 0 {
+            sign | ((exponent + 127 - 15) << 23) | (mantissa << 13)
+        } else if mantissa == 0 {
+            sign
+        } else {
+            // Subnormal in f16, but normal in f32
+            let mut exponent = 127 - 14;
+            let mut mantissa = mantissa;
+            while mantissa & 0x0400 == 0 {
+                mantissa <<= 1;
+                exponent -= 1;
+            }
+            sign | (exponent << 23) | ((mantissa & 0x03ff) << 13)
+        };
+        f32::from_bits(bits)
+    }
+
+    pub fn is_nan(self) -> bool {
+        self.0 & 0x7c00 == 0x7c00 && self.0 & 0x03ff != 0
+    }
+
+    pub fn is_infinite(self) -> bool {
+        self.0 & 0x7fff == 0x7c00
+    }
+
+    pub fn is_finite(self) -> bool {
+        self.0 & 0x7c00 != 0x7c00
+    }
+}
+
+/// The error of the strict conversions into `f16` (`f16::try_from_f32` and `f16::try_from_f64`)
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum NonFiniteError {
+    NaN,
+    Infinite,
+}
+
+/// Narrows `value` to `f32` rounding to odd, so rounding the result to `f16` gives the same value as rounding `value` directly
+///
+/// Finite values outside the range of `f32` become `f32::MAX` or `f32::MIN`, which `f16` saturates the same way.
+fn round_to_odd(value: f64) -> f32 {
+    let narrow = value as f32;
+    if value.is_nan() || value.is_infinite() || f64::from(narrow) == value {
+        narrow
+    } else if narrow.is_infinite() {
+        if value > 0.0 { lib::core::f32::MAX } else { lib::core::f32::MIN }
+    } else {
+        // Truncate towards zero and mark the result inexact with the lowest bit
+        let mut bits = narrow.to_bits();
+        if (f64::from(narrow) > value) == (value > 0.0) {
+            bits -= 1;
+        }
+        f32::from_bits(bits | 1)
+    }
+}
+
+impl From<f16> for f32 {
+    fn from(value: f16) -> f32 {
+        value.to_f32()
+    }
+}
+
+impl From<f32> for f16 {
+    fn from(value: f32) -> f16 {
+        f16::from_f32(value)
+    }
+}
+
+// The operations are exact in `f32`, so rounding the result once gives the correctly rounded `f16` result
+macro_rules! impl_f16_op {
+    ($($trait:ident, $method:ident, $assign_trait:ident, $assign_method:ident, $op:tt);*) => {$(
+        impl ops::$trait for f16 {
+            type Output = f16;
+
+            fn $method(self, other: f16) -> f16 {
+                f16::from_f32(self.to_f32() $op other.to_f32())
+            }
+        }
+
+        impl ops::$assign_trait for f16 {
+            fn $assign_method(&mut self, other: f16) {
+                *self = *self $op other;
+            }
+        }
+    )*};
+}
+
+impl_f16_op!(Add, add, AddAssign, add_assign, +; Sub, sub, SubAssign, sub_assign, -; Mul, mul, MulAssign, mul_assign, *; Div, div, DivAssign, div_assign, /);
+
+impl ops::Neg for f16 {
+    type Output = f16;
+
+    fn neg(self) -> f16 {
+        f16(self.0 ^ 0x8000)
+    }
+}
+
+impl cmp::PartialEq for f16 {
+    fn eq(&self, other: &f16) -> bool {
+        self.to_f32() == other.to_f32()
+    }
+}
+
+impl cmp::PartialOrd for f16 {
+    fn partial_cmp(&self, other: &f16) -> Option<cmp::Ordering> {
+        self.to_f32().partial_cmp(&other.to_f32())
+    }
+}
+
+impl fmt::Debug for f16 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_f32(), f)
+    }
+}
+
+impl fmt::Display for f16 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.to_f32(), f)
+    }
+}
+
+impl PrimitiveType for f16 {
+    fn from_bits(v: u64) -> Self {
+        float_codec::f16_from_bits(v)
+    }
+    fn to_bits(self) -> u64 {
+        float_codec::f16_to_bits(self)
+    }
+}
+impl_serializeable!(f16, 16);
+
+impl PrimitiveType for f32 {
+    fn from_bits(v: u64) -> Self {
+        float_codec::f32_from_bits(v)
+    }
+    fn to_bits(self) -> u64 {
+        float_codec::f32_to_bits(self)
+    }
+}
+impl_serializeable!(f32, 32);
+
+impl PrimitiveType for f64 {
+    fn from_bits(v: u64) -> Self {
+        float_codec::f64_from_bits(v)
+    }
+    fn to_bits(self) -> u64 {
+        float_codec::f64_to_bits(self)
+    }
+}
+impl_serializeable!(f64, 64);
+
+impl PrimitiveType for bool {
+    fn from_bits(v: u64) -> Self {
+        v & 1 == 1
+    }
+    fn to_bits(self) -> u64 {
+        if self {
+            1
+        } else {
+            0
+        }
+    }
+}
+impl_serializeable!(bool, 1);
+
+
+/// Conversion of native values into a field, with the DSDL cast mode of the field
+///
+/// The plain field types are `saturated`, which is the default cast mode, and clamp values to the range they can represent.
+/// Fields declared `truncated` have the type `Truncated<T>` and discard the bits that don't fit,
+/// which for floating point values means that values out of range become infinite.
+///
+/// # Examples
+/// ```
+/// use uavcan::types::*;
+///
+/// assert_eq!(u3::cast_from(12u8), u3::new(7));
+/// assert_eq!(Truncated::<u3>::cast_from(12u8), Truncated(u3::new(4)));
+///
+/// assert_eq!(i4::cast_from(-100i32), i4::new(-8));
+/// assert_eq!(Truncated::<i4>::cast_from(9i32), Truncated(i4::new(-7)));
+///
+/// assert_eq!(f16::cast_from(1.0e6f32), f16::MAX);
+/// assert_eq!(Truncated::<f16>::cast_from(1.0e6f32), Truncated(f16::INFINITY));
+/// ```
+pub trait CastFrom<T> {
+    fn cast_from(value: T) -> Self;
+}
+
+/// A field with the `truncated` cast mode, see `CastFrom`
+///
+/// The field is serialized exactly like `T`, only the conversion from native values differs.
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct Truncated<T>(pub T);
+
+impl<T: ::Serializable> ::Serializable for Truncated<T> {
+    const BIT_LENGTH_MIN: usize = T::BIT_LENGTH_MIN;
+
+    const BIT_LENGTH_MAX: usize = T::BIT_LENGTH_MAX;
+
+    const TAIL_BIT_LENGTH_MIN: usize = T::TAIL_BIT_LENGTH_MIN;
+
+    const TAIL_BIT_LENGTH_MAX: usize = T::TAIL_BIT_LENGTH_MAX;
+
+    const FLATTENED_FIELDS_NUMBER: usize = T::FLATTENED_FIELDS_NUMBER;
+
+    fn serialize(&self, flattened_field: &mut usize, bit: &mut usize, last_field: bool, buffer: &mut SerializationBuffer) -> SerializationResult {
+        self.0.serialize(flattened_field, bit, last_field, buffer)
+    }
+
+    fn deserialize(&mut self, flattened_field: &mut usize, bit: &mut usize, last_field: bool, buffer: &mut DeserializationBuffer) -> DeserializationResult {
+        self.0.deserialize(flattened_field, bit, last_field, buffer)
+    }
+}
+
+/// A nested appendable composite, serialized behind a delimiter header holding the length of its content in bytes
+///
+/// The header lets a receiver with another version of `T` skip the fields it doesn't know,
+/// and read the fields it knows but didn't receive as zero, so vendor types can be extended without breaking older nodes.
+/// The content is padded to whole bytes.
+#[derive(Copy, Clone, Default)]
+pub struct Delimited<T> {
+    pub value: T,
+    // The length from the received delimiter header in bytes, and the content bits read so far
+    length: u32,
+    bits_read: usize,
+}
+
+impl<T> Delimited<T> {
+    pub fn new(value: T) -> Self {
+        Delimited{value: value, length: 0, bits_read: 0}
+    }
+}
+
+impl<T> From<T> for Delimited<T> {
+    fn from(value: T) -> Self {
+        Delimited::new(value)
+    }
+}
+
+impl<T: cmp::PartialEq> cmp::PartialEq for Delimited<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Delimited<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Delimited").field(&self.value).finish()
+    }
+}
+
+impl<T: ::Serializable> Delimited<T> {
+    /// Counts the bits of the content by serializing it into a scratch buffer
+    fn content_bit_length(&self) -> usize {
+        let mut field = 0;
+        let mut bit = 0;
+        let mut bit_length = 0;
+        loop {
+            let mut scratch = [0u8; 8];
+            let mut buffer = SerializationBuffer::with_empty_buffer(&mut scratch);
+            let result = self.value.serialize(&mut field, &mut bit, false, &mut buffer);
+            bit_length += buffer.bit_length();
+            if result == SerializationResult::Finished {
+                return bit_length;
+            }
+        }
+    }
+}
+
+impl<T: ::Serializable> ::Serializable for Delimited<T> {
+    const BIT_LENGTH_MIN: usize = 32 + (T::BIT_LENGTH_MIN + 7) / 8 * 8;
+
+    const BIT_LENGTH_MAX: usize = 32 + (T::BIT_LENGTH_MAX + 7) / 8 * 8;
+
+    // The delimiter header, the fields of `T` and the padding (or the skipped fields when deserializing)
+    const FLATTENED_FIELDS_NUMBER: usize = T::FLATTENED_FIELDS_NUMBER + 2;
+
+    fn serialize(&self, flattened_field: &mut usize, bit: &mut usize, _last_field: bool, buffer: &mut SerializationBuffer) -> SerializationResult {
+        if *flattened_field == 0 {
+            let length = ((self.content_bit_length() + 7) / 8) as u32;
+            let mut header_field = 0;
+            if ::Serializable::serialize(&length, &mut header_field, bit, false, buffer) == SerializationResult::BufferFull {
+                return SerializationResult::BufferFull;
+            }
+            *flattened_field = 1;
+        }
+
+        if *flattened_field <= T::FLATTENED_FIELDS_NUMBER {
+            let mut field = *flattened_field - 1;
+            let result = self.value.serialize(&mut field, bit, false, buffer);
+            *flattened_field = field + 1;
+            if result == SerializationResult::BufferFull {
+                return SerializationResult::BufferFull;
+            }
+            *flattened_field = T::FLATTENED_FIELDS_NUMBER + 1;
+            *bit = 0;
+        }
+
+        // `bit` counts the padding bits written so far
+        let padding = (8 - self.content_bit_length() % 8) % 8;
+        let padding_bits = cmp::min(padding - *bit, buffer.bits_remaining());
+        if padding_bits > 0 {
+            buffer.push_bits(padding_bits, 0);
+            *bit += padding_bits;
+        }
+        if *bit < padding {
+            return SerializationResult::BufferFull;
+        }
+
+        *flattened_field = Self::FLATTENED_FIELDS_NUMBER;
+        *bit = 0;
+        SerializationResult::Finished
+    }
+
+    fn deserialize(&mut self, flattened_field: &mut usize, bit: &mut usize, _last_field: bool, buffer: &mut DeserializationBuffer) -> DeserializationResult {
+        if *flattened_field == 0 {
+            if *bit == 0 {
+                self.length = 0;
+            }
+            let mut header_field = 0;
+            if ::Serializable::deserialize(&mut self.length, &mut header_field, bit, false, buffer) != DeserializationResult::Finished {
+                return DeserializationResult::BufferInsufficient;
+            }
+            *flattened_field = 1;
+            self.bits_read = 0;
+        }
+
+        let content_bits = self.length as usize * 8;
+
+        if *flattened_field <= T::FLATTENED_FIELDS_NUMBER {
+            // The fields of `T` are only read from the content
+            let mut field = *flattened_field - 1;
+            let (result, bits_read) = {
+                let mut content = buffer.limited(content_bits - self.bits_read);
+                let available = content.bit_length();
+                let result = self.value.deserialize(&mut field, bit, false, &mut content);
+                (result, available - content.bit_length())
+            };
+            buffer.skip_bits(bits_read);
+            self.bits_read += bits_read;
+            *flattened_field = field + 1;
+
+            if result != DeserializationResult::Finished {
+                if self.bits_read < content_bits && !buffer.is_zero_extended() {
+                    return DeserializationResult::BufferInsufficient;
+                }
+
+                // The content ended before the fields of this version of `T` did, the missing bits are zero
+                let mut zero_extension = DeserializationBuffer::with_zero_extension();
+                if self.value.deserialize(&mut field, bit, false, &mut zero_extension) != DeserializationResult::Finished {
+                    return DeserializationResult::BufferInsufficient;
+                }
+            }
+            *flattened_field = T::FLATTENED_FIELDS_NUMBER + 1;
+            *bit = 0;
+        }
+
+        // Skips the padding and the fields this version of `T` doesn't know
+        let skipped_bits = cmp::min(content_bits - self.bits_read, buffer.bit_length());
+        buffer.skip_bits(skipped_bits);
+        self.bits_read += skipped_bits;
+        if self.bits_read < content_bits && !buffer.is_zero_extended() {
+            return DeserializationResult::BufferInsufficient;
+        }
+
+        *flattened_field = Self::FLATTENED_FIELDS_NUMBER;
+        *bit = 0;
+        DeserializationResult::Finished
+    }
+}
+
+/// A dynamic array of `u8` holding UTF-8 text, like the `name` of `GetNodeInfo` or the `text` of `LogMessage`
+///
+/// It is serialized exactly like the `Dynamic` array it wraps. The bytes received from other nodes are not validated
+/// until they're read with `as_str()`.
+///
+/// # Examples
+/// ```
+/// use uavcan::types::*;
+///
+/// let name: Utf8String<[u8; 80]> = "org.uavcan.node".parse().unwrap();
+///
+/// assert_eq!(name.as_str(), Ok("org.uavcan.node"));
+/// assert_eq!(name.0.length(), 15);
+/// assert!("too long".parse::<Utf8String<[u8; 4]>>().is_err());
+/// ```
+pub struct Utf8String<T: DynamicArray>(pub Dynamic<T>);
+
+/// The error when a string doesn't fit a `Utf8String`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct CapacityError {
+    /// The length of the string in bytes
+    pub length: usize,
+    pub max_length: usize,
+}
+
+impl fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a string of {} bytes doesn't fit {} bytes", self.length, self.max_length)
+    }
+}
+
+impl<T: DynamicArray> Utf8String<T> where Dynamic<T>: AsRef<[u8]> {
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+
+    /// Returns the text, or an error if the bytes are not valid UTF-8
+    pub fn as_str(&self) -> Result<&str, lib::core::str::Utf8Error> {
+        lib::core::str::from_utf8(self.as_bytes())
+    }
+}
+
+impl<T: DynamicArray> From<Dynamic<T>> for Utf8String<T> {
+    fn from(bytes: Dynamic<T>) -> Self {
+        Utf8String(bytes)
+    }
+}
+
+impl<T: DynamicArray> Default for Utf8String<T> where Dynamic<T>: Default {
+    fn default() -> Self {
+        Utf8String(Dynamic::default())
+    }
+}
+
+impl<T: DynamicArray> Clone for Utf8String<T> where Dynamic<T>: Clone {
+    fn clone(&self) -> Self {
+        Utf8String(self.0.clone())
+    }
+}
+
+impl<T: DynamicArray> cmp::PartialEq for Utf8String<T> where Dynamic<T>: cmp::PartialEq {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T: DynamicArray> fmt::Debug for Utf8String<T> where Dynamic<T>: AsRef<[u8]> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.as_str() {
+            Ok(text) => f.debug_tuple("Utf8String").field(&text).finish(),
+            Err(_) => f.debug_tuple("Utf8String").field(&self.as_bytes()).finish(),
+        }
+    }
+}
+
+impl<T: DynamicArray> ::Serializable for Utf8String<T> where Dynamic<T>: ::Serializable {
+    const BIT_LENGTH_MIN: usize = <Dynamic<T> as ::Serializable>::BIT_LENGTH_MIN;
+
+    const BIT_LENGTH_MAX: usize = <Dynamic<T> as ::Serializable>::BIT_LENGTH_MAX;
+
+    const TAIL_BIT_LENGTH_MIN: usize = <Dynamic<T> as ::Serializable>::TAIL_BIT_LENGTH_MIN;
+
+    const TAIL_BIT_LENGTH_MAX: usize = <Dynamic<T> as ::Serializable>::TAIL_BIT_LENGTH_MAX;
+
+    const FLATTENED_FIELDS_NUMBER: usize = <Dynamic<T> as ::Serializable>::FLATTENED_FIELDS_NUMBER;
+
+    fn serialize(&self, flattened_field: &mut usize, bit: &mut usize, last_field: bool, buffer: &mut SerializationBuffer) -> SerializationResult {
+        self.0.serialize(flattened_field, bit, last_field, buffer)
+    }
+
+    fn deserialize(&mut self, flattened_field: &mut usize, bit: &mut usize, last_field: bool, buffer: &mut DeserializationBuffer) -> DeserializationResult {
+        self.0.deserialize(flattened_field, bit, last_field, buffer)
+    }
+}
+
+/// The `bool` arrays that can be packed in a `BitArray` or a `DynamicBitArray`, `[bool; N]` for `N` up to 256
+pub trait BoolArray {
+    /// The bytes holding the packed array
+    type Bytes: Copy + AsRef<[u8]> + AsMut<[u8]>;
+
+    const LENGTH: usize;
+
+    /// The bit length of the length prefix of a dynamic array
+    const LENGTH_BITS: usize;
+
+    const ZEROED: Self::Bytes;
+}
+
+/// A `bool[N]` field packed one bit per element, like it is on the wire
+///
+/// `T` is `[bool; N]`, it only selects the length of the array.
+///
+/// # Examples
+/// ```
+/// use uavcan::types::*;
+///
+/// let mut flags = BitArray::from([true, false, false]);
+/// flags.set(2, true);
+///
+/// assert!(flags.get(0));
+/// assert_eq!(flags.iter().filter(|flag| *flag).count(), 2);
+/// ```
+pub struct BitArray<T: BoolArray> {
+    bytes: T::Bytes,
+}
+
+/// A `bool[<=N]` field packed one bit per element, like it is on the wire
+///
+/// `T` is `[bool; N]`, it only selects the maximum length of the array.
+pub struct DynamicBitArray<T: BoolArray> {
+    bytes: T::Bytes,
+    length: usize,
+}
+
+fn get_bit(bytes: &[u8], index: usize) -> bool {
+    bytes[index / 8] & (1 << (index % 8)) != 0
+}
+
+fn set_bit(bytes: &mut [u8], index: usize, value: bool) {
+    if value {
+        bytes[index / 8] |= 1 << (index % 8);
+    } else {
+        bytes[index / 8] &= !(1 << (index % 8));
+    }
+}
+
+impl<T: BoolArray> BitArray<T> {
+    /// Constructs a new `BitArray` with all elements `false`
+    pub fn new() -> Self {
+        BitArray{bytes: T::ZEROED}
+    }
+
+    pub fn length(&self) -> usize {
+        T::LENGTH
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        assert!(index < T::LENGTH, "Index out of range of the BitArray");
+        get_bit(self.bytes.as_ref(), index)
+    }
+
+    pub fn set(&mut self, index: usize, value: bool) {
+        assert!(index < T::LENGTH, "Index out of range of the BitArray");
+        set_bit(self.bytes.as_mut(), index, value);
+    }
+
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = bool> + 'a {
+        (0..T::LENGTH).map(move |index| self.get(index))
+    }
+}
+
+impl<T: BoolArray + AsRef<[bool]>> From<T> for BitArray<T> {
+    fn from(array: T) -> Self {
+        let mut bits = Self::new();
+        for (index, value) in array.as_ref().iter().enumerate() {
+            bits.set(index, *value);
+        }
+        bits
+    }
+}
+
+impl<T: BoolArray> DynamicBitArray<T> {
+    /// Constructs a new empty `DynamicBitArray`
+    pub fn new() -> Self {
+        DynamicBitArray{bytes: T::ZEROED, length: 0}
+    }
+
+    /// Constructs a new `DynamicBitArray` holding `data`
+    pub fn with_data(data: &[bool]) -> Self {
+        let mut bits = Self::new();
+        for value in data {
+            bits.push(*value);
+        }
+        bits
+    }
+
+    /// Push an element to the end of the `DynamicBitArray`. Length will increase by one after this operation.
+    pub fn push(&mut self, value: bool) {
+        assert!(self.length < T::LENGTH, "Can't push data to full array");
+        self.length += 1;
+        let index = self.length - 1;
+        self.set(index, value);
+    }
+
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// Set length of the array, new elements are `false`
+    pub fn set_length(&mut self, length: usize) {
+        assert!(length <= T::LENGTH, "Can't grow array beyond its maximum length");
+        for index in length..self.length {
+            set_bit(self.bytes.as_mut(), index, false);
+        }
+        self.length = length;
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        assert!(index < self.length, "Index out of range of the DynamicBitArray");
+        get_bit(self.bytes.as_ref(), index)
+    }
+
+    pub fn set(&mut self, index: usize, value: bool) {
+        assert!(index < self.length, "Index out of range of the DynamicBitArray");
+        set_bit(self.bytes.as_mut(), index, value);
+    }
+
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = bool> + 'a {
+        (0..self.length).map(move |index| self.get(index))
+    }
+}
+
+impl<T: BoolArray> Default for BitArray<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: BoolArray> Default for DynamicBitArray<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: BoolArray> Copy for BitArray<T> {}
+
+impl<T: BoolArray> Clone for BitArray<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: BoolArray> Copy for DynamicBitArray<T> {}
+
+impl<T: BoolArray> Clone for DynamicBitArray<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: BoolArray> cmp::PartialEq for BitArray<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes.as_ref() == other.bytes.as_ref()
+    }
+}
+
+impl<T: BoolArray> cmp::PartialEq for DynamicBitArray<T> {
+    fn eq(&self, other: &Self) -> bool {
+        // The elements beyond the length are always `false`
+        self.length == other.length && self.bytes.as_ref() == other.bytes.as_ref()
+    }
+}
+
+impl<T: BoolArray> fmt::Debug for BitArray<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: BoolArray> fmt::Debug for DynamicBitArray<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: BoolArray> ::Serializable for BitArray<T> {
+    const BIT_LENGTH_MIN: usize = T::LENGTH;
+
+    const BIT_LENGTH_MAX: usize = T::LENGTH;
+
+    const FLATTENED_FIELDS_NUMBER: usize = T::LENGTH;
+
+    fn serialize(&self, flattened_field: &mut usize, bit: &mut usize, _last_field: bool, buffer: &mut SerializationBuffer) -> SerializationResult {
+        while *flattened_field < T::LENGTH {
+            let mut element_field = 0;
+            if ::Serializable::serialize(&self.get(*flattened_field), &mut element_field, bit, false, buffer) == SerializationResult::BufferFull {
+                return SerializationResult::BufferFull;
+            }
+            *flattened_field += 1;
+        }
+
+        *bit = 0;
+        SerializationResult::Finished
+    }
+
+    fn deserialize(&mut self, flattened_field: &mut usize, bit: &mut usize, _last_field: bool, buffer: &mut DeserializationBuffer) -> DeserializationResult {
+        while *flattened_field < T::LENGTH {
+            let mut element_field = 0;
+            let mut element = false;
+            if ::Serializable::deserialize(&mut element, &mut element_field, bit, false, buffer) == DeserializationResult::BufferInsufficient {
+                return DeserializationResult::BufferInsufficient;
+            }
+            self.set(*flattened_field, element);
+            *flattened_field += 1;
+        }
+
+        *bit = 0;
+        DeserializationResult::Finished
+    }
+}
+
+impl<T: BoolArray> ::Serializable for DynamicBitArray<T> {
+    const BIT_LENGTH_MIN: usize = T::LENGTH_BITS;
+
+    const BIT_LENGTH_MAX: usize = T::LENGTH_BITS + T::LENGTH;
+
+    // The tail array optimization never applies to arrays of `bool`, the length is always serialized
+    const FLATTENED_FIELDS_NUMBER: usize = T::LENGTH + 1;
+
+    fn serialize(&self, flattened_field: &mut usize, bit: &mut usize, _last_field: bool, buffer: &mut SerializationBuffer) -> SerializationResult {
+        if *flattened_field == 0 {
+            let type_bits_remaining = T::LENGTH_BITS - *bit;
+            let buffer_bits_remaining = buffer.bits_remaining();
+
+            if buffer_bits_remaining == 0 {
+                return SerializationResult::BufferFull;
+            } else if buffer_bits_remaining < type_bits_remaining {
+                buffer.push_bits(buffer_bits_remaining, (self.length >> *bit) as u64);
+                *bit += buffer_bits_remaining;
+                return SerializationResult::BufferFull;
+            }
+            buffer.push_bits(type_bits_remaining, (self.length >> *bit) as u64);
+            *flattened_field = 1;
+            *bit = 0;
+        }
+
+        while *flattened_field - 1 < self.length {
+            let mut element_field = 0;
+            if ::Serializable::serialize(&self.get(*flattened_field - 1), &mut element_field, bit, false, buffer) == SerializationResult::BufferFull {
+                return SerializationResult::BufferFull;
+            }
+            *flattened_field += 1;
+        }
+
+        *flattened_field = Self::FLATTENED_FIELDS_NUMBER;
+        *bit = 0;
+        SerializationResult::Finished
+    }
+
+    fn deserialize(&mut self, flattened_field: &mut usize, bit: &mut usize, _last_field: bool, buffer: &mut DeserializationBuffer) -> DeserializationResult {
+        if *flattened_field == 0 {
+            if *bit == 0 {
+                self.bytes = T::ZEROED;
+                self.length = 0;
+            }
+
+            let buffer_len = buffer.bit_length();
+            if buffer.is_zero_extended() {
+                // The bits of the length that were not received are zero
+            } else if buffer_len == 0 {
+                return DeserializationResult::BufferInsufficient;
+            } else if buffer_len + *bit < T::LENGTH_BITS {
+                self.length |= (buffer.pop_bits(buffer_len) as usize) << *bit;
+                *bit += buffer_len;
+                return DeserializationResult::BufferInsufficient;
+            } else {
+                self.length |= (buffer.pop_bits(T::LENGTH_BITS - *bit) as usize) << *bit;
+            }
+            *flattened_field = 1;
+            *bit = 0;
+
+            // The length field may encode more elements than the array has room for
+            self.length = cmp::min(self.length, T::LENGTH);
+        }
+
+        while *flattened_field - 1 < self.length {
+            let mut element_field = 0;
+            let mut element = false;
+            if ::Serializable::deserialize(&mut element, &mut element_field, bit, false, buffer) == DeserializationResult::BufferInsufficient {
+                return DeserializationResult::BufferInsufficient;
+            }
+            self.set(*flattened_field - 1, element);
+            *flattened_field += 1;
+        }
+
+        *flattened_field = Self::FLATTENED_FIELDS_NUMBER;
+        *bit = 0;
+        DeserializationResult::Finished
+    }
+}
+
+/// An optional value is a union of an empty structure and `T`
+///
+/// It is serialized exactly like the DSDL union
+/// ```text
+/// @union
+/// Empty empty
+/// T value
+/// ```
+/// with a one bit tag, `0` for `None` and `1` for `Some`. An all zero payload decodes to `None`.
+impl<T: ::Serializable> ::Serializable for Option<T> {
+    const BIT_LENGTH_MIN: usize = 1;
+
+    const BIT_LENGTH_MAX: usize = 1 + T::BIT_LENGTH_MAX;
+
+    const TAIL_BIT_LENGTH_MIN: usize = 1;
+
+    const TAIL_BIT_LENGTH_MAX: usize = 1 + T::TAIL_BIT_LENGTH_MAX;
+
+    // The tag and the fields of `T`
+    const FLATTENED_FIELDS_NUMBER: usize = 1 + T::FLATTENED_FIELDS_NUMBER;
+
+    fn serialize(&self, flattened_field: &mut usize, bit: &mut usize, last_field: bool, buffer: &mut SerializationBuffer) -> SerializationResult {
+        if *flattened_field == 0 {
+            if buffer.bits_remaining() == 0 {
+                return SerializationResult::BufferFull;
+            }
+            buffer.push_bits(1, self.is_some() as u64);
+            *flattened_field = 1;
+            *bit = 0;
+        }
+
+        if let Some(ref value) = *self {
+            let mut field = *flattened_field - 1;
+            let result = value.serialize(&mut field, bit, last_field, buffer);
+            *flattened_field = field + 1;
+            if result == SerializationResult::BufferFull {
+                return SerializationResult::BufferFull;
+            }
+        }
+
+        *flattened_field = Self::FLATTENED_FIELDS_NUMBER;
+        *bit = 0;
+        SerializationResult::Finished
+    }
+
+    fn deserialize(&mut self, flattened_field: &mut usize, bit: &mut usize, last_field: bool, buffer: &mut DeserializationBuffer) -> DeserializationResult {
+        if *flattened_field == 0 {
+            // The tag reads as zero when the buffer is zero extended
+            let tag = if buffer.bit_length() > 0 {
+                buffer.pop_bits(1)
+            } else if buffer.is_zero_extended() {
+                0
+            } else {
+                return DeserializationResult::BufferInsufficient;
+            };
+            *self = if tag == 1 { Some(unsafe { lib::core::mem::zeroed() }) } else { None };
+            *flattened_field = 1;
+            *bit = 0;
+        }
+
+        if let Some(ref mut value) = *self {
+            let mut field = *flattened_field - 1;
+            let result = value.deserialize(&mut field, bit, last_field, buffer);
+            *flattened_field = field + 1;
+            if result == DeserializationResult::BufferInsufficient {
+                return DeserializationResult::BufferInsufficient;
+            }
+        }
+
+        *flattened_field = Self::FLATTENED_FIELDS_NUMBER;
+        *bit = 0;
+        DeserializationResult::Finished
+    }
+}
+
+/// The integer types holding the raw value of a `Scaled` number
+pub trait Integer: Copy {
+    fn to_f64(self) -> f64;
+
+    /// Rounds to the nearest integer, saturating at the limits of the type (NaN becomes zero)
+    fn from_f64(value: f64) -> Self;
+}
+
+/// Rounds half away from zero, `f64::round` is not available without std
+fn round(value: f64) -> f64 {
+    if value < 0.0 {
+        value - 0.5
+    } else {
+        value + 0.5
+    }
+}
+
+/// The scale of a `Scaled` number, the physical value of a raw value of one
+pub trait Scale {
+    const FACTOR: f64;
+}
+
+/// Scale of tenths, like decivolts
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Deci;
+
+/// Scale of hundredths, like centidegrees
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Centi;
+
+/// Scale of thousandths, like millivolts
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Milli;
+
+/// Scale of millionths, like microseconds
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Micro;
+
+impl Scale for Deci { const FACTOR: f64 = 1e-1; }
+impl Scale for Centi { const FACTOR: f64 = 1e-2; }
+impl Scale for Milli { const FACTOR: f64 = 1e-3; }
+impl Scale for Micro { const FACTOR: f64 = 1e-6; }
+
+/// An integer with an implied scale, like a temperature in centidegrees or a voltage in millivolts
+///
+/// It is serialized exactly like the raw integer `T`. Other scales are defined by implementing `Scale`.
+///
+/// # Examples
+/// ```
+/// use uavcan::types::*;
+///
+/// struct Sixteenths;
+///
+/// impl Scale for Sixteenths {
+///     const FACTOR: f64 = 1.0 / 16.0;
+/// }
+///
+/// let temperature = Scaled::<i16, Centi>::from_f32(21.5);
+/// assert_eq!(temperature.raw, 2150);
+/// assert_eq!(temperature.to_f32(), 21.5);
+///
+/// // Values outside the range of the raw integer saturate
+/// assert_eq!(Scaled::<u12, Milli>::from_f32(5.0).raw, u12::new(4095));
+/// assert_eq!(Scaled::<u8, Sixteenths>::from_f32(-1.0).raw, 0);
+/// ```
+pub struct Scaled<T, S> {
+    pub raw: T,
+    scale: lib::core::marker::PhantomData<S>,
+}
+
+impl<T, S> Scaled<T, S> {
+    pub fn from_raw(raw: T) -> Self {
+        Scaled{raw: raw, scale: lib::core::marker::PhantomData}
+    }
+}
+
+impl<T: Integer, S: Scale> Scaled<T, S> {
+    /// Converts the physical value to the nearest raw value, saturating at the limits of `T`
+    pub fn from_f32(value: f32) -> Self {
+        Self::from_raw(T::from_f64(f64::from(value) / S::FACTOR))
+    }
+
+    pub fn to_f32(&self) -> f32 {
+        (self.raw.to_f64() * S::FACTOR) as f32
+    }
+}
+
+impl<T: Copy, S> Copy for Scaled<T, S> {}
+
+impl<T: Clone, S> Clone for Scaled<T, S> {
+    fn clone(&self) -> Self {
+        Scaled::from_raw(self.raw.clone())
+    }
+}
+
+impl<T: Default, S> Default for Scaled<T, S> {
+    fn default() -> Self {
+        Scaled::from_raw(T::default())
+    }
+}
+
+impl<T: cmp::PartialEq, S> cmp::PartialEq for Scaled<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl<T: fmt::Debug, S> fmt::Debug for Scaled<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Scaled").field(&self.raw).finish()
+    }
+}
+
+impl<T: ::Serializable, S> ::Serializable for Scaled<T, S> {
+    const BIT_LENGTH_MIN: usize = T::BIT_LENGTH_MIN;
+
+    const BIT_LENGTH_MAX: usize = T::BIT_LENGTH_MAX;
+
+    const FLATTENED_FIELDS_NUMBER: usize = T::FLATTENED_FIELDS_NUMBER;
+
+    fn serialize(&self, flattened_field: &mut usize, bit: &mut usize, last_field: bool, buffer: &mut SerializationBuffer) -> SerializationResult {
+        self.raw.serialize(flattened_field, bit, last_field, buffer)
+    }
+
+    fn deserialize(&mut self, flattened_field: &mut usize, bit: &mut usize, last_field: bool, buffer: &mut DeserializationBuffer) -> DeserializationResult {
+        self.raw.deserialize(flattened_field, bit, last_field, buffer)
+    }
+}
+
+macro_rules! impl_cast_native {
+    ($($type:ident: $wide_type:ident),*) => {$(
+        impl<T: Into<$wide_type>> CastFrom<T> for $type {
+            fn cast_from(value: T) -> Self {
+                cmp::max(cmp::min(value.into(), $type::MAX as $wide_type), $type::MIN as $wide_type) as $type
+            }
+        }
+
+        impl<T: Into<$wide_type>> CastFrom<T> for Truncated<$type> {
+            fn cast_from(value: T) -> Self {
+                Truncated(value.into() as $type)
+            }
+        }
+
+        impl Integer for $type {
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+            fn from_f64(value: f64) -> Self {
+                $type::cast_from(round(value) as $wide_type)
+            }
+        }
+    )*};
+}
+
+impl_cast_native!(u8: u64, u16: u64, u32: u64, u64: u64, i8: i64, i16: i64, i32: i64, i64: i64);
+
+impl<T: Into<f64>> CastFrom<T> for f64 {
+    fn cast_from(value: T) -> Self {
+        value.into()
+    }
+}
+
+impl<T: Into<f64>> CastFrom<T> for Truncated<f64> {
+    fn cast_from(value: T) -> Self {
+        Truncated(value.into())
+    }
+}
+
+impl<T: Into<f64>> CastFrom<T> for f32 {
+    fn cast_from(value: T) -> Self {
+        // Infinities are preserved, only finite values are clamped
+        let value = value.into();
+        if value.is_infinite() {
+            value as f32
+        } else if value > f64::from(lib::core::f32::MAX) {
+            lib::core::f32::MAX
+        } else if value < f64::from(lib::core::f32::MIN) {
+            lib::core::f32::MIN
+        } else {
+            value as f32
+        }
+    }
+}
+
+impl<T: Into<f64>> CastFrom<T> for Truncated<f32> {
+    fn cast_from(value: T) -> Self {
+        Truncated(value.into() as f32)
+    }
+}
+
+impl<T: Into<f64>> CastFrom<T> for f16 {
+    fn cast_from(value: T) -> Self {
+        f16::from_f64(value.into())
+    }
+}
+
+impl<T: Into<f64>> CastFrom<T> for Truncated<f16> {
+    fn cast_from(value: T) -> Self {
+        // Values from halfway between `f16::MAX` and the next power of two round to infinity
+        let value = value.into() as f32;
+        if value >= 65520.0 {
+            Truncated(f16::INFINITY)
+        } else if value <= -65520.0 {
+            Truncated(f16::NEG_INFINITY)
+        } else {
+            Truncated(f16::from_f32(value))
+        }
+    }
+}
 
 
 
@@ -758,6 +2354,149 @@ mod tests {
 
     use types::*;
 
+    #[test]
+    fn cast_modes() {
+        assert_eq!(u7::cast_from(200u8), u7::new(127));
+        assert_eq!(u7::cast_from(100u8), u7::new(100));
+        assert_eq!(Truncated::<u7>::cast_from(200u8), Truncated(u7::new(72)));
+        assert_eq!(u40::cast_from(u64::max_value()), u40::new(0xff_ffff_ffff));
+
+        assert_eq!(i7::cast_from(100i8), i7::new(63));
+        assert_eq!(i7::cast_from(-100i8), i7::new(-64));
+        assert_eq!(Truncated::<i7>::cast_from(100i8), Truncated(i7::new(-28)));
+        assert_eq!(Truncated::<i7>::cast_from(-1i8), Truncated(i7::new(-1)));
+
+        assert_eq!(u8::cast_from(300u16), 255);
+        assert_eq!(Truncated::<u8>::cast_from(300u16), Truncated(44));
+        assert_eq!(i16::cast_from(-40000i32), -32768);
+        assert_eq!(Truncated::<i16>::cast_from(40000i32), Truncated(-25536));
+
+        assert_eq!(f32::cast_from(1.0e300), ::lib::core::f32::MAX);
+        assert_eq!(f32::cast_from(::lib::core::f64::NEG_INFINITY), ::lib::core::f32::NEG_INFINITY);
+        assert_eq!(Truncated::<f32>::cast_from(1.0e300), Truncated(::lib::core::f32::INFINITY));
+        assert_eq!(f16::cast_from(-1.0e300), f16::MIN);
+        assert_eq!(Truncated::<f16>::cast_from(-70000.0f32), Truncated(f16::NEG_INFINITY));
+        assert_eq!(Truncated::<f16>::cast_from(65504.0f32), Truncated(f16::MAX));
+
+        assert_eq!(Uint::<7>::cast_from(200u8), Uint::new(127));
+        assert_eq!(Truncated::<Uint<7>>::cast_from(200u8), Truncated(Uint::new(72)));
+        assert_eq!(Int::<7>::cast_from(-100i8), Int::new(-64));
+        assert_eq!(Truncated::<Int<7>>::cast_from(100i8), Truncated(Int::new(-28)));
+    }
+
+    #[test]
+    fn const_generic_integer_ranges() {
+        assert_eq!(Uint::<2>::MAX, 3);
+        assert_eq!(Uint::<64>::MAX, u64::max_value());
+        assert_eq!((Int::<2>::MIN, Int::<2>::MAX), (-2, 1));
+        assert_eq!((Int::<64>::MIN, Int::<64>::MAX), (i64::min_value(), i64::max_value()));
+        assert_eq!(Int::<7>::new(-3).to_bits(), i7::new(-3).to_bits());
+        assert_eq!(Int::<7>::from_bits(0x7d), Int::new(-3));
+    }
+
+    #[test]
+    fn bit_arrays() {
+        let mut flags = BitArray::<[bool; 10]>::new();
+        flags.set(9, true);
+        flags.set(3, true);
+        assert!(flags.iter().eq([false, false, false, true, false, false, false, false, false, true].iter().cloned()));
+        assert_eq!(flags, BitArray::from([false, false, false, true, false, false, false, false, false, true]));
+
+        let mut dynamic = DynamicBitArray::<[bool; 9]>::with_data(&[true, true, false]);
+        assert_eq!(dynamic.length(), 3);
+        dynamic.set_length(1);
+        dynamic.set_length(2);
+        assert_eq!(dynamic, DynamicBitArray::with_data(&[true, false]));
+        assert_eq!(<DynamicBitArray<[bool; 9]> as ::Serializable>::BIT_LENGTH_MAX, 4 + 9);
+    }
+
+    #[test]
+    fn scaled_integers() {
+        assert_eq!(Scaled::<i16, Centi>::from_f32(-12.345).raw, -1235);
+        assert_eq!(Scaled::<i16, Centi>::from_raw(-1235).to_f32(), -12.35);
+        assert_eq!(Scaled::<i16, Centi>::from_f32(1000.0).raw, i16::max_value());
+        assert_eq!(Scaled::<i16, Centi>::from_f32(-1000.0).raw, i16::min_value());
+        assert_eq!(Scaled::<u16, Milli>::from_f32(3.3).raw, 3300);
+        assert_eq!(Scaled::<u16, Milli>::from_f32(-3.3).raw, 0);
+        assert_eq!(Scaled::<i7, Deci>::from_f32(-10.0).raw, i7::new(-64));
+        assert_eq!(Scaled::<u64, Micro>::from_f32(::lib::core::f32::NAN).raw, 0);
+    }
+
+    #[test]
+    fn f16_saturation_policy() {
+        assert_eq!(f16::from_f64(65504.0).as_bits(), 0x7bff);
+        assert_eq!(f16::from_f64(1.0e300).as_bits(), 0x7bff);
+        assert_eq!(f16::from_f64(-1.0e300).as_bits(), 0xfbff);
+        assert_eq!(f16::from_f64(::lib::core::f64::INFINITY).as_bits(), 0x7c00);
+        assert!(f16::from_f64(::lib::core::f64::NAN).is_nan());
+        assert!(f16::from_f64(1.0e300).is_finite());
+        assert!(!f16::from_f64(::lib::core::f64::NEG_INFINITY).is_finite());
+
+        // 1 + 2^-11 + 2^-40 is just above a tie, rounding it to f32 first would make it a tie rounding down to 1
+        assert_eq!(f16::from_f64(1.0 + 0.000_488_281_25 + 9.094_947_017_729_282e-13).as_bits(), 0x3c01);
+        assert_eq!(f16::from_f64(-1.0 - 0.000_488_281_25 - 9.094_947_017_729_282e-13).as_bits(), 0xbc01);
+        assert_eq!(f16::from_f64(1.0 + 0.000_488_281_25).as_bits(), 0x3c00);
+        assert_eq!(f16::from_f64(1.0e-300).as_bits(), 0x0000);
+
+        assert_eq!(f16::try_from_f32(-70000.0), Ok(f16::MIN));
+        assert_eq!(f16::try_from_f32(0.5).map(f16::to_f32), Ok(0.5));
+        assert_eq!(f16::try_from_f32(::lib::core::f32::NEG_INFINITY), Err(NonFiniteError::Infinite));
+        assert_eq!(f16::try_from_f64(::lib::core::f64::NAN), Err(NonFiniteError::NaN));
+    }
+
+    #[test]
+    fn f16_conversions() {
+        assert_eq!(f16::from_f32(1.0).as_bits(), 0x3c00);
+        assert_eq!(f16::from_f32(-2.0).as_bits(), 0xc000);
+        assert_eq!(f16::from_f32(65504.0).as_bits(), 0x7bff);
+        assert_eq!(f16::from_f32(1.0e9).as_bits(), 0x7bff);
+        assert_eq!(f16::from_f32(-1.0e9).as_bits(), 0xfbff);
+        assert_eq!(f16::from_f32(65520.0).as_bits(), 0x7bff);
+        assert_eq!(f16::from_f32(::lib::core::f32::INFINITY).as_bits(), 0x7c00);
+        assert_eq!(f16::from_f32(::lib::core::f32::NEG_INFINITY).as_bits(), 0xfc00);
+        assert!(f16::from_f32(::lib::core::f32::NAN).is_nan());
+
+        // Ties round to even (1 + 2^-11 and 1 + 3 * 2^-11)
+        assert_eq!(f16::from_f32(f32::from_bits(0x3f80_1000)).as_bits(), 0x3c00);
+        assert_eq!(f16::from_f32(f32::from_bits(0x3f80_3000)).as_bits(), 0x3c02);
+
+        // Subnormals (2^-24 is the smallest one)
+        assert_eq!(f16::from_f32(f32::from_bits(0x3380_0000)).as_bits(), 0x0001);
+        assert_eq!(f16::from_f32(f32::from_bits(0x3300_0000)).as_bits(), 0x0000);
+        assert_eq!(f16::from_f32(f32::from_bits(0x3340_0000)).as_bits(), 0x0001);
+        assert_eq!(f16::from_bits(0x0001).to_f32(), f32::from_bits(0x3380_0000));
+
+        for bits in 0..0x7c00u16 {
+            assert_eq!(f16::from_f32(f16::from_bits(bits).to_f32()).as_bits(), bits);
+        }
+    }
+
+    #[test]
+    fn f16_comparisons() {
+        assert_eq!(f16::from_f32(0.0), f16::from_f32(-0.0));
+        assert_ne!(f16::NAN, f16::NAN);
+        assert!(f16::from_f32(-1.0) < f16::from_f32(0.5));
+        assert!(f16::MAX < f16::INFINITY);
+        assert!(f16::NEG_INFINITY < f16::MIN);
+        assert_eq!(f16::NAN.partial_cmp(&f16::MAX), None);
+    }
+
+    #[test]
+    fn f16_arithmetic() {
+        let mut value = f16::from_f32(1.0);
+        value += f16::from(0.25f32);
+        assert_eq!(value, f16::from_f32(1.25));
+        assert_eq!(value / f16::from_f32(0.5), f16::from_f32(2.5));
+        assert_eq!(-value, f16::from_f32(-1.25));
+        assert_eq!(f16::from_f32(1.0) - f16::from_f32(1.0), f16::from_f32(0.0));
+
+        // 2049 is not representable, the result is rounded to even
+        assert_eq!((f16::from_f32(2048.0) + f16::from_f32(1.0)).as_bits(), f16::from_f32(2048.0).as_bits());
+        assert_eq!(f16::MAX * f16::from_f32(2.0), f16::MAX);
+        assert!((f16::INFINITY - f16::INFINITY).is_nan());
+        assert!((f16::from_f32(0.0) / f16::from_f32(0.0)).is_nan());
+    }
+
     #[test]
     fn dynamic_array_with_data() {
         let a: [u8; 5] = [1, 2, 3, 4, 5];
@@ -775,10 +2514,22 @@ mod tests {
         assert_eq!(d1, d2);
     }
     
+    #[test]
+    fn utf8_string() {
+        let text: Utf8String<[u8; 8]> = "grüß".parse().unwrap();
+        assert_eq!(text.as_bytes().len(), 6);
+        assert_eq!(text.as_str(), Ok("grüß"));
+        assert_eq!("too long!".parse::<Utf8String<[u8; 8]>>(), Err(CapacityError{length: 9, max_length: 8}));
+
+        let invalid = Utf8String(Dynamic::<[u8; 8]>::with_data(&[b'a', 0xc3]));
+        assert!(invalid.as_str().is_err());
+        assert_eq!(invalid.as_bytes(), b"a\xc3");
+    }
+
     #[test]
     fn dynamic_array_push() {
         let mut a = Dynamic::<[u8; 15]>::new();
-        assert_eq!(a.as_ref(), &[]);
+        assert_eq!(a.as_ref(), &[0u8; 0]);
 
         a.push(12);
         assert_eq!(a.as_ref(), &[12]);
@@ -786,4 +2537,58 @@ mod tests {
         a.push(120);
         assert_eq!(a.as_ref(), &[12, 120]);
     }
+
+    #[test]
+    fn dynamic_array_collection() {
+        let mut a: Dynamic<[u8; 4]> = (1..4).collect();
+        assert_eq!(a.try_push(4), Ok(()));
+        assert_eq!(a.try_push(5), Err(5));
+        assert_eq!(a.len(), 4);
+        assert_eq!(a.pop(), Some(4));
+
+        for item in &mut a {
+            *item *= 10;
+        }
+        assert_eq!(a.iter().sum::<u8>(), 60);
+        assert_eq!(&a[1..], &[20, 30]);
+
+        let vec: ::heapless::Vec<u8, 4> = a.clone().into();
+        assert_eq!(&vec[..], &[10, 20, 30]);
+        assert_eq!(Dynamic::<[u8; 4]>::from(vec), a);
+
+        a.clear();
+        assert!(a.is_empty());
+        assert_eq!(a.pop(), None);
+    }
+
+    #[test]
+    fn length_prefix_bits() {
+        // (max_length, v0 bits, v1 bits)
+        let vectors = [
+            (1, 1, 8),
+            (2, 2, 8),
+            (3, 2, 8),
+            (4, 3, 8),
+            (90, 7, 8),
+            (127, 7, 8),
+            (128, 8, 8),
+            (255, 8, 8),
+            (256, 9, 16),
+            (65535, 16, 16),
+            (65536, 17, 32),
+            (0xffff_ffff, 32, 32),
+        ];
+        for &(max_length, v0, v1) in vectors.iter() {
+            assert_eq!(ProtocolVersion::V0.length_bits(max_length), v0);
+            assert_eq!(ProtocolVersion::V1.length_bits(max_length), v1);
+        }
+
+        assert_eq!(Dynamic::<[u8; 1]>::LENGTH_BITS, 1);
+        assert_eq!(Dynamic::<[u8; 127]>::LENGTH_BITS, 7);
+        assert_eq!(Dynamic::<[u8; 128]>::LENGTH_BITS, 8);
+        assert_eq!(Dynamic::<[u8; 128]>::length_bits(ProtocolVersion::V1), 8);
+        assert_eq!(Dynamic::<[u8; 256]>::length_bits(ProtocolVersion::V1), 16);
+        assert_eq!(<Dynamic<[u8; 256]> as ::Serializable>::BIT_LENGTH_MIN, 9);
+        assert_eq!(<[bool; 15] as BoolArray>::LENGTH_BITS, 4);
+    }
 }
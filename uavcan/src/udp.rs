@@ -0,0 +1,484 @@
+//! Support for the UDP/IP transport
+//!
+//! Subjects are carried over UDP multicast, every subject has its own multicast group.
+//! A UDP `TransferInterface` must be a member of the groups of all subjects that are subscribed on,
+//! and should leave the groups when the subscriptions are dropped to avoid receiving (and filtering out) unneeded traffic.
+//! Service transfers are sent to the multicast group of the destination node, which every node with a node ID is a member of.
+//!
+//! Every datagram starts with a `UdpFrameHeader` protected by its own CRC16-CCITT.
+//! The payload of a transfer is protected by a CRC-32C (`TransferCRC32C`) appended (little endian) to the last frame,
+//! instead of the CRC16 and tail byte used on CAN.
+
+use heapless::Vec;
+
+use crc::TransferCRC;
+use crc::TransferChecksum;
+pub use crc::TransferCRC32C;
+
+/// The UDP port used for all Cyphal/UDP traffic
+pub const UDP_PORT: u16 = 9382;
+
+/// The highest subject ID that can be mapped to a multicast group
+pub const MAX_SUBJECT_ID: u16 = 0x1fff;
+
+/// Returns the IPv4 multicast group (`239.0.x.x`) for `subject_id`
+///
+/// Returns `None` if `subject_id` is larger than `MAX_SUBJECT_ID`.
+pub fn subject_multicast_group(subject_id: u16) -> Option<[u8; 4]> {
+    if subject_id > MAX_SUBJECT_ID {
+        None
+    } else {
+        Some([239, 0, (subject_id >> 8) as u8, subject_id as u8])
+    }
+}
+
+/// Returns the IPv4 multicast group (`239.1.x.x`) of the service transfers to `node_id`
+///
+/// Returns `None` for `UNSET_NODE_ID`, as service transfers always have a destination.
+pub fn service_multicast_group(node_id: u16) -> Option<[u8; 4]> {
+    if node_id == UNSET_NODE_ID {
+        None
+    } else {
+        Some([239, 1, (node_id >> 8) as u8, node_id as u8])
+    }
+}
+
+/// The header format version implemented
+pub const HEADER_VERSION: u8 = 1;
+
+/// The size of a serialized `UdpFrameHeader` in bytes
+pub const HEADER_SIZE: usize = 24;
+
+/// The node ID used for anonymous sources and broadcast destinations
+pub const UNSET_NODE_ID: u16 = 0xffff;
+
+/// The size of the transfer CRC appended to the payload of the last frame in bytes
+pub const TRANSFER_CRC_SIZE: usize = <TransferCRC32C as TransferChecksum>::LENGTH;
+
+const END_OF_TRANSFER_BIT: u32 = 1 << 31;
+const SERVICE_NOT_MESSAGE_BIT: u16 = 1 << 15;
+const REQUEST_NOT_RESPONSE_BIT: u16 = 1 << 14;
+
+/// The port specifier of a transfer
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DataSpecifier {
+    /// A message published on a subject
+    Message(u16),
+
+    /// A request to a service
+    Request(u16),
+
+    /// A response from a service
+    Response(u16),
+}
+
+impl DataSpecifier {
+    fn to_bits(self) -> u16 {
+        match self {
+            DataSpecifier::Message(subject_id) => subject_id & !SERVICE_NOT_MESSAGE_BIT,
+            DataSpecifier::Request(service_id) => SERVICE_NOT_MESSAGE_BIT | REQUEST_NOT_RESPONSE_BIT | (service_id & 0x3fff),
+            DataSpecifier::Response(service_id) => SERVICE_NOT_MESSAGE_BIT | (service_id & 0x3fff),
+        }
+    }
+
+    fn from_bits(bits: u16) -> Self {
+        if bits & SERVICE_NOT_MESSAGE_BIT == 0 {
+            DataSpecifier::Message(bits)
+        } else if bits & REQUEST_NOT_RESPONSE_BIT != 0 {
+            DataSpecifier::Request(bits & 0x3fff)
+        } else {
+            DataSpecifier::Response(bits & 0x3fff)
+        }
+    }
+}
+
+/// The header in front of every Cyphal/UDP datagram
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UdpFrameHeader {
+    /// Transfer priority, 0 (exceptional) to 7 (optional)
+    pub priority: u8,
+
+    /// Node ID of the source, `UNSET_NODE_ID` for anonymous transfers
+    pub source_node_id: u16,
+
+    /// Node ID of the destination, `UNSET_NODE_ID` for broadcasts
+    pub destination_node_id: u16,
+
+    pub data_specifier: DataSpecifier,
+
+    pub transfer_id: u64,
+
+    /// Index of the frame within the transfer, starting at 0
+    pub frame_index: u32,
+
+    /// Set on the last frame of a transfer
+    pub end_of_transfer: bool,
+
+    /// Opaque data, must be zero when unused
+    pub user_data: u16,
+}
+
+/// Errors from parsing a `UdpFrameHeader`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeaderError {
+    /// The datagram is shorter than `HEADER_SIZE`
+    TooShort,
+
+    /// The header has a version other than `HEADER_VERSION`
+    UnsupportedVersion(u8),
+
+    /// The header CRC doesn't match
+    CRCError,
+}
+
+impl UdpFrameHeader {
+    /// Returns the multicast group the datagram is sent to, the group of the subject or of the destination node of a service transfer
+    ///
+    /// Returns `None` if the subject ID or the destination node ID can't be mapped to a group.
+    pub fn multicast_group(&self) -> Option<[u8; 4]> {
+        match self.data_specifier {
+            DataSpecifier::Message(subject_id) => subject_multicast_group(subject_id),
+            DataSpecifier::Request(_) | DataSpecifier::Response(_) => service_multicast_group(self.destination_node_id),
+        }
+    }
+
+    /// Serializes the header, including the header CRC
+    ///
+    /// ## Panics
+    /// Panics if `frame_index` doesn't fit in 31 bits or `priority > 7`.
+    pub fn serialize(&self) -> [u8; HEADER_SIZE] {
+        assert!(self.priority <= 7, "Priority must be 3 bits");
+        assert_eq!(self.frame_index & END_OF_TRANSFER_BIT, 0, "Frame index must be 31 bits");
+
+        let index_eot = if self.end_of_transfer {
+            self.frame_index | END_OF_TRANSFER_BIT
+        } else {
+            self.frame_index
+        };
+
+        let mut buffer = [0u8; HEADER_SIZE];
+        buffer[0] = HEADER_VERSION;
+        buffer[1] = self.priority;
+        buffer[2..4].copy_from_slice(&self.source_node_id.to_le_bytes());
+        buffer[4..6].copy_from_slice(&self.destination_node_id.to_le_bytes());
+        buffer[6..8].copy_from_slice(&self.data_specifier.to_bits().to_le_bytes());
+        buffer[8..16].copy_from_slice(&self.transfer_id.to_le_bytes());
+        buffer[16..20].copy_from_slice(&index_eot.to_le_bytes());
+        buffer[20..22].copy_from_slice(&self.user_data.to_le_bytes());
+
+        let mut crc = TransferCRC::from(0xffff);
+        crc.add(&buffer[0..22]);
+        buffer[22..24].copy_from_slice(&u16::from(crc).to_be_bytes());
+
+        buffer
+    }
+
+    /// Parses the header in front of `datagram`
+    ///
+    /// The payload follows from byte `HEADER_SIZE`.
+    pub fn deserialize(datagram: &[u8]) -> Result<UdpFrameHeader, HeaderError> {
+        if datagram.len() < HEADER_SIZE {
+            return Err(HeaderError::TooShort);
+        }
+
+        // The residue of the CRC over both the header and its (big endian) CRC is zero.
+        let mut crc = TransferCRC::from(0xffff);
+        crc.add(&datagram[0..HEADER_SIZE]);
+        if u16::from(crc) != 0 {
+            return Err(HeaderError::CRCError);
+        }
+
+        if datagram[0] != HEADER_VERSION {
+            return Err(HeaderError::UnsupportedVersion(datagram[0]));
+        }
+
+        let index_eot = u32::from_le_bytes([datagram[16], datagram[17], datagram[18], datagram[19]]);
+
+        Ok(UdpFrameHeader {
+            priority: datagram[1] & 0x7,
+            source_node_id: u16::from_le_bytes([datagram[2], datagram[3]]),
+            destination_node_id: u16::from_le_bytes([datagram[4], datagram[5]]),
+            data_specifier: DataSpecifier::from_bits(u16::from_le_bytes([datagram[6], datagram[7]])),
+            transfer_id: u64::from_le_bytes([
+                datagram[8], datagram[9], datagram[10], datagram[11],
+                datagram[12], datagram[13], datagram[14], datagram[15],
+            ]),
+            frame_index: index_eot & !END_OF_TRANSFER_BIT,
+            end_of_transfer: index_eot & END_OF_TRANSFER_BIT != 0,
+            user_data: u16::from_le_bytes([datagram[20], datagram[21]]),
+        })
+    }
+}
+
+/// Returns the transfer CRC of `payload`, in the byte order it's appended to the last frame
+pub fn transfer_crc(payload: &[u8]) -> [u8; TRANSFER_CRC_SIZE] {
+    let mut crc = TransferCRC32C::new();
+    crc.add(payload);
+    let mut bytes = [0u8; TRANSFER_CRC_SIZE];
+    crc.write_bytes(&mut bytes);
+    bytes
+}
+
+/// A socket that can join and leave IPv4 multicast groups (by IGMP)
+pub trait MulticastSocket {
+    type Error;
+
+    /// Join the multicast group `group`
+    fn join_multicast_group(&self, group: [u8; 4]) -> Result<(), Self::Error>;
+
+    /// Leave the multicast group `group`
+    fn leave_multicast_group(&self, group: [u8; 4]) -> Result<(), Self::Error>;
+}
+
+#[cfg(feature="std")]
+impl MulticastSocket for ::std::net::UdpSocket {
+    type Error = ::std::io::Error;
+
+    fn join_multicast_group(&self, group: [u8; 4]) -> Result<(), Self::Error> {
+        self.join_multicast_v4(&group.into(), &::std::net::Ipv4Addr::UNSPECIFIED)
+    }
+
+    fn leave_multicast_group(&self, group: [u8; 4]) -> Result<(), Self::Error> {
+        self.leave_multicast_v4(&group.into(), &::std::net::Ipv4Addr::UNSPECIFIED)
+    }
+}
+
+/// Errors from `MulticastMembership`
+#[derive(Debug, PartialEq, Eq)]
+pub enum MembershipError<E> {
+    /// The subject ID can't be mapped to a multicast group
+    InvalidSubjectID,
+
+    /// The maximum number of groups are already joined
+    TooManyGroups,
+
+    /// Leaving a group that hasn't been joined
+    NotJoined,
+
+    /// The socket failed to join or leave the group
+    Socket(E),
+}
+
+/// Keeps track of the multicast groups joined by a socket
+///
+/// Several subscriptions may exist for the same subject, the membership is reference counted so that a group is
+/// joined on the first subscription and left when the last subscription is dropped.
+/// At most `N` groups can be joined simultaneously.
+///
+/// A UDP `TransferInterface` will typically call `join` from `TransferInterface::on_subscribe` and `leave` from `TransferInterface::on_unsubscribe`.
+#[derive(Debug)]
+pub struct MulticastMembership<const N: usize> {
+    groups: Vec<(u16, usize), N>,
+}
+
+impl<const N: usize> MulticastMembership<N> {
+    /// Constructs a `MulticastMembership` without any joined groups
+    pub fn new() -> Self {
+        MulticastMembership {
+            groups: Vec::new(),
+        }
+    }
+
+    /// Registers a subscription to `subject_id`, joining the corresponding group if it's the first subscription.
+    pub fn join<S: MulticastSocket>(&mut self, socket: &S, subject_id: u16) -> Result<(), MembershipError<S::Error>> {
+        let group = subject_multicast_group(subject_id).ok_or(MembershipError::InvalidSubjectID)?;
+
+        if let Some(&mut (_, ref mut count)) = self.groups.iter_mut().find(|&&mut (id, _)| id == subject_id) {
+            *count += 1;
+            return Ok(());
+        }
+
+        self.groups.push((subject_id, 1)).map_err(|_| MembershipError::TooManyGroups)?;
+        if let Err(e) = socket.join_multicast_group(group) {
+            self.groups.pop();
+            return Err(MembershipError::Socket(e));
+        }
+        Ok(())
+    }
+
+    /// Unregisters a subscription to `subject_id`, leaving the corresponding group if it was the last subscription.
+    pub fn leave<S: MulticastSocket>(&mut self, socket: &S, subject_id: u16) -> Result<(), MembershipError<S::Error>> {
+        let group = subject_multicast_group(subject_id).ok_or(MembershipError::InvalidSubjectID)?;
+        let index = self.groups.iter().position(|&(id, _)| id == subject_id).ok_or(MembershipError::NotJoined)?;
+
+        if self.groups[index].1 > 1 {
+            self.groups[index].1 -= 1;
+            return Ok(());
+        }
+
+        socket.leave_multicast_group(group).map_err(MembershipError::Socket)?;
+        self.groups.swap_remove(index);
+        Ok(())
+    }
+
+    /// Returns true if the group of `subject_id` is joined
+    pub fn is_joined(&self, subject_id: u16) -> bool {
+        self.groups.iter().any(|&(id, _)| id == subject_id)
+    }
+
+    /// Returns an iterator over the subject IDs whose groups are joined
+    pub fn subjects(&self) -> impl Iterator<Item = u16> + '_ {
+        self.groups.iter().map(|&(id, _)| id)
+    }
+}
+
+impl<const N: usize> Default for MulticastMembership<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use lib::core::cell::Cell;
+
+    use udp::*;
+
+    #[derive(Default)]
+    struct MockSocket {
+        joins: Cell<usize>,
+        leaves: Cell<usize>,
+        last_group: Cell<[u8; 4]>,
+        fail: Cell<bool>,
+    }
+
+    impl MulticastSocket for MockSocket {
+        type Error = ();
+
+        fn join_multicast_group(&self, group: [u8; 4]) -> Result<(), ()> {
+            if self.fail.get() {
+                return Err(());
+            }
+            self.joins.set(self.joins.get() + 1);
+            self.last_group.set(group);
+            Ok(())
+        }
+
+        fn leave_multicast_group(&self, group: [u8; 4]) -> Result<(), ()> {
+            self.leaves.set(self.leaves.get() + 1);
+            self.last_group.set(group);
+            Ok(())
+        }
+    }
+
+    const HEADER: [u8; HEADER_SIZE] = [
+        0x01, 0x04, 0x2a, 0x00, 0xff, 0xff, 0x55, 0x1d,
+        0x89, 0x67, 0x45, 0x23, 0x01, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x24, 0xea,
+    ];
+
+    fn header() -> UdpFrameHeader {
+        UdpFrameHeader {
+            priority: 4,
+            source_node_id: 42,
+            destination_node_id: UNSET_NODE_ID,
+            data_specifier: DataSpecifier::Message(7509),
+            transfer_id: 0x01_2345_6789,
+            frame_index: 0,
+            end_of_transfer: true,
+            user_data: 0,
+        }
+    }
+
+    #[test]
+    fn serialize_header() {
+        assert_eq!(header().serialize(), HEADER);
+    }
+
+    #[test]
+    fn deserialize_header() {
+        assert_eq!(UdpFrameHeader::deserialize(&HEADER), Ok(header()));
+
+        let mut corrupted = HEADER;
+        corrupted[8] ^= 0x01;
+        assert_eq!(UdpFrameHeader::deserialize(&corrupted), Err(HeaderError::CRCError));
+        assert_eq!(UdpFrameHeader::deserialize(&HEADER[..10]), Err(HeaderError::TooShort));
+    }
+
+    #[test]
+    fn service_data_specifier() {
+        let mut request = header();
+        request.data_specifier = DataSpecifier::Request(430);
+        request.end_of_transfer = false;
+        request.frame_index = 3;
+        assert_eq!(UdpFrameHeader::deserialize(&request.serialize()), Ok(request));
+
+        let mut response = header();
+        response.data_specifier = DataSpecifier::Response(430);
+        assert_eq!(UdpFrameHeader::deserialize(&response.serialize()), Ok(response));
+    }
+
+    #[test]
+    fn transfer_crc_check_value() {
+        assert_eq!(transfer_crc(b"123456789"), [0x83, 0x92, 0x06, 0xe3]);
+    }
+
+    #[test]
+    fn multicast_group_mapping() {
+        assert_eq!(subject_multicast_group(0), Some([239, 0, 0, 0]));
+        assert_eq!(subject_multicast_group(7509), Some([239, 0, 29, 85]));
+        assert_eq!(subject_multicast_group(MAX_SUBJECT_ID), Some([239, 0, 31, 255]));
+        assert_eq!(subject_multicast_group(MAX_SUBJECT_ID + 1), None);
+
+        assert_eq!(service_multicast_group(42), Some([239, 1, 0, 42]));
+        assert_eq!(service_multicast_group(0x1234), Some([239, 1, 0x12, 0x34]));
+        assert_eq!(service_multicast_group(UNSET_NODE_ID), None);
+
+        let mut request = header();
+        request.data_specifier = DataSpecifier::Request(430);
+        request.destination_node_id = 7;
+        assert_eq!(header().multicast_group(), Some([239, 0, 29, 85]));
+        assert_eq!(request.multicast_group(), Some([239, 1, 0, 7]));
+        request.destination_node_id = UNSET_NODE_ID;
+        assert_eq!(request.multicast_group(), None);
+    }
+
+    #[test]
+    fn membership_is_reference_counted() {
+        let socket = MockSocket::default();
+        let mut membership = MulticastMembership::<4>::new();
+
+        membership.join(&socket, 7509).unwrap();
+        membership.join(&socket, 7509).unwrap();
+        assert_eq!(socket.joins.get(), 1);
+        assert_eq!(socket.last_group.get(), [239, 0, 29, 85]);
+
+        membership.leave(&socket, 7509).unwrap();
+        assert_eq!(socket.leaves.get(), 0);
+        assert!(membership.is_joined(7509));
+
+        membership.leave(&socket, 7509).unwrap();
+        assert_eq!(socket.leaves.get(), 1);
+        assert!(!membership.is_joined(7509));
+
+        assert_eq!(membership.leave(&socket, 7509), Err(MembershipError::NotJoined));
+    }
+
+    #[test]
+    fn membership_capacity() {
+        let socket = MockSocket::default();
+        let mut membership = MulticastMembership::<1>::new();
+
+        membership.join(&socket, 1).unwrap();
+        assert_eq!(membership.join(&socket, 2), Err(MembershipError::TooManyGroups));
+        assert_eq!(membership.join(&socket, 0x2000), Err(MembershipError::InvalidSubjectID));
+        assert_eq!(socket.joins.get(), 1);
+    }
+
+    #[test]
+    fn membership_socket_error() {
+        let socket = MockSocket::default();
+        let mut membership = MulticastMembership::<1>::new();
+
+        socket.fail.set(true);
+        assert_eq!(membership.join(&socket, 1), Err(MembershipError::Socket(())));
+        assert!(!membership.is_joined(1));
+
+        // The failed join didn't take up the capacity
+        socket.fail.set(false);
+        membership.join(&socket, 2).unwrap();
+        assert!(membership.is_joined(2));
+    }
+}
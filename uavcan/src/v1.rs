@@ -0,0 +1,554 @@
+//! The UAVCAN v1 (Cyphal) serialization
+//!
+//! The rest of the crate serializes UAVCAN v0, this module holds the UAVCAN v1 encoding used by the v1 types in `regulated`.
+//! The encodings differ in a few ways:
+//!
+//! - The bits of a byte are used from the least significant one.
+//! - Composites are aligned to a byte boundary, and padded to one after their fields.
+//! - Variable length arrays start at a byte boundary with a length field of 8, 16, 32 or 64 bits (see `ProtocolVersion::length_bits`),
+//!   there is no tail array optimization.
+//! - Unions start with an 8 bit tag.
+//! - A nested composite that isn't sealed starts with a 32 bit delimiter header holding its length in bytes,
+//!   so a receiver can skip the fields added by newer versions of the type.
+//!
+//! Data missing at the end reads as zero (implicit zero extension) and data past the fields is ignored (implicit truncation).
+//!
+//! ## Example
+//! ```
+//! use uavcan::v1::*;
+//!
+//! #[derive(Debug, PartialEq)]
+//! struct Reading {
+//!     sensor: u8,
+//!     values: heapless::Vec<u16, 4>,
+//! }
+//!
+//! impl Composite for Reading {
+//!     const SEALED: bool = true;
+//!     const MAX_BYTES: usize = 10;
+//!     const EXTENT: usize = 10;
+//!
+//!     fn serialize_fields(&self, writer: &mut Writer) {
+//!         writer.write(self.sensor);
+//!         writer.write_dynamic_array(&self.values, 4);
+//!     }
+//!
+//!     fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+//!         Ok(Reading{
+//!             sensor: reader.read(),
+//!             values: reader.read_dynamic_array()?,
+//!         })
+//!     }
+//! }
+//!
+//! let reading = Reading{sensor: 3, values: heapless::Vec::from_slice(&[0x1234]).unwrap()};
+//! let mut data = [0u8; Reading::MAX_BYTES];
+//! let length = reading.serialize(&mut data).unwrap();
+//! assert_eq!(&data[..length], &[3, 1, 0x34, 0x12]);
+//! assert_eq!(Reading::deserialize(&data[..length]), Ok(reading));
+//! ```
+
+use lib::core::cmp;
+
+use heapless::Vec;
+
+use bits::BufferFull;
+
+use types::*;
+
+/// The error when the serialized representation of a composite is malformed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The length of a variable length array exceeds its capacity
+    ArrayLength,
+    /// The tag of a union doesn't select a variant
+    UnionTag,
+    /// The delimiter header of a nested composite exceeds the remaining data
+    DelimiterHeader,
+}
+
+/// A primitive type, serialized as the lowest `BIT_LENGTH` bits of its two's complement or IEEE 754 representation
+pub trait Primitive: Copy {
+    const BIT_LENGTH: usize;
+
+    fn to_bits(self) -> u64;
+
+    /// The bits above `BIT_LENGTH` are ignored
+    fn from_bits(bits: u64) -> Self;
+}
+
+macro_rules! impl_primitive {
+    ($($type:ident),*) => {$(
+        impl Primitive for $type {
+            const BIT_LENGTH: usize = <$type as ::Serializable>::BIT_LENGTH_MAX;
+
+            fn to_bits(self) -> u64 {
+                PrimitiveType::to_bits(self)
+            }
+
+            fn from_bits(bits: u64) -> Self {
+                PrimitiveType::from_bits(bits)
+            }
+        }
+    )*};
+}
+
+impl_primitive!(bool, u8, u16, u32, u64, i8, i16, i32, i64, f16, f32, f64);
+
+impl_primitive!(u2, u3, u4, u5, u6, u7, u9, u10, u11, u12, u13, u14, u15, u17, u18, u19, u20, u21, u22, u23, u24, u25, u26, u27, u28, u29, u30, u31,
+                u33, u34, u35, u36, u37, u38, u39, u40, u41, u42, u43, u44, u45, u46, u47, u48, u49, u50, u51, u52, u53, u54, u55, u56, u57, u58, u59, u60, u61, u62, u63);
+
+impl_primitive!(i2, i3, i4, i5, i6, i7, i9, i10, i11, i12, i13, i14, i15, i17, i18, i19, i20, i21, i22, i23, i24, i25, i26, i27, i28, i29, i30, i31,
+                i33, i34, i35, i36, i37, i38, i39, i40, i41, i42, i43, i44, i45, i46, i47, i48, i49, i50, i51, i52, i53, i54, i55, i56, i57, i58, i59, i60, i61, i62, i63);
+
+/// A composite type, a structure or a union
+pub trait Composite: Sized {
+    /// A sealed composite is nested without a delimiter header, so its fields can't be extended
+    const SEALED: bool;
+
+    /// The largest serialized representation in bytes
+    const MAX_BYTES: usize;
+
+    /// The number of bytes a receiver accepts, at least `MAX_BYTES` to leave room for the fields of newer versions
+    const EXTENT: usize;
+
+    /// Writes the fields, the alignment and padding around them is written by the caller
+    fn serialize_fields(&self, writer: &mut Writer);
+
+    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError>;
+
+    /// Serializes the composite into `data` and returns the number of bytes used
+    fn serialize(&self, data: &mut [u8]) -> Result<usize, BufferFull> {
+        let mut writer = Writer::new(data);
+        self.serialize_fields(&mut writer);
+        writer.finish()
+    }
+
+    /// Deserializes the composite from the payload of a transfer
+    fn deserialize(data: &[u8]) -> Result<Self, DecodeError> {
+        Self::deserialize_fields(&mut Reader::new(data))
+    }
+}
+
+/// A data type with a fixed port ID, the subject ID of a message or the service ID of a service
+pub trait FixedPort {
+    const PORT_ID: u16;
+}
+
+/// Writes the UAVCAN v1 representation to a byte buffer
+///
+/// Writes that don't fit the buffer are dropped, `finish` tells if any were.
+pub struct Writer<'a> {
+    data: &'a mut [u8],
+    position: usize,
+    overflow: bool,
+}
+
+impl<'a> Writer<'a> {
+    /// Starts writing at the beginning of `data`, which is zeroed
+    pub fn new(data: &'a mut [u8]) -> Self {
+        for byte in data.iter_mut() {
+            *byte = 0;
+        }
+        Writer{data: data, position: 0, overflow: false}
+    }
+
+    /// Writes the lowest `bit_length` bits of `bits`
+    pub fn write_bits(&mut self, bit_length: usize, bits: u64) {
+        assert!(bit_length <= 64);
+        if self.position + bit_length > self.data.len() * 8 {
+            self.overflow = true;
+            self.position += bit_length;
+            return;
+        }
+
+        let mut written = 0;
+        while written < bit_length {
+            let offset = self.position % 8;
+            let chunk = cmp::min(8 - offset, bit_length - written);
+            let byte = ((bits >> written) & ((1 << chunk) - 1)) as u8;
+            self.data[self.position / 8] |= byte << offset;
+            written += chunk;
+            self.position += chunk;
+        }
+    }
+
+    pub fn write<T: Primitive>(&mut self, value: T) {
+        self.write_bits(T::BIT_LENGTH, value.to_bits());
+    }
+
+    /// Writes the elements of a fixed length array
+    pub fn write_array<T: Primitive>(&mut self, values: &[T]) {
+        for value in values {
+            self.write(*value);
+        }
+    }
+
+    /// Writes a variable length array holding up to `max_length` elements, the length is written even for the last field
+    pub fn write_dynamic_array<T: Primitive>(&mut self, values: &[T], max_length: usize) {
+        self.write_length(values.len(), max_length);
+        self.write_array(values);
+    }
+
+    /// Writes a nested composite, behind a delimiter header unless it's sealed
+    pub fn write_composite<T: Composite>(&mut self, value: &T) {
+        self.pad_to_byte();
+        if T::SEALED {
+            value.serialize_fields(self);
+            self.pad_to_byte();
+            return;
+        }
+
+        // The header is filled in once the length of the content is known
+        let header = self.position / 8;
+        self.write_bits(32, 0);
+        value.serialize_fields(self);
+        self.pad_to_byte();
+        let length = (self.position / 8 - header - 4) as u32;
+        if let Some(bytes) = self.data.get_mut(header..header + 4) {
+            bytes.copy_from_slice(&length.to_le_bytes());
+        }
+    }
+
+    /// Writes a variable length array of composites holding up to `max_length` elements
+    pub fn write_dynamic_composite_array<T: Composite>(&mut self, values: &[T], max_length: usize) {
+        self.write_length(values.len(), max_length);
+        for value in values {
+            self.write_composite(value);
+        }
+    }
+
+    /// Writes zero bits up to the next byte boundary
+    pub fn pad_to_byte(&mut self) {
+        let padding = (8 - self.position % 8) % 8;
+        self.write_bits(padding, 0);
+    }
+
+    /// The number of bits written
+    pub fn bit_length(&self) -> usize {
+        self.position
+    }
+
+    /// Pads the data to a byte boundary and returns its length in bytes
+    pub fn finish(mut self) -> Result<usize, BufferFull> {
+        self.pad_to_byte();
+        if self.overflow {
+            Err(BufferFull)
+        } else {
+            Ok(self.position / 8)
+        }
+    }
+
+    fn write_length(&mut self, length: usize, max_length: usize) {
+        debug_assert!(length <= max_length);
+        self.pad_to_byte();
+        self.write_bits(ProtocolVersion::V1.length_bits(max_length), length as u64);
+    }
+}
+
+/// Reads the UAVCAN v1 representation from a byte slice, the bits past the end of the slice read as zero
+pub struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Starts reading at the beginning of `data`
+    pub fn new(data: &'a [u8]) -> Self {
+        Reader{data: data, position: 0}
+    }
+
+    /// Reads `bit_length` bits into the lowest bits of the result
+    pub fn read_bits(&mut self, bit_length: usize) -> u64 {
+        assert!(bit_length <= 64);
+        let mut value = 0u64;
+        let mut read = 0;
+        while read < bit_length {
+            let offset = self.position % 8;
+            let chunk = cmp::min(8 - offset, bit_length - read);
+            let byte = self.data.get(self.position / 8).cloned().unwrap_or(0);
+            let bits = (u16::from(byte) >> offset) & ((1 << chunk) - 1);
+            value |= u64::from(bits) << read;
+            read += chunk;
+            self.position += chunk;
+        }
+        value
+    }
+
+    pub fn read<T: Primitive>(&mut self) -> T {
+        T::from_bits(self.read_bits(T::BIT_LENGTH))
+    }
+
+    /// Reads the elements of a fixed length array
+    pub fn read_array<T: Primitive, const N: usize>(&mut self) -> [T; N] {
+        let mut values = [T::from_bits(0); N];
+        for value in values.iter_mut() {
+            *value = self.read();
+        }
+        values
+    }
+
+    /// Reads a variable length array holding up to `N` elements
+    pub fn read_dynamic_array<T: Primitive, const N: usize>(&mut self) -> Result<Vec<T, N>, DecodeError> {
+        let length = self.read_length(N)?;
+        let mut values = Vec::new();
+        for _ in 0..length {
+            let _ = values.push(self.read());
+        }
+        Ok(values)
+    }
+
+    /// Reads a nested composite, from behind a delimiter header unless it's sealed
+    pub fn read_composite<T: Composite>(&mut self) -> Result<T, DecodeError> {
+        self.skip_to_byte();
+        if T::SEALED {
+            let value = T::deserialize_fields(self)?;
+            self.skip_to_byte();
+            return Ok(value);
+        }
+
+        // The content is read on its own, so fields missing in it read as zero and unknown fields are skipped
+        let length = self.read_bits(32) as usize;
+        let content = self.data.get(self.position / 8..).unwrap_or(&[]);
+        if length > content.len() {
+            return Err(DecodeError::DelimiterHeader);
+        }
+        let value = T::deserialize_fields(&mut Reader::new(&content[..length]))?;
+        self.position += length * 8;
+        Ok(value)
+    }
+
+    /// Reads a variable length array of composites holding up to `N` elements
+    pub fn read_dynamic_composite_array<T: Composite, const N: usize>(&mut self) -> Result<Vec<T, N>, DecodeError> {
+        let length = self.read_length(N)?;
+        let mut values = Vec::new();
+        for _ in 0..length {
+            let _ = values.push(self.read_composite()?);
+        }
+        Ok(values)
+    }
+
+    /// Skips `bit_length` bits
+    pub fn skip_bits(&mut self, bit_length: usize) {
+        self.position += bit_length;
+    }
+
+    /// Skips the bits up to the next byte boundary
+    pub fn skip_to_byte(&mut self) {
+        self.position = (self.position + 7) / 8 * 8;
+    }
+
+    /// The number of bits read or skipped
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    fn read_length(&mut self, max_length: usize) -> Result<usize, DecodeError> {
+        self.skip_to_byte();
+        let length = self.read_bits(ProtocolVersion::V1.length_bits(max_length));
+        if length > max_length as u64 {
+            Err(DecodeError::ArrayLength)
+        } else {
+            Ok(length as usize)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use v1::*;
+    use bits::{BitWriter, BufferFull};
+
+    #[derive(Debug, PartialEq, Default)]
+    struct InnerV1 {
+        x: u8,
+    }
+
+    impl Composite for InnerV1 {
+        const SEALED: bool = false;
+        const MAX_BYTES: usize = 1;
+        const EXTENT: usize = 8;
+
+        fn serialize_fields(&self, writer: &mut Writer) {
+            writer.write(self.x);
+        }
+
+        fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+            Ok(InnerV1{x: reader.read()})
+        }
+    }
+
+    #[derive(Debug, PartialEq, Default)]
+    struct InnerV2 {
+        x: u8,
+        y: u4,
+    }
+
+    impl Composite for InnerV2 {
+        const SEALED: bool = false;
+        const MAX_BYTES: usize = 2;
+        const EXTENT: usize = 8;
+
+        fn serialize_fields(&self, writer: &mut Writer) {
+            writer.write(self.x);
+            writer.write(self.y);
+        }
+
+        fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+            Ok(InnerV2{x: reader.read(), y: reader.read()})
+        }
+    }
+
+    #[derive(Debug, PartialEq, Default)]
+    struct Outer<T> {
+        a: u3,
+        inner: T,
+        b: u8,
+    }
+
+    impl<T: Composite> Composite for Outer<T> {
+        const SEALED: bool = true;
+        const MAX_BYTES: usize = 1 + 4 + 8 + 1;
+        const EXTENT: usize = Self::MAX_BYTES;
+
+        fn serialize_fields(&self, writer: &mut Writer) {
+            writer.write(self.a);
+            writer.write_composite(&self.inner);
+            writer.write(self.b);
+        }
+
+        fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {
+            Ok(Outer{a: reader.read(), inner: reader.read_composite()?, b: reader.read()})
+        }
+    }
+
+    #[test]
+    fn least_significant_bit_first() {
+        let mut data = [0xffu8; 4];
+        let length = {
+            let mut writer = Writer::new(&mut data);
+            writer.write(u3::new(0b101));
+            writer.write(0x1234u16);
+            writer.write(i5::new(-2));
+            writer.finish().unwrap()
+        };
+        assert_eq!(length, 3);
+        assert_eq!(data, [0xa5, 0x91, 0xf0, 0x00]);
+
+        let mut reader = Reader::new(&data[..length]);
+        assert_eq!(reader.read::<u3>(), u3::new(0b101));
+        assert_eq!(reader.read::<u16>(), 0x1234);
+        assert_eq!(reader.read::<i5>(), i5::new(-2));
+        assert_eq!(reader.read::<u32>(), 0);
+    }
+
+    #[test]
+    fn dynamic_arrays() {
+        let values: Vec<bool, 300> = Vec::from_slice(&[true, false, true]).unwrap();
+        let mut data = [0u8; 8];
+        let length = {
+            let mut writer = Writer::new(&mut data);
+            writer.write(true);
+            writer.write_dynamic_array(&values, 300);
+            writer.finish().unwrap()
+        };
+
+        // The length field is aligned and takes 16 bits for 300 elements
+        assert_eq!(&data[..length], &[0x01, 3, 0, 0b101]);
+
+        let mut reader = Reader::new(&data[..length]);
+        assert!(reader.read::<bool>());
+        assert_eq!(reader.read_dynamic_array::<bool, 300>(), Ok(values));
+
+        let mut reader = Reader::new(&[0, 5]);
+        reader.skip_bits(8);
+        assert_eq!(reader.read_dynamic_array::<u8, 4>(), Err(DecodeError::ArrayLength));
+    }
+
+    #[test]
+    fn dynamic_array_length_fields() {
+        let values = Dynamic::<[u8; 256]>::with_data(&[0xaa, 0xbb]);
+
+        // The v0 encoding takes 9 bits for the length of up to 256 elements, the v1 encoding a uint16
+        assert_eq!(Dynamic::<[u8; 256]>::LENGTH_BITS, 9);
+        let mut data = [0u8; 8];
+        let length = {
+            let mut writer = Writer::new(&mut data);
+            writer.write_dynamic_array(values.as_ref(), Dynamic::<[u8; 256]>::MAX_LENGTH);
+            writer.finish().unwrap()
+        };
+        assert_eq!(Dynamic::<[u8; 256]>::length_bits(ProtocolVersion::V1), 16);
+        assert_eq!(&data[..length], &[2, 0, 0xaa, 0xbb]);
+
+        let mut reader = Reader::new(&data[..length]);
+        assert_eq!(reader.read_dynamic_array::<u8, 256>().unwrap().as_slice(), values.as_ref());
+
+        // (capacity, encoded length field)
+        let vectors: [(usize, &[u8]); 4] = [
+            (1, &[1]),
+            (255, &[1]),
+            (256, &[1, 0]),
+            (65536, &[1, 0, 0, 0]),
+        ];
+        for &(capacity, expected) in vectors.iter() {
+            let mut data = [0u8; 8];
+            let length = {
+                let mut writer = Writer::new(&mut data);
+                writer.write_dynamic_array(&[true], capacity);
+                writer.finish().unwrap()
+            };
+            assert_eq!(&data[..length - 1], expected);
+        }
+    }
+
+    #[test]
+    fn no_tail_array_optimization() {
+        let values = Dynamic::<[u8; 4]>::with_data(&[0xaa, 0xbb]);
+
+        // The v0 encoding leaves out the length of a byte array at the end of a transfer
+        let mut v0_data = [0u8; 8];
+        let mut writer = BitWriter::new(&mut v0_data);
+        writer.write(&values, true).unwrap();
+        assert_eq!(writer.bit_length(), 16);
+        assert_eq!(&v0_data[..2], &[0xaa, 0xbb]);
+
+        // The v1 encoding keeps it
+        let mut data = [0u8; 8];
+        let length = {
+            let mut writer = Writer::new(&mut data);
+            writer.write_dynamic_array(values.as_ref(), 4);
+            writer.finish().unwrap()
+        };
+        assert_eq!(&data[..length], &[2, 0xaa, 0xbb]);
+
+        let mut reader = Reader::new(&data[..length]);
+        assert_eq!(reader.read_dynamic_array::<u8, 4>().unwrap().as_slice(), values.as_ref());
+    }
+
+    #[test]
+    fn delimited_composites() {
+        let mut data = [0u8; Outer::<InnerV2>::MAX_BYTES];
+        let length = Outer{a: u3::new(1), inner: InnerV2{x: 2, y: u4::new(5)}, b: 3}.serialize(&mut data).unwrap();
+        assert_eq!(&data[..length], &[1, 2, 0, 0, 0, 2, 5, 3]);
+
+        // Older receivers skip the fields they don't know
+        assert_eq!(Outer::<InnerV1>::deserialize(&data[..length]), Ok(Outer{a: u3::new(1), inner: InnerV1{x: 2}, b: 3}));
+
+        // Newer receivers read the fields they didn't receive as zero
+        let length = Outer{a: u3::new(1), inner: InnerV1{x: 2}, b: 3}.serialize(&mut data).unwrap();
+        assert_eq!(&data[..length], &[1, 1, 0, 0, 0, 2, 3]);
+        assert_eq!(Outer::<InnerV2>::deserialize(&data[..length]), Ok(Outer{a: u3::new(1), inner: InnerV2{x: 2, y: u4::new(0)}, b: 3}));
+
+        assert_eq!(Outer::<InnerV1>::deserialize(&[1, 9, 0, 0, 0, 2]), Err(DecodeError::DelimiterHeader));
+
+        // The header of a missing composite reads as zero like the rest
+        assert_eq!(Outer::<InnerV1>::deserialize(&[1]), Ok(Outer{a: u3::new(1), inner: InnerV1{x: 0}, b: 0}));
+    }
+
+    #[test]
+    fn buffer_full() {
+        let mut data = [0u8; 6];
+        assert_eq!(Outer{a: u3::new(1), inner: InnerV2{x: 2, y: u4::new(5)}, b: 3}.serialize(&mut data), Err(BufferFull));
+    }
+}
@@ -0,0 +1,178 @@
+//! The generator of the regulated data types
+//!
+//! Each namespace of the DSDL definitions becomes a module of `regulated` (without the leading `uavcan`),
+//! UAVCAN v0 definitions (`*.uavcan`) are generated as derived `UavcanStruct`s and UAVCAN v1 definitions (`*.dsdl`) implement `v1::Composite`.
+
+mod parser;
+mod v0;
+mod v1;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+pub use self::parser::*;
+
+/// The definitions of the data types of one protocol version, by full name
+pub struct Definitions {
+    pub definitions: Vec<Definition>,
+}
+
+impl Definitions {
+    /// The definition of the composite type `name` with the major version of `version`
+    pub fn get(&self, name: &str, version: Option<(u8, u8)>) -> &Definition {
+        self.definitions.iter()
+            .find(|definition| definition.full_name == name && definition.version.map(|v| v.0) == version.map(|v| v.0))
+            .unwrap_or_else(|| panic!("The definition of {} is missing", name))
+    }
+
+    /// The path of the Rust type of a section, relative to the module of `namespace`
+    pub fn type_path(&self, definition: &Definition, section: usize, namespace: &str) -> String {
+        let name = self.type_name(definition, section);
+        if definition.namespace() == namespace {
+            name
+        } else {
+            format!("::{}::{}", module_path(definition.namespace()), name)
+        }
+    }
+
+    /// The name of the Rust type of a section, services get a `Request` and a `Response`
+    ///
+    /// A data type with several major versions in the namespace gets the major version as a suffix, like `NodeIDAllocationDataV2`.
+    pub fn type_name(&self, definition: &Definition, section: usize) -> String {
+        let mut name = definition.name().to_string();
+        let majors = self.definitions.iter().filter(|other| other.full_name == definition.full_name).count();
+        if majors > 1 {
+            name += &format!("V{}", definition.version.unwrap().0);
+        }
+        if definition.is_service() {
+            name += if section == 0 { "Request" } else { "Response" };
+        }
+        name
+    }
+}
+
+/// Generates the modules of the regulated data types from the DSDL definitions in `dsdl`
+///
+/// Returns the generated files by their path relative to `src/regulated`.
+pub fn generate(dsdl: &Path) -> BTreeMap<PathBuf, String> {
+    let mut files = BTreeMap::new();
+    generate_version(dsdl, "v0", v0::generate, &mut files);
+    generate_version(dsdl, "v1", v1::generate, &mut files);
+    files
+}
+
+fn generate_version(dsdl: &Path, version: &str, generate_namespace: fn(&Definitions, &str) -> String, files: &mut BTreeMap<PathBuf, String>) {
+    let mut definitions = Vec::new();
+    read_definitions(&dsdl.join(version), "", &mut definitions);
+    let definitions = Definitions{definitions: definitions};
+
+    let mut namespaces: Vec<&str> = definitions.definitions.iter().map(|definition| definition.namespace()).collect();
+    namespaces.sort();
+    namespaces.dedup();
+
+    for namespace in namespaces.iter() {
+        let children: Vec<&str> = namespaces.iter()
+            .filter(|other| other.starts_with(namespace) && other[namespace.len()..].starts_with('.') && other[namespace.len() + 1..].find('.').is_none())
+            .map(|other| &other[namespace.len() + 1..])
+            .collect();
+
+        let mut content = format!("//! The `{}` namespace\n//!\n//! Generated from the DSDL definitions in `dsdl/{}` by `tests/regulated.rs`, regenerate it instead of editing it.\n\n", namespace, version);
+        for child in children.iter() {
+            content += &format!("pub mod {};\n", child);
+        }
+        if !children.is_empty() {
+            content += "\n";
+        }
+        content += &generate_namespace(&definitions, namespace);
+
+        let path = PathBuf::from(namespace.replacen("uavcan.", "", 1).replace('.', "/"));
+        let path = if children.is_empty() {
+            path.with_extension("rs")
+        } else {
+            path.join("mod.rs")
+        };
+        files.insert(path, content);
+    }
+}
+
+/// The Rust path of the module of a namespace
+pub fn module_path(namespace: &str) -> String {
+    namespace.replacen("uavcan", "regulated", 1).replace('.', "::")
+}
+
+fn read_definitions(directory: &Path, namespace: &str, definitions: &mut Vec<Definition>) {
+    let mut entries: Vec<PathBuf> = fs::read_dir(directory).unwrap().map(|entry| entry.unwrap().path()).collect();
+    entries.sort();
+    for path in entries {
+        let name = path.file_name().unwrap().to_str().unwrap().to_string();
+        if path.is_dir() {
+            let namespace = if namespace.is_empty() { name } else { format!("{}.{}", namespace, name) };
+            read_definitions(&path, &namespace, definitions);
+        } else {
+            let source = fs::read_to_string(&path).unwrap();
+            definitions.push(parse(namespace, &name, &source));
+        }
+    }
+}
+
+/// Renders comment lines as doc comments
+pub fn doc(lines: &[String], indent: &str) -> String {
+    lines.iter().map(|line| {
+        if line.is_empty() {
+            format!("{}///\n", indent)
+        } else {
+            format!("{}/// {}\n", indent, line)
+        }
+    }).collect()
+}
+
+/// The Rust type of a primitive, and if it comes from `types`
+pub fn primitive_type(base: &Base) -> (String, bool) {
+    match *base {
+        Base::Bool => ("bool".to_string(), false),
+        Base::Unsigned(bits @ 8) | Base::Unsigned(bits @ 16) | Base::Unsigned(bits @ 32) | Base::Unsigned(bits @ 64) => (format!("u{}", bits), false),
+        Base::Unsigned(bits) => (format!("u{}", bits), true),
+        Base::Signed(bits @ 8) | Base::Signed(bits @ 16) | Base::Signed(bits @ 32) | Base::Signed(bits @ 64) => (format!("i{}", bits), false),
+        Base::Signed(bits) => (format!("i{}", bits), true),
+        Base::Float(16) => ("f16".to_string(), true),
+        Base::Float(bits) => (format!("f{}", bits), false),
+        Base::Composite(..) => panic!("Not a primitive type"),
+    }
+}
+
+/// The constants of a section as associated constants, using the smallest native type that holds them
+pub fn constants(type_name: &str, section: &Section) -> String {
+    let constants = section.constants();
+    if constants.is_empty() {
+        return String::new();
+    }
+
+    let mut code = format!("\nimpl {} {{\n", type_name);
+    for (doc_lines, base, name, value) in constants {
+        let native_bits = |bits: usize| [8, 16, 32, 64].iter().cloned().find(|&native| native >= bits).unwrap();
+        let (ty, value) = match *base {
+            Base::Bool => ("bool".to_string(), value.to_string()),
+            Base::Unsigned(bits) => (format!("u{}", native_bits(bits)), integer(value)),
+            Base::Signed(bits) => (format!("i{}", native_bits(bits)), integer(value)),
+            Base::Float(bits) if !value.contains('.') => (format!("f{}", native_bits(bits).max(32)), format!("{}.0", value)),
+            Base::Float(bits) => (format!("f{}", native_bits(bits).max(32)), value.to_string()),
+            Base::Composite(..) => panic!("Constants must be primitive"),
+        };
+        code += &doc(doc_lines, "    ");
+        code += &format!("    pub const {}: {} = {};\n", name, ty, value);
+    }
+    code + "}\n"
+}
+
+/// An integer literal as Rust code, characters become byte literals
+fn integer(literal: &str) -> String {
+    if literal.starts_with('\'') {
+        format!("b{}", literal)
+    } else {
+        literal.to_string()
+    }
+}
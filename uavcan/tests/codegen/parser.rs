@@ -0,0 +1,268 @@
+//! Parsing of DSDL definitions, the subset used by the regulated data types of both protocol versions
+
+/// A data type with the sections of its definition, one for messages and two for services
+pub struct Definition {
+    /// Like `uavcan.protocol.NodeStatus`
+    pub full_name: String,
+    /// The major and minor version, UAVCAN v1 only
+    pub version: Option<(u8, u8)>,
+    pub id: Option<u16>,
+    /// The DSDL source, for the signatures of UAVCAN v0
+    pub source: String,
+    pub sections: Vec<Section>,
+}
+
+impl Definition {
+    pub fn name(&self) -> &str {
+        &self.full_name[self.full_name.rfind('.').unwrap() + 1..]
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.full_name[..self.full_name.rfind('.').unwrap()]
+    }
+
+    pub fn is_service(&self) -> bool {
+        self.sections.len() == 2
+    }
+}
+
+/// A message, or the request or response of a service
+#[derive(Default)]
+pub struct Section {
+    pub doc: Vec<String>,
+    pub items: Vec<Item>,
+    pub union: bool,
+    pub sealed: bool,
+    /// In bits
+    pub extent: Option<usize>,
+}
+
+impl Section {
+    pub fn fields(&self) -> Vec<(&Vec<String>, &Type, &str)> {
+        self.items.iter().filter_map(|item| match *item {
+            Item::Field{ref doc, ref ty, ref name} => Some((doc, ty, name.as_str())),
+            _ => None,
+        }).collect()
+    }
+
+    pub fn constants(&self) -> Vec<(&Vec<String>, &Base, &str, &str)> {
+        self.items.iter().filter_map(|item| match *item {
+            Item::Constant{ref doc, ref ty, ref name, ref value} => Some((doc, ty, name.as_str(), value.as_str())),
+            _ => None,
+        }).collect()
+    }
+}
+
+pub enum Item {
+    Field{doc: Vec<String>, ty: Type, name: String},
+    Void(usize),
+    Constant{doc: Vec<String>, ty: Base, name: String, value: String},
+}
+
+pub struct Type {
+    pub truncated: bool,
+    pub base: Base,
+    pub array: Array,
+}
+
+#[derive(Clone, PartialEq)]
+pub enum Base {
+    Bool,
+    Unsigned(usize),
+    Signed(usize),
+    Float(usize),
+    /// The full name and, for UAVCAN v1, the version
+    Composite(String, Option<(u8, u8)>),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Array {
+    None,
+    Fixed(usize),
+    /// Holding up to the given number of elements
+    Dynamic(usize),
+}
+
+/// Parses the definition in `source` of the data type named by `file_name`, like `341.NodeStatus.uavcan` or `7509.Heartbeat.1.0.dsdl`
+pub fn parse(namespace: &str, file_name: &str, source: &str) -> Definition {
+    let mut parts: Vec<&str> = file_name.split('.').collect();
+    parts.pop();
+    let id = parts[0].parse::<u16>().ok();
+    if id.is_some() {
+        parts.remove(0);
+    }
+    let version = match parts.len() {
+        1 => None,
+        3 => Some((parts[1].parse().unwrap(), parts[2].parse().unwrap())),
+        _ => panic!("Malformed file name {}", file_name),
+    };
+
+    let mut sections = vec![Section::default()];
+    let mut comments: Vec<String> = Vec::new();
+
+    for line in source.lines() {
+        let (code, comment) = match line.find('#') {
+            Some(index) => (line[..index].trim(), Some(line[index + 1..].strip_prefix(' ').unwrap_or(&line[index + 1..]).trim_end())),
+            None => (line.trim(), None),
+        };
+        let section = sections.last_mut().unwrap();
+
+        if code.is_empty() {
+            match comment {
+                Some(comment) => comments.push(comment.to_string()),
+                // A comment block followed by an empty line documents the section if it comes first
+                None => take_section_doc(section, &mut comments),
+            }
+            continue;
+        }
+
+        let mut doc: Vec<String> = comments.drain(..).collect();
+        if code.starts_with('@') {
+            let mut tokens = code.splitn(2, ' ');
+            match tokens.next().unwrap() {
+                "@union" => section.union = true,
+                "@sealed" => section.sealed = true,
+                "@extent" => {
+                    let extent = evaluate(tokens.next().unwrap(), &section.items);
+                    section.extent = Some(extent as usize);
+                },
+                "@assert" | "@deprecated" => (),
+                directive => panic!("Unknown directive {} in {}", directive, file_name),
+            }
+            continue;
+        } else if code == "---" {
+            sections.push(Section::default());
+            continue;
+        }
+
+        if let Some(comment) = comment {
+            doc.push(comment.to_string());
+        }
+        let item = parse_item(code, doc, namespace, version.is_some(), &section.items);
+        section.items.push(item);
+    }
+    take_section_doc(sections.last_mut().unwrap(), &mut comments);
+
+    Definition {
+        full_name: format!("{}.{}", namespace, parts[0]),
+        version: version,
+        id: id,
+        source: source.to_string(),
+        sections: sections,
+    }
+}
+
+fn take_section_doc(section: &mut Section, comments: &mut Vec<String>) {
+    let mut comments: Vec<String> = ::std::mem::take(comments);
+    if !section.items.is_empty() || !section.doc.is_empty() {
+        return;
+    }
+    while comments.first().is_some_and(|line| line.is_empty()) {
+        comments.remove(0);
+    }
+    while comments.last().is_some_and(|line| line.is_empty()) {
+        comments.pop();
+    }
+    section.doc = comments;
+}
+
+fn parse_item(code: &str, doc: Vec<String>, namespace: &str, v1: bool, items: &[Item]) -> Item {
+    if let Some(index) = code.find('=').filter(|&index| !code[..index].contains('[')) {
+        let mut tokens = code[..index].split_whitespace();
+        let ty = parse_base(tokens.next().unwrap(), namespace, v1);
+        let name = tokens.next().unwrap().to_string();
+        return Item::Constant{doc: doc, ty: ty, name: name, value: code[index + 1..].trim().to_string()};
+    }
+
+    let mut tokens = code.split_whitespace().peekable();
+    let truncated = match tokens.peek() {
+        Some(&"truncated") => true,
+        Some(&"saturated") => false,
+        _ => {
+            let field_type = tokens.next().unwrap();
+            return parse_field(false, field_type, tokens.next(), doc, namespace, v1, items);
+        },
+    };
+    tokens.next();
+    let field_type = tokens.next().unwrap();
+    parse_field(truncated, field_type, tokens.next(), doc, namespace, v1, items)
+}
+
+fn parse_field(truncated: bool, field_type: &str, name: Option<&str>, doc: Vec<String>, namespace: &str, v1: bool, items: &[Item]) -> Item {
+    let (base, array) = match field_type.find('[') {
+        Some(index) => (&field_type[..index], &field_type[index + 1..field_type.len() - 1]),
+        None => (field_type, ""),
+    };
+
+    if let Some(bits) = base.strip_prefix("void") {
+        return Item::Void(bits.parse().unwrap());
+    }
+
+    let array = if array.is_empty() {
+        Array::None
+    } else if let Some(length) = array.strip_prefix("<=") {
+        Array::Dynamic(evaluate(length, items) as usize)
+    } else if let Some(length) = array.strip_prefix('<') {
+        Array::Dynamic(evaluate(length, items) as usize - 1)
+    } else {
+        Array::Fixed(evaluate(array, items) as usize)
+    };
+
+    Item::Field {
+        doc: doc,
+        ty: Type{truncated: truncated, base: parse_base(base, namespace, v1), array: array},
+        name: name.expect("Fields must be named").to_string(),
+    }
+}
+
+fn parse_base(base: &str, namespace: &str, v1: bool) -> Base {
+    let bits = |prefix: &str| base.strip_prefix(prefix).and_then(|bits| bits.parse::<usize>().ok());
+    if base == "bool" {
+        Base::Bool
+    } else if let Some(bits) = bits("uint") {
+        Base::Unsigned(bits)
+    } else if let Some(bits) = bits("int") {
+        Base::Signed(bits)
+    } else if let Some(bits) = bits("float") {
+        Base::Float(bits)
+    } else {
+        let mut parts: Vec<&str> = base.split('.').collect();
+        let version = if v1 {
+            let minor = parts.pop().unwrap().parse().unwrap();
+            let major = parts.pop().unwrap().parse().unwrap();
+            Some((major, minor))
+        } else {
+            None
+        };
+        let name = parts.join(".");
+        if parts.len() == 1 {
+            Base::Composite(format!("{}.{}", namespace, name), version)
+        } else {
+            Base::Composite(name, version)
+        }
+    }
+}
+
+/// Evaluates a sum of products of integers and the constants defined before
+pub fn evaluate(expression: &str, items: &[Item]) -> i64 {
+    expression.split('+').map(|term| {
+        term.split('*').map(|factor| {
+            let factor = factor.trim();
+            items.iter().filter_map(|item| match *item {
+                Item::Constant{ref name, ref value, ..} if name == factor => Some(evaluate(value, items)),
+                _ => None,
+            }).next().unwrap_or_else(|| literal(factor))
+        }).product::<i64>()
+    }).sum()
+}
+
+/// The value of an integer or character literal
+pub fn literal(literal: &str) -> i64 {
+    if literal.starts_with('\'') {
+        literal.as_bytes()[1] as i64
+    } else if let Some(digits) = literal.strip_prefix("0x") {
+        i64::from_str_radix(digits, 16).unwrap()
+    } else {
+        literal.parse().unwrap_or_else(|_| panic!("Can't evaluate {}", literal))
+    }
+}
@@ -0,0 +1,136 @@
+//! Generation of UAVCAN v0 data types, as structures deriving `UavcanStruct`
+
+use uavcan::signature::{
+    data_type_signature,
+    dsdl_signature,
+};
+
+use super::*;
+
+/// The byte arrays holding text, they are generated as `Utf8String` instead of `Dynamic`
+const TEXT_FIELDS: &[(&str, &str)] = &[
+    ("uavcan.protocol.GetNodeInfo", "name"),
+    ("uavcan.protocol.debug.LogMessage", "source"),
+    ("uavcan.protocol.debug.LogMessage", "text"),
+    ("uavcan.protocol.param.GetSet", "name"),
+    ("uavcan.protocol.param.Value", "string_value"),
+];
+
+pub fn generate(definitions: &Definitions, namespace: &str) -> String {
+    let mut code = String::from("use types::*;\n");
+    let mut namespace_definitions: Vec<&Definition> = definitions.definitions.iter().filter(|definition| definition.namespace() == namespace).collect();
+    namespace_definitions.sort_by(|a, b| a.name().cmp(b.name()));
+
+    for definition in namespace_definitions {
+        let dsdl_signature = dsdl_signature(&definition.full_name, &definition.source);
+        let signature = signature(definitions, definition);
+        for (index, section) in definition.sections.iter().enumerate() {
+            let mut attributes = vec!["debug".to_string()];
+            if definition.is_service() {
+                if index == 0 {
+                    attributes.push(format!("id = {}", definition.id.expect("Services need an ID")));
+                    attributes.push(format!("response = \"{}\"", definitions.type_name(definition, 1)));
+                } else {
+                    attributes.push(format!("request = \"{}\"", definitions.type_name(definition, 0)));
+                }
+            } else if let Some(id) = definition.id {
+                attributes.push(format!("id = {}", id));
+            }
+            attributes.push(format!("signature = 0x{:016x}", signature));
+            attributes.push(format!("dsdl_signature = 0x{:016x}", dsdl_signature));
+
+            let type_name = definitions.type_name(definition, index);
+            code += "\n";
+            code += &doc(&section.doc, "");
+            code += &generate_section(definitions, definition, section, &type_name, &attributes.join(", "));
+            code += &constants(&type_name, section);
+        }
+    }
+    code
+}
+
+fn generate_section(definitions: &Definitions, definition: &Definition, section: &Section, type_name: &str, attributes: &str) -> String {
+    let namespace = definition.namespace();
+    if section.union {
+        let mut code = format!("#[derive(Clone, PartialEq, UavcanStruct)]\n#[uavcan({})]\npub enum {} {{\n", attributes, type_name);
+        for (doc_lines, ty, name) in section.fields() {
+            code += &doc(doc_lines, "    ");
+            code += &format!("    {}({}),\n", camel_case(name), field_type(definitions, definition, ty, name, namespace));
+        }
+        return code + "}\n";
+    }
+
+    let mut code = format!("#[derive(Clone, PartialEq, Default, UavcanStruct)]\n#[uavcan({})]\npub struct {} {{", attributes, type_name);
+    if section.items.iter().all(|item| match *item { Item::Constant{..} => true, _ => false }) {
+        return code + "}\n";
+    }
+    code += "\n";
+
+    let fields = section.fields();
+    let mut voids = 0;
+    for item in section.items.iter() {
+        match *item {
+            Item::Field{doc: ref doc_lines, ref ty, ref name} => {
+                code += &doc(doc_lines, "    ");
+                let names = value_names(section, name, fields.len());
+                if !names.is_empty() {
+                    code += &format!("    #[uavcan(names({}))]\n", names.join(", "));
+                }
+                code += &format!("    pub {}: {},\n", name, field_type(definitions, definition, ty, name, namespace));
+            },
+            Item::Void(bits) => {
+                code += &format!("    pub _v{}: void{},\n", voids, bits);
+                voids += 1;
+            },
+            Item::Constant{..} => (),
+        }
+    }
+    code + "}\n"
+}
+
+/// The constants naming the values of a field, they start with the name of the field or it's the only field and named `value`
+fn value_names<'a>(section: &'a Section, field: &str, fields: usize) -> Vec<&'a str> {
+    let prefix = format!("{}_", field.to_uppercase());
+    section.constants().into_iter()
+        .map(|(_, _, name, _)| name)
+        .filter(|name| name.starts_with(&prefix) || (field == "value" && fields == 1))
+        .collect()
+}
+
+fn field_type(definitions: &Definitions, definition: &Definition, ty: &Type, name: &str, namespace: &str) -> String {
+    let element = match ty.base {
+        Base::Composite(ref full_name, version) => definitions.type_path(definitions.get(full_name, version), 0, namespace),
+        ref primitive if ty.truncated => format!("Truncated<{}>", primitive_type(primitive).0),
+        ref primitive => primitive_type(primitive).0,
+    };
+
+    match ty.array {
+        Array::None => element,
+        Array::Fixed(length) => format!("[{}; {}]", element, length),
+        Array::Dynamic(length) if TEXT_FIELDS.contains(&(definition.full_name.as_str(), name)) => format!("Utf8String<[{}; {}]>", element, length),
+        Array::Dynamic(length) => format!("Dynamic<[{}; {}]>", element, length),
+    }
+}
+
+/// The data type signature, extending the DSDL signature with the signatures of the nested composite types
+fn signature(definitions: &Definitions, definition: &Definition) -> u64 {
+    let nested: Vec<u64> = definition.sections.iter()
+        .flat_map(|section| section.fields())
+        .filter_map(|(_, ty, _)| match ty.base {
+            Base::Composite(ref full_name, version) => Some(signature(definitions, definitions.get(full_name, version))),
+            _ => None,
+        })
+        .collect();
+    data_type_signature(dsdl_signature(&definition.full_name, &definition.source), &nested)
+}
+
+/// `integer_value` becomes `IntegerValue`
+pub fn camel_case(name: &str) -> String {
+    name.split('_').map(|word| {
+        let mut chars = word.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+            None => String::new(),
+        }
+    }).collect()
+}
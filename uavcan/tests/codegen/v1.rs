@@ -0,0 +1,280 @@
+//! Generation of UAVCAN v1 data types, as structures and enums implementing `v1::Composite`
+
+use uavcan::types::ProtocolVersion;
+
+use super::*;
+use super::v0::camel_case;
+
+pub fn generate(definitions: &Definitions, namespace: &str) -> String {
+    let mut namespace_definitions: Vec<&Definition> = definitions.definitions.iter().filter(|definition| definition.namespace() == namespace).collect();
+    namespace_definitions.sort_by(|a, b| (a.name(), a.version).cmp(&(b.name(), b.version)));
+
+    let mut code = String::new();
+    let mut uses_types = false;
+    for definition in namespace_definitions {
+        for (index, section) in definition.sections.iter().enumerate() {
+            let type_name = definitions.type_name(definition, index);
+            let generator = Generator{definitions: definitions, namespace: namespace, type_name: &type_name};
+            code += "\n";
+            code += &doc(&section.doc, "");
+            code += &generator.declaration(section, &mut uses_types);
+            code += &constants(&type_name, section);
+            if let Some(id) = definition.id {
+                code += &format!("\nimpl FixedPort for {} {{\n    const PORT_ID: u16 = {};\n}}\n", type_name, id);
+            }
+            code += &generator.composite(section);
+        }
+    }
+
+    if uses_types {
+        format!("use types::*;\nuse v1::*;\n{}", code)
+    } else {
+        format!("use v1::*;\n{}", code)
+    }
+}
+
+struct Generator<'a> {
+    definitions: &'a Definitions,
+    namespace: &'a str,
+    type_name: &'a str,
+}
+
+/// How a field is serialized
+enum Kind {
+    Primitive,
+    Array,
+    DynamicArray(usize),
+    Composite,
+    DynamicCompositeArray(usize),
+}
+
+impl<'a> Generator<'a> {
+    fn declaration(&self, section: &Section, uses_types: &mut bool) -> String {
+        if section.union {
+            let mut code = format!("#[derive(Clone, Debug, PartialEq)]\npub enum {} {{\n", self.type_name);
+            for (doc_lines, ty, name) in section.fields() {
+                code += &doc(doc_lines, "    ");
+                code += &format!("    {}({}),\n", camel_case(name), self.field_type(ty, uses_types));
+            }
+            code += "}\n";
+
+            // Like the other composites, the default is the zero initialized representation
+            let (_, first_type, first_name) = section.fields()[0];
+            let default = match self.kind(first_type) {
+                Kind::Primitive => format!("{}(Primitive::from_bits(0))", camel_case(first_name)),
+                _ => format!("{}(Default::default())", camel_case(first_name)),
+            };
+            code += &format!("\nimpl Default for {} {{\n    fn default() -> Self {{\n        {}::{}\n    }}\n}}\n", self.type_name, self.type_name, default);
+            return code;
+        }
+
+        let mut code = format!("#[derive(Clone, Debug, PartialEq, Default)]\npub struct {} {{", self.type_name);
+        let fields = section.fields();
+        if fields.is_empty() {
+            return code + "}\n";
+        }
+        code += "\n";
+        for (doc_lines, ty, name) in fields {
+            code += &doc(doc_lines, "    ");
+            code += &format!("    pub {}: {},\n", name, self.field_type(ty, uses_types));
+        }
+        code + "}\n"
+    }
+
+    fn composite(&self, section: &Section) -> String {
+        let max_bytes = max_bits(self.definitions, section) / 8;
+        let extent = if section.sealed {
+            "Self::MAX_BYTES".to_string()
+        } else {
+            let extent = section.extent.unwrap_or_else(|| panic!("{} needs `@sealed` or `@extent`", self.type_name));
+            assert!(extent % 8 == 0 && extent / 8 >= max_bytes, "The extent of {} is too small", self.type_name);
+            (extent / 8).to_string()
+        };
+
+        let mut code = format!("\nimpl Composite for {} {{\n", self.type_name);
+        code += &format!("    const SEALED: bool = {};\n", section.sealed);
+        code += &format!("    const MAX_BYTES: usize = {};\n", max_bytes);
+        code += &format!("    const EXTENT: usize = {};\n\n", extent);
+        if section.union {
+            code += &self.union_functions(section);
+        } else {
+            code += &self.structure_functions(section);
+        }
+        code + "}\n"
+    }
+
+    fn structure_functions(&self, section: &Section) -> String {
+        if section.items.iter().all(|item| match *item { Item::Constant{..} => true, _ => false }) {
+            return format!(
+                "    fn serialize_fields(&self, _writer: &mut Writer) {{}}\n\n    fn deserialize_fields(_reader: &mut Reader) -> Result<Self, DecodeError> {{\n        Ok({} {{}})\n    }}\n",
+                self.type_name,
+            );
+        }
+
+        let mut serialize = String::new();
+        let mut deserialize = String::new();
+        for item in section.items.iter() {
+            match *item {
+                Item::Field{ref ty, ref name, ..} => {
+                    serialize += &format!("        {};\n", self.write(ty, &format!("self.{}", name), true));
+                    deserialize += &format!("        let {} = {};\n", name, self.read(ty));
+                },
+                Item::Void(bits) => {
+                    serialize += &format!("        writer.write_bits({}, 0);\n", bits);
+                    deserialize += &format!("        reader.skip_bits({});\n", bits);
+                },
+                Item::Constant{..} => (),
+            }
+        }
+        let names: Vec<&str> = section.fields().into_iter().map(|(_, _, name)| name).collect();
+
+        let mut code = format!("    fn serialize_fields(&self, writer: &mut Writer) {{\n{}    }}\n\n", serialize);
+        code += &format!("    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {{\n{}", deserialize);
+        code += &format!("        Ok({} {{\n", self.type_name);
+        for name in names {
+            code += &format!("            {},\n", name);
+        }
+        code + "        })\n    }\n"
+    }
+
+    fn union_functions(&self, section: &Section) -> String {
+        let fields = section.fields();
+        let tag_bits = ProtocolVersion::V1.length_bits(fields.len() - 1);
+
+        let mut serialize = String::new();
+        let mut deserialize = String::new();
+        for (tag, &(_, ty, name)) in fields.iter().enumerate() {
+            let variant = camel_case(name);
+            let (binding, value) = match self.kind(ty) {
+                Kind::Primitive => ("value", "value"),
+                Kind::Composite => ("ref value", "value"),
+                _ => panic!("The variants of {} must be primitives or composites", self.type_name),
+            };
+            serialize += &format!("            {}::{}({}) => {{\n", self.type_name, variant, binding);
+            serialize += &format!("                writer.write_bits({}, {});\n", tag_bits, tag);
+            serialize += &format!("                {};\n", self.write(ty, value, false));
+            serialize += "            },\n";
+            deserialize += &format!("            {} => Ok({}::{}({})),\n", tag, self.type_name, variant, self.read(ty));
+        }
+
+        let mut code = format!("    fn serialize_fields(&self, writer: &mut Writer) {{\n        match *self {{\n{}        }}\n    }}\n\n", serialize);
+        code += &format!("    fn deserialize_fields(reader: &mut Reader) -> Result<Self, DecodeError> {{\n        match reader.read_bits({}) {{\n{}", tag_bits, deserialize);
+        code + "            _ => Err(DecodeError::UnionTag),\n        }\n    }\n"
+    }
+
+    /// `value` is a place of the field type when `place` is set, otherwise a reference to it (or the value of a primitive)
+    fn write(&self, ty: &Type, value: &str, place: bool) -> String {
+        let reference = if place { format!("&{}", value) } else { value.to_string() };
+        match self.kind(ty) {
+            Kind::Primitive => format!("writer.write({})", value),
+            Kind::Array => format!("writer.write_array({})", reference),
+            Kind::DynamicArray(length) => format!("writer.write_dynamic_array({}, {})", reference, length),
+            Kind::Composite => format!("writer.write_composite({})", reference),
+            Kind::DynamicCompositeArray(length) => format!("writer.write_dynamic_composite_array({}, {})", reference, length),
+        }
+    }
+
+    fn read(&self, ty: &Type) -> String {
+        match self.kind(ty) {
+            Kind::Primitive => "reader.read()".to_string(),
+            Kind::Array => "reader.read_array()".to_string(),
+            Kind::DynamicArray(_) => "reader.read_dynamic_array()?".to_string(),
+            Kind::Composite => "reader.read_composite()?".to_string(),
+            Kind::DynamicCompositeArray(_) => "reader.read_dynamic_composite_array()?".to_string(),
+        }
+    }
+
+    fn kind(&self, ty: &Type) -> Kind {
+        match (&ty.base, ty.array) {
+            (&Base::Composite(..), Array::None) => Kind::Composite,
+            (&Base::Composite(..), Array::Dynamic(length)) => Kind::DynamicCompositeArray(length),
+            (&Base::Composite(..), Array::Fixed(_)) => panic!("Fixed length arrays of composites aren't supported ({})", self.type_name),
+            (_, Array::None) => Kind::Primitive,
+            (_, Array::Fixed(_)) => Kind::Array,
+            (_, Array::Dynamic(length)) => Kind::DynamicArray(length),
+        }
+    }
+
+    fn field_type(&self, ty: &Type, uses_types: &mut bool) -> String {
+        let element = match ty.base {
+            Base::Composite(ref full_name, version) => self.definitions.type_path(self.definitions.get(full_name, version), 0, self.namespace),
+            ref primitive => {
+                let (element, from_types) = primitive_type(primitive);
+                *uses_types |= from_types;
+                element
+            },
+        };
+
+        match ty.array {
+            Array::None => element,
+            Array::Fixed(length) => format!("[{}; {}]", element, length),
+            Array::Dynamic(length) => format!("::heapless::Vec<{}, {}>", element, length),
+        }
+    }
+}
+
+/// The largest serialized representation of a section in bits, including the padding after it
+fn max_bits(definitions: &Definitions, section: &Section) -> usize {
+    let fields = section.fields();
+    if section.union {
+        let tag_bits = ProtocolVersion::V1.length_bits(fields.len() - 1);
+        let variant_bits = fields.iter().map(|&(_, ty, _)| field_bits(definitions, ty)).max().unwrap();
+        return align(tag_bits + variant_bits);
+    }
+
+    let mut bits = 0;
+    for item in section.items.iter() {
+        match *item {
+            Item::Field{ref ty, ..} => {
+                // Composites and variable length arrays start at a byte boundary
+                if is_composite(ty) || is_dynamic(ty) {
+                    bits = align(bits);
+                }
+                bits += field_bits(definitions, ty);
+            },
+            Item::Void(void_bits) => bits += void_bits,
+            Item::Constant{..} => (),
+        }
+    }
+    align(bits)
+}
+
+fn field_bits(definitions: &Definitions, ty: &Type) -> usize {
+    let element_bits = match ty.base {
+        Base::Composite(ref full_name, version) => {
+            let nested = definitions.get(full_name, version);
+            let section = &nested.sections[0];
+            if section.sealed {
+                max_bits(definitions, section)
+            } else {
+                32 + section.extent.unwrap()
+            }
+        },
+        Base::Bool => 1,
+        Base::Unsigned(bits) | Base::Signed(bits) | Base::Float(bits) => bits,
+    };
+
+    match ty.array {
+        Array::None => element_bits,
+        Array::Fixed(length) => element_bits * length,
+        Array::Dynamic(length) => align(ProtocolVersion::V1.length_bits(length)) + element_bits * length,
+    }
+}
+
+fn is_composite(ty: &Type) -> bool {
+    match ty.base {
+        Base::Composite(..) => true,
+        _ => false,
+    }
+}
+
+fn is_dynamic(ty: &Type) -> bool {
+    match ty.array {
+        Array::Dynamic(_) => true,
+        _ => false,
+    }
+}
+
+fn align(bits: usize) -> usize {
+    (bits + 7) / 8 * 8
+}
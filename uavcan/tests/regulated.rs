@@ -0,0 +1,33 @@
+//! Checks that the regulated data types in `src/regulated` are generated from the DSDL definitions in `dsdl`
+//!
+//! After changing a definition or the generator in `codegen`, run this test with `REGENERATE=1` to overwrite the generated files.
+
+extern crate uavcan;
+
+mod codegen;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn regulated_types_are_generated() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let regenerate = env::var_os("REGENERATE").is_some();
+
+    let mut outdated = Vec::new();
+    for (path, content) in codegen::generate(&root.join("dsdl")) {
+        let path = root.join("src/regulated").join(path);
+        if fs::read_to_string(&path).ok().as_ref() == Some(&content) {
+            continue;
+        }
+        if regenerate {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, content).unwrap();
+        } else {
+            outdated.push(path);
+        }
+    }
+
+    assert!(outdated.is_empty(), "The generated files are outdated, run the test with `REGENERATE=1` to update them: {:?}", outdated);
+}